@@ -1,9 +1,7 @@
 use criterion::*;
 use std::error;
-use std::fmt;
 use std::fs::{remove_file, OpenOptions};
-use std::io::BufRead;
-use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::io::{Cursor, Seek, SeekFrom};
 use std::path::Path;
 use std::time::Duration;
 use std::time::Instant;
@@ -22,69 +20,23 @@ const BGRA_I444_OUTPUT: &str = &"./bgra_i444_output.i444";
 
 const SAMPLE_SIZE: usize = 22;
 const PAGE_SIZE: usize = 4096;
-
-#[derive(Debug, Clone)]
-struct BenchmarkError;
-
-impl fmt::Display for BenchmarkError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Generic benchmark error")
-    }
-}
-
-impl error::Error for BenchmarkError {
-    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        None
-    }
-}
+const STRIDE_PADDING: usize = 64;
+const STRIDE_RESOLUTIONS: &[(&str, u32, u32)] = &[("1080p", 1920, 1080), ("4k", 3840, 2160)];
 
 type BenchmarkResult<T> = std::result::Result<T, Box<dyn error::Error>>;
 
-fn skip_line(file: &mut Cursor<&[u8]>) -> BenchmarkResult<()> {
-    let mut byte = [0; 1];
-    while byte[0] != 0xA {
-        file.read(&mut byte)?;
-    }
-
-    Ok(())
-}
-
-fn read_line(file: &mut Cursor<&[u8]>) -> BenchmarkResult<String> {
-    let mut string = String::new();
-    let written_chars = file.read_line(&mut string)?;
-
-    let result = match written_chars {
-        0 => Err(BenchmarkError),
-        _ => Ok(string),
-    };
-
-    result.map_err(|e| e.into())
-}
-
 fn pnm_size(file: &mut Cursor<&[u8]>) -> BenchmarkResult<(u32, u32)> {
     file.seek(SeekFrom::Start(0))?;
-    skip_line(file)?;
-
-    let dimensions: Vec<_> = read_line(file)?
-        .split_whitespace()
-        .map(|s| s.parse::<u32>().unwrap())
-        .collect();
+    let header = pnm::read_header(file)?;
 
-    let width = dimensions.get(0).ok_or(BenchmarkError)?;
-    let height = dimensions.get(1).ok_or(BenchmarkError)?;
-    Ok((*width, *height))
+    Ok((header.width, header.height))
 }
 
 fn pnm_data(file: &mut Cursor<&[u8]>) -> BenchmarkResult<(u32, u32, Vec<u8>)> {
     file.seek(SeekFrom::Start(0))?;
-    let (width, height) = pnm_size(file)?;
-
-    let size: usize = (width as usize) * (height as usize);
-    let mut x: Vec<u8> = Vec::with_capacity(size);
-    skip_line(file)?;
-    file.read_to_end(&mut x)?;
+    let (header, data) = pnm::read(file)?;
 
-    Ok((width, height, x))
+    Ok((header.width, header.height, data))
 }
 
 fn bgra_nv12(mut input_file: &mut Cursor<&[u8]>, output_path: &str) -> BenchmarkResult<Duration> {
@@ -132,8 +84,11 @@ fn bgra_nv12(mut input_file: &mut Cursor<&[u8]>, output_path: &str) -> Benchmark
             .write(true)
             .create(true)
             .open(output_path)?;
-        write!(buffer, "P5\n{} {}\n255\n", width, height + height / 2)?;
-        buffer.write(&output_buffer)?;
+        pnm::write(
+            &mut buffer,
+            &pnm::Header::new(width, height + height / 2, 1),
+            &output_buffer,
+        )?;
     }
 
     Ok(elapsed)
@@ -188,8 +143,11 @@ fn bgra_i420(mut input_file: &mut Cursor<&[u8]>, output_path: &str) -> Benchmark
             .write(true)
             .create(true)
             .open(output_path)?;
-        write!(buffer, "P5\n{} {}\n255\n", width, height + height / 2)?;
-        buffer.write(&output_buffer)?;
+        pnm::write(
+            &mut buffer,
+            &pnm::Header::new(width, height + height / 2, 1),
+            &output_buffer,
+        )?;
     }
 
     Ok(elapsed)
@@ -244,8 +202,11 @@ fn bgra_i444(mut input_file: &mut Cursor<&[u8]>, output_path: &str) -> Benchmark
             .write(true)
             .create(true)
             .open(output_path)?;
-        write!(buffer, "P5\n{} {}\n255\n", width, height + height + height)?;
-        buffer.write(&output_buffer)?;
+        pnm::write(
+            &mut buffer,
+            &pnm::Header::new(width, height + height + height, 1),
+            &output_buffer,
+        )?;
     }
 
     Ok(elapsed)
@@ -297,8 +258,11 @@ fn nv12_bgra(mut input_file: &mut Cursor<&[u8]>, output_path: &str) -> Benchmark
             .write(true)
             .create(true)
             .open(output_path)?;
-        write!(buffer, "P5\n{} {}\n255\n", 4 * width, height)?;
-        buffer.write(&output_buffer)?;
+        pnm::write(
+            &mut buffer,
+            &pnm::Header::new(4 * width, height, 1),
+            &output_buffer,
+        )?;
     }
 
     Ok(elapsed)
@@ -350,8 +314,11 @@ fn rgb_bgra(mut input_file: &mut Cursor<&[u8]>, output_path: &str) -> BenchmarkR
             .write(true)
             .create(true)
             .open(output_path)?;
-        write!(buffer, "P5\n{} {}\n255\n", 4 * width, height)?;
-        buffer.write(&output_buffer)?;
+        pnm::write(
+            &mut buffer,
+            &pnm::Header::new(4 * width, height, 1),
+            &output_buffer,
+        )?;
     }
 
     Ok(elapsed)
@@ -403,8 +370,11 @@ fn bgra_rgb(mut input_file: &mut Cursor<&[u8]>, output_path: &str) -> BenchmarkR
             .write(true)
             .create(true)
             .open(output_path)?;
-        write!(buffer, "P5\n{} {}\n255\n", 3 * width, height)?;
-        buffer.write(&output_buffer)?;
+        pnm::write(
+            &mut buffer,
+            &pnm::Header::new(3 * width, height, 1),
+            &output_buffer,
+        )?;
     }
 
     Ok(elapsed)
@@ -462,8 +432,11 @@ fn i420_bgra(mut input_file: &mut Cursor<&[u8]>, output_path: &str) -> Benchmark
             .write(true)
             .create(true)
             .open(output_path)?;
-        write!(buffer, "P5\n{} {}\n255\n", 4 * width, height)?;
-        buffer.write(&output_buffer)?;
+        pnm::write(
+            &mut buffer,
+            &pnm::Header::new(4 * width, height, 1),
+            &output_buffer,
+        )?;
     }
 
     Ok(elapsed)
@@ -520,8 +493,11 @@ fn i444_bgra(mut input_file: &mut Cursor<&[u8]>, output_path: &str) -> Benchmark
             .write(true)
             .create(true)
             .open(output_path)?;
-        write!(buffer, "P5\n{} {}\n255\n", 4 * width, height)?;
-        buffer.write(&output_buffer)?;
+        pnm::write(
+            &mut buffer,
+            &pnm::Header::new(4 * width, height, 1),
+            &output_buffer,
+        )?;
     }
 
     Ok(elapsed)
@@ -720,5 +696,140 @@ fn bench(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench);
+// Planes are described by their bytes per pixel and their subsampling shift
+fn stride_convert(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_bpp: &[(usize, u32)],
+    dst_format: &ImageFormat,
+    dst_bpp: &[(usize, u32)],
+    padding: usize,
+) -> impl FnMut() -> BenchmarkResult<()> {
+    let w = width as usize;
+    let h = height as usize;
+    let src_strides: Vec<usize> = src_bpp
+        .iter()
+        .map(|(bpp, shift)| ((bpp * w) >> shift) + padding)
+        .collect();
+    let dst_strides: Vec<usize> = dst_bpp
+        .iter()
+        .map(|(bpp, shift)| ((bpp * w) >> shift) + padding)
+        .collect();
+    let src_planes: Vec<Vec<u8>> = src_strides
+        .iter()
+        .zip(src_bpp.iter())
+        .map(|(s, (_, shift))| vec![128u8; s * (h >> shift)])
+        .collect();
+    let mut dst_planes: Vec<Vec<u8>> = dst_strides
+        .iter()
+        .zip(dst_bpp.iter())
+        .map(|(s, (_, shift))| vec![0u8; s * (h >> shift)])
+        .collect();
+    let src_format = ImageFormat { ..*src_format };
+    let dst_format = ImageFormat { ..*dst_format };
+
+    move || {
+        let src_buffers: Vec<&[u8]> = src_planes.iter().map(|p| &p[..]).collect();
+        let mut dst_buffers: Vec<&mut [u8]> = dst_planes.iter_mut().map(|p| &mut p[..]).collect();
+
+        convert_image(
+            width,
+            height,
+            &src_format,
+            Some(&src_strides[..]),
+            &src_buffers[..],
+            &dst_format,
+            Some(&dst_strides[..]),
+            &mut dst_buffers[..],
+        )?;
+
+        Ok(())
+    }
+}
+
+fn bench_strides(c: &mut Criterion) {
+    initialize();
+
+    let rgb_format = ImageFormat {
+        pixel_format: PixelFormat::Rgb,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let i444_format = ImageFormat {
+        pixel_format: PixelFormat::I444,
+        color_space: ColorSpace::Bt601,
+        num_planes: 3,
+    };
+
+    let i420_format = ImageFormat {
+        pixel_format: PixelFormat::I420,
+        color_space: ColorSpace::Bt601,
+        num_planes: 3,
+    };
+
+    type Conversion<'a> = (
+        &'a str,
+        &'a ImageFormat,
+        &'a [(usize, u32)],
+        &'a ImageFormat,
+        &'a [(usize, u32)],
+    );
+
+    let conversions: &[Conversion] = &[
+        ("rgb>bgra", &rgb_format, &[(3, 0)], &bgra_format, &[(4, 0)]),
+        ("bgra>rgb", &bgra_format, &[(4, 0)], &rgb_format, &[(3, 0)]),
+        (
+            "i444>bgra",
+            &i444_format,
+            &[(1, 0), (1, 0), (1, 0)],
+            &bgra_format,
+            &[(4, 0)],
+        ),
+        (
+            "bgra>i444",
+            &bgra_format,
+            &[(4, 0)],
+            &i444_format,
+            &[(1, 0), (1, 0), (1, 0)],
+        ),
+        (
+            "i420>bgra",
+            &i420_format,
+            &[(1, 0), (1, 1), (1, 1)],
+            &bgra_format,
+            &[(4, 0)],
+        ),
+    ];
+
+    let mut group = c.benchmark_group("dcv-color-primitives-strides");
+    group.sample_size(SAMPLE_SIZE);
+
+    for (resolution, width, height) in STRIDE_RESOLUTIONS.iter() {
+        group.throughput(Throughput::Elements((*width as u64) * (*height as u64)));
+
+        for (name, src_format, src_bpp, dst_format, dst_bpp) in conversions.iter() {
+            for (layout, padding) in &[("packed", 0), ("padded", STRIDE_PADDING)] {
+                let mut convert = stride_convert(
+                    *width, *height, src_format, src_bpp, dst_format, dst_bpp, *padding,
+                );
+
+                group.bench_function(format!("{}/{}/{}", name, resolution, layout), |b| {
+                    b.iter(|| convert().expect("Benchmark iteration failed"));
+                });
+            }
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench, bench_strides);
 criterion_main!(benches);
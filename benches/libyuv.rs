@@ -0,0 +1,574 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+// Side by side benchmarks of the conversions also implemented by libyuv. libyuv names
+// packed formats after the little endian word holding a pixel: its "ARGB" is stored as
+// b, g, r, a in memory (Bgra here) and its "RAW" as r, g, b (Rgb here). Its I420 and
+// NV12 functions use BT.601, the H420 ones BT.709.
+use criterion::*;
+use rand::Rng;
+use std::error;
+use std::os::raw::c_int;
+
+use dcp::*;
+use dcv_color_primitives as dcp;
+
+const SAMPLE_SIZE: usize = 22;
+const RESOLUTIONS: &[(&str, u32, u32)] = &[("1080p", 1920, 1080), ("4k", 3840, 2160)];
+
+type BenchmarkResult<T> = std::result::Result<T, Box<dyn error::Error>>;
+
+#[link(name = "yuv")]
+extern "C" {
+    fn ARGBToNV12(
+        src_argb: *const u8,
+        src_stride_argb: c_int,
+        dst_y: *mut u8,
+        dst_stride_y: c_int,
+        dst_uv: *mut u8,
+        dst_stride_uv: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+
+    fn ARGBToI420(
+        src_argb: *const u8,
+        src_stride_argb: c_int,
+        dst_y: *mut u8,
+        dst_stride_y: c_int,
+        dst_u: *mut u8,
+        dst_stride_u: c_int,
+        dst_v: *mut u8,
+        dst_stride_v: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+
+    fn ARGBToI444(
+        src_argb: *const u8,
+        src_stride_argb: c_int,
+        dst_y: *mut u8,
+        dst_stride_y: c_int,
+        dst_u: *mut u8,
+        dst_stride_u: c_int,
+        dst_v: *mut u8,
+        dst_stride_v: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+
+    fn NV12ToARGB(
+        src_y: *const u8,
+        src_stride_y: c_int,
+        src_uv: *const u8,
+        src_stride_uv: c_int,
+        dst_argb: *mut u8,
+        dst_stride_argb: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+
+    fn I420ToARGB(
+        src_y: *const u8,
+        src_stride_y: c_int,
+        src_u: *const u8,
+        src_stride_u: c_int,
+        src_v: *const u8,
+        src_stride_v: c_int,
+        dst_argb: *mut u8,
+        dst_stride_argb: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+
+    fn H420ToARGB(
+        src_y: *const u8,
+        src_stride_y: c_int,
+        src_u: *const u8,
+        src_stride_u: c_int,
+        src_v: *const u8,
+        src_stride_v: c_int,
+        dst_argb: *mut u8,
+        dst_stride_argb: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+
+    fn I444ToARGB(
+        src_y: *const u8,
+        src_stride_y: c_int,
+        src_u: *const u8,
+        src_stride_u: c_int,
+        src_v: *const u8,
+        src_stride_v: c_int,
+        dst_argb: *mut u8,
+        dst_stride_argb: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+
+    fn RAWToARGB(
+        src_raw: *const u8,
+        src_stride_raw: c_int,
+        dst_argb: *mut u8,
+        dst_stride_argb: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+
+    fn ARGBToRAW(
+        src_argb: *const u8,
+        src_stride_argb: c_int,
+        dst_raw: *mut u8,
+        dst_stride_raw: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> c_int;
+}
+
+/// A libyuv conversion, given the image size, the source planes and the destination
+/// planes, each with its stride
+type LibyuvConvert = fn(c_int, c_int, &[(&[u8], c_int)], &mut [(&mut [u8], c_int)]) -> c_int;
+
+fn libyuv_bgra_nv12(
+    w: c_int,
+    h: c_int,
+    s: &[(&[u8], c_int)],
+    d: &mut [(&mut [u8], c_int)],
+) -> c_int {
+    let (y, uv) = d.split_at_mut(1);
+    unsafe {
+        ARGBToNV12(
+            s[0].0.as_ptr(),
+            s[0].1,
+            y[0].0.as_mut_ptr(),
+            y[0].1,
+            uv[0].0.as_mut_ptr(),
+            uv[0].1,
+            w,
+            h,
+        )
+    }
+}
+
+fn libyuv_bgra_i420(
+    w: c_int,
+    h: c_int,
+    s: &[(&[u8], c_int)],
+    d: &mut [(&mut [u8], c_int)],
+) -> c_int {
+    let (y, uv) = d.split_at_mut(1);
+    let (u, v) = uv.split_at_mut(1);
+    unsafe {
+        ARGBToI420(
+            s[0].0.as_ptr(),
+            s[0].1,
+            y[0].0.as_mut_ptr(),
+            y[0].1,
+            u[0].0.as_mut_ptr(),
+            u[0].1,
+            v[0].0.as_mut_ptr(),
+            v[0].1,
+            w,
+            h,
+        )
+    }
+}
+
+fn libyuv_bgra_i444(
+    w: c_int,
+    h: c_int,
+    s: &[(&[u8], c_int)],
+    d: &mut [(&mut [u8], c_int)],
+) -> c_int {
+    let (y, uv) = d.split_at_mut(1);
+    let (u, v) = uv.split_at_mut(1);
+    unsafe {
+        ARGBToI444(
+            s[0].0.as_ptr(),
+            s[0].1,
+            y[0].0.as_mut_ptr(),
+            y[0].1,
+            u[0].0.as_mut_ptr(),
+            u[0].1,
+            v[0].0.as_mut_ptr(),
+            v[0].1,
+            w,
+            h,
+        )
+    }
+}
+
+fn libyuv_nv12_bgra(
+    w: c_int,
+    h: c_int,
+    s: &[(&[u8], c_int)],
+    d: &mut [(&mut [u8], c_int)],
+) -> c_int {
+    unsafe {
+        NV12ToARGB(
+            s[0].0.as_ptr(),
+            s[0].1,
+            s[1].0.as_ptr(),
+            s[1].1,
+            d[0].0.as_mut_ptr(),
+            d[0].1,
+            w,
+            h,
+        )
+    }
+}
+
+fn libyuv_i420_bgra(
+    w: c_int,
+    h: c_int,
+    s: &[(&[u8], c_int)],
+    d: &mut [(&mut [u8], c_int)],
+) -> c_int {
+    unsafe {
+        I420ToARGB(
+            s[0].0.as_ptr(),
+            s[0].1,
+            s[1].0.as_ptr(),
+            s[1].1,
+            s[2].0.as_ptr(),
+            s[2].1,
+            d[0].0.as_mut_ptr(),
+            d[0].1,
+            w,
+            h,
+        )
+    }
+}
+
+fn libyuv_h420_bgra(
+    w: c_int,
+    h: c_int,
+    s: &[(&[u8], c_int)],
+    d: &mut [(&mut [u8], c_int)],
+) -> c_int {
+    unsafe {
+        H420ToARGB(
+            s[0].0.as_ptr(),
+            s[0].1,
+            s[1].0.as_ptr(),
+            s[1].1,
+            s[2].0.as_ptr(),
+            s[2].1,
+            d[0].0.as_mut_ptr(),
+            d[0].1,
+            w,
+            h,
+        )
+    }
+}
+
+fn libyuv_i444_bgra(
+    w: c_int,
+    h: c_int,
+    s: &[(&[u8], c_int)],
+    d: &mut [(&mut [u8], c_int)],
+) -> c_int {
+    unsafe {
+        I444ToARGB(
+            s[0].0.as_ptr(),
+            s[0].1,
+            s[1].0.as_ptr(),
+            s[1].1,
+            s[2].0.as_ptr(),
+            s[2].1,
+            d[0].0.as_mut_ptr(),
+            d[0].1,
+            w,
+            h,
+        )
+    }
+}
+
+fn libyuv_rgb_bgra(
+    w: c_int,
+    h: c_int,
+    s: &[(&[u8], c_int)],
+    d: &mut [(&mut [u8], c_int)],
+) -> c_int {
+    unsafe { RAWToARGB(s[0].0.as_ptr(), s[0].1, d[0].0.as_mut_ptr(), d[0].1, w, h) }
+}
+
+fn libyuv_bgra_rgb(
+    w: c_int,
+    h: c_int,
+    s: &[(&[u8], c_int)],
+    d: &mut [(&mut [u8], c_int)],
+) -> c_int {
+    unsafe { ARGBToRAW(s[0].0.as_ptr(), s[0].1, d[0].0.as_mut_ptr(), d[0].1, w, h) }
+}
+
+/// Bytes per sample and subsampling shift of each plane
+type Planes = &'static [(usize, u32)];
+
+/// Allocates the planes of an image, filled with random samples
+fn alloc_planes(width: u32, height: u32, planes: Planes) -> (Vec<Vec<u8>>, Vec<usize>) {
+    let mut rng = rand::thread_rng();
+    let strides: Vec<usize> = planes
+        .iter()
+        .map(|&(bpp, shift)| bpp * ((width >> shift) as usize))
+        .collect();
+    let buffers = planes
+        .iter()
+        .zip(strides.iter())
+        .map(|(&(_, shift), &stride)| {
+            (0..stride * ((height >> shift) as usize))
+                .map(|_| rng.gen::<u8>())
+                .collect()
+        })
+        .collect();
+
+    (buffers, strides)
+}
+
+/// Returns the conversion by this crate and the one by libyuv of an image of random
+/// samples, with the same buffers and strides
+fn prepare(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_planes: Planes,
+    dst_format: &ImageFormat,
+    dst_planes: Planes,
+    libyuv_convert: LibyuvConvert,
+) -> (
+    impl FnMut() -> BenchmarkResult<()>,
+    impl FnMut() -> BenchmarkResult<()>,
+) {
+    let (src_buffers, src_strides) = alloc_planes(width, height, src_planes);
+    let (mut dst_buffers, dst_strides) = alloc_planes(width, height, dst_planes);
+    let (libyuv_src_buffers, libyuv_src_strides) = (src_buffers.clone(), src_strides.clone());
+    let (mut libyuv_dst_buffers, libyuv_dst_strides) = (dst_buffers.clone(), dst_strides.clone());
+
+    let src_format = ImageFormat {
+        pixel_format: src_format.pixel_format,
+        color_space: src_format.color_space,
+        num_planes: src_format.num_planes,
+    };
+
+    let dst_format = ImageFormat {
+        pixel_format: dst_format.pixel_format,
+        color_space: dst_format.color_space,
+        num_planes: dst_format.num_planes,
+    };
+
+    let dcp_convert = move || -> BenchmarkResult<()> {
+        let src: Vec<&[u8]> = src_buffers.iter().map(|buffer| &buffer[..]).collect();
+        let mut dst: Vec<&mut [u8]> = dst_buffers
+            .iter_mut()
+            .map(|buffer| &mut buffer[..])
+            .collect();
+        convert_image(
+            width,
+            height,
+            &src_format,
+            Some(&src_strides),
+            &src,
+            &dst_format,
+            Some(&dst_strides),
+            &mut dst,
+        )?;
+
+        Ok(())
+    };
+
+    let libyuv_convert = move || -> BenchmarkResult<()> {
+        let src: Vec<(&[u8], c_int)> = libyuv_src_buffers
+            .iter()
+            .zip(libyuv_src_strides.iter())
+            .map(|(buffer, &stride)| (&buffer[..], stride as c_int))
+            .collect();
+        let mut dst: Vec<(&mut [u8], c_int)> = libyuv_dst_buffers
+            .iter_mut()
+            .zip(libyuv_dst_strides.iter())
+            .map(|(buffer, &stride)| (&mut buffer[..], stride as c_int))
+            .collect();
+
+        match libyuv_convert(width as c_int, height as c_int, &src, &mut dst) {
+            0 => Ok(()),
+            error => Err(format!("libyuv conversion failed with error {}", error).into()),
+        }
+    };
+
+    (dcp_convert, libyuv_convert)
+}
+
+fn bench_libyuv(c: &mut Criterion) {
+    initialize();
+
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let rgb_format = ImageFormat {
+        pixel_format: PixelFormat::Rgb,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let nv12_format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt601,
+        num_planes: 2,
+    };
+
+    let i420_format = ImageFormat {
+        pixel_format: PixelFormat::I420,
+        color_space: ColorSpace::Bt601,
+        num_planes: 3,
+    };
+
+    let i420_bt709_format = ImageFormat {
+        pixel_format: PixelFormat::I420,
+        color_space: ColorSpace::Bt709,
+        num_planes: 3,
+    };
+
+    let i444_format = ImageFormat {
+        pixel_format: PixelFormat::I444,
+        color_space: ColorSpace::Bt601,
+        num_planes: 3,
+    };
+
+    let packed: Planes = &[(4, 0)];
+    let packed_rgb: Planes = &[(3, 0)];
+    let nv12: Planes = &[(1, 0), (2, 1)];
+    let i420: Planes = &[(1, 0), (1, 1), (1, 1)];
+    let i444: Planes = &[(1, 0), (1, 0), (1, 0)];
+
+    let conversions: &[(
+        &str,
+        &ImageFormat,
+        Planes,
+        &ImageFormat,
+        Planes,
+        LibyuvConvert,
+    )] = &[
+        (
+            "bgra>nv12",
+            &bgra_format,
+            packed,
+            &nv12_format,
+            nv12,
+            libyuv_bgra_nv12,
+        ),
+        (
+            "bgra>i420",
+            &bgra_format,
+            packed,
+            &i420_format,
+            i420,
+            libyuv_bgra_i420,
+        ),
+        (
+            "bgra>i444",
+            &bgra_format,
+            packed,
+            &i444_format,
+            i444,
+            libyuv_bgra_i444,
+        ),
+        (
+            "nv12>bgra",
+            &nv12_format,
+            nv12,
+            &bgra_format,
+            packed,
+            libyuv_nv12_bgra,
+        ),
+        (
+            "i420>bgra",
+            &i420_format,
+            i420,
+            &bgra_format,
+            packed,
+            libyuv_i420_bgra,
+        ),
+        (
+            "i420-bt709>bgra",
+            &i420_bt709_format,
+            i420,
+            &bgra_format,
+            packed,
+            libyuv_h420_bgra,
+        ),
+        (
+            "i444>bgra",
+            &i444_format,
+            i444,
+            &bgra_format,
+            packed,
+            libyuv_i444_bgra,
+        ),
+        (
+            "rgb>bgra",
+            &rgb_format,
+            packed_rgb,
+            &bgra_format,
+            packed,
+            libyuv_rgb_bgra,
+        ),
+        (
+            "bgra>rgb",
+            &bgra_format,
+            packed,
+            &rgb_format,
+            packed_rgb,
+            libyuv_bgra_rgb,
+        ),
+    ];
+
+    for (resolution, width, height) in RESOLUTIONS.iter() {
+        for (name, src_format, src_planes, dst_format, dst_planes, libyuv_convert) in
+            conversions.iter()
+        {
+            // Both libraries are measured in the same group, so that reports show them
+            // side by side
+            let mut group = c.benchmark_group(format!("{}/{}", name, resolution));
+            group.sample_size(SAMPLE_SIZE);
+            group.throughput(Throughput::Elements((*width as u64) * (*height as u64)));
+
+            let (mut dcp_convert, mut libyuv_convert) = prepare(
+                *width,
+                *height,
+                src_format,
+                src_planes,
+                dst_format,
+                dst_planes,
+                *libyuv_convert,
+            );
+
+            group.bench_function("dcv-color-primitives", |b| {
+                b.iter(|| dcp_convert().expect("Benchmark iteration failed"));
+            });
+            group.bench_function("libyuv", |b| {
+                b.iter(|| libyuv_convert().expect("Benchmark iteration failed"));
+            });
+
+            group.finish();
+        }
+    }
+}
+
+criterion_group!(benches, bench_libyuv);
+criterion_main!(benches);
@@ -0,0 +1,143 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+const KERNEL_LIST: &str = "src/kernels.in";
+
+/// A line of the kernel list: source pixel format and color space, destination pixel
+/// format and color space
+struct Kernel<'a> {
+    values: [&'a str; 4],
+}
+
+impl<'a> Kernel<'a> {
+    fn parse(line: &'a str) -> Option<Kernel<'a>> {
+        let mut tokens = line.split_whitespace();
+        let mut values = [""; 4];
+        for value in values.iter_mut() {
+            *value = tokens.next()?;
+        }
+
+        match tokens.next() {
+            Some(_) => None,
+            None => Some(Kernel { values }),
+        }
+    }
+
+    fn name(&self) -> String {
+        self.values.join("_").to_lowercase()
+    }
+
+    fn feature(&self) -> String {
+        format!("only-{}-{}", self.values[0], self.values[2]).to_lowercase()
+    }
+}
+
+/// Generates the macro filling a dispatch table with the kernels of an instruction
+/// set, and the description of the kernels built into the library
+fn generate_dispatch_table(kernels: &[Kernel], selected: bool) -> String {
+    let kernels: Vec<&Kernel> = kernels
+        .iter()
+        .filter(|kernel| {
+            let feature = kernel.feature().to_uppercase().replace('-', "_");
+            !selected || env::var_os(format!("CARGO_FEATURE_{}", feature)).is_some()
+        })
+        .collect();
+
+    let mut code = String::new();
+    writeln!(code, "// Generated by build.rs from {}", KERNEL_LIST).unwrap();
+    writeln!(code, "macro_rules! set_dispatch_table {{").unwrap();
+    writeln!(code, "    ($conv:expr, $set:ident) => {{").unwrap();
+    for kernel in &kernels {
+        writeln!(
+            code,
+            "        set_dispatcher!($conv, $set, {}, {});",
+            kernel.values.join(", "),
+            kernel.name()
+        )
+        .unwrap();
+    }
+    writeln!(code, "    }};\n}}\n").unwrap();
+
+    writeln!(code, "const BUILT_KERNELS: &[KernelDescription] = &[").unwrap();
+    for kernel in &kernels {
+        writeln!(
+            code,
+            "    KernelDescription {{ src_pixel_format: PixelFormat::{}, src_color_space: \
+             ColorSpace::{}, dst_pixel_format: PixelFormat::{}, dst_color_space: \
+             ColorSpace::{}, feature: \"{}\" }},",
+            kernel.values[0],
+            kernel.values[1],
+            kernel.values[2],
+            kernel.values[3],
+            kernel.feature()
+        )
+        .unwrap();
+    }
+    writeln!(code, "];").unwrap();
+
+    code
+}
+
+fn main() {
+    // When one or more `only-*` features are enabled, the library contains just the
+    // conversions they select. Otherwise it contains all of them
+    let selected = env::vars_os().any(|(key, _)| {
+        key.to_str()
+            .map(|key| key.starts_with("CARGO_FEATURE_ONLY_"))
+            .unwrap_or(false)
+    });
+
+    println!("cargo:rustc-check-cfg=cfg(selected_kernels)");
+    if selected {
+        println!("cargo:rustc-cfg=selected_kernels");
+    }
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={}", KERNEL_LIST);
+    let list = fs::read_to_string(KERNEL_LIST).expect("unable to read the kernel list");
+    let kernels: Vec<Kernel> = list
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| match Kernel::parse(line) {
+            Some(kernel) => kernel,
+            None => panic!("{}:{}: expected four values", KERNEL_LIST, number + 1),
+        })
+        .collect();
+
+    let out_dir = env::var_os("OUT_DIR").expect("OUT_DIR is not set");
+    fs::write(
+        Path::new(&out_dir).join("dispatch_table.rs"),
+        generate_dispatch_table(&kernels, selected),
+    )
+    .expect("unable to write the dispatch table");
+
+    // Builds without SIMD kernels do not need to detect the cpu features. Portable builds
+    // must not execute `cpuid`, which Miri does not support
+    let no_cpu_detection = ["CARGO_FEATURE_SMALL", "CARGO_FEATURE_PORTABLE"]
+        .iter()
+        .any(|feature| env::var_os(feature).is_some());
+
+    println!("cargo:rustc-check-cfg=cfg(no_cpu_detection)");
+    if no_cpu_detection {
+        println!("cargo:rustc-cfg=no_cpu_detection");
+    }
+}
@@ -322,6 +322,10 @@ static const uint8_t rgb_to_yuv_cr2_bt709_output[4][4] = {
 
 #define MAX_NUMBER_OF_PLANES 3
 
+/* Pixel formats are only ever appended, so rgb formats are not a contiguous range */
+#define LAST_PIXEL_FORMAT DCP_PIXEL_FORMAT_RGB48
+#define IS_YUV_PIXEL_FORMAT(pf) ((pf) >= DCP_PIXEL_FORMAT_I444 && (pf) <= DCP_PIXEL_FORMAT_YUV_F32_PLANAR)
+
 static const size_t num_log2_den[][2] = {
     { 4, 0, },
     { 4, 0, },
@@ -332,6 +336,13 @@ static const size_t num_log2_den[][2] = {
     { 2, 0, },
     { 3, 1, },
     { 3, 1, },
+    { 3, 0, },
+    { 12, 0, },
+    { 8, 0, },
+    { 12, 0, },
+    { 16, 0, },
+    { 6, 0, },
+    { 6, 0, },
 };
 
 static const size_t num_log2_den_per_plane[][3 * MAX_NUMBER_OF_PLANES] = {
@@ -344,6 +355,13 @@ static const size_t num_log2_den_per_plane[][3 * MAX_NUMBER_OF_PLANES] = {
     { 1, 0,  1, 1,  1, 1, },
     { 1, 0,  1, 2,  1, 2, },
     { 1, 0,  1, 1,  0, 0, },
+    { 2, 0,  1, 0,  0, 0, },
+    { 4, 0,  4, 0,  4, 0, },
+    { 8, 0,  0, 0,  0, 0, },
+    { 12, 0,  0, 0,  0, 0, },
+    { 16, 0,  0, 0,  0, 0, },
+    { 6, 0,  0, 0,  0, 0, },
+    { 6, 0,  0, 0,  0, 0, },
 };
 
 /*
@@ -424,6 +442,58 @@ unit_init(void)
     TEST_END_GROUP();
 }
 
+static void
+unit_describe_conversion(void)
+{
+    char *desc;
+    Allocator alloc = { 0, };
+
+    DcpImageFormat src_format = {
+        DCP_PIXEL_FORMAT_RGB,
+        DCP_COLOR_SPACE_LRGB,
+        1
+    };
+
+    DcpImageFormat dst_format = {
+        DCP_PIXEL_FORMAT_NV12,
+        DCP_COLOR_SPACE_BT601,
+        2
+    };
+
+    DcpImageFormat invalid_format = {
+        DCP_PIXEL_FORMAT_NV12,
+        (DcpColorSpace)(DCP_COLOR_SPACE_JFIF + 1),
+        2
+    };
+
+    TEST_BEGIN_GROUP(__FUNCTION__);
+
+    TEST_BEGIN("describe_conversion");
+
+    desc = dcp_describe_conversion(640, 480, &src_format, &dst_format);
+    TEST_ASSERT_EQ((desc == NULL), 1);
+
+    init();
+
+    desc = dcp_describe_conversion(640, 480, &src_format, &dst_format);
+    TEST_ASSERT_EQ((desc != NULL), 1);
+    TEST_ASSERT_EQ(strcmp(desc, "{route:[Rgb-Lrgb,Bgra-Lrgb,Nv12-Bt601]}"), 0);
+    dcp_unref_string(desc);
+
+    desc = dcp_describe_conversion(640, 480, &dst_format, &dst_format);
+    TEST_ASSERT_EQ((desc == NULL), 1);
+
+    desc = dcp_describe_conversion(640, 480, &src_format, &invalid_format);
+    TEST_ASSERT_EQ((desc == NULL), 1);
+
+    desc = dcp_describe_conversion(640, 480, NULL, &dst_format);
+    TEST_ASSERT_EQ((desc == NULL), 1);
+
+    TEST_END();
+
+    TEST_END_GROUP();
+}
+
 static void
 convert_image_rgb_to_yuv_size_mode_stride(uint32_t       num_planes,
                                           uint32_t       width,
@@ -1121,8 +1191,9 @@ unit_convert_image_rgb_to_yuv_errors(void)
     const uint32_t width = 2;
     const uint32_t height = 2;
     const uint32_t chroma_height = (height / 2);
-    const size_t src_stride = (size_t)width * 4;
-    const size_t in_size = src_stride * height;
+    /* Large enough for any source pixel format, as unsupported ones are routed */
+    const size_t src_stride = (size_t)width * 8;
+    const size_t in_size = src_stride * height * 3;
     const size_t out_size = (size_t)width * ((size_t)height + chroma_height);
     uint8_t *test_input;
     uint8_t *test_output;
@@ -1141,13 +1212,13 @@ unit_convert_image_rgb_to_yuv_errors(void)
     for (num_planes = 0; num_planes <= 3; num_planes++) { /* Only 1 and 2 are valid values */
         int32_t src_pixel_format;
 
-        for (src_pixel_format = 0; src_pixel_format <= DCP_PIXEL_FORMAT_NV12 + 1; src_pixel_format++) {
+        for (src_pixel_format = 0; src_pixel_format <= LAST_PIXEL_FORMAT + 1; src_pixel_format++) {
             int32_t src_color_space;
 
-            for (src_color_space = 0; src_color_space <= DCP_COLOR_SPACE_BT709 + 1; src_color_space++) {
+            for (src_color_space = 0; src_color_space <= DCP_COLOR_SPACE_JFIF + 1; src_color_space++) {
                 int32_t dst_color_space;
 
-                for (dst_color_space = 0; dst_color_space <= DCP_COLOR_SPACE_BT709 + 1; dst_color_space++) {
+                for (dst_color_space = 0; dst_color_space <= DCP_COLOR_SPACE_JFIF + 1; dst_color_space++) {
                     int32_t corrupt;
 
                     for (corrupt = 0; corrupt < 4; corrupt++) {
@@ -1205,15 +1276,15 @@ unit_convert_image_rgb_to_yuv_errors(void)
 
                         expected = dcp_status();
 
-                        SET_EXPECTED(src_pixel_format > DCP_PIXEL_FORMAT_NV12, DCP_ERROR_KIND_INVALID_VALUE);
-                        SET_EXPECTED(src_color_space > DCP_COLOR_SPACE_BT709, DCP_ERROR_KIND_INVALID_VALUE);
-                        SET_EXPECTED(dst_color_space > DCP_COLOR_SPACE_BT709, DCP_ERROR_KIND_INVALID_VALUE);
+                        SET_EXPECTED(src_pixel_format > LAST_PIXEL_FORMAT, DCP_ERROR_KIND_INVALID_VALUE);
+                        SET_EXPECTED(src_color_space > DCP_COLOR_SPACE_JFIF, DCP_ERROR_KIND_INVALID_VALUE);
+                        SET_EXPECTED(dst_color_space > DCP_COLOR_SPACE_JFIF, DCP_ERROR_KIND_INVALID_VALUE);
 
                         SET_EXPECTED((width & 1) != 0, DCP_ERROR_KIND_INVALID_VALUE);
                         SET_EXPECTED((height & 1) != 0, DCP_ERROR_KIND_INVALID_VALUE);
 
-                        SET_EXPECTED((src_pixel_format >= DCP_PIXEL_FORMAT_I444) && (src_color_space <= DCP_COLOR_SPACE_LRGB), DCP_ERROR_KIND_INVALID_VALUE);
-                        SET_EXPECTED((src_pixel_format < DCP_PIXEL_FORMAT_I444) && (src_color_space > DCP_COLOR_SPACE_LRGB), DCP_ERROR_KIND_INVALID_VALUE);
+                        SET_EXPECTED(IS_YUV_PIXEL_FORMAT(src_pixel_format) && (src_color_space <= DCP_COLOR_SPACE_LRGB), DCP_ERROR_KIND_INVALID_VALUE);
+                        SET_EXPECTED(!IS_YUV_PIXEL_FORMAT(src_pixel_format) && (src_color_space > DCP_COLOR_SPACE_LRGB), DCP_ERROR_KIND_INVALID_VALUE);
 
                         SET_EXPECTED(dst_color_space <= DCP_COLOR_SPACE_LRGB, DCP_ERROR_KIND_INVALID_VALUE);
 
@@ -1222,11 +1293,25 @@ unit_convert_image_rgb_to_yuv_errors(void)
 
                         SET_EXPECTED(corrupt != 0, DCP_ERROR_KIND_INVALID_VALUE);
 
-                        SET_EXPECTED((src_pixel_format != DCP_PIXEL_FORMAT_ARGB &&
-                                      src_pixel_format != DCP_PIXEL_FORMAT_BGRA &&
-                                      src_pixel_format != DCP_PIXEL_FORMAT_BGR), DCP_ERROR_KIND_INVALID_OPERATION);
-
-                        SET_EXPECTED(src_color_space != DCP_COLOR_SPACE_LRGB, DCP_ERROR_KIND_INVALID_OPERATION);
+                        /* Sources without a direct conversion are routed through BGRA, BT2020 PQ ones through P010 and RGBA F16 */
+                        SET_EXPECTED((src_pixel_format == DCP_PIXEL_FORMAT_RGBA ||
+                                      src_pixel_format == DCP_PIXEL_FORMAT_RGB_F32 ||
+                                      src_pixel_format == DCP_PIXEL_FORMAT_RGBA_F32 ||
+                                      src_pixel_format == DCP_PIXEL_FORMAT_I422), DCP_ERROR_KIND_INVALID_OPERATION);
+                        /* Nothing converts from YUV F32 planar */
+                        SET_EXPECTED(src_pixel_format == DCP_PIXEL_FORMAT_YUV_F32_PLANAR, DCP_ERROR_KIND_INVALID_OPERATION);
+                        SET_EXPECTED((src_pixel_format != DCP_PIXEL_FORMAT_NV12 && src_pixel_format != DCP_PIXEL_FORMAT_P010) &&
+                                     (src_color_space == DCP_COLOR_SPACE_BT2020_PQ), DCP_ERROR_KIND_INVALID_OPERATION);
+                        /* Nothing converts from JFIF P010 */
+                        SET_EXPECTED((src_pixel_format == DCP_PIXEL_FORMAT_P010) && (src_color_space == DCP_COLOR_SPACE_JFIF), DCP_ERROR_KIND_INVALID_OPERATION);
+                        SET_EXPECTED((src_pixel_format == DCP_PIXEL_FORMAT_NV12) && (src_color_space == dst_color_space), DCP_ERROR_KIND_INVALID_OPERATION);
+                        /* Only P010 converts to BT2020 PQ NV12 */
+                        SET_EXPECTED((dst_color_space == DCP_COLOR_SPACE_BT2020_PQ) &&
+                                     (src_pixel_format != DCP_PIXEL_FORMAT_P010 || src_color_space != dst_color_space), DCP_ERROR_KIND_INVALID_OPERATION);
+
+                        /* Single plane i444 and i420 images are not supported */
+                        SET_EXPECTED((src_pixel_format == DCP_PIXEL_FORMAT_I444 ||
+                                      src_pixel_format == DCP_PIXEL_FORMAT_I420), DCP_ERROR_KIND_NOT_ENOUGH_DATA);
 
                         status.result = dcp_convert_image(width, height,
                                                           &src_format, &src_stride, (const uint8_t * const *)&src_buffer,
@@ -1254,7 +1339,8 @@ unit_convert_image_yuv_to_rgb_errors(void)
     const uint32_t chroma_height = (height / 2);
     const size_t dst_stride = (size_t)width * 4;
     const size_t in_size = (size_t)width * ((size_t)height + chroma_height);
-    const size_t out_size = dst_stride * height;
+    /* Large enough for any destination pixel format, as unsupported ones are routed */
+    const size_t out_size = dst_stride * height * 2;
     uint8_t *test_input;
     uint8_t *test_output;
     Allocator alloc = { 0, };
@@ -1272,13 +1358,13 @@ unit_convert_image_yuv_to_rgb_errors(void)
     for (num_planes = 0; num_planes <= 3; num_planes++) { /* Only 1 and 2 are valid values */
         int32_t dst_pixel_format;
 
-        for (dst_pixel_format = 0; dst_pixel_format <= DCP_PIXEL_FORMAT_NV12 + 1; dst_pixel_format++) {
+        for (dst_pixel_format = 0; dst_pixel_format <= LAST_PIXEL_FORMAT + 1; dst_pixel_format++) {
             int32_t dst_color_space;
 
-            for (dst_color_space = 0; dst_color_space <= DCP_COLOR_SPACE_BT709 + 1; dst_color_space++) {
+            for (dst_color_space = 0; dst_color_space <= DCP_COLOR_SPACE_JFIF + 1; dst_color_space++) {
                 int32_t src_color_space;
 
-                for (src_color_space = 0; src_color_space <= DCP_COLOR_SPACE_BT709 + 1; src_color_space++) {
+                for (src_color_space = 0; src_color_space <= DCP_COLOR_SPACE_JFIF + 1; src_color_space++) {
                     int32_t corrupt;
 
                     for (corrupt = 0; corrupt < 4; corrupt++) {
@@ -1336,25 +1422,54 @@ unit_convert_image_yuv_to_rgb_errors(void)
 
                         expected = dcp_status();
 
-                        SET_EXPECTED(src_color_space > DCP_COLOR_SPACE_BT709, DCP_ERROR_KIND_INVALID_VALUE);
-                        SET_EXPECTED(dst_pixel_format > DCP_PIXEL_FORMAT_NV12, DCP_ERROR_KIND_INVALID_VALUE);
-                        SET_EXPECTED(dst_color_space > DCP_COLOR_SPACE_BT709, DCP_ERROR_KIND_INVALID_VALUE);
+                        SET_EXPECTED(src_color_space > DCP_COLOR_SPACE_JFIF, DCP_ERROR_KIND_INVALID_VALUE);
+                        SET_EXPECTED(dst_pixel_format > LAST_PIXEL_FORMAT, DCP_ERROR_KIND_INVALID_VALUE);
+                        SET_EXPECTED(dst_color_space > DCP_COLOR_SPACE_JFIF, DCP_ERROR_KIND_INVALID_VALUE);
 
                         SET_EXPECTED((width & 1) != 0, DCP_ERROR_KIND_INVALID_VALUE);
                         SET_EXPECTED((height & 1) != 0, DCP_ERROR_KIND_INVALID_VALUE);
 
                         SET_EXPECTED(src_color_space <= DCP_COLOR_SPACE_LRGB, DCP_ERROR_KIND_INVALID_VALUE);
 
-                        SET_EXPECTED((dst_pixel_format >= DCP_PIXEL_FORMAT_I444) && (dst_color_space <= DCP_COLOR_SPACE_LRGB), DCP_ERROR_KIND_INVALID_VALUE);
-                        SET_EXPECTED((dst_pixel_format < DCP_PIXEL_FORMAT_I444) && (dst_color_space > DCP_COLOR_SPACE_LRGB), DCP_ERROR_KIND_INVALID_VALUE);
+                        SET_EXPECTED(IS_YUV_PIXEL_FORMAT(dst_pixel_format) && (dst_color_space <= DCP_COLOR_SPACE_LRGB), DCP_ERROR_KIND_INVALID_VALUE);
+                        SET_EXPECTED(!IS_YUV_PIXEL_FORMAT(dst_pixel_format) && (dst_color_space > DCP_COLOR_SPACE_LRGB), DCP_ERROR_KIND_INVALID_VALUE);
 
                         SET_EXPECTED(num_planes < 1, DCP_ERROR_KIND_INVALID_VALUE);
                         SET_EXPECTED(num_planes > 2, DCP_ERROR_KIND_INVALID_VALUE);
 
                         SET_EXPECTED(corrupt != 0, DCP_ERROR_KIND_INVALID_VALUE);
 
-                        SET_EXPECTED(dst_pixel_format != DCP_PIXEL_FORMAT_BGRA, DCP_ERROR_KIND_INVALID_OPERATION);
-                        SET_EXPECTED(dst_color_space != DCP_COLOR_SPACE_LRGB, DCP_ERROR_KIND_INVALID_OPERATION);
+                        /* Destinations without a direct conversion are routed through BGRA, BT2020 PQ sources through P010 and RGBA F16 */
+                        SET_EXPECTED((dst_pixel_format != DCP_PIXEL_FORMAT_BGRA &&
+                                      dst_pixel_format != DCP_PIXEL_FORMAT_RGB &&
+                                      dst_pixel_format != DCP_PIXEL_FORMAT_RGB_F32 &&
+                                      dst_pixel_format != DCP_PIXEL_FORMAT_RGBA_F32 &&
+                                      dst_pixel_format != DCP_PIXEL_FORMAT_RGBA_F16 &&
+                                      dst_pixel_format != DCP_PIXEL_FORMAT_I444 &&
+                                      dst_pixel_format != DCP_PIXEL_FORMAT_I420 &&
+                                      dst_pixel_format != DCP_PIXEL_FORMAT_NV12 &&
+                                      dst_pixel_format != DCP_PIXEL_FORMAT_P010 &&
+                                      dst_pixel_format != DCP_PIXEL_FORMAT_YUV_F32_PLANAR), DCP_ERROR_KIND_INVALID_OPERATION);
+                        /* YUV F32 planar is only produced in BT601 and BT709 */
+                        SET_EXPECTED((dst_pixel_format == DCP_PIXEL_FORMAT_YUV_F32_PLANAR) &&
+                                     (dst_color_space != DCP_COLOR_SPACE_BT601 && dst_color_space != DCP_COLOR_SPACE_BT709), DCP_ERROR_KIND_INVALID_OPERATION);
+                        SET_EXPECTED((dst_pixel_format == DCP_PIXEL_FORMAT_RGBA_F16) && (src_color_space != DCP_COLOR_SPACE_BT2020_PQ), DCP_ERROR_KIND_INVALID_OPERATION);
+                        /* Nothing converts to JFIF P010 */
+                        SET_EXPECTED((dst_pixel_format == DCP_PIXEL_FORMAT_P010) && (dst_color_space == DCP_COLOR_SPACE_JFIF), DCP_ERROR_KIND_INVALID_OPERATION);
+                        /* Only NV12 converts to BT2020 PQ P010 */
+                        SET_EXPECTED((dst_color_space == DCP_COLOR_SPACE_BT2020_PQ) &&
+                                     (dst_pixel_format != DCP_PIXEL_FORMAT_P010 || src_color_space != dst_color_space), DCP_ERROR_KIND_INVALID_OPERATION);
+                        SET_EXPECTED((dst_pixel_format == DCP_PIXEL_FORMAT_NV12) && (dst_color_space == src_color_space), DCP_ERROR_KIND_INVALID_OPERATION);
+
+                        /* Single plane i444 and i420 images are not supported */
+                        SET_EXPECTED((dst_pixel_format == DCP_PIXEL_FORMAT_I444 ||
+                                      dst_pixel_format == DCP_PIXEL_FORMAT_I420), DCP_ERROR_KIND_NOT_ENOUGH_DATA);
+
+                        /* Float destinations need more than four bytes per pixel */
+                        SET_EXPECTED((dst_pixel_format == DCP_PIXEL_FORMAT_RGB_F32 ||
+                                      dst_pixel_format == DCP_PIXEL_FORMAT_RGBA_F32 ||
+                                      dst_pixel_format == DCP_PIXEL_FORMAT_RGBA_F16 ||
+                                      dst_pixel_format == DCP_PIXEL_FORMAT_YUV_F32_PLANAR), DCP_ERROR_KIND_NOT_ENOUGH_DATA);
 
                         status.result = dcp_convert_image(width, height,
                                                           &src_format, src_strides, (const uint8_t * const *)src_buffers,
@@ -1382,12 +1497,12 @@ unit_get_buffers_plane(int32_t num_planes)
     Allocator alloc = { 0, };
     int32_t pf;
 
-    for (pf = DCP_PIXEL_FORMAT_ARGB - 1; pf <= DCP_PIXEL_FORMAT_NV12 + 1; pf++) {
+    for (pf = DCP_PIXEL_FORMAT_ARGB - 1; pf <= LAST_PIXEL_FORMAT + 1; pf++) {
         size_t buffers_size[MAX_NUMBER_OF_PLANES];
         int32_t max_number_of_planes;
         DcpStatus expected;
         DcpStatus status;
-        uint8_t is_pf_valid = (pf >= DCP_PIXEL_FORMAT_ARGB && pf <= DCP_PIXEL_FORMAT_NV12);
+        uint8_t is_pf_valid = (pf >= DCP_PIXEL_FORMAT_ARGB && pf <= LAST_PIXEL_FORMAT);
 
         DcpImageFormat format = {
             pf,
@@ -1409,7 +1524,7 @@ unit_get_buffers_plane(int32_t num_planes)
         /* Invalid width */
         expected = dcp_status();
         SET_EXPECTED(!is_pf_valid, DCP_ERROR_KIND_INVALID_VALUE);
-        SET_EXPECTED(pf >= DCP_PIXEL_FORMAT_I422, DCP_ERROR_KIND_INVALID_VALUE);
+        SET_EXPECTED(pf >= DCP_PIXEL_FORMAT_I422 && pf <= DCP_PIXEL_FORMAT_P010, DCP_ERROR_KIND_INVALID_VALUE);
         SET_EXPECTED(num_planes != 1 && num_planes != max_number_of_planes, DCP_ERROR_KIND_INVALID_VALUE);
         status.result = dcp_get_buffers_size(1, valid_height, &format, NULL, buffers_size, &status.error);
         TEST_ASSERT(expected.result, expected.error);
@@ -1417,7 +1532,7 @@ unit_get_buffers_plane(int32_t num_planes)
         /* Invalid height */
         expected = dcp_status();
         SET_EXPECTED(!is_pf_valid, DCP_ERROR_KIND_INVALID_VALUE);
-        SET_EXPECTED(pf >= DCP_PIXEL_FORMAT_I420, DCP_ERROR_KIND_INVALID_VALUE);
+        SET_EXPECTED(pf >= DCP_PIXEL_FORMAT_I420 && pf <= DCP_PIXEL_FORMAT_P010, DCP_ERROR_KIND_INVALID_VALUE);
         SET_EXPECTED(num_planes != 1 && num_planes != max_number_of_planes, DCP_ERROR_KIND_INVALID_VALUE);
         status.result = dcp_get_buffers_size(valid_width, 1, &format, NULL, buffers_size, &status.error);
         TEST_ASSERT(expected.result, expected.error);
@@ -1480,6 +1595,82 @@ unit_get_buffers_size(void)
 }
 
 
+static void
+unit_describe_layout(void)
+{
+    static const uint32_t width = 1366;
+    static const uint32_t height = 768;
+    static const size_t strides[1] = { 1408, };
+    static const size_t short_strides[1] = { 1365, };
+    DcpStatus status = dcp_status();
+    DcpPlaneLayoutDescriptor planes[DCP_MAX_LOGICAL_PLANES];
+    Allocator alloc = { 0, };
+
+    DcpImageFormat nv12_format = {
+        DCP_PIXEL_FORMAT_NV12,
+        DCP_COLOR_SPACE_BT601,
+        1
+    };
+
+    DcpImageFormat i420_format = {
+        DCP_PIXEL_FORMAT_I420,
+        DCP_COLOR_SPACE_BT709,
+        3
+    };
+
+    TEST_BEGIN_GROUP(__FUNCTION__);
+
+    TEST_BEGIN("null_pointers");
+    status.result = dcp_describe_layout(width, height, NULL, NULL, planes, &status.error);
+    TEST_ASSERT(DCP_RESULT_ERR, DCP_ERROR_KIND_INVALID_VALUE);
+    status.result = dcp_describe_layout(width, height, &nv12_format, NULL, NULL, &status.error);
+    TEST_ASSERT(DCP_RESULT_ERR, DCP_ERROR_KIND_INVALID_VALUE);
+    TEST_END();
+
+    TEST_BEGIN("nv12_single_buffer");
+    memset(planes, 0xFF, sizeof(planes));
+    status.result = dcp_describe_layout(width, height, &nv12_format, strides, planes, &status.error);
+    TEST_ASSERT(DCP_RESULT_OK, 0);
+    TEST_ASSERT_EQ(planes[0].buffer, 0);
+    TEST_ASSERT_EQ(planes[0].offset, 0);
+    TEST_ASSERT_EQ(planes[0].stride, 1408);
+    TEST_ASSERT_EQ(planes[0].size, 1408 * (size_t)height);
+    TEST_ASSERT_EQ(planes[1].buffer, 0);
+    TEST_ASSERT_EQ(planes[1].offset, 1408 * (size_t)height);
+    TEST_ASSERT_EQ(planes[1].stride, 1408);
+    TEST_ASSERT_EQ(planes[1].size, 1408 * (size_t)height / 2);
+    TEST_ASSERT_EQ(planes[1].width, width / 2);
+    TEST_ASSERT_EQ(planes[1].height, height / 2);
+    TEST_ASSERT_EQ(planes[1].bytes_per_sample, 2);
+    TEST_ASSERT_EQ(planes[1].horizontal_subsampling, 2);
+    TEST_ASSERT_EQ(planes[1].vertical_subsampling, 2);
+    TEST_ASSERT_EQ(planes[2].size, 0);
+    TEST_ASSERT_EQ(planes[2].stride, 0);
+    TEST_END();
+
+    TEST_BEGIN("i420_packed");
+    status.result = dcp_describe_layout(width, height, &i420_format, NULL, planes, &status.error);
+    TEST_ASSERT(DCP_RESULT_OK, 0);
+    TEST_ASSERT_EQ(planes[2].buffer, 2);
+    TEST_ASSERT_EQ(planes[2].offset, 0);
+    TEST_ASSERT_EQ(planes[2].stride, width / 2);
+    TEST_ASSERT_EQ(planes[2].size, (size_t)width * (size_t)height / 4);
+    TEST_ASSERT_EQ(planes[2].bytes_per_sample, 1);
+    TEST_END();
+
+    TEST_BEGIN("errors");
+    status.result = dcp_describe_layout(width + 1, height, &nv12_format, NULL, planes, &status.error);
+    TEST_ASSERT(DCP_RESULT_ERR, DCP_ERROR_KIND_INVALID_VALUE);
+    status.result = dcp_describe_layout(width, height, &nv12_format, short_strides, planes, &status.error);
+    TEST_ASSERT(DCP_RESULT_ERR, DCP_ERROR_KIND_NOT_ENOUGH_DATA);
+    i420_format.num_planes = 2;
+    status.result = dcp_describe_layout(width, height, &i420_format, NULL, planes, &status.error);
+    TEST_ASSERT(DCP_RESULT_ERR, DCP_ERROR_KIND_INVALID_VALUE);
+    TEST_END();
+
+    TEST_END_GROUP();
+}
+
 static void
 unit_convert_image_over_4gb_limit(void)
 {
@@ -1747,8 +1938,12 @@ main(int   argc,
 
     if (strcmp(test_name, "unit_init") == 0) {
         unit_init();
+    } else if (strcmp(test_name, "unit_describe_conversion") == 0) {
+        unit_describe_conversion();
     } else if (strcmp(test_name, "unit_get_buffers_size") == 0) {
         unit_get_buffers_size();
+    } else if (strcmp(test_name, "unit_describe_layout") == 0) {
+        unit_describe_layout();
     } else if (strcmp(test_name, "unit_convert_image_rgb_to_nv12_ok") == 0) {
         int32_t planes = *(argv[2]) - '0';
         unit_convert_image_rgb_to_yuv(DCP_PIXEL_FORMAT_NV12, planes);
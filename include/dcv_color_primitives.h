@@ -22,19 +22,25 @@
  *
  * It is able to convert the following pixel formats:
  *
- * | Source pixel format  | Destination pixel formats  |
- * | -------------------- | -------------------------- |
- * | ARGB                 | I420, I444, NV12           |
- * | BGR                  | I420, I444, NV12           |
- * | BGRA                 | I420, I444, NV12, RGB      |
- * | I420                 | BGRA                       |
- * | I444                 | BGRA                       |
- * | NV12                 | BGRA                       |
- * | RGB                  | BGRA                       |
+ * | Source pixel format | Destination pixel formats             |
+ * | ------------------- | ------------------------------------- |
+ * | ARGB                | I420, I444, NV12, YUV F32 planar      |
+ * | BGR                 | I420, I444, NV12, YUV F32 planar      |
+ * | BGR48               | BGRA, P010                            |
+ * | BGRA                | I420, I444, NV12, RGB, YUV F32 planar |
+ * | I420                | BGRA, I420, RGB F32, RGBA F32         |
+ * | I444                | BGRA, I444, RGB F32, RGBA F32         |
+ * | NV12                | BGRA, NV12, P010, RGB F32, RGBA F32   |
+ * | P010                | NV12, RGBA F16                        |
+ * | RGB                 | BGRA                                  |
+ * | RGB48               | BGRA, P010                            |
+ * | RGBA F16            | BGRA                                  |
  *
  * The supported color models are:
  * - YCbCr, ITU-R Recommendation BT.601 (standard video system)
  * - YCbCr, ITU-R Recommendation BT.709 (CSC systems)
+ * - YCbCr, ITU-R Recommendation BT.2020 with SMPTE ST 2084 transfer function (HDR10)
+ * - YCbCr, JPEG File Interchange Format (BT.601 full range), matching libjpeg
  *
  * # Examples
  *
@@ -278,14 +284,17 @@ typedef enum {
  * @DCP_ERROR_KIND_INVALID_OPERATION: The combination of parameters is unsupported for the called function
  * @DCP_ERROR_KIND_NOT_ENOUGH_DATA: Not enough data was provided to the called function.
  *                                  Typically, provided arrays are not correctly sized
+ * @DCP_ERROR_KIND_CANCELLED: The operation was aborted through its cancellation token.
+ *                            Only reported by the Rust API
  *
- * An enumeration of errors.
+ * An enumeration of errors. New errors may be added in minor releases.
  */
 typedef enum {
     DCP_ERROR_KIND_NOT_INITIALIZED,
     DCP_ERROR_KIND_INVALID_VALUE,
     DCP_ERROR_KIND_INVALID_OPERATION,
     DCP_ERROR_KIND_NOT_ENOUGH_DATA,
+    DCP_ERROR_KIND_CANCELLED,
 } DcpErrorKind;
 
 /**
@@ -308,6 +317,31 @@ typedef enum {
  *                         Chroma planes are subsampled in both the horizontal and vertical dimensions
  *                         by a factor of 2.
  *                         12 bits per pixel
+ * @DCP_PIXEL_FORMAT_P010: YUV with one luma plane Y then one plane with U and V values interleaved.
+ *                         Chroma planes are subsampled in both the horizontal and vertical dimensions
+ *                         by a factor of 2.
+ *                         Each sample is a little endian 16-bit word holding a 10-bit value in its
+ *                         upper bits.
+ *                         24 bits per pixel
+ * @DCP_PIXEL_FORMAT_YUV_F32_PLANAR: YUV with one luma plane Y then 2 chroma planes U and V.
+ *                         Chroma planes are not sub-sampled.
+ *                         Each sample is a little endian 32-bit float, with luma in [0, 1] and
+ *                         chroma in [-0.5, 0.5].
+ *                         96 bits per pixel
+ * @DCP_PIXEL_FORMAT_RGBA_F16: RGB with alpha channel last, each component is a little endian half float.
+ *                             Components are linear and encoded using scRGB: sRGB primaries, 1.0 is the
+ *                             SDR reference white (80 nits), values above 1.0 and below 0.0 are allowed.
+ *                             64 bits per pixel
+ * @DCP_PIXEL_FORMAT_RGB_F32: RGB packed without padding, each component is a little endian single
+ *                            precision float. Components are normalized to [0, 1], as expected by
+ *                            floating point textures. 96 bits per pixel
+ * @DCP_PIXEL_FORMAT_RGBA_F32: RGB with alpha channel last, each component is a little endian single
+ *                             precision float. Components are normalized to [0, 1], as expected by
+ *                             floating point textures. 128 bits per pixel
+ * @DCP_PIXEL_FORMAT_BGR48: Reverse RGB packed without padding, each component is a little endian
+ *                          16-bit word. 48 bits per pixel
+ * @DCP_PIXEL_FORMAT_RGB48: RGB packed without padding, each component is a little endian 16-bit
+ *                          word. 48 bits per pixel
  *
  * An enumeration of supported pixel formats.
  */
@@ -321,6 +355,13 @@ typedef enum {
     DCP_PIXEL_FORMAT_I422,
     DCP_PIXEL_FORMAT_I420,
     DCP_PIXEL_FORMAT_NV12,
+    DCP_PIXEL_FORMAT_P010,
+    DCP_PIXEL_FORMAT_YUV_F32_PLANAR,
+    DCP_PIXEL_FORMAT_RGBA_F16,
+    DCP_PIXEL_FORMAT_RGB_F32,
+    DCP_PIXEL_FORMAT_RGBA_F32,
+    DCP_PIXEL_FORMAT_BGR48,
+    DCP_PIXEL_FORMAT_RGB48,
 } DcpPixelFormat;
 
 /**
@@ -328,6 +369,10 @@ typedef enum {
  * @DCP_COLOR_SPACE_LRGB: Gamma-corrected RGB
  * @DCP_COLOR_SPACE_BT601: YCbCr, ITU-R Recommendation BT.601 (standard video system)
  * @DCP_COLOR_SPACE_BT709: YCbCr, ITU-R Recommendation BT.709 (CSC systems)
+ * @DCP_COLOR_SPACE_BT2020_PQ: YCbCr, ITU-R Recommendation BT.2020 (non-constant luminance), with
+ *                             SMPTE ST 2084 perceptual quantizer transfer function (HDR10)
+ * @DCP_COLOR_SPACE_JFIF: YCbCr, JPEG File Interchange Format (BT.601 full range), rounding as
+ *                        libjpeg does
  *
  * An enumeration of supported color models.
  *
@@ -342,6 +387,8 @@ typedef enum {
     DCP_COLOR_SPACE_LRGB,
     DCP_COLOR_SPACE_BT601,
     DCP_COLOR_SPACE_BT709,
+    DCP_COLOR_SPACE_BT2020_PQ,
+    DCP_COLOR_SPACE_JFIF,
 } DcpColorSpace;
 
 /**
@@ -359,32 +406,46 @@ typedef enum {
  *
  * Each pixel format has one or more compatible color spaces:
  *
- * pixel_format          | color_space
- * ----------------------|---------------------------------------------
- * DCP_PIXEL_FORMAT_ARGB | DCP_COLOR_SPACE_LRGB
- * DCP_PIXEL_FORMAT_BGRA | DCP_COLOR_SPACE_LRGB
- * DCP_PIXEL_FORMAT_BGR  | DCP_COLOR_SPACE_LRGB
- * DCP_PIXEL_FORMAT_RGBA | DCP_COLOR_SPACE_LRGB
- * DCP_PIXEL_FORMAT_RGB  | DCP_COLOR_SPACE_LRGB
- * DCP_PIXEL_FORMAT_I444 | DCP_COLOR_SPACE_BT601, DCP_COLOR_SPACE_BT709
- * DCP_PIXEL_FORMAT_I422 | DCP_COLOR_SPACE_BT601, DCP_COLOR_SPACE_BT709
- * DCP_PIXEL_FORMAT_I420 | DCP_COLOR_SPACE_BT601, DCP_COLOR_SPACE_BT709
- * DCP_PIXEL_FORMAT_NV12 | DCP_COLOR_SPACE_BT601, DCP_COLOR_SPACE_BT709
+ * pixel_format              | color_space
+ * --------------------------|--------------------------------------------------------------------
+ * DCP_PIXEL_FORMAT_ARGB     | DCP_COLOR_SPACE_LRGB
+ * DCP_PIXEL_FORMAT_BGRA     | DCP_COLOR_SPACE_LRGB
+ * DCP_PIXEL_FORMAT_BGR      | DCP_COLOR_SPACE_LRGB
+ * DCP_PIXEL_FORMAT_RGBA     | DCP_COLOR_SPACE_LRGB
+ * DCP_PIXEL_FORMAT_RGB      | DCP_COLOR_SPACE_LRGB
+ * DCP_PIXEL_FORMAT_RGBA_F16 | DCP_COLOR_SPACE_LRGB
+ * DCP_PIXEL_FORMAT_RGB_F32  | DCP_COLOR_SPACE_LRGB
+ * DCP_PIXEL_FORMAT_RGBA_F32 | DCP_COLOR_SPACE_LRGB
+ * DCP_PIXEL_FORMAT_BGR48    | DCP_COLOR_SPACE_LRGB
+ * DCP_PIXEL_FORMAT_RGB48    | DCP_COLOR_SPACE_LRGB
+ * DCP_PIXEL_FORMAT_I444     | DCP_COLOR_SPACE_BT601, DCP_COLOR_SPACE_BT709, DCP_COLOR_SPACE_BT2020_PQ, DCP_COLOR_SPACE_JFIF
+ * DCP_PIXEL_FORMAT_I422     | DCP_COLOR_SPACE_BT601, DCP_COLOR_SPACE_BT709, DCP_COLOR_SPACE_BT2020_PQ, DCP_COLOR_SPACE_JFIF
+ * DCP_PIXEL_FORMAT_I420     | DCP_COLOR_SPACE_BT601, DCP_COLOR_SPACE_BT709, DCP_COLOR_SPACE_BT2020_PQ, DCP_COLOR_SPACE_JFIF
+ * DCP_PIXEL_FORMAT_NV12     | DCP_COLOR_SPACE_BT601, DCP_COLOR_SPACE_BT709, DCP_COLOR_SPACE_BT2020_PQ, DCP_COLOR_SPACE_JFIF
+ * DCP_PIXEL_FORMAT_P010     | DCP_COLOR_SPACE_BT601, DCP_COLOR_SPACE_BT709, DCP_COLOR_SPACE_BT2020_PQ, DCP_COLOR_SPACE_JFIF
+ * DCP_PIXEL_FORMAT_YUV_F32_PLANAR | DCP_COLOR_SPACE_BT601, DCP_COLOR_SPACE_BT709, DCP_COLOR_SPACE_BT2020_PQ, DCP_COLOR_SPACE_JFIF
  *
  * Some pixel formats might impose additional restrictions on the accepted number of
  * planes and the image size:
  *
- * pixel_format          | subsampling | w   | h   | #planes | #1     | #2     | #3
- * ----------------------|:-----------:|:---:|:---:|:-------:|:------:|:------:|:-------:
- * DCP_PIXEL_FORMAT_ARGB | 4:4:4       |     |     | 1       | argb:4 |        |
- * DCP_PIXEL_FORMAT_BGRA | 4:4:4       |     |     | 1       | bgra:4 |        |
- * DCP_PIXEL_FORMAT_BGR  | 4:4:4       |     |     | 1       | bgr:3  |        |
- * DCP_PIXEL_FORMAT_RGBA | 4:4:4       |     |     | 1       | rgba:4 |        |
- * DCP_PIXEL_FORMAT_RGB  | 4:4:4       |     |     | 1       | rgb:3  |        |
- * DCP_PIXEL_FORMAT_I444 | 4:4:4       |     |     | 3       | y:1    | u:1    | v:1
- * DCP_PIXEL_FORMAT_I422 | 4:2:2       |  2  |     | 1, 3    | y:1    | u:1/2  | v:1/2
- * DCP_PIXEL_FORMAT_I420 | 4:2:0       |  2  |  2  | 3       | y:1    | u:1/4  | v:1/4
- * DCP_PIXEL_FORMAT_NV12 | 4:2:0       |  2  |  2  | 1, 2    | y:1    | uv:1/2 |
+ * pixel_format              | subsampling | w   | h   | #planes | #1     | #2     | #3
+ * --------------------------|:-----------:|:---:|:---:|:-------:|:------:|:------:|:-------:
+ * DCP_PIXEL_FORMAT_ARGB     | 4:4:4       |     |     | 1       | argb:4 |        |
+ * DCP_PIXEL_FORMAT_BGRA     | 4:4:4       |     |     | 1       | bgra:4 |        |
+ * DCP_PIXEL_FORMAT_BGR      | 4:4:4       |     |     | 1       | bgr:3  |        |
+ * DCP_PIXEL_FORMAT_RGBA     | 4:4:4       |     |     | 1       | rgba:4 |        |
+ * DCP_PIXEL_FORMAT_RGB      | 4:4:4       |     |     | 1       | rgb:3  |        |
+ * DCP_PIXEL_FORMAT_RGBA_F16 | 4:4:4       |     |     | 1       | rgba:8 |        |
+ * DCP_PIXEL_FORMAT_RGB_F32  | 4:4:4       |     |     | 1       | rgb:12 |        |
+ * DCP_PIXEL_FORMAT_RGBA_F32 | 4:4:4       |     |     | 1       | rgba:16 |       |
+ * DCP_PIXEL_FORMAT_BGR48    | 4:4:4       |     |     | 1       | bgr:6  |        |
+ * DCP_PIXEL_FORMAT_RGB48    | 4:4:4       |     |     | 1       | rgb:6  |        |
+ * DCP_PIXEL_FORMAT_I444     | 4:4:4       |     |     | 3       | y:1    | u:1    | v:1
+ * DCP_PIXEL_FORMAT_I422     | 4:2:2       |  2  |     | 1, 3    | y:1    | u:1/2  | v:1/2
+ * DCP_PIXEL_FORMAT_I420     | 4:2:0       |  2  |  2  | 3       | y:1    | u:1/4  | v:1/4
+ * DCP_PIXEL_FORMAT_NV12     | 4:2:0       |  2  |  2  | 1, 2    | y:1    | uv:1/2 |
+ * DCP_PIXEL_FORMAT_P010     | 4:2:0       |  2  |  2  | 1, 2    | y:2    | uv:1   |
+ * DCP_PIXEL_FORMAT_YUV_F32_PLANAR | 4:4:4 |     |     | 1, 3    | y:4    | u:4    | v:4
  *
  * The values reported in columns `w` and `h`, when specified, indicate that the described
  * image should have width and height that are multiples of the specified values
@@ -401,6 +462,48 @@ typedef struct {
  */
 static const size_t DCP_STRIDE_AUTO = 0;
 
+/**
+ * DCP_MAX_DIMENSION:
+ * Largest width or height, in pixels, of an image. Every uint32_t dimension is accepted:
+ * offsets are computed with overflow checks and large images are converted in bands.
+ * Images are only rejected when the size of their buffers does not fit a size_t,
+ * which dcp_get_buffers_size() reports
+ */
+static const uint32_t DCP_MAX_DIMENSION = 4294967295u;
+
+/**
+ * DCP_MAX_LOGICAL_PLANES:
+ * Largest number of logical planes of a pixel format, and length of the array filled by
+ * function(dcp_describe_layout)
+ */
+#define DCP_MAX_LOGICAL_PLANES 3
+
+/**
+ * DcpPlaneLayoutDescriptor:
+ * @buffer: Index of the buffer holding the plane
+ * @width: Width of the plane in samples (pairs of samples for interleaved chroma)
+ * @height: Number of lines of the plane
+ * @bytes_per_sample: Bytes of each sample (pair of samples for interleaved chroma)
+ * @horizontal_subsampling: Number of pixels sharing a sample horizontally: 1, or 2 for subsampled chroma
+ * @vertical_subsampling: Number of pixels sharing a sample vertically: 1, or 2 for subsampled chroma
+ * @offset: Distance in bytes between the start of the buffer and the first line of the plane
+ * @stride: Distance in bytes between starts of consecutive lines
+ * @size: Bytes spanned by the plane, padding of the last line included
+ *
+ * Location and geometry of a logical plane of an image
+ */
+typedef struct {
+    uint32_t buffer;
+    uint32_t width;
+    uint32_t height;
+    uint32_t bytes_per_sample;
+    uint32_t horizontal_subsampling;
+    uint32_t vertical_subsampling;
+    size_t offset;
+    size_t stride;
+    size_t size;
+} DcpPlaneLayoutDescriptor;
+
 /**
  * dcp_initialize:
  * Automatically initializes the library functions that are most appropriate for
@@ -458,6 +561,55 @@ void                dcp_initialize              (void);
  */
 char *              dcp_describe_acceleration   (void);
 
+/**
+ * dcp_describe_conversion:
+ * @width: Width of the image to convert in pixels
+ * @height: Height of the image to convert in pixels
+ * @src_format: (not nullable) (in): Source image format
+ * @dst_format: (not nullable) (in): Destination image format
+ *
+ * Returns a description of the conversions used to convert an image from a format
+ * to another one: the pixel format and color space of the source image, of each
+ * intermediate image and of the destination image
+ *
+ * Returns: a null-terminated string that contains the description, or %NULL if the library was not
+ *          initialized before or the image can not be converted with function(dcp_convert_image).
+ *          String has to freed using function(dcp_unref_string)
+ *
+ * # Examples
+ *
+ * |[<!-- language="C" -->
+ * DcpImageFormat src_format = {
+ *     DCP_PIXEL_FORMAT_RGB,
+ *     DCP_COLOR_SPACE_LRGB,
+ *     1,
+ * };
+ *
+ * DcpImageFormat dst_format = {
+ *     DCP_PIXEL_FORMAT_NV12,
+ *     DCP_COLOR_SPACE_BT601,
+ *     2,
+ * };
+ *
+ * char *description = NULL;
+ *
+ * dcp_initialize();
+ * description = dcp_describe_conversion(640, 480, &src_format, &dst_format);
+ * if (description != NULL) {
+ *     printf("%s\n", description);
+ * } else {
+ *     printf("Unable to describe the conversion\n");
+ * }
+ * // => {route:[Rgb-Lrgb,Bgra-Lrgb,Nv12-Bt601]}
+ *
+ * dcp_unref_string(description);
+ * ]|
+ */
+char *              dcp_describe_conversion     (uint32_t              width,
+                                                 uint32_t              height,
+                                                 const DcpImageFormat *src_format,
+                                                 const DcpImageFormat *dst_format);
+
 /**
  * dcp_unref_string:
  * @string: A null-terminated string generated by the library. If the provided argument is %NULL,
@@ -501,6 +653,7 @@ void                dcp_unref_string            (char *string);
  *   that might by imposed by the image pixel format
  * - %DCP_ERROR_KIND_INVALID_VALUE if the image format has a number of planes which is not compatible
  *   with its pixel format
+ * - %DCP_ERROR_KIND_INVALID_VALUE if the size of one or more buffers does not fit in a size_t
 
  * # Undefined behaviour
  *
@@ -586,6 +739,8 @@ void                dcp_unref_string            (char *string);
  *
  * Default strides (e.g. the one you would set for tightly packed data) can be set
  * using the constant %DCP_STRIDE_AUTO
+ *
+ * If @width or @height is zero, all the buffer sizes are zero, whatever the strides.
  */
 DcpResult           dcp_get_buffers_size        (uint32_t              width,
                                                  uint32_t              height,
@@ -594,6 +749,75 @@ DcpResult           dcp_get_buffers_size        (uint32_t              width,
                                                  size_t               *buffers_size,
                                                  DcpErrorKind         *error);
 
+/**
+ * dcp_describe_layout:
+ * @width: Width of the image in pixels
+ * @height: Height of the image in pixels
+ * @format: (not nullable) (in): Image format
+ * @strides: (in): An array of distances in bytes between starts of consecutive lines in each image planes
+ * @planes: (not nullable) (out caller-allocates): An array of %DCP_MAX_LOGICAL_PLANES descriptors
+ *                                                 receiving the location of each logical plane
+ * @error: An optional reference to an #DcpErrorKind to receive the error description if the operation
+ *         does not complete successfully
+ *
+ * Describe where each logical plane of an image is stored, given its format, dimensions
+ * and optionally its strides. There is a descriptor for each logical plane of the pixel
+ * format (e.g. luma and interleaved chroma for %DCP_PIXEL_FORMAT_NV12), even when several
+ * planes share a buffer; the remaining descriptors are zeroed.
+ *
+ * The size of each buffer is the largest @offset + @size of the planes it holds.
+ *
+ * Returns: %DCP_RESULT_OK if the operation succeeded, else %DCP_RESULT_ERR
+ *
+ * # Errors
+ *
+ * - %DCP_ERROR_KIND_INVALID_VALUE if @format or @planes is %NULL
+ * - %DCP_ERROR_KIND_INVALID_VALUE if the image pixel format is not a #DcpPixelFormat
+ * - %DCP_ERROR_KIND_INVALID_VALUE if @width or @height violate the [size constraints][size-constraint]
+ *   that might by imposed by the image pixel format
+ * - %DCP_ERROR_KIND_INVALID_VALUE if the image format has a number of planes which is not compatible
+ *   with its pixel format
+ * - %DCP_ERROR_KIND_NOT_ENOUGH_DATA if one or more strides are smaller than the line size, or
+ *   the size of a buffer does not fit in a size_t
+ *
+ * # Undefined behaviour
+ *
+ * - @strides is not %NULL and its length is less than the image format number of planes
+ * - @planes length is less than %DCP_MAX_LOGICAL_PLANES
+ *
+ * # Examples
+ *
+ * Allocate a single buffer for an nv12 image whose lines are padded to 64 bytes:
+ *
+ * |[<!-- language="C" -->
+ * static const uint32_t WIDTH = 1366;
+ * static const uint32_t HEIGHT = 768;
+ * static const size_t STRIDES[1] = { 1408, };
+ *
+ * DcpImageFormat format = {
+ *     DCP_PIXEL_FORMAT_NV12,
+ *     DCP_COLOR_SPACE_BT601,
+ *     1,
+ * };
+ *
+ * DcpPlaneLayoutDescriptor planes[DCP_MAX_LOGICAL_PLANES];
+ * uint8_t *buffer;
+ *
+ * if (dcp_describe_layout(WIDTH, HEIGHT, &format, STRIDES, planes, NULL) != DCP_RESULT_OK) {
+ *     return;
+ * }
+ *
+ * // Chroma is stored right after luma
+ * buffer = malloc(planes[1].offset + planes[1].size);
+ * ]|
+ */
+DcpResult           dcp_describe_layout         (uint32_t                  width,
+                                                 uint32_t                  height,
+                                                 const DcpImageFormat     *format,
+                                                 const size_t             *strides,
+                                                 DcpPlaneLayoutDescriptor *planes,
+                                                 DcpErrorKind             *error);
+
 /**
  * dcp_convert_image:
  * @width: Width of the image to convert in pixels
@@ -613,6 +837,10 @@ DcpResult           dcp_get_buffers_size        (uint32_t              width,
  * Converts from a color space to another one, applying downsampling/upsampling
  * to match destination image format
  *
+ * @src_strides and @dst_strides hold a stride for each plane of the image format
+ * (#DcpImageFormat.num_planes). Images stored in a single buffer use its stride for all
+ * their planes.
+ *
  * Returns: %DCP_RESULT_OK if the operation succeeded, else %DCP_RESULT_ERR
  *
  * # Errors
@@ -627,18 +855,39 @@ DcpResult           dcp_get_buffers_size        (uint32_t              width,
  * - %DCP_ERROR_KIND_INVALID_VALUE if source or destination image formats have a number of planes
  *   which is not compatible with their pixel formats
  * - %DCP_ERROR_KIND_INVALID_OPERATION if there is no available method to convert the image with the
- *   source pixel format to the image with the destination pixel format.
+ *   source pixel format to the image with the destination pixel format, neither directly
+ *   nor through intermediate images (see [Routed conversions][routed])
  *
- *   The list of available conversions is specified here:
+ *   The list of available direct conversions is specified here:
  *
  *   Source image pixel format         | Supported destination image pixel formats
  *   ----------------------------------|------------------------------------------
  *   DCP_PIXEL_FORMAT_ARGB             | DCP_PIXEL_FORMAT_NV12 [1][algo-1]
+ *   DCP_PIXEL_FORMAT_ARGB             | DCP_PIXEL_FORMAT_YUV_F32_PLANAR [12][algo-12]
  *   DCP_PIXEL_FORMAT_BGR              | DCP_PIXEL_FORMAT_NV12 [1][algo-1]
+ *   DCP_PIXEL_FORMAT_BGR              | DCP_PIXEL_FORMAT_YUV_F32_PLANAR [12][algo-12]
+ *   DCP_PIXEL_FORMAT_BGR48            | DCP_PIXEL_FORMAT_BGRA [11][algo-11]
+ *   DCP_PIXEL_FORMAT_BGR48            | DCP_PIXEL_FORMAT_P010 [10][algo-10]
  *   DCP_PIXEL_FORMAT_BGRA             | DCP_PIXEL_FORMAT_NV12 [1][algo-1]
+ *   DCP_PIXEL_FORMAT_BGRA             | DCP_PIXEL_FORMAT_YUV_F32_PLANAR [12][algo-12]
  *   DCP_PIXEL_FORMAT_I420             | DCP_PIXEL_FORMAT_BGRA [2][algo-2]
+ *   DCP_PIXEL_FORMAT_I420             | DCP_PIXEL_FORMAT_I420 [7][algo-7]
+ *   DCP_PIXEL_FORMAT_I420             | DCP_PIXEL_FORMAT_RGB_F32 [8][algo-8]
+ *   DCP_PIXEL_FORMAT_I420             | DCP_PIXEL_FORMAT_RGBA_F32 [8][algo-8]
+ *   DCP_PIXEL_FORMAT_I444             | DCP_PIXEL_FORMAT_I444 [7][algo-7]
+ *   DCP_PIXEL_FORMAT_I444             | DCP_PIXEL_FORMAT_RGB_F32 [8][algo-8]
+ *   DCP_PIXEL_FORMAT_I444             | DCP_PIXEL_FORMAT_RGBA_F32 [8][algo-8]
  *   DCP_PIXEL_FORMAT_NV12             | DCP_PIXEL_FORMAT_BGRA [2][algo-2]
+ *   DCP_PIXEL_FORMAT_NV12             | DCP_PIXEL_FORMAT_NV12 [7][algo-7]
+ *   DCP_PIXEL_FORMAT_NV12             | DCP_PIXEL_FORMAT_P010 [9][algo-9]
+ *   DCP_PIXEL_FORMAT_NV12             | DCP_PIXEL_FORMAT_RGB_F32 [8][algo-8]
+ *   DCP_PIXEL_FORMAT_NV12             | DCP_PIXEL_FORMAT_RGBA_F32 [8][algo-8]
+ *   DCP_PIXEL_FORMAT_P010             | DCP_PIXEL_FORMAT_NV12 [9][algo-9]
+ *   DCP_PIXEL_FORMAT_P010             | DCP_PIXEL_FORMAT_RGBA_F16 [5][algo-5]
  *   DCP_PIXEL_FORMAT_RGB              | DCP_PIXEL_FORMAT_BGRA [3][algo-3]
+ *   DCP_PIXEL_FORMAT_RGB48            | DCP_PIXEL_FORMAT_BGRA [11][algo-11]
+ *   DCP_PIXEL_FORMAT_RGB48            | DCP_PIXEL_FORMAT_P010 [10][algo-10]
+ *   DCP_PIXEL_FORMAT_RGBA_F16         | DCP_PIXEL_FORMAT_BGRA [6][algo-6]
  *
  * # Undefined behaviour
  *
@@ -651,6 +900,27 @@ DcpResult           dcp_get_buffers_size        (uint32_t              width,
  *   and strides (if they are not %NULL).
  *   You can compute the buffers' size using function(dcp_get_buffers_size)
  *
+ * # Empty images # {#empty}
+ *
+ * Images with zero width or height are valid. Once the image formats have been validated,
+ * the function returns successfully without accessing the buffers, which can be empty.
+ *
+ * # Routed conversions # {#routed}
+ *
+ * When there is no direct conversion between the source and the destination image formats,
+ * the image is converted through one or more intermediate images, stored in scratch buffers
+ * allocated by the library for the duration of the call. For example, an image with
+ * pixel format %DCP_PIXEL_FORMAT_RGB is converted to %DCP_PIXEL_FORMAT_NV12 going through
+ * %DCP_PIXEL_FORMAT_BGRA. The image is converted in horizontal bands, so scratch buffers
+ * only hold a few lines and their size does not depend on the image height.
+ *
+ * The route with fewer conversions is chosen. Among routes with the same length, the ones
+ * whose intermediate images do not have chroma subsampling are preferred.
+ * Images with the same pixel format and color space are never routed.
+ *
+ * Use function(dcp_describe_conversion) to know which route is taken for a given pair
+ * of image formats.
+ *
  * # Algorithm 1 # {#algo-1}
  *
  * Conversion from linear RGB model to YCbCr color model, with 4:2:0 downsampling
@@ -671,6 +941,18 @@ DcpResult           dcp_get_buffers_size        (uint32_t              width,
  * cr =  0.511 * r - 0.464 * g - 0.047 * b + 128
  * ]|
  *
+ * If the destination image color space is Jfif, full range samples are computed exactly
+ * as libjpeg does, with the following formula:
+ *
+ * |[
+ * y  =  0.299 * r + 0.587 * g + 0.114 * b
+ * cb = -0.169 * r - 0.331 * g + 0.500 * b + 128
+ * cr =  0.500 * r - 0.419 * g - 0.081 * b + 128
+ * ]|
+ *
+ * Chroma ties are rounded down. With 4:2:0 downsampling, chroma is computed for each pixel,
+ * then averaged over each 2x2 block, rounding ties alternately down and up along the line.
+ *
  * # Algorithm 2 # {#algo-2}
  *
  * Conversion from YCbCr model to linear RGB model, with 4:4:4 upsampling
@@ -693,9 +975,156 @@ DcpResult           dcp_get_buffers_size        (uint32_t              width,
  * b = 1.164 * (y - 16) + 2.115 * (cb - 128)
  * ]|
  *
+ * If the source image color space is Jfif, full range samples are converted exactly
+ * as libjpeg does without fancy upsampling, with the following formula:
+ *
+ * |[
+ * r = y + 1.402 * (cr - 128)
+ * g = y - 0.714 * (cr - 128) - 0.344 * (cb - 128)
+ * b = y + 1.772 * (cb - 128)
+ * ]|
+ *
  * # Algorithm 3 # {#algo-3}
  *
  * Conversion from RGB to BGRA
+ *
+ * # Algorithm 5 # {#algo-5}
+ *
+ * Conversion from YCbCr model (BT.2020, PQ) to linear scRGB half float, with 4:4:4 upsampling
+ *
+ * Samples are normalized from the 10-bit limited range, then the following formula is applied:
+ *
+ * |[
+ * r' = y + 1.4746 * cr
+ * g' = y - 0.1646 * cb - 0.5714 * cr
+ * b' = y + 1.8814 * cb
+ * ]|
+ *
+ * Each component is linearized with the SMPTE ST 2084 EOTF, scaled so that 1.0 is 80 nits,
+ * and moved from BT.2020 to BT.709 primaries. Alpha is set to 1.0
+ *
+ * # Algorithm 6 # {#algo-6}
+ *
+ * Conversion from linear scRGB half float to 8-bit sRGB, with tone mapping
+ *
+ * Negative components are clipped. If the largest component exceeds 0.75, all components
+ * are scaled so that the largest one is compressed into [0.75, 1]:
+ *
+ * |[
+ * t = (max - 0.75) / 0.25
+ * max' = 0.75 + 0.25 * t / (1 + t)
+ * ]|
+ *
+ * The sRGB transfer function is then applied. Alpha is clamped to [0, 1] and preserved
+ *
+ * # Algorithm 7 # {#algo-7}
+ *
+ * Conversion between YCbCr color spaces, without going through RGB
+ *
+ * Only Bt601 and Bt709 are supported. Chroma is converted as it is, luma is corrected
+ * using the chroma sample that covers it. From Bt601 to Bt709:
+ *
+ * |[
+ * y'  = y - 0.116 * (cb - 128) - 0.208 * (cr - 128)
+ * cb' = 1.019 * (cb - 128) + 0.115 * (cr - 128) + 128
+ * cr' = 0.075 * (cb - 128) + 1.025 * (cr - 128) + 128
+ * ]|
+ *
+ * From Bt709 to Bt601:
+ *
+ * |[
+ * y'  = y + 0.099 * (cb - 128) + 0.192 * (cr - 128)
+ * cb' = 0.990 * (cb - 128) - 0.111 * (cr - 128) + 128
+ * cr' = -0.072 * (cb - 128) + 0.983 * (cr - 128) + 128
+ * ]|
+ *
+ * # Algorithm 8 # {#algo-8}
+ *
+ * Conversion from YCbCr model to normalized floating point RGB, with 4:4:4 upsampling
+ *
+ * Samples are normalized from the 8-bit limited range, then the following formula is applied:
+ *
+ * |[
+ * y' = (y - 16) / 219
+ * cb' = (cb - 128) / 224
+ * cr' = (cr - 128) / 224
+ * ]|
+ *
+ * If the source image color space is DCP_COLOR_SPACE_BT601:
+ *
+ * |[
+ * r = y' + 1.402 * cr'
+ * g = y' - 0.344 * cb' - 0.714 * cr'
+ * b = y' + 1.772 * cb'
+ * ]|
+ *
+ * If the source image color space is DCP_COLOR_SPACE_BT709:
+ *
+ * |[
+ * r = y' + 1.575 * cr'
+ * g = y' - 0.187 * cb' - 0.468 * cr'
+ * b = y' + 1.856 * cb'
+ * ]|
+ *
+ * Components are clipped to [0, 1]. If the destination image contains an alpha channel,
+ * it is set to 1.0
+ *
+ * # Algorithm 9 # {#algo-9}
+ *
+ * Change of bit depth between 8-bit and 10-bit YCbCr, with the same color space
+ *
+ * Samples are rescaled without any color transformation:
+ *
+ * |[
+ * p010 = nv12 * 4
+ * nv12 = min((p010 + 2) / 4, 255)
+ * ]|
+ *
+ * P010 samples are stored in the upper 10 bits of each 16-bit word.
+ *
+ * # Algorithm 10 # {#algo-10}
+ *
+ * Conversion from 16-bit linear RGB model to 10-bit YCbCr color model, with 4:2:0 downsampling
+ *
+ * Only BT.601 and BT.709 are supported. Components are normalized to [0, 1] dividing them
+ * by 65535, then the following formula is applied:
+ *
+ * |[
+ * y' = kr * r + (1 - kr - kb) * g + kb * b
+ * y  = 876 * y' + 64
+ * cb = 896 * (b - y') / (2 - 2 * kb) + 512
+ * cr = 896 * (r - y') / (2 - 2 * kr) + 512
+ * ]|
+ *
+ * where kr = 0.299, kb = 0.114 for BT.601 and kr = 0.2126, kb = 0.0722 for BT.709.
+ *
+ * Chroma is computed from the average of each 2x2 block. Samples are rounded to nearest
+ * and stored in the upper 10 bits of each 16-bit word.
+ *
+ * # Algorithm 11 # {#algo-11}
+ *
+ * Conversion from 16-bit RGB to 8-bit BGRA
+ *
+ * Components are rescaled and rounded to nearest, alpha is set to 255:
+ *
+ * |[
+ * bgra = (rgb48 * 255 + 32767) / 65535
+ * ]|
+ *
+ * # Algorithm 12 # {#algo-12}
+ *
+ * Conversion from linear RGB model to planar single precision YCbCr, with 4:4:4 sampling
+ *
+ * Only BT.601 and BT.709 are supported. Components are normalized to [0, 1] dividing them
+ * by 255, then the following formula is applied:
+ *
+ * |[
+ * y  = kr * r + (1 - kr - kb) * g + kb * b
+ * cb = (b - y) / (2 - 2 * kb)
+ * cr = (r - y) / (2 - 2 * kr)
+ * ]|
+ *
+ * where kr = 0.299, kb = 0.114 for BT.601 and kr = 0.2126, kb = 0.0722 for BT.709.
  */
 DcpResult           dcp_convert_image           (uint32_t               width,
                                                  uint32_t               height,
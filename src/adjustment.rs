@@ -0,0 +1,242 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Color adjustment of YCbCr images while they are converted to RGB.
+//!
+//! Video players usually let the user change brightness, contrast, saturation and hue
+//! of the displayed frames (procamp controls). The adjustment is combined with the
+//! YCbCr to RGB matrix, so that it is applied in the same pass as the conversion.
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::adjustment::{convert_adjusted_image, ColorAdjustment};
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn convert() -> Result<(), Box<dyn error::Error>> {
+//!     dcp::initialize();
+//!
+//!     const WIDTH: u32 = 640;
+//!     const HEIGHT: u32 = 480;
+//!
+//!     let src_format = ImageFormat {
+//!         pixel_format: PixelFormat::Nv12,
+//!         color_space: ColorSpace::Bt709,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let dst_format = ImageFormat {
+//!         pixel_format: PixelFormat::Bgra,
+//!         color_space: ColorSpace::Lrgb,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let src_buffer = vec![0u8; 3 * (WIDTH as usize) * (HEIGHT as usize) / 2];
+//!     let mut dst_buffer = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+//!
+//!     let adjustment = ColorAdjustment {
+//!         brightness: 0.1,
+//!         saturation: 1.2,
+//!         ..Default::default()
+//!     };
+//!
+//!     convert_adjusted_image(
+//!         WIDTH,
+//!         HEIGHT,
+//!         &src_format,
+//!         None,
+//!         &[&src_buffer],
+//!         &dst_format,
+//!         None,
+//!         &mut [&mut dst_buffer],
+//!         &adjustment,
+//!     )?;
+//!
+//!     Ok(())
+//! }
+//! ```
+use crate::color_space::ColorSpace;
+use crate::convert_image::common::{YCBCR_TO_RGB_601, YCBCR_TO_RGB_709};
+use crate::convert_image::x86::{self, RgbOutput, YuvLayout};
+use crate::pixel_format::{self, PixelFormat};
+use crate::trace_span;
+use crate::{ErrorKind, ImageFormat};
+
+/// Brightness, contrast, saturation and hue of an image
+///
+/// The default value leaves the image unchanged.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ColorAdjustment {
+    /// Offset added to the luma, normalized so that 1.0 is the whole luma range.
+    /// Must be in [-1, 1]
+    pub brightness: f32,
+    /// Gain of the luma. Must not be negative
+    pub contrast: f32,
+    /// Gain of the chroma. Must not be negative, 0.0 gives a grayscale image
+    pub saturation: f32,
+    /// Rotation of the chroma, in degrees
+    pub hue: f32,
+}
+
+impl Default for ColorAdjustment {
+    fn default() -> Self {
+        ColorAdjustment {
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+            hue: 0.0,
+        }
+    }
+}
+
+impl ColorAdjustment {
+    fn is_valid(&self) -> bool {
+        self.brightness >= -1.0
+            && self.brightness <= 1.0
+            && self.contrast >= 0.0
+            && self.contrast.is_finite()
+            && self.saturation >= 0.0
+            && self.saturation.is_finite()
+            && self.hue.is_finite()
+    }
+
+    /// Combines the adjustment with the matrix converting normalized ycbcr to rgb
+    fn get_matrix(&self, coefficients: &[f32; 4]) -> [[f32; 4]; 3] {
+        let (sin, cos) = self.hue.to_radians().sin_cos();
+        let (sin, cos) = (self.saturation * sin, self.saturation * cos);
+
+        // cb' = cos * cb - sin * cr, cr' = sin * cb + cos * cr
+        let mut matrix = x86::get_ycbcr_to_rgb_matrix(coefficients);
+        for row in matrix.iter_mut() {
+            let [l, cb, cr, _] = *row;
+            *row = [
+                self.contrast * l,
+                cos * cb + sin * cr,
+                cos * cr - sin * cb,
+                self.brightness * l,
+            ];
+        }
+
+        matrix
+    }
+}
+
+/// Converts an image from YCbCr to RGB like [`convert_image`], adjusting its colors
+///
+/// The luma is scaled by the contrast and offset by the brightness, while the chroma is
+/// rotated by the hue and scaled by the saturation. The result is then converted to RGB.
+///
+/// The following conversions are supported, both for Bt601 and Bt709 source images:
+///
+///   Source image pixel format     | Supported destination image pixel formats
+///   ------------------------------|------------------------------------------
+///   PixelFormat::I420             | PixelFormat::Bgra, PixelFormat::RgbF32, PixelFormat::RgbaF32
+///   PixelFormat::I444             | PixelFormat::Bgra, PixelFormat::RgbF32, PixelFormat::RgbaF32
+///   PixelFormat::Nv12             | PixelFormat::Bgra, PixelFormat::RgbF32, PixelFormat::RgbaF32
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+/// * `adjustment` - The color adjustment to apply
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the adjustment is out of range
+///
+/// * [`InvalidOperation`] if the conversion is not listed above
+///
+/// * All the errors reported by [`convert_image`], except that conversions are never routed
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`convert_image`]: ../fn.convert_image.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_adjusted_image(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    adjustment: &ColorAdjustment,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_adjusted_image",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    let (_, last_src_plane, _) = crate::get_converter(width, height, src_format, dst_format)?;
+
+    let layout = match src_format.pixel_format {
+        PixelFormat::I444 => YuvLayout::I444,
+        PixelFormat::I420 => YuvLayout::I420,
+        PixelFormat::Nv12 => YuvLayout::Nv12,
+        _ => return Err(ErrorKind::InvalidOperation),
+    };
+
+    let coefficients = match src_format.color_space {
+        ColorSpace::Bt601 => &YCBCR_TO_RGB_601,
+        ColorSpace::Bt709 => &YCBCR_TO_RGB_709,
+        _ => return Err(ErrorKind::InvalidOperation),
+    };
+
+    let output = match dst_format.pixel_format {
+        PixelFormat::Bgra => RgbOutput::Bgra,
+        PixelFormat::RgbF32 => RgbOutput::RgbF32,
+        PixelFormat::RgbaF32 => RgbOutput::RgbaF32,
+        _ => return Err(ErrorKind::InvalidOperation),
+    };
+
+    if !adjustment.is_valid() {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    if crate::is_empty(width, height) {
+        return Ok(());
+    }
+
+    if x86::yuv_to_lrgb_matrix(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+        src_buffers,
+        dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+        dst_buffers,
+        layout,
+        &adjustment.get_matrix(coefficients),
+        output,
+    ) {
+        Ok(())
+    } else {
+        Err(ErrorKind::NotEnoughData)
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Splitting of images into bands for multi-threaded conversion.
+//!
+//! Applications converting large frames on several threads usually split them into
+//! horizontal bands, and convert each band on its own thread. Naive even splits do not
+//! work well: with 4:2:0 formats a band boundary can fall in the middle of a chroma row,
+//! and the boundary lines of adjacent bands can share a cache line, causing false sharing
+//! between the threads writing them.
+//!
+//! [`plan_bands`] picks the band height from the number of threads and from the size of
+//! the image, so that each band fits comfortably in a per-core cache, and places the band
+//! boundaries on chroma rows and, whenever the strides allow it, on cache lines.
+//!
+//! [`plan_bands`]: ./fn.plan_bands.html
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::bands::plan_bands;
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn plan() -> Result<(), Box<dyn error::Error>> {
+//!     const WIDTH: u32 = 1920;
+//!     const HEIGHT: u32 = 1080;
+//!
+//!     let format = ImageFormat {
+//!         pixel_format: PixelFormat::I420,
+//!         color_space: ColorSpace::Bt601,
+//!         num_planes: 3,
+//!     };
+//!
+//!     let bands = plan_bands(WIDTH, HEIGHT, &format, None, 8, None)?;
+//!     for band in bands {
+//!         // Convert lines band.y..band.y + band.height on a worker thread
+//!         assert_eq!(band.y % 2, 0);
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+use crate::pixel_format;
+use crate::regions::Rect;
+use crate::trace_span;
+use crate::{ErrorKind, ImageFormat};
+
+/// Size in bytes of a cache line
+const CACHE_LINE_SIZE: usize = 64;
+
+/// Bytes of the image a band should span at most, sized after a per-core L2 cache
+const BAND_CACHE_BUDGET: usize = 256 * 1024;
+
+/// Splits an image into horizontal bands that can be converted independently
+///
+/// The bands span the whole image width, are listed top to bottom and cover the whole
+/// image. Each band starts on a chroma row. When possible, each band also starts on a
+/// cache line in every plane, relative to the start of the plane, so that threads writing
+/// adjacent bands never share a cache line.
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `format` - Image format
+/// * `strides` - An array of distances in bytes between starts of consecutive lines
+///   in each image planes
+/// * `threads` - Number of threads that will convert the bands, usually the number of
+///   cores of the machine
+/// * `band_height` - If not `None`, the height of the bands in pixels, which overrides the
+///   automatic choice. The last band can be shorter.
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if `threads` is zero
+///
+/// * [`InvalidValue`] if `width` or `height` violate the [`size constraints`] that might by
+///   imposed by the image pixel format
+///
+/// * [`InvalidValue`] if the image format has a number of planes which is not compatible
+///   with its pixel format
+///
+/// * [`InvalidValue`] if `band_height` is zero, or is not a multiple of the chroma
+///   subsampling of the image pixel format
+///
+/// * [`NotEnoughData`] if the strides array is not `None` and its length is less than
+///   the image format number of planes
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+/// [`size constraints`]: ../struct.ImageFormat.html#note
+pub fn plan_bands(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+    threads: usize,
+    band_height: Option<u32>,
+) -> Result<Vec<Rect>, ErrorKind> {
+    let _span = trace_span!(DEBUG, "plan_bands", width, height, threads);
+
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if threads == 0
+        || !pixel_format::is_compatible(format.pixel_format as u32, width, height, last_plane)
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let layouts = crate::get_plane_layouts(
+        format,
+        width,
+        height,
+        last_plane,
+        strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+    )?;
+
+    let max_shift = layouts
+        .iter()
+        .map(|layout| layout.vertical_shift)
+        .max()
+        .unwrap_or(0);
+    let chroma_rows = 1usize << max_shift;
+
+    // Bytes spanned by a number of image lines, in each plane
+    let plane_sizes = |lines: usize| {
+        layouts
+            .iter()
+            .map(move |layout| (lines >> layout.vertical_shift) * layout.stride)
+    };
+
+    let band_height = match band_height {
+        Some(band_height) => {
+            let band_height = band_height as usize;
+            if band_height == 0 || !band_height.is_multiple_of(chroma_rows) {
+                return Err(ErrorKind::InvalidValue);
+            }
+
+            band_height
+        }
+        None => {
+            // Smallest number of lines starting a chroma row and a cache line in every plane
+            let step = (1..=CACHE_LINE_SIZE)
+                .map(|i| i * chroma_rows)
+                .find(|&lines| plane_sizes(lines).all(|size| size % CACHE_LINE_SIZE == 0))
+                .unwrap_or(chroma_rows);
+
+            let steps = (height as usize).div_ceil(step);
+            let steps_per_thread = steps.div_ceil(threads);
+            let step_size: usize = plane_sizes(step).sum();
+            let cached_steps = BAND_CACHE_BUDGET / step_size.max(1);
+            step * steps_per_thread.min(cached_steps).max(1)
+        }
+    };
+
+    let height = height as usize;
+    Ok((0..height)
+        .step_by(band_height)
+        .map(|y| Rect {
+            x: 0,
+            y: y as u32,
+            width,
+            height: band_height.min(height - y) as u32,
+        })
+        .collect())
+}
@@ -0,0 +1,155 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Conversion of batches of images.
+//!
+//! A batch is converted image by image, and a conversion that fails does not stop the
+//! following ones: [`convert_images`] returns the outcome of each conversion, so that
+//! callers can skip the corrupt frames of a stream and keep the others.
+//!
+//! Each conversion can carry the [`FrameMeta`] of its frame, which is left untouched, so
+//! that the converted images are still paired with their timestamps and other metadata.
+//!
+//! [`convert_images`]: ./fn.convert_images.html
+//! [`FrameMeta`]: ../meta/struct.FrameMeta.html
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::batch::{convert_images, Conversion};
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//!
+//! fn thumbnails() {
+//!     dcp::initialize();
+//!
+//!     const WIDTH: u32 = 160;
+//!     const HEIGHT: u32 = 120;
+//!
+//!     let src_format = ImageFormat {
+//!         pixel_format: PixelFormat::Bgra,
+//!         color_space: ColorSpace::Lrgb,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let dst_format = ImageFormat {
+//!         pixel_format: PixelFormat::Nv12,
+//!         color_space: ColorSpace::Bt601,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let frame = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+//!     let truncated_frame = vec![0u8; 1024];
+//!     let mut first = vec![0u8; 3 * (WIDTH as usize) * (HEIGHT as usize) / 2];
+//!     let mut second = vec![0u8; 3 * (WIDTH as usize) * (HEIGHT as usize) / 2];
+//!
+//!     let first_src = [&frame[..]];
+//!     let second_src = [&truncated_frame[..]];
+//!     let mut first_dst = [&mut first[..]];
+//!     let mut second_dst = [&mut second[..]];
+//!
+//!     let mut batch = [
+//!         Conversion {
+//!             width: WIDTH,
+//!             height: HEIGHT,
+//!             src_format: &src_format,
+//!             src_strides: None,
+//!             src_buffers: &first_src,
+//!             dst_format: &dst_format,
+//!             dst_strides: None,
+//!             dst_buffers: &mut first_dst,
+//!             meta: None,
+//!         },
+//!         Conversion {
+//!             width: WIDTH,
+//!             height: HEIGHT,
+//!             src_format: &src_format,
+//!             src_strides: None,
+//!             src_buffers: &second_src,
+//!             dst_format: &dst_format,
+//!             dst_strides: None,
+//!             dst_buffers: &mut second_dst,
+//!             meta: None,
+//!         },
+//!     ];
+//!
+//!     let results = convert_images(&mut batch);
+//!     assert!(results[0].is_ok());
+//!     assert!(results[1].is_err());
+//! }
+//! ```
+use crate::meta::FrameMeta;
+use crate::trace_span;
+use crate::{ErrorKind, ImageFormat};
+
+/// The parameters of a conversion of a batch, as given to [`convert_image`]
+///
+/// [`convert_image`]: ../fn.convert_image.html
+#[derive(Debug)]
+pub struct Conversion<'a, 'b> {
+    /// Width of the image to convert in pixels
+    pub width: u32,
+    /// Height of the image to convert in pixels
+    pub height: u32,
+    /// Source image format
+    pub src_format: &'a ImageFormat,
+    /// An array of distances in bytes between starts of consecutive lines
+    /// in each source image planes
+    pub src_strides: Option<&'a [usize]>,
+    /// An array of image buffers in each source color plane
+    pub src_buffers: &'a [&'a [u8]],
+    /// Destination image format
+    pub dst_format: &'a ImageFormat,
+    /// An array of distances in bytes between starts of consecutive lines
+    /// in each destination image planes
+    pub dst_strides: Option<&'a [usize]>,
+    /// An array of image buffers in each destination color plane
+    pub dst_buffers: &'a mut [&'b mut [u8]],
+    /// Metadata of the frame, carried unchanged through the conversion
+    pub meta: Option<&'a FrameMeta>,
+}
+
+/// Converts a batch of images, reporting the outcome of each conversion
+///
+/// Conversions are run in order. When a conversion fails, its destination buffers may
+/// have been partially written, and the following conversions are still run.
+///
+/// # Arguments
+/// * `conversions` - The conversions of the batch
+///
+/// # Errors
+/// The result of each conversion holds the errors reported by [`convert_image`] for its
+/// parameters. An empty batch has no results.
+///
+/// [`convert_image`]: ../fn.convert_image.html
+pub fn convert_images(conversions: &mut [Conversion]) -> Vec<Result<(), ErrorKind>> {
+    let _span = trace_span!(DEBUG, "convert_images", count = conversions.len());
+
+    conversions
+        .iter_mut()
+        .map(|conversion| {
+            crate::convert_image(
+                conversion.width,
+                conversion.height,
+                conversion.src_format,
+                conversion.src_strides,
+                conversion.src_buffers,
+                conversion.dst_format,
+                conversion.dst_strides,
+                conversion.dst_buffers,
+            )
+        })
+        .collect()
+}
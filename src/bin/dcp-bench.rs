@@ -0,0 +1,288 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Measures the throughput of every conversion supported on the host machine.
+//!
+//! Usage: `dcp-bench [WIDTHxHEIGHT] [MIN_MILLISECONDS]`
+//!
+//! The report is a json document printed on the standard output: it describes the host
+//! processor and, for each pair of image formats converted by a single kernel, the number
+//! of bytes (source plus destination) processed per second. Attach it when filing
+//! performance issues.
+use dcp::{convert_image, get_buffers_size, ColorSpace, ErrorKind, ImageFormat, PixelFormat};
+use dcv_color_primitives as dcp;
+use std::env;
+use std::process;
+use std::time::{Duration, Instant};
+
+const PIXEL_FORMATS: &[PixelFormat] = &[
+    PixelFormat::Argb,
+    PixelFormat::Bgra,
+    PixelFormat::Bgr,
+    PixelFormat::Rgba,
+    PixelFormat::Rgb,
+    PixelFormat::RgbaF16,
+    PixelFormat::RgbF32,
+    PixelFormat::RgbaF32,
+    PixelFormat::Bgr48,
+    PixelFormat::Rgb48,
+    PixelFormat::I444,
+    PixelFormat::I422,
+    PixelFormat::I420,
+    PixelFormat::Nv12,
+    PixelFormat::P010,
+    PixelFormat::YuvF32Planar,
+];
+
+const COLOR_SPACES: &[ColorSpace] = &[
+    ColorSpace::Lrgb,
+    ColorSpace::Bt601,
+    ColorSpace::Bt709,
+    ColorSpace::Bt2020Pq,
+    ColorSpace::Jfif,
+];
+
+const DEFAULT_WIDTH: u32 = 1920;
+const DEFAULT_HEIGHT: u32 = 1080;
+const DEFAULT_MIN_MILLISECONDS: u64 = 200;
+const MIN_RUNS: u32 = 4;
+
+/// Byte used to fill the source images: it is a valid sample for every pixel format and
+/// yields normal (not denormal) floats, so that float kernels run at their usual speed
+const FILL_BYTE: u8 = 0x3C;
+
+struct Measure {
+    src_format: ImageFormat,
+    dst_format: ImageFormat,
+    bytes: usize,
+    runs: u32,
+    elapsed: Duration,
+}
+
+fn usage() -> ! {
+    eprintln!("usage: dcp-bench [WIDTHxHEIGHT] [MIN_MILLISECONDS]");
+    process::exit(2);
+}
+
+fn parse_args() -> (u32, u32, Duration) {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() > 2 {
+        usage();
+    }
+
+    let (width, height) = match args.first() {
+        Some(size) => {
+            let mut parts = size.splitn(2, 'x').map(str::parse::<u32>);
+            match (parts.next(), parts.next()) {
+                (Some(Ok(width)), Some(Ok(height))) if width > 0 && height > 0 => (width, height),
+                _ => usage(),
+            }
+        }
+        None => (DEFAULT_WIDTH, DEFAULT_HEIGHT),
+    };
+
+    let min_milliseconds = match args.get(1).map(|value| value.parse::<u64>()) {
+        Some(Ok(value)) => value,
+        Some(Err(_)) => usage(),
+        None => DEFAULT_MIN_MILLISECONDS,
+    };
+
+    (width, height, Duration::from_millis(min_milliseconds))
+}
+
+/// Returns the image format with the largest number of planes accepted for the
+/// given pixel format and color space, with the plane sizes
+fn planar_format(
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+    color_space: ColorSpace,
+) -> Option<(ImageFormat, Vec<usize>)> {
+    (1..=3).rev().find_map(|num_planes| {
+        let format = ImageFormat {
+            pixel_format,
+            color_space,
+            num_planes,
+        };
+
+        let mut sizes = vec![0; num_planes as usize];
+        match get_buffers_size(width, height, &format, None, &mut sizes) {
+            Ok(_) => Some((format, sizes)),
+            Err(_) => None,
+        }
+    })
+}
+
+fn copy(format: &ImageFormat) -> ImageFormat {
+    ImageFormat {
+        pixel_format: format.pixel_format,
+        color_space: format.color_space,
+        num_planes: format.num_planes,
+    }
+}
+
+fn is_direct(src_format: &ImageFormat, dst_format: &ImageFormat) -> bool {
+    match dcp::negotiate(&[copy(src_format)], &[copy(dst_format)]) {
+        Some((_, _, score)) => score.conversions == 1,
+        None => false,
+    }
+}
+
+fn measure(
+    width: u32,
+    height: u32,
+    src: &(ImageFormat, Vec<usize>),
+    dst: &(ImageFormat, Vec<usize>),
+    min_elapsed: Duration,
+) -> Result<Measure, ErrorKind> {
+    let (src_format, src_sizes) = src;
+    let (dst_format, dst_sizes) = dst;
+
+    let src_buffers: Vec<Vec<u8>> = src_sizes
+        .iter()
+        .map(|&size| vec![FILL_BYTE; size])
+        .collect();
+    let mut dst_buffers: Vec<Vec<u8>> = dst_sizes.iter().map(|&size| vec![0; size]).collect();
+
+    let src_slices: Vec<&[u8]> = src_buffers.iter().map(|buffer| &buffer[..]).collect();
+    let mut dst_slices: Vec<&mut [u8]> = dst_buffers
+        .iter_mut()
+        .map(|buffer| &mut buffer[..])
+        .collect();
+
+    let mut convert = || {
+        convert_image(
+            width,
+            height,
+            src_format,
+            None,
+            &src_slices,
+            dst_format,
+            None,
+            &mut dst_slices,
+        )
+    };
+
+    // Warm up caches and page in the destination buffers
+    convert()?;
+
+    let mut runs = 0;
+    let start = Instant::now();
+    let elapsed = loop {
+        convert()?;
+        runs += 1;
+
+        let elapsed = start.elapsed();
+        if runs >= MIN_RUNS && elapsed >= min_elapsed {
+            break elapsed;
+        }
+    };
+
+    Ok(Measure {
+        src_format: copy(src_format),
+        dst_format: copy(dst_format),
+        bytes: src_sizes.iter().chain(dst_sizes.iter()).sum(),
+        runs,
+        elapsed,
+    })
+}
+
+fn format_name(format: &ImageFormat) -> String {
+    format!(
+        "{:?}-{:?}:{}",
+        format.pixel_format, format.color_space, format.num_planes
+    )
+}
+
+fn main() {
+    let (width, height, min_elapsed) = parse_args();
+
+    dcp::initialize();
+    let cpu = match dcp::describe_cpu() {
+        Ok(cpu) => cpu,
+        Err(error) => {
+            eprintln!("dcp-bench: unable to describe the cpu: {}", error);
+            process::exit(1);
+        }
+    };
+
+    let mut formats = Vec::new();
+    for &pixel_format in PIXEL_FORMATS {
+        for &color_space in COLOR_SPACES {
+            if let Some(format) = planar_format(width, height, pixel_format, color_space) {
+                formats.push(format);
+            }
+        }
+    }
+
+    let mut results = Vec::new();
+    for src in &formats {
+        for dst in &formats {
+            if !is_direct(&src.0, &dst.0) {
+                continue;
+            }
+
+            match measure(width, height, src, dst, min_elapsed) {
+                Ok(result) => results.push(result),
+                Err(error) => eprintln!(
+                    "dcp-bench: {} -> {} failed: {}",
+                    format_name(&src.0),
+                    format_name(&dst.0),
+                    error
+                ),
+            }
+        }
+    }
+
+    let quote = |values: &[String]| {
+        values
+            .iter()
+            .map(|value| format!("\"{}\"", value))
+            .collect::<Vec<String>>()
+            .join(",")
+    };
+
+    println!("{{");
+    println!("  \"version\": \"{}\",", env!("CARGO_PKG_VERSION"));
+    println!(
+        "  \"host\": {{\"arch\": \"{}\", \"os\": \"{}\", \"manufacturer\": \"{}\", \"extensions\": [{}], \"instruction_sets\": [{}], \"instruction_set\": \"{}\"}},",
+        env::consts::ARCH,
+        env::consts::OS,
+        cpu.manufacturer,
+        quote(&cpu.extensions),
+        quote(&cpu.instruction_sets),
+        cpu.instruction_set
+    );
+    println!("  \"width\": {},", width);
+    println!("  \"height\": {},", height);
+    println!("  \"results\": [");
+    for (i, result) in results.iter().enumerate() {
+        let seconds = result.elapsed.as_secs_f64() / f64::from(result.runs);
+        let pixels = f64::from(width) * f64::from(height);
+        println!(
+            "    {{\"src\": \"{}\", \"dst\": \"{}\", \"runs\": {}, \"seconds\": {:.9}, \"gb_per_s\": {:.3}, \"mpixels_per_s\": {:.3}}}{}",
+            format_name(&result.src_format),
+            format_name(&result.dst_format),
+            result.runs,
+            seconds,
+            result.bytes as f64 / seconds / 1e9,
+            pixels / seconds / 1e6,
+            if i + 1 < results.len() { "," } else { "" }
+        );
+    }
+    println!("  ]");
+    println!("}}");
+}
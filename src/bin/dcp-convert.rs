@@ -0,0 +1,416 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Converts netpbm images to and from raw P010 frames, to prepare test content for the
+//! 10-bit conversion paths, and plays raw clips back to inspect the conversions.
+//!
+//! Usage:
+//! * `dcp-convert to-p010 INPUT.ppm OUTPUT.yuv [bt601|bt709]`
+//! * `dcp-convert from-p010 WIDTHxHEIGHT INPUT.yuv OUTPUT.ppm [bt601|bt709]`
+//! * `dcp-convert play WIDTHxHEIGHT nv12|i420|i444|p010 INPUT.yuv OUTPUT [bt601|bt709]`
+//!
+//! Pixmaps with 16-bit samples keep their full precision when converted to P010; pixmaps
+//! with a maximum value other than 255 or 65535 are rescaled to 16 bits first. Raw frames
+//! store the luma plane followed by the interleaved chroma plane, without line padding.
+//! P010 frames are converted back to 8-bit pixmaps, the library having no conversion to
+//! 16-bit rgb.
+//!
+//! `play` converts every frame of a raw file holding a sequence of frames of the given
+//! size and format. OUTPUT is either a file name pattern such as `frame%04d.ppm`, each
+//! frame being written to an 8-bit pixmap numbered from zero, or a y4m file, ending with
+//! `.y4m`, which needs the `y4m` feature too. y4m files store I420 and I444 frames as
+//! they are, and NV12 and P010 frames converted to I420.
+//!
+//! PNG files can be turned into pixmaps, and back, with the netpbm `pngtopam` and
+//! `pnmtopng` tools: 16-bit PNGs give 16-bit pixmaps.
+//!
+//! Requires the `io` feature.
+use dcp::pnm::{self, Header};
+#[cfg(feature = "y4m")]
+use dcp::y4m;
+use dcp::{convert_image, get_buffers_size, ColorSpace, ImageFormat, PixelFormat};
+use dcv_color_primitives as dcp;
+use std::env;
+use std::error;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::process;
+
+type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+
+fn usage() -> ! {
+    eprintln!("usage: dcp-convert to-p010 INPUT.ppm OUTPUT.yuv [bt601|bt709]");
+    eprintln!("       dcp-convert from-p010 WIDTHxHEIGHT INPUT.yuv OUTPUT.ppm [bt601|bt709]");
+    eprintln!(
+        "       dcp-convert play WIDTHxHEIGHT nv12|i420|i444|p010 INPUT.yuv OUTPUT [bt601|bt709]"
+    );
+    process::exit(2);
+}
+
+fn parse_size(size: &str) -> (u32, u32) {
+    let mut parts = size.splitn(2, 'x').map(str::parse::<u32>);
+    match (parts.next(), parts.next()) {
+        (Some(Ok(width)), Some(Ok(height))) if width > 0 && height > 0 => (width, height),
+        _ => usage(),
+    }
+}
+
+fn parse_color_space(name: Option<&String>) -> ColorSpace {
+    match name.map(String::as_str) {
+        Some("bt601") => ColorSpace::Bt601,
+        Some("bt709") | None => ColorSpace::Bt709,
+        Some(_) => usage(),
+    }
+}
+
+fn parse_pixel_format(name: &str) -> PixelFormat {
+    match name {
+        "nv12" => PixelFormat::Nv12,
+        "i420" => PixelFormat::I420,
+        "i444" => PixelFormat::I444,
+        "p010" => PixelFormat::P010,
+        _ => usage(),
+    }
+}
+
+/// Replaces the `%d` or `%0Nd` directive of a file name pattern with a frame number
+fn frame_path(pattern: &str, frame: usize) -> Option<String> {
+    let start = pattern.find('%')?;
+    let directive = &pattern[start + 1..];
+    let end = directive.find('d')?;
+    let width = match &directive[..end] {
+        "" => 0,
+        width if width.starts_with('0') => width.parse().ok()?,
+        _ => return None,
+    };
+
+    Some(format!(
+        "{}{:0width$}{}",
+        &pattern[..start],
+        frame,
+        &directive[end + 1..],
+        width = width
+    ))
+}
+
+fn p010_format(color_space: ColorSpace) -> ImageFormat {
+    ImageFormat {
+        pixel_format: PixelFormat::P010,
+        color_space,
+        num_planes: 1,
+    }
+}
+
+fn get_buffer_size(width: u32, height: u32, format: &ImageFormat) -> Result<usize> {
+    let sizes = &mut [0usize; 1];
+    get_buffers_size(width, height, format, None, sizes)?;
+    Ok(sizes[0])
+}
+
+/// Rescales the samples of a pixmap to 8 or 16 bits, returning the matching pixel format
+fn normalize(header: &Header, data: &mut Vec<u8>) -> PixelFormat {
+    match header.max_value {
+        255 => PixelFormat::Rgb,
+        65535 => PixelFormat::Rgb48,
+        max_value if max_value < 256 => {
+            let mut samples = vec![0u8; 2 * data.len()];
+            for (&x, sample) in data.iter().zip(samples.chunks_exact_mut(2)) {
+                sample.copy_from_slice(&(rescale(u32::from(x), max_value) as u16).to_le_bytes());
+            }
+            *data = samples;
+            PixelFormat::Rgb48
+        }
+        max_value => {
+            for sample in data.chunks_exact_mut(2) {
+                let x = u32::from(u16::from_le_bytes([sample[0], sample[1]]));
+                sample.copy_from_slice(&(rescale(x, max_value) as u16).to_le_bytes());
+            }
+            PixelFormat::Rgb48
+        }
+    }
+}
+
+fn rescale(x: u32, max_value: u32) -> u32 {
+    (x.min(max_value) * 65535 + max_value / 2) / max_value
+}
+
+fn to_p010(input: &str, output: &str, color_space: ColorSpace) -> Result<()> {
+    let (header, mut data) = pnm::read(BufReader::new(File::open(input)?))?;
+    if header.depth != 3 {
+        return Err(format!("{}: expected an rgb pixmap", input).into());
+    }
+
+    let src_format = ImageFormat {
+        pixel_format: normalize(&header, &mut data),
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+    let dst_format = p010_format(color_space);
+
+    let mut frame = vec![0u8; get_buffer_size(header.width, header.height, &dst_format)?];
+    convert_image(
+        header.width,
+        header.height,
+        &src_format,
+        None,
+        &[&data],
+        &dst_format,
+        None,
+        &mut [&mut frame],
+    )?;
+
+    let mut writer = BufWriter::new(File::create(output)?);
+    writer.write_all(&frame)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn from_p010(
+    width: u32,
+    height: u32,
+    input: &str,
+    output: &str,
+    color_space: ColorSpace,
+) -> Result<()> {
+    let src_format = p010_format(color_space);
+    let mut frame = vec![0u8; get_buffer_size(width, height, &src_format)?];
+    File::open(input)?.read_exact(&mut frame)?;
+
+    let dst_format = ImageFormat {
+        pixel_format: PixelFormat::Rgb,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let mut data = vec![0u8; get_buffer_size(width, height, &dst_format)?];
+    convert_image(
+        width,
+        height,
+        &src_format,
+        None,
+        &[&frame],
+        &dst_format,
+        None,
+        &mut [&mut data],
+    )?;
+
+    pnm::write(
+        BufWriter::new(File::create(output)?),
+        &Header::new(width, height, 3),
+        &data,
+    )?;
+    Ok(())
+}
+
+/// Reads the next frame of a raw file.
+///
+/// Returns `false` if the file ended before the first byte of the frame.
+fn read_frame<R: Read>(reader: &mut R, frame: &mut [u8]) -> Result<bool> {
+    let mut position = 0;
+    while position < frame.len() {
+        match reader.read(&mut frame[position..]) {
+            Ok(0) if position == 0 => return Ok(false),
+            Ok(0) => return Err("the last frame is truncated".into()),
+            Ok(count) => position += count,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    Ok(true)
+}
+
+/// Splits a raw frame, which stores its planes one after the other, into a buffer for
+/// each plane of the format
+fn split_planes<'a>(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    frame: &'a [u8],
+) -> Result<Vec<&'a [u8]>> {
+    let sizes = &mut [0usize; 3][..format.num_planes as usize];
+    get_buffers_size(width, height, format, None, sizes)?;
+
+    let mut planes = Vec::with_capacity(sizes.len());
+    let mut rest = frame;
+    for &size in sizes.iter() {
+        let (plane, next) = rest.split_at(size);
+        planes.push(plane);
+        rest = next;
+    }
+
+    Ok(planes)
+}
+
+fn play(
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+    input: &str,
+    output: &str,
+    color_space: ColorSpace,
+) -> Result<()> {
+    // The library takes planar formats as a buffer for each plane
+    let src_format = ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes: match pixel_format {
+            PixelFormat::I420 | PixelFormat::I444 => 3,
+            _ => 1,
+        },
+    };
+
+    let single_plane = ImageFormat {
+        num_planes: 1,
+        ..src_format
+    };
+
+    let mut frame = vec![0u8; get_buffer_size(width, height, &single_plane)?];
+    let mut reader = BufReader::new(File::open(input)?);
+    if output.ends_with(".y4m") {
+        return play_to_y4m(width, height, &src_format, &mut frame, reader, output);
+    }
+
+    if frame_path(output, 0).is_none() {
+        return Err(format!("{}: expected a y4m file or a %d pattern", output).into());
+    }
+
+    let dst_format = ImageFormat {
+        pixel_format: PixelFormat::Rgb,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let mut data = vec![0u8; get_buffer_size(width, height, &dst_format)?];
+    let mut count = 0;
+    while read_frame(&mut reader, &mut frame)? {
+        convert_image(
+            width,
+            height,
+            &src_format,
+            None,
+            &split_planes(width, height, &src_format, &frame)?,
+            &dst_format,
+            None,
+            &mut [&mut data],
+        )?;
+
+        let path = frame_path(output, count).unwrap_or_default();
+        pnm::write(
+            BufWriter::new(File::create(path)?),
+            &Header::new(width, height, 3),
+            &data,
+        )?;
+        count += 1;
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "y4m")]
+fn play_to_y4m<R: Read>(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    frame: &mut [u8],
+    mut reader: R,
+    output: &str,
+) -> Result<()> {
+    let pixel_format = match src_format.pixel_format {
+        PixelFormat::I444 => PixelFormat::I444,
+        _ => PixelFormat::I420,
+    };
+
+    let header = y4m::Header::new(width, height, pixel_format);
+    let dst_format = header.image_format(src_format.color_space);
+    let [y_size, u_size, v_size] = header.buffers_size();
+    let (mut y, mut u, mut v) = (vec![0u8; y_size], vec![0u8; u_size], vec![0u8; v_size]);
+    let mut writer = y4m::Writer::new(BufWriter::new(File::create(output)?), header)?;
+    while read_frame(&mut reader, frame)? {
+        let planes = split_planes(width, height, src_format, frame)?;
+
+        // Frames in the pixel format of the file are written as they are
+        if src_format.pixel_format as u32 == pixel_format as u32 {
+            writer.write_frame(&planes)?;
+            continue;
+        }
+
+        convert_image(
+            width,
+            height,
+            src_format,
+            None,
+            &planes,
+            &dst_format,
+            None,
+            &mut [&mut y, &mut u, &mut v],
+        )?;
+        writer.write_frame(&[&y, &u, &v])?;
+    }
+
+    writer.into_inner().flush()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "y4m"))]
+fn play_to_y4m<R: Read>(
+    _width: u32,
+    _height: u32,
+    _src_format: &ImageFormat,
+    _frame: &mut [u8],
+    _reader: R,
+    output: &str,
+) -> Result<()> {
+    Err(format!("{}: y4m output needs the y4m feature", output).into())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    dcp::initialize();
+    let result = match args.first().map(String::as_str) {
+        Some("to-p010") if (3..=4).contains(&args.len()) => {
+            to_p010(&args[1], &args[2], parse_color_space(args.get(3)))
+        }
+        Some("from-p010") if (4..=5).contains(&args.len()) => {
+            let (width, height) = parse_size(&args[1]);
+            from_p010(
+                width,
+                height,
+                &args[2],
+                &args[3],
+                parse_color_space(args.get(4)),
+            )
+        }
+        Some("play") if (5..=6).contains(&args.len()) => {
+            let (width, height) = parse_size(&args[1]);
+            play(
+                width,
+                height,
+                parse_pixel_format(&args[2]),
+                &args[3],
+                &args[4],
+                parse_color_space(args.get(5)),
+            )
+        }
+        _ => usage(),
+    };
+
+    if let Err(error) = result {
+        eprintln!("dcp-convert: {}", error);
+        process::exit(1);
+    }
+}
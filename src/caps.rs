@@ -0,0 +1,234 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! GStreamer caps strings.
+//!
+//! Raw video caps, such as `video/x-raw,format=NV12,colorimetry=bt709`, can be turned
+//! into an [`ImageFormat`] and back, so that pipeline configurations written for GStreamer
+//! can describe the images to convert.
+//!
+//! Parsed formats have one plane for each logical plane, as the planes of a mapped
+//! `GstVideoFrame`. Other fields of the caps, like the size or the frame rate, are
+//! ignored, except for the height: when the colorimetry is missing, it is inferred as
+//! GStreamer does, `sRGB` for rgb formats, `bt709` for yuv formats taller than 576 lines
+//! and `bt601` otherwise.
+//!
+//! This module requires the `caps` feature.
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::caps::{from_caps, to_caps};
+//! use dcp::{ColorSpace, PixelFormat};
+//! use std::error;
+//!
+//! fn parse() -> Result<(), Box<dyn error::Error>> {
+//!     let format = from_caps("video/x-raw, format=(string)NV12, width=1920, height=1080")?;
+//!     assert!(matches!(format.pixel_format, PixelFormat::Nv12));
+//!     assert!(matches!(format.color_space, ColorSpace::Bt709));
+//!     assert_eq!(format.num_planes, 2);
+//!
+//!     assert_eq!(to_caps(&format)?, "video/x-raw,format=NV12,colorimetry=bt709");
+//!     Ok(())
+//! }
+//! # parse().unwrap();
+//! ```
+//!
+//! [`ImageFormat`]: ../struct.ImageFormat.html
+use crate::color_space::ColorSpace;
+use crate::pixel_format::{self, PixelFormat};
+use crate::{ErrorKind, ImageFormat};
+
+const MEDIA_TYPE: &str = "video/x-raw";
+
+// Tallest standard definition format, as used by GStreamer to pick the default colorimetry
+const MAX_SD_HEIGHT: u32 = 576;
+
+// Values of GstVideoColorRange, GstVideoColorMatrix and GstVideoTransferFunction
+const RANGE_0_255: &str = "1";
+const MATRIX_RGB: &str = "1";
+const MATRIX_BT709: &str = "3";
+const MATRIX_BT601: &str = "4";
+const MATRIX_BT2020: &str = "6";
+const TRANSFER_SMPTE2084: &str = "14";
+
+const FORMAT_NAMES: &[(PixelFormat, &str)] = &[
+    (PixelFormat::Argb, "ARGB"),
+    (PixelFormat::Bgra, "BGRA"),
+    (PixelFormat::Bgr, "BGR"),
+    (PixelFormat::Rgba, "RGBA"),
+    (PixelFormat::Rgb, "RGB"),
+    (PixelFormat::I444, "Y444"),
+    (PixelFormat::I422, "Y42B"),
+    (PixelFormat::I420, "I420"),
+    (PixelFormat::Nv12, "NV12"),
+    (PixelFormat::P010, "P010_10LE"),
+];
+
+fn is_rgb(pixel_format: PixelFormat) -> bool {
+    matches!(
+        pixel_format,
+        PixelFormat::Argb
+            | PixelFormat::Bgra
+            | PixelFormat::Bgr
+            | PixelFormat::Rgba
+            | PixelFormat::Rgb
+    )
+}
+
+/// Removes the optional type annotation and quotes of a field value
+fn unwrap_value(value: &str) -> &str {
+    let value = value.trim();
+    let value = match value.strip_prefix('(') {
+        Some(annotated) => match annotated.find(')') {
+            Some(end) => annotated[end + 1..].trim(),
+            None => value,
+        },
+        None => value,
+    };
+
+    value
+        .strip_prefix('"')
+        .and_then(|quoted| quoted.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+/// Parses a colorimetry, either by name or as `range:matrix:transfer:primaries`
+fn parse_colorimetry(colorimetry: &str) -> Result<ColorSpace, ErrorKind> {
+    match colorimetry {
+        "sRGB" => return Ok(ColorSpace::Lrgb),
+        "bt601" => return Ok(ColorSpace::Bt601),
+        "bt709" => return Ok(ColorSpace::Bt709),
+        "bt2100-pq" => return Ok(ColorSpace::Bt2020Pq),
+        _ => (),
+    }
+
+    // Other names are valid, but describe unsupported color spaces
+    let fields: Vec<&str> = colorimetry.split(':').collect();
+    if fields.len() == 1 {
+        return Err(ErrorKind::InvalidOperation);
+    } else if fields.len() != 4 {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let full_range = fields[0] == RANGE_0_255;
+    match (fields[1], full_range) {
+        (MATRIX_RGB, _) => Ok(ColorSpace::Lrgb),
+        (MATRIX_BT601, true) => Ok(ColorSpace::Jfif),
+        (MATRIX_BT601, false) => Ok(ColorSpace::Bt601),
+        (MATRIX_BT709, false) => Ok(ColorSpace::Bt709),
+        (MATRIX_BT2020, false) if fields[2] == TRANSFER_SMPTE2084 => Ok(ColorSpace::Bt2020Pq),
+        _ => Err(ErrorKind::InvalidOperation),
+    }
+}
+
+/// Parses fixed raw video caps into an image format
+///
+/// Only the `format`, `colorimetry` and `height` fields are taken into account. Field
+/// values can carry a type annotation, like `format=(string)NV12`, and can be quoted.
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the caps are malformed, are not `video/x-raw` caps, do not
+///   specify a format, or specify a list or a range where a fixed value is expected
+///
+/// * [`InvalidOperation`] if the format or the colorimetry are not supported
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+pub fn from_caps(caps: &str) -> Result<ImageFormat, ErrorKind> {
+    let mut fields = caps.trim().trim_end_matches(';').split(',');
+    if fields.next().map(str::trim) != Some(MEDIA_TYPE) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let mut format = None;
+    let mut colorimetry = None;
+    let mut height = None;
+    for field in fields {
+        let mut tokens = field.splitn(2, '=');
+        let name = tokens.next().unwrap_or_default().trim();
+        let value = unwrap_value(tokens.next().ok_or(ErrorKind::InvalidValue)?);
+        if name.is_empty() || value.is_empty() || value.contains(&['{', '}', '[', ']'][..]) {
+            return Err(ErrorKind::InvalidValue);
+        }
+
+        match name {
+            "format" => format = Some(value),
+            "colorimetry" => colorimetry = Some(value),
+            "height" => height = Some(value.parse::<u32>().map_err(|_| ErrorKind::InvalidValue)?),
+            _ => (),
+        }
+    }
+
+    let format = format.ok_or(ErrorKind::InvalidValue)?;
+    let pixel_format = FORMAT_NAMES
+        .iter()
+        .find(|&&(_, name)| name == format)
+        .map(|&(pixel_format, _)| pixel_format)
+        .ok_or(ErrorKind::InvalidOperation)?;
+
+    let color_space = if is_rgb(pixel_format) {
+        ColorSpace::Lrgb
+    } else {
+        match colorimetry {
+            Some(colorimetry) => match parse_colorimetry(colorimetry)? {
+                ColorSpace::Lrgb => return Err(ErrorKind::InvalidOperation),
+                color_space => color_space,
+            },
+            None if height > Some(MAX_SD_HEIGHT) => ColorSpace::Bt709,
+            None => ColorSpace::Bt601,
+        }
+    };
+
+    Ok(ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes: pixel_format::get_logical_planes(pixel_format).len() as u32,
+    })
+}
+
+/// Formats an image format as raw video caps, with `format` and `colorimetry` fields
+///
+/// The number of planes of the image format is not part of the caps.
+///
+/// # Errors
+///
+/// * [`InvalidOperation`] if the pixel format has no GStreamer equivalent, or if the
+///   color space is not compatible with the pixel format
+///
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+pub fn to_caps(format: &ImageFormat) -> Result<String, ErrorKind> {
+    let name = FORMAT_NAMES
+        .iter()
+        .find(|&&(pixel_format, _)| pixel_format as u32 == format.pixel_format as u32)
+        .map(|&(_, name)| name)
+        .ok_or(ErrorKind::InvalidOperation)?;
+
+    let colorimetry = match (is_rgb(format.pixel_format), format.color_space) {
+        (true, ColorSpace::Lrgb) => "sRGB",
+        (false, ColorSpace::Bt601) => "bt601",
+        (false, ColorSpace::Bt709) => "bt709",
+        (false, ColorSpace::Bt2020Pq) => "bt2100-pq",
+        (false, ColorSpace::Jfif) => "1:4:0:0",
+        _ => return Err(ErrorKind::InvalidOperation),
+    };
+
+    Ok(format!(
+        "{},format={},colorimetry={}",
+        MEDIA_TYPE, name, colorimetry
+    ))
+}
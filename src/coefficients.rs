@@ -0,0 +1,196 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Fixed point coefficients of the integer conversions between rgb and YCbCr.
+//!
+//! [`convert_image`] converts between 8-bit rgb and 8-bit YCbCr formats with color space
+//! `Bt601` or `Bt709` using integer arithmetic. This module exposes the coefficients and
+//! the shifts of these kernels, so that other implementations living in the same
+//! application, like GPU shaders, can produce the same output bit for bit. Every
+//! instruction set uses the same coefficients and gives the same output.
+//!
+//! # Rgb to YCbCr
+//! Each output sample is computed from a weighted sum of the input components, with
+//! [`FORWARD_FRAC_BITS`] fractional bits:
+//! ```text
+//! y  = (w.y[0] * r + w.y[1] * g + w.y[2] * b + LUMA_OFFSET) >> FORWARD_FRAC_BITS
+//! cb = (w.cb[0] * r + w.cb[1] * g + w.cb[2] * b + CHROMA_OFFSET) >> FORWARD_FRAC_BITS
+//! cr = (w.cr[0] * r + w.cr[1] * g + w.cr[2] * b + CHROMA_OFFSET) >> FORWARD_FRAC_BITS
+//! ```
+//!
+//! With 4:2:0 subsampling, chroma is computed from the sums of the components of each
+//! 2x2 block, with [`SUBSAMPLED_FRAC_BITS`] fractional bits:
+//! ```text
+//! cb = (w.cb[0] * sr + w.cb[1] * sg + w.cb[2] * sb + SUBSAMPLED_CHROMA_OFFSET) >> SUBSAMPLED_FRAC_BITS
+//! cr = (w.cr[0] * sr + w.cr[1] * sg + w.cr[2] * sb + SUBSAMPLED_CHROMA_OFFSET) >> SUBSAMPLED_FRAC_BITS
+//! ```
+//!
+//! The results are always in [0, 255].
+//!
+//! # YCbCr to rgb
+//! Each product is computed with 32-bit integers, then shifted right by [`PRODUCT_SHIFT`].
+//! The sums have [`BACKWARD_FRAC_BITS`] fractional bits:
+//! ```text
+//! l = (y * w.y) >> PRODUCT_SHIFT
+//! r = clamp((l + ((cr * w.r_cr) >> PRODUCT_SHIFT) - w.r_bias) >> BACKWARD_FRAC_BITS)
+//! g = clamp((l - ((cb * w.g_cb) >> PRODUCT_SHIFT) - ((cr * w.g_cr) >> PRODUCT_SHIFT) + w.g_bias) >> BACKWARD_FRAC_BITS)
+//! b = clamp((l + ((cb * w.b_cb) >> PRODUCT_SHIFT) - w.b_bias) >> BACKWARD_FRAC_BITS)
+//! ```
+//!
+//! where shifts are arithmetic and clamp saturates to [0, 255]. Subsampled chroma samples
+//! are used for every pixel they cover.
+//!
+//! The vectorized kernels keep the intermediate values in 16-bit integers, which do not
+//! overflow only for samples in the nominal range: luma in [16, 235] and chroma in
+//! [16, 240]. Samples out of this range might give a different output.
+//!
+//! [`convert_image`]: ../fn.convert_image.html
+//! [`FORWARD_FRAC_BITS`]: ./constant.FORWARD_FRAC_BITS.html
+//! [`SUBSAMPLED_FRAC_BITS`]: ./constant.SUBSAMPLED_FRAC_BITS.html
+//! [`PRODUCT_SHIFT`]: ./constant.PRODUCT_SHIFT.html
+//! [`BACKWARD_FRAC_BITS`]: ./constant.BACKWARD_FRAC_BITS.html
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::coefficients::{BT709_RGB_TO_YCBCR, FORWARD_FRAC_BITS, LUMA_OFFSET};
+//! use dcp::{convert_image, ColorSpace, ImageFormat, PixelFormat};
+//!
+//! dcp::initialize();
+//!
+//! let src_format = ImageFormat {
+//!     pixel_format: PixelFormat::Bgra,
+//!     color_space: ColorSpace::Lrgb,
+//!     num_planes: 1,
+//! };
+//!
+//! let dst_format = ImageFormat {
+//!     pixel_format: PixelFormat::I444,
+//!     color_space: ColorSpace::Bt709,
+//!     num_planes: 3,
+//! };
+//!
+//! let (r, g, b) = (200, 100, 50);
+//! let (mut y, mut u, mut v) = ([0u8], [0u8], [0u8]);
+//! convert_image(
+//!     1,
+//!     1,
+//!     &src_format,
+//!     None,
+//!     &[&[b as u8, g as u8, r as u8, 255]],
+//!     &dst_format,
+//!     None,
+//!     &mut [&mut y, &mut u, &mut v],
+//! )
+//! .unwrap();
+//!
+//! let w = &BT709_RGB_TO_YCBCR.y;
+//! let luma = (w[0] * r + w[1] * g + w[2] * b + LUMA_OFFSET) >> FORWARD_FRAC_BITS;
+//! assert_eq!(i32::from(y[0]), luma);
+//! ```
+use crate::convert_image::common::*;
+
+/// Fractional bits of the rgb to YCbCr weights
+pub const FORWARD_FRAC_BITS: u32 = FIX16 as u32;
+
+/// Fractional bits of the rgb to YCbCr weights applied to the sums of 2x2 blocks
+pub const SUBSAMPLED_FRAC_BITS: u32 = FIX18 as u32;
+
+/// Offset added to luma, that includes the rounding term
+pub const LUMA_OFFSET: i32 = Y_OFFSET;
+
+/// Offset added to chroma, that includes the rounding term
+pub const CHROMA_OFFSET: i32 = C_OFFSET16;
+
+/// Offset added to chroma computed from the sums of 2x2 blocks, that includes the
+/// rounding term
+pub const SUBSAMPLED_CHROMA_OFFSET: i32 = C_OFFSET;
+
+/// Right shift applied to each product of the YCbCr to rgb conversion
+pub const PRODUCT_SHIFT: u32 = 8;
+
+/// Fractional bits of the YCbCr to rgb sums
+pub const BACKWARD_FRAC_BITS: u32 = FIX6 as u32;
+
+/// Weights of the rgb components in each YCbCr sample
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RgbToYcbcr {
+    /// Weights of r, g and b in luma
+    pub y: [i32; 3],
+    /// Weights of r, g and b in blue-difference chroma
+    pub cb: [i32; 3],
+    /// Weights of r, g and b in red-difference chroma
+    pub cr: [i32; 3],
+}
+
+/// Weights and biases of the YCbCr samples in each rgb component
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct YcbcrToRgb {
+    /// Weight of luma, shared by every component
+    pub y: i32,
+    /// Weight of red-difference chroma in red
+    pub r_cr: i32,
+    /// Weight of red-difference chroma in green, subtracted
+    pub g_cr: i32,
+    /// Weight of blue-difference chroma in green, subtracted
+    pub g_cb: i32,
+    /// Weight of blue-difference chroma in blue
+    pub b_cb: i32,
+    /// Bias subtracted from red
+    pub r_bias: i32,
+    /// Bias added to green
+    pub g_bias: i32,
+    /// Bias subtracted from blue
+    pub b_bias: i32,
+}
+
+/// Rgb to YCbCr weights for `Bt601`
+pub const BT601_RGB_TO_YCBCR: RgbToYcbcr = RgbToYcbcr {
+    y: [XR_601, XG_601, XB_601],
+    cb: [YR_601, YG_601, YB_601],
+    cr: [ZR_601, ZG_601, ZB_601],
+};
+
+/// Rgb to YCbCr weights for `Bt709`
+pub const BT709_RGB_TO_YCBCR: RgbToYcbcr = RgbToYcbcr {
+    y: [XR_709, XG_709, XB_709],
+    cb: [YR_709, YG_709, YB_709],
+    cr: [ZR_709, ZG_709, ZB_709],
+};
+
+/// YCbCr to rgb weights for `Bt601`
+pub const BT601_YCBCR_TO_RGB: YcbcrToRgb = YcbcrToRgb {
+    y: XXYM_601,
+    r_cr: RCRM_601,
+    g_cr: GCRM_601,
+    g_cb: GCBM_601,
+    b_cb: BCBM_601,
+    r_bias: RN_601,
+    g_bias: GP_601,
+    b_bias: BN_601,
+};
+
+/// YCbCr to rgb weights for `Bt709`
+pub const BT709_YCBCR_TO_RGB: YcbcrToRgb = YcbcrToRgb {
+    y: XXYM_709,
+    r_cr: RCRM_709,
+    g_cr: GCRM_709,
+    g_cb: GCBM_709,
+    b_cb: BCBM_709,
+    r_bias: RN_709,
+    g_bias: GP_709,
+    b_bias: BN_709,
+};
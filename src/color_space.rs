@@ -21,7 +21,7 @@
 /// * Gamma
 /// * Range (headroom / footroom)
 /// * Primaries
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 #[repr(C)]
 pub enum ColorSpace {
     /// Gamma-corrected RGB
@@ -30,4 +30,9 @@ pub enum ColorSpace {
     Bt601,
     /// YCbCr, ITU-R Recommendation BT.709 (CSC systems)
     Bt709,
+    /// YCbCr, ITU-R Recommendation BT.2020 (non-constant luminance), with SMPTE ST 2084
+    /// perceptual quantizer transfer function (HDR10)
+    Bt2020Pq,
+    /// YCbCr, JPEG File Interchange Format (BT.601 full range), rounding as libjpeg does
+    Jfif,
 }
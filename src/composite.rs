@@ -0,0 +1,1376 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Alpha compositing.
+//!
+//! # Compositing over a background
+//!
+//! Conversions to formats without alpha simply drop it, which makes transparent areas of
+//! captured windows show whatever color was stored behind the alpha channel. This module
+//! composites the source image over a background color, or a checkerboard, while
+//! converting, so that transparency is rendered the way a desktop compositor would.
+//!
+//! Source samples are expected to have straight (not premultiplied) alpha. Each band of
+//! the source image is composited into a small scratch buffer which is then converted, so
+//! the source image is never modified.
+//!
+//! # Blending two images
+//!
+//! [`blend_images`] blends an image (e.g. a cursor or an overlay) over another one of
+//! the same size, before the result is converted. Sources with straight or premultiplied
+//! alpha are supported.
+//!
+//! [`blend_images`]: ./fn.blend_images.html
+//!
+//! # Cross-fading frames
+//!
+//! [`blend_frames`] blends two images of the same format with a constant weight, for
+//! transitions or temporal noise reduction. It works on rgb and YCbCr images alike.
+//! [`accumulate_frames`] instead adds a weighted image to another one, e.g. to average a
+//! fixed window of frames into a cleared image.
+//!
+//! [`blend_frames`]: ./fn.blend_frames.html
+//! [`accumulate_frames`]: ./fn.accumulate_frames.html
+//!
+//! # Drawing sprites into YCbCr frames
+//!
+//! [`overlay_sprite`] composites a small image with alpha, typically the mouse cursor,
+//! directly into a YCbCr frame at given coordinates. Only the samples below the sprite
+//! are touched, so the frame does not need to be converted to rgb and back. Chroma samples
+//! shared by several pixels are blended with the average coverage of those pixels.
+//!
+//! [`overlay_sprite`]: ./fn.overlay_sprite.html
+//!
+//! # Chroma keying
+//!
+//! [`chroma_key`] turns the pixels of an rgb image matching a key color into transparent
+//! pixels. Matching uses the largest difference between the channels of a pixel and the
+//! ones of the key, with a hard edge by default: antialiased text drawn over the key keeps
+//! its edge pixels opaque unless they are within the tolerance.
+//!
+//! [`chroma_key`]: ./fn.chroma_key.html
+//!
+//! # Watermarking
+//!
+//! [`convert_image_watermarked`] stamps a small grayscale mask, such as a logo or the
+//! coverage of a privacy region, into the luma of a YCbCr frame while converting it. Each
+//! band of the frame is stamped right after being converted, while it is still in the
+//! cache, so watermarking does not cost a further pass over the frame. Frames that are
+//! already YCbCr can be stamped in place with [`stamp_watermark`]. The stamped luma is
+//! clamped to the range of the color space.
+//!
+//! [`convert_image_watermarked`]: ./fn.convert_image_watermarked.html
+//! [`stamp_watermark`]: ./fn.stamp_watermark.html
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::composite::{convert_composited_image, Background};
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn convert() -> Result<(), Box<dyn error::Error>> {
+//!     dcp::initialize();
+//!
+//!     const WIDTH: u32 = 640;
+//!     const HEIGHT: u32 = 480;
+//!
+//!     let src_format = ImageFormat {
+//!         pixel_format: PixelFormat::Bgra,
+//!         color_space: ColorSpace::Lrgb,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let dst_format = ImageFormat {
+//!         pixel_format: PixelFormat::Nv12,
+//!         color_space: ColorSpace::Bt601,
+//!         num_planes: 1,
+//!     };
+//!
+//!     // Fully transparent source
+//!     let src_buffer = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+//!     let mut dst_buffer = vec![0u8; 3 * (WIDTH as usize) * (HEIGHT as usize) / 2];
+//!
+//!     convert_composited_image(
+//!         WIDTH,
+//!         HEIGHT,
+//!         &src_format,
+//!         None,
+//!         &[&src_buffer],
+//!         &dst_format,
+//!         None,
+//!         &mut [&mut dst_buffer],
+//!         &Background::Color([255, 255, 255]),
+//!     )?;
+//!
+//!     // White luma
+//!     assert_eq!(dst_buffer[0], 235);
+//!     Ok(())
+//! }
+//! ```
+use crate::dispatcher;
+use crate::pixel_format::{self, PixelFormat, STRIDE_AUTO};
+use crate::trace_span;
+use crate::{ColorSpace, ErrorKind, ImageFormat};
+
+/// Lines composited and converted at once
+const COMPOSITE_BAND_HEIGHT: usize = 64;
+
+/// Background to composite the source image over
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Background {
+    /// A solid color, as red, green and blue
+    Color([u8; 3]),
+    /// A checkerboard, as commonly shown by image editors behind transparent areas
+    Checkerboard {
+        /// Side of each square in pixels
+        size: u32,
+        /// Color of the square at the top left corner, as red, green and blue
+        light: [u8; 3],
+        /// Color of the other squares, as red, green and blue
+        dark: [u8; 3],
+    },
+}
+
+impl Background {
+    fn color_at(&self, x: usize, y: usize) -> [u8; 3] {
+        match *self {
+            Background::Color(color) => color,
+            Background::Checkerboard { size, light, dark } => {
+                let size = size as usize;
+                if ((x / size) + (y / size)) & 1 == 0 {
+                    light
+                } else {
+                    dark
+                }
+            }
+        }
+    }
+}
+
+/// An enumeration of alpha representations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlphaMode {
+    /// Color samples are independent of alpha
+    Straight,
+    /// Color samples are already multiplied by alpha
+    Premultiplied,
+}
+
+/// Byte offsets of red, green, blue and alpha of the formats with an alpha channel
+fn get_offsets(pixel_format: PixelFormat) -> Option<[usize; 4]> {
+    match pixel_format {
+        PixelFormat::Argb => Some([1, 2, 3, 0]),
+        PixelFormat::Bgra => Some([2, 1, 0, 3]),
+        PixelFormat::Rgba => Some([0, 1, 2, 3]),
+        _ => None,
+    }
+}
+
+fn blend(color: u8, background: u8, alpha: u8) -> u8 {
+    let alpha = u32::from(alpha);
+    ((u32::from(color) * alpha + u32::from(background) * (255 - alpha) + 127) / 255) as u8
+}
+
+/// Composites a line of pixels over the background, writing opaque pixels
+fn composite_row(
+    src: &[u8],
+    dst: &mut [u8],
+    y: usize,
+    offsets: [usize; 4],
+    background: &Background,
+) {
+    let [r, g, b, a] = offsets;
+    for (x, (s, d)) in src.chunks_exact(4).zip(dst.chunks_exact_mut(4)).enumerate() {
+        let alpha = s[a];
+        let color = background.color_at(x, y);
+        d[r] = blend(s[r], color[0], alpha);
+        d[g] = blend(s[g], color[1], alpha);
+        d[b] = blend(s[b], color[2], alpha);
+        d[a] = 255;
+    }
+}
+
+/// Converts an image, compositing it over a background
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source image plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination image plane
+/// * `background` - Background to composite the source image over
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the background is a checkerboard with squares of size zero
+///
+/// * [`InvalidOperation`] if the source pixel format has no alpha channel
+///
+/// * All the errors reported by [`convert_image`], except that conversions are never routed
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`convert_image`]: ../fn.convert_image.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_composited_image(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    background: &Background,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_composited_image",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    let (converter, last_src_plane, last_dst_plane) =
+        crate::get_converter(width, height, src_format, dst_format)?;
+
+    let offsets = get_offsets(src_format.pixel_format).ok_or(ErrorKind::InvalidOperation)?;
+    if let Background::Checkerboard { size: 0, .. } = background {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    if crate::is_empty(width, height) {
+        return Ok(());
+    }
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let src_layouts =
+        crate::get_plane_layouts(src_format, width, height, last_src_plane, src_strides)?;
+    let dst_layouts =
+        crate::get_plane_layouts(dst_format, width, height, last_dst_plane, dst_strides)?;
+
+    // Each band is composited into a packed scratch buffer, then converted from there
+    let row_size = 4 * (width as usize);
+    let src_stride = src_layouts[0].stride;
+    let mut scratch = vec![0u8; row_size * COMPOSITE_BAND_HEIGHT.min(height as usize)];
+
+    crate::convert_in_bands(
+        &src_layouts,
+        src_buffers,
+        dst_format,
+        &dst_layouts,
+        dst_buffers,
+        crate::get_bands(height as usize, COMPOSITE_BAND_HEIGHT),
+        |band| {
+            for (line, dst) in scratch
+                .chunks_exact_mut(row_size)
+                .take(band.height())
+                .enumerate()
+            {
+                composite_row(
+                    &band.src_buffers[0][line * src_stride..][..row_size],
+                    dst,
+                    band.first_line + line,
+                    offsets,
+                    background,
+                );
+            }
+
+            crate::Band {
+                src_strides: &[row_size],
+                src_buffers: vec![&scratch[..]],
+                ..band
+            }
+            .convert(converter, width)
+        },
+    )
+}
+
+/// Blends an image over another one of the same size
+///
+/// Each destination pixel `d` is replaced by `s + d * (1 - alpha)`, where `s` is the source
+/// pixel with premultiplied alpha. Straight sources are premultiplied on the fly.
+/// The destination is expected to be opaque, or to use the same alpha representation as the
+/// source: the resulting alpha is always `alpha + d.alpha * (1 - alpha)`.
+///
+/// # Arguments
+/// * `width` - Width of the images in pixels
+/// * `height` - Height of the images in pixels
+/// * `format` - Image format of both images
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each plane of the image to blend
+/// * `src_buffers` - An array of image buffers in each plane of the image to blend
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each plane of the image to blend over
+/// * `dst_buffers` - An array of image buffers in each plane of the image to blend over
+/// * `alpha_mode` - Alpha representation of the image to blend
+///
+/// # Errors
+///
+/// * [`NotInitialized`] if the library was not initialized before
+///
+/// * [`InvalidValue`] if the color space is not `ColorSpace::Lrgb`
+///
+/// * [`InvalidValue`] if the image format has a number of planes which is not compatible
+///   with its pixel format
+///
+/// * [`InvalidValue`] if one or more strides are smaller than the line size
+///
+/// * [`InvalidOperation`] if the pixel format is neither `Bgra` nor `Rgba`
+///
+/// * [`NotEnoughData`] if the strides arrays are not `None` and they are empty
+///
+/// * [`NotEnoughData`] if one or more buffers does not provide enough data
+///
+/// [`NotInitialized`]: ../enum.ErrorKind.html#variant.NotInitialized
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+#[allow(clippy::too_many_arguments)]
+pub fn blend_images(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    alpha_mode: AlphaMode,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(DEBUG, "blend_images", width, height, format = ?format.pixel_format);
+
+    let blender = crate::get_blender()?;
+    match format.pixel_format {
+        PixelFormat::Bgra | PixelFormat::Rgba => {}
+        _ => return Err(ErrorKind::InvalidOperation),
+    }
+
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if !dispatcher::get_color_space_mode(format.color_space as u32)
+        || !pixel_format::is_compatible(format.pixel_format as u32, width, height, last_plane)
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let src_layouts = crate::get_plane_layouts(format, width, height, last_plane, src_strides)?;
+    let dst_layouts = crate::get_plane_layouts(format, width, height, last_plane, dst_strides)?;
+
+    let row_size = 4 * (width as usize);
+    let src_stride = src_layouts[0].stride;
+    let dst_stride = dst_layouts[0].stride;
+    if src_stride < row_size || dst_stride < row_size {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let premultiplied = alpha_mode == AlphaMode::Premultiplied;
+    for line in 0..(height as usize) {
+        let src = src_buffers
+            .first()
+            .and_then(|buffer| buffer.get(line * src_stride..))
+            .and_then(|buffer| buffer.get(..row_size))
+            .ok_or(ErrorKind::NotEnoughData)?;
+        let dst = dst_buffers
+            .first_mut()
+            .and_then(|buffer| buffer.get_mut(line * dst_stride..))
+            .and_then(|buffer| buffer.get_mut(..row_size))
+            .ok_or(ErrorKind::NotEnoughData)?;
+
+        blender(src, dst, premultiplied);
+    }
+
+    Ok(())
+}
+
+/// Cross-fades two images of the same size and format
+///
+/// Each destination sample `d` is replaced by `s * alpha + d * (1 - alpha)`, where `s` is
+/// the sample at the same position in the source image and `alpha` goes from 0 (keep the
+/// destination) to 255 (replace it with the source). Every channel, alpha included, is
+/// blended with the same weight, so the images can be in any color space: cross-fading
+/// YCbCr frames does not require converting them to rgb and back.
+///
+/// Stepping `alpha` over consecutive frames produces a transition, while blending each
+/// frame into a running average with a small `alpha` is a simple temporal noise reduction.
+///
+/// The following pixel formats are supported: `Argb`, `Bgra`, `Bgr`, `Rgba`, `Rgb`,
+/// `I444`, `I422`, `I420` and `Nv12`.
+///
+/// # Arguments
+/// * `width` - Width of the images in pixels
+/// * `height` - Height of the images in pixels
+/// * `format` - Image format of both images
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each plane of the image to fade in
+/// * `src_buffers` - An array of image buffers in each plane of the image to fade in
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each plane of the image to fade out
+/// * `dst_buffers` - An array of image buffers in each plane of the image to fade out
+/// * `alpha` - Weight of the source image
+///
+/// # Errors
+///
+/// * [`NotInitialized`] if the library was not initialized before
+///
+/// * [`InvalidValue`] if the color space does not match the pixel format, the image size
+///   does not match the pixel format or the number of planes is not supported
+///
+/// * [`InvalidOperation`] if the pixel format is not supported
+///
+/// * [`NotEnoughData`] if the strides arrays are shorter than the number of planes, or
+///   one or more strides are smaller than the line size
+///
+/// * [`NotEnoughData`] if one or more buffers does not provide enough data
+///
+/// [`NotInitialized`]: ../enum.ErrorKind.html#variant.NotInitialized
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+#[allow(clippy::too_many_arguments)]
+pub fn blend_frames(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    alpha: u8,
+) -> Result<(), ErrorKind> {
+    let _span =
+        trace_span!(DEBUG, "blend_frames", width, height, format = ?format.pixel_format, alpha);
+
+    let cross_fader = crate::get_cross_fader()?;
+    let pixel_format = format.pixel_format as u32;
+    if dispatcher::get_pixel_format_mode(pixel_format)
+        ^ dispatcher::get_color_space_mode(format.color_space as u32)
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    match format.pixel_format {
+        PixelFormat::Argb
+        | PixelFormat::Bgra
+        | PixelFormat::Bgr
+        | PixelFormat::Rgba
+        | PixelFormat::Rgb
+        | PixelFormat::I444
+        | PixelFormat::I422
+        | PixelFormat::I420
+        | PixelFormat::Nv12 => {}
+        _ => return Err(ErrorKind::InvalidOperation),
+    }
+
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if !pixel_format::is_compatible(pixel_format, width, height, last_plane) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let src_layouts = crate::get_plane_layouts(format, width, height, last_plane, src_strides)?;
+    let dst_layouts = crate::get_plane_layouts(format, width, height, last_plane, dst_strides)?;
+
+    let src_sizes: Vec<usize> = src_buffers.iter().map(|buffer| buffer.len()).collect();
+    let dst_sizes: Vec<usize> = dst_buffers.iter().map(|buffer| buffer.len()).collect();
+    for (src_layout, dst_layout) in src_layouts.iter().zip(dst_layouts.iter()) {
+        check_layout(src_layout, width, height, &src_sizes)?;
+        check_layout(dst_layout, width, height, &dst_sizes)?;
+    }
+
+    for (src_layout, dst_layout) in src_layouts.iter().zip(dst_layouts.iter()) {
+        let row_size = src_layout.depth * ((width >> src_layout.horizontal_shift) as usize);
+        let src = &src_buffers[src_layout.buffer][src_layout.offset..];
+        let dst = &mut dst_buffers[dst_layout.buffer][dst_layout.offset..];
+        for row in 0..((height >> src_layout.vertical_shift) as usize) {
+            cross_fader(
+                &src[row * src_layout.stride..][..row_size],
+                &mut dst[row * dst_layout.stride..][..row_size],
+                alpha,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds an image, scaled by a constant weight, to another one of the same format
+///
+/// Each destination sample becomes `y + x * alpha / 255`, rounded and clamped to 255,
+/// where `x` is the sample at the same position in the source image. Every channel is
+/// scaled with the same weight, so the images can be in any color space.
+///
+/// Accumulating `n` frames into a cleared image, each with `alpha` equal to `255 / n`,
+/// averages them, e.g. as a simple temporal filter before the result is converted.
+/// Unlike [`blend_frames`], the destination keeps its whole weight, and each sum is
+/// rounded to eight bits.
+///
+/// The following pixel formats are supported: `Argb`, `Bgra`, `Bgr`, `Rgba`, `Rgb`,
+/// `I444`, `I422`, `I420` and `Nv12`.
+///
+/// # Arguments
+/// * `width` - Width of the images in pixels
+/// * `height` - Height of the images in pixels
+/// * `format` - Image format of both images
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each plane of the image to add
+/// * `src_buffers` - An array of image buffers in each plane of the image to add
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each plane of the accumulated image
+/// * `dst_buffers` - An array of image buffers in each plane of the accumulated image
+/// * `alpha` - Weight of the source image
+///
+/// # Errors
+///
+/// * [`NotInitialized`] if the library was not initialized before
+///
+/// * [`InvalidValue`] if the color space does not match the pixel format, the image size
+///   does not match the pixel format or the number of planes is not supported
+///
+/// * [`InvalidOperation`] if the pixel format is not supported
+///
+/// * [`NotEnoughData`] if the strides arrays are shorter than the number of planes, or
+///   one or more strides are smaller than the line size
+///
+/// * [`NotEnoughData`] if one or more buffers does not provide enough data
+///
+/// [`blend_frames`]: ./fn.blend_frames.html
+/// [`NotInitialized`]: ../enum.ErrorKind.html#variant.NotInitialized
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+#[allow(clippy::too_many_arguments)]
+pub fn accumulate_frames(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    alpha: u8,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(DEBUG, "accumulate_frames", width, height, format = ?format.pixel_format, alpha);
+
+    let accumulator = crate::get_accumulator()?;
+    let pixel_format = format.pixel_format as u32;
+    if dispatcher::get_pixel_format_mode(pixel_format)
+        ^ dispatcher::get_color_space_mode(format.color_space as u32)
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    match format.pixel_format {
+        PixelFormat::Argb
+        | PixelFormat::Bgra
+        | PixelFormat::Bgr
+        | PixelFormat::Rgba
+        | PixelFormat::Rgb
+        | PixelFormat::I444
+        | PixelFormat::I422
+        | PixelFormat::I420
+        | PixelFormat::Nv12 => {}
+        _ => return Err(ErrorKind::InvalidOperation),
+    }
+
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if !pixel_format::is_compatible(pixel_format, width, height, last_plane) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let src_layouts = crate::get_plane_layouts(format, width, height, last_plane, src_strides)?;
+    let dst_layouts = crate::get_plane_layouts(format, width, height, last_plane, dst_strides)?;
+
+    let src_sizes: Vec<usize> = src_buffers.iter().map(|buffer| buffer.len()).collect();
+    let dst_sizes: Vec<usize> = dst_buffers.iter().map(|buffer| buffer.len()).collect();
+    for (src_layout, dst_layout) in src_layouts.iter().zip(dst_layouts.iter()) {
+        check_layout(src_layout, width, height, &src_sizes)?;
+        check_layout(dst_layout, width, height, &dst_sizes)?;
+    }
+
+    for (src_layout, dst_layout) in src_layouts.iter().zip(dst_layouts.iter()) {
+        let row_size = src_layout.depth * ((width >> src_layout.horizontal_shift) as usize);
+        let src = &src_buffers[src_layout.buffer][src_layout.offset..];
+        let dst = &mut dst_buffers[dst_layout.buffer][dst_layout.offset..];
+        for row in 0..((height >> src_layout.vertical_shift) as usize) {
+            accumulator(
+                &src[row * src_layout.stride..][..row_size],
+                &mut dst[row * dst_layout.stride..][..row_size],
+                alpha,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Luma weights of red and blue of the supported YCbCr color spaces
+fn get_luma_weights(color_space: ColorSpace) -> Option<(f32, f32)> {
+    match color_space {
+        ColorSpace::Bt601 => Some((0.299, 0.114)),
+        ColorSpace::Bt709 => Some((0.2126, 0.0722)),
+        _ => None,
+    }
+}
+
+/// Converts an rgb pixel to limited range YCbCr
+fn rgb_to_ycbcr(rgb: [u8; 3], (kr, kb): (f32, f32)) -> [u32; 3] {
+    let r = f32::from(rgb[0]);
+    let g = f32::from(rgb[1]);
+    let b = f32::from(rgb[2]);
+    let l = kr * r + (1.0 - kr - kb) * g + kb * b;
+
+    [
+        (16.5 + l * 219.0 / 255.0) as u32,
+        (128.5 + (b - l) * 112.0 / (255.0 * (1.0 - kb))) as u32,
+        (128.5 + (r - l) * 112.0 / (255.0 * (1.0 - kr))) as u32,
+    ]
+}
+
+/// Checks that a plane layout fits the image buffers
+pub(crate) fn check_layout(
+    layout: &crate::PlaneLayout,
+    width: u32,
+    height: u32,
+    buffer_sizes: &[usize],
+) -> Result<(), ErrorKind> {
+    let row_size = layout.depth * ((width >> layout.horizontal_shift) as usize);
+    if layout.stride < row_size {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let rows = (height >> layout.vertical_shift) as usize;
+    if rows == 0 {
+        return Ok(());
+    }
+
+    let end = layout
+        .stride
+        .checked_mul(rows - 1)
+        .and_then(|size| size.checked_add(row_size))
+        .and_then(|size| size.checked_add(layout.offset))
+        .ok_or(ErrorKind::NotEnoughData)?;
+    match buffer_sizes.get(layout.buffer) {
+        Some(&size) if end <= size => Ok(()),
+        _ => Err(ErrorKind::NotEnoughData),
+    }
+}
+
+/// Composites a sprite into a YCbCr frame
+///
+/// # Arguments
+/// * `x` - Horizontal position of the top left corner of the sprite in the frame,
+///   it can be negative
+/// * `y` - Vertical position of the top left corner of the sprite in the frame,
+///   it can be negative
+/// * `sprite_width` - Width of the sprite in pixels
+/// * `sprite_height` - Height of the sprite in pixels
+/// * `sprite_format` - Image format of the sprite
+/// * `sprite_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each sprite plane
+/// * `sprite_buffers` - An array of image buffers in each sprite plane
+/// * `width` - Width of the frame in pixels
+/// * `height` - Height of the frame in pixels
+/// * `format` - Image format of the frame
+/// * `strides` - An array of distances in bytes between starts of consecutive lines
+///   in each frame plane
+/// * `buffers` - An array of image buffers in each frame plane
+///
+/// # Note
+/// The sprite has to be `Argb`, `Bgra` or `Rgba` with straight alpha. The frame has to be
+/// `I444`, `I422`, `I420` or `Nv12`, either `Bt601` or `Bt709`. Parts of the sprite falling
+/// outside the frame are ignored.
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if a pixel format is not compatible with its color space
+///
+/// * [`InvalidValue`] if the frame size violates the [`size constraints`] that might by
+///   imposed by its pixel format
+///
+/// * [`InvalidValue`] if an image format has a number of planes which is not compatible
+///   with its pixel format
+///
+/// * [`InvalidValue`] if one or more strides are smaller than the plane line size
+///
+/// * [`InvalidOperation`] if the sprite or the frame formats are not supported
+///
+/// * [`NotEnoughData`] if a strides array is not `None` and its length is less than the
+///   image format number of planes
+///
+/// * [`NotEnoughData`] if one or more buffers does not provide enough data
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+/// [`size constraints`]: ../struct.ImageFormat.html#note
+#[allow(clippy::too_many_arguments)]
+pub fn overlay_sprite(
+    x: i32,
+    y: i32,
+    sprite_width: u32,
+    sprite_height: u32,
+    sprite_format: &ImageFormat,
+    sprite_strides: Option<&[usize]>,
+    sprite_buffers: &[&[u8]],
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+    buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "overlay_sprite",
+        x,
+        y,
+        sprite_width,
+        sprite_height,
+        width,
+        height,
+        format = ?format.pixel_format
+    );
+
+    // Sprite
+    let [r, g, b, a] =
+        get_offsets(sprite_format.pixel_format).ok_or(ErrorKind::InvalidOperation)?;
+    let sprite_last_plane = sprite_format.num_planes.wrapping_sub(1);
+    if !dispatcher::get_color_space_mode(sprite_format.color_space as u32)
+        || !pixel_format::is_compatible(
+            sprite_format.pixel_format as u32,
+            sprite_width,
+            sprite_height,
+            sprite_last_plane,
+        )
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let sprite_layouts = crate::get_plane_layouts(
+        sprite_format,
+        sprite_width,
+        sprite_height,
+        sprite_last_plane,
+        sprite_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+    )?;
+    let sprite_sizes: Vec<usize> = sprite_buffers.iter().map(|buffer| buffer.len()).collect();
+    check_layout(
+        &sprite_layouts[0],
+        sprite_width,
+        sprite_height,
+        &sprite_sizes,
+    )?;
+
+    // Frame
+    let pixel_format = format.pixel_format as u32;
+    if dispatcher::get_pixel_format_mode(pixel_format)
+        ^ dispatcher::get_color_space_mode(format.color_space as u32)
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    match format.pixel_format {
+        PixelFormat::I444 | PixelFormat::I422 | PixelFormat::I420 | PixelFormat::Nv12 => {}
+        _ => return Err(ErrorKind::InvalidOperation),
+    }
+
+    let weights = get_luma_weights(format.color_space).ok_or(ErrorKind::InvalidOperation)?;
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if !pixel_format::is_compatible(pixel_format, width, height, last_plane) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let layouts = crate::get_plane_layouts(
+        format,
+        width,
+        height,
+        last_plane,
+        strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+    )?;
+    let sizes: Vec<usize> = buffers.iter().map(|buffer| buffer.len()).collect();
+    for layout in layouts.iter() {
+        check_layout(layout, width, height, &sizes)?;
+    }
+
+    // Area of the frame covered by the sprite
+    let x0 = i64::from(x).max(0);
+    let y0 = i64::from(y).max(0);
+    let x1 = (i64::from(x) + i64::from(sprite_width)).min(i64::from(width));
+    let y1 = (i64::from(y) + i64::from(sprite_height)).min(i64::from(height));
+    if x0 >= x1 || y0 >= y1 {
+        return Ok(());
+    }
+
+    let sprite = &sprite_layouts[0];
+    let sprite_buffer = &sprite_buffers[sprite.buffer][sprite.offset..];
+
+    // Sprite pixel covering a frame position, as (YCbCr, alpha)
+    let sample = |px: i64, py: i64| -> Option<([u32; 3], u32)> {
+        if px < i64::from(x) || py < i64::from(y) || px >= x1 || py >= y1 {
+            return None;
+        }
+
+        let sx = (px - i64::from(x)) as usize;
+        let sy = (py - i64::from(y)) as usize;
+        let pixel = &sprite_buffer[sy * sprite.stride + 4 * sx..][..4];
+        Some((
+            rgb_to_ycbcr([pixel[r], pixel[g], pixel[b]], weights),
+            u32::from(pixel[a]),
+        ))
+    };
+
+    let luma = &layouts[0];
+    for py in y0..y1 {
+        let row = luma.offset + (py as usize) * luma.stride;
+        for px in x0..x1 {
+            if let Some((ycbcr, alpha)) = sample(px, py) {
+                let value = &mut buffers[luma.buffer][row + px as usize];
+                *value = ((ycbcr[0] * alpha + u32::from(*value) * (255 - alpha) + 127) / 255) as u8;
+            }
+        }
+    }
+
+    // Each chroma sample is shared by a block of pixels
+    let (cb, cr) = (&layouts[1], &layouts[layouts.len() - 1]);
+    let hs = cb.horizontal_shift;
+    let vs = cb.vertical_shift;
+    let block_size = 255 << (hs + vs);
+    for cy in (y0 >> vs)..=((y1 - 1) >> vs) {
+        for cx in (x0 >> hs)..=((x1 - 1) >> hs) {
+            let mut coverage = 0;
+            let mut sums = [0, 0];
+            for py in (cy << vs)..((cy + 1) << vs) {
+                for px in (cx << hs)..((cx + 1) << hs) {
+                    if let Some((ycbcr, alpha)) = sample(px, py) {
+                        coverage += alpha;
+                        sums[0] += ycbcr[1] * alpha;
+                        sums[1] += ycbcr[2] * alpha;
+                    }
+                }
+            }
+
+            if coverage == 0 {
+                continue;
+            }
+
+            // Interleaved chroma planes store cr right after cb
+            let cr_column = if cb.depth == 2 { 1 } else { 0 };
+            for &(layout, column, sum) in [(cb, 0, sums[0]), (cr, cr_column, sums[1])].iter() {
+                let index = layout.offset
+                    + (cy as usize) * layout.stride
+                    + (cx as usize) * layout.depth
+                    + column;
+                let value = &mut buffers[layout.buffer][index];
+                *value = ((sum + u32::from(*value) * (block_size - coverage) + block_size / 2)
+                    / block_size) as u8;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A color to turn into transparency
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChromaKey {
+    /// Key color, as red, green and blue
+    pub color: [u8; 3],
+    /// Largest channel difference from the key color of fully transparent pixels
+    pub tolerance: u8,
+    /// Width of the channel difference range over which alpha ramps up to opaque,
+    /// beyond the tolerance. Zero gives a hard edge
+    pub softness: u8,
+}
+
+impl ChromaKey {
+    fn alpha(&self, rgb: [u8; 3]) -> u32 {
+        let distance = rgb
+            .iter()
+            .zip(self.color.iter())
+            .map(|(&x, &y)| x.abs_diff(y))
+            .max()
+            .unwrap_or(0);
+
+        if distance <= self.tolerance {
+            0
+        } else {
+            let ramp = u32::from(distance - self.tolerance);
+            let softness = u32::from(self.softness);
+            if ramp >= softness {
+                255
+            } else {
+                (255 * ramp + softness / 2) / softness
+            }
+        }
+    }
+}
+
+/// Bytes per pixel and byte offsets of red, green, blue and alpha of the 8-bit rgb formats
+fn get_rgb_layout(pixel_format: PixelFormat) -> Option<(usize, [usize; 3], Option<usize>)> {
+    match pixel_format {
+        PixelFormat::Argb => Some((4, [1, 2, 3], Some(0))),
+        PixelFormat::Bgra => Some((4, [2, 1, 0], Some(3))),
+        PixelFormat::Bgr => Some((3, [2, 1, 0], None)),
+        PixelFormat::Rgba => Some((4, [0, 1, 2], Some(3))),
+        PixelFormat::Rgb => Some((3, [0, 1, 2], None)),
+        _ => None,
+    }
+}
+
+/// Turns the pixels matching a key color into transparency
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source image plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination image plane
+/// * `key` - Color to turn into transparency
+///
+/// # Note
+/// The source can be any 8-bit rgb format, the destination has to be `Argb`, `Bgra` or
+/// `Rgba`. Color samples are copied unchanged, source alpha (if any) is multiplied by the
+/// key coverage.
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if a color space is not `ColorSpace::Lrgb`
+///
+/// * [`InvalidValue`] if an image format has a number of planes which is not compatible
+///   with its pixel format
+///
+/// * [`InvalidValue`] if one or more strides are smaller than the line size
+///
+/// * [`InvalidOperation`] if the source or destination pixel formats are not supported
+///
+/// * [`NotEnoughData`] if the strides arrays are not `None` and they are empty
+///
+/// * [`NotEnoughData`] if one or more buffers does not provide enough data
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+#[allow(clippy::too_many_arguments)]
+pub fn chroma_key(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    key: &ChromaKey,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "chroma_key",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    let (src_bpp, src_offsets, src_alpha) =
+        get_rgb_layout(src_format.pixel_format).ok_or(ErrorKind::InvalidOperation)?;
+    let dst_offsets = get_offsets(dst_format.pixel_format).ok_or(ErrorKind::InvalidOperation)?;
+
+    let mut layouts = Vec::with_capacity(2);
+    for (format, strides, sizes) in [
+        (
+            src_format,
+            src_strides,
+            src_buffers
+                .iter()
+                .map(|buffer| buffer.len())
+                .collect::<Vec<usize>>(),
+        ),
+        (
+            dst_format,
+            dst_strides,
+            dst_buffers.iter().map(|buffer| buffer.len()).collect(),
+        ),
+    ]
+    .iter()
+    {
+        let last_plane = format.num_planes.wrapping_sub(1);
+        if !dispatcher::get_color_space_mode(format.color_space as u32)
+            || !pixel_format::is_compatible(format.pixel_format as u32, width, height, last_plane)
+        {
+            return Err(ErrorKind::InvalidValue);
+        }
+
+        let layout = crate::get_plane_layouts(
+            format,
+            width,
+            height,
+            last_plane,
+            strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+        )?;
+        check_layout(&layout[0], width, height, sizes)?;
+        layouts.push(layout);
+    }
+
+    let src_stride = layouts[0][0].stride;
+    let dst_stride = layouts[1][0].stride;
+    let w = width as usize;
+    let [r, g, b, a] = dst_offsets;
+    for line in 0..(height as usize) {
+        let src = &src_buffers[0][line * src_stride..][..src_bpp * w];
+        let dst = &mut dst_buffers[0][line * dst_stride..][..4 * w];
+        for (s, d) in src.chunks_exact(src_bpp).zip(dst.chunks_exact_mut(4)) {
+            let rgb = [s[src_offsets[0]], s[src_offsets[1]], s[src_offsets[2]]];
+            let coverage = key.alpha(rgb);
+            let alpha = match src_alpha {
+                Some(offset) => (u32::from(s[offset]) * coverage + 127) / 255,
+                None => coverage,
+            };
+
+            d[r] = rgb[0];
+            d[g] = rgb[1];
+            d[b] = rgb[2];
+            d[a] = alpha as u8;
+        }
+    }
+
+    Ok(())
+}
+
+/// How a watermark changes the luma it covers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StampMode {
+    /// Luma is scaled towards black by the mask coverage, burning in a dark mark
+    Multiply,
+    /// The given amount, scaled by the mask coverage, is added to luma, burning in a
+    /// bright mark
+    Add(u8),
+    /// Luma is blended with the given value by the mask coverage, e.g. to hide a region
+    /// behind a flat gray
+    Blend(u8),
+}
+
+impl StampMode {
+    fn apply(self, value: u8, coverage: u8, (black, white): (i32, i32)) -> u8 {
+        let value = i32::from(value);
+        let coverage = i32::from(coverage);
+        let stamped = match self {
+            StampMode::Multiply => black + ((value - black).max(0) * (255 - coverage) + 127) / 255,
+            StampMode::Add(amount) => value + (i32::from(amount) * coverage + 127) / 255,
+            StampMode::Blend(target) => {
+                (value * (255 - coverage) + i32::from(target) * coverage + 127) / 255
+            }
+        };
+
+        stamped.max(black).min(white) as u8
+    }
+}
+
+/// A grayscale mask to stamp into the luma of a frame
+#[derive(Clone, Copy, Debug)]
+pub struct Watermark<'a> {
+    /// Horizontal position of the top left corner of the mask in the frame,
+    /// it can be negative
+    pub x: i32,
+    /// Vertical position of the top left corner of the mask in the frame,
+    /// it can be negative
+    pub y: i32,
+    /// Width of the mask in pixels
+    pub width: u32,
+    /// Height of the mask in pixels
+    pub height: u32,
+    /// Distance in bytes between starts of consecutive lines of the mask, or
+    /// [`STRIDE_AUTO`]
+    ///
+    /// [`STRIDE_AUTO`]: ../constant.STRIDE_AUTO.html
+    pub stride: usize,
+    /// Coverage of each pixel, from 0 (luma is unchanged) to 255 (the stamp is fully
+    /// applied)
+    pub mask: &'a [u8],
+    /// How the mask changes the luma it covers
+    pub mode: StampMode,
+}
+
+impl<'a> Watermark<'a> {
+    /// Returns the stride of the mask, after checking that the mask fits its buffer
+    fn get_stride(&self) -> Result<usize, ErrorKind> {
+        let row_size = self.width as usize;
+        let stride = match self.stride {
+            STRIDE_AUTO => row_size,
+            stride if stride < row_size => return Err(ErrorKind::InvalidValue),
+            stride => stride,
+        };
+
+        if row_size == 0 || self.height == 0 {
+            return Ok(stride);
+        }
+
+        let end = stride
+            .checked_mul((self.height - 1) as usize)
+            .and_then(|size| size.checked_add(row_size))
+            .ok_or(ErrorKind::NotEnoughData)?;
+        if end > self.mask.len() {
+            return Err(ErrorKind::NotEnoughData);
+        }
+
+        Ok(stride)
+    }
+
+    /// Stamps the mask into the frame lines `first_line..last_line`, `luma` starting at
+    /// the first of them
+    #[allow(clippy::too_many_arguments)]
+    fn stamp(
+        &self,
+        luma: &mut [u8],
+        luma_stride: usize,
+        frame_width: u32,
+        first_line: usize,
+        last_line: usize,
+        mask_stride: usize,
+        range: (i32, i32),
+    ) {
+        let x0 = i64::from(self.x).max(0);
+        let y0 = i64::from(self.y).max(first_line as i64);
+        let x1 = (i64::from(self.x) + i64::from(self.width)).min(i64::from(frame_width));
+        let y1 = (i64::from(self.y) + i64::from(self.height)).min(last_line as i64);
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+
+        let columns = (x1 - x0) as usize;
+        let mask_column = (x0 - i64::from(self.x)) as usize;
+        for y in y0..y1 {
+            let mask_line = (y - i64::from(self.y)) as usize;
+            let mask = &self.mask[mask_line * mask_stride + mask_column..][..columns];
+            let line = (y as usize - first_line) * luma_stride + x0 as usize;
+            for (value, &coverage) in luma[line..][..columns].iter_mut().zip(mask.iter()) {
+                *value = self.mode.apply(*value, coverage, range);
+            }
+        }
+    }
+}
+
+/// Black and white luma levels of the supported YCbCr color spaces
+fn get_luma_range(color_space: ColorSpace) -> (i32, i32) {
+    match color_space {
+        ColorSpace::Jfif => (0, 255),
+        _ => (16, 235),
+    }
+}
+
+/// Checks that a frame can be watermarked
+fn check_watermark_format(format: &ImageFormat) -> Result<(), ErrorKind> {
+    if dispatcher::get_pixel_format_mode(format.pixel_format as u32)
+        ^ dispatcher::get_color_space_mode(format.color_space as u32)
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    match format.pixel_format {
+        PixelFormat::I444 | PixelFormat::I422 | PixelFormat::I420 | PixelFormat::Nv12 => Ok(()),
+        _ => Err(ErrorKind::InvalidOperation),
+    }
+}
+
+/// Stamps a watermark into the luma of a YCbCr frame, in place
+///
+/// # Arguments
+/// * `width` - Width of the frame in pixels
+/// * `height` - Height of the frame in pixels
+/// * `format` - Image format of the frame
+/// * `strides` - An array of distances in bytes between starts of consecutive lines
+///   in each frame plane
+/// * `buffers` - An array of image buffers in each frame plane
+/// * `watermark` - The mask to stamp and its position in the frame
+///
+/// # Note
+/// The frame has to be `I444`, `I422`, `I420` or `Nv12`. Parts of the mask falling
+/// outside the frame are ignored.
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the color space is not a YCbCr one, the frame size does not
+///   match the pixel format or the number of planes is not supported
+///
+/// * [`InvalidValue`] if the stride of the mask is smaller than its width
+///
+/// * [`InvalidOperation`] if the pixel format is not supported
+///
+/// * [`NotEnoughData`] if the strides array is shorter than the number of planes, or
+///   one or more strides are smaller than the line size
+///
+/// * [`NotEnoughData`] if the luma buffer or the mask do not provide enough data
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+pub fn stamp_watermark(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+    buffers: &mut [&mut [u8]],
+    watermark: &Watermark,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "stamp_watermark",
+        width,
+        height,
+        format = ?format.pixel_format
+    );
+
+    check_watermark_format(format)?;
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if !pixel_format::is_compatible(format.pixel_format as u32, width, height, last_plane) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let layouts = crate::get_plane_layouts(
+        format,
+        width,
+        height,
+        last_plane,
+        strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+    )?;
+    let sizes: Vec<usize> = buffers.iter().map(|buffer| buffer.len()).collect();
+    let luma = &layouts[0];
+    check_layout(luma, width, height, &sizes)?;
+    let mask_stride = watermark.get_stride()?;
+
+    watermark.stamp(
+        &mut buffers[luma.buffer][luma.offset..],
+        luma.stride,
+        width,
+        0,
+        height as usize,
+        mask_stride,
+        get_luma_range(format.color_space),
+    );
+
+    Ok(())
+}
+
+/// Converts an image to YCbCr, stamping a watermark into the luma of the result
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source image plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination image plane
+/// * `watermark` - The mask to stamp and its position in the destination image
+///
+/// # Note
+/// The destination image has to be `I444`, `I422`, `I420` or `Nv12`. Parts of the mask
+/// falling outside the image are ignored.
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the stride of the mask is smaller than its width
+///
+/// * [`InvalidOperation`] if the destination pixel format is not supported
+///
+/// * [`NotEnoughData`] if the mask does not provide enough data
+///
+/// * All the errors reported by [`convert_image`], except that conversions are never routed
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+/// [`convert_image`]: ../fn.convert_image.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_image_watermarked(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    watermark: &Watermark,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_image_watermarked",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    let (converter, last_src_plane, last_dst_plane) =
+        crate::get_converter(width, height, src_format, dst_format)?;
+
+    check_watermark_format(dst_format)?;
+    let mask_stride = watermark.get_stride()?;
+    if crate::is_empty(width, height) {
+        return Ok(());
+    }
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let src_layouts =
+        crate::get_plane_layouts(src_format, width, height, last_src_plane, src_strides)?;
+    let dst_layouts =
+        crate::get_plane_layouts(dst_format, width, height, last_dst_plane, dst_strides)?;
+
+    let range = get_luma_range(dst_format.color_space);
+
+    crate::convert_in_bands(
+        &src_layouts,
+        src_buffers,
+        dst_format,
+        &dst_layouts,
+        dst_buffers,
+        crate::get_bands(height as usize, COMPOSITE_BAND_HEIGHT),
+        |mut band| {
+            band.convert(converter, width)?;
+            watermark.stamp(
+                band.dst_buffers[0],
+                band.dst_strides[0],
+                width,
+                band.first_line,
+                band.last_line,
+                mask_stride,
+                range,
+            );
+
+            Ok(())
+        },
+    )
+}
@@ -0,0 +1,258 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Conversion contexts, for applications that manage their own memory.
+//!
+//! A [`Context`] holds a copy of the conversion functions selected by [`initialize`] and
+//! the scratch memory of routed conversions, which go through intermediate images. The
+//! scratch memory comes from the allocator of the context, for example an arena or a
+//! per-thread pool, and is kept for the following conversions: once it is large enough,
+//! converting with a context does not allocate image-sized buffers from the global heap.
+//! Use [`Context::reserve`] to allocate it up front.
+//!
+//! Allocators implement the [`Allocator`] trait of the `allocator-api2` crate, which is
+//! re-exported by this module.
+//!
+//! Converters registered with [`register_converter`] after a context is created are not
+//! used by the context.
+//!
+//! This module requires the `context` feature.
+//!
+//! [`initialize`]: ../fn.initialize.html
+//! [`register_converter`]: ../fn.register_converter.html
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::context::Context;
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn convert() -> Result<(), Box<dyn error::Error>> {
+//!     dcp::initialize();
+//!
+//!     const WIDTH: u32 = 640;
+//!     const HEIGHT: u32 = 480;
+//!
+//!     let src_format = ImageFormat {
+//!         pixel_format: PixelFormat::Rgb,
+//!         color_space: ColorSpace::Lrgb,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let dst_format = ImageFormat {
+//!         pixel_format: PixelFormat::Nv12,
+//!         color_space: ColorSpace::Bt601,
+//!         num_planes: 2,
+//!     };
+//!
+//!     let mut context = Context::new()?;
+//!     context.reserve(WIDTH, HEIGHT, &src_format, &dst_format)?;
+//!
+//!     let src_buffer = vec![0u8; 3 * (WIDTH as usize) * (HEIGHT as usize)];
+//!     let mut y_buffer = vec![0u8; (WIDTH as usize) * (HEIGHT as usize)];
+//!     let mut uv_buffer = vec![0u8; (WIDTH as usize) * (HEIGHT as usize) / 2];
+//!
+//!     // Rgb to Nv12 is routed through Bgra, in a band of the scratch memory
+//!     context.convert_image(
+//!         WIDTH,
+//!         HEIGHT,
+//!         &src_format,
+//!         None,
+//!         &[&src_buffer],
+//!         &dst_format,
+//!         None,
+//!         &mut [&mut y_buffer, &mut uv_buffer],
+//!     )?;
+//!
+//!     Ok(())
+//! }
+//! ```
+use crate::graph;
+use crate::pixel_format;
+use crate::trace_span;
+use crate::{ConverterTable, ErrorKind, ImageFormat};
+use allocator_api2::vec::Vec;
+
+pub use allocator_api2::alloc::{AllocError, Allocator, Global};
+
+/// Conversion functions and scratch memory, allocated with `A`
+pub struct Context<A: Allocator = Global> {
+    converters: ConverterTable,
+    scratch: Vec<u8, A>,
+}
+
+impl Context {
+    /// Creates a context whose scratch memory is allocated from the global heap
+    ///
+    /// # Errors
+    ///
+    /// * [`NotInitialized`] if the library was not initialized before
+    ///
+    /// [`NotInitialized`]: ../enum.ErrorKind.html#variant.NotInitialized
+    pub fn new() -> Result<Self, ErrorKind> {
+        Context::new_in(Global)
+    }
+}
+
+impl<A: Allocator> Context<A> {
+    /// Creates a context whose scratch memory is allocated with `alloc`
+    ///
+    /// No memory is allocated until a routed conversion needs it.
+    ///
+    /// # Errors
+    ///
+    /// * [`NotInitialized`] if the library was not initialized before
+    ///
+    /// [`NotInitialized`]: ../enum.ErrorKind.html#variant.NotInitialized
+    pub fn new_in(alloc: A) -> Result<Self, ErrorKind> {
+        Ok(Context {
+            converters: *crate::get_converters()?,
+            scratch: Vec::new_in(alloc),
+        })
+    }
+
+    /// Returns the size, in bytes, of the scratch memory allocated so far
+    pub fn scratch_size(&self) -> usize {
+        self.scratch.len()
+    }
+
+    /// Allocates the scratch memory needed to convert images of the given size and formats,
+    /// so that the following conversions with the same or smaller parameters do not
+    /// allocate it
+    ///
+    /// # Errors
+    ///
+    /// * All the errors reported by [`convert_image`] when validating the image formats
+    ///
+    /// [`convert_image`]: ../fn.convert_image.html
+    pub fn reserve(
+        &mut self,
+        width: u32,
+        height: u32,
+        src_format: &ImageFormat,
+        dst_format: &ImageFormat,
+    ) -> Result<(), ErrorKind> {
+        match crate::find_converter(&self.converters, width, height, src_format, dst_format) {
+            Ok(_) => Ok(()),
+            Err(ErrorKind::InvalidOperation) => {
+                let route =
+                    graph::find_route(&self.converters, width, height, src_format, dst_format)?;
+                self.grow(graph::get_scratch_size(width, height, &route)?);
+                Ok(())
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Converts from a color space to another one, like [`convert_image`], taking the scratch
+    /// memory of routed conversions from the context
+    ///
+    /// # Arguments
+    /// * `width` - Width of the image to convert in pixels
+    /// * `height` - Height of the image to convert in pixels
+    /// * `src_format` - Source image format
+    /// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+    ///   in each source image planes
+    /// * `src_buffers` - An array of image buffers in each source color plane
+    /// * `dst_format` - Destination image format
+    /// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+    ///   in each destination image planes
+    /// * `dst_buffers` - An array of image buffers in each destination color plane
+    ///
+    /// # Errors
+    ///
+    /// * All the errors reported by [`convert_image`], except [`NotInitialized`]
+    ///
+    /// [`convert_image`]: ../fn.convert_image.html
+    /// [`NotInitialized`]: ../enum.ErrorKind.html#variant.NotInitialized
+    #[allow(clippy::too_many_arguments)]
+    pub fn convert_image(
+        &mut self,
+        width: u32,
+        height: u32,
+        src_format: &ImageFormat,
+        src_strides: Option<&[usize]>,
+        src_buffers: &[&[u8]],
+        dst_format: &ImageFormat,
+        dst_strides: Option<&[usize]>,
+        dst_buffers: &mut [&mut [u8]],
+    ) -> Result<(), ErrorKind> {
+        let _span = trace_span!(
+            DEBUG,
+            "context_convert_image",
+            width,
+            height,
+            src = ?src_format.pixel_format,
+            dst = ?dst_format.pixel_format
+        );
+
+        let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+        let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+        match crate::find_converter(&self.converters, width, height, src_format, dst_format) {
+            Ok((converter, last_src_plane, _)) => {
+                if crate::is_empty(width, height) {
+                    return Ok(());
+                }
+
+                crate::run_converter(
+                    converter,
+                    width,
+                    height,
+                    0,
+                    last_src_plane,
+                    src_strides,
+                    src_buffers,
+                    dst_format,
+                    dst_strides,
+                    dst_buffers,
+                )?;
+            }
+            Err(ErrorKind::InvalidOperation) => {
+                let route =
+                    graph::find_route(&self.converters, width, height, src_format, dst_format)?;
+                if crate::is_empty(width, height) {
+                    return Ok(());
+                }
+
+                let scratch_size = graph::get_scratch_size(width, height, &route)?;
+                self.grow(scratch_size);
+                graph::convert_routed(
+                    &self.converters,
+                    &mut self.scratch[..scratch_size],
+                    width,
+                    height,
+                    &route,
+                    src_format,
+                    src_strides,
+                    src_buffers,
+                    dst_format,
+                    dst_strides,
+                    dst_buffers,
+                )?;
+            }
+            Err(error) => return Err(error),
+        }
+
+        Ok(())
+    }
+
+    fn grow(&mut self, size: usize) {
+        if size > self.scratch.len() {
+            self.scratch.resize(size, 0);
+        }
+    }
+}
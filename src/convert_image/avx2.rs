@@ -14,8 +14,33 @@
 // OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
 // SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 
+// Kernels take the arguments of the conversion dispatcher, which are more than seven
+#![allow(clippy::too_many_arguments)]
+// Vectors are loaded and stored with the unaligned intrinsics, through pointers to the
+// vector types
+#![allow(clippy::cast_ptr_alignment)]
+
 use crate::convert_image::common::*;
 use crate::convert_image::sse2;
+use core::ptr::{read_unaligned, write_unaligned};
+
+// No vectorized implementation yet
+pub use crate::convert_image::sse2::{
+    argb_lrgb_i420_jfif, argb_lrgb_nv12_jfif, argb_lrgb_yuvf32planar_bt601,
+    argb_lrgb_yuvf32planar_bt709, bgr48_lrgb_bgra_lrgb, bgr48_lrgb_p010_bt601,
+    bgr48_lrgb_p010_bt709, bgr_lrgb_i420_jfif, bgr_lrgb_nv12_jfif, bgr_lrgb_yuvf32planar_bt601,
+    bgr_lrgb_yuvf32planar_bt709, bgra_lrgb_i420_jfif, bgra_lrgb_nv12_jfif,
+    bgra_lrgb_yuvf32planar_bt601, bgra_lrgb_yuvf32planar_bt709, i420_bt601_i420_bt709,
+    i420_bt601_rgbaf32_lrgb, i420_bt601_rgbf32_lrgb, i420_bt709_i420_bt601,
+    i420_bt709_rgbaf32_lrgb, i420_bt709_rgbf32_lrgb, i420_jfif_bgra_lrgb, i444_bt601_i444_bt709,
+    i444_bt601_rgbaf32_lrgb, i444_bt601_rgbf32_lrgb, i444_bt709_i444_bt601,
+    i444_bt709_rgbaf32_lrgb, i444_bt709_rgbf32_lrgb, nv12_bt2020pq_p010_bt2020pq,
+    nv12_bt601_nv12_bt709, nv12_bt601_p010_bt601, nv12_bt601_rgbaf32_lrgb, nv12_bt601_rgbf32_lrgb,
+    nv12_bt709_nv12_bt601, nv12_bt709_p010_bt709, nv12_bt709_rgbaf32_lrgb, nv12_bt709_rgbf32_lrgb,
+    nv12_jfif_bgra_lrgb, p010_bt2020pq_nv12_bt2020pq, p010_bt2020pq_rgbaf16_lrgb,
+    p010_bt601_nv12_bt601, p010_bt709_nv12_bt709, rgb48_lrgb_bgra_lrgb, rgb48_lrgb_p010_bt601,
+    rgb48_lrgb_p010_bt709, rgbaf16_lrgb_bgra_lrgb,
+};
 
 #[cfg(target_arch = "x86")]
 use core::arch::x86::{
@@ -29,7 +54,12 @@ use core::arch::x86::{
     _mm256_srli_epi32, _mm256_srli_si256, _mm256_storeu_si256, _mm256_sub_epi16,
     _mm256_unpackhi_epi16, _mm256_unpackhi_epi8, _mm256_unpacklo_epi16, _mm256_unpacklo_epi32,
     _mm256_unpacklo_epi64, _mm256_unpacklo_epi8, _mm_loadu_si128, _mm_prefetch, _mm_setzero_si128,
-    _mm_storeu_si128, _MM_HINT_NTA,
+    _mm_storeu_si128, _MM_HINT_NTA, _mm256_add_epi64, _mm256_max_epu8, _mm256_sad_epu8,
+    _mm256_subs_epu8, _mm256_mullo_epi16, _mm256_shufflehi_epi16, _mm256_shufflelo_epi16,
+    _mm256_and_si256, _mm256_blendv_epi8, _mm256_cmpeq_epi8, _mm256_set1_epi8, _mm256_adds_epu8,
+    __m256, _mm256_add_ps, _mm256_andnot_ps, _mm256_and_ps, _mm256_blendv_ps, _mm256_castps_si256,
+    _mm256_cmp_ps, _mm256_i32gather_ps, _mm256_loadu_ps, _mm256_mul_ps, _mm256_or_ps,
+    _mm256_storeu_ps, _mm256_sub_ps, _CMP_GT_OQ,
 };
 
 #[cfg(target_arch = "x86_64")]
@@ -44,7 +74,12 @@ use core::arch::x86_64::{
     _mm256_srli_epi32, _mm256_srli_si256, _mm256_storeu_si256, _mm256_sub_epi16,
     _mm256_unpackhi_epi16, _mm256_unpackhi_epi8, _mm256_unpacklo_epi16, _mm256_unpacklo_epi32,
     _mm256_unpacklo_epi64, _mm256_unpacklo_epi8, _mm_loadu_si128, _mm_prefetch, _mm_setzero_si128,
-    _mm_storeu_si128, _MM_HINT_NTA,
+    _mm_storeu_si128, _MM_HINT_NTA, _mm256_add_epi64, _mm256_max_epu8, _mm256_sad_epu8,
+    _mm256_subs_epu8, _mm256_mullo_epi16, _mm256_shufflehi_epi16, _mm256_shufflelo_epi16,
+    _mm256_and_si256, _mm256_blendv_epi8, _mm256_cmpeq_epi8, _mm256_set1_epi8, _mm256_adds_epu8,
+    __m256, _mm256_add_ps, _mm256_andnot_ps, _mm256_and_ps, _mm256_blendv_ps, _mm256_castps_si256,
+    _mm256_cmp_ps, _mm256_i32gather_ps, _mm256_loadu_ps, _mm256_mul_ps, _mm256_or_ps,
+    _mm256_storeu_ps, _mm256_sub_ps, _CMP_GT_OQ,
 };
 
 const LANE_COUNT: usize = 32;
@@ -55,6 +90,7 @@ const YUV_TO_LRGB_WAVES: usize = LANE_COUNT / YUV_TO_LRGB_WG_SIZE;
 
 const PACK_LO_DQWORD_2X256: i32 = 0x20;
 const PACK_HI_DQWORD_2X256: i32 = 0x31;
+const BROADCAST_WORD_3: i32 = 0xFF;
 
 macro_rules! zero {
     () => {
@@ -62,6 +98,13 @@ macro_rules! zero {
     };
 }
 
+macro_rules! div_255_16x {
+    ($x:expr) => {{
+        let x = _mm256_add_epi16($x, _mm256_set1_epi16(128));
+        _mm256_srli_epi16(_mm256_add_epi16(x, _mm256_srli_epi16(x, 8)), 8)
+    }};
+}
+
 macro_rules! pack_lo_dword_2x128 {
     () => {
         _mm256_set_epi32(0, 0, 0, 0, 0, 0, 4, 0)
@@ -122,10 +165,30 @@ const BACKWARD_WEIGHTS: [[i16; 8]; Colorimetry::Length as usize] = [
     ],
 ];
 
+/// Weights of full range rgb to ycbcr, as libjpeg computes it.
+/// The weight of blue in cb and of red in cr is one half, and it is applied with a shift.
+const JFIF_FORWARD_WEIGHTS: [i32; 6] = [
+    i32x2_to_i32(XG_JFIF - SHORT_HALF, XR_JFIF),
+    i32x2_to_i32(SHORT_HALF, XB_JFIF),
+    i32x2_to_i32(ZG_JFIF, 0),
+    i32x2_to_i32(YG_JFIF, YR_JFIF),
+    i32x2_to_i32(0, ZB_JFIF),
+    0,
+];
+
+/// Weights of full range ycbcr to rgb, as libjpeg computes it.
+/// Weights are split into a low and a high byte, applied to chroma and to chroma << 8
+const JFIF_BACKWARD_WEIGHTS: [i32; 4] = [
+    i32x2_to_i32(BCB_JFIF >> 8, BCB_JFIF & 0xFF),
+    i32x2_to_i32(0, -GCB_JFIF),
+    i32x2_to_i32(-(GCR_JFIF >> 8), -(GCR_JFIF & 0xFF)),
+    i32x2_to_i32(RCR_JFIF >> 8, RCR_JFIF & 0xFF),
+];
+
 /// Convert fixed point to int (8-wide)
 macro_rules! fix_to_i32_8x {
     ($fix:expr, $frac_bits:expr) => {
-        _mm256_srai_epi32($fix, $frac_bits);
+        _mm256_srai_epi32($fix, $frac_bits)
     };
 }
 
@@ -245,9 +308,9 @@ unsafe fn unpack_ui8x3_i16x2_8x(image: *const u8, sampler: Sampler) -> (__m256i,
     let line = match sampler {
         Sampler::BgrOverflow => _mm256_set_epi64x(
             0,
-            *(image.offset(16) as *const i64),
-            *(image.offset(8) as *const i64),
-            *(image as *const i64),
+            read_unaligned(image.offset(16) as *const i64),
+            read_unaligned(image.offset(8) as *const i64),
+            read_unaligned(image as *const i64),
         ),
         _ => _mm256_loadu_si256(image as *const __m256i),
     };
@@ -288,7 +351,7 @@ unsafe fn pack_i32_8x(image: *mut u8, red: __m256i) {
     let x = _mm256_packs_epi32(red, red);
     let y = _mm256_packus_epi16(x, x);
     let z = _mm256_permutevar8x32_epi32(y, pack_lo_dword_2x128!());
-    *(image as *mut i64) = _mm256_extract_epi64(z, 0);
+    write_unaligned(image as *mut i64, _mm256_extract_epi64(z, 0));
 }
 
 #[inline(always)]
@@ -386,8 +449,8 @@ unsafe fn lrgb_to_i420_8x(
     let permuted = _mm256_permutevar8x32_epi32(packed_to_16, pack_lo_dword_2x128!());
     let uv_res = _mm256_extract_epi64(permuted, 0) as u64;
 
-    *(u as *mut u32) = uv_res as u32;
-    *(v as *mut u32) = (uv_res >> 32) as u32;
+    write_unaligned(u as *mut u32, uv_res as u32);
+    write_unaligned(v as *mut u32, (uv_res >> 32) as u32);
 }
 
 #[inline(always)]
@@ -400,6 +463,7 @@ unsafe fn lrgb_to_i444_8x(
     y_weights: &[__m256i; 3],
     u_weights: &[__m256i; 3],
     v_weights: &[__m256i; 3],
+    jfif: bool,
 ) {
     let (rg, bg) = unpack_ui8x3_i16x2_8x(rgb, sampler);
     pack_i32_8x(
@@ -407,15 +471,16 @@ unsafe fn lrgb_to_i444_8x(
         fix_to_i32_8x!(affine_transform(rg, bg, y_weights), FIX16),
     );
 
-    pack_i32_8x(
-        u,
-        fix_to_i32_8x!(affine_transform(rg, bg, u_weights), FIX16),
-    );
+    let mut u_fix = affine_transform(rg, bg, u_weights);
+    let mut v_fix = affine_transform(rg, bg, v_weights);
+    if jfif {
+        // Add blue << 15 to cb and red << 15 to cr
+        u_fix = _mm256_add_epi32(u_fix, _mm256_srli_epi32(_mm256_slli_epi32(bg, 16), 1));
+        v_fix = _mm256_add_epi32(v_fix, _mm256_srli_epi32(_mm256_slli_epi32(rg, 16), 1));
+    }
 
-    pack_i32_8x(
-        v,
-        fix_to_i32_8x!(affine_transform(rg, bg, v_weights), FIX16),
-    );
+    pack_i32_8x(u, fix_to_i32_8x!(u_fix, FIX16));
+    pack_i32_8x(v, fix_to_i32_8x!(v_fix, FIX16));
 }
 
 #[inline(always)]
@@ -503,7 +568,11 @@ unsafe fn lrgb_to_yuv_avx2(
 
     let rgb_plane = &src_buffers[0];
 
-    let yuv_planes = split_planes_mut(last_dst_plane, y_stride * line_count, dst_buffers);
+    let yuv_planes = split_planes_mut(
+        last_dst_plane,
+        y_stride.saturating_mul(line_count),
+        dst_buffers,
+    );
     if yuv_planes.is_none() {
         return false;
     }
@@ -904,7 +973,6 @@ unsafe fn bgra_to_rgb_avx2(
     const INPUT_BPP: usize = 4;
 
     let w = width as usize;
-    let h = height as usize;
     let output_stride_diff = if dst_strides[0] == 0 {
         0
     } else {
@@ -916,6 +984,12 @@ unsafe fn bgra_to_rgb_avx2(
         src_strides[0] - (INPUT_BPP * w)
     };
 
+    let (w, h) = collapse_lines(
+        w,
+        height as usize,
+        input_stride_diff == 0 && output_stride_diff == 0,
+    );
+
     let bgra_rgb_mask = _mm256_setr_epi8(
         2, 1, 0, 6, 5, 4, 10, 9, 8, 14, 13, 12, -128, -128, -128, -128, 2, 1, 0, 6, 5, 4, 10, 9, 8,
         14, 13, 12, -128, -128, -128, -128,
@@ -992,7 +1066,7 @@ unsafe fn bgra_to_rgb_avx2(
             let lane2_64 = _mm256_extract_epi64(rgb0, 2);
 
             _mm_storeu_si128(output_buffer.add(obuffer_offset) as *mut __m128i, lane1_128);
-            *(output_buffer.add(obuffer_offset + 16) as *mut i64) = lane2_64;
+            write_unaligned(output_buffer.add(obuffer_offset + 16) as *mut i64, lane2_64);
 
             ibuffer_offset += 4 * BGRA_RGB_ITEMS_PER_ITERATION;
             obuffer_offset += 3 * BGRA_RGB_ITEMS_PER_ITERATION;
@@ -1004,7 +1078,7 @@ unsafe fn bgra_to_rgb_avx2(
         ibuffer_offset = 0;
         obuffer_offset = 0;
 
-        for _ in 0..height {
+        for _ in 0..h {
             for y in limit..w {
                 *output_buffer.add((OUTPUT_BPP * y) + 0 + obuffer_offset) =
                     *input_buffer.add((INPUT_BPP * y) + 2 + ibuffer_offset);
@@ -1104,6 +1178,12 @@ unsafe fn rgb_to_bgra_avx2(
         src_strides[0] - (INPUT_BPP * w)
     };
 
+    let (w, h) = collapse_lines(
+        w,
+        height as usize,
+        input_stride_diff == 0 && output_stride_diff == 0,
+    );
+
     let mask_alphas =  _mm256_set1_epi32(ALPHAS_MASK as i32);
     let mask_shuffle = _mm256_loadu_si256(SHUFFLE_MASK.as_ptr() as *const __m256i);
     let output_buffer = dst_buffers[0].as_mut_ptr();
@@ -1111,10 +1191,10 @@ unsafe fn rgb_to_bgra_avx2(
     let mut ibuffer_offset = 0;
     let mut obuffer_offset = 0;
 
-    for _ in 0..height {
+    for _ in 0..h {
         _mm_prefetch(input_buffer as *const i8, _MM_HINT_NTA);
 
-        for _ in (0..width).step_by(LANE_COUNT) {
+        for _ in (0..w).step_by(LANE_COUNT) {
             // In order to avoid out of bound read, 4 bytes are substracted from the offset
             // of last read which goes in the first lane of input3, can be seen from schema.
             // bFgFrFbE gErEbDgD rDbCgCrC bBgBrBbA gArAb9g9 r9b8g8r8 b7g7r7b6 g6r6b5g5 r5b4g4r4 b3g3r3b2 g2r2b1g1 r1b0g0r0 bFgFrFbE gErEbDgD rDbCgCrC bBgBrBbA gArAb9g9 r9b8g8r8 b7g7r7b6 g6r6b5g5 r5b4g4r4 b3g3r3b2 g2r2b1g1 r1b0g0r0
@@ -1184,6 +1264,7 @@ fn lrgb_to_i444(
     channels: PixelFormatChannels,
     colorimetry: Colorimetry,
     sampler: Sampler,
+    jfif: bool,
 ) -> bool {
     unsafe {
         lrgb_to_i444_avx2(
@@ -1198,6 +1279,7 @@ fn lrgb_to_i444(
             channels,
             colorimetry,
             sampler,
+            jfif,
         )
     }
 }
@@ -1216,6 +1298,7 @@ unsafe fn lrgb_to_i444_avx2(
     channels: PixelFormatChannels,
     colorimetry: Colorimetry,
     sampler: Sampler,
+    jfif: bool,
 ) -> bool {
     if last_dst_plane != 2
         || (last_dst_plane >= dst_strides.len())
@@ -1284,27 +1367,42 @@ unsafe fn lrgb_to_i444_avx2(
         return false;
     }
 
+    let (col_count, line_count) = collapse_lines(
+        col_count,
+        line_count,
+        rgb_stride == packed_rgb_stride
+            && y_stride == col_count
+            && u_stride == col_count
+            && v_stride == col_count,
+    );
+
     let col = colorimetry as usize;
     if col > 1 {
         return false;
     }
 
+    let (weights, y_offset, c_offset) = if jfif {
+        (&JFIF_FORWARD_WEIGHTS, FIX16_HALF, C_OFFSET16 - 1)
+    } else {
+        (&FORWARD_WEIGHTS[col], Y_OFFSET, C_OFFSET16)
+    };
+
     let y_weights = [
-        _mm256_set1_epi32(FORWARD_WEIGHTS[col][0]),
-        _mm256_set1_epi32(FORWARD_WEIGHTS[col][1]),
-        _mm256_set1_epi32(Y_OFFSET),
+        _mm256_set1_epi32(weights[0]),
+        _mm256_set1_epi32(weights[1]),
+        _mm256_set1_epi32(y_offset),
     ];
 
     let u_weights = [
-        _mm256_set1_epi32(FORWARD_WEIGHTS[col][3]),
-        _mm256_set1_epi32(FORWARD_WEIGHTS[col][5]),
-        _mm256_set1_epi32(C_OFFSET16),
+        _mm256_set1_epi32(weights[3]),
+        _mm256_set1_epi32(weights[5]),
+        _mm256_set1_epi32(c_offset),
     ];
 
     let v_weights = [
-        _mm256_set1_epi32(FORWARD_WEIGHTS[col][2]),
-        _mm256_set1_epi32(FORWARD_WEIGHTS[col][4]),
-        _mm256_set1_epi32(C_OFFSET16),
+        _mm256_set1_epi32(weights[2]),
+        _mm256_set1_epi32(weights[4]),
+        _mm256_set1_epi32(c_offset),
     ];
 
     let rgb_depth = depth * LRGB_TO_YUV_WAVES;
@@ -1334,6 +1432,7 @@ unsafe fn lrgb_to_i444_avx2(
                 &y_weights,
                 &u_weights,
                 &v_weights,
+                jfif,
             );
         }
     }
@@ -1351,6 +1450,7 @@ unsafe fn lrgb_to_i444_avx2(
                 &y_weights,
                 &u_weights,
                 &v_weights,
+                jfif,
             );
         }
 
@@ -1364,6 +1464,7 @@ unsafe fn lrgb_to_i444_avx2(
             &y_weights,
             &u_weights,
             &v_weights,
+            jfif,
         );
     }
 
@@ -1447,7 +1548,11 @@ unsafe fn yuv_to_lrgb_avx2(
 
     let rgb_plane = &mut dst_buffers[0];
 
-    let yuv_planes = split_planes(last_src_plane, y_stride * line_count, src_buffers);
+    let yuv_planes = split_planes(
+        last_src_plane,
+        y_stride.saturating_mul(line_count),
+        src_buffers,
+    );
     if yuv_planes.is_none() {
         return false;
     }
@@ -1797,6 +1902,51 @@ unsafe fn i420_to_lrgb_avx2(
     true
 }
 
+/// Convert full range ycbcr to bgra exactly as libjpeg does (16-wide)
+#[inline(always)]
+unsafe fn i444_jfif_to_bgra_16x(y: *const u8, u: *const u8, v: *const u8, rgb: *mut u8) {
+    let c_half = _mm256_set1_epi16(C_HALF as i16);
+    let round = _mm256_set1_epi32(FIX16_HALF);
+    let bcb = _mm256_set1_epi32(JFIF_BACKWARD_WEIGHTS[0]);
+    let gcb = _mm256_set1_epi32(JFIF_BACKWARD_WEIGHTS[1]);
+    let gcr = _mm256_set1_epi32(JFIF_BACKWARD_WEIGHTS[2]);
+    let rcr = _mm256_set1_epi32(JFIF_BACKWARD_WEIGHTS[3]);
+
+    let l = _mm256_srli_epi16(unpack_ui8_i16be_16x(y), 8);
+    let cb = _mm256_sub_epi16(_mm256_srli_epi16(unpack_ui8_i16be_16x(u), 8), c_half);
+    let cr = _mm256_sub_epi16(_mm256_srli_epi16(unpack_ui8_i16be_16x(v), 8), c_half);
+
+    // Pairs of (chroma, chroma << 8), for the low and high four samples of each lane
+    let (cb_lo, cb_hi) = (
+        _mm256_unpacklo_epi16(cb, _mm256_slli_epi16(cb, 8)),
+        _mm256_unpackhi_epi16(cb, _mm256_slli_epi16(cb, 8)),
+    );
+    let (cr_lo, cr_hi) = (
+        _mm256_unpacklo_epi16(cr, _mm256_slli_epi16(cr, 8)),
+        _mm256_unpackhi_epi16(cr, _mm256_slli_epi16(cr, 8)),
+    );
+
+    let chroma = |lo: __m256i, hi: __m256i| {
+        _mm256_add_epi16(
+            l,
+            _mm256_packs_epi32(
+                fix_to_i32_8x!(_mm256_add_epi32(lo, round), FIX16),
+                fix_to_i32_8x!(_mm256_add_epi32(hi, round), FIX16),
+            ),
+        )
+    };
+
+    pack_i16x3_16x(
+        rgb,
+        chroma(_mm256_madd_epi16(cr_lo, rcr), _mm256_madd_epi16(cr_hi, rcr)),
+        chroma(
+            _mm256_add_epi32(_mm256_madd_epi16(cb_lo, gcb), _mm256_madd_epi16(cr_lo, gcr)),
+            _mm256_add_epi32(_mm256_madd_epi16(cb_hi, gcb), _mm256_madd_epi16(cr_hi, gcr)),
+        ),
+        chroma(_mm256_madd_epi16(cb_lo, bcb), _mm256_madd_epi16(cb_hi, bcb)),
+    );
+}
+
 #[inline(always)]
 fn i444_to_lrgb(
     width: u32,
@@ -1809,6 +1959,7 @@ fn i444_to_lrgb(
     dst_buffers: &mut [&mut [u8]],
     channels: PixelFormatChannels,
     colorimetry: Colorimetry,
+    jfif: bool,
 ) -> bool {
     unsafe {
         i444_to_lrgb_avx2(
@@ -1822,6 +1973,7 @@ fn i444_to_lrgb(
             dst_buffers,
             channels,
             colorimetry,
+            jfif,
         )
     }
 }
@@ -1839,6 +1991,7 @@ unsafe fn i444_to_lrgb_avx2(
     dst_buffers: &mut [&mut [u8]],
     channels: PixelFormatChannels,
     colorimetry: Colorimetry,
+    jfif: bool,
 ) -> bool {
     if last_src_plane != 2
         || last_src_plane >= src_strides.len()
@@ -1902,6 +2055,38 @@ unsafe fn i444_to_lrgb_avx2(
         return false;
     }
 
+    let (col_count, line_count) = collapse_lines(
+        col_count,
+        line_count,
+        rgb_stride == packed_rgb_stride
+            && y_stride == col_count
+            && u_stride == col_count
+            && v_stride == col_count,
+    );
+
+    let y_group = y_plane.as_ptr();
+    let u_group = u_plane.as_ptr();
+    let v_group = v_plane.as_ptr();
+    let rgb_group = rgb_plane.as_mut_ptr();
+    let rgb_depth = 2 * YUV_TO_LRGB_WAVES;
+    let group_width = YUV_TO_LRGB_WAVES / 2;
+    let wg_width = col_count / group_width;
+
+    if jfif {
+        for y in 0..line_count {
+            for x in 0..wg_width {
+                i444_jfif_to_bgra_16x(
+                    y_group.add(wg_index(x, y, group_width, y_stride)),
+                    u_group.add(wg_index(x, y, group_width, u_stride)),
+                    v_group.add(wg_index(x, y, group_width, v_stride)),
+                    rgb_group.add(wg_index(x, y, rgb_depth, rgb_stride)),
+                );
+            }
+        }
+
+        return true;
+    }
+
     let col = colorimetry as usize;
     if col > 1 {
         return false;
@@ -1917,14 +2102,6 @@ unsafe fn i444_to_lrgb_avx2(
     let bn = _mm256_set1_epi16(BACKWARD_WEIGHTS[col][7]);
     let zero_128 = _mm_setzero_si128();
 
-    let y_group = y_plane.as_ptr();
-    let u_group = u_plane.as_ptr();
-    let v_group = v_plane.as_ptr();
-    let rgb_group = rgb_plane.as_mut_ptr();
-    let rgb_depth = 2 * YUV_TO_LRGB_WAVES;
-    let group_width = YUV_TO_LRGB_WAVES / 2;
-    let wg_width = col_count / group_width;
-
     for y in 0..line_count {
         for x in 0..wg_width {
             let cb = _mm256_loadu2_m128i(
@@ -2415,6 +2592,7 @@ pub fn i444_bt601_bgra_lrgb(
             dst_buffers,
             PixelFormatChannels::Four,
             Colorimetry::Bt601,
+            false,
         )
     } else {
         sse2::i444_bt601_bgra_lrgb(
@@ -2452,6 +2630,7 @@ pub fn i444_bt709_bgra_lrgb(
             dst_buffers,
             PixelFormatChannels::Four,
             Colorimetry::Bt709,
+            false,
         )
     } else {
         sse2::i444_bt709_bgra_lrgb(
@@ -2467,6 +2646,44 @@ pub fn i444_bt709_bgra_lrgb(
     }
 }
 
+pub fn i444_jfif_bgra_lrgb(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    if is_wg_multiple(width, YUV_TO_LRGB_WAVES / 2) {
+        i444_to_lrgb(
+            width,
+            height,
+            last_src_plane as usize,
+            src_strides,
+            src_buffers,
+            last_dst_plane as usize,
+            dst_strides,
+            dst_buffers,
+            PixelFormatChannels::Four,
+            Colorimetry::Bt601,
+            true,
+        )
+    } else {
+        sse2::i444_jfif_bgra_lrgb(
+            width,
+            height,
+            last_src_plane,
+            src_strides,
+            src_buffers,
+            last_dst_plane,
+            dst_strides,
+            dst_buffers,
+        )
+    }
+}
+
 pub fn argb_lrgb_i420_bt601(
     width: u32,
     height: u32,
@@ -2718,6 +2935,7 @@ pub fn argb_lrgb_i444_bt601(
             PixelFormatChannels::Four,
             Colorimetry::Bt601,
             Sampler::Argb,
+            false,
         )
     } else {
         sse2::argb_lrgb_i444_bt601(
@@ -2756,6 +2974,7 @@ pub fn argb_lrgb_i444_bt709(
             PixelFormatChannels::Four,
             Colorimetry::Bt709,
             Sampler::Argb,
+            false,
         )
     } else {
         sse2::argb_lrgb_i444_bt709(
@@ -2794,6 +3013,7 @@ pub fn bgra_lrgb_i444_bt601(
             PixelFormatChannels::Four,
             Colorimetry::Bt601,
             Sampler::Bgra,
+            false,
         )
     } else {
         sse2::bgra_lrgb_i444_bt601(
@@ -2832,6 +3052,7 @@ pub fn bgra_lrgb_i444_bt709(
             PixelFormatChannels::Four,
             Colorimetry::Bt709,
             Sampler::Bgra,
+            false,
         )
     } else {
         sse2::bgra_lrgb_i444_bt709(
@@ -2870,6 +3091,7 @@ pub fn bgr_lrgb_i444_bt601(
             PixelFormatChannels::Three,
             Colorimetry::Bt601,
             Sampler::Bgr,
+            false,
         )
     } else {
         sse2::bgr_lrgb_i444_bt601(
@@ -2908,6 +3130,7 @@ pub fn bgr_lrgb_i444_bt709(
             PixelFormatChannels::Three,
             Colorimetry::Bt709,
             Sampler::Bgr,
+            false,
         )
     } else {
         sse2::bgr_lrgb_i444_bt709(
@@ -2923,6 +3146,123 @@ pub fn bgr_lrgb_i444_bt709(
     }
 }
 
+pub fn argb_lrgb_i444_jfif(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    if is_wg_multiple(width, LRGB_TO_YUV_WAVES) {
+        lrgb_to_i444(
+            width,
+            height,
+            last_src_plane as usize,
+            src_strides,
+            src_buffers,
+            last_dst_plane as usize,
+            dst_strides,
+            dst_buffers,
+            PixelFormatChannels::Four,
+            Colorimetry::Bt601,
+            Sampler::Argb,
+            true,
+        )
+    } else {
+        sse2::argb_lrgb_i444_jfif(
+            width,
+            height,
+            last_src_plane,
+            src_strides,
+            src_buffers,
+            last_dst_plane,
+            dst_strides,
+            dst_buffers,
+        )
+    }
+}
+
+pub fn bgra_lrgb_i444_jfif(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    if is_wg_multiple(width, LRGB_TO_YUV_WAVES) {
+        lrgb_to_i444(
+            width,
+            height,
+            last_src_plane as usize,
+            src_strides,
+            src_buffers,
+            last_dst_plane as usize,
+            dst_strides,
+            dst_buffers,
+            PixelFormatChannels::Four,
+            Colorimetry::Bt601,
+            Sampler::Bgra,
+            true,
+        )
+    } else {
+        sse2::bgra_lrgb_i444_jfif(
+            width,
+            height,
+            last_src_plane,
+            src_strides,
+            src_buffers,
+            last_dst_plane,
+            dst_strides,
+            dst_buffers,
+        )
+    }
+}
+
+pub fn bgr_lrgb_i444_jfif(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    if is_wg_multiple(width, LRGB_TO_YUV_WAVES) {
+        lrgb_to_i444(
+            width,
+            height,
+            last_src_plane as usize,
+            src_strides,
+            src_buffers,
+            last_dst_plane as usize,
+            dst_strides,
+            dst_buffers,
+            PixelFormatChannels::Three,
+            Colorimetry::Bt601,
+            Sampler::Bgr,
+            true,
+        )
+    } else {
+        sse2::bgr_lrgb_i444_jfif(
+            width,
+            height,
+            last_src_plane,
+            src_strides,
+            src_buffers,
+            last_dst_plane,
+            dst_strides,
+            dst_buffers,
+        )
+    }
+}
+
 pub fn bgra_lrgb_rgb_lrgb(
     width: u32,
     height: u32,
@@ -2944,3 +3284,374 @@ pub fn bgra_lrgb_rgb_lrgb(
         dst_buffers,
     )
 }
+
+#[inline(always)]
+pub fn abs_diff_sum(a: &[u8], b: &[u8]) -> (u64, u8) {
+    unsafe { abs_diff_sum_avx2(a, b) }
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn abs_diff_sum_avx2(a: &[u8], b: &[u8]) -> (u64, u8) {
+    let len = a.len().min(b.len());
+    let vector_part = len - (len % LANE_COUNT);
+
+    let mut sum = _mm256_setzero_si256();
+    let mut max = _mm256_setzero_si256();
+    for i in (0..vector_part).step_by(LANE_COUNT) {
+        let x = _mm256_loadu_si256(a.as_ptr().add(i) as *const __m256i);
+        let y = _mm256_loadu_si256(b.as_ptr().add(i) as *const __m256i);
+        let delta = _mm256_or_si256(_mm256_subs_epu8(x, y), _mm256_subs_epu8(y, x));
+        max = _mm256_max_epu8(max, delta);
+        sum = _mm256_add_epi64(sum, _mm256_sad_epu8(x, y));
+    }
+
+    let mut sums = [0u64; 4];
+    let mut maxs = [0u8; LANE_COUNT];
+    _mm256_storeu_si256(sums.as_mut_ptr() as *mut __m256i, sum);
+    _mm256_storeu_si256(maxs.as_mut_ptr() as *mut __m256i, max);
+
+    let (tail_sum, tail_max) = sse2::abs_diff_sum(&a[vector_part..len], &b[vector_part..len]);
+    (
+        (sums[0] + sums[1]) + (sums[2] + sums[3]) + tail_sum,
+        maxs.iter().fold(tail_max, |acc, &value| acc.max(value)),
+    )
+}
+
+#[inline(always)]
+pub fn blend_over(src: &[u8], dst: &mut [u8], premultiplied: bool) {
+    unsafe { blend_over_avx2(src, dst, premultiplied) }
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn blend_over_avx2(src: &[u8], dst: &mut [u8], premultiplied: bool) {
+    let len = src.len().min(dst.len()) & !3;
+    let vector_part = len - (len % LANE_COUNT);
+
+    // Unpacking and packing back work within 128-bit lanes, so the pixel order is kept
+    let max = _mm256_set1_epi16(255);
+    let opaque = _mm256_set1_epi32(-0x0100_0000);
+    for i in (0..vector_part).step_by(LANE_COUNT) {
+        let s = _mm256_loadu_si256(src.as_ptr().add(i) as *const __m256i);
+        let d = _mm256_loadu_si256(dst.as_ptr().add(i) as *const __m256i);
+        let s_lo = _mm256_unpacklo_epi8(s, zero!());
+        let s_hi = _mm256_unpackhi_epi8(s, zero!());
+        let d_lo = _mm256_unpacklo_epi8(d, zero!());
+        let d_hi = _mm256_unpackhi_epi8(d, zero!());
+
+        // Replicate alpha on the four samples of each pixel
+        let a_lo = _mm256_shufflehi_epi16(
+            _mm256_shufflelo_epi16(s_lo, BROADCAST_WORD_3),
+            BROADCAST_WORD_3,
+        );
+        let a_hi = _mm256_shufflehi_epi16(
+            _mm256_shufflelo_epi16(s_hi, BROADCAST_WORD_3),
+            BROADCAST_WORD_3,
+        );
+
+        let d_lo = _mm256_mullo_epi16(d_lo, _mm256_sub_epi16(max, a_lo));
+        let d_hi = _mm256_mullo_epi16(d_hi, _mm256_sub_epi16(max, a_hi));
+        let (lo, hi) = if premultiplied {
+            (
+                _mm256_add_epi16(s_lo, div_255_16x!(d_lo)),
+                _mm256_add_epi16(s_hi, div_255_16x!(d_hi)),
+            )
+        } else {
+            // Alpha of the result is computed as the one of an opaque color
+            let c = _mm256_or_si256(s, opaque);
+            let c_lo = _mm256_mullo_epi16(_mm256_unpacklo_epi8(c, zero!()), a_lo);
+            let c_hi = _mm256_mullo_epi16(_mm256_unpackhi_epi8(c, zero!()), a_hi);
+            (
+                div_255_16x!(_mm256_add_epi16(c_lo, d_lo)),
+                div_255_16x!(_mm256_add_epi16(c_hi, d_hi)),
+            )
+        };
+
+        _mm256_storeu_si256(
+            dst.as_mut_ptr().add(i) as *mut __m256i,
+            _mm256_packus_epi16(lo, hi),
+        );
+    }
+
+    sse2::blend_over(&src[vector_part..len], &mut dst[vector_part..len], premultiplied);
+}
+
+#[inline(always)]
+pub fn remap(samples: &mut [u8], even: &[u8; 256], odd: &[u8; 256]) {
+    unsafe { remap_avx2(samples, even, odd) }
+}
+
+/// Splits a table in sixteen rows of sixteen entries, each one repeated in both lanes
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn load_table_rows(table: &[u8; 256]) -> [__m256i; 16] {
+    let mut rows = [zero!(); 16];
+    for (i, row) in rows.iter_mut().enumerate() {
+        let entries = _mm_loadu_si128(table.as_ptr().add(16 * i) as *const __m128i);
+        *row = _mm256_set_m128i(entries, entries);
+    }
+
+    rows
+}
+
+/// Looks up 32 samples: the low nibble of a sample selects the entry within each row,
+/// the high nibble selects the row
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn remap_32x(low: __m256i, high: __m256i, rows: &[__m256i; 16]) -> __m256i {
+    let mut result = zero!();
+    for (i, row) in rows.iter().enumerate() {
+        let selected = _mm256_cmpeq_epi8(high, _mm256_set1_epi8(i as i8));
+        result = _mm256_or_si256(
+            result,
+            _mm256_and_si256(selected, _mm256_shuffle_epi8(*row, low)),
+        );
+    }
+
+    result
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn remap_avx2(samples: &mut [u8], even: &[u8; 256], odd: &[u8; 256]) {
+    let len = samples.len();
+    let vector_part = len - (len % LANE_COUNT);
+
+    let even_rows = load_table_rows(even);
+    let odd_rows = if even == odd {
+        None
+    } else {
+        Some(load_table_rows(odd))
+    };
+
+    let nibble = _mm256_set1_epi8(0x0F);
+    let odd_mask = _mm256_set1_epi16(-0x0100);
+    for i in (0..vector_part).step_by(LANE_COUNT) {
+        let x = _mm256_loadu_si256(samples.as_ptr().add(i) as *const __m256i);
+        let low = _mm256_and_si256(x, nibble);
+        let high = _mm256_and_si256(_mm256_srli_epi16(x, 4), nibble);
+
+        let mut y = remap_32x(low, high, &even_rows);
+        if let Some(odd_rows) = &odd_rows {
+            y = _mm256_blendv_epi8(y, remap_32x(low, high, odd_rows), odd_mask);
+        }
+
+        _mm256_storeu_si256(samples.as_mut_ptr().add(i) as *mut __m256i, y);
+    }
+
+    // The vector part has an even length, so the tail starts at an even position
+    sse2::remap(&mut samples[vector_part..], even, odd);
+}
+
+#[inline(always)]
+pub fn cross_fade(src: &[u8], dst: &mut [u8], alpha: u8) {
+    unsafe { cross_fade_avx2(src, dst, alpha) }
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn cross_fade_avx2(src: &[u8], dst: &mut [u8], alpha: u8) {
+    let len = src.len().min(dst.len());
+    let vector_part = len - (len % LANE_COUNT);
+
+    let a = _mm256_set1_epi16(i16::from(alpha));
+    let inv_a = _mm256_set1_epi16(i16::from(255 - alpha));
+    for i in (0..vector_part).step_by(LANE_COUNT) {
+        let s = _mm256_loadu_si256(src.as_ptr().add(i) as *const __m256i);
+        let d = _mm256_loadu_si256(dst.as_ptr().add(i) as *const __m256i);
+
+        // Unpacking and packing both work within lanes, so the order of samples is kept
+        let lo = _mm256_add_epi16(
+            _mm256_mullo_epi16(_mm256_unpacklo_epi8(s, zero!()), a),
+            _mm256_mullo_epi16(_mm256_unpacklo_epi8(d, zero!()), inv_a),
+        );
+        let hi = _mm256_add_epi16(
+            _mm256_mullo_epi16(_mm256_unpackhi_epi8(s, zero!()), a),
+            _mm256_mullo_epi16(_mm256_unpackhi_epi8(d, zero!()), inv_a),
+        );
+
+        _mm256_storeu_si256(
+            dst.as_mut_ptr().add(i) as *mut __m256i,
+            _mm256_packus_epi16(div_255_16x!(lo), div_255_16x!(hi)),
+        );
+    }
+
+    sse2::cross_fade(&src[vector_part..len], &mut dst[vector_part..len], alpha);
+}
+
+#[inline(always)]
+pub fn accumulate(src: &[u8], dst: &mut [u8], alpha: u8) {
+    unsafe { accumulate_avx2(src, dst, alpha) }
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn accumulate_avx2(src: &[u8], dst: &mut [u8], alpha: u8) {
+    let len = src.len().min(dst.len());
+    let vector_part = len - (len % LANE_COUNT);
+
+    let a = _mm256_set1_epi16(i16::from(alpha));
+    for i in (0..vector_part).step_by(LANE_COUNT) {
+        let s = _mm256_loadu_si256(src.as_ptr().add(i) as *const __m256i);
+        let d = _mm256_loadu_si256(dst.as_ptr().add(i) as *const __m256i);
+
+        // Unpacking and packing both work within lanes, so the order of samples is kept
+        let lo = _mm256_mullo_epi16(_mm256_unpacklo_epi8(s, zero!()), a);
+        let hi = _mm256_mullo_epi16(_mm256_unpackhi_epi8(s, zero!()), a);
+
+        _mm256_storeu_si256(
+            dst.as_mut_ptr().add(i) as *mut __m256i,
+            _mm256_adds_epu8(d, _mm256_packus_epi16(div_255_16x!(lo), div_255_16x!(hi))),
+        );
+    }
+
+    sse2::accumulate(&src[vector_part..len], &mut dst[vector_part..len], alpha);
+}
+
+/// Number of points interpolated at once by the vectorized look-up table kernels
+const CUBE_LANE_COUNT: usize = 8;
+
+/// Gathers the red, green and blue of the table points at the given indices
+#[inline(always)]
+unsafe fn gather_points(table: &[[f32; 3]], index: __m256i) -> [__m256; 3] {
+    let samples = table.as_ptr() as *const f32;
+    let offset = _mm256_add_epi32(index, _mm256_add_epi32(index, index));
+    [
+        _mm256_i32gather_ps(samples, offset, 4),
+        _mm256_i32gather_ps(samples.add(1), offset, 4),
+        _mm256_i32gather_ps(samples.add(2), offset, 4),
+    ]
+}
+
+#[inline(always)]
+pub fn interpolate_cube(
+    table: &[[f32; 3]],
+    size: usize,
+    index: &[u32],
+    fractions: [&[f32]; 3],
+    out: [&mut [f32]; 3],
+) {
+    unsafe { interpolate_cube_avx2(table, size, index, fractions, out) }
+}
+
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn interpolate_cube_avx2(
+    table: &[[f32; 3]],
+    size: usize,
+    index: &[u32],
+    fractions: [&[f32]; 3],
+    out: [&mut [f32]; 3],
+) {
+    let [fr, fg, fb] = fractions;
+    let [r, g, b] = out;
+    let len = index
+        .len()
+        .min(fr.len())
+        .min(fg.len())
+        .min(fb.len())
+        .min(r.len())
+        .min(g.len())
+        .min(b.len());
+
+    // Points are gathered unchecked, cells reaching past the end of the table are left
+    // to the portable kernel
+    let upper = 1 + size + size * size;
+    let vector_part = if index[..len]
+        .iter()
+        .all(|&base| (base as usize) + upper < table.len())
+    {
+        len - (len % CUBE_LANE_COUNT)
+    } else {
+        0
+    };
+
+    let dr = _mm256_set1_epi32(1);
+    let dg = _mm256_set1_epi32(size as i32);
+    let db = _mm256_set1_epi32((size * size) as i32);
+    let d111 = _mm256_set1_epi32(upper as i32);
+    for i in (0..vector_part).step_by(CUBE_LANE_COUNT) {
+        let vr = _mm256_loadu_ps(fr.as_ptr().add(i));
+        let vg = _mm256_loadu_ps(fg.as_ptr().add(i));
+        let vb = _mm256_loadu_ps(fb.as_ptr().add(i));
+        let base = _mm256_loadu_si256(index.as_ptr().add(i) as *const __m256i);
+
+        // Same tetrahedra as the portable kernel, also when fractions are equal
+        let rg = _mm256_cmp_ps(vr, vg, _CMP_GT_OQ);
+        let gb = _mm256_cmp_ps(vg, vb, _CMP_GT_OQ);
+        let rb = _mm256_cmp_ps(vr, vb, _CMP_GT_OQ);
+        let bg = _mm256_cmp_ps(vb, vg, _CMP_GT_OQ);
+        let br = _mm256_cmp_ps(vb, vr, _CMP_GT_OQ);
+        let rgb = _mm256_and_ps(rg, gb);
+        let rbg = _mm256_and_ps(rg, _mm256_andnot_ps(gb, rb));
+        let brg = _mm256_andnot_ps(_mm256_or_ps(gb, rb), rg);
+        let bgr = _mm256_andnot_ps(rg, bg);
+        let gbr = _mm256_andnot_ps(_mm256_or_ps(rg, bg), br);
+
+        // Axes sorted by decreasing fraction
+        let first_r = _mm256_or_ps(rgb, rbg);
+        let first_b = _mm256_or_ps(brg, bgr);
+        let second_g = _mm256_or_ps(rgb, bgr);
+        let second_b = _mm256_or_ps(rbg, gbr);
+        let third_g = _mm256_or_ps(rbg, brg);
+        let third_r = _mm256_or_ps(bgr, gbr);
+
+        let w0 = _mm256_blendv_ps(_mm256_blendv_ps(vg, vb, first_b), vr, first_r);
+        let w1 = _mm256_blendv_ps(_mm256_blendv_ps(vr, vb, second_b), vg, second_g);
+        let w2 = _mm256_blendv_ps(_mm256_blendv_ps(vb, vr, third_r), vg, third_g);
+
+        let d1 = _mm256_blendv_epi8(
+            _mm256_blendv_epi8(dg, db, _mm256_castps_si256(first_b)),
+            dr,
+            _mm256_castps_si256(first_r),
+        );
+        let d2 = _mm256_add_epi32(
+            d1,
+            _mm256_blendv_epi8(
+                _mm256_blendv_epi8(dr, db, _mm256_castps_si256(second_b)),
+                dg,
+                _mm256_castps_si256(second_g),
+            ),
+        );
+
+        let c000 = gather_points(table, base);
+        let p1 = gather_points(table, _mm256_add_epi32(base, d1));
+        let p2 = gather_points(table, _mm256_add_epi32(base, d2));
+        let c111 = gather_points(table, _mm256_add_epi32(base, d111));
+
+        for (channel, plane) in [r.as_mut_ptr(), g.as_mut_ptr(), b.as_mut_ptr()]
+            .iter()
+            .enumerate()
+        {
+            let value = _mm256_add_ps(
+                _mm256_add_ps(
+                    _mm256_add_ps(
+                        c000[channel],
+                        _mm256_mul_ps(w0, _mm256_sub_ps(p1[channel], c000[channel])),
+                    ),
+                    _mm256_mul_ps(w1, _mm256_sub_ps(p2[channel], p1[channel])),
+                ),
+                _mm256_mul_ps(w2, _mm256_sub_ps(c111[channel], p2[channel])),
+            );
+
+            _mm256_storeu_ps(plane.add(i), value);
+        }
+    }
+
+    sse2::interpolate_cube(
+        table,
+        size,
+        &index[vector_part..len],
+        [
+            &fr[vector_part..len],
+            &fg[vector_part..len],
+            &fb[vector_part..len],
+        ],
+        [
+            &mut r[vector_part..len],
+            &mut g[vector_part..len],
+            &mut b[vector_part..len],
+        ],
+    );
+}
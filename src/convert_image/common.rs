@@ -34,6 +34,20 @@ pub fn wg_index(x: usize, y: usize, w: usize, h: usize) -> usize {
     (h * y) + (x * w)
 }
 
+/// Returns the number of columns and lines a kernel has to iterate over.
+///
+/// When all the image planes are tightly packed, consecutive lines are contiguous
+/// in memory, so the image is processed as a single line of `col_count * line_count`
+/// columns. This removes the per-line loop overhead and the leftover handling
+/// at the end of each line.
+pub fn collapse_lines(col_count: usize, line_count: usize, packed: bool) -> (usize, usize) {
+    if packed {
+        (col_count * line_count, 1)
+    } else {
+        (col_count, line_count)
+    }
+}
+
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 pub fn is_wg_multiple(x: u32, w: usize) -> bool {
     ((x as usize) & (w - 1)) == 0
@@ -87,6 +101,27 @@ pub const RN_709: i32 = 15846;
 pub const GP_709: i32 = 4952;
 pub const BN_709: i32 = 18465;
 
+// Coefficient tables from 601 to 709 and viceversa, applied to ycbcr samples
+// Rows: y (cb and cr contributions), cb (cb, cr), cr (cb, cr)
+pub const YCBCR_601_TO_709: [[i32; 2]; 3] = [[-7573, -13627], [66758, 7512], [4918, 67196]];
+pub const YCBCR_709_TO_601: [[i32; 2]; 3] = [[6508, 12563], [64871, -7252], [-4748, 64448]];
+
+// Coefficient table for jfif (full range 601), with the values and the rounding of libjpeg
+pub const XR_JFIF: i32 = 19595;
+pub const XG_JFIF: i32 = 38470;
+pub const XB_JFIF: i32 = 7471;
+pub const YR_JFIF: i32 = -11059;
+pub const YG_JFIF: i32 = -21709;
+pub const YB_JFIF: i32 = 32768;
+pub const ZR_JFIF: i32 = 32768;
+pub const ZG_JFIF: i32 = -27439;
+pub const ZB_JFIF: i32 = -5329;
+
+pub const RCR_JFIF: i32 = 91881;
+pub const GCR_JFIF: i32 = 46802;
+pub const GCB_JFIF: i32 = 22554;
+pub const BCB_JFIF: i32 = 116130;
+
 // Other defines
 pub const Y_MIN: i32 = 16;
 pub const C_HALF: i32 = 128;
@@ -117,3 +152,152 @@ pub enum PixelFormatChannels {
     Three = 3,
     Four = 4,
 }
+
+// Half float and high dynamic range defines
+pub const F16_ONE: u16 = 0x3C00;
+
+// SMPTE ST 2084 constants
+const PQ_M1: f32 = 2610.0 / 16384.0;
+const PQ_M2: f32 = 2523.0 / 4096.0 * 128.0;
+const PQ_C1: f32 = 3424.0 / 4096.0;
+const PQ_C2: f32 = 2413.0 / 4096.0 * 32.0;
+const PQ_C3: f32 = 2392.0 / 4096.0 * 32.0;
+
+// Peak luminance of the perceptual quantizer, in scRGB units (1.0 = 80 nits)
+pub const PQ_PEAK: f32 = 10000.0 / 80.0;
+
+// Linear values above this threshold are compressed by the tone mapping
+pub const TONE_MAPPING_KNEE: f32 = 0.75;
+
+// BT.2020 non-constant luminance, limited range 10-bit
+pub const Y_MIN_10: f32 = 64.0;
+pub const Y_RANGE_10: f32 = 876.0;
+pub const C_HALF_10: f32 = 512.0;
+pub const C_RANGE_10: f32 = 896.0;
+pub const RCR_2020: f32 = 1.4746;
+pub const GCB_2020: f32 = 0.164_553;
+pub const GCR_2020: f32 = 0.571_353;
+pub const BCB_2020: f32 = 1.8814;
+
+// Limited range 8-bit to normalized float: luma is mapped to [0, 1], chroma to [-0.5, 0.5]
+pub const Y_MIN_8: f32 = 16.0;
+pub const Y_RANGE_8: f32 = 219.0;
+pub const C_HALF_8: f32 = 128.0;
+pub const C_RANGE_8: f32 = 224.0;
+
+// Normalized ycbcr to rgb coefficients, as (rcr, gcb, gcr, bcb)
+pub const YCBCR_TO_RGB_601: [f32; 4] = [1.402, 0.344_136, 0.714_136, 1.772];
+pub const YCBCR_TO_RGB_709: [f32; 4] = [1.5748, 0.187_324, 0.468_124, 1.8556];
+
+// Linear BT.2020 primaries to linear BT.709 (and scRGB) primaries
+pub const BT2020_TO_BT709: [[f32; 3]; 3] = [
+    [1.660_491, -0.587_641, -0.072_850],
+    [-0.124_550, 1.132_9, -0.008_349],
+    [-0.018_151, -0.100_579, 1.118_73],
+];
+
+/// Convert a single precision float to half precision, rounding to nearest even
+pub fn f32_to_f16(value: f32) -> u16 {
+    let x = value.to_bits();
+    let sign = ((x >> 16) & 0x8000) as u16;
+    let exp = ((x >> 23) & 0xFF) as i32;
+    let man = x & 0x7F_FFFF;
+
+    // Infinity and nan
+    if exp == 0xFF {
+        return sign | 0x7C00 | if man == 0 { 0 } else { 0x200 };
+    }
+
+    let exp = exp - 127 + 15;
+    if exp >= 0x1F {
+        return sign | 0x7C00;
+    }
+
+    // Subnormal half float, or underflow to zero
+    if exp <= 0 {
+        if exp < -10 {
+            return sign;
+        }
+
+        let man = man | 0x80_0000;
+        let shift = (14 - exp) as u32;
+        let half = 1 << (shift - 1);
+        let rem = man & ((1 << shift) - 1);
+        let mut h = (man >> shift) as u16;
+        if rem > half || (rem == half && (h & 1) != 0) {
+            h += 1;
+        }
+
+        return sign | h;
+    }
+
+    // A carry from the mantissa correctly increments the exponent
+    let mut h = (((exp as u32) << 10) | (man >> 13)) as u16;
+    let rem = man & 0x1FFF;
+    if rem > 0x1000 || (rem == 0x1000 && (h & 1) != 0) {
+        h += 1;
+    }
+
+    sign | h
+}
+
+/// Convert a half precision float to single precision
+pub fn f16_to_f32(value: u16) -> f32 {
+    let sign = u32::from(value & 0x8000) << 16;
+    let exp = u32::from((value >> 10) & 0x1F);
+    let man = u32::from(value & 0x3FF);
+
+    if exp == 0 {
+        let magnitude = (man as f32) / 16_777_216.0;
+        return if sign == 0 { magnitude } else { -magnitude };
+    }
+
+    let bits = if exp == 0x1F {
+        sign | 0x7F80_0000 | (man << 13)
+    } else {
+        sign | ((exp + 112) << 23) | (man << 13)
+    };
+
+    f32::from_bits(bits)
+}
+
+/// SMPTE ST 2084 electro-optical transfer function.
+///
+/// Maps a non-linear value in [0, 1] to linear light, where 1.0 is 10000 nits
+pub fn pq_eotf(value: f32) -> f32 {
+    let e = value.clamp(0.0, 1.0).powf(1.0 / PQ_M2);
+    ((e - PQ_C1).max(0.0) / (PQ_C2 - PQ_C3 * e)).powf(1.0 / PQ_M1)
+}
+
+/// Inverse of the SMPTE ST 2084 electro-optical transfer function.
+///
+/// Maps linear light in [0, 1], where 1.0 is 10000 nits, to a non-linear value in [0, 1]
+pub fn pq_inverse_eotf(value: f32) -> f32 {
+    let l = value.clamp(0.0, 1.0).powf(PQ_M1);
+    ((PQ_C1 + PQ_C2 * l) / (1.0 + PQ_C3 * l)).powf(PQ_M2)
+}
+
+/// sRGB opto-electronic transfer function.
+///
+/// Maps a linear value in [0, 1] to a non-linear value in [0, 1]
+pub fn srgb_oetf(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        12.92 * value.max(0.0)
+    } else {
+        1.055 * value.min(1.0).powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Compress linear values above TONE_MAPPING_KNEE into [TONE_MAPPING_KNEE, 1].
+///
+/// The curve is continuous and has unit slope at the knee, so SDR content
+/// below the knee is left untouched
+pub fn tone_map(value: f32) -> f32 {
+    if value <= TONE_MAPPING_KNEE {
+        value
+    } else {
+        let range = 1.0 - TONE_MAPPING_KNEE;
+        let t = (value - TONE_MAPPING_KNEE) / range;
+        TONE_MAPPING_KNEE + range * (t / (1.0 + t))
+    }
+}
@@ -14,8 +14,33 @@
 // OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
 // SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 
+// Kernels take the arguments of the conversion dispatcher, which are more than seven
+#![allow(clippy::too_many_arguments)]
+// Vectors are loaded and stored with the unaligned intrinsics, through pointers to the
+// vector types
+#![allow(clippy::cast_ptr_alignment)]
+
 use crate::convert_image::common::*;
 use crate::convert_image::x86;
+use core::ptr::{read_unaligned, write_unaligned};
+
+// No vectorized implementation yet
+pub use crate::convert_image::x86::{
+    argb_lrgb_i420_jfif, argb_lrgb_nv12_jfif, argb_lrgb_yuvf32planar_bt601,
+    argb_lrgb_yuvf32planar_bt709, bgr48_lrgb_bgra_lrgb, bgr48_lrgb_p010_bt601,
+    bgr48_lrgb_p010_bt709, bgr_lrgb_i420_jfif, bgr_lrgb_nv12_jfif, bgr_lrgb_yuvf32planar_bt601,
+    bgr_lrgb_yuvf32planar_bt709, bgra_lrgb_i420_jfif, bgra_lrgb_nv12_jfif,
+    bgra_lrgb_yuvf32planar_bt601, bgra_lrgb_yuvf32planar_bt709, i420_bt601_i420_bt709,
+    i420_bt601_rgbaf32_lrgb, i420_bt601_rgbf32_lrgb, i420_bt709_i420_bt601,
+    i420_bt709_rgbaf32_lrgb, i420_bt709_rgbf32_lrgb, i420_jfif_bgra_lrgb, i444_bt601_i444_bt709,
+    i444_bt601_rgbaf32_lrgb, i444_bt601_rgbf32_lrgb, i444_bt709_i444_bt601,
+    i444_bt709_rgbaf32_lrgb, i444_bt709_rgbf32_lrgb, nv12_bt2020pq_p010_bt2020pq,
+    nv12_bt601_nv12_bt709, nv12_bt601_p010_bt601, nv12_bt601_rgbaf32_lrgb, nv12_bt601_rgbf32_lrgb,
+    nv12_bt709_nv12_bt601, nv12_bt709_p010_bt709, nv12_bt709_rgbaf32_lrgb, nv12_bt709_rgbf32_lrgb,
+    nv12_jfif_bgra_lrgb, p010_bt2020pq_nv12_bt2020pq, p010_bt2020pq_rgbaf16_lrgb,
+    p010_bt601_nv12_bt601, p010_bt709_nv12_bt709, rgb48_lrgb_bgra_lrgb, rgb48_lrgb_p010_bt601,
+    rgb48_lrgb_p010_bt709, rgbaf16_lrgb_bgra_lrgb,
+};
 
 #[cfg(target_arch = "x86")]
 use core::arch::x86::{
@@ -26,7 +51,10 @@ use core::arch::x86::{
     _mm_srai_epi32, _mm_srli_epi16, _mm_srli_epi32, _mm_srli_si128, _mm_storeu_si128,
     _mm_sub_epi16, _mm_unpackhi_epi16, _mm_unpackhi_epi8, _mm_unpacklo_epi16, _mm_unpacklo_epi32,
     _mm_unpacklo_epi64, _mm_unpacklo_epi8, _mm_and_si128, _mm_slli_si128, _mm_shufflehi_epi16,
-    _mm_shufflelo_epi16, _mm_andnot_si128
+    _mm_shufflelo_epi16, _mm_andnot_si128, _mm_add_epi64, _mm_max_epu8, _mm_sad_epu8,
+    _mm_subs_epu8, _mm_mullo_epi16, _mm_adds_epu8, __m128, _mm_add_ps, _mm_and_ps, _mm_andnot_ps,
+    _mm_castps_si128, _mm_cmpgt_ps, _mm_loadu_ps, _mm_mul_ps, _mm_or_ps, _mm_setr_ps,
+    _mm_storeu_ps, _mm_sub_ps
 };
 
 #[cfg(target_arch = "x86_64")]
@@ -38,7 +66,10 @@ use core::arch::x86_64::{
     _mm_srai_epi32, _mm_srli_epi16, _mm_srli_epi32, _mm_srli_si128, _mm_storeu_si128,
     _mm_sub_epi16, _mm_unpackhi_epi16, _mm_unpackhi_epi8, _mm_unpacklo_epi16, _mm_unpacklo_epi32,
     _mm_unpacklo_epi64, _mm_unpacklo_epi8, _mm_and_si128, _mm_slli_si128, _mm_shufflehi_epi16,
-    _mm_shufflelo_epi16, _mm_andnot_si128
+    _mm_shufflelo_epi16, _mm_andnot_si128, _mm_add_epi64, _mm_max_epu8, _mm_sad_epu8,
+    _mm_subs_epu8, _mm_mullo_epi16, _mm_adds_epu8, __m128, _mm_add_ps, _mm_and_ps, _mm_andnot_ps,
+    _mm_castps_si128, _mm_cmpgt_ps, _mm_loadu_ps, _mm_mul_ps, _mm_or_ps, _mm_setr_ps,
+    _mm_storeu_ps, _mm_sub_ps
 };
 
 const LANE_COUNT: usize = 16;
@@ -57,6 +88,13 @@ macro_rules! zero {
     };
 }
 
+macro_rules! div_255_8x {
+    ($x:expr) => {{
+        let x = _mm_add_epi16($x, _mm_set1_epi16(128));
+        _mm_srli_epi16(_mm_add_epi16(x, _mm_srli_epi16(x, 8)), 8)
+    }};
+}
+
 macro_rules! xcgh_odd_even_words {
     () => {
         mm_shuffle(2, 3, 0, 1)
@@ -105,10 +143,30 @@ const BACKWARD_WEIGHTS: [[i16; 8]; Colorimetry::Length as usize] = [
     ],
 ];
 
+/// Weights of full range rgb to ycbcr, as libjpeg computes it.
+/// The weight of blue in cb and of red in cr is one half, and it is applied with a shift.
+const JFIF_FORWARD_WEIGHTS: [i32; 6] = [
+    i32x2_to_i32(XG_JFIF - SHORT_HALF, XR_JFIF),
+    i32x2_to_i32(SHORT_HALF, XB_JFIF),
+    i32x2_to_i32(ZG_JFIF, 0),
+    i32x2_to_i32(YG_JFIF, YR_JFIF),
+    i32x2_to_i32(0, ZB_JFIF),
+    0,
+];
+
+/// Weights of full range ycbcr to rgb, as libjpeg computes it.
+/// Weights are split into a low and a high byte, applied to chroma and to chroma << 8
+const JFIF_BACKWARD_WEIGHTS: [i32; 4] = [
+    i32x2_to_i32(BCB_JFIF >> 8, BCB_JFIF & 0xFF),
+    i32x2_to_i32(0, -GCB_JFIF),
+    i32x2_to_i32(-(GCR_JFIF >> 8), -(GCR_JFIF & 0xFF)),
+    i32x2_to_i32(RCR_JFIF >> 8, RCR_JFIF & 0xFF),
+];
+
 /// Convert fixed point to int (4-wide)
 macro_rules! fix_to_i32_4x {
     ($fix:expr, $frac_bits:expr) => {
-        _mm_srai_epi32($fix, $frac_bits);
+        _mm_srai_epi32($fix, $frac_bits)
     };
 }
 
@@ -136,7 +194,7 @@ unsafe fn i16_to_i16x2_8x(x: __m128i) -> (__m128i, __m128i) {
 /// res:   g7--g6-- g5--g4-- g3--g2-- g1--g0--
 #[inline(always)]
 unsafe fn unpack_ui8_i16be_8x(image: *const u8) -> __m128i {
-    let x = _mm_set1_epi64x(*(image as *const i64));
+    let x = _mm_set1_epi64x(read_unaligned(image as *const i64));
     _mm_unpacklo_epi8(zero!(), x)
 }
 
@@ -192,9 +250,9 @@ unsafe fn unpack_ui8x3_i16x2_4x(image: *const u8, sampler: Sampler) -> (__m128i,
     let line = match sampler {
         Sampler::BgrOverflow => _mm_set_epi32(
             0,
-            *(image.offset(8) as *const i32),
-            *(image.offset(4) as *const i32),
-            *(image as *const i32),
+            read_unaligned(image.offset(8) as *const i32),
+            read_unaligned(image.offset(4) as *const i32),
+            read_unaligned(image as *const i32),
         ),
         _ => _mm_loadu_si128(image as *const __m128i),
     };
@@ -255,7 +313,7 @@ unsafe fn rgb_to_bgra_4x(input: __m128i, output_buffer: *mut __m128i) {
 unsafe fn pack_i32_4x(image: *mut u8, red: __m128i) {
     let y = _mm_packs_epi32(red, red);
     let z = _mm_packus_epi16(y, y);
-    *(image as *mut i32) = _mm_cvtsi128_si32(z);
+    write_unaligned(image as *mut i32, _mm_cvtsi128_si32(z));
 }
 
 #[inline(always)]
@@ -357,8 +415,8 @@ unsafe fn lrgb_to_i420_4x(
     let packed_to_16 = _mm_packus_epi16(packed_to_32, packed_to_32);
     let uv_res = _mm_cvtsi128_si32(packed_to_16) as u32;
 
-    *(u as *mut u16) = uv_res as u16;
-    *(v as *mut u16) = (uv_res >> 16) as u16;
+    write_unaligned(u as *mut u16, uv_res as u16);
+    write_unaligned(v as *mut u16, (uv_res >> 16) as u16);
 }
 
 #[inline(always)]
@@ -371,6 +429,7 @@ unsafe fn lrgb_to_i444_4x(
     y_weights: &[__m128i; 3],
     u_weights: &[__m128i; 3],
     v_weights: &[__m128i; 3],
+    jfif: bool,
 ) {
     let (rg, bg) = unpack_ui8x3_i16x2_4x(rgb, sampler);
     pack_i32_4x(
@@ -378,15 +437,16 @@ unsafe fn lrgb_to_i444_4x(
         fix_to_i32_4x!(affine_transform(rg, bg, y_weights), FIX16),
     );
 
-    pack_i32_4x(
-        u,
-        fix_to_i32_4x!(affine_transform(rg, bg, u_weights), FIX16),
-    );
+    let mut u_fix = affine_transform(rg, bg, u_weights);
+    let mut v_fix = affine_transform(rg, bg, v_weights);
+    if jfif {
+        // Add blue << 15 to cb and red << 15 to cr
+        u_fix = _mm_add_epi32(u_fix, _mm_srli_epi32(_mm_slli_epi32(bg, 16), 1));
+        v_fix = _mm_add_epi32(v_fix, _mm_srli_epi32(_mm_slli_epi32(rg, 16), 1));
+    }
 
-    pack_i32_4x(
-        v,
-        fix_to_i32_4x!(affine_transform(rg, bg, v_weights), FIX16),
-    );
+    pack_i32_4x(u, fix_to_i32_4x!(u_fix, FIX16));
+    pack_i32_4x(v, fix_to_i32_4x!(v_fix, FIX16));
 }
 
 #[inline(always)]
@@ -469,7 +529,7 @@ unsafe fn lrgb_to_yuv_sse2(
 
     let rgb_plane = &src_buffers[0];
     let (first, last) = dst_buffers.split_at_mut(last_dst_plane);
-    let interplane_split = y_stride * line_count;
+    let interplane_split = y_stride.saturating_mul(line_count);
     if last_dst_plane == 0 && interplane_split > last[0].len() {
         return false;
     }
@@ -811,6 +871,7 @@ fn lrgb_to_i444(
     channels: PixelFormatChannels,
     colorimetry: Colorimetry,
     sampler: Sampler,
+    jfif: bool,
 ) -> bool {
     unsafe {
         lrgb_to_i444_sse2(
@@ -825,6 +886,7 @@ fn lrgb_to_i444(
             channels,
             colorimetry,
             sampler,
+            jfif,
         )
     }
 }
@@ -843,6 +905,7 @@ unsafe fn lrgb_to_i444_sse2(
     channels: PixelFormatChannels,
     colorimetry: Colorimetry,
     sampler: Sampler,
+    jfif: bool,
 ) -> bool {
     if last_dst_plane != 2
         || (last_dst_plane >= dst_strides.len())
@@ -911,24 +974,37 @@ unsafe fn lrgb_to_i444_sse2(
         return false;
     }
 
-    let col = colorimetry as usize;
+    let (col_count, line_count) = collapse_lines(
+        col_count,
+        line_count,
+        rgb_stride == packed_rgb_stride
+            && y_stride == col_count
+            && u_stride == col_count
+            && v_stride == col_count,
+    );
+
+    let (weights, y_offset, c_offset) = if jfif {
+        (&JFIF_FORWARD_WEIGHTS, FIX16_HALF, C_OFFSET16 - 1)
+    } else {
+        (&FORWARD_WEIGHTS[colorimetry as usize], Y_OFFSET, C_OFFSET16)
+    };
 
     let y_weights = [
-        _mm_set1_epi32(FORWARD_WEIGHTS[col][0]),
-        _mm_set1_epi32(FORWARD_WEIGHTS[col][1]),
-        _mm_set1_epi32(Y_OFFSET),
+        _mm_set1_epi32(weights[0]),
+        _mm_set1_epi32(weights[1]),
+        _mm_set1_epi32(y_offset),
     ];
 
     let u_weights = [
-        _mm_set1_epi32(FORWARD_WEIGHTS[col][3]),
-        _mm_set1_epi32(FORWARD_WEIGHTS[col][5]),
-        _mm_set1_epi32(C_OFFSET16),
+        _mm_set1_epi32(weights[3]),
+        _mm_set1_epi32(weights[5]),
+        _mm_set1_epi32(c_offset),
     ];
 
     let v_weights = [
-        _mm_set1_epi32(FORWARD_WEIGHTS[col][2]),
-        _mm_set1_epi32(FORWARD_WEIGHTS[col][4]),
-        _mm_set1_epi32(C_OFFSET16),
+        _mm_set1_epi32(weights[2]),
+        _mm_set1_epi32(weights[4]),
+        _mm_set1_epi32(c_offset),
     ];
 
     let rgb_depth = depth * LRGB_TO_YUV_WAVES;
@@ -958,6 +1034,7 @@ unsafe fn lrgb_to_i444_sse2(
                 &y_weights,
                 &u_weights,
                 &v_weights,
+                jfif,
             );
         }
     }
@@ -975,6 +1052,7 @@ unsafe fn lrgb_to_i444_sse2(
                 &y_weights,
                 &u_weights,
                 &v_weights,
+                jfif,
             );
         }
 
@@ -988,6 +1066,7 @@ unsafe fn lrgb_to_i444_sse2(
             &y_weights,
             &u_weights,
             &v_weights,
+            jfif,
         );
     }
 
@@ -1071,7 +1150,7 @@ unsafe fn yuv_to_lrgb_sse2(
 
     let rgb_plane = &mut dst_buffers[0];
     let (first, last) = src_buffers.split_at(last_src_plane);
-    let interplane_split = y_stride * line_count;
+    let interplane_split = y_stride.saturating_mul(line_count);
     if last_src_plane == 0 && interplane_split > last[0].len() {
         return false;
     }
@@ -1364,6 +1443,57 @@ unsafe fn i420_to_lrgb_sse2(
     true
 }
 
+/// Convert full range ycbcr to bgra exactly as libjpeg does (8-wide)
+#[inline(always)]
+unsafe fn i444_jfif_to_bgra_8x(y: *const u8, u: *const u8, v: *const u8, rgb: *mut u8) {
+    let c_half = _mm_set1_epi16(C_HALF as i16);
+    let round = _mm_set1_epi32(FIX16_HALF);
+    let bcb = _mm_set1_epi32(JFIF_BACKWARD_WEIGHTS[0]);
+    let gcb = _mm_set1_epi32(JFIF_BACKWARD_WEIGHTS[1]);
+    let gcr = _mm_set1_epi32(JFIF_BACKWARD_WEIGHTS[2]);
+    let rcr = _mm_set1_epi32(JFIF_BACKWARD_WEIGHTS[3]);
+
+    let l = _mm_unpacklo_epi8(_mm_loadl_epi64(y as *const __m128i), zero!());
+    let cb = _mm_sub_epi16(
+        _mm_unpacklo_epi8(_mm_loadl_epi64(u as *const __m128i), zero!()),
+        c_half,
+    );
+    let cr = _mm_sub_epi16(
+        _mm_unpacklo_epi8(_mm_loadl_epi64(v as *const __m128i), zero!()),
+        c_half,
+    );
+
+    // Pairs of (chroma, chroma << 8), for the low and high four samples
+    let (cb_lo, cb_hi) = (
+        _mm_unpacklo_epi16(cb, _mm_slli_epi16(cb, 8)),
+        _mm_unpackhi_epi16(cb, _mm_slli_epi16(cb, 8)),
+    );
+    let (cr_lo, cr_hi) = (
+        _mm_unpacklo_epi16(cr, _mm_slli_epi16(cr, 8)),
+        _mm_unpackhi_epi16(cr, _mm_slli_epi16(cr, 8)),
+    );
+
+    let chroma = |lo: __m128i, hi: __m128i| {
+        _mm_add_epi16(
+            l,
+            _mm_packs_epi32(
+                fix_to_i32_4x!(_mm_add_epi32(lo, round), FIX16),
+                fix_to_i32_4x!(_mm_add_epi32(hi, round), FIX16),
+            ),
+        )
+    };
+
+    pack_i16x3_8x(
+        rgb,
+        chroma(_mm_madd_epi16(cr_lo, rcr), _mm_madd_epi16(cr_hi, rcr)),
+        chroma(
+            _mm_add_epi32(_mm_madd_epi16(cb_lo, gcb), _mm_madd_epi16(cr_lo, gcr)),
+            _mm_add_epi32(_mm_madd_epi16(cb_hi, gcb), _mm_madd_epi16(cr_hi, gcr)),
+        ),
+        chroma(_mm_madd_epi16(cb_lo, bcb), _mm_madd_epi16(cb_hi, bcb)),
+    );
+}
+
 #[inline(always)]
 fn i444_to_lrgb(
     width: u32,
@@ -1376,6 +1506,7 @@ fn i444_to_lrgb(
     dst_buffers: &mut [&mut [u8]],
     channels: PixelFormatChannels,
     colorimetry: Colorimetry,
+    jfif: bool,
 ) -> bool {
     unsafe {
         i444_to_lrgb_sse2(
@@ -1389,6 +1520,7 @@ fn i444_to_lrgb(
             dst_buffers,
             channels,
             colorimetry,
+            jfif,
         )
     }
 }
@@ -1406,6 +1538,7 @@ unsafe fn i444_to_lrgb_sse2(
     dst_buffers: &mut [&mut [u8]],
     channels: PixelFormatChannels,
     colorimetry: Colorimetry,
+    jfif: bool,
 ) -> bool {
     if last_src_plane != 2
         || last_src_plane >= src_strides.len()
@@ -1469,6 +1602,38 @@ unsafe fn i444_to_lrgb_sse2(
         return false;
     }
 
+    let (col_count, line_count) = collapse_lines(
+        col_count,
+        line_count,
+        rgb_stride == packed_rgb_stride
+            && y_stride == col_count
+            && u_stride == col_count
+            && v_stride == col_count,
+    );
+
+    let y_group = y_plane.as_ptr();
+    let u_group = u_plane.as_ptr();
+    let v_group = v_plane.as_ptr();
+    let rgb_group = rgb_plane.as_mut_ptr();
+    let rgb_depth = YUV_TO_LRGB_WAVES * 2;
+    let group_width = YUV_TO_LRGB_WAVES / 2;
+    let wg_width = col_count / group_width;
+
+    if jfif {
+        for y in 0..line_count {
+            for x in 0..wg_width {
+                i444_jfif_to_bgra_8x(
+                    y_group.add(wg_index(x, y, group_width, y_stride)),
+                    u_group.add(wg_index(x, y, group_width, u_stride)),
+                    v_group.add(wg_index(x, y, group_width, v_stride)),
+                    rgb_group.add(wg_index(x, y, rgb_depth, rgb_stride)),
+                );
+            }
+        }
+
+        return true;
+    }
+
     let col = colorimetry as usize;
 
     let xxym = _mm_set1_epi16(BACKWARD_WEIGHTS[col][0]);
@@ -1480,14 +1645,6 @@ unsafe fn i444_to_lrgb_sse2(
     let gp = _mm_set1_epi16(BACKWARD_WEIGHTS[col][6]);
     let bn = _mm_set1_epi16(BACKWARD_WEIGHTS[col][7]);
 
-    let y_group = y_plane.as_ptr();
-    let u_group = u_plane.as_ptr();
-    let v_group = v_plane.as_ptr();
-    let rgb_group = rgb_plane.as_mut_ptr();
-    let rgb_depth = YUV_TO_LRGB_WAVES * 2;
-    let group_width = YUV_TO_LRGB_WAVES / 2;
-    let wg_width = col_count / group_width;
-
     for y in 0..line_count {
         for x in 0..wg_width {
             let cb0 = _mm_loadl_epi64(
@@ -1598,6 +1755,12 @@ unsafe fn rgb_to_bgra_sse2 (
         src_strides[0] - (INPUT_BPP * w)
     };
 
+    let (w, h) = collapse_lines(
+        w,
+        height as usize,
+        input_stride_diff == 0 && output_stride_diff == 0,
+    );
+
     let first_pixel_mask = _mm_set_epi32(0, 0, 0, -1);
     let first_two_pixels_mask = _mm_set_epi32(0, 0, -1, -1);
     let output_buffer = dst_buffers[0].as_mut_ptr();
@@ -1605,8 +1768,8 @@ unsafe fn rgb_to_bgra_sse2 (
     let mut ibuffer_offset = 0;
     let mut obuffer_offset = 0;
 
-    for _ in 0..height {
-        for _ in (0..width).step_by(LANE_COUNT) {
+    for _ in 0..h {
+        for _ in (0..w).step_by(LANE_COUNT) {
             let input0 = _mm_loadu_si128(input_buffer.add(ibuffer_offset) as *const __m128i);
             let input1 =
                 _mm_loadu_si128(input_buffer.add(ibuffer_offset + LANE_COUNT) as *const __m128i);
@@ -2096,6 +2259,7 @@ pub fn i444_bt601_bgra_lrgb(
             dst_buffers,
             PixelFormatChannels::Four,
             Colorimetry::Bt601,
+            false,
         )
     } else {
         x86::i444_bt601_bgra_lrgb(
@@ -2133,6 +2297,7 @@ pub fn i444_bt709_bgra_lrgb(
             dst_buffers,
             PixelFormatChannels::Four,
             Colorimetry::Bt709,
+            false,
         )
     } else {
         x86::i444_bt709_bgra_lrgb(
@@ -2148,6 +2313,44 @@ pub fn i444_bt709_bgra_lrgb(
     }
 }
 
+pub fn i444_jfif_bgra_lrgb(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    if is_wg_multiple(width, YUV_TO_LRGB_WAVES / 2) {
+        i444_to_lrgb(
+            width,
+            height,
+            last_src_plane as usize,
+            src_strides,
+            src_buffers,
+            last_dst_plane as usize,
+            dst_strides,
+            dst_buffers,
+            PixelFormatChannels::Four,
+            Colorimetry::Bt601,
+            true,
+        )
+    } else {
+        x86::i444_jfif_bgra_lrgb(
+            width,
+            height,
+            last_src_plane,
+            src_strides,
+            src_buffers,
+            last_dst_plane,
+            dst_strides,
+            dst_buffers,
+        )
+    }
+}
+
 pub fn argb_lrgb_i420_bt601(
     width: u32,
     height: u32,
@@ -2399,6 +2602,7 @@ pub fn argb_lrgb_i444_bt601(
             PixelFormatChannels::Four,
             Colorimetry::Bt601,
             Sampler::Argb,
+            false,
         )
     } else {
         x86::argb_lrgb_i444_bt601(
@@ -2437,6 +2641,7 @@ pub fn argb_lrgb_i444_bt709(
             PixelFormatChannels::Four,
             Colorimetry::Bt709,
             Sampler::Argb,
+            false,
         )
     } else {
         x86::argb_lrgb_i444_bt709(
@@ -2475,6 +2680,7 @@ pub fn bgra_lrgb_i444_bt601(
             PixelFormatChannels::Four,
             Colorimetry::Bt601,
             Sampler::Bgra,
+            false,
         )
     } else {
         x86::bgra_lrgb_i444_bt601(
@@ -2513,6 +2719,7 @@ pub fn bgra_lrgb_i444_bt709(
             PixelFormatChannels::Four,
             Colorimetry::Bt709,
             Sampler::Bgra,
+            false,
         )
     } else {
         x86::bgra_lrgb_i444_bt709(
@@ -2551,6 +2758,7 @@ pub fn bgr_lrgb_i444_bt601(
             PixelFormatChannels::Three,
             Colorimetry::Bt601,
             Sampler::Bgr,
+            false,
         )
     } else {
         x86::bgr_lrgb_i444_bt601(
@@ -2589,6 +2797,7 @@ pub fn bgr_lrgb_i444_bt709(
             PixelFormatChannels::Three,
             Colorimetry::Bt709,
             Sampler::Bgr,
+            false,
         )
     } else {
         x86::bgr_lrgb_i444_bt709(
@@ -2604,6 +2813,123 @@ pub fn bgr_lrgb_i444_bt709(
     }
 }
 
+pub fn argb_lrgb_i444_jfif(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    if is_wg_multiple(width, LRGB_TO_YUV_WAVES) {
+        lrgb_to_i444(
+            width,
+            height,
+            last_src_plane as usize,
+            src_strides,
+            src_buffers,
+            last_dst_plane as usize,
+            dst_strides,
+            dst_buffers,
+            PixelFormatChannels::Four,
+            Colorimetry::Bt601,
+            Sampler::Argb,
+            true,
+        )
+    } else {
+        x86::argb_lrgb_i444_jfif(
+            width,
+            height,
+            last_src_plane,
+            src_strides,
+            src_buffers,
+            last_dst_plane,
+            dst_strides,
+            dst_buffers,
+        )
+    }
+}
+
+pub fn bgra_lrgb_i444_jfif(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    if is_wg_multiple(width, LRGB_TO_YUV_WAVES) {
+        lrgb_to_i444(
+            width,
+            height,
+            last_src_plane as usize,
+            src_strides,
+            src_buffers,
+            last_dst_plane as usize,
+            dst_strides,
+            dst_buffers,
+            PixelFormatChannels::Four,
+            Colorimetry::Bt601,
+            Sampler::Bgra,
+            true,
+        )
+    } else {
+        x86::bgra_lrgb_i444_jfif(
+            width,
+            height,
+            last_src_plane,
+            src_strides,
+            src_buffers,
+            last_dst_plane,
+            dst_strides,
+            dst_buffers,
+        )
+    }
+}
+
+pub fn bgr_lrgb_i444_jfif(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    if is_wg_multiple(width, LRGB_TO_YUV_WAVES) {
+        lrgb_to_i444(
+            width,
+            height,
+            last_src_plane as usize,
+            src_strides,
+            src_buffers,
+            last_dst_plane as usize,
+            dst_strides,
+            dst_buffers,
+            PixelFormatChannels::Three,
+            Colorimetry::Bt601,
+            Sampler::Bgr,
+            true,
+        )
+    } else {
+        x86::bgr_lrgb_i444_jfif(
+            width,
+            height,
+            last_src_plane,
+            src_strides,
+            src_buffers,
+            last_dst_plane,
+            dst_strides,
+            dst_buffers,
+        )
+    }
+}
+
 pub fn bgra_lrgb_rgb_lrgb(
     width: u32,
     height: u32,
@@ -2625,3 +2951,316 @@ pub fn bgra_lrgb_rgb_lrgb(
         dst_buffers,
     )
 }
+
+#[inline(always)]
+pub fn abs_diff_sum(a: &[u8], b: &[u8]) -> (u64, u8) {
+    unsafe { abs_diff_sum_sse2(a, b) }
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn abs_diff_sum_sse2(a: &[u8], b: &[u8]) -> (u64, u8) {
+    let len = a.len().min(b.len());
+    let vector_part = len - (len % LANE_COUNT);
+
+    let mut sum = zero!();
+    let mut max = zero!();
+    for i in (0..vector_part).step_by(LANE_COUNT) {
+        let x = _mm_loadu_si128(a.as_ptr().add(i) as *const __m128i);
+        let y = _mm_loadu_si128(b.as_ptr().add(i) as *const __m128i);
+        let delta = _mm_or_si128(_mm_subs_epu8(x, y), _mm_subs_epu8(y, x));
+        max = _mm_max_epu8(max, delta);
+        sum = _mm_add_epi64(sum, _mm_sad_epu8(x, y));
+    }
+
+    let mut sums = [0u64; 2];
+    let mut maxs = [0u8; LANE_COUNT];
+    _mm_storeu_si128(sums.as_mut_ptr() as *mut __m128i, sum);
+    _mm_storeu_si128(maxs.as_mut_ptr() as *mut __m128i, max);
+
+    let (tail_sum, tail_max) = x86::abs_diff_sum(&a[vector_part..len], &b[vector_part..len]);
+    (
+        sums[0] + sums[1] + tail_sum,
+        maxs.iter().fold(tail_max, |acc, &value| acc.max(value)),
+    )
+}
+
+#[inline(always)]
+pub fn blend_over(src: &[u8], dst: &mut [u8], premultiplied: bool) {
+    unsafe { blend_over_sse2(src, dst, premultiplied) }
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn blend_over_sse2(src: &[u8], dst: &mut [u8], premultiplied: bool) {
+    let len = src.len().min(dst.len()) & !3;
+    let vector_part = len - (len % LANE_COUNT);
+
+    let max = _mm_set1_epi16(255);
+    let opaque = _mm_set1_epi32(-0x0100_0000);
+    for i in (0..vector_part).step_by(LANE_COUNT) {
+        let s = _mm_loadu_si128(src.as_ptr().add(i) as *const __m128i);
+        let d = _mm_loadu_si128(dst.as_ptr().add(i) as *const __m128i);
+        let s_lo = _mm_unpacklo_epi8(s, zero!());
+        let s_hi = _mm_unpackhi_epi8(s, zero!());
+        let d_lo = _mm_unpacklo_epi8(d, zero!());
+        let d_hi = _mm_unpackhi_epi8(d, zero!());
+
+        // Replicate alpha on the four samples of each pixel
+        let a_lo = _mm_shufflehi_epi16(
+            _mm_shufflelo_epi16(s_lo, mm_shuffle(3, 3, 3, 3)),
+            mm_shuffle(3, 3, 3, 3),
+        );
+        let a_hi = _mm_shufflehi_epi16(
+            _mm_shufflelo_epi16(s_hi, mm_shuffle(3, 3, 3, 3)),
+            mm_shuffle(3, 3, 3, 3),
+        );
+
+        let d_lo = _mm_mullo_epi16(d_lo, _mm_sub_epi16(max, a_lo));
+        let d_hi = _mm_mullo_epi16(d_hi, _mm_sub_epi16(max, a_hi));
+        let (lo, hi) = if premultiplied {
+            (
+                _mm_add_epi16(s_lo, div_255_8x!(d_lo)),
+                _mm_add_epi16(s_hi, div_255_8x!(d_hi)),
+            )
+        } else {
+            // Alpha of the result is computed as the one of an opaque color
+            let c = _mm_or_si128(s, opaque);
+            let c_lo = _mm_mullo_epi16(_mm_unpacklo_epi8(c, zero!()), a_lo);
+            let c_hi = _mm_mullo_epi16(_mm_unpackhi_epi8(c, zero!()), a_hi);
+            (
+                div_255_8x!(_mm_add_epi16(c_lo, d_lo)),
+                div_255_8x!(_mm_add_epi16(c_hi, d_hi)),
+            )
+        };
+
+        _mm_storeu_si128(
+            dst.as_mut_ptr().add(i) as *mut __m128i,
+            _mm_packus_epi16(lo, hi),
+        );
+    }
+
+    x86::blend_over(&src[vector_part..len], &mut dst[vector_part..len], premultiplied);
+}
+
+// Table look-ups need a byte shuffle, which sse2 does not have
+#[inline(always)]
+pub fn remap(samples: &mut [u8], even: &[u8; 256], odd: &[u8; 256]) {
+    x86::remap(samples, even, odd);
+}
+
+#[inline(always)]
+pub fn cross_fade(src: &[u8], dst: &mut [u8], alpha: u8) {
+    unsafe { cross_fade_sse2(src, dst, alpha) }
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn cross_fade_sse2(src: &[u8], dst: &mut [u8], alpha: u8) {
+    let len = src.len().min(dst.len());
+    let vector_part = len - (len % LANE_COUNT);
+
+    let a = _mm_set1_epi16(i16::from(alpha));
+    let inv_a = _mm_set1_epi16(i16::from(255 - alpha));
+    for i in (0..vector_part).step_by(LANE_COUNT) {
+        let s = _mm_loadu_si128(src.as_ptr().add(i) as *const __m128i);
+        let d = _mm_loadu_si128(dst.as_ptr().add(i) as *const __m128i);
+        let lo = _mm_add_epi16(
+            _mm_mullo_epi16(_mm_unpacklo_epi8(s, zero!()), a),
+            _mm_mullo_epi16(_mm_unpacklo_epi8(d, zero!()), inv_a),
+        );
+        let hi = _mm_add_epi16(
+            _mm_mullo_epi16(_mm_unpackhi_epi8(s, zero!()), a),
+            _mm_mullo_epi16(_mm_unpackhi_epi8(d, zero!()), inv_a),
+        );
+
+        _mm_storeu_si128(
+            dst.as_mut_ptr().add(i) as *mut __m128i,
+            _mm_packus_epi16(div_255_8x!(lo), div_255_8x!(hi)),
+        );
+    }
+
+    x86::cross_fade(&src[vector_part..len], &mut dst[vector_part..len], alpha);
+}
+
+#[inline(always)]
+pub fn accumulate(src: &[u8], dst: &mut [u8], alpha: u8) {
+    unsafe { accumulate_sse2(src, dst, alpha) }
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn accumulate_sse2(src: &[u8], dst: &mut [u8], alpha: u8) {
+    let len = src.len().min(dst.len());
+    let vector_part = len - (len % LANE_COUNT);
+
+    let a = _mm_set1_epi16(i16::from(alpha));
+    for i in (0..vector_part).step_by(LANE_COUNT) {
+        let s = _mm_loadu_si128(src.as_ptr().add(i) as *const __m128i);
+        let d = _mm_loadu_si128(dst.as_ptr().add(i) as *const __m128i);
+        let lo = _mm_mullo_epi16(_mm_unpacklo_epi8(s, zero!()), a);
+        let hi = _mm_mullo_epi16(_mm_unpackhi_epi8(s, zero!()), a);
+
+        _mm_storeu_si128(
+            dst.as_mut_ptr().add(i) as *mut __m128i,
+            _mm_adds_epu8(d, _mm_packus_epi16(div_255_8x!(lo), div_255_8x!(hi))),
+        );
+    }
+
+    x86::accumulate(&src[vector_part..len], &mut dst[vector_part..len], alpha);
+}
+
+/// Number of points interpolated at once by the vectorized look-up table kernels
+const CUBE_LANE_COUNT: usize = 4;
+
+#[inline(always)]
+unsafe fn select_ps(mask: __m128, a: __m128, b: __m128) -> __m128 {
+    _mm_or_ps(_mm_and_ps(mask, a), _mm_andnot_ps(mask, b))
+}
+
+#[inline(always)]
+unsafe fn select_epi32(mask: __m128, a: __m128i, b: __m128i) -> __m128i {
+    let mask = _mm_castps_si128(mask);
+    _mm_or_si128(_mm_and_si128(mask, a), _mm_andnot_si128(mask, b))
+}
+
+/// Loads the red, green and blue of the table points at the given indices
+#[inline(always)]
+unsafe fn load_points(table: &[[f32; 3]], index: __m128i) -> [__m128; 3] {
+    let mut lanes = [0u32; CUBE_LANE_COUNT];
+    _mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, index);
+
+    let p0 = table.get_unchecked(lanes[0] as usize);
+    let p1 = table.get_unchecked(lanes[1] as usize);
+    let p2 = table.get_unchecked(lanes[2] as usize);
+    let p3 = table.get_unchecked(lanes[3] as usize);
+    [
+        _mm_setr_ps(p0[0], p1[0], p2[0], p3[0]),
+        _mm_setr_ps(p0[1], p1[1], p2[1], p3[1]),
+        _mm_setr_ps(p0[2], p1[2], p2[2], p3[2]),
+    ]
+}
+
+#[inline(always)]
+pub fn interpolate_cube(
+    table: &[[f32; 3]],
+    size: usize,
+    index: &[u32],
+    fractions: [&[f32]; 3],
+    out: [&mut [f32]; 3],
+) {
+    unsafe { interpolate_cube_sse2(table, size, index, fractions, out) }
+}
+
+#[inline]
+#[target_feature(enable = "sse2")]
+unsafe fn interpolate_cube_sse2(
+    table: &[[f32; 3]],
+    size: usize,
+    index: &[u32],
+    fractions: [&[f32]; 3],
+    out: [&mut [f32]; 3],
+) {
+    let [fr, fg, fb] = fractions;
+    let [r, g, b] = out;
+    let len = index
+        .len()
+        .min(fr.len())
+        .min(fg.len())
+        .min(fb.len())
+        .min(r.len())
+        .min(g.len())
+        .min(b.len());
+
+    // Points are loaded unchecked, cells reaching past the end of the table are left
+    // to the portable kernel
+    let upper = 1 + size + size * size;
+    let vector_part = if index[..len]
+        .iter()
+        .all(|&base| (base as usize) + upper < table.len())
+    {
+        len - (len % CUBE_LANE_COUNT)
+    } else {
+        0
+    };
+
+    let dr = _mm_set1_epi32(1);
+    let dg = _mm_set1_epi32(size as i32);
+    let db = _mm_set1_epi32((size * size) as i32);
+    let d111 = _mm_set1_epi32(upper as i32);
+    for i in (0..vector_part).step_by(CUBE_LANE_COUNT) {
+        let vr = _mm_loadu_ps(fr.as_ptr().add(i));
+        let vg = _mm_loadu_ps(fg.as_ptr().add(i));
+        let vb = _mm_loadu_ps(fb.as_ptr().add(i));
+        let base = _mm_loadu_si128(index.as_ptr().add(i) as *const __m128i);
+
+        // Same tetrahedra as the portable kernel, also when fractions are equal
+        let rg = _mm_cmpgt_ps(vr, vg);
+        let gb = _mm_cmpgt_ps(vg, vb);
+        let rb = _mm_cmpgt_ps(vr, vb);
+        let bg = _mm_cmpgt_ps(vb, vg);
+        let br = _mm_cmpgt_ps(vb, vr);
+        let rgb = _mm_and_ps(rg, gb);
+        let rbg = _mm_and_ps(rg, _mm_andnot_ps(gb, rb));
+        let brg = _mm_andnot_ps(_mm_or_ps(gb, rb), rg);
+        let bgr = _mm_andnot_ps(rg, bg);
+        let gbr = _mm_andnot_ps(_mm_or_ps(rg, bg), br);
+
+        // Axes sorted by decreasing fraction
+        let first_r = _mm_or_ps(rgb, rbg);
+        let first_b = _mm_or_ps(brg, bgr);
+        let second_g = _mm_or_ps(rgb, bgr);
+        let second_b = _mm_or_ps(rbg, gbr);
+        let third_g = _mm_or_ps(rbg, brg);
+        let third_r = _mm_or_ps(bgr, gbr);
+
+        let w0 = select_ps(first_r, vr, select_ps(first_b, vb, vg));
+        let w1 = select_ps(second_g, vg, select_ps(second_b, vb, vr));
+        let w2 = select_ps(third_g, vg, select_ps(third_r, vr, vb));
+
+        let d1 = select_epi32(first_r, dr, select_epi32(first_b, db, dg));
+        let d2 = _mm_add_epi32(
+            d1,
+            select_epi32(second_g, dg, select_epi32(second_b, db, dr)),
+        );
+
+        let c000 = load_points(table, base);
+        let p1 = load_points(table, _mm_add_epi32(base, d1));
+        let p2 = load_points(table, _mm_add_epi32(base, d2));
+        let c111 = load_points(table, _mm_add_epi32(base, d111));
+
+        for (channel, plane) in [r.as_mut_ptr(), g.as_mut_ptr(), b.as_mut_ptr()]
+            .iter()
+            .enumerate()
+        {
+            let value = _mm_add_ps(
+                _mm_add_ps(
+                    _mm_add_ps(
+                        c000[channel],
+                        _mm_mul_ps(w0, _mm_sub_ps(p1[channel], c000[channel])),
+                    ),
+                    _mm_mul_ps(w1, _mm_sub_ps(p2[channel], p1[channel])),
+                ),
+                _mm_mul_ps(w2, _mm_sub_ps(c111[channel], p2[channel])),
+            );
+
+            _mm_storeu_ps(plane.add(i), value);
+        }
+    }
+
+    x86::interpolate_cube(
+        table,
+        size,
+        &index[vector_part..len],
+        [
+            &fr[vector_part..len],
+            &fg[vector_part..len],
+            &fb[vector_part..len],
+        ],
+        [
+            &mut r[vector_part..len],
+            &mut g[vector_part..len],
+            &mut b[vector_part..len],
+        ],
+    );
+}
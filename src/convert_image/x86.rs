@@ -14,7 +14,12 @@
 // OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
 // SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 
+// Kernels take the arguments of the conversion dispatcher, which are more than seven
+#![allow(clippy::too_many_arguments)]
+
 use crate::convert_image::common::*;
+use crate::Rounding;
+use core::ptr::{read_unaligned, write_unaligned};
 
 #[cfg(target_arch = "x86")]
 use core::arch::x86::_bswap;
@@ -154,7 +159,8 @@ unsafe fn pack_ui8x3(image: *mut u8, x: u8, y: u8, z: u8) {
     *image.add(3) = DEFAULT_ALPHA;
 }
 
-#[inline(always)]
+#[cfg_attr(not(feature = "small"), inline(always))]
+#[cfg_attr(feature = "small", inline(never))]
 fn lrgb_to_yuv(
     width: u32,
     height: u32,
@@ -202,7 +208,7 @@ fn lrgb_to_yuv(
 
     let rgb_plane = &src_buffers[0];
     let (first, last) = dst_buffers.split_at_mut(last_dst_plane);
-    let interplane_split = y_stride * line_count;
+    let interplane_split = y_stride.saturating_mul(line_count);
     if last_dst_plane == 0 && interplane_split > last[0].len() {
         return false;
     }
@@ -297,7 +303,8 @@ fn lrgb_to_yuv(
     true
 }
 
-#[inline(always)]
+#[cfg_attr(not(feature = "small"), inline(always))]
+#[cfg_attr(feature = "small", inline(never))]
 fn lrgb_to_i420(
     width: u32,
     height: u32,
@@ -448,7 +455,8 @@ fn lrgb_to_i420(
     true
 }
 
-#[inline(always)]
+#[cfg_attr(not(feature = "small"), inline(always))]
+#[cfg_attr(feature = "small", inline(never))]
 fn lrgb_to_i444(
     width: u32,
     height: u32,
@@ -529,6 +537,15 @@ fn lrgb_to_i444(
         return false;
     }
 
+    let (col_count, line_count) = collapse_lines(
+        col_count,
+        line_count,
+        rgb_stride == packed_rgb_stride
+            && y_stride == col_count
+            && u_stride == col_count
+            && v_stride == col_count,
+    );
+
     // The following constants will be automatically propagated as immediate values
     // inside the operations by optimizing compilers.
     let col = colorimetry as usize;
@@ -569,7 +586,8 @@ fn lrgb_to_i444(
     true
 }
 
-#[inline(always)]
+#[cfg_attr(not(feature = "small"), inline(always))]
+#[cfg_attr(feature = "small", inline(never))]
 fn i444_to_lrgb(
     width: u32,
     height: u32,
@@ -644,6 +662,15 @@ fn i444_to_lrgb(
         return false;
     }
 
+    let (col_count, line_count) = collapse_lines(
+        col_count,
+        line_count,
+        rgb_stride == packed_rgb_stride
+            && y_stride == col_count
+            && u_stride == col_count
+            && v_stride == col_count,
+    );
+
     let col = colorimetry as usize;
     let xxym = BACKWARD_WEIGHTS[col][0];
     let rcrm = BACKWARD_WEIGHTS[col][1];
@@ -684,7 +711,8 @@ fn i444_to_lrgb(
     true
 }
 
-#[inline(always)]
+#[cfg_attr(not(feature = "small"), inline(always))]
+#[cfg_attr(feature = "small", inline(never))]
 fn yuv_to_lrgb(
     width: u32,
     height: u32,
@@ -731,7 +759,7 @@ fn yuv_to_lrgb(
 
     let rgb_plane = &mut dst_buffers[0];
     let (first, last) = src_buffers.split_at(last_src_plane);
-    let interplane_split = y_stride * line_count;
+    let interplane_split = y_stride.saturating_mul(line_count);
     if last_src_plane == 0 && interplane_split > last[0].len() {
         return false;
     }
@@ -843,7 +871,8 @@ fn yuv_to_lrgb(
     true
 }
 
-#[inline(always)]
+#[cfg_attr(not(feature = "small"), inline(always))]
+#[cfg_attr(feature = "small", inline(never))]
 fn i420_to_lrgb(
     width: u32,
     height: u32,
@@ -990,7 +1019,8 @@ fn i420_to_lrgb(
     true
 }
 
-#[inline(always)]
+#[cfg_attr(not(feature = "small"), inline(always))]
+#[cfg_attr(feature = "small", inline(never))]
 unsafe fn bgra_to_rgb(
     width: u32,
     height: u32,
@@ -1031,22 +1061,28 @@ unsafe fn bgra_to_rgb(
         src_strides[0] - (INPUT_BPP * w)
     };
 
+    let (w, h) = collapse_lines(
+        w,
+        height as usize,
+        input_stride_diff == 0 && output_stride_diff == 0,
+    );
+
     let limit_4x = w & !(BGRA_RGB_ITEMS_PER_ITERATION_4X - 1);
     let output_buffer = dst_buffers[0].as_mut_ptr();
     let input_buffer = src_buffers[0].as_ptr();
     let mut obuffer_offset;
     let mut ibuffer_offset;
 
-    for i in 0..height as usize {
+    for i in 0..h {
         let mut y = 0;
         obuffer_offset = ((OUTPUT_BPP * w) + output_stride_diff) * i;
         ibuffer_offset = ((INPUT_BPP * w) + input_stride_diff) * i;
 
         while y < limit_4x {
-            let bgra0 = *(input_buffer.add(ibuffer_offset) as *const u64);
-            let bgra1 = *(input_buffer.add(ibuffer_offset + 8) as *const u64);
-            let bgra2 = *(input_buffer.add(ibuffer_offset + 16) as *const u64);
-            let bgra3 = *(input_buffer.add(ibuffer_offset + 24) as *const u64);
+            let bgra0 = read_unaligned(input_buffer.add(ibuffer_offset) as *const u64);
+            let bgra1 = read_unaligned(input_buffer.add(ibuffer_offset + 8) as *const u64);
+            let bgra2 = read_unaligned(input_buffer.add(ibuffer_offset + 16) as *const u64);
+            let bgra3 = read_unaligned(input_buffer.add(ibuffer_offset + 24) as *const u64);
 
             let rgb0 =
                 _bswap64((((bgra0 << 40) & HIGH_MASK) | ((bgra0 >> 16) & LOW_MASK)) as i64) as u64;
@@ -1061,9 +1097,9 @@ unsafe fn bgra_to_rgb(
             let lane2 = (rgb1 >> 16) | (rgb2 << 32);
             let lane3 = (rgb2 >> 32) | (rgb3 << 16);
 
-            *(output_buffer.add(obuffer_offset) as *mut u64) = lane1;
-            *(output_buffer.add(obuffer_offset + 8) as *mut u64) = lane2;
-            *(output_buffer.add(obuffer_offset + 16) as *mut u64) = lane3;
+            write_unaligned(output_buffer.add(obuffer_offset) as *mut u64, lane1);
+            write_unaligned(output_buffer.add(obuffer_offset + 8) as *mut u64, lane2);
+            write_unaligned(output_buffer.add(obuffer_offset + 16) as *mut u64, lane3);
 
             ibuffer_offset += INPUT_BPP * BGRA_RGB_ITEMS_PER_ITERATION_4X;
             obuffer_offset += OUTPUT_BPP * BGRA_RGB_ITEMS_PER_ITERATION_4X;
@@ -1139,6 +1175,12 @@ pub fn rgb_lrgb_bgra_lrgb(
         src_strides[0] - (INPUT_BPP * w)
     };
 
+    let (w, h) = collapse_lines(
+        w,
+        height as usize,
+        input_stride_diff == 0 && output_stride_diff == 0,
+    );
+
     // For single swap iteration, since the swap is done on 32 bits while the input is only
     // 24 bits (RGB), to avoid reading an extra byte of memory that could be outside the
     // boundaries of the buffer it is necessary to check if the there is at least one byte
@@ -1165,36 +1207,60 @@ pub fn rgb_lrgb_bgra_lrgb(
         let mut ibuffer_offset = 0;
         let mut obuffer_offset = 0;
 
-        for _ in 0..height {
+        for _ in 0..h {
             let mut x = 0;
 
             // Retrieves items_per_iteration colors per cycle if possible
             for _ in (0..multi_swap_iterations).step_by(ITEMS_PER_ITERATION) {
-                *(obuffer.add(obuffer_offset) as *mut i64) = _bswap64(
-                    (((*(ibuffer.add(ibuffer_offset) as *const u64) >> SHIFT_16) & LOW_MASK)
-                        | ((*(ibuffer.add(ibuffer_offset) as *const u64) << SHIFT_40) & HIGH_MASK)
-                        | ALPHAS_MASK) as i64,
+                write_unaligned(
+                    obuffer.add(obuffer_offset) as *mut i64,
+                    _bswap64(
+                        (((read_unaligned(ibuffer.add(ibuffer_offset) as *const u64) >> SHIFT_16)
+                            & LOW_MASK)
+                            | ((read_unaligned(ibuffer.add(ibuffer_offset) as *const u64)
+                                << SHIFT_40)
+                                & HIGH_MASK)
+                            | ALPHAS_MASK) as i64,
+                    ),
                 );
 
-                *(obuffer.add(obuffer_offset + 8) as *mut i64) = _bswap64(
-                    (((*(ibuffer.add(ibuffer_offset + 6) as *const u64) >> SHIFT_16) & LOW_MASK)
-                        | ((*(ibuffer.add(ibuffer_offset + 6) as *const u64) << SHIFT_40)
-                            & HIGH_MASK)
-                        | ALPHAS_MASK) as i64,
+                write_unaligned(
+                    obuffer.add(obuffer_offset + 8) as *mut i64,
+                    _bswap64(
+                        (((read_unaligned(ibuffer.add(ibuffer_offset + 6) as *const u64)
+                            >> SHIFT_16)
+                            & LOW_MASK)
+                            | ((read_unaligned(ibuffer.add(ibuffer_offset + 6) as *const u64)
+                                << SHIFT_40)
+                                & HIGH_MASK)
+                            | ALPHAS_MASK) as i64,
+                    ),
                 );
 
-                *(obuffer.add(obuffer_offset + 16) as *mut i64) = _bswap64(
-                    (((*(ibuffer.add(ibuffer_offset + 12) as *const u64) >> SHIFT_16) & LOW_MASK)
-                        | ((*(ibuffer.add(ibuffer_offset + 12) as *const u64) << SHIFT_40)
-                            & HIGH_MASK)
-                        | ALPHAS_MASK) as i64,
+                write_unaligned(
+                    obuffer.add(obuffer_offset + 16) as *mut i64,
+                    _bswap64(
+                        (((read_unaligned(ibuffer.add(ibuffer_offset + 12) as *const u64)
+                            >> SHIFT_16)
+                            & LOW_MASK)
+                            | ((read_unaligned(ibuffer.add(ibuffer_offset + 12) as *const u64)
+                                << SHIFT_40)
+                                & HIGH_MASK)
+                            | ALPHAS_MASK) as i64,
+                    ),
                 );
 
-                *(obuffer.add(obuffer_offset + 24) as *mut i64) = _bswap64(
-                    (((*(ibuffer.add(ibuffer_offset + 18) as *const u64) >> SHIFT_16) & LOW_MASK)
-                        | ((*(ibuffer.add(ibuffer_offset + 18) as *const u64) << SHIFT_40)
-                            & HIGH_MASK)
-                        | ALPHAS_MASK) as i64,
+                write_unaligned(
+                    obuffer.add(obuffer_offset + 24) as *mut i64,
+                    _bswap64(
+                        (((read_unaligned(ibuffer.add(ibuffer_offset + 18) as *const u64)
+                            >> SHIFT_16)
+                            & LOW_MASK)
+                            | ((read_unaligned(ibuffer.add(ibuffer_offset + 18) as *const u64)
+                                << SHIFT_40)
+                                & HIGH_MASK)
+                            | ALPHAS_MASK) as i64,
+                    ),
                 );
 
                 x += ITEMS_PER_ITERATION;
@@ -1204,8 +1270,12 @@ pub fn rgb_lrgb_bgra_lrgb(
 
             // Retrieves the ramaining colors in the line
             while x < single_swap_iterations {
-                *(obuffer.add(obuffer_offset) as *mut i32) = _bswap(
-                    ((*(ibuffer.add(ibuffer_offset) as *const u32) << SHIFT_8) | 0xFF) as i32,
+                write_unaligned(
+                    obuffer.add(obuffer_offset) as *mut i32,
+                    _bswap(
+                        ((read_unaligned(ibuffer.add(ibuffer_offset) as *const u32) << SHIFT_8)
+                            | 0xFF) as i32,
+                    ),
                 );
 
                 x += 1;
@@ -1233,620 +1303,3447 @@ pub fn rgb_lrgb_bgra_lrgb(
     true
 }
 
-pub fn argb_lrgb_nv12_bt601(
+fn read_u16(buffer: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buffer[offset], buffer[offset + 1]])
+}
+
+fn write_u16(buffer: &mut [u8], offset: usize, value: u16) {
+    let bytes = value.to_le_bytes();
+    buffer[offset] = bytes[0];
+    buffer[offset + 1] = bytes[1];
+}
+
+/// Convert a BT.2020 PQ limited range pixel to linear scRGB
+fn bt2020pq_to_scrgb(y: u16, cb: f32, cr: f32) -> (f32, f32, f32) {
+    let l = (f32::from(y >> 6) - Y_MIN_10) / Y_RANGE_10;
+    let r = PQ_PEAK * pq_eotf(l + RCR_2020 * cr);
+    let g = PQ_PEAK * pq_eotf(l - GCB_2020 * cb - GCR_2020 * cr);
+    let b = PQ_PEAK * pq_eotf(l + BCB_2020 * cb);
+
+    let m = &BT2020_TO_BT709;
+    (
+        m[0][0] * r + m[0][1] * g + m[0][2] * b,
+        m[1][0] * r + m[1][1] * g + m[1][2] * b,
+        m[2][0] * r + m[2][1] * g + m[2][2] * b,
+    )
+}
+
+fn pack_rgbaf16(buffer: &mut [u8], offset: usize, (r, g, b): (f32, f32, f32)) {
+    write_u16(buffer, offset, f32_to_f16(r));
+    write_u16(buffer, offset + 2, f32_to_f16(g));
+    write_u16(buffer, offset + 4, f32_to_f16(b));
+    write_u16(buffer, offset + 6, F16_ONE);
+}
+
+fn p010_to_rgbaf16(
     width: u32,
     height: u32,
-    last_src_plane: u32,
+    last_src_plane: usize,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
-    last_dst_plane: u32,
+    _last_dst_plane: usize,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    lrgb_to_yuv(
-        width,
-        height,
-        last_src_plane as usize,
-        src_strides,
-        src_buffers,
-        last_dst_plane as usize,
-        dst_strides,
-        dst_buffers,
-        PixelFormatChannels::Four,
-        Colorimetry::Bt601,
-        Sampler::Argb,
-    )
+    if (last_src_plane >= src_strides.len())
+        || (last_src_plane >= src_buffers.len())
+        || dst_strides.is_empty()
+        || dst_buffers.is_empty()
+    {
+        return false;
+    }
+
+    const INPUT_BPP: usize = 2;
+    const OUTPUT_BPP: usize = 8;
+
+    let col_count = width as usize;
+    let line_count = height as usize;
+    let packed_p010_stride = INPUT_BPP * col_count;
+    let packed_rgb_stride = OUTPUT_BPP * col_count;
+
+    let y_stride = if src_strides[0] == 0 {
+        packed_p010_stride
+    } else {
+        src_strides[0]
+    };
+
+    let uv_stride = if src_strides[last_src_plane] == 0 {
+        packed_p010_stride
+    } else {
+        src_strides[last_src_plane]
+    };
+
+    let rgb_stride = if dst_strides[0] == 0 {
+        packed_rgb_stride
+    } else {
+        dst_strides[0]
+    };
+
+    let rgb_plane = &mut dst_buffers[0];
+    let (first, last) = src_buffers.split_at(last_src_plane);
+    let interplane_split = y_stride.saturating_mul(line_count);
+    if last_src_plane == 0 && interplane_split > last[0].len() {
+        return false;
+    }
+
+    let (y_plane, uv_plane) = if last_src_plane == 0 {
+        last[0].split_at(interplane_split)
+    } else {
+        (first[0], last[0])
+    };
+
+    if line_count == 0 || col_count == 0 {
+        return true;
+    }
+
+    let max_stride = usize::MAX / line_count;
+    if (y_stride > max_stride) || (uv_stride > max_stride) || (rgb_stride > max_stride) {
+        return false;
+    }
+
+    let wg_height = line_count / 2;
+    if y_stride * line_count > y_plane.len()
+        || uv_stride * wg_height > uv_plane.len()
+        || rgb_stride * line_count > rgb_plane.len()
+    {
+        return false;
+    }
+
+    let wg_width = col_count / 2;
+    for y in 0..wg_height {
+        for x in 0..wg_width {
+            let uv_index = wg_index(x, y, 2 * INPUT_BPP, uv_stride);
+            let cb = (f32::from(read_u16(uv_plane, uv_index) >> 6) - C_HALF_10) / C_RANGE_10;
+            let cr =
+                (f32::from(read_u16(uv_plane, uv_index + INPUT_BPP) >> 6) - C_HALF_10) / C_RANGE_10;
+
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)].iter() {
+                let (px, py) = (2 * x + dx, 2 * y + dy);
+                let l = read_u16(y_plane, wg_index(px, py, INPUT_BPP, y_stride));
+
+                pack_rgbaf16(
+                    rgb_plane,
+                    wg_index(px, py, OUTPUT_BPP, rgb_stride),
+                    bt2020pq_to_scrgb(l, cb, cr),
+                );
+            }
+        }
+    }
+
+    true
 }
 
-pub fn argb_lrgb_nv12_bt709(
+fn scrgb_to_u8(value: f32) -> u8 {
+    (255.0 * srgb_oetf(value) + 0.5) as u8
+}
+
+fn rgbaf16_to_bgra(
     width: u32,
     height: u32,
-    last_src_plane: u32,
+    _last_src_plane: usize,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
-    last_dst_plane: u32,
+    _last_dst_plane: usize,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    lrgb_to_yuv(
-        width,
-        height,
-        last_src_plane as usize,
-        src_strides,
-        src_buffers,
-        last_dst_plane as usize,
-        dst_strides,
-        dst_buffers,
-        PixelFormatChannels::Four,
-        Colorimetry::Bt709,
-        Sampler::Argb,
-    )
+    if src_strides.is_empty()
+        || src_buffers.is_empty()
+        || dst_strides.is_empty()
+        || dst_buffers.is_empty()
+    {
+        return false;
+    }
+
+    const INPUT_BPP: usize = 8;
+    const OUTPUT_BPP: usize = 4;
+
+    let col_count = width as usize;
+    let line_count = height as usize;
+
+    let src_stride = if src_strides[0] == 0 {
+        INPUT_BPP * col_count
+    } else {
+        src_strides[0]
+    };
+
+    let dst_stride = if dst_strides[0] == 0 {
+        OUTPUT_BPP * col_count
+    } else {
+        dst_strides[0]
+    };
+
+    if line_count == 0 || col_count == 0 {
+        return true;
+    }
+
+    let max_stride = usize::MAX / line_count;
+    if (src_stride > max_stride) || (dst_stride > max_stride) {
+        return false;
+    }
+
+    let src_plane = src_buffers[0];
+    let dst_plane = &mut dst_buffers[0];
+    if src_stride * line_count > src_plane.len() || dst_stride * line_count > dst_plane.len() {
+        return false;
+    }
+
+    for y in 0..line_count {
+        for x in 0..col_count {
+            let src_index = wg_index(x, y, INPUT_BPP, src_stride);
+            let r = f16_to_f32(read_u16(src_plane, src_index)).max(0.0);
+            let g = f16_to_f32(read_u16(src_plane, src_index + 2)).max(0.0);
+            let b = f16_to_f32(read_u16(src_plane, src_index + 4)).max(0.0);
+            let a = f16_to_f32(read_u16(src_plane, src_index + 6));
+
+            // Scale all the components by the same amount to preserve the hue
+            let max = r.max(g).max(b);
+            let scale = if max > TONE_MAPPING_KNEE {
+                tone_map(max) / max
+            } else {
+                1.0
+            };
+
+            let dst_index = wg_index(x, y, OUTPUT_BPP, dst_stride);
+            dst_plane[dst_index] = scrgb_to_u8(scale * b);
+            dst_plane[dst_index + 1] = scrgb_to_u8(scale * g);
+            dst_plane[dst_index + 2] = scrgb_to_u8(scale * r);
+            dst_plane[dst_index + 3] = (255.0 * a.clamp(0.0, 1.0) + 0.5) as u8;
+        }
+    }
+
+    true
 }
 
-pub fn bgra_lrgb_nv12_bt601(
+#[derive(Copy, Clone, PartialEq)]
+pub enum YuvLayout {
+    I444,
+    I420,
+    Nv12,
+    P010,
+}
+
+/// Location of the samples of a yuv plane inside the image buffers
+struct YuvPlane {
+    buffer: usize,
+    offset: usize,
+    stride: usize,
+    step: usize,
+}
+
+impl YuvPlane {
+    fn index(&self, x: usize, y: usize) -> usize {
+        self.offset + y * self.stride + x * self.step
+    }
+}
+
+/// Locates the luma, cb and cr planes of an image, checking the buffers are large enough
+fn get_yuv_planes(
+    layout: YuvLayout,
+    col_count: usize,
+    line_count: usize,
+    last_plane: usize,
+    strides: &[usize],
+    buffer_sizes: &[usize],
+) -> Option<[YuvPlane; 3]> {
+    if last_plane >= strides.len() || last_plane >= buffer_sizes.len() {
+        return None;
+    }
+
+    let shift = match layout {
+        YuvLayout::I444 => 0,
+        _ => 1,
+    };
+
+    let chroma_col_count = col_count >> shift;
+    let chroma_line_count = line_count >> shift;
+    let get_stride = |plane: usize, line_size: usize| match strides[plane] {
+        0 => Some(line_size),
+        stride if stride >= line_size => Some(stride),
+        _ => None,
+    };
+
+    let fits = |buffer: usize, offset: usize, stride: usize, lines: usize| {
+        stride
+            .checked_mul(lines)
+            .and_then(|size| size.checked_add(offset))
+            .unwrap_or(usize::MAX)
+            <= buffer_sizes[buffer]
+    };
+
+    if let YuvLayout::Nv12 | YuvLayout::P010 = layout {
+        let sample_size = match layout {
+            YuvLayout::P010 => 2,
+            _ => 1,
+        };
+
+        let y_stride = get_stride(0, sample_size * col_count)?;
+        let uv_stride = get_stride(last_plane, 2 * sample_size * chroma_col_count)?;
+
+        // A single buffer stores the chroma plane right after the luma plane
+        let uv_offset = if last_plane == 0 {
+            y_stride.checked_mul(line_count)?
+        } else {
+            0
+        };
+
+        if !fits(0, 0, y_stride, line_count)
+            || !fits(last_plane, uv_offset, uv_stride, chroma_line_count)
+        {
+            return None;
+        }
+
+        Some([
+            YuvPlane {
+                buffer: 0,
+                offset: 0,
+                stride: y_stride,
+                step: sample_size,
+            },
+            YuvPlane {
+                buffer: last_plane,
+                offset: uv_offset,
+                stride: uv_stride,
+                step: 2 * sample_size,
+            },
+            YuvPlane {
+                buffer: last_plane,
+                offset: uv_offset + sample_size,
+                stride: uv_stride,
+                step: 2 * sample_size,
+            },
+        ])
+    } else {
+        if last_plane != 2 {
+            return None;
+        }
+
+        let y_stride = get_stride(0, col_count)?;
+        let u_stride = get_stride(1, chroma_col_count)?;
+        let v_stride = get_stride(2, chroma_col_count)?;
+        if !fits(0, 0, y_stride, line_count)
+            || !fits(1, 0, u_stride, chroma_line_count)
+            || !fits(2, 0, v_stride, chroma_line_count)
+        {
+            return None;
+        }
+
+        Some([
+            YuvPlane {
+                buffer: 0,
+                offset: 0,
+                stride: y_stride,
+                step: 1,
+            },
+            YuvPlane {
+                buffer: 1,
+                offset: 0,
+                stride: u_stride,
+                step: 1,
+            },
+            YuvPlane {
+                buffer: 2,
+                offset: 0,
+                stride: v_stride,
+                step: 1,
+            },
+        ])
+    }
+}
+
+/// Converts between two ycbcr color spaces without going through rgb
+///
+/// The chroma planes are transformed on their own, while luma is corrected using
+/// the chroma sample that covers it.
+fn yuv_to_yuv(
     width: u32,
     height: u32,
-    last_src_plane: u32,
+    last_src_plane: usize,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
-    last_dst_plane: u32,
+    last_dst_plane: usize,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
+    layout: YuvLayout,
+    coefficients: &[[i32; 2]; 3],
 ) -> bool {
-    lrgb_to_yuv(
-        width,
-        height,
-        last_src_plane as usize,
+    let col_count = width as usize;
+    let line_count = height as usize;
+
+    let src_sizes: Vec<usize> = src_buffers.iter().map(|buffer| buffer.len()).collect();
+    let dst_sizes: Vec<usize> = dst_buffers.iter().map(|buffer| buffer.len()).collect();
+    let src_planes = get_yuv_planes(
+        layout,
+        col_count,
+        line_count,
+        last_src_plane,
         src_strides,
-        src_buffers,
-        last_dst_plane as usize,
+        &src_sizes,
+    );
+    let dst_planes = get_yuv_planes(
+        layout,
+        col_count,
+        line_count,
+        last_dst_plane,
         dst_strides,
-        dst_buffers,
-        PixelFormatChannels::Four,
-        Colorimetry::Bt601,
-        Sampler::Bgra,
-    )
+        &dst_sizes,
+    );
+
+    let (src_planes, dst_planes) = match (src_planes, dst_planes) {
+        (Some(src_planes), Some(dst_planes)) => (src_planes, dst_planes),
+        _ => return false,
+    };
+
+    let shift = match layout {
+        YuvLayout::I444 => 0,
+        _ => 1,
+    };
+
+    let read = |plane: &YuvPlane, x: usize, y: usize| {
+        i32::from(src_buffers[plane.buffer][plane.index(x, y)]) - C_HALF
+    };
+
+    let [y_coefficients, cb_coefficients, cr_coefficients] = coefficients;
+    for y in 0..line_count {
+        for x in 0..col_count {
+            let cb = read(&src_planes[1], x >> shift, y >> shift);
+            let cr = read(&src_planes[2], x >> shift, y >> shift);
+            let l = i32::from(src_buffers[src_planes[0].buffer][src_planes[0].index(x, y)]);
+
+            dst_buffers[dst_planes[0].buffer][dst_planes[0].index(x, y)] = fix_to_u8_sat(
+                (l << FIX16) + y_coefficients[0] * cb + y_coefficients[1] * cr + FIX16_HALF,
+                FIX16,
+            );
+        }
+    }
+
+    for y in 0..(line_count >> shift) {
+        for x in 0..(col_count >> shift) {
+            let cb = read(&src_planes[1], x, y);
+            let cr = read(&src_planes[2], x, y);
+
+            dst_buffers[dst_planes[1].buffer][dst_planes[1].index(x, y)] = fix_to_u8_sat(
+                cb_coefficients[0] * cb + cb_coefficients[1] * cr + C_OFFSET16,
+                FIX16,
+            );
+            dst_buffers[dst_planes[2].buffer][dst_planes[2].index(x, y)] = fix_to_u8_sat(
+                cr_coefficients[0] * cb + cr_coefficients[1] * cr + C_OFFSET16,
+                FIX16,
+            );
+        }
+    }
+
+    true
 }
 
-pub fn bgra_lrgb_nv12_bt709(
+/// Ordered dithering thresholds used when reducing 10-bit samples to 8 bits
+///
+/// A 2x2 Bayer matrix covers the four values of the two dropped bits, so the
+/// average of a flat area is preserved.
+const DITHER_2X2: [[u16; 2]; 2] = [[0, 2], [3, 1]];
+
+/// Changes the bit depth of a biplanar ycbcr image, between 8-bit Nv12 and 10-bit P010
+///
+/// 8-bit samples are scaled by 4, 10-bit samples are divided by 4 rounding to nearest,
+/// or using ordered dithering if `dither` is true.
+pub fn yuv_depth(
     width: u32,
     height: u32,
-    last_src_plane: u32,
+    last_src_plane: usize,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
-    last_dst_plane: u32,
+    last_dst_plane: usize,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
+    src_layout: YuvLayout,
+    dst_layout: YuvLayout,
+    dither: bool,
 ) -> bool {
-    lrgb_to_yuv(
-        width,
-        height,
-        last_src_plane as usize,
+    let col_count = width as usize;
+    let line_count = height as usize;
+
+    let src_sizes: Vec<usize> = src_buffers.iter().map(|buffer| buffer.len()).collect();
+    let dst_sizes: Vec<usize> = dst_buffers.iter().map(|buffer| buffer.len()).collect();
+    let src_planes = get_yuv_planes(
+        src_layout,
+        col_count,
+        line_count,
+        last_src_plane,
         src_strides,
-        src_buffers,
-        last_dst_plane as usize,
+        &src_sizes,
+    );
+    let dst_planes = get_yuv_planes(
+        dst_layout,
+        col_count,
+        line_count,
+        last_dst_plane,
         dst_strides,
-        dst_buffers,
-        PixelFormatChannels::Four,
-        Colorimetry::Bt709,
-        Sampler::Bgra,
-    )
+        &dst_sizes,
+    );
+
+    let (src_planes, dst_planes) = match (src_planes, dst_planes) {
+        (Some(src_planes), Some(dst_planes)) => (src_planes, dst_planes),
+        _ => return false,
+    };
+
+    let to_p010 = dst_layout == YuvLayout::P010;
+
+    // Luma, then the two chroma planes, subsampled in both directions
+    for (plane, (src, dst)) in src_planes.iter().zip(dst_planes.iter()).enumerate() {
+        let shift = if plane == 0 { 0 } else { 1 };
+        for y in 0..(line_count >> shift) {
+            for x in 0..(col_count >> shift) {
+                let src_index = src.index(x, y);
+                let dst_index = dst.index(x, y);
+                if to_p010 {
+                    let value = u16::from(src_buffers[src.buffer][src_index]);
+                    write_u16(dst_buffers[dst.buffer], dst_index, value << 8);
+                } else {
+                    let bias = if dither { DITHER_2X2[y & 1][x & 1] } else { 2 };
+                    let value = (read_u16(src_buffers[src.buffer], src_index) >> 6) + bias;
+                    dst_buffers[dst.buffer][dst_index] = (value >> 2).min(255) as u8;
+                }
+            }
+        }
+    }
+
+    true
 }
 
-pub fn bgr_lrgb_nv12_bt601(
+/// Reduces the luma of a P010 image to an 8-bit plane, leaving out the chroma samples
+///
+/// Samples are rounded as in [`yuv_depth`]. The destination plane is `dst_stride` bytes
+/// per line, or `width` bytes if the stride is zero.
+pub fn p010_to_luma(
     width: u32,
     height: u32,
-    last_src_plane: u32,
+    last_src_plane: usize,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    dst_stride: usize,
+    dst_buffer: &mut [u8],
+    dither: bool,
+) -> bool {
+    let col_count = width as usize;
+    let line_count = height as usize;
+
+    let src_sizes: Vec<usize> = src_buffers.iter().map(|buffer| buffer.len()).collect();
+    let src_plane = match get_yuv_planes(
+        YuvLayout::P010,
+        col_count,
+        line_count,
+        last_src_plane,
+        src_strides,
+        &src_sizes,
+    ) {
+        Some([luma, _, _]) => luma,
+        None => return false,
+    };
+
+    let dst_stride = match dst_stride {
+        0 => col_count,
+        stride if stride >= col_count => stride,
+        _ => return false,
+    };
+
+    let dst_size = dst_stride
+        .checked_mul(line_count - 1)
+        .and_then(|size| size.checked_add(col_count));
+    match dst_size {
+        Some(size) if size <= dst_buffer.len() => {}
+        _ => return false,
+    }
+
+    let src_buffer = src_buffers[src_plane.buffer];
+    for y in 0..line_count {
+        let biases = if dither { DITHER_2X2[y & 1] } else { [2, 2] };
+        let src_row = &src_buffer[src_plane.index(0, y)..][..2 * col_count];
+        let dst_row = &mut dst_buffer[y * dst_stride..][..col_count];
+        for (x, (src, dst)) in src_row.chunks_exact(2).zip(dst_row.iter_mut()).enumerate() {
+            let value = (u16::from_le_bytes([src[0], src[1]]) >> 6) + biases[x & 1];
+            *dst = (value >> 2).min(255) as u8;
+        }
+    }
+
+    true
+}
+
+/// Byte offsets of red, green and blue inside a 16-bit per channel rgb pixel
+fn get_rgb48_offsets(reverse: bool) -> [usize; 3] {
+    if reverse {
+        [4, 2, 0]
+    } else {
+        [0, 2, 4]
+    }
+}
+
+/// Converts 16-bit rgb to 10-bit ycbcr in floating point, with 4:2:0 downsampling
+///
+/// `reverse` selects the blue, green, red order of Bgr48. Chroma is computed from the
+/// average of the rgb values it covers. Samples are rounded to nearest and stored in the
+/// upper bits of each word, as P010 expects.
+pub fn lrgb48_to_p010(
+    width: u32,
+    height: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: usize,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+    reverse: bool,
+    coefficients: &[f32; 4],
+) -> bool {
+    const DEPTH: usize = 6;
+
+    let col_count = width as usize;
+    let line_count = height as usize;
+
+    let rgb_stride = match get_rgb_stride(
+        DEPTH,
+        col_count,
+        line_count,
+        src_strides,
+        src_buffers.len(),
+        src_buffers.first().map_or(0, |buffer| buffer.len()),
+    ) {
+        Some(stride) => stride,
+        None => return false,
+    };
+
+    let dst_sizes: Vec<usize> = dst_buffers.iter().map(|buffer| buffer.len()).collect();
+    let dst_planes = match get_yuv_planes(
+        YuvLayout::P010,
+        col_count,
+        line_count,
+        last_dst_plane,
+        dst_strides,
+        &dst_sizes,
+    ) {
+        Some(planes) => planes,
+        None => return false,
+    };
+
+    let [rcr, _, _, bcb] = *coefficients;
+    let kr = 1.0 - 0.5 * rcr;
+    let kb = 1.0 - 0.5 * bcb;
+    let kg = 1.0 - kr - kb;
+
+    let offsets = get_rgb48_offsets(reverse);
+    let rgb_plane = src_buffers[0];
+    let read = |x: usize, y: usize| {
+        let index = y * rgb_stride + x * DEPTH;
+        (
+            f32::from(read_u16(rgb_plane, index + offsets[0])),
+            f32::from(read_u16(rgb_plane, index + offsets[1])),
+            f32::from(read_u16(rgb_plane, index + offsets[2])),
+        )
+    };
+
+    let to_p010 = |value: f32| ((value + 0.5).clamp(0.0, 1023.0) as u16) << 6;
+    let luma = |(r, g, b): (f32, f32, f32)| kr * r + kg * g + kb * b;
+
+    for y in 0..line_count {
+        for x in 0..col_count {
+            let l = Y_MIN_10 + Y_RANGE_10 * luma(read(x, y)) / 65535.0;
+            let plane = &dst_planes[0];
+            write_u16(dst_buffers[plane.buffer], plane.index(x, y), to_p010(l));
+        }
+    }
+
+    for y in 0..(line_count >> 1) {
+        for x in 0..(col_count >> 1) {
+            let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+            for dy in 0..2 {
+                for dx in 0..2 {
+                    let (sr, sg, sb) = read((x << 1) + dx, (y << 1) + dy);
+                    r += sr;
+                    g += sg;
+                    b += sb;
+                }
+            }
+
+            let rgb = (r / 4.0, g / 4.0, b / 4.0);
+            let l = luma(rgb);
+            let cb = C_HALF_10 + C_RANGE_10 * (rgb.2 - l) / (bcb * 65535.0);
+            let cr = C_HALF_10 + C_RANGE_10 * (rgb.0 - l) / (rcr * 65535.0);
+            let (cb_plane, cr_plane) = (&dst_planes[1], &dst_planes[2]);
+            write_u16(
+                dst_buffers[cb_plane.buffer],
+                cb_plane.index(x, y),
+                to_p010(cb),
+            );
+            write_u16(
+                dst_buffers[cr_plane.buffer],
+                cr_plane.index(x, y),
+                to_p010(cr),
+            );
+        }
+    }
+
+    true
+}
+
+/// Reduces 16-bit rgb to 8-bit bgra
+///
+/// Samples are scaled by 255 / 65535 and rounded to nearest, or using ordered dithering
+/// if `dither` is true. Values that are exact 8-bit levels are never changed by dithering.
+pub fn lrgb48_to_bgra(
+    width: u32,
+    height: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+    reverse: bool,
+    dither: bool,
+) -> bool {
+    const INPUT_BPP: usize = 6;
+    const OUTPUT_BPP: usize = 4;
+
+    let col_count = width as usize;
+    let line_count = height as usize;
+
+    let src_stride = get_rgb_stride(
+        INPUT_BPP,
+        col_count,
+        line_count,
+        src_strides,
+        src_buffers.len(),
+        src_buffers.first().map_or(0, |buffer| buffer.len()),
+    );
+    let dst_stride = get_rgb_stride(
+        OUTPUT_BPP,
+        col_count,
+        line_count,
+        dst_strides,
+        dst_buffers.len(),
+        dst_buffers.first().map_or(0, |buffer| buffer.len()),
+    );
+
+    let (src_stride, dst_stride) = match (src_stride, dst_stride) {
+        (Some(src_stride), Some(dst_stride)) => (src_stride, dst_stride),
+        _ => return false,
+    };
+
+    let offsets = get_rgb48_offsets(reverse);
+    let src_plane = src_buffers[0];
+    let dst_plane = &mut dst_buffers[0];
+    for y in 0..line_count {
+        for x in 0..col_count {
+            // Thresholds are in eighths of an 8-bit level, a half when not dithering
+            let bias = if dither {
+                2 * u32::from(DITHER_2X2[y & 1][x & 1]) + 1
+            } else {
+                4
+            };
+
+            let to_u8 = |offset: usize| {
+                let value = u32::from(read_u16(src_plane, y * src_stride + x * INPUT_BPP + offset));
+                ((8 * 255 * value + bias * 65535) / (8 * 65535)) as u8
+            };
+
+            let dst_index = wg_index(x, y, OUTPUT_BPP, dst_stride);
+            dst_plane[dst_index] = to_u8(offsets[2]);
+            dst_plane[dst_index + 1] = to_u8(offsets[1]);
+            dst_plane[dst_index + 2] = to_u8(offsets[0]);
+            dst_plane[dst_index + 3] = DEFAULT_ALPHA;
+        }
+    }
+
+    true
+}
+
+/// Destination of the conversions from ycbcr computed in floating point
+#[derive(Copy, Clone)]
+pub enum RgbOutput {
+    Bgra,
+    RgbF32,
+    RgbaF32,
+}
+
+/// Builds the matrix converting normalized ycbcr to rgb, given (rcr, gcb, gcr, bcb)
+pub fn get_ycbcr_to_rgb_matrix(coefficients: &[f32; 4]) -> [[f32; 4]; 3] {
+    let [rcr, gcb, gcr, bcb] = *coefficients;
+    [
+        [1.0, 0.0, rcr, 0.0],
+        [1.0, -gcb, -gcr, 0.0],
+        [1.0, bcb, 0.0, 0.0],
+    ]
+}
+
+/// Converts ycbcr to rgb in floating point, with 4:4:4 upsampling
+///
+/// Luma and chroma are normalized to [0, 1] and [-0.5, 0.5], then each rgb component
+/// is computed as a row of the matrix times (luma, cb, cr, 1). Components are clipped
+/// to [0, 1]. The alpha channel, if any, is opaque
+pub fn yuv_to_lrgb_matrix(
+    width: u32,
+    height: u32,
+    last_src_plane: usize,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+    layout: YuvLayout,
+    matrix: &[[f32; 4]; 3],
+    output: RgbOutput,
+) -> bool {
+    if dst_strides.is_empty() || dst_buffers.is_empty() {
+        return false;
+    }
+
+    let col_count = width as usize;
+    let line_count = height as usize;
+
+    let src_sizes: Vec<usize> = src_buffers.iter().map(|buffer| buffer.len()).collect();
+    let src_planes = match get_yuv_planes(
+        layout,
+        col_count,
+        line_count,
+        last_src_plane,
+        src_strides,
+        &src_sizes,
+    ) {
+        Some(planes) => planes,
+        None => return false,
+    };
+
+    let output_bpp = match output {
+        RgbOutput::Bgra => 4,
+        RgbOutput::RgbF32 => 12,
+        RgbOutput::RgbaF32 => 16,
+    };
+
+    let rgb_stride = match dst_strides[0] {
+        0 => output_bpp * col_count,
+        stride if stride >= output_bpp * col_count => stride,
+        _ => return false,
+    };
+
+    let rgb_plane = &mut dst_buffers[0];
+    if rgb_stride.saturating_mul(line_count) > rgb_plane.len() {
+        return false;
+    }
+
+    let shift = match layout {
+        YuvLayout::I444 => 0,
+        _ => 1,
+    };
+
+    let read = |plane: &YuvPlane, x: usize, y: usize| {
+        f32::from(src_buffers[plane.buffer][plane.index(x, y)])
+    };
+
+    for y in 0..line_count {
+        for x in 0..col_count {
+            let l = (read(&src_planes[0], x, y) - Y_MIN_8) / Y_RANGE_8;
+            let cb = (read(&src_planes[1], x >> shift, y >> shift) - C_HALF_8) / C_RANGE_8;
+            let cr = (read(&src_planes[2], x >> shift, y >> shift) - C_HALF_8) / C_RANGE_8;
+
+            let mut rgb = [0.0f32; 3];
+            for (value, row) in rgb.iter_mut().zip(matrix.iter()) {
+                *value = (row[0] * l + row[1] * cb + row[2] * cr + row[3]).clamp(0.0, 1.0);
+            }
+
+            let offset = y * rgb_stride + x * output_bpp;
+            let pixel = &mut rgb_plane[offset..offset + output_bpp];
+            if let RgbOutput::Bgra = output {
+                pixel[0] = (255.0 * rgb[2] + 0.5) as u8;
+                pixel[1] = (255.0 * rgb[1] + 0.5) as u8;
+                pixel[2] = (255.0 * rgb[0] + 0.5) as u8;
+                pixel[3] = DEFAULT_ALPHA;
+            } else {
+                for (bytes, value) in pixel.chunks_exact_mut(4).zip(rgb.iter().chain(&[1.0])) {
+                    bytes.copy_from_slice(&value.to_bits().to_le_bytes());
+                }
+            }
+        }
+    }
+
+    true
+}
+
+/// Locates the single plane of a packed rgb image, checking the buffer is large enough
+///
+/// Returns the stride of the plane
+fn get_rgb_stride(
+    depth: usize,
+    col_count: usize,
+    line_count: usize,
+    strides: &[usize],
+    buffers_len: usize,
+    buffer_size: usize,
+) -> Option<usize> {
+    if strides.is_empty() || buffers_len == 0 {
+        return None;
+    }
+
+    let stride = match strides[0] {
+        0 => depth * col_count,
+        stride if stride >= depth * col_count => stride,
+        _ => return None,
+    };
+
+    if stride.checked_mul(line_count)? > buffer_size {
+        return None;
+    }
+
+    Some(stride)
+}
+
+/// Weights of luma and chroma used by [`yuv_to_bgra_fix16`], given (rcr, gcb, gcr, bcb),
+/// scaled to 8-bit rgb and 16 fractional bits: (luma, rcr, gcb, gcr, bcb)
+pub fn get_fix16_ycbcr_to_rgb_weights(coefficients: &[f32; 4]) -> [i32; 5] {
+    let fix = |value: f32| (value * (1 << FIX16) as f32 + 0.5) as i32;
+    let [rcr, gcb, gcr, bcb] = *coefficients;
+    let c_scale = 255.0 / C_RANGE_8;
+    [
+        fix(255.0 / Y_RANGE_8),
+        fix(rcr * c_scale),
+        fix(gcb * c_scale),
+        fix(gcr * c_scale),
+        fix(bcb * c_scale),
+    ]
+}
+
+/// Converts ycbcr to 8-bit bgra in fixed point, with 4:4:4 upsampling
+///
+/// Same as [`yuv_to_lrgb_matrix`], using 16 fractional bits and rounding once per
+/// component as requested, instead of single precision floats.
+pub fn yuv_to_bgra_fix16(
+    width: u32,
+    height: u32,
+    last_src_plane: usize,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+    layout: YuvLayout,
+    coefficients: &[f32; 4],
+    rounding: Rounding,
+) -> bool {
+    const OUTPUT_BPP: usize = 4;
+
+    let col_count = width as usize;
+    let line_count = height as usize;
+
+    let src_sizes: Vec<usize> = src_buffers.iter().map(|buffer| buffer.len()).collect();
+    let src_planes = match get_yuv_planes(
+        layout,
+        col_count,
+        line_count,
+        last_src_plane,
+        src_strides,
+        &src_sizes,
+    ) {
+        Some(planes) => planes,
+        None => return false,
+    };
+
+    let rgb_stride = match get_rgb_stride(
+        OUTPUT_BPP,
+        col_count,
+        line_count,
+        dst_strides,
+        dst_buffers.len(),
+        dst_buffers.first().map_or(0, |buffer| buffer.len()),
+    ) {
+        Some(stride) => stride,
+        None => return false,
+    };
+
+    let [y_weight, rcr, gcb, gcr, bcb] = get_fix16_ycbcr_to_rgb_weights(coefficients);
+
+    let shift = match layout {
+        YuvLayout::I444 => 0,
+        _ => 1,
+    };
+
+    let read = |plane: &YuvPlane, x: usize, y: usize| {
+        i32::from(src_buffers[plane.buffer][plane.index(x, y)])
+    };
+
+    let to_u8 = |fix: i32| round_fix(fix, FIX16, rounding).clamp(0, 255) as u8;
+
+    let rgb_plane = &mut dst_buffers[0];
+    for y in 0..line_count {
+        for x in 0..col_count {
+            let l = (read(&src_planes[0], x, y) - Y_MIN) * y_weight;
+            let cb = read(&src_planes[1], x >> shift, y >> shift) - C_HALF;
+            let cr = read(&src_planes[2], x >> shift, y >> shift) - C_HALF;
+
+            let offset = y * rgb_stride + x * OUTPUT_BPP;
+            let pixel = &mut rgb_plane[offset..offset + OUTPUT_BPP];
+            pixel[0] = to_u8(l + bcb * cb);
+            pixel[1] = to_u8(l - gcb * cb - gcr * cr);
+            pixel[2] = to_u8(l + rcr * cr);
+            pixel[3] = DEFAULT_ALPHA;
+        }
+    }
+
+    true
+}
+
+/// Converts 8-bit rgb to ycbcr in floating point, with 4:2:0 or 4:4:4 downsampling
+///
+/// The rgb to ycbcr matrix is derived from the ycbcr to rgb coefficients
+/// (rcr, gcb, gcr, bcb). Chroma of subsampled images is computed from the average
+/// of the rgb values it covers. Samples are rounded to nearest.
+pub fn lrgb_to_yuv_matrix(
+    width: u32,
+    height: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: usize,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+    channels: PixelFormatChannels,
+    sampler: Sampler,
+    layout: YuvLayout,
+    coefficients: &[f32; 4],
+) -> bool {
+    let depth = channels as usize;
+    let col_count = width as usize;
+    let line_count = height as usize;
+
+    let rgb_stride = match get_rgb_stride(
+        depth,
+        col_count,
+        line_count,
+        src_strides,
+        src_buffers.len(),
+        src_buffers.first().map_or(0, |buffer| buffer.len()),
+    ) {
+        Some(stride) => stride,
+        None => return false,
+    };
+
+    let dst_sizes: Vec<usize> = dst_buffers.iter().map(|buffer| buffer.len()).collect();
+    let dst_planes = match get_yuv_planes(
+        layout,
+        col_count,
+        line_count,
+        last_dst_plane,
+        dst_strides,
+        &dst_sizes,
+    ) {
+        Some(planes) => planes,
+        None => return false,
+    };
+
+    // Luma weights follow from the red and blue chroma coefficients
+    let [rcr, _, _, bcb] = *coefficients;
+    let kr = 1.0 - 0.5 * rcr;
+    let kb = 1.0 - 0.5 * bcb;
+    let kg = 1.0 - kr - kb;
+
+    let offsets = &SAMPLER_OFFSETS[sampler as usize];
+    let rgb_plane = src_buffers[0];
+    let read = |x: usize, y: usize| {
+        let pixel = &rgb_plane[y * rgb_stride + x * depth..];
+        (
+            f32::from(pixel[offsets[0]]),
+            f32::from(pixel[offsets[1]]),
+            f32::from(pixel[offsets[2]]),
+        )
+    };
+
+    let to_u8 = |value: f32| (value + 0.5).clamp(0.0, 255.0) as u8;
+    let luma = |(r, g, b): (f32, f32, f32)| kr * r + kg * g + kb * b;
+
+    for y in 0..line_count {
+        for x in 0..col_count {
+            let l = Y_MIN_8 + Y_RANGE_8 * luma(read(x, y)) / 255.0;
+            dst_buffers[dst_planes[0].buffer][dst_planes[0].index(x, y)] = to_u8(l);
+        }
+    }
+
+    let shift = match layout {
+        YuvLayout::I444 => 0,
+        _ => 1,
+    };
+
+    let count = (1 << (2 * shift)) as f32;
+    for y in 0..(line_count >> shift) {
+        for x in 0..(col_count >> shift) {
+            let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+            for dy in 0..(1 << shift) {
+                for dx in 0..(1 << shift) {
+                    let (sr, sg, sb) = read((x << shift) + dx, (y << shift) + dy);
+                    r += sr;
+                    g += sg;
+                    b += sb;
+                }
+            }
+
+            let rgb = (r / count, g / count, b / count);
+            let l = luma(rgb);
+            let cb = C_HALF_8 + C_RANGE_8 * (rgb.2 - l) / (bcb * 255.0);
+            let cr = C_HALF_8 + C_RANGE_8 * (rgb.0 - l) / (rcr * 255.0);
+            dst_buffers[dst_planes[1].buffer][dst_planes[1].index(x, y)] = to_u8(cb);
+            dst_buffers[dst_planes[2].buffer][dst_planes[2].index(x, y)] = to_u8(cr);
+        }
+    }
+
+    true
+}
+
+/// (mean, std) of each plane leaving the samples unchanged
+pub const IDENTITY_NORMALIZATION: [[f32; 2]; 3] = [[0.0, 1.0]; 3];
+
+/// Converts 8-bit rgb to planar ycbcr in single precision floating point
+///
+/// Luma and chroma are normalized to [0, 1] and [-0.5, 0.5], then each plane is
+/// standardized as (value - mean) / std. A single buffer stores the three planes
+/// one after the other.
+pub fn lrgb_to_yuvf32(
+    width: u32,
+    height: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: usize,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+    channels: PixelFormatChannels,
+    sampler: Sampler,
+    coefficients: &[f32; 4],
+    normalization: &[[f32; 2]; 3],
+) -> bool {
+    const SAMPLE_SIZE: usize = 4;
+
+    let depth = channels as usize;
+    let col_count = width as usize;
+    let line_count = height as usize;
+
+    let rgb_stride = match get_rgb_stride(
+        depth,
+        col_count,
+        line_count,
+        src_strides,
+        src_buffers.len(),
+        src_buffers.first().map_or(0, |buffer| buffer.len()),
+    ) {
+        Some(stride) => stride,
+        None => return false,
+    };
+
+    if (last_dst_plane != 0 && last_dst_plane != 2)
+        || last_dst_plane >= dst_strides.len()
+        || last_dst_plane >= dst_buffers.len()
+    {
+        return false;
+    }
+
+    // (buffer, offset, stride) of each plane
+    let mut planes = [(0usize, 0usize, 0usize); 3];
+    let mut offset = 0usize;
+    for (i, plane) in planes.iter_mut().enumerate() {
+        let line_size = SAMPLE_SIZE * col_count;
+        let stride = match dst_strides.get(i).copied().unwrap_or(0) {
+            0 => line_size,
+            stride if stride >= line_size => stride,
+            _ => return false,
+        };
+
+        let buffer = if last_dst_plane == 0 { 0 } else { i };
+        let end = match stride
+            .checked_mul(line_count)
+            .and_then(|size| size.checked_add(offset))
+        {
+            Some(end) if end <= dst_buffers[buffer].len() => end,
+            _ => return false,
+        };
+
+        *plane = (buffer, offset, stride);
+        offset = if last_dst_plane == 0 { end } else { 0 };
+    }
+
+    let [rcr, _, _, bcb] = *coefficients;
+    let kr = 1.0 - 0.5 * rcr;
+    let kb = 1.0 - 0.5 * bcb;
+    let kg = 1.0 - kr - kb;
+
+    let offsets = &SAMPLER_OFFSETS[sampler as usize];
+    let rgb_plane = src_buffers[0];
+    for y in 0..line_count {
+        for x in 0..col_count {
+            let pixel = &rgb_plane[y * rgb_stride + x * depth..];
+            let r = f32::from(pixel[offsets[0]]) / 255.0;
+            let g = f32::from(pixel[offsets[1]]) / 255.0;
+            let b = f32::from(pixel[offsets[2]]) / 255.0;
+
+            let l = kr * r + kg * g + kb * b;
+            let values = [l, (b - l) / bcb, (r - l) / rcr];
+            for ((&(buffer, offset, stride), value), &[mean, std]) in
+                planes.iter().zip(values.iter()).zip(normalization.iter())
+            {
+                let index = offset + y * stride + x * SAMPLE_SIZE;
+                let value = (value - mean) / std;
+                dst_buffers[buffer][index..index + SAMPLE_SIZE]
+                    .copy_from_slice(&value.to_bits().to_le_bytes());
+            }
+        }
+    }
+
+    true
+}
+
+/// Rounds a fixed point number to integer
+fn round_fix(fix: i32, frac_bits: i32, rounding: Rounding) -> i32 {
+    let half = 1 << (frac_bits - 1);
+    match rounding {
+        Rounding::Truncate => fix >> frac_bits,
+        Rounding::HalfUp => (fix + half) >> frac_bits,
+        Rounding::HalfEven => {
+            let integer = fix >> frac_bits;
+            let fraction = fix & ((1 << frac_bits) - 1);
+            if fraction > half || (fraction == half && (integer & 1) != 0) {
+                integer + 1
+            } else {
+                integer
+            }
+        }
+    }
+}
+
+/// Planes of a ycbcr image written by a conversion
+#[derive(Copy, Clone, PartialEq)]
+pub enum YuvPlanes {
+    All,
+    Luma,
+    Chroma,
+}
+
+/// Converts 8-bit rgb to ycbcr in fixed point, with 4:2:0 or 4:4:4 downsampling
+///
+/// Uses the weights of [`lrgb_to_yuv`], with 16 fractional bits, and rounds once per
+/// sample as requested. Chroma of subsampled images is computed from the sum of the rgb
+/// values it covers, with two more fractional bits. When rounding half up, the output
+/// is the same as the one of [`lrgb_to_yuv`].
+///
+/// Only the requested planes are written, the others are left untouched.
+pub fn lrgb_to_yuv_fix16(
+    width: u32,
+    height: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: usize,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+    channels: PixelFormatChannels,
+    sampler: Sampler,
+    layout: YuvLayout,
+    colorimetry: Colorimetry,
+    rounding: Rounding,
+    planes: YuvPlanes,
+) -> bool {
+    let depth = channels as usize;
+    let col_count = width as usize;
+    let line_count = height as usize;
+
+    let rgb_stride = match get_rgb_stride(
+        depth,
+        col_count,
+        line_count,
+        src_strides,
+        src_buffers.len(),
+        src_buffers.first().map_or(0, |buffer| buffer.len()),
+    ) {
+        Some(stride) => stride,
+        None => return false,
+    };
+
+    let dst_sizes: Vec<usize> = dst_buffers.iter().map(|buffer| buffer.len()).collect();
+    let dst_planes = match get_yuv_planes(
+        layout,
+        col_count,
+        line_count,
+        last_dst_plane,
+        dst_strides,
+        &dst_sizes,
+    ) {
+        Some(planes) => planes,
+        None => return false,
+    };
+
+    let weights = &FORWARD_WEIGHTS[colorimetry as usize];
+    let offsets = &SAMPLER_OFFSETS[sampler as usize];
+    let rgb_plane = src_buffers[0];
+    let read = |x: usize, y: usize| {
+        let pixel = &rgb_plane[y * rgb_stride + x * depth..];
+        (
+            i32::from(pixel[offsets[0]]),
+            i32::from(pixel[offsets[1]]),
+            i32::from(pixel[offsets[2]]),
+        )
+    };
+
+    let sample = |(r, g, b): (i32, i32, i32), weights: &[i32], frac_bits: i32| {
+        round_fix(
+            affine_transform(r, g, b, weights[0], weights[1], weights[2], 0),
+            frac_bits,
+            rounding,
+        )
+    };
+
+    if planes != YuvPlanes::Chroma {
+        for y in 0..line_count {
+            for x in 0..col_count {
+                let l = Y_MIN + sample(read(x, y), &weights[0..3], FIX16);
+                dst_buffers[dst_planes[0].buffer][dst_planes[0].index(x, y)] = l as u8;
+            }
+        }
+    }
+
+    if planes == YuvPlanes::Luma {
+        return true;
+    }
+
+    let shift = match layout {
+        YuvLayout::I444 => 0,
+        _ => 1,
+    };
+
+    for y in 0..(line_count >> shift) {
+        for x in 0..(col_count >> shift) {
+            let (mut r, mut g, mut b) = (0, 0, 0);
+            for dy in 0..(1 << shift) {
+                for dx in 0..(1 << shift) {
+                    let (sr, sg, sb) = read((x << shift) + dx, (y << shift) + dy);
+                    r += sr;
+                    g += sg;
+                    b += sb;
+                }
+            }
+
+            let frac_bits = FIX16 + 2 * shift;
+            let cb = C_HALF + sample((r, g, b), &weights[3..6], frac_bits);
+            let cr = C_HALF + sample((r, g, b), &weights[6..9], frac_bits);
+            dst_buffers[dst_planes[1].buffer][dst_planes[1].index(x, y)] = cb as u8;
+            dst_buffers[dst_planes[2].buffer][dst_planes[2].index(x, y)] = cr as u8;
+        }
+    }
+
+    true
+}
+
+/// Converts 8-bit rgb to full range ycbcr exactly as libjpeg does, with 4:2:0 or 4:4:4
+/// downsampling
+///
+/// Samples are computed with weights of 16 fractional bits and rounded once, with chroma
+/// ties rounded down. Chroma of subsampled images is computed for each pixel, then the
+/// samples of each 2x2 block are averaged with a bias alternating between 1 and 2 along
+/// the line.
+pub fn lrgb_to_yuv_jfif(
+    width: u32,
+    height: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: usize,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+    channels: PixelFormatChannels,
+    sampler: Sampler,
+    layout: YuvLayout,
+) -> bool {
+    const WEIGHTS: [i32; 9] = [
+        XR_JFIF, XG_JFIF, XB_JFIF, YR_JFIF, YG_JFIF, YB_JFIF, ZR_JFIF, ZG_JFIF, ZB_JFIF,
+    ];
+
+    let depth = channels as usize;
+    let col_count = width as usize;
+    let line_count = height as usize;
+
+    let rgb_stride = match get_rgb_stride(
+        depth,
+        col_count,
+        line_count,
+        src_strides,
+        src_buffers.len(),
+        src_buffers.first().map_or(0, |buffer| buffer.len()),
+    ) {
+        Some(stride) => stride,
+        None => return false,
+    };
+
+    let dst_sizes: Vec<usize> = dst_buffers.iter().map(|buffer| buffer.len()).collect();
+    let dst_planes = match get_yuv_planes(
+        layout,
+        col_count,
+        line_count,
+        last_dst_plane,
+        dst_strides,
+        &dst_sizes,
+    ) {
+        Some(planes) => planes,
+        None => return false,
+    };
+
+    let offsets = &SAMPLER_OFFSETS[sampler as usize];
+    let rgb_plane = src_buffers[0];
+    let read = |x: usize, y: usize| {
+        let pixel = &rgb_plane[y * rgb_stride + x * depth..];
+        (
+            i32::from(pixel[offsets[0]]),
+            i32::from(pixel[offsets[1]]),
+            i32::from(pixel[offsets[2]]),
+        )
+    };
+
+    let sample = |(r, g, b): (i32, i32, i32), weights: &[i32], offset: i32| {
+        affine_transform(r, g, b, weights[0], weights[1], weights[2], offset) >> FIX16
+    };
+
+    for y in 0..line_count {
+        for x in 0..col_count {
+            let l = sample(read(x, y), &WEIGHTS[0..3], FIX16_HALF);
+            dst_buffers[dst_planes[0].buffer][dst_planes[0].index(x, y)] = l as u8;
+        }
+    }
+
+    let shift = match layout {
+        YuvLayout::I444 => 0,
+        _ => 1,
+    };
+
+    for y in 0..(line_count >> shift) {
+        for x in 0..(col_count >> shift) {
+            let (mut cb, mut cr) = (0, 0);
+            for dy in 0..(1 << shift) {
+                for dx in 0..(1 << shift) {
+                    let rgb = read((x << shift) + dx, (y << shift) + dy);
+                    cb += sample(rgb, &WEIGHTS[3..6], C_OFFSET16 - 1);
+                    cr += sample(rgb, &WEIGHTS[6..9], C_OFFSET16 - 1);
+                }
+            }
+
+            let bias = if shift == 0 { 0 } else { 1 + (x & 1) as i32 };
+            let cb = (cb + bias) >> (2 * shift);
+            let cr = (cr + bias) >> (2 * shift);
+            dst_buffers[dst_planes[1].buffer][dst_planes[1].index(x, y)] = cb as u8;
+            dst_buffers[dst_planes[2].buffer][dst_planes[2].index(x, y)] = cr as u8;
+        }
+    }
+
+    true
+}
+
+/// Converts full range ycbcr to 8-bit bgra exactly as libjpeg does, with 4:4:4 upsampling
+///
+/// Chroma is replicated, as libjpeg does when fancy upsampling is disabled. The chroma
+/// contribution to each component is computed with weights of 16 fractional bits and
+/// rounded to nearest, then added to luma. Components are saturated.
+pub fn yuv_jfif_to_bgra(
+    width: u32,
+    height: u32,
+    last_src_plane: usize,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+    layout: YuvLayout,
+) -> bool {
+    const OUTPUT_BPP: usize = 4;
+
+    let col_count = width as usize;
+    let line_count = height as usize;
+
+    let src_sizes: Vec<usize> = src_buffers.iter().map(|buffer| buffer.len()).collect();
+    let src_planes = match get_yuv_planes(
+        layout,
+        col_count,
+        line_count,
+        last_src_plane,
+        src_strides,
+        &src_sizes,
+    ) {
+        Some(planes) => planes,
+        None => return false,
+    };
+
+    let rgb_stride = match get_rgb_stride(
+        OUTPUT_BPP,
+        col_count,
+        line_count,
+        dst_strides,
+        dst_buffers.len(),
+        dst_buffers.first().map_or(0, |buffer| buffer.len()),
+    ) {
+        Some(stride) => stride,
+        None => return false,
+    };
+
+    let shift = match layout {
+        YuvLayout::I444 => 0,
+        _ => 1,
+    };
+
+    let read = |plane: &YuvPlane, x: usize, y: usize| {
+        i32::from(src_buffers[plane.buffer][plane.index(x, y)])
+    };
+
+    let to_u8 = |value: i32| value.clamp(0, 255) as u8;
+
+    let rgb_plane = &mut dst_buffers[0];
+    for y in 0..line_count {
+        for x in 0..col_count {
+            let l = read(&src_planes[0], x, y);
+            let cb = read(&src_planes[1], x >> shift, y >> shift) - C_HALF;
+            let cr = read(&src_planes[2], x >> shift, y >> shift) - C_HALF;
+
+            let offset = y * rgb_stride + x * OUTPUT_BPP;
+            let pixel = &mut rgb_plane[offset..offset + OUTPUT_BPP];
+            pixel[0] = to_u8(l + ((BCB_JFIF * cb + FIX16_HALF) >> FIX16));
+            pixel[1] = to_u8(l + ((-GCB_JFIF * cb - GCR_JFIF * cr + FIX16_HALF) >> FIX16));
+            pixel[2] = to_u8(l + ((RCR_JFIF * cr + FIX16_HALF) >> FIX16));
+            pixel[3] = DEFAULT_ALPHA;
+        }
+    }
+
+    true
+}
+
+pub fn argb_lrgb_nv12_bt601(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_yuv(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Four,
+        Colorimetry::Bt601,
+        Sampler::Argb,
+    )
+}
+
+pub fn argb_lrgb_nv12_bt709(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_yuv(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Four,
+        Colorimetry::Bt709,
+        Sampler::Argb,
+    )
+}
+
+pub fn bgra_lrgb_nv12_bt601(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_yuv(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Four,
+        Colorimetry::Bt601,
+        Sampler::Bgra,
+    )
+}
+
+pub fn bgra_lrgb_nv12_bt709(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_yuv(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Four,
+        Colorimetry::Bt709,
+        Sampler::Bgra,
+    )
+}
+
+pub fn bgr_lrgb_nv12_bt601(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_yuv(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Three,
+        Colorimetry::Bt601,
+        Sampler::Bgr,
+    )
+}
+
+pub fn bgr_lrgb_nv12_bt709(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_yuv(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Three,
+        Colorimetry::Bt709,
+        Sampler::Bgr,
+    )
+}
+
+pub fn nv12_bt601_bgra_lrgb(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_to_lrgb(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Four,
+        Colorimetry::Bt601,
+    )
+}
+
+pub fn nv12_bt709_bgra_lrgb(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_to_lrgb(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Four,
+        Colorimetry::Bt709,
+    )
+}
+
+pub fn i420_bt601_bgra_lrgb(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    i420_to_lrgb(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Four,
+        Colorimetry::Bt601,
+    )
+}
+
+pub fn i420_bt709_bgra_lrgb(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    i420_to_lrgb(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Four,
+        Colorimetry::Bt709,
+    )
+}
+
+pub fn i444_bt601_bgra_lrgb(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    i444_to_lrgb(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Four,
+        Colorimetry::Bt601,
+    )
+}
+
+pub fn i444_bt709_bgra_lrgb(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    i444_to_lrgb(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Four,
+        Colorimetry::Bt709,
+    )
+}
+
+pub fn argb_lrgb_i420_bt601(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_i420(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Four,
+        Colorimetry::Bt601,
+        Sampler::Argb,
+    )
+}
+
+pub fn argb_lrgb_i420_bt709(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_i420(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Four,
+        Colorimetry::Bt709,
+        Sampler::Argb,
+    )
+}
+
+pub fn bgra_lrgb_i420_bt601(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_i420(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Four,
+        Colorimetry::Bt601,
+        Sampler::Bgra,
+    )
+}
+
+pub fn bgra_lrgb_i420_bt709(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_i420(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Four,
+        Colorimetry::Bt709,
+        Sampler::Bgra,
+    )
+}
+
+pub fn bgr_lrgb_i420_bt601(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_i420(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Three,
+        Colorimetry::Bt601,
+        Sampler::Bgr,
+    )
+}
+
+pub fn bgr_lrgb_i420_bt709(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_i420(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Three,
+        Colorimetry::Bt709,
+        Sampler::Bgr,
+    )
+}
+
+pub fn argb_lrgb_i444_bt601(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_i444(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Four,
+        Colorimetry::Bt601,
+        Sampler::Argb,
+    )
+}
+
+pub fn argb_lrgb_i444_bt709(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_i444(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Four,
+        Colorimetry::Bt709,
+        Sampler::Argb,
+    )
+}
+
+pub fn bgra_lrgb_i444_bt601(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_i444(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Four,
+        Colorimetry::Bt601,
+        Sampler::Bgra,
+    )
+}
+
+pub fn bgra_lrgb_i444_bt709(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_i444(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Four,
+        Colorimetry::Bt709,
+        Sampler::Bgra,
+    )
+}
+
+pub fn bgr_lrgb_i444_bt601(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_i444(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Three,
+        Colorimetry::Bt601,
+        Sampler::Bgr,
+    )
+}
+
+pub fn bgr_lrgb_i444_bt709(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_i444(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Three,
+        Colorimetry::Bt709,
+        Sampler::Bgr,
+    )
+}
+
+pub fn bgra_lrgb_rgb_lrgb(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    unsafe {
+        bgra_to_rgb(
+            width,
+            height,
+            last_src_plane,
+            src_strides,
+            src_buffers,
+            last_dst_plane,
+            dst_strides,
+            dst_buffers,
+        )
+    }
+}
+
+pub fn p010_bt2020pq_rgbaf16_lrgb(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    p010_to_rgbaf16(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+    )
+}
+
+pub fn rgbaf16_lrgb_bgra_lrgb(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    rgbaf16_to_bgra(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+    )
+}
+
+pub fn bgr48_lrgb_p010_bt601(
+    width: u32,
+    height: u32,
+    _last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb48_to_p010(
+        width,
+        height,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        true,
+        &YCBCR_TO_RGB_601,
+    )
+}
+
+pub fn bgr48_lrgb_p010_bt709(
+    width: u32,
+    height: u32,
+    _last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb48_to_p010(
+        width,
+        height,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        true,
+        &YCBCR_TO_RGB_709,
+    )
+}
+
+pub fn rgb48_lrgb_p010_bt601(
+    width: u32,
+    height: u32,
+    _last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb48_to_p010(
+        width,
+        height,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        false,
+        &YCBCR_TO_RGB_601,
+    )
+}
+
+pub fn rgb48_lrgb_p010_bt709(
+    width: u32,
+    height: u32,
+    _last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb48_to_p010(
+        width,
+        height,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        false,
+        &YCBCR_TO_RGB_709,
+    )
+}
+
+pub fn bgr48_lrgb_bgra_lrgb(
+    width: u32,
+    height: u32,
+    _last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    _last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb48_to_bgra(
+        width,
+        height,
+        src_strides,
+        src_buffers,
+        dst_strides,
+        dst_buffers,
+        true,
+        false,
+    )
+}
+
+pub fn rgb48_lrgb_bgra_lrgb(
+    width: u32,
+    height: u32,
+    _last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    _last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb48_to_bgra(
+        width,
+        height,
+        src_strides,
+        src_buffers,
+        dst_strides,
+        dst_buffers,
+        false,
+        false,
+    )
+}
+
+pub fn nv12_bt601_nv12_bt709(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_to_yuv(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::Nv12,
+        &YCBCR_601_TO_709,
+    )
+}
+
+pub fn nv12_bt709_nv12_bt601(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_to_yuv(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::Nv12,
+        &YCBCR_709_TO_601,
+    )
+}
+
+pub fn i420_bt601_i420_bt709(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_to_yuv(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::I420,
+        &YCBCR_601_TO_709,
+    )
+}
+
+pub fn i420_bt709_i420_bt601(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_to_yuv(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::I420,
+        &YCBCR_709_TO_601,
+    )
+}
+
+pub fn i444_bt601_i444_bt709(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_to_yuv(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::I444,
+        &YCBCR_601_TO_709,
+    )
+}
+
+pub fn i444_bt709_i444_bt601(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_to_yuv(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::I444,
+        &YCBCR_709_TO_601,
+    )
+}
+
+pub fn nv12_bt601_p010_bt601(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_depth(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::Nv12,
+        YuvLayout::P010,
+        false,
+    )
+}
+
+pub fn nv12_bt709_p010_bt709(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_depth(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::Nv12,
+        YuvLayout::P010,
+        false,
+    )
+}
+
+pub fn nv12_bt2020pq_p010_bt2020pq(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_depth(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::Nv12,
+        YuvLayout::P010,
+        false,
+    )
+}
+
+pub fn p010_bt601_nv12_bt601(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_depth(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::P010,
+        YuvLayout::Nv12,
+        false,
+    )
+}
+
+pub fn p010_bt709_nv12_bt709(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_depth(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::P010,
+        YuvLayout::Nv12,
+        false,
+    )
+}
+
+pub fn p010_bt2020pq_nv12_bt2020pq(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
     last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    lrgb_to_yuv(
+    yuv_depth(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::P010,
+        YuvLayout::Nv12,
+        false,
+    )
+}
+
+pub fn nv12_bt601_rgbf32_lrgb(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    _last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_to_lrgb_matrix(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::Nv12,
+        &get_ycbcr_to_rgb_matrix(&YCBCR_TO_RGB_601),
+        RgbOutput::RgbF32,
+    )
+}
+
+pub fn nv12_bt601_rgbaf32_lrgb(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    _last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_to_lrgb_matrix(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::Nv12,
+        &get_ycbcr_to_rgb_matrix(&YCBCR_TO_RGB_601),
+        RgbOutput::RgbaF32,
+    )
+}
+
+pub fn nv12_bt709_rgbf32_lrgb(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    _last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_to_lrgb_matrix(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::Nv12,
+        &get_ycbcr_to_rgb_matrix(&YCBCR_TO_RGB_709),
+        RgbOutput::RgbF32,
+    )
+}
+
+pub fn nv12_bt709_rgbaf32_lrgb(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    _last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_to_lrgb_matrix(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::Nv12,
+        &get_ycbcr_to_rgb_matrix(&YCBCR_TO_RGB_709),
+        RgbOutput::RgbaF32,
+    )
+}
+
+pub fn i420_bt601_rgbf32_lrgb(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    _last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_to_lrgb_matrix(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::I420,
+        &get_ycbcr_to_rgb_matrix(&YCBCR_TO_RGB_601),
+        RgbOutput::RgbF32,
+    )
+}
+
+pub fn i420_bt601_rgbaf32_lrgb(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    _last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_to_lrgb_matrix(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides,
+        src_buffers,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::I420,
+        &get_ycbcr_to_rgb_matrix(&YCBCR_TO_RGB_601),
+        RgbOutput::RgbaF32,
+    )
+}
+
+pub fn i420_bt709_rgbf32_lrgb(
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    _last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    yuv_to_lrgb_matrix(
         width,
         height,
         last_src_plane as usize,
         src_strides,
         src_buffers,
-        last_dst_plane as usize,
         dst_strides,
         dst_buffers,
-        PixelFormatChannels::Three,
-        Colorimetry::Bt601,
-        Sampler::Bgr,
+        YuvLayout::I420,
+        &get_ycbcr_to_rgb_matrix(&YCBCR_TO_RGB_709),
+        RgbOutput::RgbF32,
     )
 }
 
-pub fn bgr_lrgb_nv12_bt709(
+pub fn i420_bt709_rgbaf32_lrgb(
     width: u32,
     height: u32,
     last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
-    last_dst_plane: u32,
+    _last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    lrgb_to_yuv(
+    yuv_to_lrgb_matrix(
         width,
         height,
         last_src_plane as usize,
         src_strides,
         src_buffers,
-        last_dst_plane as usize,
         dst_strides,
         dst_buffers,
-        PixelFormatChannels::Three,
-        Colorimetry::Bt709,
-        Sampler::Bgr,
+        YuvLayout::I420,
+        &get_ycbcr_to_rgb_matrix(&YCBCR_TO_RGB_709),
+        RgbOutput::RgbaF32,
     )
 }
 
-pub fn nv12_bt601_bgra_lrgb(
+pub fn i444_bt601_rgbf32_lrgb(
     width: u32,
     height: u32,
     last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
-    last_dst_plane: u32,
+    _last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    yuv_to_lrgb(
+    yuv_to_lrgb_matrix(
         width,
         height,
         last_src_plane as usize,
         src_strides,
         src_buffers,
-        last_dst_plane as usize,
         dst_strides,
         dst_buffers,
-        PixelFormatChannels::Four,
-        Colorimetry::Bt601,
+        YuvLayout::I444,
+        &get_ycbcr_to_rgb_matrix(&YCBCR_TO_RGB_601),
+        RgbOutput::RgbF32,
     )
 }
 
-pub fn nv12_bt709_bgra_lrgb(
+pub fn i444_bt601_rgbaf32_lrgb(
     width: u32,
     height: u32,
     last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
-    last_dst_plane: u32,
+    _last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    yuv_to_lrgb(
+    yuv_to_lrgb_matrix(
         width,
         height,
         last_src_plane as usize,
         src_strides,
         src_buffers,
-        last_dst_plane as usize,
         dst_strides,
         dst_buffers,
-        PixelFormatChannels::Four,
-        Colorimetry::Bt709,
+        YuvLayout::I444,
+        &get_ycbcr_to_rgb_matrix(&YCBCR_TO_RGB_601),
+        RgbOutput::RgbaF32,
     )
 }
 
-pub fn i420_bt601_bgra_lrgb(
+pub fn i444_bt709_rgbf32_lrgb(
     width: u32,
     height: u32,
     last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
-    last_dst_plane: u32,
+    _last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    i420_to_lrgb(
+    yuv_to_lrgb_matrix(
         width,
         height,
         last_src_plane as usize,
         src_strides,
         src_buffers,
-        last_dst_plane as usize,
         dst_strides,
         dst_buffers,
-        PixelFormatChannels::Four,
-        Colorimetry::Bt601,
+        YuvLayout::I444,
+        &get_ycbcr_to_rgb_matrix(&YCBCR_TO_RGB_709),
+        RgbOutput::RgbF32,
     )
 }
 
-pub fn i420_bt709_bgra_lrgb(
+pub fn i444_bt709_rgbaf32_lrgb(
     width: u32,
     height: u32,
     last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
-    last_dst_plane: u32,
+    _last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    i420_to_lrgb(
+    yuv_to_lrgb_matrix(
         width,
         height,
         last_src_plane as usize,
         src_strides,
         src_buffers,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::I444,
+        &get_ycbcr_to_rgb_matrix(&YCBCR_TO_RGB_709),
+        RgbOutput::RgbaF32,
+    )
+}
+
+pub fn argb_lrgb_nv12_jfif(
+    width: u32,
+    height: u32,
+    _last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_yuv_jfif(
+        width,
+        height,
+        src_strides,
+        src_buffers,
         last_dst_plane as usize,
         dst_strides,
         dst_buffers,
         PixelFormatChannels::Four,
-        Colorimetry::Bt709,
+        Sampler::Argb,
+        YuvLayout::Nv12,
     )
 }
 
-pub fn i444_bt601_bgra_lrgb(
+pub fn bgra_lrgb_nv12_jfif(
     width: u32,
     height: u32,
-    last_src_plane: u32,
+    _last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
     last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    i444_to_lrgb(
+    lrgb_to_yuv_jfif(
         width,
         height,
-        last_src_plane as usize,
         src_strides,
         src_buffers,
         last_dst_plane as usize,
         dst_strides,
         dst_buffers,
         PixelFormatChannels::Four,
-        Colorimetry::Bt601,
+        Sampler::Bgra,
+        YuvLayout::Nv12,
     )
 }
 
-pub fn i444_bt709_bgra_lrgb(
+pub fn bgr_lrgb_nv12_jfif(
     width: u32,
     height: u32,
-    last_src_plane: u32,
+    _last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
     last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    i444_to_lrgb(
+    lrgb_to_yuv_jfif(
         width,
         height,
-        last_src_plane as usize,
         src_strides,
         src_buffers,
         last_dst_plane as usize,
         dst_strides,
         dst_buffers,
-        PixelFormatChannels::Four,
-        Colorimetry::Bt709,
+        PixelFormatChannels::Three,
+        Sampler::Bgr,
+        YuvLayout::Nv12,
     )
 }
 
-pub fn argb_lrgb_i420_bt601(
+pub fn argb_lrgb_i420_jfif(
     width: u32,
     height: u32,
-    last_src_plane: u32,
+    _last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
     last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    lrgb_to_i420(
+    lrgb_to_yuv_jfif(
         width,
         height,
-        last_src_plane as usize,
         src_strides,
         src_buffers,
         last_dst_plane as usize,
         dst_strides,
         dst_buffers,
         PixelFormatChannels::Four,
-        Colorimetry::Bt601,
         Sampler::Argb,
+        YuvLayout::I420,
     )
 }
 
-pub fn argb_lrgb_i420_bt709(
+pub fn bgra_lrgb_i420_jfif(
     width: u32,
     height: u32,
-    last_src_plane: u32,
+    _last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
     last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    lrgb_to_i420(
+    lrgb_to_yuv_jfif(
         width,
         height,
-        last_src_plane as usize,
         src_strides,
         src_buffers,
         last_dst_plane as usize,
         dst_strides,
         dst_buffers,
         PixelFormatChannels::Four,
-        Colorimetry::Bt709,
-        Sampler::Argb,
+        Sampler::Bgra,
+        YuvLayout::I420,
     )
 }
 
-pub fn bgra_lrgb_i420_bt601(
+pub fn bgr_lrgb_i420_jfif(
     width: u32,
     height: u32,
-    last_src_plane: u32,
+    _last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
     last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    lrgb_to_i420(
+    lrgb_to_yuv_jfif(
+        width,
+        height,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Three,
+        Sampler::Bgr,
+        YuvLayout::I420,
+    )
+}
+
+pub fn argb_lrgb_i444_jfif(
+    width: u32,
+    height: u32,
+    _last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_yuv_jfif(
         width,
         height,
-        last_src_plane as usize,
         src_strides,
         src_buffers,
         last_dst_plane as usize,
         dst_strides,
         dst_buffers,
         PixelFormatChannels::Four,
-        Colorimetry::Bt601,
-        Sampler::Bgra,
+        Sampler::Argb,
+        YuvLayout::I444,
     )
 }
 
-pub fn bgra_lrgb_i420_bt709(
+pub fn bgra_lrgb_i444_jfif(
     width: u32,
     height: u32,
-    last_src_plane: u32,
+    _last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
     last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    lrgb_to_i420(
+    lrgb_to_yuv_jfif(
         width,
         height,
-        last_src_plane as usize,
         src_strides,
         src_buffers,
         last_dst_plane as usize,
         dst_strides,
         dst_buffers,
         PixelFormatChannels::Four,
-        Colorimetry::Bt709,
         Sampler::Bgra,
+        YuvLayout::I444,
     )
 }
 
-pub fn bgr_lrgb_i420_bt601(
+pub fn bgr_lrgb_i444_jfif(
     width: u32,
     height: u32,
-    last_src_plane: u32,
+    _last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
     last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    lrgb_to_i420(
+    lrgb_to_yuv_jfif(
         width,
         height,
-        last_src_plane as usize,
         src_strides,
         src_buffers,
         last_dst_plane as usize,
         dst_strides,
         dst_buffers,
         PixelFormatChannels::Three,
-        Colorimetry::Bt601,
         Sampler::Bgr,
+        YuvLayout::I444,
     )
 }
 
-pub fn bgr_lrgb_i420_bt709(
+pub fn nv12_jfif_bgra_lrgb(
     width: u32,
     height: u32,
     last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
-    last_dst_plane: u32,
+    _last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    lrgb_to_i420(
+    yuv_jfif_to_bgra(
         width,
         height,
         last_src_plane as usize,
         src_strides,
         src_buffers,
-        last_dst_plane as usize,
         dst_strides,
         dst_buffers,
-        PixelFormatChannels::Three,
-        Colorimetry::Bt709,
-        Sampler::Bgr,
+        YuvLayout::Nv12,
     )
 }
 
-pub fn argb_lrgb_i444_bt601(
+pub fn i420_jfif_bgra_lrgb(
     width: u32,
     height: u32,
     last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
-    last_dst_plane: u32,
+    _last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    lrgb_to_i444(
+    yuv_jfif_to_bgra(
         width,
         height,
         last_src_plane as usize,
         src_strides,
         src_buffers,
-        last_dst_plane as usize,
         dst_strides,
         dst_buffers,
-        PixelFormatChannels::Four,
-        Colorimetry::Bt601,
-        Sampler::Argb,
+        YuvLayout::I420,
     )
 }
 
-pub fn argb_lrgb_i444_bt709(
+pub fn i444_jfif_bgra_lrgb(
     width: u32,
     height: u32,
     last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
-    last_dst_plane: u32,
+    _last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    lrgb_to_i444(
+    yuv_jfif_to_bgra(
         width,
         height,
         last_src_plane as usize,
         src_strides,
         src_buffers,
+        dst_strides,
+        dst_buffers,
+        YuvLayout::I444,
+    )
+}
+
+pub fn argb_lrgb_yuvf32planar_bt601(
+    width: u32,
+    height: u32,
+    _last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    last_dst_plane: u32,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> bool {
+    lrgb_to_yuvf32(
+        width,
+        height,
+        src_strides,
+        src_buffers,
         last_dst_plane as usize,
         dst_strides,
         dst_buffers,
         PixelFormatChannels::Four,
-        Colorimetry::Bt709,
         Sampler::Argb,
+        &YCBCR_TO_RGB_601,
+        &IDENTITY_NORMALIZATION,
     )
 }
 
-pub fn bgra_lrgb_i444_bt601(
+pub fn argb_lrgb_yuvf32planar_bt709(
     width: u32,
     height: u32,
-    last_src_plane: u32,
+    _last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
     last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    lrgb_to_i444(
+    lrgb_to_yuvf32(
         width,
         height,
-        last_src_plane as usize,
         src_strides,
         src_buffers,
         last_dst_plane as usize,
         dst_strides,
         dst_buffers,
         PixelFormatChannels::Four,
-        Colorimetry::Bt601,
-        Sampler::Bgra,
+        Sampler::Argb,
+        &YCBCR_TO_RGB_709,
+        &IDENTITY_NORMALIZATION,
     )
 }
 
-pub fn bgra_lrgb_i444_bt709(
+pub fn bgra_lrgb_yuvf32planar_bt601(
     width: u32,
     height: u32,
-    last_src_plane: u32,
+    _last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
     last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    lrgb_to_i444(
+    lrgb_to_yuvf32(
         width,
         height,
-        last_src_plane as usize,
         src_strides,
         src_buffers,
         last_dst_plane as usize,
         dst_strides,
         dst_buffers,
         PixelFormatChannels::Four,
-        Colorimetry::Bt709,
         Sampler::Bgra,
+        &YCBCR_TO_RGB_601,
+        &IDENTITY_NORMALIZATION,
     )
 }
 
-pub fn bgr_lrgb_i444_bt601(
+pub fn bgra_lrgb_yuvf32planar_bt709(
     width: u32,
     height: u32,
-    last_src_plane: u32,
+    _last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
     last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    lrgb_to_i444(
+    lrgb_to_yuvf32(
         width,
         height,
-        last_src_plane as usize,
         src_strides,
         src_buffers,
         last_dst_plane as usize,
         dst_strides,
         dst_buffers,
-        PixelFormatChannels::Three,
-        Colorimetry::Bt601,
-        Sampler::Bgr,
+        PixelFormatChannels::Four,
+        Sampler::Bgra,
+        &YCBCR_TO_RGB_709,
+        &IDENTITY_NORMALIZATION,
     )
 }
 
-pub fn bgr_lrgb_i444_bt709(
+pub fn bgr_lrgb_yuvf32planar_bt601(
     width: u32,
     height: u32,
-    last_src_plane: u32,
+    _last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
     last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    lrgb_to_i444(
+    lrgb_to_yuvf32(
         width,
         height,
-        last_src_plane as usize,
         src_strides,
         src_buffers,
         last_dst_plane as usize,
         dst_strides,
         dst_buffers,
         PixelFormatChannels::Three,
-        Colorimetry::Bt709,
         Sampler::Bgr,
+        &YCBCR_TO_RGB_601,
+        &IDENTITY_NORMALIZATION,
     )
 }
 
-pub fn bgra_lrgb_rgb_lrgb(
+pub fn bgr_lrgb_yuvf32planar_bt709(
     width: u32,
     height: u32,
-    last_src_plane: u32,
+    _last_src_plane: u32,
     src_strides: &[usize],
     src_buffers: &[&[u8]],
     last_dst_plane: u32,
     dst_strides: &[usize],
     dst_buffers: &mut [&mut [u8]],
 ) -> bool {
-    unsafe {
-        bgra_to_rgb(
-            width,
-            height,
-            last_src_plane,
-            src_strides,
-            src_buffers,
-            last_dst_plane,
-            dst_strides,
-            dst_buffers,
-        )
+    lrgb_to_yuvf32(
+        width,
+        height,
+        src_strides,
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides,
+        dst_buffers,
+        PixelFormatChannels::Three,
+        Sampler::Bgr,
+        &YCBCR_TO_RGB_709,
+        &IDENTITY_NORMALIZATION,
+    )
+}
+
+pub fn abs_diff_sum(a: &[u8], b: &[u8]) -> (u64, u8) {
+    a.iter().zip(b.iter()).fold((0, 0), |(sum, max), (&x, &y)| {
+        let delta = x.abs_diff(y);
+        (sum + u64::from(delta), max.max(delta))
+    })
+}
+
+/// Divides by 255, rounding to nearest, for values up to 255 * 255
+fn div_255(x: u32) -> u32 {
+    let x = x + 128;
+    (x + (x >> 8)) >> 8
+}
+
+/// Blends a line of pixels over another, alpha being the last byte of each pixel
+pub fn blend_over(src: &[u8], dst: &mut [u8], premultiplied: bool) {
+    for (s, d) in src.chunks_exact(4).zip(dst.chunks_exact_mut(4)) {
+        let alpha = u32::from(s[3]);
+        let inv_alpha = 255 - alpha;
+        for (i, (&x, y)) in s.iter().zip(d.iter_mut()).enumerate() {
+            let value = if premultiplied {
+                u32::from(x) + div_255(u32::from(*y) * inv_alpha)
+            } else {
+                let x = if i == 3 { 255 } else { u32::from(x) };
+                div_255(x * alpha + u32::from(*y) * inv_alpha)
+            };
+
+            *y = value.min(255) as u8;
+        }
+    }
+}
+
+/// Replaces each sample of a line with its entry in a table. Samples at even and odd
+/// positions use their own table, for lines of interleaved chroma
+pub fn remap(samples: &mut [u8], even: &[u8; 256], odd: &[u8; 256]) {
+    for pair in samples.chunks_mut(2) {
+        pair[0] = even[usize::from(pair[0])];
+        if let Some(sample) = pair.get_mut(1) {
+            *sample = odd[usize::from(*sample)];
+        }
+    }
+}
+
+/// Blends a line of samples over another with the same weight for every sample
+pub fn cross_fade(src: &[u8], dst: &mut [u8], alpha: u8) {
+    let alpha = u32::from(alpha);
+    let inv_alpha = 255 - alpha;
+    for (&x, y) in src.iter().zip(dst.iter_mut()) {
+        *y = div_255(u32::from(x) * alpha + u32::from(*y) * inv_alpha) as u8;
+    }
+}
+
+/// Adds a line of samples, scaled by the same weight, to another one, saturating the sums
+pub fn accumulate(src: &[u8], dst: &mut [u8], alpha: u8) {
+    let alpha = u32::from(alpha);
+    for (&x, y) in src.iter().zip(dst.iter_mut()) {
+        *y = (u32::from(*y) + div_255(u32::from(x) * alpha)).min(255) as u8;
+    }
+}
+
+/// Interpolates a 3D look-up table at a point of one of its cells, with tetrahedral
+/// interpolation
+///
+/// `table` holds the points of a cube having `size` points along each axis, red changing
+/// fastest and blue slowest. `base` is the table point at the lower corner of the cell and
+/// `fraction` are the red, green and blue distances from it, between 0 and 1.
+#[inline(always)]
+pub fn interpolate_point(
+    table: &[[f32; 3]],
+    size: usize,
+    base: usize,
+    fraction: [f32; 3],
+) -> [f32; 3] {
+    let [fr, fg, fb] = fraction;
+    let (dr, dg, db) = (1, size, size * size);
+
+    // Pick the tetrahedron containing the point, walking from the lower to the upper
+    // corner of the cell along the axes sorted by decreasing fraction
+    let (w, d1, d2) = if fr > fg {
+        if fg > fb {
+            ([fr, fg, fb], dr, dr + dg)
+        } else if fr > fb {
+            ([fr, fb, fg], dr, dr + db)
+        } else {
+            ([fb, fr, fg], db, dr + db)
+        }
+    } else if fb > fg {
+        ([fb, fg, fr], db, dg + db)
+    } else if fb > fr {
+        ([fg, fb, fr], dg, dg + db)
+    } else {
+        ([fg, fr, fb], dg, dr + dg)
+    };
+
+    let c000 = table[base];
+    let p1 = table[base + d1];
+    let p2 = table[base + d2];
+    let c111 = table[base + dr + dg + db];
+
+    let mut out = [0.0; 3];
+    for (i, value) in out.iter_mut().enumerate() {
+        *value =
+            c000[i] + w[0] * (p1[i] - c000[i]) + w[1] * (p2[i] - p1[i]) + w[2] * (c111[i] - p2[i]);
+    }
+
+    out
+}
+
+/// Interpolates a 3D look-up table at a line of points, with tetrahedral interpolation
+///
+/// For each point, `index` is the table point at the lower corner of its cell and
+/// `fractions` hold the red, green and blue distances from it. Interpolated red, green
+/// and blue are written to `out`. See [`interpolate_point`] for the layout of the table.
+///
+/// [`interpolate_point`]: ./fn.interpolate_point.html
+pub fn interpolate_cube(
+    table: &[[f32; 3]],
+    size: usize,
+    index: &[u32],
+    fractions: [&[f32]; 3],
+    out: [&mut [f32]; 3],
+) {
+    let [fr, fg, fb] = fractions;
+    let [r, g, b] = out;
+    let points = index.iter().zip(fr).zip(fg).zip(fb);
+    let values = r.iter_mut().zip(g.iter_mut()).zip(b.iter_mut());
+    for ((((&base, &fr), &fg), &fb), ((r, g), b)) in points.zip(values) {
+        let rgb = interpolate_point(table, size, base as usize, [fr, fg, fb]);
+        *r = rgb[0];
+        *g = rgb[1];
+        *b = rgb[2];
     }
 }
@@ -13,35 +13,85 @@
 // HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
 // OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
 // SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
-#[cfg(target_arch = "x86")]
-use core::arch::x86::__cpuid;
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(target_arch = "x86", not(no_cpu_detection)))]
+use core::arch::x86::{__cpuid, has_cpuid};
+#[cfg(all(target_arch = "x86_64", not(no_cpu_detection)))]
 use core::arch::x86_64::__cpuid;
 
 #[derive(Debug)]
 pub enum CpuManufacturer {
     Unknown,
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[cfg(all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        not(no_cpu_detection)
+    ))]
     Intel,
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[cfg(all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        not(no_cpu_detection)
+    ))]
     Amd,
 }
 
 #[derive(Debug)]
 pub enum InstructionSet {
     X86,
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[cfg(all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        not(feature = "no-sse2")
+    ))]
     Sse2,
-    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[cfg(all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        not(feature = "no-avx2"),
+        not(feature = "no-sse2")
+    ))]
     Avx2,
 }
 
-#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+/// Instruction set extensions supported by the cpu, whether or not they are used
+#[derive(Copy, Clone, Debug)]
+pub struct CpuFeatures {
+    pub sse2: bool,
+    pub avx2: bool,
+}
+
+pub const NO_CPU_FEATURES: CpuFeatures = CpuFeatures {
+    sse2: false,
+    avx2: false,
+};
+
+/// Returns the instruction sets the library was built with, from the slowest to the fastest
+pub fn built_instruction_sets() -> Vec<InstructionSet> {
+    #[allow(unused_mut)]
+    let mut sets = vec![InstructionSet::X86];
+    #[cfg(all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        not(feature = "no-sse2")
+    ))]
+    sets.push(InstructionSet::Sse2);
+    #[cfg(all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        not(feature = "no-avx2"),
+        not(feature = "no-sse2")
+    ))]
+    sets.push(InstructionSet::Avx2);
+
+    sets
+}
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    not(no_cpu_detection)
+))]
 const fn four_cc(a: u8, b: u8, c: u8, d: u8) -> u32 {
     ((d as u32) << 24) | ((c as u32) << 16) | ((b as u32) << 8) | (a as u32)
 }
 
-#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    not(no_cpu_detection)
+))]
 fn cpuid(functionnumber: u32, output: &mut [u32; 4]) {
     unsafe {
         let result = __cpuid(functionnumber);
@@ -52,22 +102,62 @@ fn cpuid(functionnumber: u32, output: &mut [u32; 4]) {
     }
 }
 
-#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    not(no_cpu_detection)
+))]
 fn compare_cpu_manufacturer(features: &[u32; 4], name: &[u8; 12]) -> u32 {
     features[1].wrapping_sub(four_cc(name[0], name[1], name[2], name[3]))
         | features[3].wrapping_sub(four_cc(name[4], name[5], name[6], name[7]))
         | features[2].wrapping_sub(four_cc(name[8], name[9], name[10], name[11]))
 }
 
-#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-pub fn get_cpu_info() -> (CpuManufacturer, InstructionSet) {
+/// Returns the best instruction set among the supported ones that the library was built with.
+/// Avx2 kernels use the sse2 ones, so they are not built without them
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    not(no_cpu_detection)
+))]
+#[allow(unused_variables)]
+fn select_instruction_set(sse2: bool, avx2: bool) -> InstructionSet {
+    #[cfg(not(any(feature = "no-avx2", feature = "no-sse2")))]
+    {
+        if avx2 {
+            return InstructionSet::Avx2;
+        }
+    }
+
+    #[cfg(not(feature = "no-sse2"))]
+    {
+        if sse2 {
+            return InstructionSet::Sse2;
+        }
+    }
+
+    InstructionSet::X86
+}
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    not(no_cpu_detection)
+))]
+pub fn get_cpu_info() -> (CpuManufacturer, InstructionSet, CpuFeatures) {
     let mut manufacturer = CpuManufacturer::Unknown;
-    let mut set = InstructionSet::X86;
+    let mut cpu_features = NO_CPU_FEATURES;
+
+    // Some 32-bit cpus do not have the cpuid instruction, nor sse2
+    #[cfg(target_arch = "x86")]
+    {
+        if !has_cpuid() {
+            return (manufacturer, InstructionSet::X86, cpu_features);
+        }
+    }
 
     let features = &mut [0u32; 4];
     cpuid(0, features);
 
-    if features[0] != 0 {
+    let max_function = features[0];
+    if max_function != 0 {
         if compare_cpu_manufacturer(features, b"GenuineIntel") == 0 {
             manufacturer = CpuManufacturer::Intel;
         } else if (compare_cpu_manufacturer(features, b"AuthenticAMD") == 0)
@@ -79,30 +169,38 @@ pub fn get_cpu_info() -> (CpuManufacturer, InstructionSet) {
         // This ensures we always use hardware intrinsics and we do not use software emulation
         cpuid(1, features);
         if (features[3] & (1 << 26)) != 0 {
-            // On AMD cpus, all encode/decode using avx2 have worse performance than sse2 ones
-            // For now, disable the avx2 path even if supported.
-            // See https://en.wikipedia.org/wiki/CPUID for additional details
-            match manufacturer {
-                CpuManufacturer::Amd => {
-                    features[1] = 0;
-                }
-                _ => {
-                    cpuid(7, features);
-                }
+            cpu_features.sse2 = true;
+            if max_function >= 7 {
+                cpuid(7, features);
+                cpu_features.avx2 = (features[1] & (1 << 5)) != 0;
             }
-
-            set = if (features[1] & (1 << 5)) != 0 {
-                InstructionSet::Avx2
-            } else {
-                InstructionSet::Sse2
-            };
         }
     }
 
-    (manufacturer, set)
+    // On AMD cpus, all encode/decode using avx2 have worse performance than sse2 ones
+    // For now, disable the avx2 path even if supported.
+    // See https://en.wikipedia.org/wiki/CPUID for additional details
+    let avx2 = match manufacturer {
+        CpuManufacturer::Amd => false,
+        _ => cpu_features.avx2,
+    };
+
+    (
+        manufacturer,
+        select_instruction_set(cpu_features.sse2, avx2),
+        cpu_features,
+    )
 }
 
-#[cfg(all(not(target_arch = "x86"), not(target_arch = "x86_64")))]
-pub fn get_cpu_info() -> (CpuManufacturer, InstructionSet) {
-    (CpuManufacturer::Unknown, InstructionSet::X86)
+/// Without cpu detection, only the portable kernels are used
+#[cfg(any(
+    all(not(target_arch = "x86"), not(target_arch = "x86_64")),
+    no_cpu_detection
+))]
+pub fn get_cpu_info() -> (CpuManufacturer, InstructionSet, CpuFeatures) {
+    (
+        CpuManufacturer::Unknown,
+        InstructionSet::X86,
+        NO_CPU_FEATURES,
+    )
 }
@@ -0,0 +1,557 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Three-dimensional look-up tables.
+//!
+//! Loads 3D look-up tables in the `.cube` format and applies them to rgb images, either
+//! standalone or right after a conversion to rgb, while each band of the destination image
+//! is still in cache. Values between the table points are computed with tetrahedral
+//! interpolation, using SSE2 or AVX2 when the processor supports them.
+//!
+//! Only 3D tables are supported: files declaring `LUT_1D_SIZE` are rejected, use the
+//! [`lut`] module for one-dimensional tables instead.
+//!
+//! This module requires the `cube` feature.
+//!
+//! [`lut`]: ../lut/index.html
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::cube::{self, apply_lut_in_place};
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn invert() -> Result<(), Box<dyn error::Error>> {
+//!     let file = "TITLE \"invert\"\n\
+//!                 LUT_3D_SIZE 2\n\
+//!                 1 1 1\n0 1 1\n1 0 1\n0 0 1\n\
+//!                 1 1 0\n0 1 0\n1 0 0\n0 0 0\n";
+//!     let lut = cube::read(file.as_bytes())?;
+//!
+//!     let format = ImageFormat {
+//!         pixel_format: PixelFormat::Rgb,
+//!         color_space: ColorSpace::Lrgb,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let mut image = [0u8, 64, 255];
+//!     apply_lut_in_place(1, 1, &format, None, &mut [&mut image], &lut)?;
+//!     assert_eq!(image, [255, 191, 0]);
+//!
+//!     Ok(())
+//! }
+//! ```
+use crate::convert_image;
+use crate::pixel_format::{self, PixelFormat, STRIDE_AUTO};
+use crate::trace_span;
+use crate::{CubeDispatcher, ErrorKind, ImageFormat};
+use std::io::{self, BufRead, BufReader, Read};
+
+/// Lines converted before the look-up table is applied
+const CUBE_BAND_HEIGHT: usize = 64;
+
+/// Pixels interpolated by each call of the look-up table kernel
+const CUBE_CHUNK_SIZE: usize = 64;
+
+/// Largest supported number of points along each axis
+pub const MAX_CUBE_SIZE: usize = 256;
+
+/// A three-dimensional look-up table
+#[derive(Clone, Debug)]
+pub struct Lut3d {
+    size: usize,
+    domain_min: [f32; 3],
+    domain_max: [f32; 3],
+    table: Vec<[f32; 3]>,
+}
+
+impl Lut3d {
+    /// Creates a look-up table over the unit cube
+    ///
+    /// # Arguments
+    /// * `size` - Number of points along each axis
+    /// * `table` - Output values, `size * size * size` of them, with red changing fastest
+    ///   and blue slowest
+    ///
+    /// # Errors
+    ///
+    /// * [`InvalidValue`] if `size` is less than 2 or greater than [`MAX_CUBE_SIZE`]
+    ///
+    /// * [`InvalidValue`] if `table` does not have exactly `size * size * size` entries
+    ///
+    /// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+    /// [`MAX_CUBE_SIZE`]: ./constant.MAX_CUBE_SIZE.html
+    pub fn new(size: usize, table: Vec<[f32; 3]>) -> Result<Self, ErrorKind> {
+        if !(2..=MAX_CUBE_SIZE).contains(&size) || table.len() != size * size * size {
+            return Err(ErrorKind::InvalidValue);
+        }
+
+        Ok(Lut3d {
+            size,
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+            table,
+        })
+    }
+
+    /// Restricts the input domain of the table
+    ///
+    /// # Errors
+    ///
+    /// * [`InvalidValue`] if the domain is empty along one of the axes
+    ///
+    /// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+    pub fn with_domain(mut self, min: [f32; 3], max: [f32; 3]) -> Result<Self, ErrorKind> {
+        if min
+            .iter()
+            .zip(max.iter())
+            .any(|(&lo, &hi)| lo >= hi || lo.is_nan() || hi.is_nan())
+        {
+            return Err(ErrorKind::InvalidValue);
+        }
+
+        self.domain_min = min;
+        self.domain_max = max;
+        Ok(self)
+    }
+
+    /// Number of points along each axis
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Splits a normalized value into the index of the lower table point and the
+    /// distance from it
+    fn locate(&self, channel: usize, value: f32) -> (usize, f32) {
+        let min = self.domain_min[channel];
+        let max = self.domain_max[channel];
+        let scale = (self.size - 1) as f32;
+        let x = ((value - min) / (max - min)).clamp(0.0, 1.0) * scale;
+
+        // Values exactly on the last point interpolate the last cell
+        let index = (x as usize).min(self.size - 2);
+        (index, x - index as f32)
+    }
+
+    /// Index of the table point having the given red, green and blue indices
+    fn point_index(&self, r: usize, g: usize, b: usize) -> usize {
+        r + self.size * (g + self.size * b)
+    }
+
+    /// Looks up an rgb triplet, interpolating between the table points
+    pub fn sample(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let (r, fr) = self.locate(0, rgb[0]);
+        let (g, fg) = self.locate(1, rgb[1]);
+        let (b, fb) = self.locate(2, rgb[2]);
+        convert_image::x86::interpolate_point(
+            &self.table,
+            self.size,
+            self.point_index(r, g, b),
+            [fr, fg, fb],
+        )
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn parse_floats(tokens: &[&str]) -> io::Result<[f32; 3]> {
+    if tokens.len() != 3 {
+        return Err(invalid_data("expected three values"));
+    }
+
+    let mut values = [0.0; 3];
+    for (value, token) in values.iter_mut().zip(tokens.iter()) {
+        *value = token.parse().map_err(|_| invalid_data("invalid number"))?;
+    }
+
+    Ok(values)
+}
+
+/// Reads a look-up table in the `.cube` format
+///
+/// # Errors
+///
+/// * [`InvalidData`] if the table is malformed, if it is a 1D table, or if the number of
+///   data lines does not match `LUT_3D_SIZE`
+///
+/// * Any error reported by the reader
+///
+/// [`InvalidData`]: https://doc.rust-lang.org/std/io/enum.ErrorKind.html#variant.InvalidData
+pub fn read<R: Read>(reader: R) -> io::Result<Lut3d> {
+    let mut size = 0;
+    let mut domain_min = [0.0; 3];
+    let mut domain_max = [1.0; 3];
+    let mut table = Vec::new();
+
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens[0] {
+            "TITLE" => {}
+            "LUT_1D_SIZE" | "LUT_1D_INPUT_RANGE" => {
+                return Err(invalid_data("1D tables are not supported"));
+            }
+            "LUT_3D_SIZE" => {
+                if size != 0 || tokens.len() != 2 {
+                    return Err(invalid_data("invalid table size"));
+                }
+
+                size = tokens[1]
+                    .parse()
+                    .map_err(|_| invalid_data("invalid table size"))?;
+                if !(2..=MAX_CUBE_SIZE).contains(&size) {
+                    return Err(invalid_data("invalid table size"));
+                }
+
+                table.reserve(size * size * size);
+            }
+            "DOMAIN_MIN" => domain_min = parse_floats(&tokens[1..])?,
+            "DOMAIN_MAX" => domain_max = parse_floats(&tokens[1..])?,
+            "LUT_3D_INPUT_RANGE" => {
+                if tokens.len() != 3 {
+                    return Err(invalid_data("invalid input range"));
+                }
+
+                let min: f32 = tokens[1]
+                    .parse()
+                    .map_err(|_| invalid_data("invalid input range"))?;
+                let max: f32 = tokens[2]
+                    .parse()
+                    .map_err(|_| invalid_data("invalid input range"))?;
+                domain_min = [min; 3];
+                domain_max = [max; 3];
+            }
+            _ => {
+                if size == 0 {
+                    return Err(invalid_data("missing table size"));
+                }
+
+                if table.len() == size * size * size {
+                    return Err(invalid_data("too many data lines"));
+                }
+
+                table.push(parse_floats(&tokens)?);
+            }
+        }
+    }
+
+    if size == 0 {
+        return Err(invalid_data("missing table size"));
+    }
+
+    Lut3d::new(size, table)
+        .and_then(|lut| lut.with_domain(domain_min, domain_max))
+        .map_err(|_| invalid_data("invalid table"))
+}
+
+fn read_f32(buffer: &[u8]) -> f32 {
+    f32::from_bits(u32::from_le_bytes([
+        buffer[0], buffer[1], buffer[2], buffer[3],
+    ]))
+}
+
+fn write_f32(buffer: &mut [u8], value: f32) {
+    buffer[..4].copy_from_slice(&value.to_bits().to_le_bytes());
+}
+
+/// Bytes per pixel and byte offsets of red, green and blue of the supported formats
+fn get_rgb_layout(pixel_format: PixelFormat) -> Option<(usize, [usize; 3])> {
+    match pixel_format {
+        PixelFormat::Argb => Some((4, [1, 2, 3])),
+        PixelFormat::Bgra => Some((4, [2, 1, 0])),
+        PixelFormat::Bgr => Some((3, [2, 1, 0])),
+        PixelFormat::Rgba => Some((4, [0, 1, 2])),
+        PixelFormat::Rgb => Some((3, [0, 1, 2])),
+        PixelFormat::RgbF32 => Some((12, [0, 4, 8])),
+        PixelFormat::RgbaF32 => Some((16, [0, 4, 8])),
+        _ => None,
+    }
+}
+
+/// Applies a table to lines of pixels
+struct Applier<'a> {
+    lut: &'a Lut3d,
+    bpp: usize,
+    offsets: [usize; 3],
+    // For 8-bit formats, table cell and distance from it of every sample value
+    cells: Vec<[(usize, f32); 3]>,
+    interpolator: CubeDispatcher,
+}
+
+impl<'a> Applier<'a> {
+    fn new(lut: &'a Lut3d, pixel_format: PixelFormat) -> Option<Self> {
+        let (bpp, offsets) = get_rgb_layout(pixel_format)?;
+        let cells = if bpp < 12 {
+            (0..256)
+                .map(|value| {
+                    let x = value as f32 / 255.0;
+                    [lut.locate(0, x), lut.locate(1, x), lut.locate(2, x)]
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Some(Applier {
+            lut,
+            bpp,
+            offsets,
+            cells,
+            interpolator: crate::get_cube_interpolator(),
+        })
+    }
+
+    fn apply_row(&self, row: &mut [u8]) {
+        let [r, g, b] = self.offsets;
+        let quantize = |value: f32| {
+            if value > 0.0 {
+                (value.min(1.0) * 255.0 + 0.5) as u8
+            } else {
+                0
+            }
+        };
+
+        // Pixels are interpolated in chunks, so that the kernel works on planar values
+        let mut index = [0u32; CUBE_CHUNK_SIZE];
+        let mut fractions = [[0f32; CUBE_CHUNK_SIZE]; 3];
+        let mut values = [[0f32; CUBE_CHUNK_SIZE]; 3];
+        for chunk in row.chunks_mut(self.bpp * CUBE_CHUNK_SIZE) {
+            let count = chunk.len() / self.bpp;
+            for (i, pixel) in chunk.chunks_exact(self.bpp).enumerate() {
+                let cell = if self.cells.is_empty() {
+                    [
+                        self.lut.locate(0, read_f32(&pixel[r..])),
+                        self.lut.locate(1, read_f32(&pixel[g..])),
+                        self.lut.locate(2, read_f32(&pixel[b..])),
+                    ]
+                } else {
+                    [
+                        self.cells[pixel[r] as usize][0],
+                        self.cells[pixel[g] as usize][1],
+                        self.cells[pixel[b] as usize][2],
+                    ]
+                };
+
+                index[i] = self.lut.point_index(cell[0].0, cell[1].0, cell[2].0) as u32;
+                for (fraction, &(_, distance)) in fractions.iter_mut().zip(cell.iter()) {
+                    fraction[i] = distance;
+                }
+            }
+
+            let [fr, fg, fb] = &fractions;
+            let [vr, vg, vb] = &mut values;
+            (self.interpolator)(
+                &self.lut.table,
+                self.lut.size,
+                &index[..count],
+                [&fr[..count], &fg[..count], &fb[..count]],
+                [&mut vr[..count], &mut vg[..count], &mut vb[..count]],
+            );
+
+            for (i, pixel) in chunk.chunks_exact_mut(self.bpp).enumerate() {
+                if self.cells.is_empty() {
+                    write_f32(&mut pixel[r..], values[0][i]);
+                    write_f32(&mut pixel[g..], values[1][i]);
+                    write_f32(&mut pixel[b..], values[2][i]);
+                } else {
+                    pixel[r] = quantize(values[0][i]);
+                    pixel[g] = quantize(values[1][i]);
+                    pixel[b] = quantize(values[2][i]);
+                }
+            }
+        }
+    }
+}
+
+/// Applies a look-up table to an rgb image in place
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `format` - Image format
+/// * `strides` - An array of distances in bytes between starts of consecutive lines
+///   in each image planes
+/// * `buffers` - An array of image buffers in each image plane
+/// * `lut` - Look-up table to apply
+///
+/// # Note
+/// Supported pixel formats are the 8-bit rgb formats, `RgbF32` and `RgbaF32`.
+/// Alpha is left untouched.
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the color space is not `ColorSpace::Lrgb`
+///
+/// * [`InvalidValue`] if the image format has a number of planes which is not compatible
+///   with its pixel format
+///
+/// * [`InvalidValue`] if the stride is smaller than the line size
+///
+/// * [`InvalidOperation`] if the pixel format is not supported
+///
+/// * [`NotEnoughData`] if the strides array is not `None` and it is empty
+///
+/// * [`NotEnoughData`] if the buffer does not provide enough data
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+pub fn apply_lut_in_place(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+    buffers: &mut [&mut [u8]],
+    lut: &Lut3d,
+) -> Result<(), ErrorKind> {
+    let applier = Applier::new(lut, format.pixel_format).ok_or(ErrorKind::InvalidOperation)?;
+    let pixel_format = format.pixel_format as u32;
+    if !crate::dispatcher::get_color_space_mode(format.color_space as u32)
+        || !pixel_format::is_compatible(
+            pixel_format,
+            width,
+            height,
+            format.num_planes.wrapping_sub(1),
+        )
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let strides = strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    if strides.is_empty() || buffers.is_empty() {
+        return Err(ErrorKind::NotEnoughData);
+    }
+
+    let row_size = applier.bpp * (width as usize);
+    let stride = match strides[0] {
+        STRIDE_AUTO => row_size,
+        stride => stride,
+    };
+
+    if stride < row_size {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let rows = height as usize;
+    if rows > 0 {
+        let size = stride
+            .checked_mul(rows - 1)
+            .and_then(|size| size.checked_add(row_size))
+            .ok_or(ErrorKind::NotEnoughData)?;
+        if size > buffers[0].len() {
+            return Err(ErrorKind::NotEnoughData);
+        }
+    }
+
+    let buffer = &mut buffers[0];
+    for row in 0..rows {
+        applier.apply_row(&mut buffer[row * stride..][..row_size]);
+    }
+
+    Ok(())
+}
+
+/// Converts an image and applies a look-up table to its rgb output
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source image plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination image plane
+/// * `lut` - Look-up table to apply to the destination pixels
+///
+/// # Note
+/// The destination pixel format has to be one of the 8-bit rgb formats, `RgbF32` or
+/// `RgbaF32`. Alpha is left untouched.
+///
+/// # Errors
+///
+/// * [`InvalidOperation`] if the destination pixel format is not supported
+///
+/// * All the errors reported by [`convert_image`], except that conversions are never routed
+///
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`convert_image`]: ../fn.convert_image.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_image_with_cube(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    lut: &Lut3d,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_image_with_cube",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    let (converter, last_src_plane, last_dst_plane) =
+        crate::get_converter(width, height, src_format, dst_format)?;
+
+    let applier = Applier::new(lut, dst_format.pixel_format).ok_or(ErrorKind::InvalidOperation)?;
+    if crate::is_empty(width, height) {
+        return Ok(());
+    }
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let src_layouts =
+        crate::get_plane_layouts(src_format, width, height, last_src_plane, src_strides)?;
+    let dst_layouts =
+        crate::get_plane_layouts(dst_format, width, height, last_dst_plane, dst_strides)?;
+
+    let row_size = applier.bpp * (width as usize);
+
+    crate::convert_in_bands(
+        &src_layouts,
+        src_buffers,
+        dst_format,
+        &dst_layouts,
+        dst_buffers,
+        crate::get_bands(height as usize, CUBE_BAND_HEIGHT),
+        |mut band| {
+            band.convert(converter, width)?;
+            for line in 0..band.height() {
+                applier
+                    .apply_row(&mut band.dst_buffers[0][line * band.dst_strides[0]..][..row_size]);
+            }
+
+            Ok(())
+        },
+    )
+}
@@ -0,0 +1,348 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Bit depth conversion.
+//!
+//! Converts between 8-bit [`Nv12`] and 10-bit [`P010`] images with the same color space,
+//! and from 16-bit [`Bgr48`] and [`Rgb48`] images to 8-bit [`Bgra`], like [`convert_image`],
+//! with control over how samples are reduced to 8 bits.
+//! Rounding to nearest can show banding on smooth gradients; ordered dithering trades it
+//! for a fine, regular noise.
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::depth::{convert_depth, Dithering};
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn reduce() -> Result<(), Box<dyn error::Error>> {
+//!     dcp::initialize();
+//!
+//!     const WIDTH: u32 = 640;
+//!     const HEIGHT: u32 = 480;
+//!
+//!     let src_format = ImageFormat {
+//!         pixel_format: PixelFormat::P010,
+//!         color_space: ColorSpace::Bt709,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let dst_format = ImageFormat {
+//!         pixel_format: PixelFormat::Nv12,
+//!         color_space: ColorSpace::Bt709,
+//!         num_planes: 1,
+//!     };
+//!
+//!     // 10-bit luma 65 lies between the 8-bit values 16 and 17
+//!     let mut src_buffer = vec![0u8; 3 * (WIDTH as usize) * (HEIGHT as usize)];
+//!     for sample in src_buffer.chunks_exact_mut(2) {
+//!         sample.copy_from_slice(&(65u16 << 6).to_le_bytes());
+//!     }
+//!
+//!     let mut dst_buffer = vec![0u8; 3 * (WIDTH as usize) * (HEIGHT as usize) / 2];
+//!     convert_depth(
+//!         WIDTH,
+//!         HEIGHT,
+//!         &src_format,
+//!         None,
+//!         &[&src_buffer],
+//!         &dst_format,
+//!         None,
+//!         &mut [&mut dst_buffer],
+//!         Dithering::Ordered,
+//!     )?;
+//!
+//!     assert_eq!(&dst_buffer[..2], &[16, 16]);
+//!     assert_eq!(&dst_buffer[WIDTH as usize..][..2], &[17, 16]);
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Previews that only need the luma of a [`P010`] stream, such as thumbnails, can reduce
+//! it to an 8-bit plane with [`reduce_luma`], without any work on the chroma samples.
+//!
+//! [`Nv12`]: ../enum.PixelFormat.html#variant.Nv12
+//! [`P010`]: ../enum.PixelFormat.html#variant.P010
+//! [`Bgr48`]: ../enum.PixelFormat.html#variant.Bgr48
+//! [`Rgb48`]: ../enum.PixelFormat.html#variant.Rgb48
+//! [`Bgra`]: ../enum.PixelFormat.html#variant.Bgra
+//! [`convert_image`]: ../fn.convert_image.html
+//! [`reduce_luma`]: ./fn.reduce_luma.html
+use crate::convert_image::x86::{self, YuvLayout};
+use crate::dispatcher;
+use crate::pixel_format::{self, PixelFormat};
+use crate::trace_span;
+use crate::{ErrorKind, ImageFormat};
+
+/// Bit depth conversions supported by [`convert_depth`]
+enum Reduction {
+    Yuv(YuvLayout, YuvLayout),
+    // Component order of the source, true for Bgr48
+    Rgb48(bool),
+}
+
+/// How 10-bit and 16-bit samples are reduced to 8 bits
+#[derive(Copy, Clone, Debug)]
+pub enum Dithering {
+    /// Samples are rounded to nearest, as done by [`convert_image`]
+    ///
+    /// [`convert_image`]: ../fn.convert_image.html
+    None,
+    /// A 2x2 ordered dither is added before truncating the samples. The average value
+    /// of flat areas is preserved
+    Ordered,
+}
+
+/// Converts an image between 8-bit `Nv12` and 10-bit `P010`, keeping its color space,
+/// or from 16-bit `Bgr48` and `Rgb48` to 8-bit `Bgra`
+///
+/// Increasing the bit depth gives the same result as [`convert_image`], `dithering`
+/// only applies to `P010` to `Nv12` and to 16-bit rgb to `Bgra` conversions.
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+/// * `dithering` - How samples are reduced to 8 bits
+///
+/// # Errors
+///
+/// * [`InvalidOperation`] if the conversion is not between `Nv12` and `P010` or from
+///   `Bgr48`/`Rgb48` to `Bgra`, or if the color spaces differ
+///
+/// * All the errors reported by [`convert_image`], except that conversions are never routed
+///
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`convert_image`]: ../fn.convert_image.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_depth(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    dithering: Dithering,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_depth",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    let (_, last_src_plane, last_dst_plane) =
+        crate::get_converter(width, height, src_format, dst_format)?;
+
+    let reduction = match (src_format.pixel_format, dst_format.pixel_format) {
+        (PixelFormat::Nv12, PixelFormat::P010) => Reduction::Yuv(YuvLayout::Nv12, YuvLayout::P010),
+        (PixelFormat::P010, PixelFormat::Nv12) => Reduction::Yuv(YuvLayout::P010, YuvLayout::Nv12),
+        (PixelFormat::Bgr48, PixelFormat::Bgra) => Reduction::Rgb48(true),
+        (PixelFormat::Rgb48, PixelFormat::Bgra) => Reduction::Rgb48(false),
+        _ => return Err(ErrorKind::InvalidOperation),
+    };
+
+    if crate::is_empty(width, height) {
+        return Ok(());
+    }
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dither = match dithering {
+        Dithering::None => false,
+        Dithering::Ordered => true,
+    };
+
+    let converted = match reduction {
+        Reduction::Yuv(src_layout, dst_layout) => x86::yuv_depth(
+            width,
+            height,
+            last_src_plane as usize,
+            src_strides,
+            src_buffers,
+            last_dst_plane as usize,
+            dst_strides,
+            dst_buffers,
+            src_layout,
+            dst_layout,
+            dither,
+        ),
+        Reduction::Rgb48(reverse) => x86::lrgb48_to_bgra(
+            width,
+            height,
+            src_strides,
+            src_buffers,
+            dst_strides,
+            dst_buffers,
+            reverse,
+            dither,
+        ),
+    };
+
+    if converted {
+        Ok(())
+    } else {
+        Err(ErrorKind::NotEnoughData)
+    }
+}
+
+/// Reduces the luma of a 10-bit `P010` image to an 8-bit plane
+///
+/// The luma samples are the same written by [`convert_depth`] to an `Nv12` image, while
+/// the chroma samples are neither read nor converted.
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_stride` - Distance in bytes between starts of consecutive lines of the luma
+///   plane, or [`STRIDE_AUTO`] if lines are contiguous
+/// * `dst_buffer` - The 8-bit luma plane
+/// * `dithering` - How samples are reduced to 8 bits
+///
+/// # Errors
+///
+/// * [`NotInitialized`] if the library was not initialized before
+///
+/// * [`InvalidValue`] if the source pixel format is not compatible with the color space,
+///   the image size or the number of planes
+///
+/// * [`InvalidOperation`] if the source pixel format is not `P010`
+///
+/// * [`NotEnoughData`] if the source strides array is shorter than the number of planes,
+///   the source buffers do not provide enough data, or the destination stride or buffer
+///   is too small for the luma plane
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::depth::{reduce_luma, Dithering};
+/// use dcp::{ColorSpace, ImageFormat, PixelFormat, STRIDE_AUTO};
+///
+/// dcp::initialize();
+///
+/// let src_format = ImageFormat {
+///     pixel_format: PixelFormat::P010,
+///     color_space: ColorSpace::Bt709,
+///     num_planes: 2,
+/// };
+///
+/// // 10-bit luma 940 is the 8-bit white 235
+/// let mut y = [0u8; 8];
+/// for sample in y.chunks_exact_mut(2) {
+///     sample.copy_from_slice(&(940u16 << 6).to_le_bytes());
+/// }
+///
+/// let uv = [0u8; 4];
+/// let mut luma = [0u8; 4];
+/// reduce_luma(
+///     2,
+///     2,
+///     &src_format,
+///     None,
+///     &[&y, &uv],
+///     STRIDE_AUTO,
+///     &mut luma,
+///     Dithering::Ordered,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(luma, [235; 4]);
+/// ```
+///
+/// [`convert_depth`]: ./fn.convert_depth.html
+/// [`STRIDE_AUTO`]: ../constant.STRIDE_AUTO.html
+/// [`NotInitialized`]: ../enum.ErrorKind.html#variant.NotInitialized
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+#[allow(clippy::too_many_arguments)]
+pub fn reduce_luma(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_stride: usize,
+    dst_buffer: &mut [u8],
+    dithering: Dithering,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "reduce_luma",
+        width,
+        height,
+        src = ?src_format.pixel_format
+    );
+
+    crate::get_converters()?;
+
+    let pixel_format = src_format.pixel_format as u32;
+    if dispatcher::get_pixel_format_mode(pixel_format)
+        ^ dispatcher::get_color_space_mode(src_format.color_space as u32)
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    match src_format.pixel_format {
+        PixelFormat::P010 => {}
+        _ => return Err(ErrorKind::InvalidOperation),
+    }
+
+    let last_src_plane = src_format.num_planes.wrapping_sub(1);
+    if !pixel_format::is_compatible(pixel_format, width, height, last_src_plane) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    if crate::is_empty(width, height) {
+        return Ok(());
+    }
+
+    let dither = match dithering {
+        Dithering::None => false,
+        Dithering::Ordered => true,
+    };
+
+    if x86::p010_to_luma(
+        width,
+        height,
+        last_src_plane as usize,
+        src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+        src_buffers,
+        dst_stride,
+        dst_buffer,
+        dither,
+    ) {
+        Ok(())
+    } else {
+        Err(ErrorKind::NotEnoughData)
+    }
+}
@@ -0,0 +1,287 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Image comparison.
+//!
+//! Compares two images with the same format sample by sample, either to summarize
+//! their difference for each plane or to produce a map of the absolute differences.
+//! Useful to validate a conversion against a reference without an external tool.
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::diff::diff_images;
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn compare() -> Result<(), Box<dyn error::Error>> {
+//!     dcp::initialize();
+//!
+//!     const WIDTH: u32 = 640;
+//!     const HEIGHT: u32 = 480;
+//!
+//!     let format = ImageFormat {
+//!         pixel_format: PixelFormat::Nv12,
+//!         color_space: ColorSpace::Bt601,
+//!         num_planes: 2,
+//!     };
+//!
+//!     let y = vec![16u8; (WIDTH as usize) * (HEIGHT as usize)];
+//!     let uv = vec![128u8; (WIDTH as usize) * (HEIGHT as usize) / 2];
+//!     let mut other_uv = uv.clone();
+//!     other_uv[0] = 130;
+//!
+//!     let stats = diff_images(WIDTH, HEIGHT, &format, None, &[&y, &uv], None, &[&y, &other_uv])?;
+//!     assert_eq!(stats.planes[0].max, 0);
+//!     assert_eq!(stats.planes[1].max, 2);
+//!
+//!     Ok(())
+//! }
+//! ```
+use crate::dispatcher;
+use crate::pixel_format;
+use crate::{ErrorKind, ImageFormat, PlaneLayout};
+
+/// Difference between the samples of a plane in two images
+#[derive(Copy, Clone, Debug)]
+pub struct PlaneDiff {
+    /// Largest absolute difference between two samples
+    pub max: u8,
+    /// Average absolute difference between two samples
+    pub mean: f64,
+}
+
+/// Difference between two images, for each logical plane of their pixel format
+///
+/// Logical planes do not depend on the number of buffers the images are stored in:
+/// a nv12 image always has a luma plane and an interleaved chroma plane.
+#[derive(Clone, Debug)]
+pub struct DiffStats {
+    /// Difference of each plane
+    pub planes: Vec<PlaneDiff>,
+}
+
+fn get_layouts(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+) -> Result<Vec<PlaneLayout>, ErrorKind> {
+    let pixel_format = format.pixel_format as u32;
+    if dispatcher::get_pixel_format_mode(pixel_format)
+        ^ dispatcher::get_color_space_mode(format.color_space as u32)
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    if pixel_format::get_bit_depth(format.pixel_format) > 8 {
+        return Err(ErrorKind::InvalidOperation);
+    }
+
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if !pixel_format::is_compatible(pixel_format, width, height, last_plane) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    crate::get_plane_layouts(
+        format,
+        width,
+        height,
+        last_plane,
+        strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+    )
+}
+
+/// Returns the samples in a line of a plane, as (buffer, start, end)
+fn get_line(layout: &PlaneLayout, line: usize, line_size: usize) -> (usize, usize, usize) {
+    let start = layout.offset + line * layout.stride;
+    (layout.buffer, start, start + line_size)
+}
+
+/// Logical planes of a pixel format, as (line size in bytes, line count)
+fn get_plane_sizes(format: &ImageFormat, width: u32, height: u32) -> Vec<(usize, usize)> {
+    pixel_format::get_logical_planes(format.pixel_format)
+        .iter()
+        .map(|&(depth, horizontal_shift, vertical_shift)| {
+            (
+                depth * ((width >> horizontal_shift) as usize),
+                (height >> vertical_shift) as usize,
+            )
+        })
+        .collect()
+}
+
+/// Computes the largest and the average absolute difference for each plane of two images
+///
+/// # Arguments
+/// * `width` - Width of the images in pixels
+/// * `height` - Height of the images in pixels
+/// * `format` - Image format of both images
+/// * `a_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each plane of the first image
+/// * `a_buffers` - An array of image buffers in each plane of the first image
+/// * `b_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each plane of the second image
+/// * `b_buffers` - An array of image buffers in each plane of the second image
+///
+/// # Errors
+///
+/// * [`NotInitialized`] if the library was not initialized before
+///
+/// * [`InvalidValue`] if the pixel format is not compatible with the color space
+///
+/// * [`InvalidValue`] if `width` or `height` violate the [`size constraints`] that might by
+///   imposed by the image pixel format
+///
+/// * [`InvalidValue`] if the image format has a number of planes which is not compatible
+///   with its pixel format
+///
+/// * [`InvalidOperation`] if the pixel format has more than 8 bits per sample
+///
+/// * [`NotEnoughData`] if the strides arrays are not `None` and their length is less than
+///   the image format number of planes
+///
+/// * [`NotEnoughData`] if one or more buffers does not provide enough data
+///
+/// [`NotInitialized`]: ../enum.ErrorKind.html#variant.NotInitialized
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+/// [`size constraints`]: ../struct.ImageFormat.html#note
+pub fn diff_images(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    a_strides: Option<&[usize]>,
+    a_buffers: &[&[u8]],
+    b_strides: Option<&[usize]>,
+    b_buffers: &[&[u8]],
+) -> Result<DiffStats, ErrorKind> {
+    let differ = crate::get_differ()?;
+    let a_layouts = get_layouts(width, height, format, a_strides)?;
+    let b_layouts = get_layouts(width, height, format, b_strides)?;
+
+    let mut planes = Vec::with_capacity(a_layouts.len());
+    for ((a_layout, b_layout), (line_size, line_count)) in a_layouts
+        .iter()
+        .zip(b_layouts.iter())
+        .zip(get_plane_sizes(format, width, height))
+    {
+        let mut sum = 0;
+        let mut max = 0;
+        for line in 0..line_count {
+            let (a_buffer, a_start, a_end) = get_line(a_layout, line, line_size);
+            let (b_buffer, b_start, b_end) = get_line(b_layout, line, line_size);
+            let a_line = a_buffers
+                .get(a_buffer)
+                .and_then(|buffer| buffer.get(a_start..a_end))
+                .ok_or(ErrorKind::NotEnoughData)?;
+            let b_line = b_buffers
+                .get(b_buffer)
+                .and_then(|buffer| buffer.get(b_start..b_end))
+                .ok_or(ErrorKind::NotEnoughData)?;
+
+            let (line_sum, line_max) = differ(a_line, b_line);
+            sum += line_sum;
+            max = line_max.max(max);
+        }
+
+        let sample_count = line_size * line_count;
+        planes.push(PlaneDiff {
+            max,
+            mean: if sample_count > 0 {
+                (sum as f64) / (sample_count as f64)
+            } else {
+                0.0
+            },
+        });
+    }
+
+    Ok(DiffStats { planes })
+}
+
+/// Writes the absolute difference of each sample of two images into a third one
+///
+/// All the images share the same format. Unlike [`diff_images`], the library does
+/// not need to be initialized.
+///
+/// # Arguments
+/// * `width` - Width of the images in pixels
+/// * `height` - Height of the images in pixels
+/// * `format` - Image format of the three images
+/// * `a_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each plane of the first image
+/// * `a_buffers` - An array of image buffers in each plane of the first image
+/// * `b_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each plane of the second image
+/// * `b_buffers` - An array of image buffers in each plane of the second image
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each plane of the difference image
+/// * `dst_buffers` - An array of image buffers in each plane of the difference image
+///
+/// # Errors
+///
+/// The same errors reported by [`diff_images`], except [`NotInitialized`]
+///
+/// [`diff_images`]: ./fn.diff_images.html
+/// [`NotInitialized`]: ../enum.ErrorKind.html#variant.NotInitialized
+#[allow(clippy::too_many_arguments)]
+pub fn diff_map(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    a_strides: Option<&[usize]>,
+    a_buffers: &[&[u8]],
+    b_strides: Option<&[usize]>,
+    b_buffers: &[&[u8]],
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    let a_layouts = get_layouts(width, height, format, a_strides)?;
+    let b_layouts = get_layouts(width, height, format, b_strides)?;
+    let dst_layouts = get_layouts(width, height, format, dst_strides)?;
+
+    for (((a_layout, b_layout), dst_layout), (line_size, line_count)) in a_layouts
+        .iter()
+        .zip(b_layouts.iter())
+        .zip(dst_layouts.iter())
+        .zip(get_plane_sizes(format, width, height))
+    {
+        for line in 0..line_count {
+            let (a_buffer, a_start, a_end) = get_line(a_layout, line, line_size);
+            let (b_buffer, b_start, b_end) = get_line(b_layout, line, line_size);
+            let (dst_buffer, dst_start, dst_end) = get_line(dst_layout, line, line_size);
+            let a_line = a_buffers
+                .get(a_buffer)
+                .and_then(|buffer| buffer.get(a_start..a_end))
+                .ok_or(ErrorKind::NotEnoughData)?;
+            let b_line = b_buffers
+                .get(b_buffer)
+                .and_then(|buffer| buffer.get(b_start..b_end))
+                .ok_or(ErrorKind::NotEnoughData)?;
+            let dst_line = dst_buffers
+                .get_mut(dst_buffer)
+                .and_then(|buffer| buffer.get_mut(dst_start..dst_end))
+                .ok_or(ErrorKind::NotEnoughData)?;
+
+            for (delta, (&x, &y)) in dst_line.iter_mut().zip(a_line.iter().zip(b_line.iter())) {
+                *delta = x.abs_diff(y);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -25,22 +25,44 @@ const fn upper_power_of_two(x: u32) -> u32 {
     1 << (32 - (x - 1).leading_zeros())
 }
 
-const LO_RGB_PIXEL_FORMAT: u32 = PixelFormat::Argb as u32;
-const HI_RGB_PIXEL_FORMAT: u32 = PixelFormat::Rgb as u32;
-const LO_YUV_PIXEL_FORMAT: u32 = PixelFormat::I444 as u32;
-const HI_YUV_PIXEL_FORMAT: u32 = PixelFormat::Nv12 as u32;
-static_assert!(HI_RGB_PIXEL_FORMAT == LO_YUV_PIXEL_FORMAT - 1);
+// Mode of each pixel format (true for rgb) and its position among the pixel formats
+// of the same mode. Pixel formats are only ever appended to PixelFormat, so rgb and yuv
+// pixel formats do not form contiguous ranges of values
+const PIXEL_FORMAT_SLOTS: [(bool, u32); 16] = [
+    (true, 0),  // Argb
+    (true, 1),  // Bgra
+    (true, 2),  // Bgr
+    (true, 3),  // Rgba
+    (true, 4),  // Rgb
+    (false, 0), // I444
+    (false, 1), // I422
+    (false, 2), // I420
+    (false, 3), // Nv12
+    (false, 4), // P010
+    (false, 5), // YuvF32Planar
+    (true, 5),  // RgbaF16
+    (true, 6),  // RgbF32
+    (true, 7),  // RgbaF32
+    (true, 8),  // Bgr48
+    (true, 9),  // Rgb48
+];
+
+static_assert!(PIXEL_FORMAT_SLOTS.len() == PixelFormat::Rgb48 as usize + 1);
+
+const RGB_PIXEL_FORMAT_COUNT: u32 = 10;
+const YUV_PIXEL_FORMAT_COUNT: u32 = 6;
+static_assert!(
+    (RGB_PIXEL_FORMAT_COUNT + YUV_PIXEL_FORMAT_COUNT) as usize == PIXEL_FORMAT_SLOTS.len()
+);
 
 const LO_RGB_COLOR_SPACE: u32 = ColorSpace::Lrgb as u32;
 const HI_RGB_COLOR_SPACE: u32 = ColorSpace::Lrgb as u32;
 const LO_YUV_COLOR_SPACE: u32 = ColorSpace::Bt601 as u32;
-const HI_YUV_COLOR_SPACE: u32 = ColorSpace::Bt709 as u32;
+const HI_YUV_COLOR_SPACE: u32 = ColorSpace::Jfif as u32;
 static_assert!(HI_RGB_COLOR_SPACE == LO_YUV_COLOR_SPACE - 1);
 
-const RGB_PIXEL_FORMAT_COUNT: u32 = enum_count(LO_RGB_PIXEL_FORMAT, HI_RGB_PIXEL_FORMAT);
 const RGB_COLOR_SPACE_COUNT: u32 = enum_count(LO_RGB_COLOR_SPACE, HI_RGB_COLOR_SPACE);
 const RGB_COUNT: u32 = RGB_PIXEL_FORMAT_COUNT * RGB_COLOR_SPACE_COUNT;
-const YUV_PIXEL_FORMAT_COUNT: u32 = enum_count(LO_YUV_PIXEL_FORMAT, HI_YUV_PIXEL_FORMAT);
 const YUV_COLOR_SPACE_COUNT: u32 = enum_count(LO_YUV_COLOR_SPACE, HI_YUV_COLOR_SPACE);
 
 const ROWS: u32 = RGB_COUNT + (YUV_PIXEL_FORMAT_COUNT * YUV_COLOR_SPACE_COUNT);
@@ -54,7 +76,7 @@ fn select_mode(a: u32, b: u32, cond: bool) -> u32 {
 pub const TABLE_SIZE: usize = (ROWS * COLUMNS) as usize;
 
 pub fn get_pixel_format_mode(pixel_format: u32) -> bool {
-    pixel_format <= HI_RGB_PIXEL_FORMAT
+    PIXEL_FORMAT_SLOTS[pixel_format as usize].0
 }
 
 pub fn get_color_space_mode(color_space: u32) -> bool {
@@ -63,17 +85,17 @@ pub fn get_color_space_mode(color_space: u32) -> bool {
 }
 
 pub fn get_image_index(pixel_format: u32, color_space: u32, pixel_format_mode: bool) -> u32 {
+    let slot = PIXEL_FORMAT_SLOTS[pixel_format as usize].1;
     select_mode(
         // This never overflows
-        RGB_PIXEL_FORMAT_COUNT * (color_space - LO_RGB_COLOR_SPACE)
-            + (pixel_format - LO_RGB_PIXEL_FORMAT),
-        // When pixel_format > HI_RGB_PIXEL_FORMAT, this is allowed to be garbage
+        RGB_PIXEL_FORMAT_COUNT * (color_space - LO_RGB_COLOR_SPACE) + slot,
+        // When the pixel format is rgb, this is allowed to be garbage
         // because the value above will be selected
         RGB_COUNT
             .wrapping_add(
                 YUV_PIXEL_FORMAT_COUNT.wrapping_mul(color_space.wrapping_sub(LO_YUV_COLOR_SPACE)),
             )
-            .wrapping_add(pixel_format.wrapping_sub(LO_YUV_PIXEL_FORMAT)),
+            .wrapping_add(slot),
         pixel_format_mode,
     )
 }
@@ -84,8 +106,7 @@ pub fn get_index(src_index: u32, dst_index: u32) -> usize {
 
 #[inline(never)]
 pub fn is_pixel_format_valid(pixel_format: u32) -> bool {
-    pixel_format.wrapping_sub(LO_RGB_PIXEL_FORMAT)
-        <= HI_YUV_PIXEL_FORMAT.wrapping_sub(LO_RGB_PIXEL_FORMAT)
+    (pixel_format as usize) < PIXEL_FORMAT_SLOTS.len()
 }
 
 #[inline(never)]
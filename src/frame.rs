@@ -0,0 +1,231 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Import of frames whose planes live in externally allocated memory.
+//!
+//! Screen capture streams on Linux (e.g. PipeWire) deliver frames as DMA-buf or memfd
+//! file descriptors, each plane being described by the descriptor holding it, an offset
+//! and a stride. Several planes can share the same descriptor. Once the descriptors are
+//! mapped into memory, a [`FrameDescriptor`] validates the plane layout against the
+//! mappings and converts the frame without copying it.
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::frame::{FrameDescriptor, PlaneDescriptor};
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn convert() -> Result<(), Box<dyn error::Error>> {
+//!     dcp::initialize();
+//!
+//!     const WIDTH: u32 = 640;
+//!     const HEIGHT: u32 = 480;
+//!     const STRIDE: usize = 768;
+//!
+//!     // An nv12 frame exported through a single descriptor, the chroma plane
+//!     // starting at a page boundary
+//!     let frame = FrameDescriptor {
+//!         width: WIDTH,
+//!         height: HEIGHT,
+//!         format: ImageFormat {
+//!             pixel_format: PixelFormat::Nv12,
+//!             color_space: ColorSpace::Bt601,
+//!             num_planes: 2,
+//!         },
+//!         planes: vec![
+//!             PlaneDescriptor { memory: 0, offset: 0, stride: STRIDE },
+//!             PlaneDescriptor { memory: 0, offset: 368_640, stride: STRIDE },
+//!         ],
+//!     };
+//!
+//!     // The memory mapping of the descriptor
+//!     let mapping = vec![0u8; 552_960];
+//!     frame.validate(&[&mapping])?;
+//!
+//!     let dst_format = ImageFormat {
+//!         pixel_format: PixelFormat::Bgra,
+//!         color_space: ColorSpace::Lrgb,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let mut dst_buffer = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+//!     frame.convert(&[&mapping], &dst_format, None, &mut [&mut dst_buffer])?;
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`FrameDescriptor`]: ./struct.FrameDescriptor.html
+use crate::pixel_format;
+use crate::{ErrorKind, ImageFormat};
+
+/// Location of an image plane inside a memory mapping
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlaneDescriptor {
+    /// Index of the mapping holding the plane, e.g. the position of its file descriptor
+    /// in the list of mapped descriptors
+    pub memory: usize,
+    /// Distance in bytes between the start of the mapping and the start of the plane
+    pub offset: usize,
+    /// Distance in bytes between starts of consecutive lines, or [`STRIDE_AUTO`]
+    ///
+    /// [`STRIDE_AUTO`]: ../constant.STRIDE_AUTO.html
+    pub stride: usize,
+}
+
+/// An image whose planes are stored at arbitrary offsets of one or more memory mappings
+///
+/// There is one plane descriptor for each plane of the image format. As for
+/// [`convert_image`], a plane can hold more logical planes (e.g. an nv12 image with
+/// a single plane holds both luma and interleaved chroma), stored one after the other.
+///
+/// [`convert_image`]: ../fn.convert_image.html
+#[derive(Debug)]
+pub struct FrameDescriptor {
+    /// Width of the image in pixels
+    pub width: u32,
+    /// Height of the image in pixels
+    pub height: u32,
+    /// Image format
+    pub format: ImageFormat,
+    /// Location of each image plane
+    pub planes: Vec<PlaneDescriptor>,
+}
+
+impl FrameDescriptor {
+    /// Returns the strides of the image planes, as expected by [`convert_image`]
+    ///
+    /// [`convert_image`]: ../fn.convert_image.html
+    pub fn strides(&self) -> Vec<usize> {
+        self.planes.iter().map(|plane| plane.stride).collect()
+    }
+
+    /// Returns the buffers holding each image plane, as expected by [`convert_image`]
+    ///
+    /// Each buffer starts at the plane offset, and is as large as the plane.
+    /// Empty images have empty buffers, and the mappings are not accessed.
+    ///
+    /// # Arguments
+    /// * `mappings` - The memory mappings referenced by the plane descriptors
+    ///
+    /// # Errors
+    ///
+    /// * [`InvalidValue`] if `width` or `height` violate the [`size constraints`] that might by
+    ///   imposed by the image pixel format
+    ///
+    /// * [`InvalidValue`] if the image format has a number of planes which is not compatible
+    ///   with its pixel format, or if the number of plane descriptors differs from it
+    ///
+    /// * [`NotEnoughData`] if a plane descriptor references a mapping that does not exist, or
+    ///   if a plane does not fit in its mapping
+    ///
+    /// * [`NotEnoughData`] if a stride is less than the size of a line
+    ///
+    /// [`convert_image`]: ../fn.convert_image.html
+    /// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+    /// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+    /// [`size constraints`]: ../struct.ImageFormat.html#note
+    pub fn import<'a>(&self, mappings: &[&'a [u8]]) -> Result<Vec<&'a [u8]>, ErrorKind> {
+        let last_plane = self.format.num_planes.wrapping_sub(1);
+        if !pixel_format::is_compatible(
+            self.format.pixel_format as u32,
+            self.width,
+            self.height,
+            last_plane,
+        ) || self.planes.len() != self.format.num_planes as usize
+        {
+            return Err(ErrorKind::InvalidValue);
+        }
+
+        if crate::is_empty(self.width, self.height) {
+            return Ok(self.planes.iter().map(|_| &[][..]).collect());
+        }
+
+        let layouts = crate::get_plane_layouts(
+            &self.format,
+            self.width,
+            self.height,
+            last_plane,
+            &self.strides(),
+        )?;
+
+        // Each plane ends where its last logical plane ends
+        let mut sizes = vec![0; self.planes.len()];
+        for layout in &layouts {
+            sizes[layout.buffer] =
+                layout.offset + layout.stride * ((self.height >> layout.vertical_shift) as usize);
+        }
+
+        self.planes
+            .iter()
+            .zip(sizes)
+            .map(|(plane, size)| {
+                let end = plane.offset.checked_add(size)?;
+                mappings.get(plane.memory)?.get(plane.offset..end)
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or(ErrorKind::NotEnoughData)
+    }
+
+    /// Checks that the image fits in the given memory mappings
+    ///
+    /// # Errors
+    ///
+    /// The errors reported by [`import`].
+    ///
+    /// [`import`]: #method.import
+    pub fn validate(&self, mappings: &[&[u8]]) -> Result<(), ErrorKind> {
+        self.import(mappings).map(|_| ())
+    }
+
+    /// Converts the image to another color space, like [`convert_image`]
+    ///
+    /// # Arguments
+    /// * `mappings` - The memory mappings referenced by the plane descriptors
+    /// * `dst_format` - Destination image format
+    /// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+    ///   in each destination image planes
+    /// * `dst_buffers` - An array of image buffers in each destination color plane
+    ///
+    /// # Errors
+    ///
+    /// * All the errors reported by [`import`]
+    ///
+    /// * All the errors reported by [`convert_image`]
+    ///
+    /// [`import`]: #method.import
+    /// [`convert_image`]: ../fn.convert_image.html
+    pub fn convert(
+        &self,
+        mappings: &[&[u8]],
+        dst_format: &ImageFormat,
+        dst_strides: Option<&[usize]>,
+        dst_buffers: &mut [&mut [u8]],
+    ) -> Result<(), ErrorKind> {
+        let src_buffers = self.import(mappings)?;
+        crate::convert_image(
+            self.width,
+            self.height,
+            &self.format,
+            Some(&self.strides()),
+            &src_buffers,
+            dst_format,
+            dst_strides,
+            dst_buffers,
+        )
+    }
+}
@@ -0,0 +1,457 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Color primaries conversion.
+//!
+//! Converts gamma encoded rgb images between the primaries of the supported color spaces,
+//! so that, for example, standard definition material mastered for SMPTE C phosphors does
+//! not look oversaturated once displayed in a BT.709 pipeline.
+//!
+//! Samples are linearized with the BT.709 transfer characteristic, multiplied by the
+//! primaries conversion matrix and encoded back. All primaries share the D65 white point,
+//! so neutral colors are preserved. Colors that fall outside the destination gamut are
+//! either clipped or compressed toward the luminance axis, see [`GamutMapping`].
+//!
+//! [`GamutMapping`]: ./enum.GamutMapping.html
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::gamut::{convert_primaries_in_place, GamutMapping, Primaries};
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn sd_to_hd() -> Result<(), Box<dyn error::Error>> {
+//!     const WIDTH: u32 = 640;
+//!     const HEIGHT: u32 = 480;
+//!
+//!     let format = ImageFormat {
+//!         pixel_format: PixelFormat::Bgra,
+//!         color_space: ColorSpace::Lrgb,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let mut frame = vec![128u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+//!     convert_primaries_in_place(
+//!         Primaries::Bt601,
+//!         Primaries::Bt709,
+//!         GamutMapping::Clip,
+//!         WIDTH,
+//!         HEIGHT,
+//!         &format,
+//!         None,
+//!         &mut [&mut frame],
+//!     )?;
+//!
+//!     assert_eq!(frame[0], 128);
+//!     Ok(())
+//! }
+//! ```
+use crate::dispatcher;
+use crate::pixel_format::{self, PixelFormat, STRIDE_AUTO};
+use crate::{ErrorKind, ImageFormat};
+
+/// An enumeration of color primaries.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Primaries {
+    /// SMPTE C primaries, used by 525 lines BT.601 material
+    Bt601,
+    /// EBU Tech. 3213 primaries, used by 625 lines BT.601 material
+    Bt601Pal,
+    /// BT.709 primaries, shared with sRGB
+    Bt709,
+    /// BT.2020 primaries
+    Bt2020,
+}
+
+/// An enumeration of strategies to map colors outside the destination gamut.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GamutMapping {
+    /// Clips each component independently
+    Clip,
+    /// Desaturates toward the luminance axis, preserving luminance and hue
+    Compress,
+}
+
+const D65: (f64, f64) = (0.3127, 0.3290);
+
+/// Chromaticity coordinates of red, green and blue
+fn get_chromaticities(primaries: Primaries) -> [(f64, f64); 3] {
+    match primaries {
+        Primaries::Bt601 => [(0.630, 0.340), (0.310, 0.595), (0.155, 0.070)],
+        Primaries::Bt601Pal => [(0.640, 0.330), (0.290, 0.600), (0.150, 0.060)],
+        Primaries::Bt709 => [(0.640, 0.330), (0.300, 0.600), (0.150, 0.060)],
+        Primaries::Bt2020 => [(0.708, 0.292), (0.170, 0.797), (0.131, 0.046)],
+    }
+}
+
+fn xy_to_xyz((x, y): (f64, f64)) -> [f64; 3] {
+    [x / y, 1.0, (1.0 - x - y) / y]
+}
+
+fn invert(m: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let c00 = m[1][1] * m[2][2] - m[1][2] * m[2][1];
+    let c01 = m[1][2] * m[2][0] - m[1][0] * m[2][2];
+    let c02 = m[1][0] * m[2][1] - m[1][1] * m[2][0];
+    let det = m[0][0] * c00 + m[0][1] * c01 + m[0][2] * c02;
+
+    [
+        [
+            c00 / det,
+            (m[0][2] * m[2][1] - m[0][1] * m[2][2]) / det,
+            (m[0][1] * m[1][2] - m[0][2] * m[1][1]) / det,
+        ],
+        [
+            c01 / det,
+            (m[0][0] * m[2][2] - m[0][2] * m[2][0]) / det,
+            (m[0][2] * m[1][0] - m[0][0] * m[1][2]) / det,
+        ],
+        [
+            c02 / det,
+            (m[0][1] * m[2][0] - m[0][0] * m[2][1]) / det,
+            (m[0][0] * m[1][1] - m[0][1] * m[1][0]) / det,
+        ],
+    ]
+}
+
+fn multiply(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut m = [[0.0; 3]; 3];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = a[i][0] * b[0][j] + a[i][1] * b[1][j] + a[i][2] * b[2][j];
+        }
+    }
+
+    m
+}
+
+/// Linear rgb to CIE XYZ matrix, normalized so that white has unit luminance
+fn get_rgb_to_xyz(primaries: Primaries) -> [[f64; 3]; 3] {
+    let xyz: Vec<[f64; 3]> = get_chromaticities(primaries)
+        .iter()
+        .map(|&xy| xy_to_xyz(xy))
+        .collect();
+
+    let m = [
+        [xyz[0][0], xyz[1][0], xyz[2][0]],
+        [xyz[0][1], xyz[1][1], xyz[2][1]],
+        [xyz[0][2], xyz[1][2], xyz[2][2]],
+    ];
+
+    let w = xy_to_xyz(D65);
+    let inv = invert(&m);
+    let s: Vec<f64> = inv
+        .iter()
+        .map(|row| row[0] * w[0] + row[1] * w[1] + row[2] * w[2])
+        .collect();
+
+    let mut m = m;
+    for row in m.iter_mut() {
+        for (value, scale) in row.iter_mut().zip(s.iter()) {
+            *value *= scale;
+        }
+    }
+
+    m
+}
+
+/// Returns the matrix converting linear rgb values from the source primaries to
+/// the destination primaries
+///
+/// # Arguments
+/// * `src_primaries` - Primaries of the source values
+/// * `dst_primaries` - Primaries of the destination values
+pub fn get_conversion_matrix(src_primaries: Primaries, dst_primaries: Primaries) -> [[f32; 3]; 3] {
+    let m = multiply(
+        &invert(&get_rgb_to_xyz(dst_primaries)),
+        &get_rgb_to_xyz(src_primaries),
+    );
+
+    let mut result = [[0.0; 3]; 3];
+    for (dst, src) in result.iter_mut().zip(m.iter()) {
+        for (d, &s) in dst.iter_mut().zip(src.iter()) {
+            *d = s as f32;
+        }
+    }
+
+    result
+}
+
+/// BT.709 inverse opto-electronic transfer function
+fn to_linear(value: f32) -> f32 {
+    if value < 0.081 {
+        value / 4.5
+    } else {
+        ((value + 0.099) / 1.099).powf(1.0 / 0.45)
+    }
+}
+
+/// BT.709 opto-electronic transfer function
+fn from_linear(value: f32) -> f32 {
+    if value < 0.018 {
+        4.5 * value
+    } else {
+        1.099 * value.powf(0.45) - 0.099
+    }
+}
+
+struct Mapper {
+    matrix: [[f32; 3]; 3],
+    luma: [f32; 3],
+    mapping: GamutMapping,
+}
+
+impl Mapper {
+    fn new(src_primaries: Primaries, dst_primaries: Primaries, mapping: GamutMapping) -> Self {
+        let y = get_rgb_to_xyz(dst_primaries)[1];
+        Mapper {
+            matrix: get_conversion_matrix(src_primaries, dst_primaries),
+            luma: [y[0] as f32, y[1] as f32, y[2] as f32],
+            mapping,
+        }
+    }
+
+    /// Converts a linear rgb triplet, returning values in [0, 1]
+    fn map(&self, rgb: [f32; 3]) -> [f32; 3] {
+        let m = &self.matrix;
+        let mut c = [
+            m[0][0] * rgb[0] + m[0][1] * rgb[1] + m[0][2] * rgb[2],
+            m[1][0] * rgb[0] + m[1][1] * rgb[1] + m[1][2] * rgb[2],
+            m[2][0] * rgb[0] + m[2][1] * rgb[1] + m[2][2] * rgb[2],
+        ];
+
+        if let GamutMapping::Compress = self.mapping {
+            let y =
+                (self.luma[0] * c[0] + self.luma[1] * c[1] + self.luma[2] * c[2]).clamp(0.0, 1.0);
+            let min = c[0].min(c[1]).min(c[2]);
+            let max = c[0].max(c[1]).max(c[2]);
+
+            // Blend toward gray just enough to bring every component back in range
+            let mut t: f32 = 1.0;
+            if min < 0.0 {
+                t = t.min(y / (y - min));
+            }
+
+            if max > 1.0 {
+                t = t.min((1.0 - y) / (max - y));
+            }
+
+            for value in c.iter_mut() {
+                *value = y + t * (*value - y);
+            }
+        }
+
+        [
+            c[0].clamp(0.0, 1.0),
+            c[1].clamp(0.0, 1.0),
+            c[2].clamp(0.0, 1.0),
+        ]
+    }
+}
+
+/// Size of the table used to encode linear values back to 8-bit samples
+const ENCODE_TABLE_SIZE: usize = 4096;
+
+fn convert_row_u8(
+    row: &mut [u8],
+    bpp: usize,
+    offsets: (usize, usize, usize),
+    mapper: &Mapper,
+    decode: &[f32],
+    encode: &[u8],
+) {
+    let (r, g, b) = offsets;
+    let scale = (ENCODE_TABLE_SIZE - 1) as f32;
+    for pixel in row.chunks_exact_mut(bpp) {
+        let rgb = mapper.map([
+            decode[pixel[r] as usize],
+            decode[pixel[g] as usize],
+            decode[pixel[b] as usize],
+        ]);
+
+        pixel[r] = encode[(rgb[0] * scale + 0.5) as usize];
+        pixel[g] = encode[(rgb[1] * scale + 0.5) as usize];
+        pixel[b] = encode[(rgb[2] * scale + 0.5) as usize];
+    }
+}
+
+fn read_f32(buffer: &[u8]) -> f32 {
+    f32::from_bits(u32::from_le_bytes([
+        buffer[0], buffer[1], buffer[2], buffer[3],
+    ]))
+}
+
+/// Reads a sample clamped to [0, 1], mapping NaN to 0
+fn read_unit(buffer: &[u8]) -> f32 {
+    let value = read_f32(buffer);
+    if value.is_nan() {
+        0.0
+    } else {
+        value.clamp(0.0, 1.0)
+    }
+}
+
+fn write_f32(buffer: &mut [u8], value: f32) {
+    buffer[..4].copy_from_slice(&value.to_bits().to_le_bytes());
+}
+
+fn convert_row_f32(row: &mut [u8], bpp: usize, mapper: &Mapper) {
+    for pixel in row.chunks_exact_mut(bpp) {
+        let rgb = mapper.map([
+            to_linear(read_unit(&pixel[0..])),
+            to_linear(read_unit(&pixel[4..])),
+            to_linear(read_unit(&pixel[8..])),
+        ]);
+
+        write_f32(&mut pixel[0..], from_linear(rgb[0]));
+        write_f32(&mut pixel[4..], from_linear(rgb[1]));
+        write_f32(&mut pixel[8..], from_linear(rgb[2]));
+    }
+}
+
+/// Converts the primaries of a gamma encoded rgb image in place
+///
+/// # Arguments
+/// * `src_primaries` - Primaries the image is currently encoded with
+/// * `dst_primaries` - Primaries to convert the image to
+/// * `mapping` - How to handle colors outside the destination gamut
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `format` - Image format
+/// * `strides` - An array of distances in bytes between starts of consecutive lines
+///   in each image planes
+/// * `buffers` - An array of image buffers in each image plane
+///
+/// # Note
+/// Supported pixel formats are the 8-bit rgb formats, `RgbF32` and `RgbaF32`.
+/// Alpha is left untouched.
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the pixel format is not compatible with the color space
+///
+/// * [`InvalidValue`] if the image format has a number of planes which is not compatible
+///   with its pixel format
+///
+/// * [`InvalidValue`] if the stride is smaller than the line size
+///
+/// * [`InvalidOperation`] if the pixel format is not supported
+///
+/// * [`NotEnoughData`] if the strides array is not `None` and it is empty
+///
+/// * [`NotEnoughData`] if the buffer does not provide enough data
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+#[allow(clippy::too_many_arguments)]
+pub fn convert_primaries_in_place(
+    src_primaries: Primaries,
+    dst_primaries: Primaries,
+    mapping: GamutMapping,
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+    buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    let pixel_format = format.pixel_format as u32;
+    if dispatcher::get_pixel_format_mode(pixel_format)
+        ^ dispatcher::get_color_space_mode(format.color_space as u32)
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let (bpp, offsets) = match format.pixel_format {
+        PixelFormat::Argb => (4, Some((1, 2, 3))),
+        PixelFormat::Bgra => (4, Some((2, 1, 0))),
+        PixelFormat::Bgr => (3, Some((2, 1, 0))),
+        PixelFormat::Rgba => (4, Some((0, 1, 2))),
+        PixelFormat::Rgb => (3, Some((0, 1, 2))),
+        PixelFormat::RgbF32 => (12, None),
+        PixelFormat::RgbaF32 => (16, None),
+        _ => return Err(ErrorKind::InvalidOperation),
+    };
+
+    if !pixel_format::is_compatible(
+        pixel_format,
+        width,
+        height,
+        format.num_planes.wrapping_sub(1),
+    ) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let strides = strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    if strides.is_empty() || buffers.is_empty() {
+        return Err(ErrorKind::NotEnoughData);
+    }
+
+    let row_size = bpp * (width as usize);
+    let stride = match strides[0] {
+        STRIDE_AUTO => row_size,
+        stride => stride,
+    };
+
+    if stride < row_size {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let rows = height as usize;
+    if rows > 0 {
+        let size = stride
+            .checked_mul(rows - 1)
+            .and_then(|size| size.checked_add(row_size))
+            .ok_or(ErrorKind::NotEnoughData)?;
+        if size > buffers[0].len() {
+            return Err(ErrorKind::NotEnoughData);
+        }
+    }
+
+    if src_primaries == dst_primaries {
+        return Ok(());
+    }
+
+    let mapper = Mapper::new(src_primaries, dst_primaries, mapping);
+    let buffer = &mut buffers[0];
+    match offsets {
+        Some(offsets) => {
+            let decode: Vec<f32> = (0..256).map(|i| to_linear(i as f32 / 255.0)).collect();
+            let scale = (ENCODE_TABLE_SIZE - 1) as f32;
+            let encode: Vec<u8> = (0..ENCODE_TABLE_SIZE)
+                .map(|i| (255.0 * from_linear(i as f32 / scale) + 0.5) as u8)
+                .collect();
+
+            for row in 0..rows {
+                convert_row_u8(
+                    &mut buffer[row * stride..][..row_size],
+                    bpp,
+                    offsets,
+                    &mapper,
+                    &decode,
+                    &encode,
+                );
+            }
+        }
+        None => {
+            for row in 0..rows {
+                convert_row_f32(&mut buffer[row * stride..][..row_size], bpp, &mapper);
+            }
+        }
+    }
+
+    Ok(())
+}
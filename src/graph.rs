@@ -0,0 +1,443 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+// Conversion graph: every valid combination of pixel format and color space is a node,
+// every conversion in the dispatch table is an edge. Pairs without a direct conversion
+// are routed through intermediate images, each one stored in a scratch buffer.
+use crate::color_space::ColorSpace;
+use crate::pixel_format::{self, PixelFormat};
+use crate::trace_span;
+use crate::{dispatcher, ConverterTable, ErrorKind, ImageFormat};
+
+const NODE_COUNT: usize = 32;
+
+const NODES: [(PixelFormat, ColorSpace); NODE_COUNT] = [
+    (PixelFormat::Argb, ColorSpace::Lrgb),
+    (PixelFormat::Bgra, ColorSpace::Lrgb),
+    (PixelFormat::Bgr, ColorSpace::Lrgb),
+    (PixelFormat::Rgba, ColorSpace::Lrgb),
+    (PixelFormat::Rgb, ColorSpace::Lrgb),
+    (PixelFormat::RgbaF16, ColorSpace::Lrgb),
+    (PixelFormat::RgbF32, ColorSpace::Lrgb),
+    (PixelFormat::RgbaF32, ColorSpace::Lrgb),
+    (PixelFormat::Bgr48, ColorSpace::Lrgb),
+    (PixelFormat::Rgb48, ColorSpace::Lrgb),
+    (PixelFormat::I444, ColorSpace::Bt601),
+    (PixelFormat::I422, ColorSpace::Bt601),
+    (PixelFormat::I420, ColorSpace::Bt601),
+    (PixelFormat::Nv12, ColorSpace::Bt601),
+    (PixelFormat::P010, ColorSpace::Bt601),
+    (PixelFormat::I444, ColorSpace::Bt709),
+    (PixelFormat::I422, ColorSpace::Bt709),
+    (PixelFormat::I420, ColorSpace::Bt709),
+    (PixelFormat::Nv12, ColorSpace::Bt709),
+    (PixelFormat::P010, ColorSpace::Bt709),
+    (PixelFormat::I444, ColorSpace::Bt2020Pq),
+    (PixelFormat::I422, ColorSpace::Bt2020Pq),
+    (PixelFormat::I420, ColorSpace::Bt2020Pq),
+    (PixelFormat::Nv12, ColorSpace::Bt2020Pq),
+    (PixelFormat::P010, ColorSpace::Bt2020Pq),
+    (PixelFormat::I444, ColorSpace::Jfif),
+    (PixelFormat::I422, ColorSpace::Jfif),
+    (PixelFormat::I420, ColorSpace::Jfif),
+    (PixelFormat::Nv12, ColorSpace::Jfif),
+    (PixelFormat::P010, ColorSpace::Jfif),
+    (PixelFormat::YuvF32Planar, ColorSpace::Bt601),
+    (PixelFormat::YuvF32Planar, ColorSpace::Bt709),
+];
+
+// Every hop costs more than all the chroma subsampling a route can go through,
+// so the route with fewer hops always wins. Among routes with the same number of hops,
+// the one that does not discard chroma samples in intermediate images is preferred.
+const HOP_COST: u32 = 32;
+const SUBSAMPLING_COST: u32 = 1;
+
+fn get_image_index(node: usize) -> u32 {
+    let (pixel_format, color_space) = NODES[node];
+    let pixel_format = pixel_format as u32;
+    dispatcher::get_image_index(
+        pixel_format,
+        color_space as u32,
+        dispatcher::get_pixel_format_mode(pixel_format),
+    )
+}
+
+fn find_node(format: &ImageFormat) -> Option<usize> {
+    let pixel_format = format.pixel_format as u32;
+    let color_space = format.color_space as u32;
+    NODES
+        .iter()
+        .position(|&(pf, cs)| pf as u32 == pixel_format && cs as u32 == color_space)
+}
+
+fn has_converter(converters: &ConverterTable, from: usize, to: usize) -> bool {
+    let index = dispatcher::get_index(get_image_index(from), get_image_index(to));
+    index < converters.len() && converters[index].is_some()
+}
+
+/// Intermediate image format of a route, with a buffer for each plane
+/// as not every conversion supports all the plane layouts
+fn get_intermediate_format(node: usize) -> ImageFormat {
+    let (pixel_format, color_space) = NODES[node];
+    ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes: pixel_format::get_logical_planes(pixel_format).len() as u32,
+    }
+}
+
+/// Cost of a conversion with the fewest hops, see [`HOP_COST`]
+fn get_hop_cost(_from: PixelFormat, to: PixelFormat, is_destination: bool) -> u32 {
+    if !is_destination && pixel_format::is_subsampled(to) {
+        HOP_COST + SUBSAMPLING_COST
+    } else {
+        HOP_COST
+    }
+}
+
+/// Finds the sequence of conversions from the source to the destination format with
+/// the fewest hops, and returns the intermediate image formats it goes through.
+///
+/// Only intermediate formats whose size constraints are satisfied by the image
+/// dimensions are considered. Images with the same pixel format and color space
+/// are never routed.
+///
+/// The library must be initialized and both formats must have been validated.
+pub fn find_route(
+    converters: &ConverterTable,
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    dst_format: &ImageFormat,
+) -> Result<Vec<ImageFormat>, ErrorKind> {
+    find_cheapest_route(
+        converters,
+        width,
+        height,
+        src_format,
+        dst_format,
+        get_hop_cost,
+    )
+}
+
+/// Like [`find_route`], but the cost of each conversion is given by `hop_cost`, which
+/// receives the pixel formats of the conversion and whether it writes the destination image
+pub fn find_cheapest_route(
+    converters: &ConverterTable,
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    dst_format: &ImageFormat,
+    hop_cost: fn(PixelFormat, PixelFormat, bool) -> u32,
+) -> Result<Vec<ImageFormat>, ErrorKind> {
+    let src = find_node(src_format).ok_or(ErrorKind::InvalidOperation)?;
+    let dst = find_node(dst_format).ok_or(ErrorKind::InvalidOperation)?;
+    if src == dst {
+        return Err(ErrorKind::InvalidOperation);
+    }
+
+    let mut cost = [u32::MAX; NODE_COUNT];
+    let mut previous = [src; NODE_COUNT];
+    let mut visited = [false; NODE_COUNT];
+    cost[src] = 0;
+
+    loop {
+        let node = (0..NODE_COUNT)
+            .filter(|&i| !visited[i] && cost[i] != u32::MAX)
+            .min_by_key(|&i| cost[i]);
+
+        let node = match node {
+            Some(node) if node != dst => node,
+            Some(_) => break,
+            None => return Err(ErrorKind::InvalidOperation),
+        };
+
+        visited[node] = true;
+        for next in 0..NODE_COUNT {
+            if visited[next] || !has_converter(converters, node, next) {
+                continue;
+            }
+
+            let is_destination = next == dst;
+            if !is_destination
+                && !pixel_format::is_compatible(NODES[next].0 as u32, width, height, 0)
+            {
+                continue;
+            }
+
+            let next_cost =
+                cost[node].saturating_add(hop_cost(NODES[node].0, NODES[next].0, is_destination));
+
+            if next_cost < cost[next] {
+                cost[next] = next_cost;
+                previous[next] = node;
+            }
+        }
+    }
+
+    let mut route = Vec::new();
+    let mut node = previous[dst];
+    while node != src {
+        route.push(get_intermediate_format(node));
+        node = previous[node];
+    }
+
+    route.reverse();
+    Ok(route)
+}
+
+/// Converts a band to the next image of a route. `first_line` is the line where the band
+/// starts when the next image is the destination image, or `None` for intermediate images
+#[allow(clippy::too_many_arguments)]
+fn convert_hop(
+    converters: &ConverterTable,
+    width: u32,
+    height: u32,
+    first_line: Option<usize>,
+    src_format: &ImageFormat,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_hop",
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    let (converter, last_src_plane, last_dst_plane) =
+        crate::find_converter(converters, width, height, src_format, dst_format)?;
+
+    if let Some(first_line) = first_line {
+        return crate::run_converter(
+            converter,
+            width,
+            height,
+            first_line,
+            last_src_plane,
+            src_strides,
+            src_buffers,
+            dst_format,
+            dst_strides,
+            dst_buffers,
+        );
+    }
+
+    if converter(
+        width,
+        height,
+        last_src_plane,
+        src_strides,
+        src_buffers,
+        last_dst_plane,
+        dst_strides,
+        dst_buffers,
+    ) {
+        Ok(())
+    } else {
+        Err(ErrorKind::NotEnoughData)
+    }
+}
+
+/// Number of lines converted through the whole route before moving to the next band.
+/// It is even, so that bands of images with vertical subsampling are valid images too.
+const ROUTE_BAND_HEIGHT: usize = 64;
+
+fn get_band_format(format: &ImageFormat, num_planes: usize) -> ImageFormat {
+    ImageFormat {
+        pixel_format: format.pixel_format,
+        color_space: format.color_space,
+        num_planes: num_planes as u32,
+    }
+}
+
+/// Returns the size of the scratch buffer needed by [`convert_routed`] to hold a band of
+/// each intermediate image of a route
+pub fn get_scratch_size(
+    width: u32,
+    height: u32,
+    route: &[ImageFormat],
+) -> Result<usize, ErrorKind> {
+    let band_height = (height as usize).min(ROUTE_BAND_HEIGHT);
+    let mut scratch_size = 0usize;
+    for format in route {
+        let buffers_size = &mut [0usize; pixel_format::MAX_NUMBER_OF_PLANES];
+        crate::get_buffers_size(width, band_height as u32, format, None, buffers_size)?;
+        for &size in &buffers_size[..format.num_planes as usize] {
+            scratch_size = scratch_size
+                .checked_add(size)
+                .ok_or(ErrorKind::InvalidValue)?;
+        }
+    }
+
+    Ok(scratch_size)
+}
+
+/// Converts an image through the intermediate formats of a route
+///
+/// The image is converted in horizontal bands. Each plane of an intermediate image is
+/// tightly packed in the scratch buffer, which holds a single band of each intermediate
+/// image, so that the memory required does not depend on the image height. The scratch
+/// buffer must be at least [`get_scratch_size`] bytes and is reused for all the bands.
+///
+/// Both formats must have been validated.
+#[allow(clippy::too_many_arguments)]
+pub fn convert_routed(
+    converters: &ConverterTable,
+    scratch: &mut [u8],
+    width: u32,
+    height: u32,
+    route: &[ImageFormat],
+    src_format: &ImageFormat,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    // Bands have one buffer for each logical plane, but like their direct conversions,
+    // routes do not support images with three planes stored in a single buffer
+    for format in &[src_format, dst_format] {
+        let num_planes = pixel_format::get_logical_planes(format.pixel_format).len() as u32;
+        if num_planes > 2 && format.num_planes != num_planes {
+            return Err(ErrorKind::NotEnoughData);
+        }
+    }
+
+    let line_count = height as usize;
+    let band_height = line_count.min(ROUTE_BAND_HEIGHT);
+    let mut remainder = scratch;
+    let mut intermediates = Vec::with_capacity(route.len());
+    for format in route {
+        let buffers_size = &mut [0usize; pixel_format::MAX_NUMBER_OF_PLANES];
+        crate::get_buffers_size(width, band_height as u32, format, None, buffers_size)?;
+
+        let mut planes = Vec::with_capacity(format.num_planes as usize);
+        for &size in &buffers_size[..format.num_planes as usize] {
+            if size > remainder.len() {
+                return Err(ErrorKind::NotEnoughData);
+            }
+
+            let (plane, tail) = remainder.split_at_mut(size);
+            planes.push(plane);
+            remainder = tail;
+        }
+
+        intermediates.push(planes);
+    }
+
+    let last_src_plane = src_format.num_planes - 1;
+    let last_dst_plane = dst_format.num_planes - 1;
+    let src_layouts =
+        crate::get_plane_layouts(src_format, width, height, last_src_plane, src_strides)?;
+    let dst_layouts =
+        crate::get_plane_layouts(dst_format, width, height, last_dst_plane, dst_strides)?;
+
+    let band_src_format = get_band_format(src_format, src_layouts.len());
+    let band_dst_format = get_band_format(dst_format, dst_layouts.len());
+    crate::convert_in_bands(
+        &src_layouts,
+        src_buffers,
+        dst_format,
+        &dst_layouts,
+        dst_buffers,
+        crate::get_bands(line_count, band_height),
+        |mut band| {
+            convert_band(
+                converters,
+                width,
+                band.height() as u32,
+                band.first_line,
+                route,
+                &mut intermediates,
+                &band_src_format,
+                band.src_strides,
+                &band.src_buffers,
+                &band_dst_format,
+                band.dst_strides,
+                &mut band.dst_buffers,
+            )
+        },
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn convert_band(
+    converters: &ConverterTable,
+    width: u32,
+    height: u32,
+    first_line: usize,
+    route: &[ImageFormat],
+    scratch: &mut [Vec<&mut [u8]>],
+    src_format: &ImageFormat,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    let last_hop = route.len();
+    for hop in 0..=last_hop {
+        let (done, pending) = scratch.split_at_mut(hop);
+        let previous_buffers: Vec<&[u8]> = done
+            .last()
+            .map_or(Vec::new(), |planes| planes.iter().map(|x| &x[..]).collect());
+        let (hop_src_format, hop_src_strides, hop_src_buffers) = if hop == 0 {
+            (src_format, src_strides, src_buffers)
+        } else {
+            (
+                &route[hop - 1],
+                &pixel_format::DEFAULT_STRIDES[..],
+                &previous_buffers[..],
+            )
+        };
+
+        if hop == last_hop {
+            convert_hop(
+                converters,
+                width,
+                height,
+                Some(first_line),
+                hop_src_format,
+                hop_src_strides,
+                hop_src_buffers,
+                dst_format,
+                dst_strides,
+                dst_buffers,
+            )?;
+        } else {
+            let mut next_buffers: Vec<&mut [u8]> =
+                pending[0].iter_mut().map(|x| &mut x[..]).collect();
+            convert_hop(
+                converters,
+                width,
+                height,
+                None,
+                hop_src_format,
+                hop_src_strides,
+                hop_src_buffers,
+                &route[hop],
+                &pixel_format::DEFAULT_STRIDES,
+                &mut next_buffers,
+            )?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,306 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Conversion of HDR screenshots to SDR images.
+//!
+//! Windows captures HDR desktops as 10-bit packed rgb, encoded with the SMPTE ST 2084
+//! perceptual quantizer and BT.2020 primaries. [`bgra30_to_bgra`] converts such images to
+//! 8-bit sRGB [`Bgra`] in a single pass, compressing the highlights brighter than the SDR
+//! white with the curve selected by a [`ToneMapping`] hint.
+//!
+//! Each Bgra30 pixel is a little endian 32-bit word, holding blue in bits 0-9, green in
+//! bits 10-19, red in bits 20-29 and alpha in bits 30-31.
+//!
+//! Components are linearized, moved to BT.709 primaries and divided by the SDR white.
+//! The tone mapping curve is applied to the largest component of each pixel, and all the
+//! components are scaled by the same amount to preserve the hue. Colors dimmer than the
+//! knee of the curve are left untouched.
+//!
+//! [`bgra30_to_bgra`]: ./fn.bgra30_to_bgra.html
+//! [`Bgra`]: ../enum.PixelFormat.html#variant.Bgra
+//! [`ToneMapping`]: ./struct.ToneMapping.html
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::hdr::{bgra30_to_bgra, ToneCurve, ToneMapping};
+//! use dcp::STRIDE_AUTO;
+//! use std::error;
+//!
+//! fn screenshot() -> Result<(), Box<dyn error::Error>> {
+//!     const WIDTH: u32 = 640;
+//!     const HEIGHT: u32 = 480;
+//!
+//!     // Opaque white at the peak luminance of the perceptual quantizer
+//!     let pixel = 0xFFFF_FFFFu32.to_le_bytes();
+//!     let hdr: Vec<u8> = pixel.iter().cycle().take(4 * 640 * 480).cloned().collect();
+//!
+//!     let mut sdr = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+//!     let tone_mapping = ToneMapping {
+//!         curve: ToneCurve::Bt2390,
+//!         sdr_white: 203.0,
+//!         peak: 1000.0,
+//!     };
+//!
+//!     bgra30_to_bgra(
+//!         WIDTH,
+//!         HEIGHT,
+//!         STRIDE_AUTO,
+//!         &hdr,
+//!         STRIDE_AUTO,
+//!         &mut sdr,
+//!         &tone_mapping,
+//!     )?;
+//!
+//!     assert_eq!(&sdr[..4], &[255, 255, 255, 255]);
+//!     Ok(())
+//! }
+//! # screenshot().unwrap();
+//! ```
+use crate::convert_image::common::{pq_eotf, pq_inverse_eotf, srgb_oetf, BT2020_TO_BT709};
+use crate::pixel_format::STRIDE_AUTO;
+use crate::trace_span;
+use crate::ErrorKind;
+
+/// Peak luminance of the perceptual quantizer, in nits
+const PQ_PEAK_NITS: f32 = 10000.0;
+
+/// An enumeration of tone mapping curves.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ToneCurve {
+    /// Clips the components brighter than the SDR white
+    Clip,
+    /// Extended Reinhard curve, mapping the peak luminance to the SDR white.
+    /// Highlights are compressed smoothly, but mid tones are darkened as well
+    Reinhard,
+    /// The EETF of ITU-R BT.2390, mapping the peak luminance to the SDR white.
+    /// Only highlights above a knee are compressed, along a spline in the perceptual
+    /// quantizer domain
+    Bt2390,
+}
+
+/// How HDR luminances are mapped to the SDR range
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ToneMapping {
+    /// The tone mapping curve
+    pub curve: ToneCurve,
+    /// Luminance in nits displayed as SDR white, the brightest 8-bit value.
+    /// Must be positive and at most 10000
+    pub sdr_white: f32,
+    /// Luminance in nits of the brightest highlights of the source, e.g. the peak
+    /// luminance of the display the screenshot was taken on. Must be positive and at
+    /// most 10000. Curves other than [`Clip`] compress it to the SDR white, and clip
+    /// anything brighter
+    ///
+    /// [`Clip`]: ./enum.ToneCurve.html#variant.Clip
+    pub peak: f32,
+}
+
+impl Default for ToneMapping {
+    /// BT.2390 curve, with the reference white of ITU-R BT.2408 and a 1000 nits peak
+    fn default() -> Self {
+        ToneMapping {
+            curve: ToneCurve::Bt2390,
+            sdr_white: 203.0,
+            peak: 1000.0,
+        }
+    }
+}
+
+impl ToneMapping {
+    fn is_valid(&self) -> bool {
+        let is_luminance = |nits: f32| nits > 0.0 && nits <= PQ_PEAK_NITS;
+        is_luminance(self.sdr_white) && is_luminance(self.peak)
+    }
+}
+
+/// Maps a linear luminance in nits to a fraction of the SDR white, in [0, 1]
+struct Curve {
+    curve: ToneCurve,
+    sdr_white: f32,
+    // Peak luminance relative to the SDR white
+    relative_peak: f32,
+    // Peak luminance and maximum output luminance in the perceptual quantizer domain
+    pq_peak: f32,
+    pq_white: f32,
+}
+
+impl Curve {
+    fn new(tone_mapping: &ToneMapping) -> Curve {
+        let pq_peak = pq_inverse_eotf(tone_mapping.peak / PQ_PEAK_NITS);
+        let curve = if tone_mapping.peak > tone_mapping.sdr_white {
+            tone_mapping.curve
+        } else {
+            ToneCurve::Clip
+        };
+
+        Curve {
+            curve,
+            sdr_white: tone_mapping.sdr_white,
+            relative_peak: tone_mapping.peak / tone_mapping.sdr_white,
+            pq_peak,
+            pq_white: pq_inverse_eotf(tone_mapping.sdr_white / PQ_PEAK_NITS) / pq_peak,
+        }
+    }
+
+    fn apply(&self, nits: f32) -> f32 {
+        let l = nits / self.sdr_white;
+        let mapped = match self.curve {
+            ToneCurve::Clip => l,
+            ToneCurve::Reinhard => {
+                let p = self.relative_peak;
+                l * (1.0 + l / (p * p)) / (1.0 + l)
+            }
+            ToneCurve::Bt2390 => {
+                let e = (pq_inverse_eotf(nits / PQ_PEAK_NITS) / self.pq_peak).min(1.0);
+                let ks = 1.5 * self.pq_white - 0.5;
+                if e < ks {
+                    l
+                } else {
+                    // Hermite spline from the knee to the maximum output luminance
+                    let t = (e - ks) / (1.0 - ks);
+                    let (t2, t3) = (t * t, t * t * t);
+                    let e = (2.0 * t3 - 3.0 * t2 + 1.0) * ks
+                        + (t3 - 2.0 * t2 + t) * (1.0 - ks)
+                        + (-2.0 * t3 + 3.0 * t2) * self.pq_white;
+                    PQ_PEAK_NITS * pq_eotf(e * self.pq_peak) / self.sdr_white
+                }
+            }
+        };
+
+        mapped.clamp(0.0, 1.0)
+    }
+}
+
+fn get_stride(line_size: usize, stride: usize) -> Result<usize, ErrorKind> {
+    match stride {
+        STRIDE_AUTO => Ok(line_size),
+        stride if stride < line_size => Err(ErrorKind::NotEnoughData),
+        stride => Ok(stride),
+    }
+}
+
+fn check_size(line_size: usize, lines: usize, stride: usize, size: usize) -> Result<(), ErrorKind> {
+    match stride
+        .checked_mul(lines - 1)
+        .and_then(|end| end.checked_add(line_size))
+    {
+        Some(end) if end <= size => Ok(()),
+        _ => Err(ErrorKind::NotEnoughData),
+    }
+}
+
+fn to_u8(value: f32) -> u8 {
+    (255.0 * srgb_oetf(value) + 0.5) as u8
+}
+
+/// Converts a PQ encoded, BT.2020 Bgra30 image to an 8-bit sRGB `Bgra` image, with tone
+/// mapping
+///
+/// The 2-bit alpha is scaled to 8 bits.
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_stride` - Distance in bytes between starts of consecutive lines of the source
+///   image, or [`STRIDE_AUTO`] if lines are contiguous
+/// * `src_buffer` - The source Bgra30 image
+/// * `dst_stride` - Distance in bytes between starts of consecutive lines of the destination
+///   image, or [`STRIDE_AUTO`] if lines are contiguous
+/// * `dst_buffer` - The destination Bgra image
+/// * `tone_mapping` - How HDR luminances are mapped to the SDR range
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the luminances of the tone mapping are not valid
+///
+/// * [`NotEnoughData`] if a stride is smaller than the size of a line, or a buffer does
+///   not provide enough data
+///
+/// [`STRIDE_AUTO`]: ../constant.STRIDE_AUTO.html
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+pub fn bgra30_to_bgra(
+    width: u32,
+    height: u32,
+    src_stride: usize,
+    src_buffer: &[u8],
+    dst_stride: usize,
+    dst_buffer: &mut [u8],
+    tone_mapping: &ToneMapping,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "bgra30_to_bgra",
+        width,
+        height,
+        curve = ?tone_mapping.curve
+    );
+
+    if !tone_mapping.is_valid() {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let col_count = width as usize;
+    let line_count = height as usize;
+    if col_count == 0 || line_count == 0 {
+        return Ok(());
+    }
+
+    let line_size = col_count.checked_mul(4).ok_or(ErrorKind::NotEnoughData)?;
+    let src_stride = get_stride(line_size, src_stride)?;
+    let dst_stride = get_stride(line_size, dst_stride)?;
+    check_size(line_size, line_count, src_stride, src_buffer.len())?;
+    check_size(line_size, line_count, dst_stride, dst_buffer.len())?;
+
+    // Luminance in nits of each 10-bit code value
+    let mut nits = [0f32; 1024];
+    for (code, value) in nits.iter_mut().enumerate() {
+        *value = PQ_PEAK_NITS * pq_eotf(code as f32 / 1023.0);
+    }
+
+    let curve = Curve::new(tone_mapping);
+    let m = &BT2020_TO_BT709;
+    for y in 0..line_count {
+        let src_line = &src_buffer[y * src_stride..][..line_size];
+        let dst_line = &mut dst_buffer[y * dst_stride..][..line_size];
+        for (src, dst) in src_line.chunks_exact(4).zip(dst_line.chunks_exact_mut(4)) {
+            let pixel = u32::from_le_bytes([src[0], src[1], src[2], src[3]]);
+            let b = nits[(pixel & 0x3FF) as usize];
+            let g = nits[((pixel >> 10) & 0x3FF) as usize];
+            let r = nits[((pixel >> 20) & 0x3FF) as usize];
+
+            // Colors outside the BT.709 gamut are clipped
+            let r709 = (m[0][0] * r + m[0][1] * g + m[0][2] * b).max(0.0);
+            let g709 = (m[1][0] * r + m[1][1] * g + m[1][2] * b).max(0.0);
+            let b709 = (m[2][0] * r + m[2][1] * g + m[2][2] * b).max(0.0);
+
+            let max = r709.max(g709).max(b709);
+            let scale = if max > 0.0 {
+                curve.apply(max) / max
+            } else {
+                0.0
+            };
+
+            dst[0] = to_u8(scale * b709);
+            dst[1] = to_u8(scale * g709);
+            dst[2] = to_u8(scale * r709);
+            dst[3] = (85 * (pixel >> 30)) as u8;
+        }
+    }
+
+    Ok(())
+}
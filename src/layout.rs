@@ -0,0 +1,336 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Memory layout of pixel formats, including the ones defined outside of this crate.
+//!
+//! [`PixelLayout`] describes how the samples of a pixel format are stored: its logical
+//! planes, their subsampling and the bit depth of the samples. It is implemented by
+//! [`PixelFormat`], and downstream crates can implement it for their own formats to
+//! compute buffer sizes and strides exactly like this crate does, even if no conversion
+//! is available for them.
+//!
+//! Size constraints follow from the planes: the width of the image must be a multiple
+//! of the horizontal subsampling factor of every plane, and the height a multiple of
+//! the vertical one. Images are stored either in a single buffer, holding all the
+//! planes one after the other, or in a buffer for each plane.
+//!
+//! [`PixelLayout`]: ./trait.PixelLayout.html
+//! [`PixelFormat`]: ../enum.PixelFormat.html
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::layout::{get_buffers_size, recommended_stride, PixelLayout, PlaneGeometry};
+//! use std::error;
+//!
+//! // Packed 4:2:2, each pair of pixels is stored as y0, u, y1, v
+//! struct Yuyv;
+//!
+//! impl PixelLayout for Yuyv {
+//!     fn planes(&self) -> &[PlaneGeometry] {
+//!         &[PlaneGeometry {
+//!             bytes_per_sample: 4,
+//!             horizontal_shift: 1,
+//!             vertical_shift: 0,
+//!         }]
+//!     }
+//!
+//!     fn bit_depth(&self) -> u32 {
+//!         8
+//!     }
+//! }
+//!
+//! fn compute() -> Result<(), Box<dyn error::Error>> {
+//!     const WIDTH: u32 = 1366;
+//!     const HEIGHT: u32 = 768;
+//!
+//!     let sizes = &mut [0usize; 1];
+//!     get_buffers_size(WIDTH, HEIGHT, &Yuyv, 1, None, sizes)?;
+//!     assert_eq!(sizes[0], 2 * 1366 * 768);
+//!
+//!     let stride = recommended_stride(WIDTH, &Yuyv, 0);
+//!     assert_eq!(stride, 2752);
+//!     get_buffers_size(WIDTH, HEIGHT, &Yuyv, 1, Some(&[stride]), sizes)?;
+//!     assert_eq!(sizes[0], 2752 * 768);
+//!
+//!     // Odd widths split a pair of pixels
+//!     assert!(get_buffers_size(WIDTH + 1, HEIGHT, &Yuyv, 1, None, sizes).is_err());
+//!
+//!     Ok(())
+//! }
+//! # compute().unwrap();
+//! ```
+use crate::pixel_format;
+use crate::{ErrorKind, PixelFormat, STRIDE_AUTO};
+
+/// Alignment in bytes of the strides returned by [`recommended_stride`]
+///
+/// [`recommended_stride`]: ./fn.recommended_stride.html
+const STRIDE_ALIGNMENT: usize = 64;
+
+/// Geometry of a logical plane of a pixel format
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlaneGeometry {
+    /// Bytes of each sample of the plane. With subsampling, a sample covers several pixels
+    /// (e.g. 2 bytes for the interleaved chroma of `PixelFormat::Nv12`)
+    pub bytes_per_sample: usize,
+    /// Base 2 logarithm of the horizontal subsampling factor
+    pub horizontal_shift: u32,
+    /// Base 2 logarithm of the vertical subsampling factor
+    pub vertical_shift: u32,
+}
+
+/// Memory layout of a pixel format
+pub trait PixelLayout {
+    /// Logical planes of the format (e.g. luma and interleaved chroma for
+    /// `PixelFormat::Nv12`), in the order they are stored when they share a buffer
+    fn planes(&self) -> &[PlaneGeometry];
+
+    /// Significant bits of each sample
+    fn bit_depth(&self) -> u32;
+}
+
+impl PixelLayout for PixelFormat {
+    fn planes(&self) -> &[PlaneGeometry] {
+        const fn plane(
+            bytes_per_sample: usize,
+            horizontal_shift: u32,
+            vertical_shift: u32,
+        ) -> PlaneGeometry {
+            PlaneGeometry {
+                bytes_per_sample,
+                horizontal_shift,
+                vertical_shift,
+            }
+        }
+
+        const PACKED_3: PlaneGeometry = plane(3, 0, 0);
+        const PACKED_4: PlaneGeometry = plane(4, 0, 0);
+        const PACKED_6: PlaneGeometry = plane(6, 0, 0);
+        const PACKED_8: PlaneGeometry = plane(8, 0, 0);
+        const PACKED_12: PlaneGeometry = plane(12, 0, 0);
+        const PACKED_16: PlaneGeometry = plane(16, 0, 0);
+        const FULL: PlaneGeometry = plane(1, 0, 0);
+        const HALF_WIDTH: PlaneGeometry = plane(1, 1, 0);
+        const QUARTER: PlaneGeometry = plane(1, 1, 1);
+        const INTERLEAVED: PlaneGeometry = plane(2, 1, 1);
+        const FULL_16: PlaneGeometry = plane(2, 0, 0);
+        const INTERLEAVED_16: PlaneGeometry = plane(4, 1, 1);
+        const FULL_32: PlaneGeometry = plane(4, 0, 0);
+
+        match self {
+            PixelFormat::Argb | PixelFormat::Bgra | PixelFormat::Rgba => &[PACKED_4],
+            PixelFormat::Bgr | PixelFormat::Rgb => &[PACKED_3],
+            PixelFormat::RgbaF16 => &[PACKED_8],
+            PixelFormat::RgbF32 => &[PACKED_12],
+            PixelFormat::RgbaF32 => &[PACKED_16],
+            PixelFormat::Bgr48 | PixelFormat::Rgb48 => &[PACKED_6],
+            PixelFormat::I444 => &[FULL, FULL, FULL],
+            PixelFormat::I422 => &[FULL, HALF_WIDTH, HALF_WIDTH],
+            PixelFormat::I420 => &[FULL, QUARTER, QUARTER],
+            PixelFormat::Nv12 => &[FULL, INTERLEAVED],
+            PixelFormat::P010 => &[FULL_16, INTERLEAVED_16],
+            PixelFormat::YuvF32Planar => &[FULL_32, FULL_32, FULL_32],
+        }
+    }
+
+    fn bit_depth(&self) -> u32 {
+        pixel_format::get_bit_depth(*self)
+    }
+}
+
+fn shift(dimension: u32, shift: u32) -> usize {
+    dimension.checked_shr(shift).unwrap_or(0) as usize
+}
+
+/// Bytes spanned by a plane with the given stride, padding of the last line included
+pub(crate) fn plane_size(
+    width: u32,
+    height: u32,
+    plane: &PlaneGeometry,
+    stride: usize,
+) -> Option<usize> {
+    let size = stride.checked_mul(shift(height, plane.vertical_shift))?;
+
+    // Empty images need no storage, even when explicit strides are given
+    Some(if width == 0 || height == 0 { 0 } else { size })
+}
+
+fn is_multiple(dimension: u32, shift: u32) -> bool {
+    dimension.trailing_zeros() >= shift
+}
+
+/// Returns the size in bytes of a tightly packed line of a plane
+///
+/// Returns `None` if the pixel format has fewer planes, or if the size does not fit
+/// in a `usize`.
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `layout` - Layout of the pixel format of the image
+/// * `plane` - Index of the logical plane
+pub fn line_size<L: PixelLayout + ?Sized>(width: u32, layout: &L, plane: u32) -> Option<usize> {
+    layout.planes().get(plane as usize).and_then(|plane| {
+        plane
+            .bytes_per_sample
+            .checked_mul(shift(width, plane.horizontal_shift))
+    })
+}
+
+/// Returns the stride, in bytes, that conversions perform best with for a plane of an image
+/// stored with one buffer for each plane
+///
+/// This is the generalization of [`dcp::recommended_stride`] to any [`PixelLayout`]:
+/// the size of a line of the plane, rounded up to a multiple of 64 bytes.
+///
+/// Returns zero if the pixel format has fewer planes, or if the stride does not fit in a
+/// `usize`.
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `layout` - Layout of the pixel format of the image
+/// * `plane` - Index of the logical plane
+///
+/// [`dcp::recommended_stride`]: ../fn.recommended_stride.html
+/// [`PixelLayout`]: ./trait.PixelLayout.html
+pub fn recommended_stride<L: PixelLayout + ?Sized>(width: u32, layout: &L, plane: u32) -> usize {
+    aligned_stride(width, layout, plane, STRIDE_ALIGNMENT)
+}
+
+/// Returns the size, in bytes, of a line of a plane rounded up to a multiple of `alignment`
+///
+/// This is the generalization of [`dcp::aligned_stride`] to any [`PixelLayout`].
+///
+/// Returns zero if `alignment` is zero, if the pixel format has fewer planes, or if the
+/// stride does not fit in a `usize`.
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `layout` - Layout of the pixel format of the image
+/// * `plane` - Index of the logical plane
+/// * `alignment` - Alignment of the stride in bytes, not necessarily a power of two
+///
+/// [`dcp::aligned_stride`]: ../fn.aligned_stride.html
+/// [`PixelLayout`]: ./trait.PixelLayout.html
+pub fn aligned_stride<L: PixelLayout + ?Sized>(
+    width: u32,
+    layout: &L,
+    plane: u32,
+    alignment: usize,
+) -> usize {
+    if alignment == 0 {
+        return 0;
+    }
+
+    line_size(width, layout, plane)
+        .and_then(|size| size.checked_add(alignment - 1))
+        .map_or(0, |size| size - size % alignment)
+}
+
+/// Compute number of bytes required to store an image given its format, dimensions
+/// and optionally its strides
+///
+/// This is the generalization of [`dcp::get_buffers_size`] to any [`PixelLayout`].
+/// Images are stored in `num_planes` buffers, which is either one, or the number of
+/// logical planes of the pixel format. A single buffer holds all the planes one after
+/// the other, each one with its own stride if given, or the stride of the first plane.
+///
+/// Default strides (e.g. the one you would set for tightly packed data) can be set
+/// using the constant [`STRIDE_AUTO`]. If `width` or `height` is zero, all the buffer
+/// sizes are zero, whatever the strides.
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `layout` - Layout of the pixel format of the image
+/// * `num_planes` - Number of buffers the image is stored in
+/// * `strides` - An array of distances in bytes between starts of consecutive lines
+///   in each image planes
+/// * `buffers_size` - An array describing the minimum number of bytes required in each
+///   image planes
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if `width` or `height` are not multiples of the subsampling factors
+///   of the planes of the pixel format
+///
+/// * [`InvalidValue`] if `num_planes` is not compatible with the pixel format
+///
+/// * [`InvalidValue`] if the size of one or more buffers does not fit in a `usize`
+///
+/// * [`NotEnoughData`] if the strides array is not `None` and its length is less than
+///   `num_planes`
+///
+/// * [`NotEnoughData`] if the buffers_sizes array length is less than `num_planes`
+///
+/// [`dcp::get_buffers_size`]: ../fn.get_buffers_size.html
+/// [`PixelLayout`]: ./trait.PixelLayout.html
+/// [`STRIDE_AUTO`]: ../constant.STRIDE_AUTO.html
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+pub fn get_buffers_size<L: PixelLayout + ?Sized>(
+    width: u32,
+    height: u32,
+    layout: &L,
+    num_planes: u32,
+    strides: Option<&[usize]>,
+    buffers_size: &mut [usize],
+) -> Result<(), ErrorKind> {
+    let planes = layout.planes();
+    let num_planes = num_planes as usize;
+    if planes.is_empty() || (num_planes != 1 && num_planes != planes.len()) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    if !planes.iter().all(|plane| {
+        is_multiple(width, plane.horizontal_shift) && is_multiple(height, plane.vertical_shift)
+    }) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let strides = strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    if num_planes > strides.len() || num_planes > buffers_size.len() {
+        return Err(ErrorKind::NotEnoughData);
+    }
+
+    // Sizes come from untrusted dimensions and strides: any overflow is reported
+    // as an invalid value rather than wrapping around
+    let mut plane_sizes = Vec::with_capacity(planes.len());
+    for (i, plane) in planes.iter().enumerate() {
+        let stride = match strides.get(i) {
+            Some(&stride) if stride != STRIDE_AUTO => Some(stride),
+            _ if num_planes == 1 && strides[0] != STRIDE_AUTO => Some(strides[0]),
+            _ => line_size(width, layout, i as u32),
+        };
+
+        let size = stride
+            .and_then(|x| plane_size(width, height, plane, x))
+            .ok_or(ErrorKind::InvalidValue)?;
+
+        plane_sizes.push(size);
+    }
+
+    if num_planes == 1 {
+        buffers_size[0] = plane_sizes
+            .iter()
+            .try_fold(0usize, |total, &size| total.checked_add(size))
+            .ok_or(ErrorKind::InvalidValue)?;
+    } else {
+        buffers_size[..num_planes].copy_from_slice(&plane_sizes);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,238 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Levels adjustment of YCbCr images.
+//!
+//! Surveillance pipelines often stretch the contrast of dim frames or lift their shadows
+//! before encoding them. Levels are applied in place to each channel of a YCbCr image,
+//! without converting it to RGB and back: every sample is replaced with its entry in a
+//! table computed once for each channel.
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::levels::{apply_levels, Levels, YuvLevels};
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn enhance() -> Result<(), Box<dyn error::Error>> {
+//!     dcp::initialize();
+//!
+//!     const WIDTH: u32 = 640;
+//!     const HEIGHT: u32 = 480;
+//!
+//!     let format = ImageFormat {
+//!         pixel_format: PixelFormat::Nv12,
+//!         color_space: ColorSpace::Bt709,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let mut buffer = vec![128u8; 3 * (WIDTH as usize) * (HEIGHT as usize) / 2];
+//!
+//!     // Stretch the luma of a dim frame to the whole video range, brightening mid tones
+//!     let levels = YuvLevels {
+//!         luma: Levels {
+//!             input_black: 16,
+//!             input_white: 160,
+//!             gamma: 1.4,
+//!             output_black: 16,
+//!             output_white: 235,
+//!         },
+//!         ..Default::default()
+//!     };
+//!
+//!     apply_levels(WIDTH, HEIGHT, &format, None, &mut [&mut buffer], &levels)?;
+//!
+//!     Ok(())
+//! }
+//! ```
+use crate::composite::check_layout;
+use crate::dispatcher;
+use crate::pixel_format::{self, PixelFormat};
+use crate::trace_span;
+use crate::{ErrorKind, ImageFormat};
+
+/// Levels of a channel
+///
+/// Samples between the input black and white points are stretched to the output range,
+/// after being raised to the power `1 / gamma`. Samples outside the input range are
+/// clipped. The default value leaves the channel unchanged.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Levels {
+    /// Samples at or below this value are mapped to the output black point
+    pub input_black: u8,
+    /// Samples at or above this value are mapped to the output white point.
+    /// Must be greater than the input black point
+    pub input_white: u8,
+    /// Gamma of the mid tones: values above 1.0 brighten them, values below 1.0
+    /// darken them. Must be positive
+    pub gamma: f32,
+    /// Value of the samples at or below the input black point
+    pub output_black: u8,
+    /// Value of the samples at or above the input white point. It can be lower than the
+    /// output black point, to invert the channel
+    pub output_white: u8,
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Levels {
+            input_black: 0,
+            input_white: 255,
+            gamma: 1.0,
+            output_black: 0,
+            output_white: 255,
+        }
+    }
+}
+
+impl Levels {
+    fn is_valid(&self) -> bool {
+        self.input_black < self.input_white && self.gamma > 0.0 && self.gamma.is_finite()
+    }
+
+    fn get_table(&self) -> [u8; 256] {
+        let black = f32::from(self.input_black);
+        let range = f32::from(self.input_white) - black;
+        let output_black = f32::from(self.output_black);
+        let output_range = f32::from(self.output_white) - output_black;
+
+        let mut table = [0u8; 256];
+        for (value, entry) in table.iter_mut().enumerate() {
+            let x = ((value as f32 - black) / range).clamp(0.0, 1.0);
+            *entry = (output_black + x.powf(1.0 / self.gamma) * output_range + 0.5) as u8;
+        }
+
+        table
+    }
+}
+
+/// Levels of each channel of a YCbCr image
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct YuvLevels {
+    /// Levels of the luma
+    pub luma: Levels,
+    /// Levels of the blue difference chroma
+    pub cb: Levels,
+    /// Levels of the red difference chroma
+    pub cr: Levels,
+}
+
+/// Applies levels to each channel of a YCbCr image, in place
+///
+/// The following pixel formats are supported: `I444`, `I422`, `I420` and `Nv12`.
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `format` - Image format
+/// * `strides` - An array of distances in bytes between starts of consecutive lines
+///   in each image planes
+/// * `buffers` - An array of image buffers in each color plane
+/// * `levels` - The levels to apply to each channel
+///
+/// # Errors
+///
+/// * [`NotInitialized`] if the library was not initialized before
+///
+/// * [`InvalidValue`] if the levels of a channel are not valid
+///
+/// * [`InvalidValue`] if the color space is not a YCbCr one, the image size does not
+///   match the pixel format or the number of planes is not supported
+///
+/// * [`InvalidOperation`] if the pixel format is not supported
+///
+/// * [`NotEnoughData`] if the strides array is shorter than the number of planes, or one
+///   or more buffers does not provide enough data
+///
+/// [`NotInitialized`]: ../enum.ErrorKind.html#variant.NotInitialized
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+pub fn apply_levels(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+    buffers: &mut [&mut [u8]],
+    levels: &YuvLevels,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "apply_levels",
+        width,
+        height,
+        format = ?format.pixel_format
+    );
+
+    let remap = crate::get_remapper()?;
+
+    let pixel_format = format.pixel_format as u32;
+    if dispatcher::get_pixel_format_mode(pixel_format)
+        ^ dispatcher::get_color_space_mode(format.color_space as u32)
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    match format.pixel_format {
+        PixelFormat::I444 | PixelFormat::I422 | PixelFormat::I420 | PixelFormat::Nv12 => {}
+        _ => return Err(ErrorKind::InvalidOperation),
+    }
+
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if !pixel_format::is_compatible(pixel_format, width, height, last_plane)
+        || !levels.luma.is_valid()
+        || !levels.cb.is_valid()
+        || !levels.cr.is_valid()
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let layouts = crate::get_plane_layouts(
+        format,
+        width,
+        height,
+        last_plane,
+        strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+    )?;
+    let sizes: Vec<usize> = buffers.iter().map(|buffer| buffer.len()).collect();
+    for layout in layouts.iter() {
+        check_layout(layout, width, height, &sizes)?;
+    }
+
+    let tables = [
+        levels.luma.get_table(),
+        levels.cb.get_table(),
+        levels.cr.get_table(),
+    ];
+
+    for (plane, layout) in layouts.iter().enumerate() {
+        // Interleaved chroma planes store cr right after cb
+        let (even, odd) = if layout.depth == 2 {
+            (&tables[1], &tables[2])
+        } else {
+            (&tables[plane], &tables[plane])
+        };
+
+        let row_size = layout.depth * ((width >> layout.horizontal_shift) as usize);
+        let buffer = &mut buffers[layout.buffer][layout.offset..];
+        for row in 0..((height >> layout.vertical_shift) as usize) {
+            remap(&mut buffer[row * layout.stride..][..row_size], even, odd);
+        }
+    }
+
+    Ok(())
+}
@@ -24,19 +24,25 @@
 //!
 //! It is able to convert the following pixel formats:
 //!
-//! | Source pixel format  | Destination pixel formats  |
-//! | -------------------- | -------------------------- |
-//! | ARGB                 | I420, I444, NV12           |
-//! | BGR                  | I420, I444, NV12           |
-//! | BGRA                 | I420, I444, NV12, RGB      |
-//! | I420                 | BGRA                       |
-//! | I444                 | BGRA                       |
-//! | NV12                 | BGRA                       |
-//! | RGB                  | BGRA                       |
+//! | Source pixel format | Destination pixel formats             |
+//! | ------------------- | ------------------------------------- |
+//! | ARGB                | I420, I444, NV12, YUV F32 planar      |
+//! | BGR                 | I420, I444, NV12, YUV F32 planar      |
+//! | BGR48               | BGRA, P010                            |
+//! | BGRA                | I420, I444, NV12, RGB, YUV F32 planar |
+//! | I420                | BGRA, I420, RGB F32, RGBA F32         |
+//! | I444                | BGRA, I444, RGB F32, RGBA F32         |
+//! | NV12                | BGRA, NV12, P010, RGB F32, RGBA F32   |
+//! | P010                | NV12, RGBA F16                        |
+//! | RGB                 | BGRA                                  |
+//! | RGB48               | BGRA, P010                            |
+//! | RGBA F16            | BGRA                                  |
 //!
 //! The supported color models are:
 //! * YCbCr, ITU-R Recommendation BT.601 (standard video system)
 //! * YCbCr, ITU-R Recommendation BT.709 (CSC systems)
+//! * YCbCr, ITU-R Recommendation BT.2020 with PQ transfer function (HDR10)
+//! * YCbCr, JPEG File Interchange Format (BT.601 full range), matching libjpeg
 //!
 //! # Examples
 //!
@@ -271,23 +277,76 @@
 //!     Ok(())
 //! }
 //! ```
+pub mod adjustment;
+pub mod bands;
+pub mod batch;
+#[cfg(feature = "caps")]
+pub mod caps;
+mod checksum;
+pub mod coefficients;
 mod color_space;
+pub mod composite;
+#[cfg(feature = "context")]
+pub mod context;
+#[cfg_attr(
+    any(selected_kernels, feature = "no-sse2"),
+    allow(dead_code, unused_imports)
+)]
 mod convert_image;
 mod cpu_info;
+#[cfg(feature = "cube")]
+pub mod cube;
+pub mod depth;
+pub mod diff;
 mod dispatcher;
+pub mod frame;
+pub mod gamut;
+mod graph;
+pub mod hdr;
+pub mod layout;
+pub mod levels;
+pub mod lut;
+pub mod meta;
+#[cfg(feature = "noise")]
+mod noise;
+pub mod patterns;
 mod pixel_format;
+pub mod planes;
+pub mod planner;
+#[cfg(feature = "io")]
+pub mod pnm;
+mod precision;
+pub mod range;
+pub mod reference;
+pub mod regions;
+pub mod rotation;
+pub mod rows;
+pub mod scaling;
 mod static_assert;
+pub mod stream;
+pub mod tiling;
+mod trace;
+#[cfg(feature = "y4m")]
+pub mod y4m;
 
 use cpu_info::*;
 use std::error;
 use std::fmt;
+use std::io::IoSlice;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Once};
 
 pub use color_space::ColorSpace;
-pub use pixel_format::{PixelFormat, STRIDE_AUTO};
+pub use pixel_format::{PixelFormat, MAX_DIMENSION, STRIDE_AUTO};
 
 /// An enumeration of errors.
+///
+/// New errors may be added in minor releases, so matches on this enumeration need
+/// a wildcard arm.
 #[derive(Debug)]
 #[repr(C)]
+#[non_exhaustive]
 pub enum ErrorKind {
     /// [`initialize`] was never called
     ///
@@ -300,6 +359,10 @@ pub enum ErrorKind {
     /// Not enough data was provided to the called function. Typically, provided
     /// arrays are not correctly sized
     NotEnoughData,
+    /// The operation was aborted through its [`CancellationToken`], or by a callback
+    ///
+    /// [`CancellationToken`]: ./struct.CancellationToken.html
+    Cancelled,
 }
 
 impl fmt::Display for ErrorKind {
@@ -317,6 +380,7 @@ impl fmt::Display for ErrorKind {
                 "The combination of parameters is not legal for the command"
             ),
             ErrorKind::NotEnoughData => write!(f, "Not enough data provided"),
+            ErrorKind::Cancelled => write!(f, "The operation was cancelled"),
         }
     }
 }
@@ -335,35 +399,60 @@ impl error::Error for ErrorKind {
 ///
 /// Each pixel format has one or more compatible color spaces:
 ///
-/// pixel_format      | color_space
-/// ------------------|--------------------------------------
-/// PixelFormat::Argb | ColorSpace::Lrgb
-/// PixelFormat::Bgra | ColorSpace::Lrgb
-/// PixelFormat::Bgr  | ColorSpace::Lrgb
-/// PixelFormat::Rgba | ColorSpace::Lrgb
-/// PixelFormat::Rgb  | ColorSpace::Lrgb
-/// PixelFormat::I444 | ColorSpace::Bt601, ColorSpace::Bt709
-/// PixelFormat::I422 | ColorSpace::Bt601, ColorSpace::Bt709
-/// PixelFormat::I420 | ColorSpace::Bt601, ColorSpace::Bt709
-/// PixelFormat::Nv12 | ColorSpace::Bt601, ColorSpace::Bt709
+/// pixel_format         | color_space
+/// ---------------------|--------------------------------------
+/// PixelFormat::Argb    | ColorSpace::Lrgb
+/// PixelFormat::Bgra    | ColorSpace::Lrgb
+/// PixelFormat::Bgr     | ColorSpace::Lrgb
+/// PixelFormat::Rgba    | ColorSpace::Lrgb
+/// PixelFormat::Rgb     | ColorSpace::Lrgb
+/// PixelFormat::RgbaF16 | ColorSpace::Lrgb
+/// PixelFormat::RgbF32  | ColorSpace::Lrgb
+/// PixelFormat::RgbaF32 | ColorSpace::Lrgb
+/// PixelFormat::Bgr48   | ColorSpace::Lrgb
+/// PixelFormat::Rgb48   | ColorSpace::Lrgb
+/// PixelFormat::I444    | ColorSpace::Bt601, ColorSpace::Bt709, ColorSpace::Bt2020Pq, ColorSpace::Jfif
+/// PixelFormat::I422    | ColorSpace::Bt601, ColorSpace::Bt709, ColorSpace::Bt2020Pq, ColorSpace::Jfif
+/// PixelFormat::I420    | ColorSpace::Bt601, ColorSpace::Bt709, ColorSpace::Bt2020Pq, ColorSpace::Jfif
+/// PixelFormat::Nv12    | ColorSpace::Bt601, ColorSpace::Bt709, ColorSpace::Bt2020Pq, ColorSpace::Jfif
+/// PixelFormat::P010    | ColorSpace::Bt601, ColorSpace::Bt709, ColorSpace::Bt2020Pq, ColorSpace::Jfif
+/// PixelFormat::YuvF32Planar | ColorSpace::Bt601, ColorSpace::Bt709, ColorSpace::Bt2020Pq, ColorSpace::Jfif
 ///
 /// Some pixel formats might impose additional restrictions on the accepted number of
 /// planes and the image size:
 ///
-/// pixel_format      | subsampling | w   | h   | #planes | #1     | #2     | #3
-/// ------------------|:-----------:|:---:|:---:|:-------:|:------:|:------:|:-------:
-/// PixelFormat::Argb | 4:4:4       |     |     | 1       | argb:4 |        |
-/// PixelFormat::Bgra | 4:4:4       |     |     | 1       | bgra:4 |        |
-/// PixelFormat::Bgr  | 4:4:4       |     |     | 1       | bgr:3  |        |
-/// PixelFormat::Rgba | 4:4:4       |     |     | 1       | rgba:4 |        |
-/// PixelFormat::Rgb  | 4:4:4       |     |     | 1       | rgb:3  |        |
-/// PixelFormat::I444 | 4:4:4       |     |     | 3       | y:1    | u:1    | v:1
-/// PixelFormat::I422 | 4:2:2       |  2  |     | 1, 3    | y:1    | u:1/2  | v:1/2
-/// PixelFormat::I420 | 4:2:0       |  2  |  2  | 3       | y:1    | u:1/4  | v:1/4
-/// PixelFormat::Nv12 | 4:2:0       |  2  |  2  | 1, 2    | y:1    | uv:1/2 |
+/// pixel_format         | subsampling | w   | h   | #planes | #1     | #2     | #3
+/// ---------------------|:-----------:|:---:|:---:|:-------:|:------:|:------:|:-------:
+/// PixelFormat::Argb    | 4:4:4       |     |     | 1       | argb:4 |        |
+/// PixelFormat::Bgra    | 4:4:4       |     |     | 1       | bgra:4 |        |
+/// PixelFormat::Bgr     | 4:4:4       |     |     | 1       | bgr:3  |        |
+/// PixelFormat::Rgba    | 4:4:4       |     |     | 1       | rgba:4 |        |
+/// PixelFormat::Rgb     | 4:4:4       |     |     | 1       | rgb:3  |        |
+/// PixelFormat::RgbaF16 | 4:4:4       |     |     | 1       | rgba:8 |        |
+/// PixelFormat::RgbF32  | 4:4:4       |     |     | 1       | rgb:12 |        |
+/// PixelFormat::RgbaF32 | 4:4:4       |     |     | 1       | rgba:16 |       |
+/// PixelFormat::Bgr48   | 4:4:4       |     |     | 1       | bgr:6  |        |
+/// PixelFormat::Rgb48   | 4:4:4       |     |     | 1       | rgb:6  |        |
+/// PixelFormat::I444    | 4:4:4       |     |     | 3       | y:1    | u:1    | v:1
+/// PixelFormat::I422    | 4:2:2       |  2  |     | 1, 3    | y:1    | u:1/2  | v:1/2
+/// PixelFormat::I420    | 4:2:0       |  2  |  2  | 3       | y:1    | u:1/4  | v:1/4
+/// PixelFormat::Nv12    | 4:2:0       |  2  |  2  | 1, 2    | y:1    | uv:1/2 |
+/// PixelFormat::P010    | 4:2:0       |  2  |  2  | 1, 2    | y:2    | uv:1   |
+/// PixelFormat::YuvF32Planar | 4:4:4 |     |     | 1, 3    | y:4    | u:4    | v:4
 ///
 /// The values reported in columns `w` and `h`, when specified, indicate that the described
 /// image should have width and height that are multiples of the specified values
+///
+/// Images one pixel wide or one pixel tall are valid for every pixel format without a
+/// constraint in the corresponding column, and are converted exactly like the top left
+/// column or line of a larger image, whatever the instruction set. Chroma samples of
+/// subsampled pixel formats cover two pixels in each subsampled direction, so a lone
+/// column (4:2:2 and 4:2:0) or line (4:2:0) has no chroma of its own: such images are
+/// rejected with [`InvalidValue`] and should be merged with a neighbouring column or line
+/// before being converted.
+///
+/// [`InvalidValue`]: ./enum.ErrorKind.html#variant.InvalidValue
+#[derive(Debug)]
 #[repr(C)]
 pub struct ImageFormat {
     /// Pixel format
@@ -374,8 +463,234 @@ pub struct ImageFormat {
     pub num_planes: u32,
 }
 
-type ConvertDispatcher =
-    fn(u32, u32, u32, &[usize], &[&[u8]], u32, &[usize], &mut [&mut [u8]]) -> bool;
+impl ImageFormat {
+    /// Returns a value that displays the format together with the image size and the
+    /// default strides of each plane, e.g. `Nv12 Bt601, 2 planes, 640x480, strides [640, 640]`
+    ///
+    /// If the format does not support the given size, the strides are replaced by
+    /// `invalid size`.
+    ///
+    /// # Examples
+    /// ```
+    /// use dcv_color_primitives::{ColorSpace, ImageFormat, PixelFormat};
+    ///
+    /// let format = ImageFormat {
+    ///     pixel_format: PixelFormat::I420,
+    ///     color_space: ColorSpace::Bt709,
+    ///     num_planes: 3,
+    /// };
+    ///
+    /// assert_eq!(format.to_string(), "I420 Bt709, 3 planes");
+    /// assert_eq!(
+    ///     format.layout(64, 32).to_string(),
+    ///     "I420 Bt709, 3 planes, 64x32, strides [64, 32, 32]"
+    /// );
+    /// assert_eq!(
+    ///     format.layout(63, 32).to_string(),
+    ///     "I420 Bt709, 3 planes, 63x32, invalid size"
+    /// );
+    /// ```
+    pub fn layout(&self, width: u32, height: u32) -> ImageLayout<'_> {
+        ImageLayout {
+            format: self,
+            width,
+            height,
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} {:?}, {} plane{}",
+            self.pixel_format,
+            self.color_space,
+            self.num_planes,
+            if self.num_planes == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// Displays an image format with the size of the image and the derived default strides
+///
+/// Returned by [`ImageFormat::layout`].
+///
+/// [`ImageFormat::layout`]: ./struct.ImageFormat.html#method.layout
+pub struct ImageLayout<'a> {
+    format: &'a ImageFormat,
+    width: u32,
+    height: u32,
+}
+
+impl<'a> fmt::Display for ImageLayout<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}x{}, ", self.format, self.width, self.height)?;
+
+        let last_plane = self.format.num_planes.wrapping_sub(1);
+        let layouts = if pixel_format::is_compatible(
+            self.format.pixel_format as u32,
+            self.width,
+            self.height,
+            last_plane,
+        ) {
+            get_plane_layouts(
+                self.format,
+                self.width,
+                self.height,
+                last_plane,
+                &pixel_format::DEFAULT_STRIDES,
+            )
+            .ok()
+        } else {
+            None
+        };
+
+        match layouts {
+            Some(layouts) => {
+                // Planes sharing a buffer are reported once, with the stride of the buffer
+                let strides: Vec<usize> = layouts
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, layout)| layout.buffer == i)
+                    .map(|(_, layout)| layout.stride)
+                    .collect();
+                write!(f, "strides {:?}", strides)
+            }
+            None => write!(f, "invalid size"),
+        }
+    }
+}
+
+/// Range of the samples of an image
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleRange {
+    /// Limited (studio) range: 8-bit luma is in [16, 235], 8-bit chroma in [16, 240]
+    Limited,
+    /// Full (pc) range: 8-bit samples are in [0, 255]
+    Full,
+}
+
+/// An image format carrying bit depth and sample range explicitly
+///
+/// [`ImageFormat`] implies them from the pixel format and the color space:
+/// * bit depth is 10 for `PixelFormat::P010`, 16 for `PixelFormat::RgbaF16`,
+///   `PixelFormat::Bgr48` and `PixelFormat::Rgb48`, 32 for `PixelFormat::RgbF32`,
+///   `PixelFormat::RgbaF32` and `PixelFormat::YuvF32Planar` and 8 otherwise
+/// * range is full for `ColorSpace::Lrgb` and `ColorSpace::Jfif` and limited otherwise
+///
+/// Conversions only support the implied values.
+///
+/// [`ImageFormat`]: ./struct.ImageFormat.html
+#[derive(Clone, Copy, Debug)]
+pub struct ImageFormatExt {
+    /// Pixel format
+    pub pixel_format: PixelFormat,
+    /// Color space
+    pub color_space: ColorSpace,
+    /// Number of planes
+    pub num_planes: u32,
+    /// Significant bits of each sample, for each logical plane (e.g. luma and
+    /// interleaved chroma for `PixelFormat::Nv12`). Entries past the last logical
+    /// plane are zero
+    pub bit_depths: [u32; 3],
+    /// Range of the samples
+    pub range: SampleRange,
+}
+
+impl ImageFormatExt {
+    /// Creates an image format with the bit depth and the range implied by
+    /// pixel format and color space
+    pub fn new(pixel_format: PixelFormat, color_space: ColorSpace, num_planes: u32) -> Self {
+        let mut bit_depths = [0; 3];
+        let bit_depth = pixel_format::get_bit_depth(pixel_format);
+        for depth in bit_depths
+            .iter_mut()
+            .take(pixel_format::get_logical_planes(pixel_format).len())
+        {
+            *depth = bit_depth;
+        }
+
+        let range = if let ColorSpace::Lrgb | ColorSpace::Jfif = color_space {
+            SampleRange::Full
+        } else {
+            SampleRange::Limited
+        };
+
+        ImageFormatExt {
+            pixel_format,
+            color_space,
+            num_planes,
+            bit_depths,
+            range,
+        }
+    }
+
+    /// Returns true if bit depth and range are the ones implied by pixel format
+    /// and color space
+    pub fn is_implied(&self) -> bool {
+        let implied = ImageFormatExt::new(self.pixel_format, self.color_space, self.num_planes);
+        self.bit_depths == implied.bit_depths && self.range == implied.range
+    }
+
+    /// Returns the equivalent [`ImageFormat`]
+    ///
+    /// # Errors
+    ///
+    /// * [`InvalidOperation`] if bit depth or range are not the implied ones
+    ///
+    /// [`ImageFormat`]: ./struct.ImageFormat.html
+    /// [`InvalidOperation`]: ./enum.ErrorKind.html#variant.InvalidOperation
+    pub fn to_format(&self) -> Result<ImageFormat, ErrorKind> {
+        if self.is_implied() {
+            Ok(ImageFormat {
+                pixel_format: self.pixel_format,
+                color_space: self.color_space,
+                num_planes: self.num_planes,
+            })
+        } else {
+            Err(ErrorKind::InvalidOperation)
+        }
+    }
+}
+
+impl From<&ImageFormat> for ImageFormatExt {
+    fn from(format: &ImageFormat) -> Self {
+        ImageFormatExt::new(format.pixel_format, format.color_space, format.num_planes)
+    }
+}
+
+/// Function converting an image, as registered with [`register_converter`]
+///
+/// The arguments are, in order: the width and height of the image in pixels, the index of
+/// the last source plane, the source strides and buffers, the index of the last destination
+/// plane, the destination strides and buffers. Strides equal to [`STRIDE_AUTO`] stand for
+/// tightly packed lines.
+///
+/// The function returns `false` if the buffers do not provide enough data, which is reported
+/// as [`NotEnoughData`].
+///
+/// [`register_converter`]: ./fn.register_converter.html
+/// [`STRIDE_AUTO`]: ./constant.STRIDE_AUTO.html
+/// [`NotEnoughData`]: ./enum.ErrorKind.html#variant.NotEnoughData
+pub type Converter = fn(u32, u32, u32, &[usize], &[&[u8]], u32, &[usize], &mut [&mut [u8]]) -> bool;
+
+type ConvertDispatcher = Converter;
+
+/// Conversion functions, indexed by source and destination image
+type ConverterTable = [Option<ConvertDispatcher>; dispatcher::TABLE_SIZE];
+
+type DiffDispatcher = fn(&[u8], &[u8]) -> (u64, u8);
+
+type BlendDispatcher = fn(&[u8], &mut [u8], bool);
+
+type RemapDispatcher = fn(&mut [u8], &[u8; 256], &[u8; 256]);
+
+type CrossFadeDispatcher = fn(&[u8], &mut [u8], u8);
+
+type AccumulateDispatcher = fn(&[u8], &mut [u8], u8);
+
+type CubeDispatcher = fn(&[[f32; 3]], usize, &[u32], [&[f32]; 3], [&mut [f32]; 3]);
 
 macro_rules! set_dispatcher {
     ($conv:expr, $set:ident, $src_pf:ident, $src_cs:ident, $dst_pf:ident, $dst_cs:ident, $name:ident) => {
@@ -394,51 +709,40 @@ macro_rules! set_dispatcher {
     };
 }
 
-macro_rules! set_dispatch_table {
-    ($conv:expr, $set:ident) => {
-        set_dispatcher!($conv, $set, Argb, Lrgb, Nv12, Bt601, argb_lrgb_nv12_bt601);
-        set_dispatcher!($conv, $set, Argb, Lrgb, Nv12, Bt709, argb_lrgb_nv12_bt709);
-        set_dispatcher!($conv, $set, Bgra, Lrgb, Nv12, Bt601, bgra_lrgb_nv12_bt601);
-        set_dispatcher!($conv, $set, Bgra, Lrgb, Nv12, Bt709, bgra_lrgb_nv12_bt709);
-        set_dispatcher!($conv, $set, Bgr, Lrgb, Nv12, Bt601, bgr_lrgb_nv12_bt601);
-        set_dispatcher!($conv, $set, Bgr, Lrgb, Nv12, Bt709, bgr_lrgb_nv12_bt709);
-        set_dispatcher!($conv, $set, Argb, Lrgb, I420, Bt601, argb_lrgb_i420_bt601);
-        set_dispatcher!($conv, $set, Argb, Lrgb, I420, Bt709, argb_lrgb_i420_bt709);
-        set_dispatcher!($conv, $set, Bgra, Lrgb, I420, Bt601, bgra_lrgb_i420_bt601);
-        set_dispatcher!($conv, $set, Bgra, Lrgb, I420, Bt709, bgra_lrgb_i420_bt709);
-        set_dispatcher!($conv, $set, Bgr, Lrgb, I420, Bt601, bgr_lrgb_i420_bt601);
-        set_dispatcher!($conv, $set, Bgr, Lrgb, I420, Bt709, bgr_lrgb_i420_bt709);
-        set_dispatcher!($conv, $set, Argb, Lrgb, I444, Bt601, argb_lrgb_i444_bt601);
-        set_dispatcher!($conv, $set, Argb, Lrgb, I444, Bt709, argb_lrgb_i444_bt709);
-        set_dispatcher!($conv, $set, Bgra, Lrgb, I444, Bt601, bgra_lrgb_i444_bt601);
-        set_dispatcher!($conv, $set, Bgra, Lrgb, I444, Bt709, bgra_lrgb_i444_bt709);
-        set_dispatcher!($conv, $set, Bgr, Lrgb, I444, Bt601, bgr_lrgb_i444_bt601);
-        set_dispatcher!($conv, $set, Bgr, Lrgb, I444, Bt709, bgr_lrgb_i444_bt709);
-        set_dispatcher!($conv, $set, Nv12, Bt601, Bgra, Lrgb, nv12_bt601_bgra_lrgb);
-        set_dispatcher!($conv, $set, Nv12, Bt709, Bgra, Lrgb, nv12_bt709_bgra_lrgb);
-        set_dispatcher!($conv, $set, Rgb, Lrgb, Bgra, Lrgb, rgb_lrgb_bgra_lrgb);
-        set_dispatcher!($conv, $set, I420, Bt601, Bgra, Lrgb, i420_bt601_bgra_lrgb);
-        set_dispatcher!($conv, $set, I420, Bt709, Bgra, Lrgb, i420_bt709_bgra_lrgb);
-        set_dispatcher!($conv, $set, I444, Bt601, Bgra, Lrgb, i444_bt601_bgra_lrgb);
-        set_dispatcher!($conv, $set, I444, Bt709, Bgra, Lrgb, i444_bt709_bgra_lrgb);
-        set_dispatcher!($conv, $set, Bgra, Lrgb, Rgb, Lrgb, bgra_lrgb_rgb_lrgb);
-    };
-}
+// Generated from src/kernels.in: without `only-*` features all the conversions are built,
+// otherwise only the selected ones
+include!(concat!(env!("OUT_DIR"), "/dispatch_table.rs"));
 
 struct GlobalState {
     init: bool,
     manufacturer: CpuManufacturer,
     set: InstructionSet,
-    converters: [Option<ConvertDispatcher>; dispatcher::TABLE_SIZE],
+    features: CpuFeatures,
+    converters: ConverterTable,
+    differ: DiffDispatcher,
+    blender: BlendDispatcher,
+    remapper: RemapDispatcher,
+    cross_fader: CrossFadeDispatcher,
+    accumulator: AccumulateDispatcher,
+    cube_interpolator: CubeDispatcher,
 }
 
 static mut GLOBAL_STATE: GlobalState = GlobalState {
     init: false,
     manufacturer: CpuManufacturer::Unknown,
     set: InstructionSet::X86,
+    features: NO_CPU_FEATURES,
     converters: [None; dispatcher::TABLE_SIZE],
+    differ: convert_image::x86::abs_diff_sum,
+    blender: convert_image::x86::blend_over,
+    remapper: convert_image::x86::remap,
+    cross_fader: convert_image::x86::cross_fade,
+    accumulator: convert_image::x86::accumulate,
+    cube_interpolator: convert_image::x86::interpolate_cube,
 };
 
+static INITIALIZE: Once = Once::new();
+
 /// Automatically initializes the library functions that are most appropriate for
 /// the current processor type.
 ///
@@ -454,38 +758,56 @@ static mut GLOBAL_STATE: GlobalState = GlobalState {
 /// dcp::initialize();
 /// ```
 pub fn initialize() {
-    unsafe {
-        if GLOBAL_STATE.init {
-            return;
-        }
-    }
+    // Concurrent calls, e.g. from parallel tests, must not race on the global state
+    INITIALIZE.call_once(|| {
+        let (manufacturer, set, features) = get_cpu_info();
 
-    let (manufacturer, set) = get_cpu_info();
+        unsafe {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            match set {
+                InstructionSet::X86 => {
+                    set_dispatch_table!(GLOBAL_STATE.converters, x86);
+                    GLOBAL_STATE.differ = convert_image::x86::abs_diff_sum;
+                    GLOBAL_STATE.blender = convert_image::x86::blend_over;
+                    GLOBAL_STATE.remapper = convert_image::x86::remap;
+                    GLOBAL_STATE.cross_fader = convert_image::x86::cross_fade;
+                    GLOBAL_STATE.accumulator = convert_image::x86::accumulate;
+                    GLOBAL_STATE.cube_interpolator = convert_image::x86::interpolate_cube;
+                }
+                #[cfg(not(feature = "no-sse2"))]
+                InstructionSet::Sse2 => {
+                    set_dispatch_table!(GLOBAL_STATE.converters, sse2);
+                    GLOBAL_STATE.differ = convert_image::sse2::abs_diff_sum;
+                    GLOBAL_STATE.blender = convert_image::sse2::blend_over;
+                    GLOBAL_STATE.remapper = convert_image::sse2::remap;
+                    GLOBAL_STATE.cross_fader = convert_image::sse2::cross_fade;
+                    GLOBAL_STATE.accumulator = convert_image::sse2::accumulate;
+                    GLOBAL_STATE.cube_interpolator = convert_image::sse2::interpolate_cube;
+                }
+                #[cfg(not(any(feature = "no-avx2", feature = "no-sse2")))]
+                InstructionSet::Avx2 => {
+                    set_dispatch_table!(GLOBAL_STATE.converters, avx2);
+                    GLOBAL_STATE.differ = convert_image::avx2::abs_diff_sum;
+                    GLOBAL_STATE.blender = convert_image::avx2::blend_over;
+                    GLOBAL_STATE.remapper = convert_image::avx2::remap;
+                    GLOBAL_STATE.cross_fader = convert_image::avx2::cross_fade;
+                    GLOBAL_STATE.accumulator = convert_image::avx2::accumulate;
+                    GLOBAL_STATE.cube_interpolator = convert_image::avx2::interpolate_cube;
+                }
+            }
 
-    unsafe {
-        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-        match set {
-            InstructionSet::X86 => {
+            // This is the default for arm and wasm32 targets
+            #[cfg(all(not(target_arch = "x86"), not(target_arch = "x86_64")))]
+            {
                 set_dispatch_table!(GLOBAL_STATE.converters, x86);
             }
-            InstructionSet::Sse2 => {
-                set_dispatch_table!(GLOBAL_STATE.converters, sse2);
-            }
-            InstructionSet::Avx2 => {
-                set_dispatch_table!(GLOBAL_STATE.converters, avx2);
-            }
-        }
 
-        // This is the default for arm and wasm32 targets
-        #[cfg(all(not(target_arch = "x86"), not(target_arch = "x86_64")))]
-        {
-            set_dispatch_table!(GLOBAL_STATE.converters, x86);
+            GLOBAL_STATE.manufacturer = manufacturer;
+            GLOBAL_STATE.set = set;
+            GLOBAL_STATE.features = features;
+            GLOBAL_STATE.init = true;
         }
-
-        GLOBAL_STATE.manufacturer = manufacturer;
-        GLOBAL_STATE.set = set;
-        GLOBAL_STATE.init = true;
-    }
+    });
 }
 
 /// Returns a description of the algorithms that are best for the running cpu and
@@ -530,339 +852,4651 @@ pub fn describe_acceleration() -> Result<String, ErrorKind> {
     }
 }
 
-/// Compute number of bytes required to store an image given its format, dimensions
-/// and optionally its strides
+/// Capabilities of the running cpu, and kernels selected for it by [`initialize`]
 ///
-/// # Arguments
-/// * `width` - Width of the image in pixels
-/// * `height` - Height of the image in pixels
-/// * `format` - Image format
-/// * `strides` - An array of distances in bytes between starts of consecutive lines
-///               in each image planes
-/// * `buffers_size` - An array describing the minimum number of bytes required in each
-///                    image planes
+/// [`initialize`]: ./fn.initialize.html
+#[derive(Clone, Debug)]
+pub struct CpuCapabilities {
+    /// Cpu manufacturer: `Intel`, `Amd` or `Unknown`
+    pub manufacturer: String,
+    /// Instruction set extensions supported by the cpu that the library has kernels for
+    /// (e.g. `sse2`, `avx2`), whether or not the library was built with these kernels
+    pub extensions: Vec<String>,
+    /// Instruction sets the library was built with kernels for, from the slowest to
+    /// the fastest
+    pub instruction_sets: Vec<String>,
+    /// Instruction set of the kernels selected for the running cpu
+    pub instruction_set: String,
+}
+
+/// Returns the capabilities detected on the running cpu, and the kernels selected
+/// for it
+///
+/// This is a structured counterpart of [`describe_acceleration`], suitable for support
+/// logs and bug reports. Kernels using a detected extension can be left unused, for example
+/// because the library was built without them, or because they are slower on the running
+/// cpu (avx2 kernels are not used on AMD cpus).
+///
+/// # Errors
+/// * [`NotInitialized`] if the library was not initialized before
 ///
 /// # Examples
-/// Compute how many bytes are needed to store and image of a given format and size
-/// assuming *all planes contain data which is tightly packed*:
 /// ```
 /// use dcv_color_primitives as dcp;
-/// use dcp::{get_buffers_size, ColorSpace, ImageFormat, PixelFormat};
-/// use std::error;
+/// dcp::initialize();
 ///
-/// fn compute_size_packed() -> Result<(), Box<dyn error::Error>> {
-///     dcp::initialize();
+/// let capabilities = dcp::describe_cpu().unwrap();
+/// assert!(capabilities
+///     .instruction_sets
+///     .contains(&capabilities.instruction_set));
+/// println!("{:?}", capabilities);
+/// // => CpuCapabilities { manufacturer: "Intel", extensions: ["sse2", "avx2"],
+/// //    instruction_sets: ["X86", "Sse2", "Avx2"], instruction_set: "Avx2" }
+/// ```
 ///
-///     const WIDTH: u32 = 640;
-///     const HEIGHT: u32 = 480;
-///     const NUM_PLANES: u32 = 2;
+/// [`NotInitialized`]: ./enum.ErrorKind.html#variant.NotInitialized
+/// [`describe_acceleration`]: ./fn.describe_acceleration.html
+pub fn describe_cpu() -> Result<CpuCapabilities, ErrorKind> {
+    let state = unsafe { &*std::ptr::addr_of!(GLOBAL_STATE) };
+    if !state.init {
+        return Err(ErrorKind::NotInitialized);
+    }
+
+    let mut extensions = Vec::new();
+    if state.features.sse2 {
+        extensions.push("sse2".to_owned());
+    }
+
+    if state.features.avx2 {
+        extensions.push("avx2".to_owned());
+    }
+
+    Ok(CpuCapabilities {
+        manufacturer: format!("{:?}", state.manufacturer),
+        extensions,
+        instruction_sets: built_instruction_sets()
+            .iter()
+            .map(|set| format!("{:?}", set))
+            .collect(),
+        instruction_set: format!("{:?}", state.set),
+    })
+}
+
+/// A conversion built into the library
+#[derive(Clone, Copy, Debug)]
+pub struct KernelDescription {
+    /// Pixel format of the source image
+    pub src_pixel_format: PixelFormat,
+    /// Color space of the source image
+    pub src_color_space: ColorSpace,
+    /// Pixel format of the destination image
+    pub dst_pixel_format: PixelFormat,
+    /// Color space of the destination image
+    pub dst_color_space: ColorSpace,
+    /// Cargo feature selecting the conversion (e.g. `only-nv12-bgra`)
+    pub feature: &'static str,
+}
+
+/// Returns the conversions built into the library, each one converting images directly
+/// with a kernel for every instruction set
 ///
-///     let format = ImageFormat {
-///         pixel_format: PixelFormat::Nv12,
-///         color_space: ColorSpace::Bt601,
-///         num_planes: NUM_PLANES,
-///     };
+/// Builds enabling one or more `only-<source>-<destination>` features contain just the
+/// conversions they select. Conversions added with [`register_converter`] are not listed.
 ///
-///     let sizes: &mut [usize] = &mut [0usize; NUM_PLANES as usize];
-///     get_buffers_size(WIDTH, HEIGHT, &format, None, sizes)?;
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
 ///
-///     Ok(())
+/// for kernel in dcp::describe_kernels() {
+///     println!(
+///         "{:?}-{:?} -> {:?}-{:?} ({})",
+///         kernel.src_pixel_format,
+///         kernel.src_color_space,
+///         kernel.dst_pixel_format,
+///         kernel.dst_color_space,
+///         kernel.feature
+///     );
 /// }
+/// // => Argb-Lrgb -> Nv12-Bt601 (only-argb-nv12)
+/// //    ...
 /// ```
 ///
-/// Compute how many bytes are needed to store and image of a given format and size
-/// in which *all planes have custom strides*:
-/// ```
-/// use dcv_color_primitives as dcp;
-/// use dcp::{get_buffers_size, ColorSpace, ImageFormat, PixelFormat};
-/// use std::error;
+/// [`register_converter`]: ./fn.register_converter.html
+pub fn describe_kernels() -> &'static [KernelDescription] {
+    BUILT_KERNELS
+}
+
+/// Registers a function converting images from a format to another one.
 ///
-/// fn compute_size_custom_strides() -> Result<(), Box<dyn error::Error>> {
-///     dcp::initialize();
+/// The function overrides the built-in conversion between the two formats, if any,
+/// or adds a conversion that the library does not provide. Image dimensions, number of
+/// planes and strides are validated as for built-in conversions before the function
+/// is called, and registered conversions are also used as steps of routed conversions
+/// (see [`convert_image`]).
 ///
-///     const WIDTH: u32 = 640;
-///     const HEIGHT: u32 = 480;
-///     const NUM_PLANES: u32 = 2;
-///     const Y_STRIDE: usize = (WIDTH as usize) + 1;
-///     const UV_STRIDE: usize = (WIDTH as usize) + 3;
+/// The number of planes of `src_format` and `dst_format` is ignored. Conversion plans
+/// created before the registration keep using the previous conversion, while
+/// [`convert_image_with_quality`] and [`convert_image_with_rounding`] always use
+/// built-in kernels. Error bounds reported by [`max_error`] only apply to built-in
+/// conversions.
 ///
-///     let format = ImageFormat {
-///         pixel_format: PixelFormat::Nv12,
-///         color_space: ColorSpace::Bt601,
-///         num_planes: NUM_PLANES,
-///     };
+/// # Safety
+/// The conversion table is shared by all the library functions without synchronization:
+/// you can not use any other library function (also in other threads) while the registration
+/// is in progress. Failure to do so result in undefined behaviour
 ///
-///     let strides: &[usize] = &[ Y_STRIDE, UV_STRIDE, ];
-///     let sizes: &mut [usize] = &mut [0usize; NUM_PLANES as usize];
-///     get_buffers_size(WIDTH, HEIGHT, &format, Some(strides), sizes)?;
+/// # Errors
 ///
-///     Ok(())
-/// }
-/// ```
+/// * [`NotInitialized`] if the library was not initialized before
 ///
-/// Compute how many bytes are needed to store and image of a given format and size
-/// in which *some planes have custom strides*, while *some other are assumed to
-/// contain data which is tightly packed*:
+/// * [`InvalidValue`] if the pixel format and the color space of the source or destination
+///   image format are not compatible
+///
+/// * [`InvalidOperation`] if the conversion table has no entry for the pair of formats
+///
+/// # Examples
 /// ```
 /// use dcv_color_primitives as dcp;
-/// use dcp::{get_buffers_size, ColorSpace, ImageFormat, PixelFormat, STRIDE_AUTO};
+/// use dcp::{convert_image, describe_conversion, register_converter};
+/// use dcp::{ColorSpace, ImageFormat, PixelFormat, STRIDE_AUTO};
 /// use std::error;
 ///
-/// fn compute_size_custom_strides() -> Result<(), Box<dyn error::Error>> {
-///     dcp::initialize();
-///
-///     const WIDTH: u32 = 640;
-///     const HEIGHT: u32 = 480;
-///     const NUM_PLANES: u32 = 2;
-///     const Y_STRIDE: usize = (WIDTH as usize) + 1;
-///
-///     let format = ImageFormat {
-///         pixel_format: PixelFormat::Nv12,
-///         color_space: ColorSpace::Bt601,
-///         num_planes: NUM_PLANES,
-///     };
-///
-///     let strides: &[usize] = &[ Y_STRIDE, STRIDE_AUTO, ];
-///     let sizes: &mut [usize] = &mut [0usize; NUM_PLANES as usize];
-///     get_buffers_size(WIDTH, HEIGHT, &format, Some(strides), sizes)?;
+/// fn bgra_to_rgba(
+///     width: u32,
+///     height: u32,
+///     _last_src_plane: u32,
+///     src_strides: &[usize],
+///     src_buffers: &[&[u8]],
+///     _last_dst_plane: u32,
+///     dst_strides: &[usize],
+///     dst_buffers: &mut [&mut [u8]],
+/// ) -> bool {
+///     let line = 4 * (width as usize);
+///     let stride = |stride| if stride == STRIDE_AUTO { line } else { stride };
+///     let (src_stride, dst_stride) = (stride(src_strides[0]), stride(dst_strides[0]));
+///     let (src, dst) = (src_buffers[0], &mut dst_buffers[0][..]);
+///     for y in 0..(height as usize) {
+///         match (src.get(y * src_stride..), dst.get_mut(y * dst_stride..)) {
+///             (Some(src), Some(dst)) if src.len() >= line && dst.len() >= line => {
+///                 for (s, d) in src[..line].chunks(4).zip(dst[..line].chunks_mut(4)) {
+///                     d.copy_from_slice(&[s[2], s[1], s[0], s[3]]);
+///                 }
+///             }
+///             _ => return false,
+///         }
+///     }
 ///
-///     Ok(())
+///     true
 /// }
-/// ```
 ///
-/// Default strides (e.g. the one you would set for tightly packed data) can be set
-/// using the constant [`STRIDE_AUTO`]
+/// fn convert() -> Result<(), Box<dyn error::Error>> {
+///     dcp::initialize();
 ///
-/// # Errors
+///     let bgra = ImageFormat {
+///         pixel_format: PixelFormat::Bgra,
+///         color_space: ColorSpace::Lrgb,
+///         num_planes: 1,
+///     };
+///     let rgba = ImageFormat {
+///         pixel_format: PixelFormat::Rgba,
+///         color_space: ColorSpace::Lrgb,
+///         num_planes: 1,
+///     };
+///     let rgb = ImageFormat {
+///         pixel_format: PixelFormat::Rgb,
+///         color_space: ColorSpace::Lrgb,
+///         num_planes: 1,
+///     };
 ///
-/// * [`InvalidValue`] if `width` or `height` violate the [`size constraints`] that might by
-///   imposed by the image pixel format
+///     // No other library function is running
+///     unsafe { register_converter(&bgra, &rgba, bgra_to_rgba)? };
 ///
-/// * [`InvalidValue`] if the image format has a number of planes which is not compatible
-///   with its pixel format
+///     let src_buffers: &[&[u8]] = &[&[1, 2, 3, 4, 5, 6, 7, 8]];
+///     let mut dst_data = [0u8; 8];
+///     let dst_buffers: &mut [&mut [u8]] = &mut [&mut dst_data];
+///     convert_image(2, 1, &bgra, None, src_buffers, &rgba, None, dst_buffers)?;
+///     assert_eq!(dst_data, [3, 2, 1, 4, 7, 6, 5, 8]);
 ///
-/// * [`NotEnoughData`] if the strides array is not `None` and its length is less than the
-///   image format number of planes
+///     // Registered conversions are used by routed conversions too
+///     assert_eq!(
+///         describe_conversion(2, 1, &rgb, &rgba)?,
+///         "{route:[Rgb-Lrgb,Bgra-Lrgb,Rgba-Lrgb]}"
+///     );
 ///
-/// * [`NotEnoughData`] if the buffers_sizes array is not `None` and its length is less than the
-///   image format number of planes
+///     Ok(())
+/// }
+/// # convert().unwrap();
+/// ```
 ///
-/// [`InvalidValue`]: ./enum.ErrorKind.html#variant.NotInitialized
-/// [`NotEnoughData`]: ./enum.ErrorKind.html#variant.NotEnoughData
-/// [`size constraints`]: ./struct.ImageFormat.html#note
-/// [`STRIDE_AUTO`]: ./constant.STRIDE_AUTO.html
-pub fn get_buffers_size(
-    width: u32,
-    height: u32,
-    format: &ImageFormat,
-    strides: Option<&[usize]>,
-    buffers_size: &mut [usize],
+/// [`convert_image`]: ./fn.convert_image.html
+/// [`convert_image_with_quality`]: ./fn.convert_image_with_quality.html
+/// [`convert_image_with_rounding`]: ./fn.convert_image_with_rounding.html
+/// [`max_error`]: ./fn.max_error.html
+/// [`NotInitialized`]: ./enum.ErrorKind.html#variant.NotInitialized
+/// [`InvalidValue`]: ./enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ./enum.ErrorKind.html#variant.InvalidOperation
+pub unsafe fn register_converter(
+    src_format: &ImageFormat,
+    dst_format: &ImageFormat,
+    converter: Converter,
 ) -> Result<(), ErrorKind> {
-    let pixel_format = format.pixel_format as u32;
+    if !GLOBAL_STATE.init {
+        return Err(ErrorKind::NotInitialized);
+    }
+
+    let src_pixel_format = src_format.pixel_format as u32;
+    let dst_pixel_format = dst_format.pixel_format as u32;
+    let src_color_space = src_format.color_space as u32;
+    let dst_color_space = dst_format.color_space as u32;
+    let src_pf_mode = dispatcher::get_pixel_format_mode(src_pixel_format);
+    let dst_pf_mode = dispatcher::get_pixel_format_mode(dst_pixel_format);
+    if (src_pf_mode ^ dispatcher::get_color_space_mode(src_color_space))
+        | (dst_pf_mode ^ dispatcher::get_color_space_mode(dst_color_space))
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let src_index = dispatcher::get_image_index(src_pixel_format, src_color_space, src_pf_mode);
+    let dst_index = dispatcher::get_image_index(dst_pixel_format, dst_color_space, dst_pf_mode);
+    let index = dispatcher::get_index(src_index, dst_index);
+    if index >= dispatcher::TABLE_SIZE {
+        return Err(ErrorKind::InvalidOperation);
+    }
+
+    GLOBAL_STATE.converters[index] = Some(converter);
+
+    Ok(())
+}
+
+/// Result of checking an accelerated kernel against its portable implementation
+#[derive(Clone, Debug)]
+pub struct KernelReport {
+    /// Name of the kernel: source and destination formats for conversions
+    /// (e.g. `Bgra-Lrgb:Nv12-Bt601`), `diff`, `blend`, `blend-premultiplied`, `remap`,
+    /// `cross-fade`, `accumulate` or `cube` otherwise
+    pub kernel: String,
+    /// Instruction set used by the accelerated kernel
+    pub instruction_set: String,
+    /// Number of output values that differ from the ones of the portable implementation
+    /// by more than the precision guaranteed by the conversions: two code values for integer
+    /// and half float samples, 1e-5 for single precision floats
+    pub mismatches: usize,
+    /// Time taken by the portable implementation divided by the time taken by the
+    /// accelerated kernel
+    pub speedup: f64,
+}
+
+/// Width of the images used by [`self_test`]
+const SELF_TEST_WIDTH: u32 = 640;
+
+/// Height of the images used by [`self_test`]
+const SELF_TEST_HEIGHT: u32 = 480;
+
+/// Number of times each kernel is timed by [`self_test`]
+const SELF_TEST_RUNS: u32 = 4;
+
+/// Pseudo-random generator for the self test input, so that runs are reproducible
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn xorshift(state: &mut u32) -> u32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state
+}
+
+/// Returns a random single precision float in [0, 1]
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn random_unit(state: &mut u32) -> f32 {
+    (xorshift(state) >> 8) as f32 / ((1 << 24) - 1) as f32
+}
+
+/// Fills a buffer with random samples in the domain of a pixel format. Yuv samples are
+/// kept in the studio range.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn fill_random(pixel_format: PixelFormat, buffer: &mut [u8], state: &mut u32) {
+    match pixel_format {
+        PixelFormat::I444 | PixelFormat::I422 | PixelFormat::I420 | PixelFormat::Nv12 => {
+            for byte in buffer.iter_mut() {
+                *byte = (16 + xorshift(state) % 220) as u8;
+            }
+        }
+        PixelFormat::P010 => {
+            for sample in buffer.chunks_exact_mut(2) {
+                let value = ((64 + xorshift(state) % 877) << 6) as u16;
+                sample.copy_from_slice(&value.to_le_bytes());
+            }
+        }
+        PixelFormat::RgbaF16 => {
+            // Half floats in [0, 1]
+            for sample in buffer.chunks_exact_mut(2) {
+                let value = (xorshift(state) % 0x3C01) as u16;
+                sample.copy_from_slice(&value.to_le_bytes());
+            }
+        }
+        PixelFormat::RgbF32 | PixelFormat::RgbaF32 | PixelFormat::YuvF32Planar => {
+            for sample in buffer.chunks_exact_mut(4) {
+                let value = random_unit(state);
+                sample.copy_from_slice(&value.to_bits().to_le_bytes());
+            }
+        }
+        _ => {
+            for byte in buffer.iter_mut() {
+                *byte = xorshift(state) as u8;
+            }
+        }
+    }
+}
+
+/// Counts the output values that differ by more than the precision guaranteed by the
+/// conversions: two code values for integer and half float samples, 1e-5 for single
+/// precision floats
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn count_mismatches(pixel_format: PixelFormat, a: &[u8], b: &[u8]) -> usize {
+    match pixel_format {
+        PixelFormat::RgbF32 | PixelFormat::RgbaF32 | PixelFormat::YuvF32Planar => a
+            .chunks_exact(4)
+            .zip(b.chunks_exact(4))
+            .filter(|(x, y)| {
+                let x = f32::from_bits(u32::from_le_bytes([x[0], x[1], x[2], x[3]]));
+                let y = f32::from_bits(u32::from_le_bytes([y[0], y[1], y[2], y[3]]));
+                (x - y).is_nan() || (x - y).abs() > 1e-5
+            })
+            .count(),
+        PixelFormat::P010 | PixelFormat::RgbaF16 | PixelFormat::Bgr48 | PixelFormat::Rgb48 => a
+            .chunks_exact(2)
+            .zip(b.chunks_exact(2))
+            .filter(|(x, y)| {
+                let x = i32::from(u16::from_le_bytes([x[0], x[1]]));
+                let y = i32::from(u16::from_le_bytes([y[0], y[1]]));
+                (x - y).abs() > 2
+            })
+            .count(),
+        _ => a
+            .iter()
+            .zip(b.iter())
+            .filter(|(&x, &y)| (i32::from(x) - i32::from(y)).abs() > 2)
+            .count(),
+    }
+}
+
+/// Runs a kernel several times, returning the elapsed time in seconds
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn time_kernel<F: FnMut()>(mut kernel: F) -> f64 {
+    let start = std::time::Instant::now();
+    for _ in 0..SELF_TEST_RUNS {
+        kernel();
+    }
+
+    start.elapsed().as_secs_f64().max(1e-9)
+}
+
+/// Checks the accelerated kernels against their portable implementation
+///
+/// Each kernel available for the running cpu is run on pseudo-random data with every
+/// accelerated instruction set the cpu supports, and its output is compared with the one of
+/// the portable implementation. The comparison also measures how much faster the accelerated
+/// kernel is. Applications deployed on unusual cpus can call this function once at startup,
+/// and refuse to proceed if any kernel reports mismatches.
+///
+/// The returned list is empty when no accelerated instruction set is available.
+///
+/// # Errors
+/// * [`NotInitialized`] if the library was not initialized before
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// dcp::initialize();
+///
+/// for report in dcp::self_test().unwrap() {
+///     assert_eq!(report.mismatches, 0, "{}", report.kernel);
+///     println!("{} {}: {:.1}x", report.kernel, report.instruction_set, report.speedup);
+/// }
+/// // => Bgra-Lrgb:Nv12-Bt601 Avx2: 9.2x
+/// ```
+///
+/// [`NotInitialized`]: ./enum.ErrorKind.html#variant.NotInitialized
+pub fn self_test() -> Result<Vec<KernelReport>, ErrorKind> {
+    let _span = trace_span!(DEBUG, "self_test");
+
+    unsafe {
+        if !GLOBAL_STATE.init {
+            return Err(ErrorKind::NotInitialized);
+        }
+    }
+
+    #[cfg_attr(
+        not(any(target_arch = "x86", target_arch = "x86_64")),
+        allow(unused_mut)
+    )]
+    let mut reports = Vec::new();
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        const PIXEL_FORMATS: [PixelFormat; 16] = [
+            PixelFormat::Argb,
+            PixelFormat::Bgra,
+            PixelFormat::Bgr,
+            PixelFormat::Rgba,
+            PixelFormat::Rgb,
+            PixelFormat::I444,
+            PixelFormat::I422,
+            PixelFormat::I420,
+            PixelFormat::Nv12,
+            PixelFormat::P010,
+            PixelFormat::YuvF32Planar,
+            PixelFormat::RgbaF16,
+            PixelFormat::RgbF32,
+            PixelFormat::RgbaF32,
+            PixelFormat::Bgr48,
+            PixelFormat::Rgb48,
+        ];
+        const COLOR_SPACES: [ColorSpace; 5] = [
+            ColorSpace::Lrgb,
+            ColorSpace::Bt601,
+            ColorSpace::Bt709,
+            ColorSpace::Bt2020Pq,
+            ColorSpace::Jfif,
+        ];
+
+        type DispatchTable = [Option<ConvertDispatcher>; dispatcher::TABLE_SIZE];
+        type Kernels = (
+            &'static str,
+            DispatchTable,
+            DiffDispatcher,
+            BlendDispatcher,
+            RemapDispatcher,
+            CrossFadeDispatcher,
+            AccumulateDispatcher,
+            CubeDispatcher,
+        );
+
+        let mut portable: DispatchTable = [None; dispatcher::TABLE_SIZE];
+        set_dispatch_table!(portable, x86);
+
+        #[cfg_attr(feature = "no-sse2", allow(unused_mut))]
+        let mut accelerated: Vec<Kernels> = Vec::new();
+        match unsafe { &*std::ptr::addr_of!(GLOBAL_STATE.set) } {
+            InstructionSet::X86 => {}
+            #[cfg(not(feature = "no-sse2"))]
+            InstructionSet::Sse2 => {
+                let mut sse2: DispatchTable = [None; dispatcher::TABLE_SIZE];
+                set_dispatch_table!(sse2, sse2);
+                accelerated.push((
+                    "Sse2",
+                    sse2,
+                    convert_image::sse2::abs_diff_sum,
+                    convert_image::sse2::blend_over,
+                    convert_image::sse2::remap,
+                    convert_image::sse2::cross_fade,
+                    convert_image::sse2::accumulate,
+                    convert_image::sse2::interpolate_cube,
+                ));
+            }
+            #[cfg(not(any(feature = "no-avx2", feature = "no-sse2")))]
+            InstructionSet::Avx2 => {
+                let mut sse2: DispatchTable = [None; dispatcher::TABLE_SIZE];
+                set_dispatch_table!(sse2, sse2);
+                accelerated.push((
+                    "Sse2",
+                    sse2,
+                    convert_image::sse2::abs_diff_sum,
+                    convert_image::sse2::blend_over,
+                    convert_image::sse2::remap,
+                    convert_image::sse2::cross_fade,
+                    convert_image::sse2::accumulate,
+                    convert_image::sse2::interpolate_cube,
+                ));
+
+                let mut avx2: DispatchTable = [None; dispatcher::TABLE_SIZE];
+                set_dispatch_table!(avx2, avx2);
+                accelerated.push((
+                    "Avx2",
+                    avx2,
+                    convert_image::avx2::abs_diff_sum,
+                    convert_image::avx2::blend_over,
+                    convert_image::avx2::remap,
+                    convert_image::avx2::cross_fade,
+                    convert_image::avx2::accumulate,
+                    convert_image::avx2::interpolate_cube,
+                ));
+            }
+        }
+
+        if accelerated.is_empty() {
+            return Ok(reports);
+        }
+
+        // Every image has a single plane and tightly packed lines
+        let strides = &pixel_format::DEFAULT_STRIDES;
+        let get_size = |pixel_format: PixelFormat| {
+            let mut size = [0usize; 1];
+            pixel_format::get_buffers_size(
+                pixel_format as u32,
+                SELF_TEST_WIDTH,
+                SELF_TEST_HEIGHT,
+                0,
+                strides,
+                &mut size,
+            )
+            .map(|_| size[0])
+        };
+
+        let mut state = 0x2545_F491;
+        for (src_pixel_format, src_color_space, dst_pixel_format, dst_color_space) in
+            PIXEL_FORMATS.iter().flat_map(|&src_pf| {
+                COLOR_SPACES.iter().flat_map(move |&src_cs| {
+                    PIXEL_FORMATS.iter().flat_map(move |&dst_pf| {
+                        COLOR_SPACES
+                            .iter()
+                            .map(move |&dst_cs| (src_pf, src_cs, dst_pf, dst_cs))
+                    })
+                })
+            })
+        {
+            let src_mode = dispatcher::get_pixel_format_mode(src_pixel_format as u32);
+            let dst_mode = dispatcher::get_pixel_format_mode(dst_pixel_format as u32);
+            if src_mode ^ dispatcher::get_color_space_mode(src_color_space as u32)
+                || dst_mode ^ dispatcher::get_color_space_mode(dst_color_space as u32)
+            {
+                continue;
+            }
+
+            let index = dispatcher::get_index(
+                dispatcher::get_image_index(
+                    src_pixel_format as u32,
+                    src_color_space as u32,
+                    src_mode,
+                ),
+                dispatcher::get_image_index(
+                    dst_pixel_format as u32,
+                    dst_color_space as u32,
+                    dst_mode,
+                ),
+            );
+            let reference = match portable[index] {
+                Some(reference) => reference,
+                None => continue,
+            };
+
+            let mut src = vec![0u8; get_size(src_pixel_format)?];
+            fill_random(src_pixel_format, &mut src, &mut state);
+            let dst_size = get_size(dst_pixel_format)?;
+            let mut expected = vec![0u8; dst_size];
+            let mut output = vec![0u8; dst_size];
+            let kernel = format!(
+                "{:?}-{:?}:{:?}-{:?}",
+                src_pixel_format, src_color_space, dst_pixel_format, dst_color_space
+            );
+
+            let reference_time = time_kernel(|| {
+                reference(
+                    SELF_TEST_WIDTH,
+                    SELF_TEST_HEIGHT,
+                    0,
+                    strides,
+                    &[&src],
+                    0,
+                    strides,
+                    &mut [&mut expected],
+                );
+            });
+
+            for &(instruction_set, ref converters, _, _, _, _, _, _) in accelerated.iter() {
+                if let Some(converter) = converters[index] {
+                    let time = time_kernel(|| {
+                        converter(
+                            SELF_TEST_WIDTH,
+                            SELF_TEST_HEIGHT,
+                            0,
+                            strides,
+                            &[&src],
+                            0,
+                            strides,
+                            &mut [&mut output],
+                        );
+                    });
+
+                    reports.push(KernelReport {
+                        kernel: kernel.clone(),
+                        instruction_set: instruction_set.to_owned(),
+                        mismatches: count_mismatches(dst_pixel_format, &expected, &output),
+                        speedup: reference_time / time,
+                    });
+                }
+            }
+        }
+
+        // Difference and blending kernels work on packed 32 bits per pixel images
+        let size = 4 * (SELF_TEST_WIDTH as usize) * (SELF_TEST_HEIGHT as usize);
+        let mut a = vec![0u8; size];
+        let mut b = vec![0u8; size];
+        fill_random(PixelFormat::Bgra, &mut a, &mut state);
+        fill_random(PixelFormat::Bgra, &mut b, &mut state);
+
+        let mut expected = (0, 0);
+        let reference_time = time_kernel(|| {
+            expected = convert_image::x86::abs_diff_sum(&a, &b);
+        });
+        for &(instruction_set, _, differ, _, _, _, _, _) in accelerated.iter() {
+            let mut output = (0, 0);
+            let time = time_kernel(|| output = differ(&a, &b));
+            reports.push(KernelReport {
+                kernel: "diff".to_owned(),
+                instruction_set: instruction_set.to_owned(),
+                mismatches: (output != expected) as usize,
+                speedup: reference_time / time,
+            });
+        }
+
+        for &premultiplied in [false, true].iter() {
+            let mut expected = b.clone();
+            let reference_time = time_kernel(|| {
+                expected.copy_from_slice(&b);
+                convert_image::x86::blend_over(&a, &mut expected, premultiplied);
+            });
+            for &(instruction_set, _, _, blender, _, _, _, _) in accelerated.iter() {
+                let mut output = b.clone();
+                let time = time_kernel(|| {
+                    output.copy_from_slice(&b);
+                    blender(&a, &mut output, premultiplied);
+                });
+                reports.push(KernelReport {
+                    kernel: if premultiplied {
+                        "blend-premultiplied".to_owned()
+                    } else {
+                        "blend".to_owned()
+                    },
+                    instruction_set: instruction_set.to_owned(),
+                    mismatches: count_mismatches(PixelFormat::Bgra, &expected, &output),
+                    speedup: reference_time / time,
+                });
+            }
+        }
+
+        // Interleaved chroma uses a table for each channel
+        let mut tables = [[0u8; 256]; 2];
+        fill_random(PixelFormat::Bgra, &mut tables[0], &mut state);
+        fill_random(PixelFormat::Bgra, &mut tables[1], &mut state);
+
+        let mut expected = a.clone();
+        let reference_time = time_kernel(|| {
+            expected.copy_from_slice(&a);
+            convert_image::x86::remap(&mut expected, &tables[0], &tables[1]);
+        });
+        for &(instruction_set, _, _, _, remapper, _, _, _) in accelerated.iter() {
+            let mut output = a.clone();
+            let time = time_kernel(|| {
+                output.copy_from_slice(&a);
+                remapper(&mut output, &tables[0], &tables[1]);
+            });
+            reports.push(KernelReport {
+                kernel: "remap".to_owned(),
+                instruction_set: instruction_set.to_owned(),
+                mismatches: expected
+                    .iter()
+                    .zip(output.iter())
+                    .filter(|(x, y)| x != y)
+                    .count(),
+                speedup: reference_time / time,
+            });
+        }
+
+        let alpha = (state >> 24) as u8;
+        let mut expected = b.clone();
+        let reference_time = time_kernel(|| {
+            expected.copy_from_slice(&b);
+            convert_image::x86::cross_fade(&a, &mut expected, alpha);
+        });
+        for &(instruction_set, _, _, _, _, cross_fader, _, _) in accelerated.iter() {
+            let mut output = b.clone();
+            let time = time_kernel(|| {
+                output.copy_from_slice(&b);
+                cross_fader(&a, &mut output, alpha);
+            });
+            reports.push(KernelReport {
+                kernel: "cross-fade".to_owned(),
+                instruction_set: instruction_set.to_owned(),
+                mismatches: expected
+                    .iter()
+                    .zip(output.iter())
+                    .filter(|(x, y)| x != y)
+                    .count(),
+                speedup: reference_time / time,
+            });
+        }
+
+        let mut expected = b.clone();
+        let reference_time = time_kernel(|| {
+            expected.copy_from_slice(&b);
+            convert_image::x86::accumulate(&a, &mut expected, alpha);
+        });
+        for &(instruction_set, _, _, _, _, _, accumulator, _) in accelerated.iter() {
+            let mut output = b.clone();
+            let time = time_kernel(|| {
+                output.copy_from_slice(&b);
+                accumulator(&a, &mut output, alpha);
+            });
+            reports.push(KernelReport {
+                kernel: "accumulate".to_owned(),
+                instruction_set: instruction_set.to_owned(),
+                mismatches: expected
+                    .iter()
+                    .zip(output.iter())
+                    .filter(|(x, y)| x != y)
+                    .count(),
+                speedup: reference_time / time,
+            });
+        }
+
+        // Look-up table of 17 points along each axis, sampled at random points
+        const CUBE_SIZE: usize = 17;
+        let mut table = vec![[0f32; 3]; CUBE_SIZE * CUBE_SIZE * CUBE_SIZE];
+        for value in table.iter_mut().flatten() {
+            *value = random_unit(&mut state);
+        }
+
+        let count = (SELF_TEST_WIDTH as usize) * (SELF_TEST_HEIGHT as usize);
+        let cells = CUBE_SIZE - 1;
+        let index: Vec<u32> = (0..count)
+            .map(|_| {
+                let r = xorshift(&mut state) as usize % cells;
+                let g = xorshift(&mut state) as usize % cells;
+                let b = xorshift(&mut state) as usize % cells;
+                (r + CUBE_SIZE * (g + CUBE_SIZE * b)) as u32
+            })
+            .collect();
+        let mut fractions = vec![vec![0f32; count]; 3];
+        for fraction in fractions.iter_mut().flatten() {
+            *fraction = random_unit(&mut state);
+        }
+
+        let fractions = [&fractions[0][..], &fractions[1][..], &fractions[2][..]];
+        let mut expected = vec![vec![0f32; count]; 3];
+        let reference_time = time_kernel(|| {
+            if let [r, g, b] = &mut expected[..] {
+                convert_image::x86::interpolate_cube(
+                    &table,
+                    CUBE_SIZE,
+                    &index,
+                    fractions,
+                    [r, g, b],
+                );
+            }
+        });
+        for &(instruction_set, _, _, _, _, _, _, cube_interpolator) in accelerated.iter() {
+            let mut output = vec![vec![0f32; count]; 3];
+            let time = time_kernel(|| {
+                if let [r, g, b] = &mut output[..] {
+                    cube_interpolator(&table, CUBE_SIZE, &index, fractions, [r, g, b]);
+                }
+            });
+            reports.push(KernelReport {
+                kernel: "cube".to_owned(),
+                instruction_set: instruction_set.to_owned(),
+                mismatches: expected
+                    .iter()
+                    .flatten()
+                    .zip(output.iter().flatten())
+                    .filter(|(x, y)| (*x - *y).is_nan() || (*x - *y).abs() > 1e-5)
+                    .count(),
+                speedup: reference_time / time,
+            });
+        }
+    }
+
+    Ok(reports)
+}
+
+/// Returns a description of the conversions used to convert an image from a format
+/// to another one
+///
+/// The description lists the pixel format and color space of the source image, of each
+/// intermediate image and of the destination image. When the formats can be converted
+/// directly, there are no intermediate images.
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `dst_format` - Destination image format
+///
+/// # Errors
+/// The same errors reported by [`convert_image`] for the given parameters,
+/// except the ones related to strides and buffers
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{ColorSpace, ImageFormat, PixelFormat};
+///
+/// dcp::initialize();
+///
+/// let src_format = ImageFormat {
+///     pixel_format: PixelFormat::Rgb,
+///     color_space: ColorSpace::Lrgb,
+///     num_planes: 1,
+/// };
+///
+/// let dst_format = ImageFormat {
+///     pixel_format: PixelFormat::Nv12,
+///     color_space: ColorSpace::Bt601,
+///     num_planes: 2,
+/// };
+///
+/// match dcp::describe_conversion(640, 480, &src_format, &dst_format) {
+///     Ok(description) => println!("{}", description),
+///     Err(error) => println!("Unable to describe the conversion: {}", error),
+/// }
+/// // => {route:[Rgb-Lrgb,Bgra-Lrgb,Nv12-Bt601]}
+/// ```
+///
+/// [`convert_image`]: ./fn.convert_image.html
+pub fn describe_conversion(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    dst_format: &ImageFormat,
+) -> Result<String, ErrorKind> {
+    let route = match get_converter(width, height, src_format, dst_format) {
+        Ok(_) => Vec::new(),
+        Err(ErrorKind::InvalidOperation) => {
+            graph::find_route(get_converters()?, width, height, src_format, dst_format)?
+        }
+        Err(error) => return Err(error),
+    };
+
+    let images: Vec<String> = std::iter::once(src_format)
+        .chain(route.iter())
+        .chain(std::iter::once(dst_format))
+        .map(|format| format!("{:?}-{:?}", format.pixel_format, format.color_space))
+        .collect();
+
+    Ok(format!("{{route:[{}]}}", images.join(",")))
+}
+
+/// Cost of the conversions between two image formats, as computed by [`negotiate`].
+///
+/// Scores are ordered from the cheapest to the most expensive: a score with fewer
+/// conversions is always cheaper, and among scores with the same number of conversions
+/// the ones with fewer subsampled intermediate images are cheaper.
+///
+/// [`negotiate`]: ./fn.negotiate.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Score {
+    /// Number of conversions: zero when the image formats are the same, one when they
+    /// can be converted directly, more when the conversion is routed
+    pub conversions: u32,
+    /// Number of intermediate images with chroma subsampling
+    pub subsampled_images: u32,
+}
+
+fn get_score(src_format: &ImageFormat, dst_format: &ImageFormat) -> Option<Score> {
+    let route = match get_converter(0, 0, src_format, dst_format) {
+        Ok(_) => Vec::new(),
+        Err(ErrorKind::InvalidOperation) => {
+            if src_format.pixel_format as u32 == dst_format.pixel_format as u32
+                && src_format.color_space as u32 == dst_format.color_space as u32
+                && src_format.num_planes == dst_format.num_planes
+            {
+                return Some(Score {
+                    conversions: 0,
+                    subsampled_images: 0,
+                });
+            }
+
+            graph::find_route(get_converters().ok()?, 0, 0, src_format, dst_format).ok()?
+        }
+        Err(_) => return None,
+    };
+
+    Some(Score {
+        conversions: route.len() as u32 + 1,
+        subsampled_images: route
+            .iter()
+            .filter(|format| pixel_format::is_subsampled(format.pixel_format))
+            .count() as u32,
+    })
+}
+
+/// Picks the cheapest pair of image formats to convert between, e.g. to choose the formats
+/// of a capture device and of an encoder among the ones they support
+///
+/// Every source candidate is paired with every destination candidate, and the pair with
+/// the lowest [`Score`] is returned. Among pairs with the same score, the earliest source
+/// candidate is preferred, then the earliest destination candidate. A pair of identical
+/// formats needs no conversion at all.
+///
+/// The image size is not known, so the [`size constraints`] of the formats are not
+/// checked.
+///
+/// Returns `None` if the library was not initialized, or if no candidate pair can be
+/// converted.
+///
+/// # Arguments
+/// * `src_candidates` - Image formats the source can provide, in order of preference
+/// * `dst_candidates` - Image formats the destination can accept, in order of preference
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{ColorSpace, ImageFormat, PixelFormat};
+///
+/// dcp::initialize();
+///
+/// let format = |pixel_format, color_space, num_planes| ImageFormat {
+///     pixel_format,
+///     color_space,
+///     num_planes,
+/// };
+///
+/// let src_candidates = [
+///     format(PixelFormat::Rgb, ColorSpace::Lrgb, 1),
+///     format(PixelFormat::Bgra, ColorSpace::Lrgb, 1),
+/// ];
+/// let dst_candidates = [
+///     format(PixelFormat::I420, ColorSpace::Bt709, 3),
+///     format(PixelFormat::Nv12, ColorSpace::Bt709, 2),
+/// ];
+///
+/// if let Some((src_format, dst_format, score)) = dcp::negotiate(&src_candidates, &dst_candidates) {
+///     println!("{:?} -> {:?}: {:?}", src_format.pixel_format, dst_format.pixel_format, score);
+/// }
+/// // => Bgra -> I420: Score { conversions: 1, subsampled_images: 0 }
+/// ```
+///
+/// [`Score`]: ./struct.Score.html
+/// [`size constraints`]: ./struct.ImageFormat.html#note
+pub fn negotiate(
+    src_candidates: &[ImageFormat],
+    dst_candidates: &[ImageFormat],
+) -> Option<(ImageFormat, ImageFormat, Score)> {
+    let mut best: Option<(&ImageFormat, &ImageFormat, Score)> = None;
+    for src_format in src_candidates {
+        for dst_format in dst_candidates {
+            if let Some(score) = get_score(src_format, dst_format) {
+                if best.as_ref().map(|best| score < best.2).unwrap_or(true) {
+                    best = Some((src_format, dst_format, score));
+                }
+            }
+        }
+    }
+
+    let copy = |format: &ImageFormat| ImageFormat {
+        pixel_format: format.pixel_format,
+        color_space: format.color_space,
+        num_planes: format.num_planes,
+    };
+
+    best.map(|(src_format, dst_format, score)| (copy(src_format), copy(dst_format), score))
+}
+
+/// Color space metadata carried by a container or a bitstream, used by
+/// [`suggest_color_space`]
+///
+/// [`suggest_color_space`]: ./fn.suggest_color_space.html
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ColorSpaceHints {
+    /// Color space signalled explicitly, which overrides any other hint
+    pub color_space: Option<ColorSpace>,
+    /// Samples use the full range, e.g. the `full_range_flag` of the H.264 VUI is set
+    pub full_range: bool,
+    /// The transfer function is SMPTE ST 2084 (perceptual quantizer)
+    pub pq_transfer: bool,
+}
+
+/// Suggests the most likely color space of a yuv frame, when the source does not
+/// signal it
+///
+/// The suggestion follows the conventions of broadcast and streaming video:
+/// * an explicit color space in `hints` is returned as it is
+/// * full range frames are `ColorSpace::Jfif`, the only full range yuv color space
+/// * frames with the perceptual quantizer transfer function are `ColorSpace::Bt2020Pq`
+/// * otherwise the frame size decides: standard definition frames (up to 1024x576)
+///   are `ColorSpace::Bt601`, high definition frames (up to 2048x1152) are
+///   `ColorSpace::Bt709` and larger, ultra high definition, frames are
+///   `ColorSpace::Bt2020Pq`
+///
+/// # Arguments
+/// * `width` - Width of the frame in pixels
+/// * `height` - Height of the frame in pixels
+/// * `hints` - Color space metadata available for the frame
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{suggest_color_space, ColorSpace, ColorSpaceHints};
+///
+/// let hints = ColorSpaceHints::default();
+/// assert!(matches!(suggest_color_space(720, 576, &hints), ColorSpace::Bt601));
+/// assert!(matches!(suggest_color_space(1920, 1080, &hints), ColorSpace::Bt709));
+///
+/// // Metadata takes precedence over the frame size
+/// let hints = ColorSpaceHints {
+///     color_space: Some(ColorSpace::Bt601),
+///     ..Default::default()
+/// };
+/// assert!(matches!(suggest_color_space(1920, 1080, &hints), ColorSpace::Bt601));
+/// ```
+pub fn suggest_color_space(width: u32, height: u32, hints: &ColorSpaceHints) -> ColorSpace {
+    const MAX_SD_SIZE: (u32, u32) = (1024, 576);
+    const MAX_HD_SIZE: (u32, u32) = (2048, 1152);
+
+    if let Some(color_space) = hints.color_space {
+        color_space
+    } else if hints.full_range {
+        ColorSpace::Jfif
+    } else if hints.pq_transfer {
+        ColorSpace::Bt2020Pq
+    } else if width <= MAX_SD_SIZE.0 && height <= MAX_SD_SIZE.1 {
+        ColorSpace::Bt601
+    } else if width <= MAX_HD_SIZE.0 && height <= MAX_HD_SIZE.1 {
+        ColorSpace::Bt709
+    } else {
+        ColorSpace::Bt2020Pq
+    }
+}
+
+/// Compute number of bytes required to store an image given its format, dimensions
+/// and optionally its strides
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `format` - Image format
+/// * `strides` - An array of distances in bytes between starts of consecutive lines
+///               in each image planes
+/// * `buffers_size` - An array describing the minimum number of bytes required in each
+///                    image planes
+///
+/// # Examples
+/// Compute how many bytes are needed to store and image of a given format and size
+/// assuming *all planes contain data which is tightly packed*:
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{get_buffers_size, ColorSpace, ImageFormat, PixelFormat};
+/// use std::error;
+///
+/// fn compute_size_packed() -> Result<(), Box<dyn error::Error>> {
+///     dcp::initialize();
+///
+///     const WIDTH: u32 = 640;
+///     const HEIGHT: u32 = 480;
+///     const NUM_PLANES: u32 = 2;
+///
+///     let format = ImageFormat {
+///         pixel_format: PixelFormat::Nv12,
+///         color_space: ColorSpace::Bt601,
+///         num_planes: NUM_PLANES,
+///     };
+///
+///     let sizes: &mut [usize] = &mut [0usize; NUM_PLANES as usize];
+///     get_buffers_size(WIDTH, HEIGHT, &format, None, sizes)?;
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// Compute how many bytes are needed to store and image of a given format and size
+/// in which *all planes have custom strides*:
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{get_buffers_size, ColorSpace, ImageFormat, PixelFormat};
+/// use std::error;
+///
+/// fn compute_size_custom_strides() -> Result<(), Box<dyn error::Error>> {
+///     dcp::initialize();
+///
+///     const WIDTH: u32 = 640;
+///     const HEIGHT: u32 = 480;
+///     const NUM_PLANES: u32 = 2;
+///     const Y_STRIDE: usize = (WIDTH as usize) + 1;
+///     const UV_STRIDE: usize = (WIDTH as usize) + 3;
+///
+///     let format = ImageFormat {
+///         pixel_format: PixelFormat::Nv12,
+///         color_space: ColorSpace::Bt601,
+///         num_planes: NUM_PLANES,
+///     };
+///
+///     let strides: &[usize] = &[ Y_STRIDE, UV_STRIDE, ];
+///     let sizes: &mut [usize] = &mut [0usize; NUM_PLANES as usize];
+///     get_buffers_size(WIDTH, HEIGHT, &format, Some(strides), sizes)?;
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// Compute how many bytes are needed to store and image of a given format and size
+/// in which *some planes have custom strides*, while *some other are assumed to
+/// contain data which is tightly packed*:
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{get_buffers_size, ColorSpace, ImageFormat, PixelFormat, STRIDE_AUTO};
+/// use std::error;
+///
+/// fn compute_size_custom_strides() -> Result<(), Box<dyn error::Error>> {
+///     dcp::initialize();
+///
+///     const WIDTH: u32 = 640;
+///     const HEIGHT: u32 = 480;
+///     const NUM_PLANES: u32 = 2;
+///     const Y_STRIDE: usize = (WIDTH as usize) + 1;
+///
+///     let format = ImageFormat {
+///         pixel_format: PixelFormat::Nv12,
+///         color_space: ColorSpace::Bt601,
+///         num_planes: NUM_PLANES,
+///     };
+///
+///     let strides: &[usize] = &[ Y_STRIDE, STRIDE_AUTO, ];
+///     let sizes: &mut [usize] = &mut [0usize; NUM_PLANES as usize];
+///     get_buffers_size(WIDTH, HEIGHT, &format, Some(strides), sizes)?;
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// Default strides (e.g. the one you would set for tightly packed data) can be set
+/// using the constant [`STRIDE_AUTO`]
+///
+/// When an image is stored in a single buffer, the planes after the first one use the
+/// stride of the buffer, unless the strides array gives the stride of each plane (e.g.
+/// decoders that only pad the luma lines). [`STRIDE_AUTO`] selects the stride of the buffer
+/// for these planes.
+///
+/// If `width` or `height` is zero, all the buffer sizes are zero, whatever the strides.
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if `width` or `height` violate the [`size constraints`] that might by
+///   imposed by the image pixel format
+///
+/// * [`InvalidValue`] if the image format has a number of planes which is not compatible
+///   with its pixel format
+///
+/// * [`InvalidValue`] if the size of one or more buffers does not fit in a `usize`
+///
+/// * [`NotEnoughData`] if the strides array is not `None` and its length is less than the
+///   image format number of planes
+///
+/// * [`NotEnoughData`] if the buffers_sizes array is not `None` and its length is less than the
+///   image format number of planes
+///
+/// [`InvalidValue`]: ./enum.ErrorKind.html#variant.NotInitialized
+/// [`NotEnoughData`]: ./enum.ErrorKind.html#variant.NotEnoughData
+/// [`size constraints`]: ./struct.ImageFormat.html#note
+/// [`STRIDE_AUTO`]: ./constant.STRIDE_AUTO.html
+pub fn get_buffers_size(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+    buffers_size: &mut [usize],
+) -> Result<(), ErrorKind> {
+    let pixel_format = format.pixel_format as u32;
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if !pixel_format::is_compatible(pixel_format, width, height, last_plane) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    pixel_format::get_buffers_size(
+        pixel_format,
+        width,
+        height,
+        last_plane,
+        strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+        buffers_size,
+    )
+}
+
+/// Returns the stride, in bytes, that conversions perform best with for a plane of an image
+/// stored with one buffer for each plane
+///
+/// The stride is the size of a line of the plane rounded up to a multiple of 64 bytes, so that
+/// every line starts on a cache line and SIMD loads and stores never straddle two lines'
+/// cache lines. Allocators can use it to lay out the buffers passed to [`get_buffers_size`]
+/// and [`convert_image`].
+///
+/// Returns zero if the pixel format has fewer planes, or if the stride does not fit in a
+/// `usize`.
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `pixel_format` - Pixel format of the image
+/// * `plane` - Index of the plane, e.g. 0 for luma and 1 for interleaved chroma in `Nv12`
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{get_buffers_size, recommended_stride, ColorSpace, ImageFormat, PixelFormat};
+///
+/// const WIDTH: u32 = 1366;
+/// const HEIGHT: u32 = 768;
+///
+/// let format = ImageFormat {
+///     pixel_format: PixelFormat::Nv12,
+///     color_space: ColorSpace::Bt601,
+///     num_planes: 2,
+/// };
+///
+/// let strides = [
+///     recommended_stride(WIDTH, format.pixel_format, 0),
+///     recommended_stride(WIDTH, format.pixel_format, 1),
+/// ];
+/// assert_eq!(strides, [1408, 1408]);
+///
+/// let sizes = &mut [0usize; 2];
+/// get_buffers_size(WIDTH, HEIGHT, &format, Some(&strides), sizes).unwrap();
+/// assert_eq!(sizes, &[1408 * 768, 1408 * 384]);
+/// ```
+///
+/// [`get_buffers_size`]: ./fn.get_buffers_size.html
+/// [`convert_image`]: ./fn.convert_image.html
+/// [`recommended_stride`]: ./fn.recommended_stride.html
+pub fn recommended_stride(width: u32, pixel_format: PixelFormat, plane: u32) -> usize {
+    layout::recommended_stride(width, &pixel_format, plane)
+}
+
+/// Returns the size, in bytes, of a line of a plane rounded up to a multiple of `alignment`
+///
+/// Graphics APIs require the rows of buffers copied to textures to be aligned, e.g. to
+/// 256 bytes for Direct3D 12. Use it to size the buffers written by
+/// [`convert_image_aligned`].
+///
+/// Returns zero if `alignment` is zero, if the pixel format has fewer planes, or if the
+/// stride does not fit in a `usize`.
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `pixel_format` - Pixel format of the image
+/// * `plane` - Index of the plane, e.g. 0 for luma and 1 for interleaved chroma in `Nv12`
+/// * `alignment` - Alignment of the stride in bytes, not necessarily a power of two
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{aligned_stride, PixelFormat};
+///
+/// assert_eq!(aligned_stride(1366, PixelFormat::Bgra, 0, 256), 5632);
+/// assert_eq!(aligned_stride(1366, PixelFormat::I420, 1, 3), 684);
+/// ```
+///
+/// [`convert_image_aligned`]: ./fn.convert_image_aligned.html
+pub fn aligned_stride(
+    width: u32,
+    pixel_format: PixelFormat,
+    plane: u32,
+    alignment: usize,
+) -> usize {
+    layout::aligned_stride(width, &pixel_format, plane, alignment)
+}
+
+fn get_split_sizes(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+    buffer_size: usize,
+) -> Result<Vec<usize>, ErrorKind> {
+    let buffers_size = &mut [0usize; pixel_format::MAX_NUMBER_OF_PLANES];
+    get_buffers_size(width, height, format, strides, buffers_size)?;
+
+    let buffers_size = &buffers_size[..format.num_planes as usize];
+    let total_size = buffers_size
+        .iter()
+        .try_fold(0usize, |total, &size| total.checked_add(size))
+        .ok_or(ErrorKind::InvalidValue)?;
+    if total_size > buffer_size {
+        return Err(ErrorKind::NotEnoughData);
+    }
+
+    Ok(buffers_size.to_vec())
+}
+
+/// Splits a contiguous allocation into the buffers of an image, one for each plane
+/// of its format.
+///
+/// Buffers are stored one after the other, each one having the size returned by
+/// [`get_buffers_size`] for the same arguments. Bytes past the last buffer are ignored.
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `format` - Image format
+/// * `strides` - An array of distances in bytes between starts of consecutive lines
+///   in each image plane
+/// * `buffer` - The allocation holding all the image planes
+///
+/// # Examples
+/// Hand a single nv12 allocation to a conversion using a buffer for each plane:
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{convert_image, split_planes_mut, ColorSpace, ImageFormat, PixelFormat};
+/// use std::error;
+///
+/// fn convert() -> Result<(), Box<dyn error::Error>> {
+///     dcp::initialize();
+///
+///     const WIDTH: u32 = 640;
+///     const HEIGHT: u32 = 480;
+///
+///     let src_format = ImageFormat {
+///         pixel_format: PixelFormat::Bgra,
+///         color_space: ColorSpace::Lrgb,
+///         num_planes: 1,
+///     };
+///
+///     let dst_format = ImageFormat {
+///         pixel_format: PixelFormat::Nv12,
+///         color_space: ColorSpace::Bt601,
+///         num_planes: 2,
+///     };
+///
+///     let src_buffer = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+///     let mut dst_buffer = vec![0u8; 3 * (WIDTH as usize) * (HEIGHT as usize) / 2];
+///     let mut dst_buffers = split_planes_mut(WIDTH, HEIGHT, &dst_format, None, &mut dst_buffer)?;
+///
+///     convert_image(
+///         WIDTH,
+///         HEIGHT,
+///         &src_format,
+///         None,
+///         &[&src_buffer],
+///         &dst_format,
+///         None,
+///         &mut dst_buffers,
+///     )?;
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// # Errors
+///
+/// * The errors reported by [`get_buffers_size`]
+///
+/// * [`InvalidValue`] if the total size of the buffers does not fit in a `usize`
+///
+/// * [`NotEnoughData`] if `buffer` is smaller than the total size of the buffers
+///
+/// [`get_buffers_size`]: ./fn.get_buffers_size.html
+/// [`InvalidValue`]: ./enum.ErrorKind.html#variant.InvalidValue
+/// [`NotEnoughData`]: ./enum.ErrorKind.html#variant.NotEnoughData
+pub fn split_planes<'a>(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+    buffer: &'a [u8],
+) -> Result<Vec<&'a [u8]>, ErrorKind> {
+    let sizes = get_split_sizes(width, height, format, strides, buffer.len())?;
+
+    let mut planes = Vec::with_capacity(sizes.len());
+    let mut remaining = buffer;
+    for size in sizes {
+        let (plane, rest) = remaining.split_at(size);
+        planes.push(plane);
+        remaining = rest;
+    }
+
+    Ok(planes)
+}
+
+/// Splits a contiguous mutable allocation into the buffers of an image, one for each plane
+/// of its format.
+///
+/// See [`split_planes`] for details.
+///
+/// [`split_planes`]: ./fn.split_planes.html
+pub fn split_planes_mut<'a>(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+    buffer: &'a mut [u8],
+) -> Result<Vec<&'a mut [u8]>, ErrorKind> {
+    let sizes = get_split_sizes(width, height, format, strides, buffer.len())?;
+
+    let mut planes = Vec::with_capacity(sizes.len());
+    let mut remaining = buffer;
+    for size in sizes {
+        let (plane, rest) = { remaining }.split_at_mut(size);
+        planes.push(plane);
+        remaining = rest;
+    }
+
+    Ok(planes)
+}
+
+/// Returns the segments of the buffers of an image that hold its samples, in encode order,
+/// ready to be written with a single vectored write (e.g. `writev` or `sendmsg`).
+///
+/// Planes are listed in order, each one line after line. Stride padding is skipped, and
+/// consecutive segments that are contiguous in memory are merged, so that a tightly packed
+/// image stored in a single buffer is a single segment. Writing all the segments in order
+/// produces the tightly packed image, as expected by raw video consumers.
+///
+/// Empty images have no segments, and their buffers are not accessed.
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `format` - Image format
+/// * `strides` - An array of distances in bytes between starts of consecutive lines
+///   in each image planes
+/// * `buffers` - An array of image buffers in each image plane
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if `width` or `height` violate the [`size constraints`] that might by
+///   imposed by the image pixel format
+///
+/// * [`InvalidValue`] if the image format has a number of planes which is not compatible
+///   with its pixel format
+///
+/// * [`NotEnoughData`] if the strides array is not `None` and its length is less than the
+///   image format number of planes
+///
+/// * [`NotEnoughData`] if one or more buffers does not provide enough data
+///
+/// # Examples
+/// Stream an nv12 frame whose luma lines are padded to 64 bytes:
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{get_io_slices, ColorSpace, ImageFormat, PixelFormat};
+/// use std::error;
+/// use std::io::Write;
+///
+/// fn stream<W: Write>(output: &mut W) -> Result<(), Box<dyn error::Error>> {
+///     const WIDTH: u32 = 1366;
+///     const HEIGHT: u32 = 768;
+///     const Y_STRIDE: usize = 1408;
+///
+///     let format = ImageFormat {
+///         pixel_format: PixelFormat::Nv12,
+///         color_space: ColorSpace::Bt601,
+///         num_planes: 2,
+///     };
+///
+///     let y_plane = vec![0u8; Y_STRIDE * (HEIGHT as usize)];
+///     let uv_plane = vec![0u8; (WIDTH as usize) * (HEIGHT as usize) / 2];
+///     let strides = &[Y_STRIDE, 0];
+///     let buffers: &[&[u8]] = &[&y_plane, &uv_plane];
+///
+///     // A segment for each luma line, then the whole chroma plane
+///     let slices = get_io_slices(WIDTH, HEIGHT, &format, Some(strides), buffers)?;
+///     assert_eq!(slices.len(), (HEIGHT as usize) + 1);
+///
+///     let size: usize = slices.iter().map(|slice| slice.len()).sum();
+///     assert_eq!(size, 3 * (WIDTH as usize) * (HEIGHT as usize) / 2);
+///
+///     // A vectored write can be partial, like a plain one
+///     let written = output.write_vectored(&slices)?;
+///     assert!(written <= size);
+///
+///     Ok(())
+/// }
+/// # stream(&mut Vec::new()).unwrap();
+/// ```
+///
+/// [`InvalidValue`]: ./enum.ErrorKind.html#variant.InvalidValue
+/// [`NotEnoughData`]: ./enum.ErrorKind.html#variant.NotEnoughData
+/// [`size constraints`]: ./struct.ImageFormat.html#note
+pub fn get_io_slices<'a>(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+    buffers: &[&'a [u8]],
+) -> Result<Vec<IoSlice<'a>>, ErrorKind> {
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if !pixel_format::is_compatible(format.pixel_format as u32, width, height, last_plane) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    if is_empty(width, height) {
+        return Ok(Vec::new());
+    }
+
+    let strides = strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let layouts = get_plane_layouts(format, width, height, last_plane, strides)?;
+
+    // Ranges of the buffers holding each line, merged when contiguous
+    let mut ranges: Vec<(usize, usize, usize)> = Vec::new();
+    for layout in &layouts {
+        let line_size = layout.depth * ((width >> layout.horizontal_shift) as usize);
+        for line in 0..((height >> layout.vertical_shift) as usize) {
+            let start = layout.offset + line * layout.stride;
+            match ranges.last_mut() {
+                Some(range) if range.0 == layout.buffer && range.2 == start => {
+                    range.2 = start + line_size;
+                }
+                _ => ranges.push((layout.buffer, start, start + line_size)),
+            }
+        }
+    }
+
+    ranges
+        .iter()
+        .map(|&(buffer, start, end)| Some(IoSlice::new(buffers.get(buffer)?.get(start..end)?)))
+        .collect::<Option<Vec<_>>>()
+        .ok_or(ErrorKind::NotEnoughData)
+}
+
+/// Location and geometry of a logical plane of an image
+///
+/// Returned by [`describe_layout`]. The structure has a C layout, and it is the
+/// `DcpPlaneLayoutDescriptor` structure of the C interface.
+///
+/// [`describe_layout`]: ./fn.describe_layout.html
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct PlaneLayoutDescriptor {
+    /// Index of the buffer holding the plane
+    pub buffer: u32,
+    /// Width of the plane in samples (pairs of samples for interleaved chroma)
+    pub width: u32,
+    /// Number of lines of the plane
+    pub height: u32,
+    /// Bytes of each sample (pair of samples for interleaved chroma)
+    pub bytes_per_sample: u32,
+    /// Number of pixels sharing a sample horizontally: 1, or 2 for subsampled chroma
+    pub horizontal_subsampling: u32,
+    /// Number of pixels sharing a sample vertically: 1, or 2 for subsampled chroma
+    pub vertical_subsampling: u32,
+    /// Distance in bytes between the start of the buffer and the first line of the plane
+    pub offset: usize,
+    /// Distance in bytes between starts of consecutive lines
+    pub stride: usize,
+    /// Bytes spanned by the plane, padding of the last line included
+    pub size: usize,
+}
+
+/// Describes where each logical plane of an image is stored, given its format, size and
+/// strides
+///
+/// There is a descriptor for each logical plane of the pixel format (e.g. luma and
+/// interleaved chroma for `PixelFormat::Nv12`), even when several planes share a buffer.
+/// Planes sharing a buffer use its stride, unless the strides array gives their own.
+/// The size of each buffer is the largest `offset + size` of the planes it holds, which
+/// is the size returned by [`get_buffers_size`] when the strides are `None`.
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `format` - Image format
+/// * `strides` - An array of distances in bytes between starts of consecutive lines
+///   in each image planes
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if `width` or `height` violate the [`size constraints`] that might by
+///   imposed by the image pixel format
+///
+/// * [`InvalidValue`] if the image format has a number of planes which is not compatible
+///   with its pixel format
+///
+/// * [`NotEnoughData`] if the strides array is not `None` and its length is less than the
+///   image format number of planes
+///
+/// * [`NotEnoughData`] if one or more strides are smaller than the line size, or the size
+///   of a buffer does not fit in a `usize`
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{describe_layout, ColorSpace, ImageFormat, PixelFormat};
+///
+/// let format = ImageFormat {
+///     pixel_format: PixelFormat::Nv12,
+///     color_space: ColorSpace::Bt601,
+///     num_planes: 1,
+/// };
+///
+/// let planes = describe_layout(640, 480, &format, Some(&[704])).unwrap();
+/// assert_eq!(planes.len(), 2);
+///
+/// // Chroma follows luma in the same buffer, with the same stride
+/// assert_eq!((planes[1].buffer, planes[1].offset), (0, 704 * 480));
+/// assert_eq!((planes[1].width, planes[1].height), (320, 240));
+/// assert_eq!(planes[1].bytes_per_sample, 2);
+/// assert_eq!(planes[1].size, 704 * 240);
+/// ```
+///
+/// [`get_buffers_size`]: ./fn.get_buffers_size.html
+/// [`InvalidValue`]: ./enum.ErrorKind.html#variant.InvalidValue
+/// [`NotEnoughData`]: ./enum.ErrorKind.html#variant.NotEnoughData
+/// [`size constraints`]: ./struct.ImageFormat.html#note
+pub fn describe_layout(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+) -> Result<Vec<PlaneLayoutDescriptor>, ErrorKind> {
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if !pixel_format::is_compatible(format.pixel_format as u32, width, height, last_plane) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let strides = strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let layouts = get_plane_layouts(format, width, height, last_plane, strides)?;
+
+    // Geometry and sizes come from the same code as layout::get_buffers_size
+    let planes = layout::PixelLayout::planes(&format.pixel_format);
+    layouts
+        .iter()
+        .zip(planes)
+        .map(|(layout, plane)| {
+            Some(PlaneLayoutDescriptor {
+                buffer: layout.buffer as u32,
+                width: width >> plane.horizontal_shift,
+                height: height >> plane.vertical_shift,
+                bytes_per_sample: plane.bytes_per_sample as u32,
+                horizontal_subsampling: 1 << plane.horizontal_shift,
+                vertical_subsampling: 1 << plane.vertical_shift,
+                offset: layout.offset,
+                stride: layout.stride,
+                size: layout::plane_size(width, height, plane, layout.stride)?,
+            })
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or(ErrorKind::NotEnoughData)
+}
+
+/// Converts from a color space to another one, applying downsampling/upsampling
+/// to match destination image format.
+///
+/// Buffers and strides can have any alignment. Misaligned buffers never cause a crash
+/// and are converted by the same kernels as aligned ones, which only use unaligned
+/// memory accesses; aligning lines as suggested by [`recommended_stride`] can still
+/// make conversions faster.
+///
+/// Images stored in a single buffer can give the stride of each plane, as described in
+/// [`get_buffers_size`].
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///                   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///                   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+///
+/// # Errors
+///
+/// * [`NotInitialized`] if the library was not initialized before
+///
+/// * [`InvalidValue`] if `width` or `height` violate the [`size constraints`]
+///   that might by imposed by the source and destination image pixel formats
+///
+/// * [`InvalidValue`] if source or destination image formats have a number of planes
+///   which is not compatible with their pixel formats
+///
+/// * [`InvalidOperation`] if there is no available method to convert the image with the
+///   source pixel format to the image with the destination pixel format, neither directly
+///   nor through intermediate images (see [`Routed conversions`]).
+///
+///   The list of available direct conversions is specified here:
+///
+///   Source image pixel format     | Supported destination image pixel formats
+///   ------------------------------|------------------------------------------
+///   PixelFormat::Argb             | PixelFormat::I420 [`1`]
+///   PixelFormat::Argb             | PixelFormat::I444 [`1`]
+///   PixelFormat::Argb             | PixelFormat::Nv12 [`1`]
+///   PixelFormat::Argb             | PixelFormat::YuvF32Planar [`12`]
+///   PixelFormat::Bgra             | PixelFormat::I420 [`1`]
+///   PixelFormat::Bgra             | PixelFormat::I444 [`1`]
+///   PixelFormat::Bgra             | PixelFormat::Nv12 [`1`]
+///   PixelFormat::Bgra             | PixelFormat::Rgb  [`4`]
+///   PixelFormat::Bgra             | PixelFormat::YuvF32Planar [`12`]
+///   PixelFormat::Bgr              | PixelFormat::I420 [`1`]
+///   PixelFormat::Bgr              | PixelFormat::I444 [`1`]
+///   PixelFormat::Bgr              | PixelFormat::Nv12 [`1`]
+///   PixelFormat::Bgr              | PixelFormat::YuvF32Planar [`12`]
+///   PixelFormat::Bgr48            | PixelFormat::Bgra [`11`]
+///   PixelFormat::Bgr48            | PixelFormat::P010 [`10`]
+///   PixelFormat::I420             | PixelFormat::Bgra [`2`]
+///   PixelFormat::I420             | PixelFormat::I420 [`7`]
+///   PixelFormat::I420             | PixelFormat::RgbF32 [`8`]
+///   PixelFormat::I420             | PixelFormat::RgbaF32 [`8`]
+///   PixelFormat::I444             | PixelFormat::Bgra [`2`]
+///   PixelFormat::I444             | PixelFormat::I444 [`7`]
+///   PixelFormat::I444             | PixelFormat::RgbF32 [`8`]
+///   PixelFormat::I444             | PixelFormat::RgbaF32 [`8`]
+///   PixelFormat::Nv12             | PixelFormat::Bgra [`2`]
+///   PixelFormat::Nv12             | PixelFormat::Nv12 [`7`]
+///   PixelFormat::Nv12             | PixelFormat::P010 [`9`]
+///   PixelFormat::Nv12             | PixelFormat::RgbF32 [`8`]
+///   PixelFormat::Nv12             | PixelFormat::RgbaF32 [`8`]
+///   PixelFormat::P010             | PixelFormat::Nv12 [`9`]
+///   PixelFormat::P010             | PixelFormat::RgbaF16 [`5`]
+///   PixelFormat::Rgb              | PixelFormat::Bgra [`3`]
+///   PixelFormat::Rgb48            | PixelFormat::Bgra [`11`]
+///   PixelFormat::Rgb48            | PixelFormat::P010 [`10`]
+///   PixelFormat::RgbaF16          | PixelFormat::Bgra [`6`]
+///
+/// * [`NotEnoughData`] if the source stride array is not `None` and its length is less than the
+///   source image format number of planes
+///
+/// * [`NotEnoughData`] if the destination stride array is not `None` and its length is less than the
+///   destination image format number of planes
+///
+/// * [`NotEnoughData`] if one or more source/destination buffers does not provide enough data.
+///
+///   The minimum number of bytes to provide for each buffer depends from the image format, dimensions,
+///   and strides (if they are not `None`).
+///
+///   You can compute the buffers' size using [`get_buffers_size`]
+///
+/// # Empty images
+/// Images with zero width or height are valid. Once the image formats have been validated,
+/// the function returns successfully without accessing the buffers, which can be empty.
+///
+/// # Routed conversions
+/// When there is no direct conversion between the source and the destination image formats,
+/// the image is converted through one or more intermediate images, stored in scratch buffers
+/// allocated by the library for the duration of the call. For example, an image with
+/// pixel format `Rgb` is converted to `Nv12` going through `Bgra`. The image is converted
+/// in horizontal bands, so scratch buffers only hold a few lines and their size does not
+/// depend on the image height.
+///
+/// The route with fewer conversions is chosen. Among routes with the same length, the ones
+/// whose intermediate images do not have chroma subsampling are preferred.
+/// Images with the same pixel format and color space are never routed.
+///
+/// Routing only applies to this function. Use [`describe_conversion`] to know which route
+/// is taken for a given pair of image formats.
+///
+/// # Noise injection
+/// When the library is built with the `noise` feature, this function and every other
+/// conversion function move each 8-bit color sample they write by at most one code value,
+/// deterministically. This is meant to check that test suites do not depend on the exact
+/// output of the current kernels.
+/// [`max_error`] accounts for the perturbation. Never enable this feature in production.
+///
+/// # Algorithm 1
+/// Conversion from linear RGB model to YCbCr color model, with 4:2:0 downsampling
+///
+/// If the destination image color space is Bt601, the following formula is applied:
+/// ```text
+/// y  =  0.257 * r + 0.504 * g + 0.098 * b + 16
+/// cb = -0.148 * r - 0.291 * g + 0.439 * b + 128
+/// cr =  0.439 * r - 0.368 * g - 0.071 * b + 128
+/// ```
+///
+/// If the destination image color space is Bt709, the following formula is applied:
+/// ```text
+/// y  =  0.213 * r + 0.715 * g + 0.072 * b + 16
+/// cb = -0.117 * r - 0.394 * g + 0.511 * b + 128
+/// cr =  0.511 * r - 0.464 * g - 0.047 * b + 128
+/// ```
+///
+/// If the destination image color space is Jfif, full range samples are computed exactly
+/// as libjpeg does, with the following formula:
+/// ```text
+/// y  =  0.299 * r + 0.587 * g + 0.114 * b
+/// cb = -0.169 * r - 0.331 * g + 0.500 * b + 128
+/// cr =  0.500 * r - 0.419 * g - 0.081 * b + 128
+/// ```
+///
+/// Chroma ties are rounded down. With 4:2:0 downsampling, chroma is computed for each pixel,
+/// then averaged over each 2x2 block, rounding ties alternately down and up along the line.
+///
+/// # Algorithm 2
+/// Conversion from YCbCr model to linear RGB model, with 4:4:4 upsampling
+///
+/// If the destination image contains an alpha channel, each component will be set to 255
+///
+/// If the source image color space is Bt601, the following formula is applied:
+/// ```text
+/// r = 1.164 * (y - 16) + 1.596 * (cr - 128)
+/// g = 1.164 * (y - 16) - 0.813 * (cr - 128) - 0.392 * (cb - 128)
+/// b = 1.164 * (y - 16) + 2.017 * (cb - 128)
+/// ```
+///
+/// If the source image color space is Bt709, the following formula is applied:
+/// ```text
+/// r = 1.164 * (y - 16) + 1.793 * (cr - 128)
+/// g = 1.164 * (y - 16) - 0.534 * (cr - 128) - 0.213 * (cb - 128)
+/// b = 1.164 * (y - 16) + 2.115 * (cb - 128)
+/// ```
+///
+/// If the source image color space is Jfif, full range samples are converted exactly
+/// as libjpeg does without fancy upsampling, with the following formula:
+/// ```text
+/// r = y + 1.402 * (cr - 128)
+/// g = y - 0.714 * (cr - 128) - 0.344 * (cb - 128)
+/// b = y + 1.772 * (cb - 128)
+/// ```
+///
+/// # Algorithm 3
+/// Conversion from RGB to BGRA
+///
+/// # Algorithm 4
+/// Conversion from BGRA to RGB
+///
+/// # Algorithm 5
+/// Conversion from YCbCr model (BT.2020, PQ) to linear scRGB half float, with 4:4:4 upsampling
+///
+/// Samples are normalized from the 10-bit limited range, then the following formula is applied:
+/// ```text
+/// r' = y + 1.4746 * cr
+/// g' = y - 0.1646 * cb - 0.5714 * cr
+/// b' = y + 1.8814 * cb
+/// ```
+///
+/// Each component is linearized with the SMPTE ST 2084 EOTF, scaled so that 1.0 is 80 nits,
+/// and moved from BT.2020 to BT.709 primaries. Alpha is set to 1.0
+///
+/// # Algorithm 6
+/// Conversion from linear scRGB half float to 8-bit sRGB, with tone mapping
+///
+/// Negative components are clipped. If the largest component exceeds 0.75, all components
+/// are scaled so that the largest one is compressed into [0.75, 1]:
+/// ```text
+/// t = (max - 0.75) / 0.25
+/// max' = 0.75 + 0.25 * t / (1 + t)
+/// ```
+///
+/// The sRGB transfer function is then applied. Alpha is clamped to [0, 1] and preserved
+///
+/// # Algorithm 7
+/// Conversion between YCbCr color spaces, without going through RGB
+///
+/// Only Bt601 and Bt709 are supported. Chroma is converted as it is, luma is corrected
+/// using the chroma sample that covers it. From Bt601 to Bt709:
+/// ```text
+/// y'  = y - 0.116 * (cb - 128) - 0.208 * (cr - 128)
+/// cb' = 1.019 * (cb - 128) + 0.115 * (cr - 128) + 128
+/// cr' = 0.075 * (cb - 128) + 1.025 * (cr - 128) + 128
+/// ```
+///
+/// From Bt709 to Bt601:
+/// ```text
+/// y'  = y + 0.099 * (cb - 128) + 0.192 * (cr - 128)
+/// cb' = 0.990 * (cb - 128) - 0.111 * (cr - 128) + 128
+/// cr' = -0.072 * (cb - 128) + 0.983 * (cr - 128) + 128
+/// ```
+///
+/// # Algorithm 8
+/// Conversion from YCbCr model to normalized floating point RGB, with 4:4:4 upsampling
+///
+/// Samples are normalized from the 8-bit limited range, then the following formula is applied:
+/// ```text
+/// y' = (y - 16) / 219
+/// cb' = (cb - 128) / 224
+/// cr' = (cr - 128) / 224
+/// ```
+///
+/// If the source image color space is Bt601:
+/// ```text
+/// r = y' + 1.402 * cr'
+/// g = y' - 0.344 * cb' - 0.714 * cr'
+/// b = y' + 1.772 * cb'
+/// ```
+///
+/// If the source image color space is Bt709:
+/// ```text
+/// r = y' + 1.575 * cr'
+/// g = y' - 0.187 * cb' - 0.468 * cr'
+/// b = y' + 1.856 * cb'
+/// ```
+///
+/// Components are clipped to [0, 1]. If the destination image contains an alpha channel,
+/// it is set to 1.0
+///
+/// # Algorithm 9
+/// Change of bit depth between 8-bit and 10-bit YCbCr, with the same color space
+///
+/// Samples are rescaled without any color transformation:
+/// ```text
+/// p010 = nv12 * 4
+/// nv12 = min((p010 + 2) / 4, 255)
+/// ```
+///
+/// P010 samples are stored in the upper 10 bits of each 16-bit word. To reduce banding
+/// when going to 8 bits, use [`convert_depth`] with ordered dithering.
+///
+/// # Algorithm 10
+/// Conversion from 16-bit linear RGB model to 10-bit YCbCr color model, with 4:2:0
+/// downsampling
+///
+/// Only Bt601 and Bt709 are supported. Components are normalized to [0, 1] dividing them
+/// by 65535, then the following formula is applied:
+/// ```text
+/// y' = kr * r + (1 - kr - kb) * g + kb * b
+/// y  = 876 * y' + 64
+/// cb = 896 * (b - y') / (2 - 2 * kb) + 512
+/// cr = 896 * (r - y') / (2 - 2 * kr) + 512
+/// ```
+///
+/// where kr = 0.299, kb = 0.114 for Bt601 and kr = 0.2126, kb = 0.0722 for Bt709.
+///
+/// Chroma is computed from the average of each 2x2 block. Samples are rounded to nearest
+/// and stored in the upper 10 bits of each 16-bit word.
+///
+/// # Algorithm 11
+/// Conversion from 16-bit RGB to 8-bit BGRA
+///
+/// Components are rescaled and rounded to nearest, alpha is set to 255:
+/// ```text
+/// bgra = (rgb48 * 255 + 32767) / 65535
+/// ```
+///
+/// To reduce banding, use [`convert_depth`] with ordered dithering.
+///
+/// # Algorithm 12
+/// Conversion from linear RGB model to planar single precision YCbCr, with 4:4:4 sampling
+///
+/// Only Bt601 and Bt709 are supported. Components are normalized to [0, 1] dividing them
+/// by 255, then the following formula is applied:
+/// ```text
+/// y  = kr * r + (1 - kr - kb) * g + kb * b
+/// cb = (b - y) / (2 - 2 * kb)
+/// cr = (r - y) / (2 - 2 * kr)
+/// ```
+///
+/// where kr = 0.299, kb = 0.114 for Bt601 and kr = 0.2126, kb = 0.0722 for Bt709.
+/// Luma is in [0, 1] and chroma in [-0.5, 0.5]. Samples are not quantized, so the
+/// sample range of the color space does not apply. To standardize the planes for a
+/// model in the same pass, use [`convert_image_normalized`].
+///
+/// [`NotInitialized`]: ./enum.ErrorKind.html#variant.NotInitialized
+/// [`InvalidValue`]: ./enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ./enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ./enum.ErrorKind.html#variant.NotEnoughData
+/// [`size constraints`]: ./struct.ImageFormat.html#note
+/// [`get_buffers_size`]: ./fn.get_buffers_size.html
+/// [`describe_conversion`]: ./fn.describe_conversion.html
+/// [`recommended_stride`]: ./fn.recommended_stride.html
+/// [`Routed conversions`]: ./fn.convert_image.html#routed-conversions
+/// [`1`]: ./fn.convert_image.html#algorithm-1
+/// [`2`]: ./fn.convert_image.html#algorithm-2
+/// [`3`]: ./fn.convert_image.html#algorithm-3
+/// [`4`]: ./fn.convert_image.html#algorithm-4
+/// [`5`]: ./fn.convert_image.html#algorithm-5
+/// [`6`]: ./fn.convert_image.html#algorithm-6
+/// [`7`]: ./fn.convert_image.html#algorithm-7
+/// [`8`]: ./fn.convert_image.html#algorithm-8
+/// [`9`]: ./fn.convert_image.html#algorithm-9
+/// [`10`]: ./fn.convert_image.html#algorithm-10
+/// [`11`]: ./fn.convert_image.html#algorithm-11
+/// [`12`]: ./fn.convert_image.html#algorithm-12
+/// [`convert_depth`]: ./depth/fn.convert_depth.html
+/// [`convert_image_normalized`]: ./fn.convert_image_normalized.html
+/// [`max_error`]: ./fn.max_error.html
+pub fn convert_image(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_image",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+
+    // Kernels expect the planes sharing a buffer to use its stride: images giving the stride
+    // of each plane are converted as images having a buffer for each logical plane
+    if has_logical_strides(width, height, src_format, src_strides) {
+        let (src_format, src_strides, src_buffers) =
+            split_logical_planes(width, height, src_format, src_strides, src_buffers)?;
+        return convert_image(
+            width,
+            height,
+            &src_format,
+            Some(&src_strides),
+            &src_buffers,
+            dst_format,
+            Some(dst_strides),
+            dst_buffers,
+        );
+    }
+
+    if has_logical_strides(width, height, dst_format, dst_strides) {
+        let (dst_format, dst_strides, mut dst_buffers) =
+            split_logical_planes_mut(width, height, dst_format, dst_strides, dst_buffers)?;
+        return convert_image(
+            width,
+            height,
+            src_format,
+            Some(src_strides),
+            src_buffers,
+            &dst_format,
+            Some(&dst_strides),
+            &mut dst_buffers,
+        );
+    }
+
+    let (converter, last_src_plane, _) = match get_converter(width, height, src_format, dst_format)
+    {
+        Ok(converter) => converter,
+        Err(ErrorKind::InvalidOperation) => {
+            let converters = get_converters()?;
+            let route = graph::find_route(converters, width, height, src_format, dst_format)?;
+            if is_empty(width, height) {
+                return Ok(());
+            }
+
+            let mut scratch = vec![0u8; graph::get_scratch_size(width, height, &route)?];
+            graph::convert_routed(
+                converters,
+                &mut scratch,
+                width,
+                height,
+                &route,
+                src_format,
+                src_strides,
+                src_buffers,
+                dst_format,
+                dst_strides,
+                dst_buffers,
+            )?;
+
+            return Ok(());
+        }
+        Err(error) => return Err(error),
+    };
+
+    if is_empty(width, height) {
+        return Ok(());
+    }
+
+    run_converter(
+        converter,
+        width,
+        height,
+        0,
+        last_src_plane,
+        src_strides,
+        src_buffers,
+        dst_format,
+        dst_strides,
+        dst_buffers,
+    )
+}
+
+/// Trade-off between speed and fidelity of a conversion
+///
+/// Each level is at least as accurate as the previous one.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Quality {
+    /// The integer approximations used by [`convert_image`], vectorized for the running cpu
+    ///
+    /// [`convert_image`]: ./fn.convert_image.html
+    Fast,
+    /// Fixed point arithmetic with 16 fractional bits, rounding once per sample
+    Balanced,
+    /// Single precision floating point arithmetic, rounding once per sample
+    Accurate,
+}
+
+/// Converts an image like [`convert_image`], selecting the kernels by quality
+///
+/// The quality affects the direct conversions between 8-bit rgb and YCbCr formats
+/// with color space `Bt601` or `Bt709`:
+///
+/// Conversion      | Fast    | Balanced    | Accurate
+/// ----------------|---------|-------------|---------------
+/// rgb to YCbCr    | integer | integer     | floating point
+/// YCbCr to `Bgra` | integer | fixed point | floating point
+///
+/// where rgb is one of `Argb`, `Bgra`, `Bgr` and YCbCr is one of `I444`, `I420`, `Nv12`.
+///
+/// The integer rgb to YCbCr kernels already use 16 fractional bits, so `Balanced`
+/// selects them as well. Balanced and accurate kernels are not vectorized.
+///
+/// Any other conversion, including routed ones, is performed by [`convert_image`]
+/// whatever the quality.
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+/// * `quality` - The quality of the kernels to use
+///
+/// # Errors
+///
+/// * All the errors reported by [`convert_image`]
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{convert_image_with_quality, ColorSpace, ImageFormat, PixelFormat, Quality};
+///
+/// dcp::initialize();
+///
+/// let src_format = ImageFormat {
+///     pixel_format: PixelFormat::I444,
+///     color_space: ColorSpace::Bt709,
+///     num_planes: 3,
+/// };
+///
+/// let dst_format = ImageFormat {
+///     pixel_format: PixelFormat::Bgra,
+///     color_space: ColorSpace::Lrgb,
+///     num_planes: 1,
+/// };
+///
+/// // A dark red
+/// let (y, u, v) = ([63u8], [102u8], [200u8]);
+/// let mut bgra = [0u8; 4];
+///
+/// convert_image_with_quality(
+///     1,
+///     1,
+///     &src_format,
+///     None,
+///     &[&y, &u, &v],
+///     &dst_format,
+///     None,
+///     &mut [&mut bgra],
+///     Quality::Accurate,
+/// )
+/// .unwrap();
+///
+/// // r = 255 / 219 * 47 + 1.5748 * 255 / 224 * 72 = 183.8
+/// assert_eq!(bgra[2], 184);
+/// ```
+///
+/// [`convert_image`]: ./fn.convert_image.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_image_with_quality(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    quality: Quality,
+) -> Result<(), ErrorKind> {
+    use convert_image::common::{PixelFormatChannels, Sampler, YCBCR_TO_RGB_601, YCBCR_TO_RGB_709};
+    use convert_image::x86::{self, RgbOutput, YuvLayout};
+
+    let layout = |pixel_format| match pixel_format {
+        PixelFormat::I444 => Some(YuvLayout::I444),
+        PixelFormat::I420 => Some(YuvLayout::I420),
+        PixelFormat::Nv12 => Some(YuvLayout::Nv12),
+        _ => None,
+    };
+
+    let sampler = |pixel_format| match pixel_format {
+        PixelFormat::Argb => Some((PixelFormatChannels::Four, Sampler::Argb)),
+        PixelFormat::Bgra => Some((PixelFormatChannels::Four, Sampler::Bgra)),
+        PixelFormat::Bgr => Some((PixelFormatChannels::Three, Sampler::Bgr)),
+        _ => None,
+    };
+
+    let coefficients = |color_space| match color_space {
+        ColorSpace::Bt601 => Some(&YCBCR_TO_RGB_601),
+        ColorSpace::Bt709 => Some(&YCBCR_TO_RGB_709),
+        _ => None,
+    };
+
+    let is_bgra = dst_format.pixel_format as u32 == PixelFormat::Bgra as u32;
+
+    let to_rgb = match (layout(src_format.pixel_format), quality) {
+        (Some(layout), Quality::Balanced) | (Some(layout), Quality::Accurate) if is_bgra => {
+            coefficients(src_format.color_space).map(|coefficients| (layout, coefficients))
+        }
+        _ => None,
+    };
+
+    let to_yuv = match (sampler(src_format.pixel_format), quality) {
+        (Some(sampler), Quality::Accurate) => layout(dst_format.pixel_format)
+            .and_then(|layout| {
+                coefficients(dst_format.color_space).map(|coefficients| (layout, coefficients))
+            })
+            .map(|(layout, coefficients)| (sampler, layout, coefficients)),
+        _ => None,
+    };
+
+    if to_rgb.is_none() && to_yuv.is_none() {
+        return convert_image(
+            width,
+            height,
+            src_format,
+            src_strides,
+            src_buffers,
+            dst_format,
+            dst_strides,
+            dst_buffers,
+        );
+    }
+
+    let _span = trace_span!(
+        DEBUG,
+        "convert_image_with_quality",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    let (_, last_src_plane, last_dst_plane) = get_converter(width, height, src_format, dst_format)?;
+    if is_empty(width, height) {
+        return Ok(());
+    }
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let converted = match (to_rgb, to_yuv) {
+        (Some((layout, coefficients)), _) => match quality {
+            Quality::Accurate => x86::yuv_to_lrgb_matrix(
+                width,
+                height,
+                last_src_plane as usize,
+                src_strides,
+                src_buffers,
+                dst_strides,
+                dst_buffers,
+                layout,
+                &x86::get_ycbcr_to_rgb_matrix(coefficients),
+                RgbOutput::Bgra,
+            ),
+            _ => x86::yuv_to_bgra_fix16(
+                width,
+                height,
+                last_src_plane as usize,
+                src_strides,
+                src_buffers,
+                dst_strides,
+                dst_buffers,
+                layout,
+                coefficients,
+                Rounding::HalfUp,
+            ),
+        },
+        (_, Some(((channels, sampler), layout, coefficients))) => x86::lrgb_to_yuv_matrix(
+            width,
+            height,
+            src_strides,
+            src_buffers,
+            last_dst_plane as usize,
+            dst_strides,
+            dst_buffers,
+            channels,
+            sampler,
+            layout,
+            coefficients,
+        ),
+        (None, None) => true,
+    };
+
+    if converted {
+        Ok(())
+    } else {
+        Err(ErrorKind::NotEnoughData)
+    }
+}
+
+/// Rounding applied by the fixed point kernels of [`convert_image_with_rounding`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    /// Discard the fractional part (round toward negative infinity)
+    Truncate,
+    /// Round to nearest, ties away from zero. This is what [`convert_image`] does
+    ///
+    /// [`convert_image`]: ./fn.convert_image.html
+    HalfUp,
+    /// Round to nearest, ties to the even integer
+    HalfEven,
+}
+
+/// Converts an image like [`convert_image`], rounding the fixed point results as requested
+///
+/// The rounding affects the direct conversions between 8-bit rgb and YCbCr formats
+/// with color space `Bt601` or `Bt709`, where rgb is one of `Argb`, `Bgra`, `Bgr` and
+/// YCbCr is one of `I444`, `I420`, `Nv12`, both to YCbCr and to `Bgra`.
+/// These are computed with 16 fractional bits and rounded once per sample, so that the
+/// output can match bit by bit the one of an encoder or decoder rounding the same way.
+/// With `Rounding::HalfUp` the output is the same as the one of [`convert_image`] for
+/// rgb to YCbCr, and of `Quality::Balanced` for YCbCr to `Bgra`. The kernels are not
+/// vectorized.
+///
+/// Any other conversion, including routed ones, is performed by [`convert_image`]
+/// whatever the rounding.
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+/// * `rounding` - How to round fixed point numbers to integers
+///
+/// # Errors
+///
+/// * All the errors reported by [`convert_image`]
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{convert_image_with_rounding, ColorSpace, ImageFormat, PixelFormat, Rounding};
+///
+/// dcp::initialize();
+///
+/// let src_format = ImageFormat {
+///     pixel_format: PixelFormat::Bgra,
+///     color_space: ColorSpace::Lrgb,
+///     num_planes: 1,
+/// };
+///
+/// let dst_format = ImageFormat {
+///     pixel_format: PixelFormat::I444,
+///     color_space: ColorSpace::Bt601,
+///     num_planes: 3,
+/// };
+///
+/// // A light gray
+/// let bgra = [200u8, 200, 200, 255];
+/// let (mut y, mut u, mut v) = ([0u8], [0u8], [0u8]);
+///
+/// for (rounding, luma) in [(Rounding::Truncate, 187), (Rounding::HalfUp, 188)].iter() {
+///     convert_image_with_rounding(
+///         1,
+///         1,
+///         &src_format,
+///         None,
+///         &[&bgra],
+///         &dst_format,
+///         None,
+///         &mut [&mut y, &mut u, &mut v],
+///         *rounding,
+///     )
+///     .unwrap();
+///
+///     // y = 16 + 219 / 255 * 200 = 187.76
+///     assert_eq!(y[0], *luma);
+/// }
+/// ```
+///
+/// [`convert_image`]: ./fn.convert_image.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_image_with_rounding(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    rounding: Rounding,
+) -> Result<(), ErrorKind> {
+    use convert_image::common::{
+        Colorimetry, PixelFormatChannels, Sampler, YCBCR_TO_RGB_601, YCBCR_TO_RGB_709,
+    };
+    use convert_image::x86::{self, YuvLayout, YuvPlanes};
+
+    let layout = |pixel_format| match pixel_format {
+        PixelFormat::I444 => Some(YuvLayout::I444),
+        PixelFormat::I420 => Some(YuvLayout::I420),
+        PixelFormat::Nv12 => Some(YuvLayout::Nv12),
+        _ => None,
+    };
+
+    let sampler = |pixel_format| match pixel_format {
+        PixelFormat::Argb => Some((PixelFormatChannels::Four, Sampler::Argb)),
+        PixelFormat::Bgra => Some((PixelFormatChannels::Four, Sampler::Bgra)),
+        PixelFormat::Bgr => Some((PixelFormatChannels::Three, Sampler::Bgr)),
+        _ => None,
+    };
+
+    let is_bgra = dst_format.pixel_format as u32 == PixelFormat::Bgra as u32;
+
+    let to_rgb = match (layout(src_format.pixel_format), src_format.color_space) {
+        (Some(layout), ColorSpace::Bt601) if is_bgra => Some((layout, &YCBCR_TO_RGB_601)),
+        (Some(layout), ColorSpace::Bt709) if is_bgra => Some((layout, &YCBCR_TO_RGB_709)),
+        _ => None,
+    };
+
+    let to_yuv = match (sampler(src_format.pixel_format), dst_format.color_space) {
+        (Some(sampler), ColorSpace::Bt601) => {
+            layout(dst_format.pixel_format).map(|layout| (sampler, layout, Colorimetry::Bt601))
+        }
+        (Some(sampler), ColorSpace::Bt709) => {
+            layout(dst_format.pixel_format).map(|layout| (sampler, layout, Colorimetry::Bt709))
+        }
+        _ => None,
+    };
+
+    if to_rgb.is_none() && to_yuv.is_none() {
+        return convert_image(
+            width,
+            height,
+            src_format,
+            src_strides,
+            src_buffers,
+            dst_format,
+            dst_strides,
+            dst_buffers,
+        );
+    }
+
+    let _span = trace_span!(
+        DEBUG,
+        "convert_image_with_rounding",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    let (_, last_src_plane, last_dst_plane) = get_converter(width, height, src_format, dst_format)?;
+    if is_empty(width, height) {
+        return Ok(());
+    }
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let converted = match (to_rgb, to_yuv) {
+        (Some((layout, coefficients)), _) => x86::yuv_to_bgra_fix16(
+            width,
+            height,
+            last_src_plane as usize,
+            src_strides,
+            src_buffers,
+            dst_strides,
+            dst_buffers,
+            layout,
+            coefficients,
+            rounding,
+        ),
+        (_, Some(((channels, sampler), layout, colorimetry))) => x86::lrgb_to_yuv_fix16(
+            width,
+            height,
+            src_strides,
+            src_buffers,
+            last_dst_plane as usize,
+            dst_strides,
+            dst_buffers,
+            channels,
+            sampler,
+            layout,
+            colorimetry,
+            rounding,
+            YuvPlanes::All,
+        ),
+        (None, None) => true,
+    };
+
+    if converted {
+        Ok(())
+    } else {
+        Err(ErrorKind::NotEnoughData)
+    }
+}
+
+/// Per-plane normalization applied by [`convert_image_normalized`]
+///
+/// Each sample of plane `i` is written as `(value - mean[i]) / std[i]`, where value is
+/// luma in [0, 1] or chroma in [-0.5, 0.5]. The default leaves the samples unchanged.
+///
+/// [`convert_image_normalized`]: ./fn.convert_image_normalized.html
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Normalization {
+    /// Mean of the Y, U and V planes
+    pub mean: [f32; 3],
+    /// Standard deviation of the Y, U and V planes
+    pub std: [f32; 3],
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Normalization {
+            mean: [0.0; 3],
+            std: [1.0; 3],
+        }
+    }
+}
+
+/// Converts an rgb image to `YuvF32Planar`, normalizing each plane in the same pass
+///
+/// This suits models trained on YCbCr inputs, that expect standardized planar float
+/// tensors. The conversion follows [`Algorithm 12`] of [`convert_image`], then applies
+/// the normalization.
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+/// * `normalization` - Mean and standard deviation of each destination plane
+///
+/// # Errors
+///
+/// * All the errors reported by [`convert_image`]
+/// * [`InvalidOperation`] if the destination pixel format is not `YuvF32Planar`, or the
+///   source pixel format is not one of `Argb`, `Bgra` and `Bgr`
+/// * [`InvalidValue`] if a standard deviation is zero or not finite
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{convert_image_normalized, ColorSpace, ImageFormat, Normalization, PixelFormat};
+///
+/// dcp::initialize();
+///
+/// let src_format = ImageFormat {
+///     pixel_format: PixelFormat::Bgra,
+///     color_space: ColorSpace::Lrgb,
+///     num_planes: 1,
+/// };
+///
+/// let dst_format = ImageFormat {
+///     pixel_format: PixelFormat::YuvF32Planar,
+///     color_space: ColorSpace::Bt709,
+///     num_planes: 1,
+/// };
+///
+/// let normalization = Normalization {
+///     mean: [0.5, 0.0, 0.0],
+///     std: [0.5, 0.5, 0.5],
+/// };
+///
+/// let bgra = [255u8; 4];
+/// let mut yuv = [0u8; 12];
+/// convert_image_normalized(
+///     1,
+///     1,
+///     &src_format,
+///     None,
+///     &[&bgra],
+///     &dst_format,
+///     None,
+///     &mut [&mut yuv],
+///     &normalization,
+/// )
+/// .unwrap();
+///
+/// // White has luma 1, standardized to (1 - 0.5) / 0.5
+/// let y = f32::from_le_bytes([yuv[0], yuv[1], yuv[2], yuv[3]]);
+/// assert!((y - 1.0).abs() < 1e-6);
+/// ```
+///
+/// [`convert_image`]: ./fn.convert_image.html
+/// [`Algorithm 12`]: ./fn.convert_image.html#algorithm-12
+/// [`InvalidValue`]: ./enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ./enum.ErrorKind.html#variant.InvalidOperation
+#[allow(clippy::too_many_arguments)]
+pub fn convert_image_normalized(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    normalization: &Normalization,
+) -> Result<(), ErrorKind> {
+    use convert_image::common::{PixelFormatChannels, Sampler, YCBCR_TO_RGB_601, YCBCR_TO_RGB_709};
+    use convert_image::x86;
+
+    let (channels, sampler) = match (src_format.pixel_format, dst_format.pixel_format) {
+        (PixelFormat::Argb, PixelFormat::YuvF32Planar) => {
+            (PixelFormatChannels::Four, Sampler::Argb)
+        }
+        (PixelFormat::Bgra, PixelFormat::YuvF32Planar) => {
+            (PixelFormatChannels::Four, Sampler::Bgra)
+        }
+        (PixelFormat::Bgr, PixelFormat::YuvF32Planar) => (PixelFormatChannels::Three, Sampler::Bgr),
+        _ => return Err(ErrorKind::InvalidOperation),
+    };
+
+    if normalization
+        .std
+        .iter()
+        .any(|&std| std == 0.0 || !std.is_finite())
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let (_, _, last_dst_plane) = get_converter(width, height, src_format, dst_format)?;
+    if is_empty(width, height) {
+        return Ok(());
+    }
+
+    let coefficients = match dst_format.color_space {
+        ColorSpace::Bt601 => &YCBCR_TO_RGB_601,
+        _ => &YCBCR_TO_RGB_709,
+    };
+
+    let mut planes = [[0.0; 2]; 3];
+    for ((plane, &mean), &std) in planes
+        .iter_mut()
+        .zip(normalization.mean.iter())
+        .zip(normalization.std.iter())
+    {
+        *plane = [mean, std];
+    }
+
+    if x86::lrgb_to_yuvf32(
+        width,
+        height,
+        src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+        dst_buffers,
+        channels,
+        sampler,
+        coefficients,
+        &planes,
+    ) {
+        Ok(())
+    } else {
+        Err(ErrorKind::NotEnoughData)
+    }
+}
+
+/// Converts an rgb image to YCbCr, writing only the luma plane
+///
+/// Streaming applications that refresh luma every frame but chroma at a lower rate can
+/// pair this function with [`convert_chroma_only`], instead of converting the whole image
+/// every time. The written samples are the same as the ones written by [`convert_image`].
+///
+/// The destination buffers describe the whole destination image, and the chroma samples
+/// are left untouched.
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+///
+/// # Errors
+///
+/// * All the errors reported by [`convert_image`]
+/// * [`InvalidOperation`] if the source pixel format is not one of `Argb`, `Bgra` and
+///   `Bgr`, or the destination pixel format is not one of `I444`, `I420` and `Nv12`, or
+///   the destination color space is not one of `Bt601` and `Bt709`
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{convert_chroma_only, convert_luma_only, ColorSpace, ImageFormat, PixelFormat};
+///
+/// dcp::initialize();
+///
+/// let src_format = ImageFormat {
+///     pixel_format: PixelFormat::Bgra,
+///     color_space: ColorSpace::Lrgb,
+///     num_planes: 1,
+/// };
+///
+/// let dst_format = ImageFormat {
+///     pixel_format: PixelFormat::Nv12,
+///     color_space: ColorSpace::Bt601,
+///     num_planes: 2,
+/// };
+///
+/// let bgra = [255u8; 16];
+/// let (mut y, mut uv) = ([0u8; 4], [0u8; 2]);
+/// for frame in 0..4 {
+///     convert_luma_only(
+///         2,
+///         2,
+///         &src_format,
+///         None,
+///         &[&bgra],
+///         &dst_format,
+///         None,
+///         &mut [&mut y, &mut uv],
+///     )
+///     .unwrap();
+///
+///     // Refresh chroma every other frame
+///     if frame % 2 == 0 {
+///         convert_chroma_only(
+///             2,
+///             2,
+///             &src_format,
+///             None,
+///             &[&bgra],
+///             &dst_format,
+///             None,
+///             &mut [&mut y, &mut uv],
+///         )
+///         .unwrap();
+///     }
+/// }
+///
+/// assert_eq!(y, [235; 4]);
+/// assert_eq!(uv, [128; 2]);
+/// ```
+///
+/// [`convert_image`]: ./fn.convert_image.html
+/// [`convert_chroma_only`]: ./fn.convert_chroma_only.html
+/// [`InvalidOperation`]: ./enum.ErrorKind.html#variant.InvalidOperation
+#[allow(clippy::too_many_arguments)]
+pub fn convert_luma_only(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_luma_only",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    convert_yuv_planes(
+        width,
+        height,
+        src_format,
+        src_strides,
+        src_buffers,
+        dst_format,
+        dst_strides,
+        dst_buffers,
+        convert_image::x86::YuvPlanes::Luma,
+    )
+}
+
+/// Converts an rgb image to YCbCr, writing only the chroma planes
+///
+/// This is the counterpart of [`convert_luma_only`]: the luma samples are left untouched,
+/// and the chroma samples are the same as the ones written by [`convert_image`].
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+///
+/// # Errors
+///
+/// * All the errors reported by [`convert_luma_only`]
+///
+/// [`convert_image`]: ./fn.convert_image.html
+/// [`convert_luma_only`]: ./fn.convert_luma_only.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_chroma_only(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_chroma_only",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    convert_yuv_planes(
+        width,
+        height,
+        src_format,
+        src_strides,
+        src_buffers,
+        dst_format,
+        dst_strides,
+        dst_buffers,
+        convert_image::x86::YuvPlanes::Chroma,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn convert_yuv_planes(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    planes: convert_image::x86::YuvPlanes,
+) -> Result<(), ErrorKind> {
+    use convert_image::common::{Colorimetry, PixelFormatChannels, Sampler};
+    use convert_image::x86::{self, YuvLayout};
+
+    let (channels, sampler) = match src_format.pixel_format {
+        PixelFormat::Argb => (PixelFormatChannels::Four, Sampler::Argb),
+        PixelFormat::Bgra => (PixelFormatChannels::Four, Sampler::Bgra),
+        PixelFormat::Bgr => (PixelFormatChannels::Three, Sampler::Bgr),
+        _ => return Err(ErrorKind::InvalidOperation),
+    };
+
+    let layout = match dst_format.pixel_format {
+        PixelFormat::I444 => YuvLayout::I444,
+        PixelFormat::I420 => YuvLayout::I420,
+        PixelFormat::Nv12 => YuvLayout::Nv12,
+        _ => return Err(ErrorKind::InvalidOperation),
+    };
+
+    let colorimetry = match dst_format.color_space {
+        ColorSpace::Bt601 => Colorimetry::Bt601,
+        ColorSpace::Bt709 => Colorimetry::Bt709,
+        _ => return Err(ErrorKind::InvalidOperation),
+    };
+
+    let (_, _, last_dst_plane) = get_converter(width, height, src_format, dst_format)?;
+    if is_empty(width, height) {
+        return Ok(());
+    }
+
+    if x86::lrgb_to_yuv_fix16(
+        width,
+        height,
+        src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+        src_buffers,
+        last_dst_plane as usize,
+        dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+        dst_buffers,
+        channels,
+        sampler,
+        layout,
+        colorimetry,
+        Rounding::HalfUp,
+        planes,
+    ) {
+        Ok(())
+    } else {
+        Err(ErrorKind::NotEnoughData)
+    }
+}
+
+/// Returns an upper bound of the error of each sample written by [`convert_image_with_quality`]
+///
+/// The error of a sample is its distance from the value given by the exact conversion
+/// formulas, evaluated on real numbers and clipped to the range of the destination samples.
+/// It is expressed in 8-bit code values: one code value of a ten bit sample is 0.25, and
+/// a difference of 1 / 255 between floating point samples is 1.0.
+///
+/// The bound is derived from the constants of the kernels, holds for every instruction set,
+/// and covers YCbCr sources within their nominal range: the limited (studio) range, or the
+/// full range for `Jfif`. Routed conversions add up the error of each conversion of their
+/// route, amplified by the following conversions.
+/// The error of conversions that go through the perceptual quantizer (`Bt2020Pq` to
+/// `RgbaF16`) has no bound, and is reported as infinity.
+///
+/// # Arguments
+/// * `src_format` - Source image format
+/// * `dst_format` - Destination image format
+/// * `quality` - The quality of the kernels to use
+///
+/// # Errors
+///
+/// * [`NotInitialized`] if the library was not initialized before
+/// * [`InvalidValue`] if source or destination image formats have a number of planes
+///   which is not compatible with their pixel formats
+/// * [`InvalidValue`] if source or destination pixel format is not compatible with the
+///   color space
+/// * [`InvalidOperation`] if there is no available method to convert the image with the
+///   source pixel format to the image with the destination pixel format
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{max_error, ColorSpace, ImageFormat, PixelFormat, Quality};
+///
+/// dcp::initialize();
+///
+/// let src_format = ImageFormat {
+///     pixel_format: PixelFormat::Nv12,
+///     color_space: ColorSpace::Bt709,
+///     num_planes: 2,
+/// };
+///
+/// let dst_format = ImageFormat {
+///     pixel_format: PixelFormat::Bgra,
+///     color_space: ColorSpace::Lrgb,
+///     num_planes: 1,
+/// };
+///
+/// let fast = max_error(&src_format, &dst_format, Quality::Fast).unwrap();
+/// let accurate = max_error(&src_format, &dst_format, Quality::Accurate).unwrap();
+/// assert!(accurate <= fast && fast < 1.0);
+/// ```
+///
+/// [`convert_image_with_quality`]: ./fn.convert_image_with_quality.html
+/// [`NotInitialized`]: ./enum.ErrorKind.html#variant.NotInitialized
+/// [`InvalidValue`]: ./enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ./enum.ErrorKind.html#variant.InvalidOperation
+pub fn max_error(
+    src_format: &ImageFormat,
+    dst_format: &ImageFormat,
+    quality: Quality,
+) -> Result<f32, ErrorKind> {
+    // Routes depend on the parity of the image dimensions,
+    // as only even dimensions allow subsampled intermediate images
+    let mut bound: Option<f32> = None;
+    let mut failure = None;
+    for &(width, height) in [(2, 2), (1, 2), (2, 1), (1, 1)].iter() {
+        let route = match get_converter(width, height, src_format, dst_format) {
+            Ok(_) => Ok(Vec::new()),
+            Err(ErrorKind::InvalidOperation) => {
+                graph::find_route(get_converters()?, width, height, src_format, dst_format)
+            }
+            Err(error) => Err(error),
+        };
+
+        match route {
+            Ok(route) => {
+                let error = precision::get_max_error(src_format, &route, dst_format, quality);
+                bound = Some(bound.map_or(error, |bound| bound.max(error)));
+            }
+            Err(ErrorKind::NotInitialized) => return Err(ErrorKind::NotInitialized),
+            Err(error) => {
+                failure.get_or_insert(error);
+            }
+        }
+    }
+
+    bound.ok_or_else(|| failure.unwrap_or(ErrorKind::InvalidOperation))
+}
+
+/// Returns true if an image with the given dimensions has no pixels to convert
+fn is_empty(width: u32, height: u32) -> bool {
+    width == 0 || height == 0
+}
+
+fn get_differ() -> Result<DiffDispatcher, ErrorKind> {
+    unsafe {
+        if GLOBAL_STATE.init {
+            Ok(GLOBAL_STATE.differ)
+        } else {
+            Err(ErrorKind::NotInitialized)
+        }
+    }
+}
+
+fn get_blender() -> Result<BlendDispatcher, ErrorKind> {
+    unsafe {
+        if GLOBAL_STATE.init {
+            Ok(GLOBAL_STATE.blender)
+        } else {
+            Err(ErrorKind::NotInitialized)
+        }
+    }
+}
+
+fn get_remapper() -> Result<RemapDispatcher, ErrorKind> {
+    unsafe {
+        if GLOBAL_STATE.init {
+            Ok(GLOBAL_STATE.remapper)
+        } else {
+            Err(ErrorKind::NotInitialized)
+        }
+    }
+}
+
+fn get_cross_fader() -> Result<CrossFadeDispatcher, ErrorKind> {
+    unsafe {
+        if GLOBAL_STATE.init {
+            Ok(GLOBAL_STATE.cross_fader)
+        } else {
+            Err(ErrorKind::NotInitialized)
+        }
+    }
+}
+
+/// Returns the look-up table kernel, the portable one until the library is initialized
+#[cfg(feature = "cube")]
+fn get_cube_interpolator() -> CubeDispatcher {
+    unsafe { GLOBAL_STATE.cube_interpolator }
+}
+
+fn get_accumulator() -> Result<AccumulateDispatcher, ErrorKind> {
+    unsafe {
+        if GLOBAL_STATE.init {
+            Ok(GLOBAL_STATE.accumulator)
+        } else {
+            Err(ErrorKind::NotInitialized)
+        }
+    }
+}
+
+fn get_converter(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    dst_format: &ImageFormat,
+) -> Result<(ConvertDispatcher, u32, u32), ErrorKind> {
+    find_converter(get_converters()?, width, height, src_format, dst_format)
+}
+
+fn get_converters() -> Result<&'static ConverterTable, ErrorKind> {
+    unsafe {
+        if GLOBAL_STATE.init {
+            Ok(&*std::ptr::addr_of!(GLOBAL_STATE.converters))
+        } else {
+            Err(ErrorKind::NotInitialized)
+        }
+    }
+}
+
+fn find_converter(
+    converters: &ConverterTable,
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    dst_format: &ImageFormat,
+) -> Result<(ConvertDispatcher, u32, u32), ErrorKind> {
+    let src_pixel_format = src_format.pixel_format as u32;
+    let dst_pixel_format = dst_format.pixel_format as u32;
+    let src_color_space = src_format.color_space as u32;
+    let dst_color_space = dst_format.color_space as u32;
+
+    // Cross-correlate pixel format with color space. Predicate handles Table 1
+    let src_pf_mode = dispatcher::get_pixel_format_mode(src_pixel_format);
+    let src_cs_mode = dispatcher::get_color_space_mode(src_color_space);
+    let dst_pf_mode = dispatcher::get_pixel_format_mode(dst_pixel_format);
+    let dst_cs_mode = dispatcher::get_color_space_mode(dst_color_space);
+    let src_pf_cs_mismatch = src_pf_mode ^ src_cs_mode;
+    let dst_pf_cs_mismatch = dst_pf_mode ^ dst_cs_mode;
+    if src_pf_cs_mismatch | dst_pf_cs_mismatch {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    // Cross-correlate pixel format with planes and alignment.
+    // wrapping_sub is wanted. If num_planes is 0, this turns in a very big number that
+    // still represents an invalid number of planes.
+    let last_src_plane = src_format.num_planes.wrapping_sub(1);
+    if !pixel_format::is_compatible(src_pixel_format, width, height, last_src_plane) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let last_dst_plane = dst_format.num_planes.wrapping_sub(1);
+    if !pixel_format::is_compatible(dst_pixel_format, width, height, last_dst_plane) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    // Kernels compute packed strides in usize: reject lines that do not fit the address space
+    if !pixel_format::is_line_addressable(src_pixel_format, width)
+        || !pixel_format::is_line_addressable(dst_pixel_format, width)
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    // Cross-correlate modes.
+    let src_index = dispatcher::get_image_index(src_pixel_format, src_color_space, src_pf_mode);
+    let dst_index = dispatcher::get_image_index(dst_pixel_format, dst_color_space, dst_pf_mode);
+    let index = dispatcher::get_index(src_index, dst_index);
+    if index >= converters.len() {
+        return Err(ErrorKind::InvalidOperation);
+    }
+
+    match converters[index] {
+        None => Err(ErrorKind::InvalidOperation),
+        Some(image_converter) => Ok((image_converter, last_src_plane, last_dst_plane)),
+    }
+}
+
+/// A conversion whose parameters have been validated once and that can be
+/// executed many times on different buffers.
+///
+/// A plan holds the image dimensions, the source and destination strides and
+/// the conversion function selected for the running cpu. Use it when the same
+/// conversion is repeated many times per frame (e.g. per-tile conversions), to
+/// avoid validating the same parameters over and over.
+///
+/// A plan is executed with [`ConversionPlan::convert`], which only checks the library
+/// is initialized and the buffers are large enough.
+///
+/// [`ConversionPlan::convert`]: ./struct.ConversionPlan.html#method.convert
+pub struct ConversionPlan {
+    width: u32,
+    height: u32,
+    last_src_plane: u32,
+    src_strides: [usize; pixel_format::MAX_NUMBER_OF_PLANES],
+    dst_format: ImageFormat,
+    dst_strides: [usize; pixel_format::MAX_NUMBER_OF_PLANES],
+    converter: ConvertDispatcher,
+}
+
+fn copy_strides(
+    last_plane: u32,
+    strides: Option<&[usize]>,
+) -> Result<[usize; pixel_format::MAX_NUMBER_OF_PLANES], ErrorKind> {
+    let mut result = pixel_format::DEFAULT_STRIDES;
+    if let Some(strides) = strides {
+        let num_planes = (last_plane as usize) + 1;
+        if strides.len() < num_planes {
+            return Err(ErrorKind::NotEnoughData);
+        }
+
+        result[..num_planes].copy_from_slice(&strides[..num_planes]);
+    }
+
+    Ok(result)
+}
+
+impl ConversionPlan {
+    /// Validates the parameters of a conversion and selects the conversion function
+    ///
+    /// # Arguments
+    /// * `width` - Width of the image to convert in pixels
+    /// * `height` - Height of the image to convert in pixels
+    /// * `src_format` - Source image format
+    /// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+    ///   in each source image planes
+    /// * `dst_format` - Destination image format
+    /// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+    ///   in each destination image planes
+    ///
+    /// # Errors
+    /// The same errors reported by [`convert_image`] for the given parameters,
+    /// except the ones related to buffers, which are reported on execution.
+    ///
+    /// # Examples
+    /// ```
+    /// use dcv_color_primitives as dcp;
+    /// use dcp::{ColorSpace, ConversionPlan, ImageFormat, PixelFormat};
+    /// use std::error;
+    ///
+    /// fn convert_tiles() -> Result<(), Box<dyn error::Error>> {
+    ///     dcp::initialize();
+    ///
+    ///     const TILE_SIZE: u32 = 16;
+    ///
+    ///     let src_format = ImageFormat {
+    ///         pixel_format: PixelFormat::Bgra,
+    ///         color_space: ColorSpace::Lrgb,
+    ///         num_planes: 1,
+    ///     };
+    ///
+    ///     let dst_format = ImageFormat {
+    ///         pixel_format: PixelFormat::Nv12,
+    ///         color_space: ColorSpace::Bt601,
+    ///         num_planes: 1,
+    ///     };
+    ///
+    ///     let plan = ConversionPlan::new(TILE_SIZE, TILE_SIZE, &src_format, None, &dst_format, None)?;
+    ///
+    ///     let src_tile = [0u8; 4 * (TILE_SIZE as usize) * (TILE_SIZE as usize)];
+    ///     let mut dst_tile = [0u8; 3 * (TILE_SIZE as usize) * (TILE_SIZE as usize) / 2];
+    ///     for _ in 0..64 {
+    ///         plan.convert(&[&src_tile], &mut [&mut dst_tile])?;
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// [`convert_image`]: ./fn.convert_image.html
+    pub fn new(
+        width: u32,
+        height: u32,
+        src_format: &ImageFormat,
+        src_strides: Option<&[usize]>,
+        dst_format: &ImageFormat,
+        dst_strides: Option<&[usize]>,
+    ) -> Result<ConversionPlan, ErrorKind> {
+        let _span = trace_span!(
+            DEBUG,
+            "conversion_plan",
+            width,
+            height,
+            src = ?src_format.pixel_format,
+            dst = ?dst_format.pixel_format
+        );
+
+        let (converter, last_src_plane, last_dst_plane) =
+            get_converter(width, height, src_format, dst_format)?;
+
+        Ok(ConversionPlan {
+            width,
+            height,
+            last_src_plane,
+            src_strides: copy_strides(last_src_plane, src_strides)?,
+            dst_format: ImageFormat {
+                pixel_format: dst_format.pixel_format,
+                color_space: dst_format.color_space,
+                num_planes: dst_format.num_planes,
+            },
+            dst_strides: copy_strides(last_dst_plane, dst_strides)?,
+            converter,
+        })
+    }
+
+    /// Executes the conversion on the given buffers
+    ///
+    /// # Arguments
+    /// * `src_buffers` - An array of image buffers in each source color plane
+    /// * `dst_buffers` - An array of image buffers in each destination color plane
+    ///
+    /// # Errors
+    ///
+    /// * [`NotInitialized`] if the library was not initialized before
+    ///
+    /// * [`NotEnoughData`] if one or more source/destination buffers does not provide enough data.
+    ///
+    /// [`NotInitialized`]: ./enum.ErrorKind.html#variant.NotInitialized
+    /// [`NotEnoughData`]: ./enum.ErrorKind.html#variant.NotEnoughData
+    pub fn convert(
+        &self,
+        src_buffers: &[&[u8]],
+        dst_buffers: &mut [&mut [u8]],
+    ) -> Result<(), ErrorKind> {
+        unsafe {
+            if !GLOBAL_STATE.init {
+                return Err(ErrorKind::NotInitialized);
+            }
+        }
+
+        let _span = trace_span!(
+            DEBUG,
+            "convert_plan",
+            width = self.width,
+            height = self.height
+        );
+
+        if is_empty(self.width, self.height) {
+            return Ok(());
+        }
+
+        run_converter(
+            self.converter,
+            self.width,
+            self.height,
+            0,
+            self.last_src_plane,
+            &self.src_strides,
+            src_buffers,
+            &self.dst_format,
+            &self.dst_strides,
+            dst_buffers,
+        )
+    }
+}
+
+/// Converts an image whose buffers are given as raw pointers and lengths, like
+/// [`convert_image`]
+///
+/// This is meant for callers that can not build slices themselves without taking on the
+/// aliasing and validity requirements of slices, for example language bindings or code
+/// working with memory mapped from a device. Null pointers are accepted for buffers whose
+/// length is zero.
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of pointers to the image buffers in each source color plane
+/// * `src_lengths` - An array of lengths in bytes of the buffers in `src_buffers`
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of pointers to the image buffers in each destination color plane
+/// * `dst_lengths` - An array of lengths in bytes of the buffers in `dst_buffers`
+///
+/// # Safety
+/// For the whole duration of the call:
+/// * each source pointer must be valid for reads of its length in bytes
+/// * each destination pointer must be valid for reads and writes of its length in bytes
+/// * destination buffers must not overlap each other, nor any source buffer
+/// * no other thread may write to the source buffers, nor access the destination buffers
+/// * no length may exceed `isize::MAX`
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if a pointer is null and its length is not zero
+///
+/// * [`NotEnoughData`] if a buffers array and its lengths array do not have the same length
+///
+/// * All the errors reported by [`convert_image`]
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{ColorSpace, ImageFormat, PixelFormat};
+///
+/// dcp::initialize();
+///
+/// const WIDTH: u32 = 640;
+/// const HEIGHT: u32 = 480;
+///
+/// let src_format = ImageFormat {
+///     pixel_format: PixelFormat::Bgra,
+///     color_space: ColorSpace::Lrgb,
+///     num_planes: 1,
+/// };
+///
+/// let dst_format = ImageFormat {
+///     pixel_format: PixelFormat::Nv12,
+///     color_space: ColorSpace::Bt601,
+///     num_planes: 1,
+/// };
+///
+/// let src_buffer = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+/// let mut dst_buffer = vec![0u8; 3 * (WIDTH as usize) * (HEIGHT as usize) / 2];
+///
+/// let result = unsafe {
+///     dcp::convert_image_from_raw_parts(
+///         WIDTH,
+///         HEIGHT,
+///         &src_format,
+///         None,
+///         &[src_buffer.as_ptr()],
+///         &[src_buffer.len()],
+///         &dst_format,
+///         None,
+///         &[dst_buffer.as_mut_ptr()],
+///         &[dst_buffer.len()],
+///     )
+/// };
+/// assert!(result.is_ok());
+/// ```
+///
+/// [`convert_image`]: ./fn.convert_image.html
+/// [`InvalidValue`]: ./enum.ErrorKind.html#variant.InvalidValue
+/// [`NotEnoughData`]: ./enum.ErrorKind.html#variant.NotEnoughData
+#[allow(clippy::too_many_arguments)]
+pub unsafe fn convert_image_from_raw_parts(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[*const u8],
+    src_lengths: &[usize],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &[*mut u8],
+    dst_lengths: &[usize],
+) -> Result<(), ErrorKind> {
+    if src_buffers.len() != src_lengths.len() || dst_buffers.len() != dst_lengths.len() {
+        return Err(ErrorKind::NotEnoughData);
+    }
+
+    let src_buffers = src_buffers
+        .iter()
+        .zip(src_lengths.iter())
+        .map(|(&ptr, &len)| match (ptr.is_null(), len) {
+            (true, 0) => Ok(&[][..]),
+            (true, _) => Err(ErrorKind::InvalidValue),
+            (false, _) => Ok(std::slice::from_raw_parts(ptr, len)),
+        })
+        .collect::<Result<Vec<&[u8]>, ErrorKind>>()?;
+
+    let mut dst_buffers = dst_buffers
+        .iter()
+        .zip(dst_lengths.iter())
+        .map(|(&ptr, &len)| match (ptr.is_null(), len) {
+            (true, 0) => Ok(&mut [][..]),
+            (true, _) => Err(ErrorKind::InvalidValue),
+            (false, _) => Ok(std::slice::from_raw_parts_mut(ptr, len)),
+        })
+        .collect::<Result<Vec<&mut [u8]>, ErrorKind>>()?;
+
+    convert_image(
+        width,
+        height,
+        src_format,
+        src_strides,
+        &src_buffers,
+        dst_format,
+        dst_strides,
+        &mut dst_buffers,
+    )
+}
+
+/// Converts from a color space to another one, writing into uninitialized destination buffers.
+///
+/// It behaves like [`convert_image`], but callers do not have to initialize the destination
+/// buffers before each conversion. Every byte of the destination buffers is initialized:
+/// the buffers are set to zero, then the image lines are written by the conversion, so the
+/// padding at the end of the lines, between the planes and after the image stays zero.
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of uninitialized image buffers in each destination color plane
+///
+/// # Errors
+///
+/// * All the errors reported by [`convert_image`]. The destination buffers are still
+///   initialized when an error is returned, but their content is unspecified
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{convert_image_uninit, ColorSpace, ImageFormat, PixelFormat};
+/// use std::mem::MaybeUninit;
+///
+/// dcp::initialize();
+///
+/// const WIDTH: usize = 640;
+/// const HEIGHT: usize = 480;
+///
+/// let src_format = ImageFormat {
+///     pixel_format: PixelFormat::Bgra,
+///     color_space: ColorSpace::Lrgb,
+///     num_planes: 1,
+/// };
+///
+/// let dst_format = ImageFormat {
+///     pixel_format: PixelFormat::Nv12,
+///     color_space: ColorSpace::Bt601,
+///     num_planes: 1,
+/// };
+///
+/// let src_buffer = vec![0u8; 4 * WIDTH * HEIGHT];
+/// let mut dst_buffer = vec![MaybeUninit::<u8>::uninit(); 3 * WIDTH * HEIGHT / 2];
+///
+/// convert_image_uninit(
+///     WIDTH as u32,
+///     HEIGHT as u32,
+///     &src_format,
+///     None,
+///     &[&src_buffer],
+///     &dst_format,
+///     None,
+///     &mut [&mut dst_buffer],
+/// )
+/// .unwrap();
+///
+/// // The whole buffer has been initialized
+/// let dst_buffer: Vec<u8> = dst_buffer
+///     .into_iter()
+///     .map(|sample| unsafe { sample.assume_init() })
+///     .collect();
+/// assert_eq!(dst_buffer[0], 16);
+/// ```
+///
+/// [`convert_image`]: ./fn.convert_image.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_image_uninit(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [MaybeUninit<u8>]],
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_image_uninit",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    // Zero the buffers first: the padding stays zero, and no reference to bytes that are not
+    // initialized is ever created
+    let mut dst_buffers: Vec<&mut [u8]> = dst_buffers
+        .iter_mut()
+        .map(|buffer| {
+            for sample in buffer.iter_mut() {
+                *sample = MaybeUninit::new(0);
+            }
+
+            // Safety: every byte of the buffer has just been initialized
+            unsafe {
+                std::slice::from_raw_parts_mut(buffer.as_mut_ptr().cast::<u8>(), buffer.len())
+            }
+        })
+        .collect();
+
+    convert_image(
+        width,
+        height,
+        src_format,
+        src_strides,
+        src_buffers,
+        dst_format,
+        dst_strides,
+        &mut dst_buffers,
+    )
+}
+
+/// A token used to abort a conversion from another thread.
+///
+/// Clones of a token share the same state, so the token can be handed to the thread
+/// running [`convert_image_cancellable`] while another one (e.g. a user interface)
+/// keeps a clone to call [`cancel`] on.
+///
+/// [`convert_image_cancellable`]: ./fn.convert_image_cancellable.html
+/// [`cancel`]: ./struct.CancellationToken.html#method.cancel
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Creates a new token, not cancelled
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Requests the cancellation of the conversions using this token
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if [`cancel`] was called on this token or on one of its clones
+    ///
+    /// [`cancel`]: ./struct.CancellationToken.html#method.cancel
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Number of lines converted between two checks of the cancellation token
+const CANCELLATION_BAND_HEIGHT: usize = 64;
+
+/// Location of a logical plane inside the image buffers
+struct PlaneLayout {
+    buffer: usize,
+    offset: usize,
+    stride: usize,
+    depth: usize,
+    horizontal_shift: u32,
+    vertical_shift: u32,
+}
+
+fn get_plane_layouts(
+    format: &ImageFormat,
+    width: u32,
+    height: u32,
+    last_plane: u32,
+    strides: &[usize],
+) -> Result<Vec<PlaneLayout>, ErrorKind> {
+    let last_plane = last_plane as usize;
+    if last_plane >= strides.len() {
+        return Err(ErrorKind::NotEnoughData);
+    }
+
+    // Planes sharing a buffer are stored one after the other, using the stride of the buffer
+    // unless their own stride is given
+    let planes = pixel_format::get_logical_planes(format.pixel_format);
+    let mut layouts = Vec::with_capacity(planes.len());
+    let mut offset = 0;
+    for (i, &(depth, horizontal_shift, vertical_shift)) in planes.iter().enumerate() {
+        let buffer = i.min(last_plane);
+        if buffer == i {
+            offset = 0;
+        }
+
+        // Lines can not overlap, or bands would not be disjoint
+        let line_size = depth * ((width >> horizontal_shift) as usize);
+        let requested = match strides.get(i) {
+            Some(&stride) if buffer != i && stride != STRIDE_AUTO => stride,
+            _ => strides[buffer],
+        };
+
+        let stride = match requested {
+            STRIDE_AUTO => line_size,
+            stride if stride < line_size => return Err(ErrorKind::NotEnoughData),
+            stride => stride,
+        };
+
+        layouts.push(PlaneLayout {
+            buffer,
+            offset,
+            stride,
+            depth,
+            horizontal_shift,
+            vertical_shift,
+        });
+
+        offset = stride
+            .checked_mul((height >> vertical_shift) as usize)
+            .and_then(|size| size.checked_add(offset))
+            .ok_or(ErrorKind::NotEnoughData)?;
+    }
+
+    Ok(layouts)
+}
+
+/// Returns true if the strides of an image stored in a single buffer give the stride of
+/// some logical planes after the first one
+fn has_logical_strides(width: u32, height: u32, format: &ImageFormat, strides: &[usize]) -> bool {
+    let pixel_format = format.pixel_format as u32;
+    let planes = pixel_format::get_logical_planes(format.pixel_format).len();
+    format.num_planes == 1
+        && planes > 1
+        && pixel_format::is_compatible(pixel_format, width, height, 0)
+        && pixel_format::are_planes_compatible(pixel_format, planes as u32)
+        && strides
+            .iter()
+            .take(planes)
+            .skip(1)
+            .any(|&stride| stride != STRIDE_AUTO)
+}
+
+/// Format and strides of an image stored in a single buffer, viewed as an image having
+/// a buffer for each logical plane
+fn get_logical_layout(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: &[usize],
+) -> Result<(ImageFormat, Vec<PlaneLayout>), ErrorKind> {
+    let layouts = get_plane_layouts(format, width, height, 0, strides)?;
+    let logical_format = ImageFormat {
+        pixel_format: format.pixel_format,
+        color_space: format.color_space,
+        num_planes: layouts.len() as u32,
+    };
+
+    Ok((logical_format, layouts))
+}
+
+/// Format, strides and buffers of each logical plane of an image stored in a single buffer
+type LogicalPlanes<T> = (ImageFormat, Vec<usize>, Vec<T>);
+
+fn split_logical_planes<'a>(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: &[usize],
+    buffers: &[&'a [u8]],
+) -> Result<LogicalPlanes<&'a [u8]>, ErrorKind> {
+    let (logical_format, layouts) = get_logical_layout(width, height, format, strides)?;
+    let mut remaining = *buffers.first().ok_or(ErrorKind::NotEnoughData)?;
+
+    // Planes are stored one after the other, the last one takes the rest of the buffer
+    let mut planes = Vec::with_capacity(layouts.len());
+    let mut position = 0;
+    for layout in layouts.iter().skip(1) {
+        let (plane, rest) = remaining.split_at((layout.offset - position).min(remaining.len()));
+        planes.push(plane);
+        remaining = rest;
+        position = layout.offset;
+    }
+    planes.push(remaining);
+
+    let strides = layouts.iter().map(|layout| layout.stride).collect();
+    Ok((logical_format, strides, planes))
+}
+
+fn split_logical_planes_mut<'a>(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: &[usize],
+    buffers: &'a mut [&mut [u8]],
+) -> Result<LogicalPlanes<&'a mut [u8]>, ErrorKind> {
+    let (logical_format, layouts) = get_logical_layout(width, height, format, strides)?;
+    let mut remaining = &mut buffers.first_mut().ok_or(ErrorKind::NotEnoughData)?[..];
+
+    let mut planes = Vec::with_capacity(layouts.len());
+    let mut position = 0;
+    for layout in layouts.iter().skip(1) {
+        let size = (layout.offset - position).min(remaining.len());
+        let (plane, rest) = { remaining }.split_at_mut(size);
+        planes.push(plane);
+        remaining = rest;
+        position = layout.offset;
+    }
+    planes.push(remaining);
+
+    let strides = layouts.iter().map(|layout| layout.stride).collect();
+    Ok((logical_format, strides, planes))
+}
+
+fn get_band_range(layout: &PlaneLayout, first_line: usize, last_line: usize) -> (usize, usize) {
+    (
+        layout.offset + (first_line >> layout.vertical_shift) * layout.stride,
+        layout.offset + (last_line >> layout.vertical_shift) * layout.stride,
+    )
+}
+
+fn get_src_band<'a>(
+    buffers: &[&'a [u8]],
+    layouts: &[PlaneLayout],
+    first_line: usize,
+    last_line: usize,
+) -> Option<Vec<&'a [u8]>> {
+    layouts
+        .iter()
+        .map(|layout| {
+            let (start, end) = get_band_range(layout, first_line, last_line);
+            buffers.get(layout.buffer)?.get(start..end)
+        })
+        .collect()
+}
+
+fn get_dst_band<'a>(
+    buffers: &'a mut [&mut [u8]],
+    layouts: &[PlaneLayout],
+    first_line: usize,
+    last_line: usize,
+) -> Option<Vec<&'a mut [u8]>> {
+    let mut band = Vec::with_capacity(layouts.len());
+    let mut buffer_iter = buffers.iter_mut();
+    let mut remainder: &mut [u8] = &mut [];
+    let mut position = 0;
+    for (i, layout) in layouts.iter().enumerate() {
+        if layout.buffer == i {
+            remainder = &mut buffer_iter.next()?[..];
+            position = 0;
+        }
+
+        let (start, end) = get_band_range(layout, first_line, last_line);
+        if end - position > remainder.len() {
+            return None;
+        }
+
+        let (_, tail) = std::mem::take(&mut remainder).split_at_mut(start - position);
+        let (plane, tail) = tail.split_at_mut(end - start);
+        remainder = tail;
+        position = end;
+        band.push(plane);
+    }
+
+    Some(band)
+}
+
+/// Strides of the logical planes of a band, laid out as in the image
+fn get_band_strides(layouts: &[PlaneLayout]) -> Vec<usize> {
+    layouts.iter().map(|layout| layout.stride).collect()
+}
+
+/// Returns the first and last line (excluded) of each band of an image having `line_count`
+/// lines, converted `band_height` lines at a time
+fn get_bands(line_count: usize, band_height: usize) -> impl Iterator<Item = (usize, usize)> {
+    (0..line_count)
+        .step_by(band_height)
+        .map(move |first_line| (first_line, line_count.min(first_line + band_height)))
+}
+
+/// Format of the bands of an image, which have one buffer for each logical plane
+fn get_band_format(format: &ImageFormat) -> ImageFormat {
+    ImageFormat {
+        pixel_format: format.pixel_format,
+        color_space: format.color_space,
+        num_planes: pixel_format::get_logical_planes(format.pixel_format).len() as u32,
+    }
+}
+
+/// Runs a conversion function writing to the destination image of the caller, or to the
+/// band of it starting at `first_line`. Every conversion writes its output through here,
+/// so that the `noise` feature perturbs it whatever the entry point
+#[allow(clippy::too_many_arguments)]
+#[cfg_attr(not(feature = "noise"), allow(unused_variables))]
+fn run_converter(
+    converter: ConvertDispatcher,
+    width: u32,
+    height: u32,
+    first_line: usize,
+    last_src_plane: u32,
+    src_strides: &[usize],
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: &[usize],
+    dst_buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    if !converter(
+        width,
+        height,
+        last_src_plane,
+        src_strides,
+        src_buffers,
+        dst_format.num_planes.wrapping_sub(1),
+        dst_strides,
+        dst_buffers,
+    ) {
+        return Err(ErrorKind::NotEnoughData);
+    }
+
+    #[cfg(feature = "noise")]
+    noise::perturb(
+        width,
+        height,
+        first_line,
+        dst_format,
+        dst_strides,
+        dst_buffers,
+    );
+    Ok(())
+}
+
+/// Lines of the source and destination images converted at once by a conversion done
+/// in bands. Each band is converted as an image having one buffer for each logical plane,
+/// whose format is `dst_format` for the destination image
+struct Band<'a> {
+    first_line: usize,
+    last_line: usize,
+    src_strides: &'a [usize],
+    src_buffers: Vec<&'a [u8]>,
+    dst_format: &'a ImageFormat,
+    dst_strides: &'a [usize],
+    dst_buffers: Vec<&'a mut [u8]>,
+}
+
+impl<'a> Band<'a> {
+    fn height(&self) -> usize {
+        self.last_line - self.first_line
+    }
+
+    fn convert(&mut self, converter: ConvertDispatcher, width: u32) -> Result<(), ErrorKind> {
+        run_converter(
+            converter,
+            width,
+            self.height() as u32,
+            self.first_line,
+            (self.src_strides.len() - 1) as u32,
+            self.src_strides,
+            &self.src_buffers,
+            self.dst_format,
+            self.dst_strides,
+            &mut self.dst_buffers,
+        )
+    }
+}
+
+/// Calls `process_band` for each band of the source and destination images, from top
+/// to bottom, stopping at the first error
+fn convert_in_bands<I, F>(
+    src_layouts: &[PlaneLayout],
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_layouts: &[PlaneLayout],
+    dst_buffers: &mut [&mut [u8]],
+    bands: I,
+    mut process_band: F,
+) -> Result<(), ErrorKind>
+where
+    I: IntoIterator<Item = (usize, usize)>,
+    F: FnMut(Band) -> Result<(), ErrorKind>,
+{
+    let src_strides = get_band_strides(src_layouts);
+    let dst_format = get_band_format(dst_format);
+    let dst_strides = get_band_strides(dst_layouts);
+    for (first_line, last_line) in bands {
+        let _band_span = trace_span!(TRACE, "band", first_line, last_line);
+        process_band(Band {
+            first_line,
+            last_line,
+            src_strides: &src_strides,
+            src_buffers: get_src_band(src_buffers, src_layouts, first_line, last_line)
+                .ok_or(ErrorKind::NotEnoughData)?,
+            dst_format: &dst_format,
+            dst_strides: &dst_strides,
+            dst_buffers: get_dst_band(dst_buffers, dst_layouts, first_line, last_line)
+                .ok_or(ErrorKind::NotEnoughData)?,
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Converts from a color space to another one, like [`convert_image`], checking for
+/// cancellation requests while the conversion is in progress.
+///
+/// The image is converted in horizontal bands, and `token` is checked before each band
+/// is converted. When the conversion is cancelled, part of the destination image might
+/// have been already written.
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+/// * `token` - Token used to cancel the conversion
+///
+/// # Errors
+///
+/// * [`Cancelled`] if `token` was cancelled before the conversion completed
+///
+/// * All the errors reported by [`convert_image`]
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{convert_image_cancellable, CancellationToken, ColorSpace, ImageFormat, PixelFormat};
+/// use std::error;
+/// use std::thread;
+///
+/// fn convert() -> Result<(), Box<dyn error::Error>> {
+///     dcp::initialize();
+///
+///     const WIDTH: u32 = 640;
+///     const HEIGHT: u32 = 480;
+///
+///     let src_format = ImageFormat {
+///         pixel_format: PixelFormat::Bgra,
+///         color_space: ColorSpace::Lrgb,
+///         num_planes: 1,
+///     };
+///
+///     let dst_format = ImageFormat {
+///         pixel_format: PixelFormat::Nv12,
+///         color_space: ColorSpace::Bt601,
+///         num_planes: 1,
+///     };
+///
+///     let token = CancellationToken::new();
+///     let worker_token = token.clone();
+///     let worker = thread::spawn(move || {
+///         let src_buffers: &[&[u8]] = &[&[0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)]];
+///         let dst_buffers: &mut [&mut [u8]] =
+///             &mut [&mut [0u8; 3 * (WIDTH as usize) * (HEIGHT as usize) / 2]];
+///
+///         convert_image_cancellable(
+///             WIDTH,
+///             HEIGHT,
+///             &src_format,
+///             None,
+///             src_buffers,
+///             &dst_format,
+///             None,
+///             dst_buffers,
+///             &worker_token,
+///         )
+///     });
+///
+///     token.cancel();
+///     let _ = worker.join();
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// [`Cancelled`]: ./enum.ErrorKind.html#variant.Cancelled
+/// [`convert_image`]: ./fn.convert_image.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_image_cancellable(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    token: &CancellationToken,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_image_cancellable",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    let (converter, last_src_plane, last_dst_plane) =
+        get_converter(width, height, src_format, dst_format)?;
+
+    if is_empty(width, height) {
+        return Ok(());
+    }
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let src_layouts = get_plane_layouts(src_format, width, height, last_src_plane, src_strides)?;
+    let dst_layouts = get_plane_layouts(dst_format, width, height, last_dst_plane, dst_strides)?;
+
+    convert_in_bands(
+        &src_layouts,
+        src_buffers,
+        dst_format,
+        &dst_layouts,
+        dst_buffers,
+        get_bands(height as usize, CANCELLATION_BAND_HEIGHT),
+        |mut band| {
+            if token.is_cancelled() {
+                return Err(ErrorKind::Cancelled);
+            }
+
+            band.convert(converter, width)
+        },
+    )
+}
+
+/// Converts from a color space to another one, like [`convert_image`], skipping the
+/// lines that did not change since the previous conversion.
+///
+/// Screen capture applications usually convert frames where most lines are identical to
+/// the ones of the previous frame. `dirty_rows` is a bitmap of the source lines that
+/// changed: line `y` changed if bit `y % 8` (least significant first) of byte `y / 8` is
+/// set. Only the destination lines computed from changed lines are written, the others
+/// keep the content of the previous conversion.
+///
+/// Lines sharing chroma samples, in the source or in the destination image, are
+/// converted together: with 4:2:0 formats, a change in either line of a pair converts
+/// both.
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+/// * `dirty_rows` - Bitmap of the changed source lines. If `None`, the whole image is
+///   converted.
+///
+/// # Errors
+///
+/// * [`NotEnoughData`] if `dirty_rows` has fewer bits than the image lines
+///
+/// * All the errors reported by [`convert_image`], except that conversions are never
+///   routed when `dirty_rows` is not `None`
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{convert_image_dirty_rows, ColorSpace, ImageFormat, PixelFormat};
+///
+/// dcp::initialize();
+///
+/// const WIDTH: u32 = 2;
+/// const HEIGHT: u32 = 4;
+///
+/// let src_format = ImageFormat {
+///     pixel_format: PixelFormat::Bgra,
+///     color_space: ColorSpace::Lrgb,
+///     num_planes: 1,
+/// };
+///
+/// let dst_format = ImageFormat {
+///     pixel_format: PixelFormat::Nv12,
+///     color_space: ColorSpace::Bt601,
+///     num_planes: 2,
+/// };
+///
+/// let bgra = [255u8; 32];
+/// let (mut y, mut uv) = ([0u8; 8], [0u8; 4]);
+///
+/// // Only line 1 changed, line 0 shares its chroma samples and is converted too
+/// convert_image_dirty_rows(
+///     WIDTH,
+///     HEIGHT,
+///     &src_format,
+///     None,
+///     &[&bgra],
+///     &dst_format,
+///     None,
+///     &mut [&mut y, &mut uv],
+///     Some(&[0b0010]),
+/// )
+/// .unwrap();
+///
+/// assert_eq!(y, [235, 235, 235, 235, 0, 0, 0, 0]);
+/// assert_eq!(uv, [128, 128, 0, 0]);
+/// ```
+///
+/// [`NotEnoughData`]: ./enum.ErrorKind.html#variant.NotEnoughData
+/// [`convert_image`]: ./fn.convert_image.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_image_dirty_rows(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    dirty_rows: Option<&[u8]>,
+) -> Result<(), ErrorKind> {
+    let dirty_rows = match dirty_rows {
+        Some(dirty_rows) => dirty_rows,
+        None => {
+            return convert_image(
+                width,
+                height,
+                src_format,
+                src_strides,
+                src_buffers,
+                dst_format,
+                dst_strides,
+                dst_buffers,
+            )
+        }
+    };
+
+    let _span = trace_span!(
+        DEBUG,
+        "convert_image_dirty_rows",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    let (converter, last_src_plane, last_dst_plane) =
+        get_converter(width, height, src_format, dst_format)?;
+
+    let line_count = height as usize;
+    if dirty_rows.len() < line_count.div_ceil(8) {
+        return Err(ErrorKind::NotEnoughData);
+    }
+
+    if is_empty(width, height) {
+        return Ok(());
+    }
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let src_layouts = get_plane_layouts(src_format, width, height, last_src_plane, src_strides)?;
+    let dst_layouts = get_plane_layouts(dst_format, width, height, last_dst_plane, dst_strides)?;
+
+    // Lines sharing chroma samples are converted together
+    let max_shift = src_layouts
+        .iter()
+        .chain(dst_layouts.iter())
+        .map(|layout| layout.vertical_shift)
+        .max()
+        .unwrap_or(0);
+    let group_height = 1usize << max_shift;
+    let group_end = |line: usize| line_count.min(line + group_height);
+    let is_dirty =
+        |line: usize| (line..group_end(line)).any(|y| dirty_rows[y / 8] & (1 << (y % 8)) != 0);
+
+    let mut next_line = 0;
+    let bands = std::iter::from_fn(|| {
+        let mut first_line = next_line;
+        while first_line < line_count && !is_dirty(first_line) {
+            first_line = group_end(first_line);
+        }
+
+        if first_line >= line_count {
+            return None;
+        }
+
+        // Consecutive changed lines are converted as a single band
+        let mut last_line = group_end(first_line);
+        while last_line < line_count && is_dirty(last_line) {
+            last_line = group_end(last_line);
+        }
+
+        next_line = last_line;
+        Some((first_line, last_line))
+    });
+
+    convert_in_bands(
+        &src_layouts,
+        src_buffers,
+        dst_format,
+        &dst_layouts,
+        dst_buffers,
+        bands,
+        |mut band| band.convert(converter, width),
+    )
+}
+
+/// Height of the bands checksummed by [`convert_image_with_checksum`] right after being
+/// converted, while they are still in cache
+///
+/// [`convert_image_with_checksum`]: ./fn.convert_image_with_checksum.html
+const CHECKSUM_BAND_HEIGHT: usize = 64;
+
+/// Height of the bands padded by [`convert_image_aligned`] right after being converted,
+/// while they are still in cache
+///
+/// [`convert_image_aligned`]: ./fn.convert_image_aligned.html
+const PADDING_BAND_HEIGHT: usize = 64;
+
+fn update_checksums<T: AsRef<[u8]>>(
+    checksums: &mut [checksum::Crc32],
+    band: &[T],
+    layouts: &[PlaneLayout],
+    width: u32,
+    first_line: usize,
+    last_line: usize,
+) {
+    for ((checksum, plane), layout) in checksums.iter_mut().zip(band).zip(layouts) {
+        let line_size = layout.depth * ((width >> layout.horizontal_shift) as usize);
+        let line_count =
+            (last_line >> layout.vertical_shift) - (first_line >> layout.vertical_shift);
+        for line in plane.as_ref().chunks(layout.stride).take(line_count) {
+            checksum.update(&line[..line_size]);
+        }
+    }
+}
+
+/// Computes a checksum for each plane of an image.
+///
+/// The checksum of a plane is the CRC-32 (the one computed by zlib `crc32`) of its lines,
+/// from top to bottom, excluding the padding bytes between the end of a line and the start
+/// of the next one. Checksums are returned in logical plane order (e.g. luma and
+/// interleaved chroma for [`Nv12`]), also when some planes share the same buffer, so they
+/// do not depend on strides nor on the number of buffers.
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `format` - Image format
+/// * `strides` - An array of distances in bytes between starts of consecutive lines
+///   in each image planes
+/// * `buffers` - An array of image buffers in each color plane
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the image format or the image dimensions do not satisfy the
+///   [`size constraints`]
+///
+/// * [`NotEnoughData`] if the strides or the buffers are not large enough for the image
+///
+/// [`Nv12`]: ./enum.PixelFormat.html#variant.Nv12
+/// [`InvalidValue`]: ./enum.ErrorKind.html#variant.InvalidValue
+/// [`NotEnoughData`]: ./enum.ErrorKind.html#variant.NotEnoughData
+/// [`size constraints`]: ./struct.ImageFormat.html#note
+pub fn image_checksum(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+    buffers: &[&[u8]],
+) -> Result<Vec<u32>, ErrorKind> {
     let last_plane = format.num_planes.wrapping_sub(1);
-    if !pixel_format::is_compatible(pixel_format, width, height, last_plane) {
+    if !pixel_format::is_compatible(format.pixel_format as u32, width, height, last_plane) {
         return Err(ErrorKind::InvalidValue);
     }
 
-    if pixel_format::get_buffers_size(
-        pixel_format,
-        width,
-        height,
-        last_plane,
-        strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
-        buffers_size,
-    ) {
-        Ok(())
-    } else {
-        Err(ErrorKind::NotEnoughData)
-    }
+    let strides = strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let layouts = get_plane_layouts(format, width, height, last_plane, strides)?;
+    let line_count = height as usize;
+    let band = get_src_band(buffers, &layouts, 0, line_count).ok_or(ErrorKind::NotEnoughData)?;
+
+    let mut checksums = vec![checksum::Crc32::new(); layouts.len()];
+    update_checksums(&mut checksums, &band, &layouts, width, 0, line_count);
+
+    Ok(checksums.into_iter().map(checksum::Crc32::finish).collect())
 }
 
-/// Converts from a color space to another one, applying downsampling/upsampling
-/// to match destination image format.
+/// Converts from a color space to another one, like [`convert_image`], returning a
+/// checksum for each plane of the destination image.
+///
+/// The image is converted in bands and each band is checksummed right after being
+/// written, so the cost over [`convert_image`] is small. Checksums are the ones returned by
+/// [`image_checksum`] for the destination image: streaming tests can compare them against
+/// golden values instead of keeping copies of the converted frames.
 ///
 /// # Arguments
 /// * `width` - Width of the image to convert in pixels
 /// * `height` - Height of the image to convert in pixels
 /// * `src_format` - Source image format
 /// * `src_strides` - An array of distances in bytes between starts of consecutive lines
-///                   in each source image planes
+///   in each source image planes
 /// * `src_buffers` - An array of image buffers in each source color plane
 /// * `dst_format` - Destination image format
 /// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
-///                   in each destination image planes
+///   in each destination image planes
 /// * `dst_buffers` - An array of image buffers in each destination color plane
 ///
 /// # Errors
 ///
-/// * [`NotInitialized`] if the library was not initialized before
+/// * All the errors reported by [`convert_image`], except that conversions are never
+///   routed
 ///
-/// * [`InvalidValue`] if `width` or `height` violate the [`size constraints`]
-///   that might by imposed by the source and destination image pixel formats
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{convert_image_with_checksum, ColorSpace, ImageFormat, PixelFormat};
 ///
-/// * [`InvalidValue`] if source or destination image formats have a number of planes
-///   which is not compatible with their pixel formats
+/// dcp::initialize();
 ///
-/// * [`InvalidOperation`] if there is no available method to convert the image with the
-///   source pixel format to the image with the destination pixel format.
+/// let src_format = ImageFormat {
+///     pixel_format: PixelFormat::Bgra,
+///     color_space: ColorSpace::Lrgb,
+///     num_planes: 1,
+/// };
 ///
-///   The list of available conversions is specified here:
+/// let dst_format = ImageFormat {
+///     pixel_format: PixelFormat::Nv12,
+///     color_space: ColorSpace::Bt601,
+///     num_planes: 1,
+/// };
 ///
-///   Source image pixel format     | Supported destination image pixel formats
-///   ------------------------------|------------------------------------------
-///   PixelFormat::Argb             | PixelFormat::I420 [`1`]
-///   PixelFormat::Argb             | PixelFormat::I444 [`1`]
-///   PixelFormat::Argb             | PixelFormat::Nv12 [`1`]
-///   PixelFormat::Bgra             | PixelFormat::I420 [`1`]
-///   PixelFormat::Bgra             | PixelFormat::I444 [`1`]
-///   PixelFormat::Bgra             | PixelFormat::Nv12 [`1`]
-///   PixelFormat::Bgra             | PixelFormat::Rgb  [`4`]
-///   PixelFormat::Bgr              | PixelFormat::I420 [`1`]
-///   PixelFormat::Bgr              | PixelFormat::I444 [`1`]
-///   PixelFormat::Bgr              | PixelFormat::Nv12 [`1`]
-///   PixelFormat::I420             | PixelFormat::Bgra [`2`]
-///   PixelFormat::I444             | PixelFormat::Bgra [`2`]
-///   PixelFormat::Nv12             | PixelFormat::Bgra [`2`]
-///   PixelFormat::Rgb              | PixelFormat::Bgra [`3`]
+/// let bgra = [255u8; 16];
+/// let mut nv12 = [0u8; 6];
 ///
-/// * [`NotEnoughData`] if the source stride array is not `None` and its length is less than the
-///   source image format number of planes
+/// let checksums = convert_image_with_checksum(
+///     2,
+///     2,
+///     &src_format,
+///     None,
+///     &[&bgra],
+///     &dst_format,
+///     None,
+///     &mut [&mut nv12],
+/// )
+/// .unwrap();
 ///
-/// * [`NotEnoughData`] if the destination stride array is not `None` and its length is less than the
-///   destination image format number of planes
+/// assert_eq!(
+///     checksums,
+///     dcp::image_checksum(2, 2, &dst_format, None, &[&nv12]).unwrap()
+/// );
+/// ```
 ///
-/// * [`NotEnoughData`] if one or more source/destination buffers does not provide enough data.
+/// [`convert_image`]: ./fn.convert_image.html
+/// [`image_checksum`]: ./fn.image_checksum.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_image_with_checksum(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+) -> Result<Vec<u32>, ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_image_with_checksum",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    let (converter, last_src_plane, last_dst_plane) =
+        get_converter(width, height, src_format, dst_format)?;
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let src_layouts = get_plane_layouts(src_format, width, height, last_src_plane, src_strides)?;
+    let dst_layouts = get_plane_layouts(dst_format, width, height, last_dst_plane, dst_strides)?;
+
+    let mut checksums = vec![checksum::Crc32::new(); dst_layouts.len()];
+    let line_count = if is_empty(width, height) {
+        0
+    } else {
+        height as usize
+    };
+
+    convert_in_bands(
+        &src_layouts,
+        src_buffers,
+        dst_format,
+        &dst_layouts,
+        dst_buffers,
+        get_bands(line_count, CHECKSUM_BAND_HEIGHT),
+        |mut band| {
+            band.convert(converter, width)?;
+            update_checksums(
+                &mut checksums,
+                &band.dst_buffers,
+                &dst_layouts,
+                width,
+                band.first_line,
+                band.last_line,
+            );
+            Ok(())
+        },
+    )?;
+
+    Ok(checksums.into_iter().map(checksum::Crc32::finish).collect())
+}
+
+fn fill_padding(
+    band: &mut [&mut [u8]],
+    layouts: &[PlaneLayout],
+    width: u32,
+    first_line: usize,
+    last_line: usize,
+    value: u8,
+) {
+    for (plane, layout) in band.iter_mut().zip(layouts) {
+        let line_size = layout.depth * ((width >> layout.horizontal_shift) as usize);
+        let line_count =
+            (last_line >> layout.vertical_shift) - (first_line >> layout.vertical_shift);
+        for line in plane.chunks_mut(layout.stride).take(line_count) {
+            for sample in &mut line[line_size..] {
+                *sample = value;
+            }
+        }
+    }
+}
+
+/// Converts from a color space to another one, like [`convert_image`], padding each line
+/// of the destination image to a multiple of `alignment` bytes.
 ///
-///   The minimum number of bytes to provide for each buffer depends from the image format, dimensions,
-///   and strides (if they are not `None`).
+/// Buffers copied to textures by graphics APIs need aligned rows, e.g. to 256 bytes for
+/// Direct3D 12 buffer to texture copies. The stride of each destination buffer is the
+/// one returned by [`aligned_stride`] for the first plane stored in it, and the padding
+/// bytes at the end of each line are set to `padding`, so that the uploaded data is fully
+/// defined. Direct conversions pad each band right after converting it, while it is
+/// still in cache.
 ///
-///   You can compute the buffers' size using [`get_buffers_size`]
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_format` - Destination image format
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+/// * `alignment` - Alignment of the destination strides in bytes
+/// * `padding` - Value of the destination padding bytes
 ///
-/// # Algorithm 1
-/// Conversion from linear RGB model to YCbCr color model, with 4:2:0 downsampling
+/// # Errors
 ///
-/// If the destination image color space is Bt601, the following formula is applied:
-/// ```text
-/// y  =  0.257 * r + 0.504 * g + 0.098 * b + 16
-/// cb = -0.148 * r - 0.291 * g + 0.439 * b + 128
-/// cr =  0.439 * r - 0.368 * g - 0.071 * b + 128
-/// ```
+/// * [`InvalidValue`] if `alignment` is zero
 ///
-/// If the destination image color space is Bt709, the following formula is applied:
-/// ```text
-/// y  =  0.213 * r + 0.715 * g + 0.072 * b + 16
-/// cb = -0.117 * r - 0.394 * g + 0.511 * b + 128
-/// cr =  0.511 * r - 0.464 * g - 0.047 * b + 128
+/// * [`NotEnoughData`] if the destination buffers are smaller than the size returned by
+///   [`get_buffers_size`] for the aligned strides
+///
+/// * All the errors reported by [`convert_image`]
+///
+/// # Examples
 /// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::{aligned_stride, convert_image_aligned, ColorSpace, ImageFormat, PixelFormat};
 ///
-/// # Algorithm 2
-/// Conversion from YCbCr model to linear RGB model, with 4:4:4 upsampling
+/// dcp::initialize();
 ///
-/// If the destination image contains an alpha channel, each component will be set to 255
+/// let src_format = ImageFormat {
+///     pixel_format: PixelFormat::Bgra,
+///     color_space: ColorSpace::Lrgb,
+///     num_planes: 1,
+/// };
 ///
-/// If the source image color space is Bt601, the following formula is applied:
-/// ```text
-/// r = 1.164 * (y - 16) + 1.596 * (cr - 128)
-/// g = 1.164 * (y - 16) - 0.813 * (cr - 128) - 0.392 * (cb - 128)
-/// b = 1.164 * (y - 16) + 2.017 * (cb - 128)
-/// ```
+/// let dst_format = ImageFormat {
+///     pixel_format: PixelFormat::Nv12,
+///     color_space: ColorSpace::Bt601,
+///     num_planes: 2,
+/// };
 ///
-/// If the source image color space is Bt709, the following formula is applied:
-/// ```text
-/// r = 1.164 * (y - 16) + 1.793 * (cr - 128)
-/// g = 1.164 * (y - 16) - 0.534 * (cr - 128) - 0.213 * (cb - 128)
-/// b = 1.164 * (y - 16) + 2.115 * (cb - 128)
-/// ```
+/// let bgra = [255u8; 16];
+/// let stride = aligned_stride(2, PixelFormat::Nv12, 0, 4);
+/// let (mut y, mut uv) = ([0u8; 8], [0u8; 4]);
 ///
-/// # Algorithm 3
-/// Conversion from RGB to BGRA
+/// convert_image_aligned(
+///     2,
+///     2,
+///     &src_format,
+///     None,
+///     &[&bgra],
+///     &dst_format,
+///     &mut [&mut y, &mut uv],
+///     4,
+///     0,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(stride, 4);
+/// assert_eq!(y, [235, 235, 0, 0, 235, 235, 0, 0]);
+/// assert_eq!(uv, [128, 128, 0, 0]);
+/// ```
 ///
-/// # Algorithm 4
-/// Conversion from BGRA to RGB
-/// 
-/// [`NotInitialized`]: ./enum.ErrorKind.html#variant.NotInitialized
 /// [`InvalidValue`]: ./enum.ErrorKind.html#variant.InvalidValue
-/// [`InvalidOperation`]: ./enum.ErrorKind.html#variant.InvalidOperation
 /// [`NotEnoughData`]: ./enum.ErrorKind.html#variant.NotEnoughData
-/// [`size constraints`]: ./struct.ImageFormat.html#note
+/// [`aligned_stride`]: ./fn.aligned_stride.html
 /// [`get_buffers_size`]: ./fn.get_buffers_size.html
-/// [`1`]: ./fn.convert_image.html#algorithm-1
-/// [`2`]: ./fn.convert_image.html#algorithm-2
-/// [`3`]: ./fn.convert_image.html#algorithm-3
-pub fn convert_image(
+/// [`convert_image`]: ./fn.convert_image.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_image_aligned(
     width: u32,
     height: u32,
     src_format: &ImageFormat,
     src_strides: Option<&[usize]>,
     src_buffers: &[&[u8]],
     dst_format: &ImageFormat,
-    dst_strides: Option<&[usize]>,
     dst_buffers: &mut [&mut [u8]],
+    alignment: usize,
+    padding: u8,
 ) -> Result<(), ErrorKind> {
-    unsafe {
-        if !GLOBAL_STATE.init {
-            return Err(ErrorKind::NotInitialized);
-        }
-    }
-
-    let src_pixel_format = src_format.pixel_format as u32;
-    let dst_pixel_format = dst_format.pixel_format as u32;
-    let src_color_space = src_format.color_space as u32;
-    let dst_color_space = dst_format.color_space as u32;
+    let _span = trace_span!(
+        DEBUG,
+        "convert_image_aligned",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
 
-    // Cross-correlate pixel format with color space. Predicate handles Table 1
-    let src_pf_mode = dispatcher::get_pixel_format_mode(src_pixel_format);
-    let src_cs_mode = dispatcher::get_color_space_mode(src_color_space);
-    let dst_pf_mode = dispatcher::get_pixel_format_mode(dst_pixel_format);
-    let dst_cs_mode = dispatcher::get_color_space_mode(dst_color_space);
-    let src_pf_cs_mismatch = src_pf_mode ^ src_cs_mode;
-    let dst_pf_cs_mismatch = dst_pf_mode ^ dst_cs_mode;
-    if src_pf_cs_mismatch | dst_pf_cs_mismatch {
+    if alignment == 0 {
         return Err(ErrorKind::InvalidValue);
     }
 
-    // Cross-correlate pixel format with planes and alignment.
-    // wrapping_sub is wanted. If num_planes is 0, this turns in a very big number that
-    // still represents an invalid number of planes.
-    let last_src_plane = src_format.num_planes.wrapping_sub(1);
-    if !pixel_format::is_compatible(src_pixel_format, width, height, last_src_plane) {
-        return Err(ErrorKind::InvalidValue);
+    let dst_strides = &mut [0usize; pixel_format::MAX_NUMBER_OF_PLANES];
+    let dst_planes = dst_format
+        .num_planes
+        .min(pixel_format::MAX_NUMBER_OF_PLANES as u32);
+    for (plane, stride) in dst_strides.iter_mut().enumerate().take(dst_planes as usize) {
+        *stride = aligned_stride(width, dst_format.pixel_format, plane as u32, alignment);
+        if *stride == 0 && !is_empty(width, height) {
+            return Err(ErrorKind::InvalidValue);
+        }
     }
 
-    let last_dst_plane = dst_format.num_planes.wrapping_sub(1);
-    if !pixel_format::is_compatible(dst_pixel_format, width, height, last_dst_plane) {
-        return Err(ErrorKind::InvalidValue);
-    }
+    let (converter, last_src_plane, last_dst_plane) =
+        match get_converter(width, height, src_format, dst_format) {
+            Ok(converter) => converter,
+            Err(ErrorKind::InvalidOperation) => {
+                // Routed conversions are padded once converted
+                convert_image(
+                    width,
+                    height,
+                    src_format,
+                    src_strides,
+                    src_buffers,
+                    dst_format,
+                    Some(dst_strides),
+                    dst_buffers,
+                )?;
 
-    // Cross-correlate modes.
-    let src_index = dispatcher::get_image_index(src_pixel_format, src_color_space, src_pf_mode);
-    let dst_index = dispatcher::get_image_index(dst_pixel_format, dst_color_space, dst_pf_mode);
-    let index = dispatcher::get_index(src_index, dst_index);
-    let converters = { unsafe { &GLOBAL_STATE.converters } };
-    if index >= converters.len() {
-        return Err(ErrorKind::InvalidOperation);
-    }
+                if is_empty(width, height) {
+                    return Ok(());
+                }
 
-    let converter = converters[index];
-    match converter {
-        None => Err(ErrorKind::InvalidOperation),
-        Some(image_converter) => {
-            if image_converter(
-                width,
-                height,
-                last_src_plane,
-                src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
-                src_buffers,
-                last_dst_plane,
-                dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
-                dst_buffers,
-            ) {
-                Ok(())
-            } else {
-                Err(ErrorKind::NotEnoughData)
+                let line_count = height as usize;
+                let last_dst_plane = dst_format.num_planes - 1;
+                let dst_layouts =
+                    get_plane_layouts(dst_format, width, height, last_dst_plane, dst_strides)?;
+                let mut dst_band = get_dst_band(dst_buffers, &dst_layouts, 0, line_count)
+                    .ok_or(ErrorKind::NotEnoughData)?;
+                fill_padding(&mut dst_band, &dst_layouts, width, 0, line_count, padding);
+                return Ok(());
             }
-        }
+            Err(error) => return Err(error),
+        };
+
+    if is_empty(width, height) {
+        return Ok(());
     }
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let src_layouts = get_plane_layouts(src_format, width, height, last_src_plane, src_strides)?;
+    let dst_layouts = get_plane_layouts(dst_format, width, height, last_dst_plane, dst_strides)?;
+
+    convert_in_bands(
+        &src_layouts,
+        src_buffers,
+        dst_format,
+        &dst_layouts,
+        dst_buffers,
+        get_bands(height as usize, PADDING_BAND_HEIGHT),
+        |mut band| {
+            band.convert(converter, width)?;
+            fill_padding(
+                &mut band.dst_buffers,
+                &dst_layouts,
+                width,
+                band.first_line,
+                band.last_line,
+                padding,
+            );
+            Ok(())
+        },
+    )
 }
 
 #[doc(hidden)]
@@ -871,13 +5505,16 @@ mod c_bindings {
     use pixel_format::{are_planes_compatible, MAX_NUMBER_OF_PLANES};
     use std::cmp;
     use std::ffi::CString;
-    use std::mem::{transmute, MaybeUninit};
+    use std::mem::{self, transmute, MaybeUninit};
     use std::os::raw::c_char;
     use std::ptr;
     use std::slice;
 
     const UNBOUNDED_C_ARRAY: usize = std::isize::MAX as usize;
 
+    // Length of the array filled by dcp_describe_layout
+    const MAX_LOGICAL_PLANES: usize = 3;
+
     #[repr(C)]
     pub enum Result {
         Ok,
@@ -892,6 +5529,17 @@ mod c_bindings {
         self::Result::Err
     }
 
+    // C enums are untrusted in the sense you can cast any value to an enum type.
+    // Read them as integers, because loading an invalid value as a Rust enum is undefined
+    // behaviour and allows the compiler to drop the validation.
+    static_assert!(mem::size_of::<PixelFormat>() == mem::size_of::<u32>());
+    static_assert!(mem::size_of::<ColorSpace>() == mem::size_of::<u32>());
+
+    unsafe fn get_raw_enums(format: *const ImageFormat) -> (u32, u32) {
+        let enums = format as *const u32;
+        (ptr::read(enums), ptr::read(enums.add(1)))
+    }
+
     #[no_mangle]
     pub extern "C" fn dcp_initialize() {
         initialize();
@@ -914,6 +5562,42 @@ mod c_bindings {
         }
     }
 
+    #[no_mangle]
+    pub unsafe extern "C" fn dcp_describe_conversion(
+        width: u32,
+        height: u32,
+        src_format: *const ImageFormat,
+        dst_format: *const ImageFormat,
+    ) -> *mut c_char {
+        let null: *const c_char = ptr::null();
+
+        // Protect from C null pointers
+        if src_format.is_null() || dst_format.is_null() {
+            return null as *mut c_char;
+        }
+
+        let (src_pixel_format, src_color_space) = get_raw_enums(src_format);
+        let (dst_pixel_format, dst_color_space) = get_raw_enums(dst_format);
+        if !dispatcher::is_pixel_format_valid(src_pixel_format)
+            || !dispatcher::is_pixel_format_valid(dst_pixel_format)
+            || !dispatcher::is_color_space_valid(src_color_space)
+            || !dispatcher::is_color_space_valid(dst_color_space)
+        {
+            return null as *mut c_char;
+        }
+
+        let src_format: &ImageFormat = &*src_format;
+        let dst_format: &ImageFormat = &*dst_format;
+
+        match describe_conversion(width, height, src_format, dst_format) {
+            Ok(description) => match CString::new(description) {
+                Ok(s) => s.into_raw(),
+                Err(_) => null as *mut c_char,
+            },
+            Err(_) => null as *mut c_char,
+        }
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn dcp_unref_string(string: *mut c_char) {
         if !string.is_null() {
@@ -935,13 +5619,13 @@ mod c_bindings {
             return set_error(error, ErrorKind::InvalidValue);
         }
 
-        // C enums are untrusted in the sense you can cast any value to an enum type
-        let format = &*format;
-        let pixel_format = format.pixel_format as u32;
+        let (pixel_format, _) = get_raw_enums(format);
         if !dispatcher::is_pixel_format_valid(pixel_format) {
             return set_error(error, ErrorKind::InvalidValue);
         }
 
+        let format = &*format;
+
         // We assume there is enough data in the buffers
         // If the assumption will not hold undefined behaviour occurs (like in C)
         let num_planes = format.num_planes as usize;
@@ -963,6 +5647,51 @@ mod c_bindings {
         }
     }
 
+    #[no_mangle]
+    pub unsafe extern "C" fn dcp_describe_layout(
+        width: u32,
+        height: u32,
+        format: *const ImageFormat,
+        strides: *const usize,
+        planes: *mut PlaneLayoutDescriptor,
+        error: *mut ErrorKind,
+    ) -> self::Result {
+        // Protect from C null pointers
+        if format.is_null() || planes.is_null() {
+            return set_error(error, ErrorKind::InvalidValue);
+        }
+
+        let (pixel_format, _) = get_raw_enums(format);
+        if !dispatcher::is_pixel_format_valid(pixel_format) {
+            return set_error(error, ErrorKind::InvalidValue);
+        }
+
+        let format = &*format;
+        if !are_planes_compatible(pixel_format, format.num_planes) {
+            return set_error(error, ErrorKind::InvalidValue);
+        }
+
+        // Convert nullable type to Option
+        let strides = if strides.is_null() {
+            None
+        } else {
+            Some(slice::from_raw_parts(strides, format.num_planes as usize))
+        };
+
+        match describe_layout(width, height, format, strides) {
+            Ok(descriptors) => {
+                // Descriptors past the last logical plane are zeroed
+                let planes = slice::from_raw_parts_mut(planes, MAX_LOGICAL_PLANES);
+                for (i, plane) in planes.iter_mut().enumerate() {
+                    *plane = descriptors.get(i).copied().unwrap_or_default();
+                }
+
+                self::Result::Ok
+            }
+            Err(error_kind) => set_error(error, error_kind),
+        }
+    }
+
     #[no_mangle]
     pub unsafe extern "C" fn dcp_convert_image(
         width: u32,
@@ -984,19 +5713,19 @@ mod c_bindings {
             return set_error(error, ErrorKind::InvalidValue);
         }
 
-        // C enums are untrusted in the sense you can cast any value to an enum type
-        let src_format: &ImageFormat = &*src_format;
-        let dst_format: &ImageFormat = &*dst_format;
-        let src_pixel_format = src_format.pixel_format as u32;
-        let dst_pixel_format = dst_format.pixel_format as u32;
+        let (src_pixel_format, src_color_space) = get_raw_enums(src_format);
+        let (dst_pixel_format, dst_color_space) = get_raw_enums(dst_format);
         if !dispatcher::is_pixel_format_valid(src_pixel_format)
             || !dispatcher::is_pixel_format_valid(dst_pixel_format)
-            || !dispatcher::is_color_space_valid(src_format.color_space as u32)
-            || !dispatcher::is_color_space_valid(dst_format.color_space as u32)
+            || !dispatcher::is_color_space_valid(src_color_space)
+            || !dispatcher::is_color_space_valid(dst_color_space)
         {
             return set_error(error, ErrorKind::InvalidValue);
         }
 
+        let src_format: &ImageFormat = &*src_format;
+        let dst_format: &ImageFormat = &*dst_format;
+
         // We assume there is enough data in the buffers
         // If the assumption will not hold undefined behaviour occurs (like in C)
         if !are_planes_compatible(src_pixel_format, src_format.num_planes)
@@ -1042,16 +5771,24 @@ mod c_bindings {
         };
 
         // Convert nullable type to Option
+        // C callers give a stride for each plane of the image format, so images stored in
+        // a single buffer can not give the stride of each logical plane
         let src_strides = if src_strides.is_null() {
             None
         } else {
-            Some(slice::from_raw_parts(src_strides, UNBOUNDED_C_ARRAY))
+            Some(slice::from_raw_parts(
+                src_strides,
+                src_format.num_planes as usize,
+            ))
         };
 
         let dst_strides = if dst_strides.is_null() {
             None
         } else {
-            Some(slice::from_raw_parts(dst_strides, UNBOUNDED_C_ARRAY))
+            Some(slice::from_raw_parts(
+                dst_strides,
+                dst_format.num_planes as usize,
+            ))
         };
 
         match convert_image(
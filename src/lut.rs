@@ -0,0 +1,312 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! One-dimensional look-up tables.
+//!
+//! Applies a per-channel look-up table to the rgb output of a conversion, for gamma
+//! correction or simple grading. The conversion proceeds in bands and each band is
+//! remapped right after being written, while it is still in cache, so that no further
+//! pass over the whole destination image is needed.
+//!
+//! Tables have 256 entries (8-bit) or 1024 entries (10-bit). Samples of 8-bit rgb formats
+//! are expanded to the table depth before the look-up and rounded back afterwards, samples
+//! of float formats are quantized to the nearest entry.
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::lut::{convert_image_with_lut, Lut1d};
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn convert() -> Result<(), Box<dyn error::Error>> {
+//!     dcp::initialize();
+//!
+//!     const WIDTH: u32 = 640;
+//!     const HEIGHT: u32 = 480;
+//!
+//!     let src_format = ImageFormat {
+//!         pixel_format: PixelFormat::Nv12,
+//!         color_space: ColorSpace::Bt601,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let dst_format = ImageFormat {
+//!         pixel_format: PixelFormat::Bgra,
+//!         color_space: ColorSpace::Lrgb,
+//!         num_planes: 1,
+//!     };
+//!
+//!     // Brighten mid tones
+//!     let lut = Lut1d::from_fn(8, |x| x.powf(1.0 / 1.2))?;
+//!
+//!     let src_buffer = vec![0u8; 3 * (WIDTH as usize) * (HEIGHT as usize) / 2];
+//!     let mut dst_buffer = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+//!
+//!     convert_image_with_lut(
+//!         WIDTH,
+//!         HEIGHT,
+//!         &src_format,
+//!         None,
+//!         &[&src_buffer],
+//!         &dst_format,
+//!         None,
+//!         &mut [&mut dst_buffer],
+//!         &lut,
+//!     )?;
+//!
+//!     Ok(())
+//! }
+//! ```
+use crate::pixel_format::{self, PixelFormat};
+use crate::trace_span;
+use crate::{ErrorKind, ImageFormat};
+
+/// Lines converted before the look-up table is applied
+const LUT_BAND_HEIGHT: usize = 64;
+
+/// A per-channel one-dimensional look-up table
+#[derive(Clone, Debug)]
+pub struct Lut1d {
+    bits: u32,
+    tables: [Vec<u16>; 3],
+}
+
+impl Lut1d {
+    /// Creates a look-up table from the entries of each channel
+    ///
+    /// # Arguments
+    /// * `bits` - Depth of the table, either 8 or 10
+    /// * `red` - Entries of the red channel
+    /// * `green` - Entries of the green channel
+    /// * `blue` - Entries of the blue channel
+    ///
+    /// # Errors
+    ///
+    /// * [`InvalidValue`] if `bits` is neither 8 nor 10
+    ///
+    /// * [`InvalidValue`] if a channel does not have exactly `1 << bits` entries, or one
+    ///   of the entries does not fit in `bits` bits
+    ///
+    /// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+    pub fn new(bits: u32, red: &[u16], green: &[u16], blue: &[u16]) -> Result<Self, ErrorKind> {
+        if bits != 8 && bits != 10 {
+            return Err(ErrorKind::InvalidValue);
+        }
+
+        let size = 1 << bits;
+        for table in &[red, green, blue] {
+            if table.len() != size || table.iter().any(|&entry| usize::from(entry) >= size) {
+                return Err(ErrorKind::InvalidValue);
+            }
+        }
+
+        Ok(Lut1d {
+            bits,
+            tables: [red.to_vec(), green.to_vec(), blue.to_vec()],
+        })
+    }
+
+    /// Creates a look-up table applying the same curve to every channel
+    ///
+    /// # Arguments
+    /// * `bits` - Depth of the table, either 8 or 10
+    /// * `curve` - Function mapping normalized values in [0, 1] to normalized values.
+    ///   Results outside [0, 1] are clipped
+    ///
+    /// # Errors
+    ///
+    /// * [`InvalidValue`] if `bits` is neither 8 nor 10
+    ///
+    /// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+    pub fn from_fn<F: Fn(f32) -> f32>(bits: u32, curve: F) -> Result<Self, ErrorKind> {
+        if bits != 8 && bits != 10 {
+            return Err(ErrorKind::InvalidValue);
+        }
+
+        let max = ((1 << bits) - 1) as f32;
+        let table: Vec<u16> = (0..(1 << bits))
+            .map(|entry| {
+                let value = curve(entry as f32 / max);
+                if value > 0.0 {
+                    (value.min(1.0) * max + 0.5) as u16
+                } else {
+                    0
+                }
+            })
+            .collect();
+
+        Lut1d::new(bits, &table, &table, &table)
+    }
+
+    /// Depth of the table
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    fn map_u8(&self, channel: usize, value: u8) -> u8 {
+        let shift = self.bits - 8;
+        let index = (usize::from(value) << shift) | (usize::from(value) >> (8 - shift));
+        let max = (1 << self.bits) - 1;
+        ((255 * u32::from(self.tables[channel][index]) + max / 2) / max) as u8
+    }
+
+    fn map_f32(&self, channel: usize, value: f32) -> f32 {
+        let max = ((1 << self.bits) - 1) as f32;
+        let index = if value > 0.0 {
+            (value.min(1.0) * max + 0.5) as usize
+        } else {
+            0
+        };
+
+        f32::from(self.tables[channel][index]) / max
+    }
+}
+
+fn read_f32(buffer: &[u8]) -> f32 {
+    f32::from_bits(u32::from_le_bytes([
+        buffer[0], buffer[1], buffer[2], buffer[3],
+    ]))
+}
+
+fn write_f32(buffer: &mut [u8], value: f32) {
+    buffer[..4].copy_from_slice(&value.to_bits().to_le_bytes());
+}
+
+/// Applies the table to a line of packed rgb pixels
+fn apply_row(row: &mut [u8], pixel_format: PixelFormat, lut: &Lut1d) {
+    let (bpp, offsets) = match pixel_format {
+        PixelFormat::Argb => (4, [1, 2, 3]),
+        PixelFormat::Bgra => (4, [2, 1, 0]),
+        PixelFormat::Bgr => (3, [2, 1, 0]),
+        PixelFormat::Rgba => (4, [0, 1, 2]),
+        PixelFormat::Rgb => (3, [0, 1, 2]),
+        PixelFormat::RgbF32 => (12, [0, 4, 8]),
+        _ => (16, [0, 4, 8]),
+    };
+
+    if bpp < 12 {
+        for pixel in row.chunks_exact_mut(bpp) {
+            for (channel, &offset) in offsets.iter().enumerate() {
+                pixel[offset] = lut.map_u8(channel, pixel[offset]);
+            }
+        }
+    } else {
+        for pixel in row.chunks_exact_mut(bpp) {
+            for (channel, &offset) in offsets.iter().enumerate() {
+                let value = lut.map_f32(channel, read_f32(&pixel[offset..]));
+                write_f32(&mut pixel[offset..], value);
+            }
+        }
+    }
+}
+
+/// Converts an image and applies a look-up table to its rgb output
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source image plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination image plane
+/// * `lut` - Look-up table to apply to the destination samples
+///
+/// # Note
+/// The destination pixel format has to be one of the 8-bit rgb formats, `RgbF32` or
+/// `RgbaF32`. Alpha is left untouched.
+///
+/// # Errors
+///
+/// * [`InvalidOperation`] if the destination pixel format is not supported
+///
+/// * All the errors reported by [`convert_image`], except that conversions are never routed
+///
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`convert_image`]: ../fn.convert_image.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_image_with_lut(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    lut: &Lut1d,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_image_with_lut",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    let (converter, last_src_plane, last_dst_plane) =
+        crate::get_converter(width, height, src_format, dst_format)?;
+
+    match dst_format.pixel_format {
+        PixelFormat::Argb
+        | PixelFormat::Bgra
+        | PixelFormat::Bgr
+        | PixelFormat::Rgba
+        | PixelFormat::Rgb
+        | PixelFormat::RgbF32
+        | PixelFormat::RgbaF32 => {}
+        _ => return Err(ErrorKind::InvalidOperation),
+    }
+
+    if crate::is_empty(width, height) {
+        return Ok(());
+    }
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let src_layouts =
+        crate::get_plane_layouts(src_format, width, height, last_src_plane, src_strides)?;
+    let dst_layouts =
+        crate::get_plane_layouts(dst_format, width, height, last_dst_plane, dst_strides)?;
+
+    let row_size = dst_layouts[0].depth * (width as usize);
+
+    crate::convert_in_bands(
+        &src_layouts,
+        src_buffers,
+        dst_format,
+        &dst_layouts,
+        dst_buffers,
+        crate::get_bands(height as usize, LUT_BAND_HEIGHT),
+        |mut band| {
+            band.convert(converter, width)?;
+            for line in 0..band.height() {
+                apply_row(
+                    &mut band.dst_buffers[0][line * band.dst_strides[0]..][..row_size],
+                    dst_format.pixel_format,
+                    lut,
+                );
+            }
+
+            Ok(())
+        },
+    )
+}
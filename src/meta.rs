@@ -0,0 +1,70 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Metadata of frames flowing through a pipeline.
+//!
+//! Multi-stage pipelines need to know, for each output image, which frame it came from:
+//! its timestamp, the color metadata of the source and any application defined field. A
+//! [`FrameMeta`] travels with the image through the operations of this crate that take
+//! it, which never read nor change it, so that pipelines do not need a bookkeeping layer
+//! of their own to pair images with their metadata.
+//!
+//! [`FrameMeta`]: ./struct.FrameMeta.html
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::meta::FrameMeta;
+//!
+//! let mut meta = FrameMeta {
+//!     timestamp: Some(45000),
+//!     time_base: (1, 90000),
+//!     ..Default::default()
+//! };
+//!
+//! meta.user_fields.insert("camera".to_owned(), "lobby".to_owned());
+//! assert_eq!(meta.timestamp_seconds(), Some(0.5));
+//! ```
+use crate::ColorSpaceHints;
+use std::collections::BTreeMap;
+
+/// Metadata of a frame
+#[derive(Clone, Debug, Default)]
+pub struct FrameMeta {
+    /// Presentation timestamp, in units of `time_base`
+    pub timestamp: Option<i64>,
+    /// Seconds per unit of the timestamp, as numerator and denominator. `(0, 0)` when
+    /// unknown
+    pub time_base: (u32, u32),
+    /// Color metadata signalled by the source of the frame
+    pub color: ColorSpaceHints,
+    /// Application defined fields, e.g. the identifier of the capture device
+    pub user_fields: BTreeMap<String, String>,
+}
+
+impl FrameMeta {
+    /// Returns the presentation timestamp in seconds, if both the timestamp and the time
+    /// base are known
+    pub fn timestamp_seconds(&self) -> Option<f64> {
+        let (numerator, denominator) = self.time_base;
+        match self.timestamp {
+            Some(timestamp) if denominator != 0 => {
+                Some(timestamp as f64 * f64::from(numerator) / f64::from(denominator))
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Deterministic perturbation of the output of conversions, for testing purposes.
+//!
+//! When the `noise` feature is enabled, conversions move each 8-bit color sample they
+//! write by at most one code value, and [`max_error`] widens its bounds accordingly. This
+//! applies to every conversion entry point, from [`convert_image`] to conversion plans,
+//! cancellable conversions and conversions of dirty rows, as they all write their output
+//! through the same function.
+//! Downstream test suites built with this feature fail if they expect the exact bytes
+//! written by the current kernels, rather than values within the documented error bounds.
+//!
+//! The perturbation only depends on the position of the sample in the image, so it is the
+//! same at every run and whether or not the image is converted in bands. Alpha samples are
+//! left untouched.
+//!
+//! [`convert_image`]: ../fn.convert_image.html
+//! [`max_error`]: ../fn.max_error.html
+use crate::{get_plane_layouts, ImageFormat, PixelFormat};
+
+/// Largest perturbation of a sample, in 8-bit code values
+pub const NOISE_AMPLITUDE: f32 = 1.0;
+
+/// Returns the position of alpha in each pixel of a pixel format, if any and if its
+/// samples can be perturbed
+pub fn get_noisy_samples(pixel_format: PixelFormat) -> Option<Option<usize>> {
+    match pixel_format {
+        PixelFormat::Argb => Some(Some(0)),
+        PixelFormat::Bgra | PixelFormat::Rgba => Some(Some(3)),
+        PixelFormat::Bgr
+        | PixelFormat::Rgb
+        | PixelFormat::I444
+        | PixelFormat::I422
+        | PixelFormat::I420
+        | PixelFormat::Nv12 => Some(None),
+        _ => None,
+    }
+}
+
+/// Returns -1, 0 or 1 from the position of a sample
+fn get_offset(plane: usize, x: usize, y: usize) -> i32 {
+    let mut state = (x as u32).wrapping_mul(0x9E37_79B1)
+        ^ (y as u32).wrapping_mul(0x85EB_CA77)
+        ^ (plane as u32).wrapping_mul(0xC2B2_AE3D);
+    state ^= state >> 15;
+    state = state.wrapping_mul(0x2C1B_3C6D);
+    state ^= state >> 12;
+    state = state.wrapping_mul(0x297A_2D39);
+    state ^= state >> 15;
+
+    (state % 3) as i32 - 1
+}
+
+/// Perturbs the 8-bit color samples of a converted image, or of a band of it starting at
+/// line `first_line`
+pub fn perturb(
+    width: u32,
+    height: u32,
+    first_line: usize,
+    format: &ImageFormat,
+    strides: &[usize],
+    buffers: &mut [&mut [u8]],
+) {
+    let alpha = match get_noisy_samples(format.pixel_format) {
+        Some(alpha) => alpha,
+        None => return,
+    };
+
+    let layouts = match get_plane_layouts(
+        format,
+        width,
+        height,
+        format.num_planes.wrapping_sub(1),
+        strides,
+    ) {
+        Ok(layouts) => layouts,
+        Err(_) => return,
+    };
+
+    for (plane, layout) in layouts.iter().enumerate() {
+        let buffer = match buffers.get_mut(layout.buffer) {
+            Some(buffer) => buffer,
+            None => return,
+        };
+
+        let line_size = layout.depth * ((width >> layout.horizontal_shift) as usize);
+        let first_line = first_line >> layout.vertical_shift;
+        for y in 0..((height >> layout.vertical_shift) as usize) {
+            let start = layout.offset + y * layout.stride;
+            let line = match buffer.get_mut(start..start + line_size) {
+                Some(line) => line,
+                None => return,
+            };
+
+            for (x, sample) in line.iter_mut().enumerate() {
+                if alpha != Some(x % layout.depth) {
+                    let value = i32::from(*sample) + get_offset(plane, x, first_line + y);
+                    *sample = value.clamp(0, 255) as u8;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,333 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Test pattern generators.
+//!
+//! Patterns are rendered directly into any supported pixel format and color space,
+//! which is useful for calibration screens and for testing sinks without a real
+//! decoder. YCbCr values are computed with the formulas in the [`reference`] module.
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::patterns::{generate, Pattern};
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn color_bars() -> Result<(), Box<dyn error::Error>> {
+//!     const WIDTH: u32 = 640;
+//!     const HEIGHT: u32 = 480;
+//!
+//!     let format = ImageFormat {
+//!         pixel_format: PixelFormat::Nv12,
+//!         color_space: ColorSpace::Bt709,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let mut frame = vec![0u8; 3 * (WIDTH as usize) * (HEIGHT as usize) / 2];
+//!     generate(Pattern::ColorBars, WIDTH, HEIGHT, &format, None, &mut [&mut frame])?;
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`reference`]: ../reference/index.html
+use crate::dispatcher;
+use crate::pixel_format::{self, PixelFormat, STRIDE_AUTO};
+use crate::reference;
+use crate::{ErrorKind, ImageFormat};
+use std::f64::consts::PI;
+
+/// An enumeration of test patterns.
+#[derive(Copy, Clone)]
+pub enum Pattern {
+    /// SMPTE color bars (75% bars, castellations, -I/white/+Q and PLUGE)
+    ColorBars,
+    /// Circular zone plate, whose frequency grows from the center of the image
+    /// up to the Nyquist frequency at its border
+    ZonePlate,
+    /// Gray ramp going from black (left) to white (right)
+    HorizontalRamp,
+    /// Gray ramp going from black (top) to white (bottom)
+    VerticalRamp,
+}
+
+type Rgb = (u8, u8, u8);
+
+const BLACK: Rgb = (19, 19, 19);
+
+const TOP_BARS: [Rgb; 7] = [
+    (191, 191, 191),
+    (191, 191, 0),
+    (0, 191, 191),
+    (0, 191, 0),
+    (191, 0, 191),
+    (191, 0, 0),
+    (0, 0, 191),
+];
+
+const MIDDLE_BARS: [Rgb; 7] = [
+    (0, 0, 191),
+    BLACK,
+    (191, 0, 191),
+    BLACK,
+    (0, 191, 191),
+    BLACK,
+    (191, 191, 191),
+];
+
+// Bottom row, in units of 1/12 of a top bar: -I, white, +Q and black
+// are 5/4 of a bar wide, each PLUGE step is 1/3 of a bar wide.
+const BOTTOM_BARS: [(Rgb, usize); 8] = [
+    ((0, 33, 76), 15),
+    ((255, 255, 255), 15),
+    ((50, 0, 106), 15),
+    (BLACK, 15),
+    ((9, 9, 9), 4),
+    (BLACK, 4),
+    ((29, 29, 29), 4),
+    (BLACK, 12),
+];
+
+fn color_bars(x: usize, y: usize, width: usize, height: usize) -> Rgb {
+    let bar = (7 * x) / width;
+    if 3 * y < 2 * height {
+        TOP_BARS[bar]
+    } else if 4 * y < 3 * height {
+        MIDDLE_BARS[bar]
+    } else {
+        let mut unit = (84 * x) / width;
+        for &(color, size) in BOTTOM_BARS.iter() {
+            if unit < size {
+                return color;
+            }
+
+            unit -= size;
+        }
+
+        BLACK
+    }
+}
+
+fn zone_plate(x: usize, y: usize, width: usize, height: usize) -> Rgb {
+    let dx = (x as f64) - (width as f64) / 2.0;
+    let dy = (y as f64) - (height as f64) / 2.0;
+    let k = PI / (width.max(height) as f64);
+    let gray = (127.5 * (1.0 + (k * (dx * dx + dy * dy)).cos())).round() as u8;
+
+    (gray, gray, gray)
+}
+
+fn ramp(i: usize, count: usize) -> Rgb {
+    let gray = if count > 1 {
+        ((255 * i) / (count - 1)) as u8
+    } else {
+        0
+    };
+
+    (gray, gray, gray)
+}
+
+fn sample(pattern: Pattern, x: usize, y: usize, width: usize, height: usize) -> Rgb {
+    match pattern {
+        Pattern::ColorBars => color_bars(x, y, width, height),
+        Pattern::ZonePlate => zone_plate(x, y, width, height),
+        Pattern::HorizontalRamp => ramp(x, width),
+        Pattern::VerticalRamp => ramp(y, height),
+    }
+}
+
+/// Byte offsets of red, green, blue and alpha in a packed rgb pixel
+fn get_rgb_offsets(pixel_format: PixelFormat) -> (usize, usize, usize, Option<usize>) {
+    match pixel_format {
+        PixelFormat::Argb => (1, 2, 3, Some(0)),
+        PixelFormat::Bgra => (2, 1, 0, Some(3)),
+        PixelFormat::Bgr => (2, 1, 0, None),
+        PixelFormat::Rgba => (0, 1, 2, Some(3)),
+        _ => (0, 1, 2, None),
+    }
+}
+
+/// Generates a test pattern into an image
+///
+/// # Arguments
+/// * `pattern` - Pattern to generate
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `format` - Image format
+/// * `strides` - An array of distances in bytes between starts of consecutive lines
+///   in each image planes
+/// * `buffers` - An array of image buffers in each image plane
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the pixel format is not compatible with the color space
+///
+/// * [`InvalidValue`] if `width` or `height` violate the [`size constraints`] that might by
+///   imposed by the image pixel format
+///
+/// * [`InvalidValue`] if the image format has a number of planes which is not compatible
+///   with its pixel format
+///
+/// * [`InvalidValue`] if one or more strides are smaller than the plane line size
+///
+/// * [`InvalidOperation`] if the pixel format has more than 8 bits per sample
+///
+/// * [`NotEnoughData`] if the strides array is not `None` and its length is less than the
+///   image format number of planes
+///
+/// * [`NotEnoughData`] if one or more buffers does not provide enough data
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+/// [`size constraints`]: ../struct.ImageFormat.html#note
+pub fn generate(
+    pattern: Pattern,
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+    buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    let pixel_format = format.pixel_format as u32;
+    let color_space = format.color_space;
+    if dispatcher::get_pixel_format_mode(pixel_format)
+        ^ dispatcher::get_color_space_mode(color_space as u32)
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    if pixel_format::get_bit_depth(format.pixel_format) > 8 {
+        return Err(ErrorKind::InvalidOperation);
+    }
+
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if !pixel_format::is_compatible(pixel_format, width, height, last_plane) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let last_plane = last_plane as usize;
+    let strides = strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    if last_plane >= strides.len() || last_plane >= buffers.len() {
+        return Err(ErrorKind::NotEnoughData);
+    }
+
+    let w = width as usize;
+    let h = height as usize;
+    let planes = pixel_format::get_logical_planes(format.pixel_format);
+
+    // Split the buffers into one slice for each logical plane
+    let mut plane_buffers: Vec<(&mut [u8], usize)> = Vec::with_capacity(planes.len());
+    let mut buffer_iter = buffers.iter_mut().take(last_plane + 1);
+    let mut remainder: &mut [u8] = &mut [];
+    for (i, &(depth, hs, vs)) in planes.iter().enumerate() {
+        let packed_stride = depth * (w >> hs);
+        let stride = match strides.get(i) {
+            Some(&stride) if stride != STRIDE_AUTO => stride,
+            _ => packed_stride,
+        };
+
+        if stride < packed_stride {
+            return Err(ErrorKind::InvalidValue);
+        }
+
+        let size = stride
+            .checked_mul(h >> vs)
+            .ok_or(ErrorKind::NotEnoughData)?;
+
+        let buffer = match buffer_iter.next() {
+            Some(buffer) => &mut buffer[..],
+            None => std::mem::take(&mut remainder),
+        };
+
+        if size > buffer.len() {
+            return Err(ErrorKind::NotEnoughData);
+        }
+
+        let (plane, rest) = buffer.split_at_mut(size);
+        remainder = rest;
+        plane_buffers.push((plane, stride));
+    }
+
+    let rgb = |x: usize, y: usize| sample(pattern, x, y, w, h);
+    match format.pixel_format {
+        PixelFormat::I444 => {
+            for y in 0..h {
+                for x in 0..w {
+                    let (r, g, b) = rgb(x, y);
+                    let (l, cb, cr) = reference::rgb_to_yuv(r, g, b, color_space)?;
+                    for (&mut (ref mut plane, stride), value) in
+                        plane_buffers.iter_mut().zip([l, cb, cr].iter())
+                    {
+                        plane[y * stride + x] = *value;
+                    }
+                }
+            }
+        }
+        PixelFormat::I422 | PixelFormat::I420 | PixelFormat::Nv12 => {
+            let vertical = match format.pixel_format {
+                PixelFormat::I422 => 1,
+                _ => 2,
+            };
+
+            let (y_plane, chroma_planes) = plane_buffers.split_at_mut(1);
+            let (y_plane, y_stride) = (&mut y_plane[0].0, y_plane[0].1);
+            for y in 0..(h / vertical) {
+                for x in 0..(w / 2) {
+                    let (x0, y0) = (2 * x, vertical * y);
+                    let (x1, y1) = (x0 + 1, y0 + vertical - 1);
+                    let block = [rgb(x0, y0), rgb(x1, y0), rgb(x0, y1), rgb(x1, y1)];
+                    let (luma, cb, cr) = reference::rgb_to_yuv_2x2(&block, color_space)?;
+
+                    y_plane[y0 * y_stride + x0] = luma[0];
+                    y_plane[y0 * y_stride + x1] = luma[1];
+                    y_plane[y1 * y_stride + x0] = luma[2];
+                    y_plane[y1 * y_stride + x1] = luma[3];
+
+                    if let [(ref mut uv_plane, uv_stride)] = chroma_planes {
+                        uv_plane[y * *uv_stride + 2 * x] = cb;
+                        uv_plane[y * *uv_stride + 2 * x + 1] = cr;
+                    } else if let [(ref mut u_plane, u_stride), (ref mut v_plane, v_stride)] =
+                        chroma_planes
+                    {
+                        u_plane[y * *u_stride + x] = cb;
+                        v_plane[y * *v_stride + x] = cr;
+                    }
+                }
+            }
+        }
+        _ => {
+            let (depth, _, _) = planes[0];
+            let (r_offset, g_offset, b_offset, a_offset) = get_rgb_offsets(format.pixel_format);
+            let (ref mut plane, stride) = plane_buffers[0];
+            for y in 0..h {
+                for x in 0..w {
+                    let (r, g, b) = rgb(x, y);
+                    let pixel = &mut plane[y * stride + depth * x..];
+                    pixel[r_offset] = r;
+                    pixel[g_offset] = g;
+                    pixel[b_offset] = b;
+                    if let Some(a_offset) = a_offset {
+                        pixel[a_offset] = 255;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -13,10 +13,12 @@
 // HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
 // OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
 // SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+use crate::ErrorKind;
+
 pub const MAX_NUMBER_OF_PLANES: usize = 4;
 
 /// An enumeration of supported pixel formats.
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug)]
 #[repr(C)]
 pub enum PixelFormat {
     /// RGB with alpha channel first.
@@ -59,11 +61,172 @@ pub enum PixelFormat {
     ///
     /// 12 bits per pixel
     Nv12,
+    /// YUV with one luma plane Y then one plane with U and V values interleaved.
+    /// Chroma planes are subsampled in both the horizontal and vertical dimensions by a factor of 2.
+    /// Each sample is a little endian 16-bit word holding a 10-bit value in its upper bits.
+    ///
+    /// 24 bits per pixel
+    P010,
+    /// YUV with one luma plane Y then 2 chroma planes U and V.
+    /// Chroma planes are not sub-sampled.
+    /// Each sample is a little endian 32-bit float, with luma in [0, 1] and chroma in
+    /// [-0.5, 0.5] before normalization.
+    ///
+    /// 96 bits per pixel
+    YuvF32Planar,
+    /// RGB with alpha channel last, each component is a little endian half float.
+    ///
+    /// Components are linear and encoded using scRGB: sRGB primaries, 1.0 is the
+    /// SDR reference white (80 nits), values above 1.0 and below 0.0 are allowed.
+    ///
+    /// 64 bits per pixel
+    RgbaF16,
+    /// RGB packed without padding, each component is a little endian single precision float.
+    ///
+    /// Components are normalized to [0, 1], as expected by floating point textures.
+    ///
+    /// 96 bits per pixel
+    RgbF32,
+    /// RGB with alpha channel last, each component is a little endian single precision float.
+    ///
+    /// Components are normalized to [0, 1], as expected by floating point textures.
+    ///
+    /// 128 bits per pixel
+    RgbaF32,
+    /// Reverse RGB packed without padding, each component is a little endian 16-bit word.
+    ///
+    /// 48 bits per pixel
+    Bgr48,
+    /// RGB packed without padding, each component is a little endian 16-bit word.
+    ///
+    /// 48 bits per pixel
+    Rgb48,
+}
+
+impl PixelFormat {
+    /// Returns the number of bits per pixel, averaged over all the planes
+    ///
+    /// # Examples
+    /// ```
+    /// use dcv_color_primitives::PixelFormat;
+    ///
+    /// const NV12_BITS_PER_PIXEL: u32 = PixelFormat::Nv12.bits_per_pixel();
+    /// assert_eq!(NV12_BITS_PER_PIXEL, 12);
+    /// ```
+    pub const fn bits_per_pixel(self) -> u32 {
+        BITS_PER_PIXEL[self as usize]
+    }
+
+    /// Returns the size in bytes of a frame with default strides, i.e. the sum of the
+    /// buffers sizes returned by [`get_buffers_size`] when strides are `None`
+    ///
+    /// Being a `const fn`, it allows to size frame pools at compile time.
+    ///
+    /// Returns `None` if `width` or `height` violate the [`size constraints`] imposed by
+    /// the pixel format, or if the size does not fit the address space.
+    ///
+    /// # Examples
+    /// ```
+    /// use dcv_color_primitives::PixelFormat;
+    ///
+    /// const FRAME_SIZE: Option<usize> = PixelFormat::I420.frame_size(1920, 1080);
+    /// static POOL: [u8; 1920 * 1080 * 3 / 2] = [0; 1920 * 1080 * 3 / 2];
+    ///
+    /// assert_eq!(FRAME_SIZE, Some(POOL.len()));
+    /// assert_eq!(PixelFormat::I420.frame_size(1919, 1080), None);
+    /// ```
+    ///
+    /// [`get_buffers_size`]: ./fn.get_buffers_size.html
+    /// [`size constraints`]: ./struct.ImageFormat.html#note
+    pub const fn frame_size(self, width: u32, height: u32) -> Option<usize> {
+        let spec = PF_SPECS[self as usize];
+        let size = (width as u128) * (height as u128) * (BITS_PER_PIXEL[self as usize] as u128) / 8;
+
+        // Branches are not allowed in const functions on the minimum supported compiler
+        let valid = (((width & get_pf_width(spec)) | (height & get_pf_height(spec))) == 0)
+            & (size <= usize::MAX as u128);
+
+        [None, Some(size as usize)][valid as usize]
+    }
+
+    /// Returns the pixel format matching a fourcc code, or `None` if the code is unknown
+    ///
+    /// Packed RGB formats use the DRM fourcc codes, which name the components of a
+    /// little endian word (`AR24` is [`Bgra`] in memory order). Planar formats use the
+    /// customary video codes; common aliases (`IYUV`, `YU12`, `YU16`, `YU24`...) are
+    /// accepted as well.
+    ///
+    /// # Examples
+    /// ```
+    /// use dcv_color_primitives::PixelFormat;
+    ///
+    /// let nv12 = PixelFormat::from_fourcc(b"NV12").map(|format| format as u32);
+    /// assert_eq!(nv12, Some(PixelFormat::Nv12 as u32));
+    ///
+    /// let bgra = PixelFormat::from_fourcc(b"AR24").map(|format| format as u32);
+    /// assert_eq!(bgra, Some(PixelFormat::Bgra as u32));
+    /// assert!(PixelFormat::from_fourcc(b"YUY2").is_none());
+    /// ```
+    ///
+    /// [`Bgra`]: ./enum.PixelFormat.html#variant.Bgra
+    pub fn from_fourcc(fourcc: &[u8; 4]) -> Option<PixelFormat> {
+        match fourcc {
+            b"BA24" => Some(PixelFormat::Argb),
+            b"AR24" => Some(PixelFormat::Bgra),
+            b"RG24" => Some(PixelFormat::Bgr),
+            b"AB24" => Some(PixelFormat::Rgba),
+            b"BG24" => Some(PixelFormat::Rgb),
+            b"AB4H" => Some(PixelFormat::RgbaF16),
+            b"Y444" | b"YU24" => Some(PixelFormat::I444),
+            b"Y42B" | b"YU16" => Some(PixelFormat::I422),
+            b"I420" | b"IYUV" | b"YU12" => Some(PixelFormat::I420),
+            b"NV12" => Some(PixelFormat::Nv12),
+            b"P010" => Some(PixelFormat::P010),
+            _ => None,
+        }
+    }
+
+    /// Returns the canonical fourcc code of the pixel format, as accepted by
+    /// [`from_fourcc`]
+    ///
+    /// Returns `None` for [`RgbF32`], [`RgbaF32`], [`Bgr48`], [`Rgb48`] and [`YuvF32Planar`],
+    /// which have no registered code.
+    ///
+    /// # Examples
+    /// ```
+    /// use dcv_color_primitives::PixelFormat;
+    ///
+    /// assert_eq!(PixelFormat::I420.to_fourcc(), Some(*b"I420"));
+    /// assert_eq!(PixelFormat::RgbF32.to_fourcc(), None);
+    /// ```
+    ///
+    /// [`from_fourcc`]: ./enum.PixelFormat.html#method.from_fourcc
+    /// [`RgbF32`]: ./enum.PixelFormat.html#variant.RgbF32
+    /// [`RgbaF32`]: ./enum.PixelFormat.html#variant.RgbaF32
+    /// [`Bgr48`]: ./enum.PixelFormat.html#variant.Bgr48
+    /// [`Rgb48`]: ./enum.PixelFormat.html#variant.Rgb48
+    /// [`YuvF32Planar`]: ./enum.PixelFormat.html#variant.YuvF32Planar
+    pub const fn to_fourcc(self) -> Option<[u8; 4]> {
+        FOURCCS[self as usize]
+    }
 }
 
 /// If a plane stride is assigned to this constant, the plane will be assumed to contain packed data
 pub const STRIDE_AUTO: usize = 0;
 
+/// Largest width or height, in pixels, of an image
+///
+/// Every `u32` dimension is accepted: offsets are computed with overflow checks and large
+/// images are converted in bands, so no dimension needs to be rejected up front.
+/// Images are only rejected when the size of their buffers does not fit the address space
+/// of the target, which [`get_buffers_size`] and [`frame_size`] report.
+///
+/// Callers validating untrusted dimensions can still compare them against this constant.
+///
+/// [`get_buffers_size`]: ./fn.get_buffers_size.html
+/// [`frame_size`]: ./enum.PixelFormat.html#method.frame_size
+pub const MAX_DIMENSION: u32 = u32::MAX;
+
 pub const DEFAULT_STRIDES: [usize; MAX_NUMBER_OF_PLANES] = [STRIDE_AUTO; MAX_NUMBER_OF_PLANES];
 
 const fn make_pf_spec(planes: u32, width: u32, height: u32) -> u32 {
@@ -76,7 +239,7 @@ const fn make_plane_spec(plane0: u32, plane1: u32, plane2: u32, plane3: u32) ->
 
 const INVALID_PLANE: u32 = 32;
 
-const PF_SPECS: [u32; 9] = [
+const PF_SPECS: [u32; 16] = [
     make_pf_spec(0, 0, 0),
     make_pf_spec(0, 0, 0),
     make_pf_spec(0, 0, 0),
@@ -86,9 +249,16 @@ const PF_SPECS: [u32; 9] = [
     make_pf_spec(2, 1, 0),
     make_pf_spec(2, 1, 1),
     make_pf_spec(1, 1, 1),
+    make_pf_spec(1, 1, 1),
+    make_pf_spec(2, 0, 0),
+    make_pf_spec(0, 0, 0),
+    make_pf_spec(0, 0, 0),
+    make_pf_spec(0, 0, 0),
+    make_pf_spec(0, 0, 0),
+    make_pf_spec(0, 0, 0),
 ];
 
-const STRIDE_SPECS: [u32; 9] = [
+const STRIDE_SPECS: [u32; 16] = [
     make_plane_spec(0, 0, 0, 0),
     make_plane_spec(0, 0, 0, 0),
     make_plane_spec(0, 0, 0, INVALID_PLANE),
@@ -98,9 +268,16 @@ const STRIDE_SPECS: [u32; 9] = [
     make_plane_spec(0, 1, 1, INVALID_PLANE),
     make_plane_spec(0, 1, 1, INVALID_PLANE),
     make_plane_spec(0, 0, INVALID_PLANE, INVALID_PLANE),
+    make_plane_spec(0, 0, INVALID_PLANE, INVALID_PLANE),
+    make_plane_spec(0, 0, 0, INVALID_PLANE),
+    make_plane_spec(0, 0, 0, 0),
+    make_plane_spec(0, 0, 0, INVALID_PLANE),
+    make_plane_spec(0, 0, 0, 0),
+    make_plane_spec(0, 0, 0, INVALID_PLANE),
+    make_plane_spec(0, 0, 0, INVALID_PLANE),
 ];
 
-const HEIGHT_SPECS: [u32; 9] = [
+const HEIGHT_SPECS: [u32; 16] = [
     make_plane_spec(0, 0, 0, 0),
     make_plane_spec(0, 0, 0, 0),
     make_plane_spec(0, 0, 0, INVALID_PLANE),
@@ -110,13 +287,48 @@ const HEIGHT_SPECS: [u32; 9] = [
     make_plane_spec(0, 0, 0, INVALID_PLANE),
     make_plane_spec(0, 1, 1, INVALID_PLANE),
     make_plane_spec(0, 1, INVALID_PLANE, INVALID_PLANE),
+    make_plane_spec(0, 1, INVALID_PLANE, INVALID_PLANE),
+    make_plane_spec(0, 0, 0, INVALID_PLANE),
+    make_plane_spec(0, 0, 0, 0),
+    make_plane_spec(0, 0, 0, INVALID_PLANE),
+    make_plane_spec(0, 0, 0, 0),
+    make_plane_spec(0, 0, 0, INVALID_PLANE),
+    make_plane_spec(0, 0, 0, INVALID_PLANE),
+];
+
+// Number of bits per pixel, averaged over all the planes
+const BITS_PER_PIXEL: [u32; 16] = [
+    32, 32, 24, 32, 24, 24, 16, 12, 12, 24, 96, 64, 96, 128, 48, 48,
 ];
 
-fn get_pf_width(pf: u32) -> u32 {
+// Canonical fourcc code of each pixel format
+const FOURCCS: [Option<[u8; 4]>; 16] = [
+    Some(*b"BA24"),
+    Some(*b"AR24"),
+    Some(*b"RG24"),
+    Some(*b"AB24"),
+    Some(*b"BG24"),
+    Some(*b"Y444"),
+    Some(*b"Y42B"),
+    Some(*b"I420"),
+    Some(*b"NV12"),
+    Some(*b"P010"),
+    None,
+    Some(*b"AB4H"),
+    None,
+    None,
+    None,
+    None,
+];
+
+// Number of bytes of each sample. Default strides are multiplied by this value
+const SAMPLE_SIZES: [usize; 16] = [1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 4, 2, 4, 4, 2, 2];
+
+const fn get_pf_width(pf: u32) -> u32 {
     (pf >> 2) & 1
 }
 
-fn get_pf_height(pf: u32) -> u32 {
+const fn get_pf_height(pf: u32) -> u32 {
     pf >> 3
 }
 
@@ -144,6 +356,11 @@ pub fn is_compatible(pixel_format: u32, width: u32, height: u32, last_plane: u32
         == 0
 }
 
+pub fn is_line_addressable(pixel_format: u32, width: u32) -> bool {
+    let line_size = u64::from(width) * u64::from(BITS_PER_PIXEL[pixel_format as usize]) / 8;
+    line_size <= usize::MAX as u64
+}
+
 pub fn get_buffers_size(
     pixel_format: u32,
     width: u32,
@@ -151,45 +368,67 @@ pub fn get_buffers_size(
     last_plane: u32,
     strides: &[usize],
     buffers_size: &mut [usize],
-) -> bool {
+) -> Result<(), ErrorKind> {
     let last_plane = last_plane as usize;
     if last_plane >= MAX_NUMBER_OF_PLANES
         || last_plane >= strides.len()
         || last_plane >= buffers_size.len()
     {
-        return false;
+        return Err(ErrorKind::NotEnoughData);
     }
 
-    let stride = &mut [0usize; MAX_NUMBER_OF_PLANES];
-
     let pixel_format = pixel_format as usize;
     let stride_spec = STRIDE_SPECS[pixel_format];
-    for i in 0..MAX_NUMBER_OF_PLANES {
-        stride[i] = if i >= strides.len() || strides[i] == STRIDE_AUTO {
-            get_plane_mask(stride_spec, i as u32) * get_plane_spec(width, stride_spec, i as u32)
+    let height_spec = HEIGHT_SPECS[pixel_format];
+    let sample_size = SAMPLE_SIZES[pixel_format];
+
+    // Sizes come from untrusted dimensions and strides: any overflow is reported
+    // as an invalid value rather than wrapping around
+    // Planes sharing a single buffer use its stride, unless their own stride is given
+    let shared_planes = if last_plane == 0 {
+        get_pf_planes(PF_SPECS[pixel_format]) as usize
+    } else {
+        0
+    };
+
+    let plane_size = &mut [0usize; MAX_NUMBER_OF_PLANES];
+    for (i, size) in plane_size.iter_mut().enumerate() {
+        let requested = match strides.get(i) {
+            Some(&stride) if stride != STRIDE_AUTO => stride,
+            _ if i <= shared_planes => strides[0],
+            _ => STRIDE_AUTO,
+        };
+
+        let stride = if requested == STRIDE_AUTO {
+            sample_size
+                .checked_mul(get_plane_mask(stride_spec, i as u32))
+                .and_then(|x| x.checked_mul(get_plane_spec(width, stride_spec, i as u32)))
         } else {
-            strides[i]
+            Some(requested)
         };
+
+        *size = stride
+            .and_then(|x| x.checked_mul(get_plane_spec(height, height_spec, i as u32)))
+            .ok_or(ErrorKind::InvalidValue)?;
+    }
+
+    // Empty images need no storage, even when explicit strides are given
+    if width == 0 || height == 0 {
+        for size in plane_size.iter_mut() {
+            *size = 0;
+        }
     }
 
-    let height_spec = HEIGHT_SPECS[pixel_format];
     if last_plane == 0 {
-        buffers_size[0] = ((stride[0] * get_plane_spec(height, height_spec, 0))
-            + (stride[1] * get_plane_spec(height, height_spec, 1)))
-            + ((stride[2] * get_plane_spec(height, height_spec, 2))
-                + (stride[3] * get_plane_spec(height, height_spec, 3)));
+        buffers_size[0] = plane_size
+            .iter()
+            .try_fold(0usize, |total, &size| total.checked_add(size))
+            .ok_or(ErrorKind::InvalidValue)?;
     } else {
-        let buffer_array = &mut buffers_size[..last_plane + 1];
-        let stride_array = &stride[..last_plane + 1];
-
-        for (buffer_size, (i, stride)) in
-            buffer_array.iter_mut().zip(stride_array.iter().enumerate())
-        {
-            *buffer_size = *stride * get_plane_spec(height, height_spec, i as u32);
-        }
+        buffers_size[..=last_plane].copy_from_slice(&plane_size[..=last_plane]);
     }
 
-    true
+    Ok(())
 }
 
 pub fn are_planes_compatible(pixel_format: u32, num_planes: u32) -> bool {
@@ -197,3 +436,39 @@ pub fn are_planes_compatible(pixel_format: u32, num_planes: u32) -> bool {
     let spec = PF_SPECS[pixel_format as usize];
     last_plane.wrapping_mul(last_plane.wrapping_sub(get_pf_planes(spec))) == 0
 }
+
+/// Returns true if the pixel format has fewer chroma samples than luma samples
+pub fn is_subsampled(pixel_format: PixelFormat) -> bool {
+    get_logical_planes(pixel_format)
+        .iter()
+        .any(|&(_, horizontal_shift, vertical_shift)| (horizontal_shift | vertical_shift) != 0)
+}
+
+/// Significant bits of each sample of a pixel format
+pub fn get_bit_depth(pixel_format: PixelFormat) -> u32 {
+    match pixel_format {
+        PixelFormat::P010 => 10,
+        PixelFormat::RgbaF16 | PixelFormat::Bgr48 | PixelFormat::Rgb48 => 16,
+        PixelFormat::RgbF32 | PixelFormat::RgbaF32 | PixelFormat::YuvF32Planar => 32,
+        _ => 8,
+    }
+}
+
+/// Logical planes of a pixel format, as (bytes per sample, horizontal and
+/// vertical subsampling shifts)
+pub fn get_logical_planes(pixel_format: PixelFormat) -> &'static [(usize, u32, u32)] {
+    match pixel_format {
+        PixelFormat::Argb | PixelFormat::Bgra | PixelFormat::Rgba => &[(4, 0, 0)],
+        PixelFormat::Bgr | PixelFormat::Rgb => &[(3, 0, 0)],
+        PixelFormat::RgbaF16 => &[(8, 0, 0)],
+        PixelFormat::RgbF32 => &[(12, 0, 0)],
+        PixelFormat::RgbaF32 => &[(16, 0, 0)],
+        PixelFormat::Bgr48 | PixelFormat::Rgb48 => &[(6, 0, 0)],
+        PixelFormat::I444 => &[(1, 0, 0), (1, 0, 0), (1, 0, 0)],
+        PixelFormat::I422 => &[(1, 0, 0), (1, 1, 0), (1, 1, 0)],
+        PixelFormat::I420 => &[(1, 0, 0), (1, 1, 1), (1, 1, 1)],
+        PixelFormat::Nv12 => &[(1, 0, 0), (2, 1, 1)],
+        PixelFormat::P010 => &[(2, 0, 0), (4, 1, 1)],
+        PixelFormat::YuvF32Planar => &[(4, 0, 0), (4, 0, 0), (4, 0, 0)],
+    }
+}
@@ -0,0 +1,509 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Filling and copying image planes.
+//!
+//! Frame pools clear recycled frames and copy planes between buffers with different line
+//! padding. These helpers do it a plane at a time, given the size of its lines in bytes:
+//! planes whose lines are contiguous are filled or copied as a single block, otherwise
+//! line by line. Blocks are handled by the platform `memset` and `memcpy`, which use the
+//! widest vector instructions available.
+//!
+//! Shared memory transports usually carry a frame in a single allocation.
+//! [`consolidate`] packs an image stored in a buffer for each plane into a single buffer,
+//! with lines aligned as requested, and [`split`] copies it back to separate buffers.
+//!
+//! [`consolidate`]: ./fn.consolidate.html
+//! [`split`]: ./fn.split.html
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::planes::{copy_plane_with_strides, fill_plane};
+//! use dcp::STRIDE_AUTO;
+//! use std::error;
+//!
+//! fn copy() -> Result<(), Box<dyn error::Error>> {
+//!     const WIDTH: usize = 640;
+//!     const HEIGHT: usize = 480;
+//!     const PADDED_STRIDE: usize = 768;
+//!
+//!     // Black Nv12 frame, with padded lines
+//!     let mut y_plane = vec![0u8; PADDED_STRIDE * HEIGHT];
+//!     let mut uv_plane = vec![0u8; PADDED_STRIDE * HEIGHT / 2];
+//!     fill_plane(WIDTH, HEIGHT, PADDED_STRIDE, &mut y_plane, 16)?;
+//!     fill_plane(WIDTH, HEIGHT / 2, PADDED_STRIDE, &mut uv_plane, 128)?;
+//!
+//!     // Tightly packed copy of the luma plane
+//!     let mut packed = vec![0u8; WIDTH * HEIGHT];
+//!     copy_plane_with_strides(
+//!         WIDTH,
+//!         HEIGHT,
+//!         PADDED_STRIDE,
+//!         &y_plane,
+//!         STRIDE_AUTO,
+//!         &mut packed,
+//!     )?;
+//!     assert!(packed.iter().all(|&x| x == 16));
+//!
+//!     Ok(())
+//! }
+//! # copy().unwrap();
+//! ```
+use crate::composite::check_layout;
+use crate::pixel_format;
+use crate::trace_span;
+use crate::{ErrorKind, ImageFormat, PixelFormat, PlaneLayout, STRIDE_AUTO};
+
+fn get_stride(line_size: usize, stride: usize) -> Result<usize, ErrorKind> {
+    match stride {
+        STRIDE_AUTO => Ok(line_size),
+        stride if stride < line_size => Err(ErrorKind::InvalidValue),
+        stride => Ok(stride),
+    }
+}
+
+/// Bytes spanned by a plane, the padding after its last line excluded
+fn get_plane_size(line_size: usize, lines: usize, stride: usize) -> Result<usize, ErrorKind> {
+    if lines == 0 {
+        return Ok(0);
+    }
+
+    stride
+        .checked_mul(lines - 1)
+        .and_then(|size| size.checked_add(line_size))
+        .ok_or(ErrorKind::NotEnoughData)
+}
+
+fn set(block: &mut [u8], value: u8) {
+    // Compiled to a call to memset
+    for sample in block {
+        *sample = value;
+    }
+}
+
+/// Sets the samples of each line of a plane to a value, leaving the line padding untouched
+///
+/// # Arguments
+/// * `line_size` - Size in bytes of the samples of a line
+/// * `lines` - Number of lines of the plane
+/// * `stride` - Distance in bytes between starts of consecutive lines, or [`STRIDE_AUTO`]
+///   if lines are contiguous
+/// * `buffer` - Buffer of the plane
+/// * `value` - Value of the samples
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the stride is smaller than the line size
+///
+/// * [`NotEnoughData`] if the buffer does not provide enough data
+///
+/// [`STRIDE_AUTO`]: ../constant.STRIDE_AUTO.html
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+pub fn fill_plane(
+    line_size: usize,
+    lines: usize,
+    stride: usize,
+    buffer: &mut [u8],
+    value: u8,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(DEBUG, "fill_plane", line_size, lines, stride);
+
+    let stride = get_stride(line_size, stride)?;
+    let buffer = buffer
+        .get_mut(..get_plane_size(line_size, lines, stride)?)
+        .ok_or(ErrorKind::NotEnoughData)?;
+
+    if stride == line_size {
+        set(buffer, value);
+    } else {
+        for line in buffer.chunks_mut(stride) {
+            set(&mut line[..line_size], value);
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies the samples of each line of a plane into another plane with a different stride,
+/// leaving the line padding of the destination untouched
+///
+/// # Arguments
+/// * `line_size` - Size in bytes of the samples of a line
+/// * `lines` - Number of lines of the planes
+/// * `src_stride` - Distance in bytes between starts of consecutive lines of the source,
+///   or [`STRIDE_AUTO`] if lines are contiguous
+/// * `src_buffer` - Buffer of the source plane
+/// * `dst_stride` - Distance in bytes between starts of consecutive lines of the
+///   destination, or [`STRIDE_AUTO`] if lines are contiguous
+/// * `dst_buffer` - Buffer of the destination plane
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if one or more strides are smaller than the line size
+///
+/// * [`NotEnoughData`] if one or more buffers does not provide enough data
+///
+/// [`STRIDE_AUTO`]: ../constant.STRIDE_AUTO.html
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+pub fn copy_plane_with_strides(
+    line_size: usize,
+    lines: usize,
+    src_stride: usize,
+    src_buffer: &[u8],
+    dst_stride: usize,
+    dst_buffer: &mut [u8],
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "copy_plane_with_strides",
+        line_size,
+        lines,
+        src_stride,
+        dst_stride
+    );
+
+    let src_stride = get_stride(line_size, src_stride)?;
+    let dst_stride = get_stride(line_size, dst_stride)?;
+    let src = src_buffer
+        .get(..get_plane_size(line_size, lines, src_stride)?)
+        .ok_or(ErrorKind::NotEnoughData)?;
+    let dst = dst_buffer
+        .get_mut(..get_plane_size(line_size, lines, dst_stride)?)
+        .ok_or(ErrorKind::NotEnoughData)?;
+
+    if src_stride == line_size && dst_stride == line_size {
+        dst.copy_from_slice(src);
+    } else {
+        for (src_line, dst_line) in src.chunks(src_stride).zip(dst.chunks_mut(dst_stride)) {
+            dst_line[..line_size].copy_from_slice(&src_line[..line_size]);
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the size in bytes of the buffer written by [`consolidate`]
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `pixel_format` - Pixel format of the image
+/// * `alignment` - Alignment of the lines in bytes, not necessarily a power of two
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if `width` or `height` violate the [`size constraints`] that might by
+///   imposed by the pixel format, or `alignment` is zero
+///
+/// * [`InvalidValue`] if the size of the buffer does not fit in a `usize`
+///
+/// [`consolidate`]: ./fn.consolidate.html
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`size constraints`]: ../struct.ImageFormat.html#note
+pub fn consolidated_size(
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+    alignment: usize,
+) -> Result<usize, ErrorKind> {
+    if alignment == 0 || !pixel_format::is_compatible(pixel_format as u32, width, height, 0) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let stride = match crate::aligned_stride(width, pixel_format, 0, alignment) {
+        0 if width > 0 => return Err(ErrorKind::InvalidValue),
+        stride => stride,
+    };
+
+    // All the planes use the stride of the buffer
+    pixel_format::get_logical_planes(pixel_format)
+        .iter()
+        .try_fold(0usize, |size, &(_, _, vertical_shift)| {
+            stride
+                .checked_mul((height >> vertical_shift) as usize)
+                .and_then(|plane_size| plane_size.checked_add(size))
+        })
+        .ok_or(ErrorKind::InvalidValue)
+}
+
+fn get_single_buffer_format(format: &ImageFormat) -> ImageFormat {
+    ImageFormat {
+        pixel_format: format.pixel_format,
+        color_space: format.color_space,
+        num_planes: 1,
+    }
+}
+
+fn get_layouts(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: &[usize],
+) -> Result<Vec<PlaneLayout>, ErrorKind> {
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if !pixel_format::is_compatible(format.pixel_format as u32, width, height, last_plane) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    crate::get_plane_layouts(format, width, height, last_plane, strides)
+}
+
+/// Copies each logical plane between two layouts of the same image, after checking all
+/// of them fit in their buffers
+fn copy_planes(
+    width: u32,
+    height: u32,
+    src_layouts: &[PlaneLayout],
+    src_buffers: &[&[u8]],
+    dst_layouts: &[PlaneLayout],
+    dst_buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    if crate::is_empty(width, height) {
+        return Ok(());
+    }
+
+    let src_sizes: Vec<usize> = src_buffers.iter().map(|buffer| buffer.len()).collect();
+    let dst_sizes: Vec<usize> = dst_buffers.iter().map(|buffer| buffer.len()).collect();
+    for (src, dst) in src_layouts.iter().zip(dst_layouts.iter()) {
+        check_layout(src, width, height, &src_sizes)?;
+        check_layout(dst, width, height, &dst_sizes)?;
+    }
+
+    for (src, dst) in src_layouts.iter().zip(dst_layouts.iter()) {
+        copy_plane_with_strides(
+            src.depth * ((width >> src.horizontal_shift) as usize),
+            (height >> src.vertical_shift) as usize,
+            src.stride,
+            &src_buffers[src.buffer][src.offset..],
+            dst.stride,
+            &mut dst_buffers[dst.buffer][dst.offset..],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Packs an image stored in a buffer for each plane into a single buffer
+///
+/// The planes are stored one after the other, as in an image of the same pixel format
+/// with a single plane. All of them use the size of a line of the first plane rounded up
+/// to a multiple of `alignment` as stride, so every line starts at an aligned distance
+/// from the start of the buffer. The stride is returned, to describe the image for later
+/// conversions.
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `format` - Format of the source image
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `alignment` - Alignment of the lines in bytes, not necessarily a power of two
+/// * `dst_buffer` - The buffer to pack the planes into, at least [`consolidated_size`]
+///   bytes long
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if `width` or `height` violate the [`size constraints`] that might by
+///   imposed by the pixel format, or `alignment` is zero
+///
+/// * [`InvalidValue`] if the image format has a number of planes which is not compatible
+///   with its pixel format
+///
+/// * [`NotEnoughData`] if the strides array is shorter than the number of planes, or one
+///   or more buffers does not provide enough data
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::planes::{consolidate, consolidated_size, split};
+/// use dcp::{ColorSpace, ImageFormat, PixelFormat};
+/// use std::error;
+///
+/// fn transport() -> Result<(), Box<dyn error::Error>> {
+///     const WIDTH: u32 = 640;
+///     const HEIGHT: u32 = 480;
+///     const ALIGNMENT: usize = 256;
+///
+///     let format = ImageFormat {
+///         pixel_format: PixelFormat::Nv12,
+///         color_space: ColorSpace::Bt601,
+///         num_planes: 2,
+///     };
+///
+///     let y_plane = vec![16u8; (WIDTH as usize) * (HEIGHT as usize)];
+///     let uv_plane = vec![128u8; (WIDTH as usize) * (HEIGHT as usize) / 2];
+///
+///     let mut shared = vec![0u8; consolidated_size(WIDTH, HEIGHT, format.pixel_format, ALIGNMENT)?];
+///     let stride = consolidate(
+///         WIDTH,
+///         HEIGHT,
+///         &format,
+///         None,
+///         &[&y_plane, &uv_plane],
+///         ALIGNMENT,
+///         &mut shared,
+///     )?;
+///     assert_eq!(stride, 768);
+///
+///     // On the other side of the transport
+///     let (mut y_copy, mut uv_copy) = (vec![0u8; y_plane.len()], vec![0u8; uv_plane.len()]);
+///     split(
+///         WIDTH,
+///         HEIGHT,
+///         &format,
+///         stride,
+///         &shared,
+///         None,
+///         &mut [&mut y_copy, &mut uv_copy],
+///     )?;
+///     assert_eq!((y_copy, uv_copy), (y_plane, uv_plane));
+///
+///     Ok(())
+/// }
+/// # transport().unwrap();
+/// ```
+///
+/// [`consolidated_size`]: ./fn.consolidated_size.html
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+/// [`size constraints`]: ../struct.ImageFormat.html#note
+pub fn consolidate(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    alignment: usize,
+    dst_buffer: &mut [u8],
+) -> Result<usize, ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "consolidate",
+        width,
+        height,
+        format = ?format.pixel_format,
+        alignment
+    );
+
+    if alignment == 0 {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let src_layouts = get_layouts(
+        width,
+        height,
+        format,
+        src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+    )?;
+
+    let dst_stride = crate::aligned_stride(width, format.pixel_format, 0, alignment);
+    let dst_layouts = get_layouts(
+        width,
+        height,
+        &get_single_buffer_format(format),
+        &[dst_stride],
+    )?;
+
+    copy_planes(
+        width,
+        height,
+        &src_layouts,
+        src_buffers,
+        &dst_layouts,
+        &mut [dst_buffer],
+    )?;
+
+    Ok(dst_stride)
+}
+
+/// Copies an image stored in a single buffer to a buffer for each plane
+///
+/// This is the reverse of [`consolidate`]. Unlike [`split_planes`], which borrows the
+/// planes from the single buffer, the samples are copied to the destination buffers,
+/// with their own strides.
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `format` - Format of the destination image
+/// * `src_stride` - Distance in bytes between starts of consecutive lines of the planes
+///   in the single buffer, as returned by [`consolidate`]
+/// * `src_buffer` - The buffer holding all the planes
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if `width` or `height` violate the [`size constraints`] that might by
+///   imposed by the pixel format
+///
+/// * [`InvalidValue`] if the image format has a number of planes which is not compatible
+///   with its pixel format
+///
+/// * [`NotEnoughData`] if the strides array is shorter than the number of planes, a stride
+///   is smaller than the size of a line, or one or more buffers does not provide enough
+///   data
+///
+/// [`consolidate`]: ./fn.consolidate.html
+/// [`split_planes`]: ../fn.split_planes.html
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+/// [`size constraints`]: ../struct.ImageFormat.html#note
+pub fn split(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    src_stride: usize,
+    src_buffer: &[u8],
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "split",
+        width,
+        height,
+        format = ?format.pixel_format,
+        src_stride
+    );
+
+    let src_layouts = get_layouts(
+        width,
+        height,
+        &get_single_buffer_format(format),
+        &[src_stride],
+    )?;
+    let dst_layouts = get_layouts(
+        width,
+        height,
+        format,
+        dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+    )?;
+
+    copy_planes(
+        width,
+        height,
+        &src_layouts,
+        &[src_buffer],
+        &dst_layouts,
+        dst_buffers,
+    )
+}
@@ -0,0 +1,302 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Planning of conversions.
+//!
+//! Pairs of formats without a direct conversion are converted through intermediate
+//! images, one pass for each conversion. [`convert_image`] picks the sequence with the
+//! fewest passes; a [`Plan`] can instead pick the one moving the fewest bytes to and from
+//! memory, and reports the passes it is made of together with their estimated memory
+//! traffic, e.g. to budget the bandwidth of a pipeline before running it.
+//!
+//! A plan is validated once and holds no buffers, so it can be shared by the threads
+//! converting many images of the same size and formats.
+//!
+//! [`convert_image`]: ../fn.convert_image.html
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::planner::{CostModel, Plan};
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn convert() -> Result<(), Box<dyn error::Error>> {
+//!     dcp::initialize();
+//!
+//!     const WIDTH: u32 = 640;
+//!     const HEIGHT: u32 = 480;
+//!
+//!     let src_format = ImageFormat {
+//!         pixel_format: PixelFormat::Rgb,
+//!         color_space: ColorSpace::Lrgb,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let dst_format = ImageFormat {
+//!         pixel_format: PixelFormat::Nv12,
+//!         color_space: ColorSpace::Bt601,
+//!         num_planes: 2,
+//!     };
+//!
+//!     let plan = Plan::new(WIDTH, HEIGHT, &src_format, &dst_format, CostModel::Bandwidth)?;
+//!     for pass in plan.passes() {
+//!         println!("{} -> {}: {} bytes", pass.src_format, pass.dst_format, pass.bytes());
+//!     }
+//!     println!("Total: {} bytes", plan.estimated_bandwidth());
+//!
+//!     let src_buffer = vec![0u8; 3 * (WIDTH as usize) * (HEIGHT as usize)];
+//!     let mut y_buffer = vec![0u8; (WIDTH as usize) * (HEIGHT as usize)];
+//!     let mut uv_buffer = vec![0u8; (WIDTH as usize) * (HEIGHT as usize) / 2];
+//!     plan.convert(None, &[&src_buffer], None, &mut [&mut y_buffer, &mut uv_buffer])?;
+//!
+//!     Ok(())
+//! }
+//! ```
+use crate::graph;
+use crate::pixel_format::{self, PixelFormat};
+use crate::trace_span;
+use crate::{ErrorKind, ImageFormat};
+
+/// How the cost of a sequence of passes is measured
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CostModel {
+    /// Fewest passes, preferring intermediate images without chroma subsampling.
+    /// This is the sequence used by [`convert_image`]
+    ///
+    /// [`convert_image`]: ../fn.convert_image.html
+    Passes,
+    /// Fewest bytes read and written by all the passes. Intermediate images may have
+    /// chroma subsampling, trading chroma resolution for memory traffic
+    Bandwidth,
+}
+
+/// A conversion between two images of a plan
+#[derive(Debug)]
+pub struct Pass {
+    /// Format of the image read by the pass
+    pub src_format: ImageFormat,
+    /// Format of the image written by the pass
+    pub dst_format: ImageFormat,
+    /// Bytes of the image read by the pass, without line padding
+    pub bytes_read: u64,
+    /// Bytes of the image written by the pass, without line padding
+    pub bytes_written: u64,
+}
+
+impl Pass {
+    /// Returns the bytes read and written by the pass
+    pub fn bytes(&self) -> u64 {
+        self.bytes_read + self.bytes_written
+    }
+}
+
+/// The passes converting images of a given size between two formats
+#[derive(Debug)]
+pub struct Plan {
+    width: u32,
+    height: u32,
+    route: Vec<ImageFormat>,
+    passes: Vec<Pass>,
+}
+
+fn copy_format(format: &ImageFormat) -> ImageFormat {
+    ImageFormat {
+        pixel_format: format.pixel_format,
+        color_space: format.color_space,
+        num_planes: format.num_planes,
+    }
+}
+
+/// Bytes of a 2x2 block of pixels, the smallest block with whole chroma samples
+fn get_block_size(pixel_format: PixelFormat) -> u32 {
+    pixel_format::get_logical_planes(pixel_format)
+        .iter()
+        .map(|&(depth, horizontal_shift, vertical_shift)| {
+            (4 * depth as u32) >> (horizontal_shift + vertical_shift)
+        })
+        .sum()
+}
+
+fn get_bandwidth_cost(from: PixelFormat, to: PixelFormat, _is_destination: bool) -> u32 {
+    get_block_size(from) + get_block_size(to)
+}
+
+fn get_image_size(width: u32, height: u32, format: &ImageFormat) -> Result<u64, ErrorKind> {
+    let buffers_size = &mut [0usize; pixel_format::MAX_NUMBER_OF_PLANES];
+    crate::get_buffers_size(width, height, format, None, buffers_size)?;
+    Ok(buffers_size[..format.num_planes as usize]
+        .iter()
+        .map(|&size| size as u64)
+        .sum())
+}
+
+impl Plan {
+    /// Picks the cheapest sequence of passes converting images of the given size
+    ///
+    /// A direct conversion is always the cheapest, whatever the cost model.
+    ///
+    /// # Arguments
+    /// * `width` - Width of the images to convert in pixels
+    /// * `height` - Height of the images to convert in pixels
+    /// * `src_format` - Source image format
+    /// * `dst_format` - Destination image format
+    /// * `cost_model` - How the cost of the passes is measured
+    ///
+    /// # Errors
+    /// The same errors reported by [`convert_image`] for the given parameters,
+    /// except the ones related to strides and buffers, which are reported on execution.
+    ///
+    /// [`convert_image`]: ../fn.convert_image.html
+    pub fn new(
+        width: u32,
+        height: u32,
+        src_format: &ImageFormat,
+        dst_format: &ImageFormat,
+        cost_model: CostModel,
+    ) -> Result<Plan, ErrorKind> {
+        let _span = trace_span!(
+            DEBUG,
+            "plan",
+            width,
+            height,
+            src = ?src_format.pixel_format,
+            dst = ?dst_format.pixel_format,
+            cost_model = ?cost_model
+        );
+
+        let route = match crate::get_converter(width, height, src_format, dst_format) {
+            Ok(_) => Vec::new(),
+            Err(ErrorKind::InvalidOperation) => {
+                let converters = crate::get_converters()?;
+                match cost_model {
+                    CostModel::Passes => {
+                        graph::find_route(converters, width, height, src_format, dst_format)?
+                    }
+                    CostModel::Bandwidth => graph::find_cheapest_route(
+                        converters,
+                        width,
+                        height,
+                        src_format,
+                        dst_format,
+                        get_bandwidth_cost,
+                    )?,
+                }
+            }
+            Err(error) => return Err(error),
+        };
+
+        let images: Vec<&ImageFormat> = std::iter::once(src_format)
+            .chain(route.iter())
+            .chain(std::iter::once(dst_format))
+            .collect();
+        let passes = images
+            .windows(2)
+            .map(|pair| {
+                Ok(Pass {
+                    src_format: copy_format(pair[0]),
+                    dst_format: copy_format(pair[1]),
+                    bytes_read: get_image_size(width, height, pair[0])?,
+                    bytes_written: get_image_size(width, height, pair[1])?,
+                })
+            })
+            .collect::<Result<Vec<Pass>, ErrorKind>>()?;
+
+        Ok(Plan {
+            width,
+            height,
+            route,
+            passes,
+        })
+    }
+
+    /// Returns the passes of the plan, from the source to the destination image
+    pub fn passes(&self) -> &[Pass] {
+        &self.passes
+    }
+
+    /// Returns the bytes read and written by all the passes of the plan
+    pub fn estimated_bandwidth(&self) -> u64 {
+        self.passes.iter().map(Pass::bytes).sum()
+    }
+
+    /// Returns the size of the scratch memory allocated by [`Plan::convert`] for the
+    /// intermediate images, which is zero for direct conversions
+    ///
+    /// [`Plan::convert`]: ./struct.Plan.html#method.convert
+    pub fn scratch_size(&self) -> Result<usize, ErrorKind> {
+        graph::get_scratch_size(self.width, self.height, &self.route)
+    }
+
+    /// Converts an image with the passes of the plan
+    ///
+    /// # Arguments
+    /// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+    ///   in each source image planes
+    /// * `src_buffers` - An array of image buffers in each source color plane
+    /// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+    ///   in each destination image planes
+    /// * `dst_buffers` - An array of image buffers in each destination color plane
+    ///
+    /// # Errors
+    /// The same errors reported by [`convert_image`] for the strides and the buffers.
+    ///
+    /// [`convert_image`]: ../fn.convert_image.html
+    pub fn convert(
+        &self,
+        src_strides: Option<&[usize]>,
+        src_buffers: &[&[u8]],
+        dst_strides: Option<&[usize]>,
+        dst_buffers: &mut [&mut [u8]],
+    ) -> Result<(), ErrorKind> {
+        let src_format = &self.passes[0].src_format;
+        let dst_format = &self.passes[self.passes.len() - 1].dst_format;
+        if self.route.is_empty() {
+            return crate::convert_image(
+                self.width,
+                self.height,
+                src_format,
+                src_strides,
+                src_buffers,
+                dst_format,
+                dst_strides,
+                dst_buffers,
+            );
+        }
+
+        let converters = crate::get_converters()?;
+        if crate::is_empty(self.width, self.height) {
+            return Ok(());
+        }
+
+        let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+        let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+        let mut scratch = vec![0u8; self.scratch_size()?];
+        graph::convert_routed(
+            converters,
+            &mut scratch,
+            self.width,
+            self.height,
+            &self.route,
+            src_format,
+            src_strides,
+            src_buffers,
+            dst_format,
+            dst_strides,
+            dst_buffers,
+        )
+    }
+}
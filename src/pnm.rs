@@ -0,0 +1,347 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Netpbm images.
+//!
+//! Reads and writes binary graymaps (`P5`), pixmaps (`P6`) and arbitrary maps (`P7`,
+//! also known as PAM), with 8-bit or 16-bit samples. Useful to load test images and
+//! to dump conversion results in a format most image viewers understand.
+//!
+//! Netpbm stores 16-bit samples in big-endian order, while the library expects
+//! little-endian ones: samples are swapped while reading and writing, so that
+//! image data is always little-endian in memory. For instance, the luma plane of a
+//! p010 image can be saved as it is as a 16-bit graymap having a maximum value of 65535.
+//!
+//! This module requires the `io` feature.
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::pnm::{self, Header};
+//! use std::error;
+//!
+//! fn round_trip() -> Result<(), Box<dyn error::Error>> {
+//!     let header = Header::new(2, 2, 3);
+//!     let data = [255u8, 0, 0, 0, 255, 0, 0, 0, 255, 255, 255, 255];
+//!
+//!     let mut file = Vec::new();
+//!     pnm::write(&mut file, &header, &data)?;
+//!     assert!(file.starts_with(b"P6\n2 2\n255\n"));
+//!
+//!     let (header, image) = pnm::read(&file[..])?;
+//!     assert_eq!((header.width, header.height, header.depth), (2, 2, 3));
+//!     assert_eq!(&image[..], &data[..]);
+//!
+//!     Ok(())
+//! }
+//! ```
+use std::io::{self, Read, Write};
+
+// Header lines and tokens are short, a larger one is most likely not a netpbm image
+const MAX_TOKEN_SIZE: usize = 4096;
+
+/// Header of a netpbm image
+#[derive(Clone, Debug)]
+pub struct Header {
+    /// Width of the image in pixels
+    pub width: u32,
+    /// Height of the image in pixels
+    pub height: u32,
+    /// Number of samples in each pixel: 1 for graymaps, 3 for pixmaps
+    pub depth: u32,
+    /// Largest value of a sample, up to 65535. Samples are 16-bit when it is above 255
+    pub max_value: u32,
+    /// Tuple type (e.g. `RGB_ALPHA`), possibly empty, of an arbitrary map
+    ///
+    /// `None` for graymaps and pixmaps. Images having a tuple type are written as
+    /// arbitrary maps, the other ones as graymaps or pixmaps depending on their depth.
+    pub tuple_type: Option<String>,
+}
+
+impl Header {
+    /// Creates a header for an image with 8-bit samples and no tuple type
+    pub fn new(width: u32, height: u32, depth: u32) -> Header {
+        Header {
+            width,
+            height,
+            depth,
+            max_value: 255,
+            tuple_type: None,
+        }
+    }
+
+    /// Returns the size in bytes of a sample
+    pub fn bytes_per_sample(&self) -> usize {
+        if self.max_value > 255 {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Returns the size in bytes of the image data
+    ///
+    /// Returns `None` if the size can not be represented.
+    pub fn data_size(&self) -> Option<usize> {
+        (self.width as usize)
+            .checked_mul(self.height as usize)?
+            .checked_mul(self.depth as usize)?
+            .checked_mul(self.bytes_per_sample())
+    }
+
+    fn validate(&self) -> io::Result<usize> {
+        if self.width == 0 || self.height == 0 {
+            return Err(invalid_data("invalid image size"));
+        }
+
+        if self.depth == 0 || self.max_value == 0 || self.max_value > 65535 {
+            return Err(invalid_data("invalid sample format"));
+        }
+
+        self.data_size()
+            .ok_or_else(|| invalid_data("image data too large"))
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn parse_number(value: &[u8]) -> io::Result<u32> {
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| invalid_data("invalid number"))
+}
+
+fn read_byte<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut byte = [0u8];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// Reads a whitespace separated token of a graymap or pixmap header, skipping comments.
+///
+/// The whitespace following the token is consumed as well.
+fn read_token<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut token = Vec::new();
+    loop {
+        let byte = read_byte(reader)?;
+        if byte.is_ascii_whitespace() {
+            if !token.is_empty() {
+                return Ok(token);
+            }
+        } else if byte == b'#' && token.is_empty() {
+            while read_byte(reader)? != b'\n' {}
+        } else if token.len() == MAX_TOKEN_SIZE {
+            return Err(invalid_data("header token too long"));
+        } else {
+            token.push(byte);
+        }
+    }
+}
+
+/// Reads a line of an arbitrary map header, without the terminating newline
+fn read_line<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut line = Vec::new();
+    loop {
+        let byte = read_byte(reader)?;
+        if byte == b'\n' {
+            return Ok(line);
+        }
+
+        if line.len() == MAX_TOKEN_SIZE {
+            return Err(invalid_data("header line too long"));
+        }
+
+        line.push(byte);
+    }
+}
+
+fn read_pam_header<R: Read>(reader: &mut R) -> io::Result<Header> {
+    let (mut width, mut height, mut depth, mut max_value) = (None, None, None, None);
+    let mut tuple_type: Option<String> = None;
+    loop {
+        let line = read_line(reader)?;
+        let line = std::str::from_utf8(&line).map_err(|_| invalid_data("invalid header"))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line.splitn(2, char::is_whitespace);
+        let key = fields.next().unwrap_or_default();
+        let value = fields.next().unwrap_or_default().trim();
+        match key {
+            "ENDHDR" => break,
+            "WIDTH" => width = Some(parse_number(value.as_bytes())?),
+            "HEIGHT" => height = Some(parse_number(value.as_bytes())?),
+            "DEPTH" => depth = Some(parse_number(value.as_bytes())?),
+            "MAXVAL" => max_value = Some(parse_number(value.as_bytes())?),
+            // Multiple tuple types are concatenated
+            "TUPLTYPE" => {
+                tuple_type = Some(match tuple_type {
+                    Some(tuple_type) => format!("{} {}", tuple_type, value),
+                    None => value.to_string(),
+                })
+            }
+            _ => return Err(invalid_data("invalid header field")),
+        }
+    }
+
+    match (width, height, depth, max_value) {
+        (Some(width), Some(height), Some(depth), Some(max_value)) => Ok(Header {
+            width,
+            height,
+            depth,
+            max_value,
+            tuple_type: Some(tuple_type.unwrap_or_default()),
+        }),
+        _ => Err(invalid_data("missing header field")),
+    }
+}
+
+/// Reads the header of a netpbm image
+///
+/// The reader is left at the start of the image data. Headers are read one byte at a
+/// time: wrap unbuffered readers (e.g. a `File`) in a `BufReader`.
+///
+/// # Errors
+///
+/// * `InvalidData` if the image is not a binary graymap, pixmap or arbitrary map,
+///   or its header is malformed
+///
+/// * `UnexpectedEof` if the image ends before the end of the header
+pub fn read_header<R: Read>(reader: &mut R) -> io::Result<Header> {
+    let mut magic = [0u8; 2];
+    reader.read_exact(&mut magic)?;
+
+    let header = match &magic {
+        b"P5" | b"P6" => {
+            let width = parse_number(&read_token(reader)?)?;
+            let height = parse_number(&read_token(reader)?)?;
+            let max_value = parse_number(&read_token(reader)?)?;
+            Header {
+                width,
+                height,
+                depth: if magic[1] == b'5' { 1 } else { 3 },
+                max_value,
+                tuple_type: None,
+            }
+        }
+        b"P7" => {
+            if read_byte(reader)? != b'\n' {
+                return Err(invalid_data("invalid header"));
+            }
+
+            read_pam_header(reader)?
+        }
+        _ => return Err(invalid_data("not a binary netpbm image")),
+    };
+
+    header.validate()?;
+    Ok(header)
+}
+
+/// Reads a netpbm image
+///
+/// Returns the image header and its data, with 16-bit samples in little-endian order.
+///
+/// # Errors
+///
+/// * The errors reported by [`read_header`]
+///
+/// * `UnexpectedEof` if the image data is truncated
+///
+/// [`read_header`]: ./fn.read_header.html
+pub fn read<R: Read>(mut reader: R) -> io::Result<(Header, Vec<u8>)> {
+    let header = read_header(&mut reader)?;
+    let data_size = header.validate()?;
+
+    let mut data = vec![0u8; data_size];
+    reader.read_exact(&mut data)?;
+    if header.bytes_per_sample() == 2 {
+        swap_samples(&mut data);
+    }
+
+    Ok((header, data))
+}
+
+fn swap_samples(data: &mut [u8]) {
+    for sample in data.chunks_exact_mut(2) {
+        sample.swap(0, 1);
+    }
+}
+
+/// Writes a netpbm image
+///
+/// Images with a tuple type are written as arbitrary maps (`P7`), the other ones
+/// as graymaps (`P5`) if their depth is 1 or as pixmaps (`P6`) if their depth is 3.
+/// 16-bit samples are expected in little-endian order.
+///
+/// # Errors
+///
+/// * `InvalidData` if the header is not valid
+///
+/// * `InvalidInput` if the image has no tuple type and its depth is neither 1 nor 3,
+///   or if `data` is smaller than the image data size
+pub fn write<W: Write>(mut writer: W, header: &Header, data: &[u8]) -> io::Result<()> {
+    let data_size = header.validate()?;
+    if data.len() < data_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not enough image data",
+        ));
+    }
+
+    match (&header.tuple_type, header.depth) {
+        (Some(tuple_type), _) => {
+            write!(
+                writer,
+                "P7\nWIDTH {}\nHEIGHT {}\nDEPTH {}\nMAXVAL {}\n",
+                header.width, header.height, header.depth, header.max_value
+            )?;
+            if !tuple_type.is_empty() {
+                writeln!(writer, "TUPLTYPE {}", tuple_type)?;
+            }
+
+            writer.write_all(b"ENDHDR\n")?;
+        }
+        (None, 1) | (None, 3) => write!(
+            writer,
+            "P{}\n{} {}\n{}\n",
+            if header.depth == 1 { 5 } else { 6 },
+            header.width,
+            header.height,
+            header.max_value
+        )?,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "depth requires a tuple type",
+            ))
+        }
+    }
+
+    let data = &data[..data_size];
+    if header.bytes_per_sample() == 2 {
+        let mut swapped = data.to_vec();
+        swap_samples(&mut swapped);
+        writer.write_all(&swapped)
+    } else {
+        writer.write_all(data)
+    }
+}
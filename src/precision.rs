@@ -0,0 +1,523 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+// Error bounds of the conversions. The output of each kernel is compared with the exact
+// formulas of its conversion, evaluated on real numbers and clipped to the range of the
+// destination samples, for any input within the nominal range of the source samples.
+// Bounds are derived from the constants of the kernels and are expressed in 8-bit code
+// values. The bound of a routed conversion is the bound of its last conversion, plus the
+// bound of the previous ones amplified by how much the last conversion can amplify an
+// error of its input.
+use crate::color_space::ColorSpace;
+use crate::convert_image::common::*;
+use crate::convert_image::x86::get_fix16_ycbcr_to_rgb_weights;
+use crate::pixel_format::PixelFormat;
+use crate::{ImageFormat, Quality, SampleRange};
+
+/// Error of a sequence of single precision operations: at most 16 roundings,
+/// each one off by half an ulp of values up to 512 code values
+const FLOAT_ERROR: f64 = 16.0 * 256.0 * f32::EPSILON as f64;
+
+/// Largest slope of the sRGB transfer function, found at its linear segment
+const SRGB_MAX_SLOPE: f64 = 12.92;
+
+const RGB_RANGE: (f64, f64) = (0.0, 255.0);
+const Y_RANGE: (f64, f64) = (16.0, 235.0);
+const C_RANGE: (f64, f64) = (16.0, 240.0);
+
+/// Luma and chroma ranges relative to their offsets
+const CENTERED_Y_RANGE: (f64, f64) = (0.0, 219.0);
+const CENTERED_C_RANGE: (f64, f64) = (-112.0, 112.0);
+const CENTERED_FULL_C_RANGE: (f64, f64) = (-128.0, 127.0);
+
+const JFIF_LUMA_WEIGHTS: (f64, f64) = (0.299, 0.114);
+
+/// Upper bounds of the error of a conversion, and of the factor it can amplify
+/// an error of its input by
+#[derive(Copy, Clone)]
+struct Step {
+    error: f64,
+    gain: f64,
+}
+
+/// Constants of the kernels for a YCbCr color space
+struct Constants {
+    luma_weights: (f64, f64),
+    forward: [i32; 9],
+    backward: [i32; 8],
+    coefficients: &'static [f32; 4],
+}
+
+fn get_constants(color_space: ColorSpace) -> Option<Constants> {
+    match color_space {
+        ColorSpace::Bt601 => Some(Constants {
+            luma_weights: (0.299, 0.114),
+            forward: [
+                XR_601, XG_601, XB_601, YR_601, YG_601, YB_601, ZR_601, ZG_601, ZB_601,
+            ],
+            backward: [
+                XXYM_601, RCRM_601, GCRM_601, GCBM_601, BCBM_601, RN_601, GP_601, BN_601,
+            ],
+            coefficients: &YCBCR_TO_RGB_601,
+        }),
+        ColorSpace::Bt709 => Some(Constants {
+            luma_weights: (0.2126, 0.0722),
+            forward: [
+                XR_709, XG_709, XB_709, YR_709, YG_709, YB_709, ZR_709, ZG_709, ZB_709,
+            ],
+            backward: [
+                XXYM_709, RCRM_709, GCRM_709, GCBM_709, BCBM_709, RN_709, GP_709, BN_709,
+            ],
+            coefficients: &YCBCR_TO_RGB_709,
+        }),
+        _ => None,
+    }
+}
+
+/// Code values spanned by 8-bit luma and chroma
+fn get_spans(range: SampleRange) -> (f64, f64) {
+    match range {
+        SampleRange::Limited => (219.0, 224.0),
+        SampleRange::Full => (255.0, 255.0),
+    }
+}
+
+/// Rgb to YCbCr matrix, from 8-bit rgb to 8-bit luma and chroma relative to their offsets
+fn get_forward_matrix((kr, kb): (f64, f64), range: SampleRange) -> [[f64; 3]; 3] {
+    let kg = 1.0 - kr - kb;
+    let (y_span, c_span) = get_spans(range);
+    let y = y_span / 255.0;
+    let cb = c_span / (255.0 * 2.0 * (1.0 - kb));
+    let cr = c_span / (255.0 * 2.0 * (1.0 - kr));
+    [
+        [y * kr, y * kg, y * kb],
+        [-cb * kr, -cb * kg, cb * (1.0 - kb)],
+        [cr * (1.0 - kr), -cr * kg, -cr * kb],
+    ]
+}
+
+/// YCbCr to rgb matrix, from 8-bit luma and chroma relative to their offsets to 8-bit rgb
+fn get_backward_matrix((kr, kb): (f64, f64), range: SampleRange) -> [[f64; 3]; 3] {
+    let kg = 1.0 - kr - kb;
+    let (y_span, c_span) = get_spans(range);
+    let y = 255.0 / y_span;
+    let rcr = 255.0 / c_span * 2.0 * (1.0 - kr);
+    let bcb = 255.0 / c_span * 2.0 * (1.0 - kb);
+    [
+        [y, 0.0, rcr],
+        [y, -bcb * kb / kg, -rcr * kr / kg],
+        [y, bcb, 0.0],
+    ]
+}
+
+fn multiply(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut result = [[0.0; 3]; 3];
+    for (i, row) in result.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+
+    result
+}
+
+/// Largest factor a matrix can amplify an error of its input by
+fn get_gain(matrix: &[[f64; 3]; 3]) -> f64 {
+    matrix
+        .iter()
+        .map(|row| row.iter().map(|x| x.abs()).sum::<f64>())
+        .fold(0.0, f64::max)
+}
+
+/// Range of the sum of weights[i] * x[i], with each x[i] within ranges[i]
+fn get_range(weights: &[f64], ranges: &[(f64, f64)]) -> (f64, f64) {
+    weights
+        .iter()
+        .zip(ranges.iter())
+        .fold((0.0, 0.0), |(low, high), (&weight, &(min, max))| {
+            (
+                low + (weight * min).min(weight * max),
+                high + (weight * min).max(weight * max),
+            )
+        })
+}
+
+/// Error of a weighted sum rounded to nearest, where the weights are off by deltas
+fn get_rounded_error(deltas: &[f64], ranges: &[(f64, f64)]) -> f64 {
+    let (low, high) = get_range(deltas, ranges);
+    0.5 + high.max(-low)
+}
+
+/// Rgb to YCbCr with weights of 16 fractional bits, rounding once per sample.
+/// Chroma of subsampled images is computed from the sum of the rgb values it covers,
+/// with two more fractional bits.
+fn get_fast_rgb_to_yuv(constants: &Constants) -> Step {
+    let exact = get_forward_matrix(constants.luma_weights, SampleRange::Limited);
+    let error = exact
+        .iter()
+        .zip(constants.forward.chunks(3))
+        .map(|(row, weights)| {
+            let deltas: Vec<f64> = weights
+                .iter()
+                .zip(row.iter())
+                .map(|(&weight, &x)| f64::from(weight) / f64::from(1 << FIX16) - x)
+                .collect();
+            get_rounded_error(&deltas, &[RGB_RANGE; 3])
+        })
+        .fold(0.0, f64::max);
+
+    Step {
+        error,
+        gain: get_gain(&exact),
+    }
+}
+
+/// Rgb to YCbCr in single precision, with the luma weights derived from the
+/// ycbcr to rgb coefficients
+fn get_float_rgb_to_yuv(constants: &Constants) -> Step {
+    let exact = get_forward_matrix(constants.luma_weights, SampleRange::Limited);
+    let [rcr, _, _, bcb] = *constants.coefficients;
+    let actual = get_forward_matrix(
+        (1.0 - 0.5 * f64::from(rcr), 1.0 - 0.5 * f64::from(bcb)),
+        SampleRange::Limited,
+    );
+
+    let error = exact
+        .iter()
+        .zip(actual.iter())
+        .map(|(exact, actual)| {
+            let deltas: Vec<f64> = actual
+                .iter()
+                .zip(exact.iter())
+                .map(|(a, x)| a - x)
+                .collect();
+            get_rounded_error(&deltas, &[RGB_RANGE; 3])
+        })
+        .fold(0.0, f64::max);
+
+    Step {
+        error: error + FLOAT_ERROR,
+        gain: get_gain(&exact),
+    }
+}
+
+/// YCbCr to rgb with weights of 14 fractional bits. Each product is truncated to
+/// 6 fractional bits, and so is the sum of the products, with the offset that rounds
+/// the result to nearest.
+fn get_fast_yuv_to_rgb(constants: &Constants) -> Step {
+    let exact = get_backward_matrix(constants.luma_weights, SampleRange::Limited);
+    let [xxym, rcrm, gcrm, gcbm, bcbm, rn, gp, bn] = constants.backward;
+    let components = [
+        ([xxym, 0, rcrm], -rn),
+        ([xxym, -gcbm, -gcrm], gp),
+        ([xxym, bcbm, 0], -bn),
+    ];
+
+    let error = exact
+        .iter()
+        .zip(components.iter())
+        .map(|(row, (weights, offset))| {
+            let deltas: Vec<f64> = weights
+                .iter()
+                .zip(row.iter())
+                .map(|(&weight, &x)| f64::from(weight) / f64::from(1 << (FIX16 - 2)) - x)
+                .collect();
+            let (low, high) = get_range(&deltas, &[Y_RANGE, C_RANGE, C_RANGE]);
+
+            // The exact formulas are relative to the luma and chroma offsets
+            let exact_offset = -f64::from(Y_MIN) * row[0] - f64::from(C_HALF) * (row[1] + row[2]);
+            let offset = f64::from(*offset) / f64::from(1 << FIX6) - exact_offset;
+
+            // Truncating a subtracted product increases the result
+            let truncation = |negative: bool| {
+                weights
+                    .iter()
+                    .filter(|&&weight| weight != 0 && (weight < 0) == negative)
+                    .count() as f64
+                    / f64::from(1 << FIX6)
+            };
+
+            let high = high + offset + truncation(true);
+            let low = low + offset - truncation(false) - 1.0;
+            high.max(-low)
+        })
+        .fold(0.0, f64::max);
+
+    Step {
+        error,
+        gain: get_gain(&exact),
+    }
+}
+
+/// YCbCr to rgb with weights of 16 fractional bits, rounding once per sample
+fn get_fix16_yuv_to_rgb(constants: &Constants) -> Step {
+    let exact = get_backward_matrix(constants.luma_weights, SampleRange::Limited);
+    let [y, rcr, gcb, gcr, bcb] = get_fix16_ycbcr_to_rgb_weights(constants.coefficients);
+    let weights = [[y, 0, rcr], [y, -gcb, -gcr], [y, bcb, 0]];
+
+    let error = exact
+        .iter()
+        .zip(weights.iter())
+        .map(|(row, weights)| {
+            let deltas: Vec<f64> = weights
+                .iter()
+                .zip(row.iter())
+                .map(|(&weight, &x)| f64::from(weight) / f64::from(1 << FIX16) - x)
+                .collect();
+            get_rounded_error(
+                &deltas,
+                &[CENTERED_Y_RANGE, CENTERED_C_RANGE, CENTERED_C_RANGE],
+            )
+        })
+        .fold(0.0, f64::max);
+
+    Step {
+        error,
+        gain: get_gain(&exact),
+    }
+}
+
+/// YCbCr to rgb in single precision, rounded to 8-bit or kept as floats
+fn get_float_yuv_to_rgb(constants: &Constants, rounded: bool) -> Step {
+    let exact = get_backward_matrix(constants.luma_weights, SampleRange::Limited);
+    let [rcr, gcb, gcr, bcb] = *constants.coefficients;
+    let y = 255.0 / 219.0;
+    let c = 255.0 / 224.0;
+    let actual = [
+        [y, 0.0, c * f64::from(rcr)],
+        [y, -c * f64::from(gcb), -c * f64::from(gcr)],
+        [y, c * f64::from(bcb), 0.0],
+    ];
+
+    let rounding = if rounded { 0.5 } else { 0.0 };
+    let error = exact
+        .iter()
+        .zip(actual.iter())
+        .map(|(exact, actual)| {
+            let deltas: Vec<f64> = actual
+                .iter()
+                .zip(exact.iter())
+                .map(|(a, x)| a - x)
+                .collect();
+            let (low, high) = get_range(
+                &deltas,
+                &[CENTERED_Y_RANGE, CENTERED_C_RANGE, CENTERED_C_RANGE],
+            );
+            rounding + high.max(-low)
+        })
+        .fold(0.0, f64::max);
+
+    Step {
+        error: error + FLOAT_ERROR,
+        gain: get_gain(&exact),
+    }
+}
+
+/// YCbCr to YCbCr of another color space, with weights of 16 fractional bits applied
+/// to chroma, rounding once per sample. Luma is kept and corrected by chroma.
+fn get_yuv_to_yuv(from: &Constants, to: &Constants, weights: &[[i32; 2]; 3]) -> Step {
+    let exact = multiply(
+        &get_forward_matrix(to.luma_weights, SampleRange::Limited),
+        &get_backward_matrix(from.luma_weights, SampleRange::Limited),
+    );
+
+    let error = exact
+        .iter()
+        .zip(weights.iter())
+        .map(|(row, weights)| {
+            let deltas: Vec<f64> = weights
+                .iter()
+                .zip(row[1..].iter())
+                .map(|(&weight, &x)| f64::from(weight) / f64::from(1 << FIX16) - x)
+                .collect();
+            get_rounded_error(&deltas, &[CENTERED_C_RANGE; 2])
+        })
+        .fold(0.0, f64::max);
+
+    Step {
+        error,
+        gain: get_gain(&exact),
+    }
+}
+
+/// Rgb to full range YCbCr as libjpeg does, with weights of 16 fractional bits, rounding
+/// once per sample. Subsampled chroma is the average of the chroma samples it covers,
+/// rounded again with a bias that can move it by half a code value.
+fn get_jfif_rgb_to_yuv(subsampled: bool) -> Step {
+    let exact = get_forward_matrix(JFIF_LUMA_WEIGHTS, SampleRange::Full);
+    let weights = [
+        XR_JFIF, XG_JFIF, XB_JFIF, YR_JFIF, YG_JFIF, YB_JFIF, ZR_JFIF, ZG_JFIF, ZB_JFIF,
+    ];
+
+    let error = exact
+        .iter()
+        .zip(weights.chunks(3))
+        .map(|(row, weights)| {
+            let deltas: Vec<f64> = weights
+                .iter()
+                .zip(row.iter())
+                .map(|(&weight, &x)| f64::from(weight) / f64::from(1 << FIX16) - x)
+                .collect();
+            get_rounded_error(&deltas, &[RGB_RANGE; 3])
+        })
+        .fold(0.0, f64::max);
+
+    let downsampling = if subsampled { 0.5 } else { 0.0 };
+    Step {
+        error: error + downsampling,
+        gain: get_gain(&exact),
+    }
+}
+
+/// Full range YCbCr to rgb as libjpeg does. The chroma contribution to each component
+/// has weights of 16 fractional bits and is rounded to nearest, then luma is added.
+fn get_jfif_yuv_to_rgb() -> Step {
+    let exact = get_backward_matrix(JFIF_LUMA_WEIGHTS, SampleRange::Full);
+    let weights = [[0, RCR_JFIF], [-GCB_JFIF, -GCR_JFIF], [BCB_JFIF, 0]];
+
+    let error = exact
+        .iter()
+        .zip(weights.iter())
+        .map(|(row, weights)| {
+            let deltas: Vec<f64> = weights
+                .iter()
+                .zip(row[1..].iter())
+                .map(|(&weight, &x)| f64::from(weight) / f64::from(1 << FIX16) - x)
+                .collect();
+            get_rounded_error(&deltas, &[CENTERED_FULL_C_RANGE; 2])
+        })
+        .fold(0.0, f64::max);
+
+    Step {
+        error,
+        gain: get_gain(&exact),
+    }
+}
+
+/// Bounds of a conversion of the dispatch table, or None if its error has no bound
+fn get_step(src_format: &ImageFormat, dst_format: &ImageFormat, quality: Quality) -> Option<Step> {
+    let exact = Step {
+        error: 0.0,
+        gain: 1.0,
+    };
+
+    match (src_format.pixel_format, dst_format.pixel_format) {
+        (PixelFormat::Rgb, PixelFormat::Bgra)
+        | (PixelFormat::Bgra, PixelFormat::Rgb)
+        | (PixelFormat::Nv12, PixelFormat::P010) => Some(exact),
+        // Ten bit samples are rounded to nearest
+        (PixelFormat::P010, PixelFormat::Nv12) => Some(Step {
+            error: 0.5,
+            ..exact
+        }),
+        // The error of the perceptual quantizer is relative to the luminance,
+        // which spans many orders of magnitude
+        (PixelFormat::P010, PixelFormat::RgbaF16) => None,
+        // Components are scaled by the tone mapping, then encoded with the sRGB transfer
+        // function. Scaling a component by the tone mapping of the largest one can double
+        // its error.
+        (PixelFormat::RgbaF16, PixelFormat::Bgra) => Some(Step {
+            error: 0.5 + FLOAT_ERROR,
+            gain: 2.0 * SRGB_MAX_SLOPE,
+        }),
+        // Samples are not quantized, in code values of the equivalent full range image
+        (_, PixelFormat::YuvF32Planar) => {
+            get_constants(dst_format.color_space).map(|constants| Step {
+                error: FLOAT_ERROR,
+                gain: get_gain(&get_forward_matrix(
+                    constants.luma_weights,
+                    SampleRange::Full,
+                )),
+            })
+        }
+        (PixelFormat::Argb, _) | (PixelFormat::Bgra, _) | (PixelFormat::Bgr, _) => {
+            if let ColorSpace::Jfif = dst_format.color_space {
+                let subsampled = dst_format.pixel_format as u32 != PixelFormat::I444 as u32;
+                return Some(get_jfif_rgb_to_yuv(subsampled));
+            }
+
+            let constants = get_constants(dst_format.color_space)?;
+            match quality {
+                Quality::Accurate => Some(get_float_rgb_to_yuv(&constants)),
+                _ => Some(get_fast_rgb_to_yuv(&constants)),
+            }
+        }
+        (_, PixelFormat::Bgra) => {
+            if let ColorSpace::Jfif = src_format.color_space {
+                return Some(get_jfif_yuv_to_rgb());
+            }
+
+            let constants = get_constants(src_format.color_space)?;
+            match quality {
+                Quality::Fast => Some(get_fast_yuv_to_rgb(&constants)),
+                Quality::Balanced => Some(get_fix16_yuv_to_rgb(&constants)),
+                Quality::Accurate => Some(get_float_yuv_to_rgb(&constants, true)),
+            }
+        }
+        (_, PixelFormat::RgbF32) | (_, PixelFormat::RgbaF32) => {
+            get_constants(src_format.color_space)
+                .map(|constants| get_float_yuv_to_rgb(&constants, false))
+        }
+        _ => {
+            let from = get_constants(src_format.color_space)?;
+            let to = get_constants(dst_format.color_space)?;
+            let weights = match src_format.color_space {
+                ColorSpace::Bt601 => &YCBCR_601_TO_709,
+                _ => &YCBCR_709_TO_601,
+            };
+
+            Some(get_yuv_to_yuv(&from, &to, weights))
+        }
+    }
+}
+
+/// Bound of the error of a conversion, performed directly when the route is empty,
+/// or through the intermediate image formats of the route otherwise.
+///
+/// The quality only affects direct conversions. Returns infinity when the error
+/// has no bound.
+pub fn get_max_error(
+    src_format: &ImageFormat,
+    route: &[ImageFormat],
+    dst_format: &ImageFormat,
+    quality: Quality,
+) -> f32 {
+    let quality = if route.is_empty() {
+        quality
+    } else {
+        Quality::Fast
+    };
+
+    let formats: Vec<&ImageFormat> = std::iter::once(src_format)
+        .chain(route.iter())
+        .chain(std::iter::once(dst_format))
+        .collect();
+
+    let error = formats
+        .windows(2)
+        .try_fold(0.0, |error, pair| {
+            get_step(pair[0], pair[1], quality).map(|step| step.gain * error + step.error)
+        })
+        .map_or(f32::INFINITY, |error| error as f32);
+
+    #[cfg(feature = "noise")]
+    let error = match crate::noise::get_noisy_samples(dst_format.pixel_format) {
+        Some(_) => error + crate::noise::NOISE_AMPLITUDE,
+        None => error,
+    };
+
+    error
+}
@@ -0,0 +1,348 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! YCbCr range conversion.
+//!
+//! Rescales the samples of a YCbCr image between the limited (studio) range and the
+//! full (pc) range, without changing its pixel format or color space.
+//!
+//! For 8-bit formats the limited range is [16, 235] for luma and [16, 240] for chroma,
+//! the full range is [0, 255]. For 10-bit formats the bounds are multiplied by 4.
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::range::{convert_range_in_place, RangeConversion};
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn expand() -> Result<(), Box<dyn error::Error>> {
+//!     const WIDTH: u32 = 640;
+//!     const HEIGHT: u32 = 480;
+//!
+//!     let format = ImageFormat {
+//!         pixel_format: PixelFormat::Nv12,
+//!         color_space: ColorSpace::Bt601,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let mut frame = vec![16u8; 3 * (WIDTH as usize) * (HEIGHT as usize) / 2];
+//!     convert_range_in_place(
+//!         RangeConversion::LimitedToFull,
+//!         WIDTH,
+//!         HEIGHT,
+//!         &format,
+//!         None,
+//!         &mut [&mut frame],
+//!     )?;
+//!
+//!     assert_eq!(frame[0], 0);
+//!     Ok(())
+//! }
+//! ```
+use crate::dispatcher;
+use crate::pixel_format::{self, PixelFormat, STRIDE_AUTO};
+use crate::{ErrorKind, ImageFormat};
+
+/// An enumeration of range conversions.
+#[derive(Copy, Clone)]
+pub enum RangeConversion {
+    /// Expands limited range samples to full range
+    LimitedToFull,
+    /// Compresses full range samples to limited range
+    FullToLimited,
+}
+
+/// A logical plane, located inside one of the image buffers
+struct Plane {
+    buffer: usize,
+    offset: usize,
+    stride: usize,
+    row_size: usize,
+    rows: usize,
+    chroma: bool,
+}
+
+/// Logical planes of a yuv pixel format, as (bytes per sample, number of interleaved
+/// samples, horizontal and vertical subsampling shifts)
+fn get_planes(pixel_format: PixelFormat) -> Option<&'static [(usize, usize, u32, u32)]> {
+    match pixel_format {
+        PixelFormat::I444 => Some(&[(1, 1, 0, 0), (1, 1, 0, 0), (1, 1, 0, 0)]),
+        PixelFormat::I422 => Some(&[(1, 1, 0, 0), (1, 1, 1, 0), (1, 1, 1, 0)]),
+        PixelFormat::I420 => Some(&[(1, 1, 0, 0), (1, 1, 1, 1), (1, 1, 1, 1)]),
+        PixelFormat::Nv12 => Some(&[(1, 1, 0, 0), (1, 2, 1, 1)]),
+        PixelFormat::P010 => Some(&[(2, 1, 0, 0), (2, 2, 1, 1)]),
+        _ => None,
+    }
+}
+
+fn get_layout(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+    buffer_sizes: &[usize],
+) -> Result<Vec<Plane>, ErrorKind> {
+    let pixel_format = format.pixel_format as u32;
+    if dispatcher::get_pixel_format_mode(pixel_format)
+        ^ dispatcher::get_color_space_mode(format.color_space as u32)
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let planes = get_planes(format.pixel_format).ok_or(ErrorKind::InvalidOperation)?;
+
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if !pixel_format::is_compatible(pixel_format, width, height, last_plane) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let last_plane = last_plane as usize;
+    let strides = strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    if last_plane >= strides.len() || last_plane >= buffer_sizes.len() {
+        return Err(ErrorKind::NotEnoughData);
+    }
+
+    let w = width as usize;
+    let h = height as usize;
+
+    // Lay out logical planes one after the other when they share a buffer
+    let mut layout = Vec::with_capacity(planes.len());
+    let mut buffer = 0;
+    let mut offset: usize = 0;
+    for (i, &(depth, samples, hs, vs)) in planes.iter().enumerate() {
+        if i <= last_plane {
+            buffer = i;
+            offset = 0;
+        }
+
+        let row_size = depth * samples * (w >> hs);
+        let stride = match strides.get(i) {
+            Some(&stride) if stride != STRIDE_AUTO => stride,
+            _ => row_size,
+        };
+
+        if stride < row_size {
+            return Err(ErrorKind::InvalidValue);
+        }
+
+        let rows = h >> vs;
+        let size = stride.checked_mul(rows).ok_or(ErrorKind::NotEnoughData)?;
+        let end = offset.checked_add(size).ok_or(ErrorKind::NotEnoughData)?;
+        if end > buffer_sizes[buffer] {
+            return Err(ErrorKind::NotEnoughData);
+        }
+
+        layout.push(Plane {
+            buffer,
+            offset,
+            stride,
+            row_size,
+            rows,
+            chroma: i > 0,
+        });
+
+        offset = end;
+    }
+
+    Ok(layout)
+}
+
+/// Rescales a single sample with the given number of bits
+fn rescale(value: i32, bits: u32, chroma: bool, conversion: RangeConversion) -> i32 {
+    let scale = 1 << (bits - 8);
+    let max = (1 << bits) - 1;
+
+    // Luma ranges start from their base, chroma ranges are centered on it
+    let (limited_base, full_base, range) = if chroma {
+        (128 * scale, 128 * scale, 224 * scale)
+    } else {
+        (16 * scale, 0, 219 * scale)
+    };
+
+    let (src_base, dst_base, num, den) = match conversion {
+        RangeConversion::LimitedToFull => (limited_base, full_base, max, range),
+        RangeConversion::FullToLimited => (full_base, limited_base, range, max),
+    };
+
+    // Round half away from zero, so that chroma stays symmetric around its center
+    let delta = value - src_base;
+    let half = if delta < 0 { -den / 2 } else { den / 2 };
+    (dst_base + (delta * num + half) / den).max(0).min(max)
+}
+
+fn get_table(bits: u32, chroma: bool, conversion: RangeConversion) -> Vec<u16> {
+    (0..(1 << bits))
+        .map(|value| rescale(value, bits, chroma, conversion) as u16)
+        .collect()
+}
+
+/// Rescales a row of samples, reading from `src` and writing to `dst`
+fn convert_row(src: &[u8], dst: &mut [u8], depth: usize, table: &[u16]) {
+    if depth == 1 {
+        for (d, &s) in dst.iter_mut().zip(src.iter()) {
+            *d = table[s as usize] as u8;
+        }
+    } else {
+        // 10-bit samples stored in the upper bits of little endian words
+        for (d, s) in dst.chunks_exact_mut(2).zip(src.chunks_exact(2)) {
+            let value = u16::from_le_bytes([s[0], s[1]]) >> 6;
+            d.copy_from_slice(&(table[value as usize] << 6).to_le_bytes());
+        }
+    }
+}
+
+/// Rescales a row of samples in place
+fn convert_row_in_place(row: &mut [u8], depth: usize, table: &[u16]) {
+    if depth == 1 {
+        for value in row.iter_mut() {
+            *value = table[*value as usize] as u8;
+        }
+    } else {
+        for word in row.chunks_exact_mut(2) {
+            let value = u16::from_le_bytes([word[0], word[1]]) >> 6;
+            word.copy_from_slice(&(table[value as usize] << 6).to_le_bytes());
+        }
+    }
+}
+
+fn get_tables(pixel_format: PixelFormat, conversion: RangeConversion) -> (usize, [Vec<u16>; 2]) {
+    let (depth, bits) = match pixel_format {
+        PixelFormat::P010 => (2, 10),
+        _ => (1, 8),
+    };
+
+    (
+        depth,
+        [
+            get_table(bits, false, conversion),
+            get_table(bits, true, conversion),
+        ],
+    )
+}
+
+/// Converts the range of an image, writing the result into another image
+/// with the same format
+///
+/// # Arguments
+/// * `conversion` - Range conversion to apply
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `format` - Image format of both source and destination images
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source image plane
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination image plane
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the pixel format is not compatible with the color space
+///
+/// * [`InvalidValue`] if `width` or `height` violate the [`size constraints`] that might by
+///   imposed by the image pixel format
+///
+/// * [`InvalidValue`] if the image format has a number of planes which is not compatible
+///   with its pixel format
+///
+/// * [`InvalidValue`] if one or more strides are smaller than the plane line size
+///
+/// * [`InvalidOperation`] if the pixel format is not a YCbCr format
+///
+/// * [`NotEnoughData`] if the source or destination strides array is not `None` and its
+///   length is less than the image format number of planes
+///
+/// * [`NotEnoughData`] if one or more source or destination buffers does not provide
+///   enough data
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+/// [`size constraints`]: ../struct.ImageFormat.html#note
+#[allow(clippy::too_many_arguments)]
+pub fn convert_range(
+    conversion: RangeConversion,
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    let src_sizes: Vec<usize> = src_buffers.iter().map(|buffer| buffer.len()).collect();
+    let dst_sizes: Vec<usize> = dst_buffers.iter().map(|buffer| buffer.len()).collect();
+    let src_layout = get_layout(width, height, format, src_strides, &src_sizes)?;
+    let dst_layout = get_layout(width, height, format, dst_strides, &dst_sizes)?;
+
+    let (depth, tables) = get_tables(format.pixel_format, conversion);
+    for (src_plane, dst_plane) in src_layout.iter().zip(dst_layout.iter()) {
+        let table = &tables[dst_plane.chroma as usize];
+        let src_buffer = &src_buffers[src_plane.buffer][src_plane.offset..];
+        let dst_buffer = &mut dst_buffers[dst_plane.buffer][dst_plane.offset..];
+        for row in 0..dst_plane.rows {
+            let src_row = &src_buffer[row * src_plane.stride..][..src_plane.row_size];
+            let dst_row = &mut dst_buffer[row * dst_plane.stride..][..dst_plane.row_size];
+            convert_row(src_row, dst_row, depth, table);
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts the range of an image in place
+///
+/// # Arguments
+/// * `conversion` - Range conversion to apply
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `format` - Image format
+/// * `strides` - An array of distances in bytes between starts of consecutive lines
+///   in each image planes
+/// * `buffers` - An array of image buffers in each image plane
+///
+/// # Errors
+///
+/// Same as [`convert_range`]
+///
+/// [`convert_range`]: ./fn.convert_range.html
+pub fn convert_range_in_place(
+    conversion: RangeConversion,
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+    buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    let sizes: Vec<usize> = buffers.iter().map(|buffer| buffer.len()).collect();
+    let layout = get_layout(width, height, format, strides, &sizes)?;
+
+    let (depth, tables) = get_tables(format.pixel_format, conversion);
+    for plane in layout.iter() {
+        let table = &tables[plane.chroma as usize];
+        let buffer = &mut buffers[plane.buffer][plane.offset..];
+        for row in 0..plane.rows {
+            convert_row_in_place(
+                &mut buffer[row * plane.stride..][..plane.row_size],
+                depth,
+                table,
+            );
+        }
+    }
+
+    Ok(())
+}
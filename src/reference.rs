@@ -0,0 +1,292 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Pixel-accurate reference formulas used by the conversion functions.
+//!
+//! The functions in this module operate on single pixels and return exactly the
+//! values [`convert_image`] writes, whatever instruction set has been selected at
+//! initialization time. They are meant to be used to unit-test pipelines built on
+//! top of this library, not to convert images.
+//!
+//! YCbCr values use the limited (studio) range: luma in [16, 235] and chroma
+//! in [16, 240], except for [`Jfif`] which uses the full range.
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::reference;
+//! use dcp::ColorSpace;
+//!
+//! assert_eq!(reference::rgb_to_yuv(255, 255, 255, ColorSpace::Bt601).ok(), Some((235, 128, 128)));
+//! assert_eq!(reference::yuv_to_rgb(235, 128, 128, ColorSpace::Bt709).ok(), Some((255, 255, 255)));
+//! ```
+//!
+//! [`convert_image`]: ../fn.convert_image.html
+//! [`Jfif`]: ../enum.ColorSpace.html#variant.Jfif
+use crate::color_space::ColorSpace;
+use crate::convert_image::common::*;
+use crate::ErrorKind;
+
+struct ForwardWeights {
+    xr: i32,
+    xg: i32,
+    xb: i32,
+    yr: i32,
+    yg: i32,
+    yb: i32,
+    zr: i32,
+    zg: i32,
+    zb: i32,
+    y_offset: i32,
+    c_offset: i32,
+}
+
+struct BackwardWeights {
+    xxym: i32,
+    rcrm: i32,
+    gcrm: i32,
+    gcbm: i32,
+    bcbm: i32,
+    rn: i32,
+    gp: i32,
+    bn: i32,
+}
+
+const FORWARD_601: ForwardWeights = ForwardWeights {
+    xr: XR_601,
+    xg: XG_601,
+    xb: XB_601,
+    yr: YR_601,
+    yg: YG_601,
+    yb: YB_601,
+    zr: ZR_601,
+    zg: ZG_601,
+    zb: ZB_601,
+    y_offset: Y_OFFSET,
+    c_offset: C_OFFSET16,
+};
+
+const FORWARD_709: ForwardWeights = ForwardWeights {
+    xr: XR_709,
+    xg: XG_709,
+    xb: XB_709,
+    yr: YR_709,
+    yg: YG_709,
+    yb: YB_709,
+    zr: ZR_709,
+    zg: ZG_709,
+    zb: ZB_709,
+    y_offset: Y_OFFSET,
+    c_offset: C_OFFSET16,
+};
+
+// Chroma ties are rounded down, as libjpeg does
+const FORWARD_JFIF: ForwardWeights = ForwardWeights {
+    xr: XR_JFIF,
+    xg: XG_JFIF,
+    xb: XB_JFIF,
+    yr: YR_JFIF,
+    yg: YG_JFIF,
+    yb: YB_JFIF,
+    zr: ZR_JFIF,
+    zg: ZG_JFIF,
+    zb: ZB_JFIF,
+    y_offset: FIX16_HALF,
+    c_offset: C_OFFSET16 - 1,
+};
+
+const BACKWARD_601: BackwardWeights = BackwardWeights {
+    xxym: XXYM_601,
+    rcrm: RCRM_601,
+    gcrm: GCRM_601,
+    gcbm: GCBM_601,
+    bcbm: BCBM_601,
+    rn: RN_601,
+    gp: GP_601,
+    bn: BN_601,
+};
+
+const BACKWARD_709: BackwardWeights = BackwardWeights {
+    xxym: XXYM_709,
+    rcrm: RCRM_709,
+    gcrm: GCRM_709,
+    gcbm: GCBM_709,
+    bcbm: BCBM_709,
+    rn: RN_709,
+    gp: GP_709,
+    bn: BN_709,
+};
+
+fn forward_weights(color_space: ColorSpace) -> Result<&'static ForwardWeights, ErrorKind> {
+    match color_space {
+        ColorSpace::Bt601 => Ok(&FORWARD_601),
+        ColorSpace::Bt709 => Ok(&FORWARD_709),
+        ColorSpace::Jfif => Ok(&FORWARD_JFIF),
+        _ => Err(ErrorKind::InvalidValue),
+    }
+}
+
+fn backward_weights(color_space: ColorSpace) -> Result<&'static BackwardWeights, ErrorKind> {
+    match color_space {
+        ColorSpace::Bt601 => Ok(&BACKWARD_601),
+        ColorSpace::Bt709 => Ok(&BACKWARD_709),
+        _ => Err(ErrorKind::InvalidValue),
+    }
+}
+
+fn affine_transform(r: i32, g: i32, b: i32, ar: i32, ag: i32, ab: i32, offset: i32) -> i32 {
+    (ar * r) + (ag * g) + (ab * b) + offset
+}
+
+fn mulhi_i32(a: i32, b: i32) -> i32 {
+    (a * b) >> 8
+}
+
+fn fix_to_u8_sat(fix: i32, frac_bits: i32) -> u8 {
+    (fix >> frac_bits).clamp(0, 255) as u8
+}
+
+/// Converts a linear RGB pixel to YCbCr, without chroma subsampling.
+///
+/// This is the formula applied when converting to [`I444`].
+///
+/// # Arguments
+/// * `r`, `g`, `b` - Red, green and blue components
+/// * `color_space` - Destination color space
+///
+/// # Errors
+/// * [`InvalidValue`] if `color_space` is not [`Bt601`], [`Bt709`] or [`Jfif`]
+///
+/// [`I444`]: ../enum.PixelFormat.html#variant.I444
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`Bt601`]: ../enum.ColorSpace.html#variant.Bt601
+/// [`Bt709`]: ../enum.ColorSpace.html#variant.Bt709
+/// [`Jfif`]: ../enum.ColorSpace.html#variant.Jfif
+pub fn rgb_to_yuv(r: u8, g: u8, b: u8, color_space: ColorSpace) -> Result<(u8, u8, u8), ErrorKind> {
+    let w = forward_weights(color_space)?;
+    let (r, g, b) = (i32::from(r), i32::from(g), i32::from(b));
+
+    Ok((
+        (affine_transform(r, g, b, w.xr, w.xg, w.xb, w.y_offset) >> FIX16) as u8,
+        (affine_transform(r, g, b, w.yr, w.yg, w.yb, w.c_offset) >> FIX16) as u8,
+        (affine_transform(r, g, b, w.zr, w.zg, w.zb, w.c_offset) >> FIX16) as u8,
+    ))
+}
+
+/// Converts a 2x2 block of linear RGB pixels to YCbCr, with 4:2:0 chroma subsampling.
+///
+/// This is the formula applied when converting to [`I420`] and [`Nv12`].
+///
+/// # Arguments
+/// * `block` - Red, green and blue components of the pixels in the block, in raster order
+///   (top-left, top-right, bottom-left, bottom-right)
+/// * `color_space` - Destination color space
+///
+/// Returns the luma of each pixel in the block, in the same order, and the chroma
+/// shared by the whole block.
+///
+/// [`Jfif`] is not supported, as the rounding of its subsampled chroma alternates
+/// along the line.
+///
+/// # Errors
+/// * [`InvalidValue`] if `color_space` is neither [`Bt601`] nor [`Bt709`]
+///
+/// [`I420`]: ../enum.PixelFormat.html#variant.I420
+/// [`Nv12`]: ../enum.PixelFormat.html#variant.Nv12
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`Bt601`]: ../enum.ColorSpace.html#variant.Bt601
+/// [`Bt709`]: ../enum.ColorSpace.html#variant.Bt709
+/// [`Jfif`]: ../enum.ColorSpace.html#variant.Jfif
+pub fn rgb_to_yuv_2x2(
+    block: &[(u8, u8, u8); 4],
+    color_space: ColorSpace,
+) -> Result<([u8; 4], u8, u8), ErrorKind> {
+    if let ColorSpace::Jfif = color_space {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let w = forward_weights(color_space)?;
+    let mut luma = [0u8; 4];
+    let (mut sr, mut sg, mut sb) = (0, 0, 0);
+
+    for (y, &(r, g, b)) in luma.iter_mut().zip(block.iter()) {
+        let (r, g, b) = (i32::from(r), i32::from(g), i32::from(b));
+        *y = (affine_transform(r, g, b, w.xr, w.xg, w.xb, Y_OFFSET) >> FIX16) as u8;
+        sr += r;
+        sg += g;
+        sb += b;
+    }
+
+    Ok((
+        luma,
+        (affine_transform(sr, sg, sb, w.yr, w.yg, w.yb, C_OFFSET) >> FIX18) as u8,
+        (affine_transform(sr, sg, sb, w.zr, w.zg, w.zb, C_OFFSET) >> FIX18) as u8,
+    ))
+}
+
+/// Converts a YCbCr pixel to linear RGB.
+///
+/// This is the formula applied when converting from [`I444`], [`I420`] and [`Nv12`]:
+/// subsampled chroma is shared by all the pixels in the block. Results are saturated
+/// to [0, 255].
+///
+/// # Arguments
+/// * `y`, `cb`, `cr` - Luma and chroma components
+/// * `color_space` - Source color space
+///
+/// Returns the red, green and blue components.
+///
+/// # Errors
+/// * [`InvalidValue`] if `color_space` is not [`Bt601`], [`Bt709`] or [`Jfif`]
+///
+/// [`I444`]: ../enum.PixelFormat.html#variant.I444
+/// [`I420`]: ../enum.PixelFormat.html#variant.I420
+/// [`Nv12`]: ../enum.PixelFormat.html#variant.Nv12
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`Bt601`]: ../enum.ColorSpace.html#variant.Bt601
+/// [`Bt709`]: ../enum.ColorSpace.html#variant.Bt709
+/// [`Jfif`]: ../enum.ColorSpace.html#variant.Jfif
+pub fn yuv_to_rgb(
+    y: u8,
+    cb: u8,
+    cr: u8,
+    color_space: ColorSpace,
+) -> Result<(u8, u8, u8), ErrorKind> {
+    if let ColorSpace::Jfif = color_space {
+        let (y, cb, cr) = (i32::from(y), i32::from(cb) - C_HALF, i32::from(cr) - C_HALF);
+        let sat = |x: i32| x.clamp(0, 255) as u8;
+
+        return Ok((
+            sat(y + ((RCR_JFIF * cr + FIX16_HALF) >> FIX16)),
+            sat(y + ((-GCB_JFIF * cb - GCR_JFIF * cr + FIX16_HALF) >> FIX16)),
+            sat(y + ((BCB_JFIF * cb + FIX16_HALF) >> FIX16)),
+        ));
+    }
+
+    let w = backward_weights(color_space)?;
+    let (y, cb, cr) = (i32::from(y), i32::from(cb), i32::from(cr));
+
+    let sr = mulhi_i32(cr, w.rcrm) - w.rn;
+    let sg = -mulhi_i32(cb, w.gcbm) - mulhi_i32(cr, w.gcrm) + w.gp;
+    let sb = mulhi_i32(cb, w.bcbm) - w.bn;
+    let sy = mulhi_i32(y, w.xxym);
+
+    Ok((
+        fix_to_u8_sat(sy + sr, FIX6),
+        fix_to_u8_sat(sy + sg, FIX6),
+        fix_to_u8_sat(sy + sb, FIX6),
+    ))
+}
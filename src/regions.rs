@@ -0,0 +1,984 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Conversion of regions of an image.
+//!
+//! Screen sharing applications usually know which regions of the screen changed since
+//! the previous frame. Those regions can be converted in a single call, leaving the rest
+//! of the destination image untouched: the source and the destination images are both
+//! as large as the whole frame.
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::regions::{convert_regions, Rect};
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn convert() -> Result<(), Box<dyn error::Error>> {
+//!     dcp::initialize();
+//!
+//!     const WIDTH: u32 = 640;
+//!     const HEIGHT: u32 = 480;
+//!
+//!     let src_format = ImageFormat {
+//!         pixel_format: PixelFormat::Bgra,
+//!         color_space: ColorSpace::Lrgb,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let dst_format = ImageFormat {
+//!         pixel_format: PixelFormat::Nv12,
+//!         color_space: ColorSpace::Bt601,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let src_buffer = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+//!     let mut dst_buffer = vec![0u8; 3 * (WIDTH as usize) * (HEIGHT as usize) / 2];
+//!
+//!     let regions = [
+//!         Rect { x: 10, y: 20, width: 100, height: 30 },
+//!         Rect { x: 301, y: 5, width: 17, height: 9 },
+//!     ];
+//!
+//!     convert_regions(
+//!         WIDTH,
+//!         HEIGHT,
+//!         &src_format,
+//!         None,
+//!         &[&src_buffer],
+//!         &dst_format,
+//!         None,
+//!         &mut [&mut dst_buffer],
+//!         &regions,
+//!     )?;
+//!
+//!     Ok(())
+//! }
+//! ```
+use crate::pixel_format;
+use crate::trace_span;
+use crate::{ErrorKind, ImageFormat, PlaneLayout};
+
+/// A rectangular region of an image
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Rect {
+    /// Column of the top left pixel of the region
+    pub x: u32,
+    /// Line of the top left pixel of the region
+    pub y: u32,
+    /// Width of the region in pixels
+    pub width: u32,
+    /// Height of the region in pixels
+    pub height: u32,
+}
+
+/// Location of a logical plane of a region inside the image buffers
+struct RegionPlane {
+    buffer: usize,
+    start: usize,
+    stride: usize,
+    line_size: usize,
+    line_count: usize,
+    /// False if the region lines have to be copied to a scratch buffer
+    in_place: bool,
+}
+
+impl RegionPlane {
+    fn end(&self) -> usize {
+        self.start + self.line_count * self.stride
+    }
+
+    fn band_stride(&self) -> usize {
+        if self.in_place {
+            self.stride
+        } else {
+            self.line_size
+        }
+    }
+}
+
+fn get_plane_end(layout: &PlaneLayout, height: u32) -> usize {
+    layout.offset + ((height >> layout.vertical_shift) as usize) * layout.stride
+}
+
+fn has_enough_data(layouts: &[PlaneLayout], height: u32, buffer_sizes: &[usize]) -> bool {
+    layouts.iter().all(|layout| {
+        layout.buffer < buffer_sizes.len()
+            && get_plane_end(layout, height) <= buffer_sizes[layout.buffer]
+    })
+}
+
+/// Enlarges a region so that it covers whole chroma samples
+fn align_region(
+    region: &Rect,
+    width: u32,
+    height: u32,
+    horizontal_mask: u32,
+    vertical_mask: u32,
+) -> Result<Rect, ErrorKind> {
+    let right = region
+        .x
+        .checked_add(region.width)
+        .filter(|&right| right <= width)
+        .ok_or(ErrorKind::InvalidValue)?;
+    let bottom = region
+        .y
+        .checked_add(region.height)
+        .filter(|&bottom| bottom <= height)
+        .ok_or(ErrorKind::InvalidValue)?;
+
+    if crate::is_empty(region.width, region.height) {
+        return Ok(Rect::default());
+    }
+
+    let x = region.x & !horizontal_mask;
+    let y = region.y & !vertical_mask;
+    Ok(Rect {
+        x,
+        y,
+        width: ((right + horizontal_mask) & !horizontal_mask) - x,
+        height: ((bottom + vertical_mask) & !vertical_mask) - y,
+    })
+}
+
+fn get_region_planes(layouts: &[PlaneLayout], height: u32, region: &Rect) -> Vec<RegionPlane> {
+    layouts
+        .iter()
+        .map(|layout| {
+            let line_count = (region.height >> layout.vertical_shift) as usize;
+            let start = layout.offset
+                + ((region.y >> layout.vertical_shift) as usize) * layout.stride
+                + ((region.x >> layout.horizontal_shift) as usize) * layout.depth;
+
+            // Converters need a whole stride for each line: the lines of a region
+            // touching the bottom of the plane are copied, unless it starts at column 0
+            RegionPlane {
+                buffer: layout.buffer,
+                start,
+                stride: layout.stride,
+                line_size: ((region.width >> layout.horizontal_shift) as usize) * layout.depth,
+                line_count,
+                in_place: start + line_count * layout.stride <= get_plane_end(layout, height),
+            }
+        })
+        .collect()
+}
+
+fn gather(plane: &RegionPlane, src: &[u8], dst: &mut Vec<u8>) {
+    dst.clear();
+    for line in 0..plane.line_count {
+        let start = plane.start + line * plane.stride;
+        dst.extend_from_slice(&src[start..start + plane.line_size]);
+    }
+}
+
+fn scatter(plane: &RegionPlane, src: &[u8], dst: &mut [u8]) {
+    for (line, data) in src.chunks_exact(plane.line_size).enumerate() {
+        let start = plane.start + line * plane.stride;
+        dst[start..start + plane.line_size].copy_from_slice(data);
+    }
+}
+
+fn get_dst_region<'a>(
+    buffers: &'a mut [&mut [u8]],
+    planes: &[RegionPlane],
+    scratch: &'a mut [Vec<u8>],
+) -> Option<Vec<&'a mut [u8]>> {
+    let mut band = Vec::with_capacity(planes.len());
+    let mut buffer_iter = buffers.iter_mut();
+    let mut remainder: &mut [u8] = &mut [];
+    let mut position = 0;
+    for (i, (plane, scratch)) in planes.iter().zip(scratch.iter_mut()).enumerate() {
+        if plane.buffer == i {
+            remainder = &mut buffer_iter.next()?[..];
+            position = 0;
+        }
+
+        if plane.in_place {
+            let (_, tail) = std::mem::take(&mut remainder).split_at_mut(plane.start - position);
+            let (lines, tail) = tail.split_at_mut(plane.end() - plane.start);
+            remainder = tail;
+            position = plane.end();
+            band.push(lines);
+        } else {
+            scratch.clear();
+            scratch.resize(plane.line_count * plane.line_size, 0);
+            band.push(&mut scratch[..]);
+        }
+    }
+
+    Some(band)
+}
+
+/// Converts some regions of an image from a color space to another one, like [`convert_image`]
+///
+/// Both images have the given dimensions, and only the pixels inside the regions are
+/// written to the destination image. Regions are enlarged so that they cover whole chroma
+/// samples of both images: for example, when the destination image has 4:2:0 chroma
+/// subsampling, regions are enlarged to even coordinates and sizes. Regions may overlap.
+///
+/// # Arguments
+/// * `width` - Width of the images in pixels
+/// * `height` - Height of the images in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+/// * `regions` - The regions to convert
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if one or more regions are not inside the images
+///
+/// * [`NotEnoughData`] if one or more source/destination buffers does not provide enough
+///   data for the whole images
+///
+/// * All the errors reported by [`convert_image`], except that conversions are never routed
+///
+/// Regions and buffers are validated before any region is converted.
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+/// [`convert_image`]: ../fn.convert_image.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_regions(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    regions: &[Rect],
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_regions",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format,
+        regions = regions.len()
+    );
+
+    let (converter, last_src_plane, last_dst_plane) =
+        crate::get_converter(width, height, src_format, dst_format)?;
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let src_layouts =
+        crate::get_plane_layouts(src_format, width, height, last_src_plane, src_strides)?;
+    let dst_layouts =
+        crate::get_plane_layouts(dst_format, width, height, last_dst_plane, dst_strides)?;
+    let band_dst_format = crate::get_band_format(dst_format);
+
+    let (horizontal_shift, vertical_shift) = src_layouts.iter().chain(dst_layouts.iter()).fold(
+        (0, 0),
+        |(horizontal, vertical), layout| {
+            (
+                horizontal.max(layout.horizontal_shift),
+                vertical.max(layout.vertical_shift),
+            )
+        },
+    );
+
+    let regions = regions
+        .iter()
+        .map(|region| {
+            align_region(
+                region,
+                width,
+                height,
+                (1 << horizontal_shift) - 1,
+                (1 << vertical_shift) - 1,
+            )
+        })
+        .collect::<Result<Vec<Rect>, ErrorKind>>()?;
+
+    let src_sizes: Vec<usize> = src_buffers.iter().map(|buffer| buffer.len()).collect();
+    let dst_sizes: Vec<usize> = dst_buffers.iter().map(|buffer| buffer.len()).collect();
+    if !has_enough_data(&src_layouts, height, &src_sizes)
+        || !has_enough_data(&dst_layouts, height, &dst_sizes)
+    {
+        return Err(ErrorKind::NotEnoughData);
+    }
+
+    // Each region is converted as an image having one buffer for each logical plane
+    let mut src_scratch: Vec<Vec<u8>> = src_layouts.iter().map(|_| Vec::new()).collect();
+    let mut dst_scratch: Vec<Vec<u8>> = dst_layouts.iter().map(|_| Vec::new()).collect();
+    for region in regions
+        .iter()
+        .filter(|region| !crate::is_empty(region.width, region.height))
+    {
+        let _region_span = trace_span!(
+            TRACE,
+            "region",
+            x = region.x,
+            y = region.y,
+            width = region.width,
+            height = region.height
+        );
+
+        let src_planes = get_region_planes(&src_layouts, height, region);
+        let dst_planes = get_region_planes(&dst_layouts, height, region);
+        for (plane, buffer) in src_planes.iter().zip(src_scratch.iter_mut()) {
+            if !plane.in_place {
+                gather(plane, src_buffers[plane.buffer], buffer);
+            }
+        }
+
+        let band_src_strides: Vec<usize> =
+            src_planes.iter().map(RegionPlane::band_stride).collect();
+        let band_dst_strides: Vec<usize> =
+            dst_planes.iter().map(RegionPlane::band_stride).collect();
+        let src_band: Vec<&[u8]> = src_planes
+            .iter()
+            .zip(src_scratch.iter())
+            .map(|(plane, buffer)| {
+                if plane.in_place {
+                    &src_buffers[plane.buffer][plane.start..plane.end()]
+                } else {
+                    &buffer[..]
+                }
+            })
+            .collect();
+        crate::Band {
+            first_line: region.y as usize,
+            last_line: (region.y + region.height) as usize,
+            src_strides: &band_src_strides,
+            src_buffers: src_band,
+            dst_format: &band_dst_format,
+            dst_strides: &band_dst_strides,
+            dst_buffers: get_dst_region(dst_buffers, &dst_planes, &mut dst_scratch)
+                .ok_or(ErrorKind::NotEnoughData)?,
+        }
+        .convert(converter, region.width)?;
+
+        for (plane, buffer) in dst_planes.iter().zip(dst_scratch.iter()) {
+            if !plane.in_place {
+                scatter(plane, buffer, dst_buffers[plane.buffer]);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// How the pixels of a destination image lying past the right and bottom edges of the
+/// converted region are filled
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EdgePolicy {
+    /// Repeat the last column and the last line of the region
+    Replicate,
+    /// Set the samples to zero
+    Zero,
+}
+
+/// Height of the bands padded by [`convert_image_padded`] right after being converted,
+/// while they are still in cache
+///
+/// [`convert_image_padded`]: ./fn.convert_image_padded.html
+const PADDING_BAND_HEIGHT: usize = 64;
+
+/// Rounds the dimensions of an image up to a multiple of a block size
+///
+/// Encoders need frames made of whole blocks: 2 for the chroma samples of 4:2:0 images,
+/// 16 for H.264 macroblocks, 64 for HEVC coding tree units and AV1 superblocks. The
+/// returned dimensions are the ones of the destination image of [`convert_image_padded`]
+/// and [`convert_region_padded`].
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `block_size` - Size in pixels of the blocks, a power of two
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if `block_size` is not a power of two, or a rounded dimension does
+///   not fit 32 bits
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::regions::padded_dimensions;
+///
+/// assert_eq!(padded_dimensions(1366, 767, 16).unwrap(), (1376, 768));
+/// assert_eq!(padded_dimensions(1920, 1080, 64).unwrap(), (1920, 1088));
+/// ```
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`convert_image_padded`]: ./fn.convert_image_padded.html
+/// [`convert_region_padded`]: ./fn.convert_region_padded.html
+pub fn padded_dimensions(
+    width: u32,
+    height: u32,
+    block_size: u32,
+) -> Result<(u32, u32), ErrorKind> {
+    if !block_size.is_power_of_two() {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let mask = block_size - 1;
+    let round = |size: u32| {
+        size.checked_add(mask)
+            .map(|size| size & !mask)
+            .ok_or(ErrorKind::InvalidValue)
+    };
+
+    Ok((round(width)?, round(height)?))
+}
+
+fn get_sample_count(size: u32, shift: u32) -> usize {
+    ((size + (1 << shift) - 1) >> shift) as usize
+}
+
+/// Locates a logical plane of a region, including the samples it partially covers
+fn get_padded_region_plane(layout: &PlaneLayout, region: &Rect) -> RegionPlane {
+    RegionPlane {
+        buffer: layout.buffer,
+        start: layout.offset
+            + ((region.y >> layout.vertical_shift) as usize) * layout.stride
+            + ((region.x >> layout.horizontal_shift) as usize) * layout.depth,
+        stride: layout.stride,
+        line_size: get_sample_count(region.width, layout.horizontal_shift) * layout.depth,
+        line_count: get_sample_count(region.height, layout.vertical_shift),
+        in_place: false,
+    }
+}
+
+/// Copies a logical plane of a region to a buffer as large as the destination image,
+/// repeating the last sample of each line and the last line
+fn replicate(
+    plane: &RegionPlane,
+    src: &[u8],
+    depth: usize,
+    line_size: usize,
+    line_count: usize,
+    dst: &mut Vec<u8>,
+) {
+    dst.clear();
+    for line in 0..plane.line_count {
+        let start = plane.start + line * plane.stride;
+        let data = &src[start..start + plane.line_size];
+        dst.extend_from_slice(data);
+
+        let last = &data[data.len() - depth..];
+        for _ in (plane.line_size..line_size).step_by(depth) {
+            dst.extend_from_slice(last);
+        }
+    }
+
+    for _ in plane.line_count..line_count {
+        dst.extend_from_within(dst.len() - line_size..);
+    }
+}
+
+/// Converts a region of an image to the top left corner of a larger destination image,
+/// filling the rest of the destination image according to an edge policy
+///
+/// Encoders work on whole macroblocks, so they need images whose dimensions are multiples
+/// of the macroblock size. This function converts the region and fills the pixels past its
+/// right and bottom edges, without copying the region to a padded image first. Whole
+/// images whose size matches the chroma subsampling of both formats are padded in a single
+/// pass by [`convert_image_padded`].
+///
+/// The region does not need to cover whole chroma samples of the destination image: when it
+/// has an odd width or height and the destination image has 4:2:0 chroma subsampling, the
+/// chroma samples straddling its edges are computed by replicating the last column or line.
+/// With [`EdgePolicy::Zero`], the destination samples lying entirely past the edges of the
+/// region are then set to zero.
+///
+/// # Arguments
+/// * `width` - Width of the source image in pixels
+/// * `height` - Height of the source image in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `region` - The region of the source image to convert
+/// * `dst_width` - Width of the destination image in pixels
+/// * `dst_height` - Height of the destination image in pixels
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+/// * `edge_policy` - How the pixels past the edges of the region are filled
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the region is not inside the source image, is larger than the
+///   destination image, does not start on a chroma sample of the source image, or is empty
+///   while the destination image is not
+///
+/// * [`NotEnoughData`] if the source buffers do not provide enough data for the whole
+///   source image
+///
+/// * All the errors reported by [`convert_image`] for a source image as large as the
+///   destination image, except that conversions are never routed
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::regions::{convert_region_padded, padded_dimensions, EdgePolicy, Rect};
+/// use dcp::{ColorSpace, ImageFormat, PixelFormat};
+///
+/// dcp::initialize();
+///
+/// const WIDTH: u32 = 1366;
+/// const HEIGHT: u32 = 767;
+///
+/// // Round up to 16x16 macroblocks
+/// let (dst_width, dst_height) = padded_dimensions(WIDTH, HEIGHT, 16).unwrap();
+///
+/// let src_format = ImageFormat {
+///     pixel_format: PixelFormat::Bgra,
+///     color_space: ColorSpace::Lrgb,
+///     num_planes: 1,
+/// };
+///
+/// let dst_format = ImageFormat {
+///     pixel_format: PixelFormat::I420,
+///     color_space: ColorSpace::Bt709,
+///     num_planes: 3,
+/// };
+///
+/// let src_buffer = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+/// let luma_size = (dst_width as usize) * (dst_height as usize);
+/// let mut y = vec![0u8; luma_size];
+/// let mut u = vec![0u8; luma_size / 4];
+/// let mut v = vec![0u8; luma_size / 4];
+///
+/// convert_region_padded(
+///     WIDTH,
+///     HEIGHT,
+///     &src_format,
+///     None,
+///     &[&src_buffer],
+///     &Rect { x: 0, y: 0, width: WIDTH, height: HEIGHT },
+///     dst_width,
+///     dst_height,
+///     &dst_format,
+///     None,
+///     &mut [&mut y, &mut u, &mut v],
+///     EdgePolicy::Replicate,
+/// )
+/// .unwrap();
+/// ```
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+/// [`convert_image`]: ../fn.convert_image.html
+/// [`EdgePolicy::Zero`]: ./enum.EdgePolicy.html#variant.Zero
+/// [`convert_image_padded`]: ./fn.convert_image_padded.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_region_padded(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    region: &Rect,
+    dst_width: u32,
+    dst_height: u32,
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    edge_policy: EdgePolicy,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_region_padded",
+        width,
+        height,
+        dst_width,
+        dst_height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format,
+        edge_policy = ?edge_policy
+    );
+
+    // The converter sees a source image as large as the destination image
+    let (converter, last_src_plane, last_dst_plane) =
+        crate::get_converter(dst_width, dst_height, src_format, dst_format)?;
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let src_layouts =
+        crate::get_plane_layouts(src_format, width, height, last_src_plane, src_strides)?;
+    let dst_layouts = crate::get_plane_layouts(
+        dst_format,
+        dst_width,
+        dst_height,
+        last_dst_plane,
+        dst_strides,
+    )?;
+
+    let (horizontal_shift, vertical_shift) =
+        src_layouts
+            .iter()
+            .fold((0, 0), |(horizontal, vertical), layout| {
+                (
+                    horizontal.max(layout.horizontal_shift),
+                    vertical.max(layout.vertical_shift),
+                )
+            });
+
+    let is_aligned = region.x & ((1 << horizontal_shift) - 1) == 0
+        && region.y & ((1 << vertical_shift) - 1) == 0;
+    let is_inside = matches!(region.x.checked_add(region.width), Some(right) if right <= width)
+        && matches!(region.y.checked_add(region.height), Some(bottom) if bottom <= height);
+    let fits = region.width <= dst_width && region.height <= dst_height;
+    if !is_aligned || !is_inside || !fits {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    if crate::is_empty(dst_width, dst_height) {
+        return Ok(());
+    }
+
+    if crate::is_empty(region.width, region.height) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let src_sizes: Vec<usize> = src_buffers.iter().map(|buffer| buffer.len()).collect();
+    if !has_enough_data(&src_layouts, height, &src_sizes) {
+        return Err(ErrorKind::NotEnoughData);
+    }
+
+    // The source region is padded to the destination size, one buffer for each logical plane
+    let mut src_scratch: Vec<Vec<u8>> = src_layouts.iter().map(|_| Vec::new()).collect();
+    let mut band_src_strides = Vec::with_capacity(src_layouts.len());
+    for (layout, buffer) in src_layouts.iter().zip(src_scratch.iter_mut()) {
+        let plane = get_padded_region_plane(layout, region);
+        let line_size = layout.depth * ((dst_width >> layout.horizontal_shift) as usize);
+        let line_count = (dst_height >> layout.vertical_shift) as usize;
+        replicate(
+            &plane,
+            src_buffers[plane.buffer],
+            layout.depth,
+            line_size,
+            line_count,
+            buffer,
+        );
+        band_src_strides.push(line_size);
+    }
+
+    let src_band: Vec<&[u8]> = src_scratch.iter().map(|buffer| &buffer[..]).collect();
+    crate::run_converter(
+        converter,
+        dst_width,
+        dst_height,
+        0,
+        (src_layouts.len() - 1) as u32,
+        &band_src_strides,
+        &src_band,
+        dst_format,
+        dst_strides,
+        dst_buffers,
+    )?;
+
+    if edge_policy == EdgePolicy::Zero {
+        for layout in &dst_layouts {
+            let columns = get_sample_count(region.width, layout.horizontal_shift);
+            let lines = get_sample_count(region.height, layout.vertical_shift);
+            let line_size = layout.depth * ((dst_width >> layout.horizontal_shift) as usize);
+            let line_count = (dst_height >> layout.vertical_shift) as usize;
+
+            let buffer = &mut dst_buffers[layout.buffer];
+            for line in 0..line_count {
+                let start = layout.offset + line * layout.stride;
+                let first = if line < lines {
+                    columns * layout.depth
+                } else {
+                    0
+                };
+
+                for sample in &mut buffer[start + first..start + line_size] {
+                    *sample = 0;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Fills the samples of the lines from `first_line` to `last_line` (excluded) lying past
+/// the right edge of the converted image
+fn pad_columns(
+    band: &mut [&mut [u8]],
+    layouts: &[PlaneLayout],
+    width: u32,
+    dst_width: u32,
+    first_line: usize,
+    last_line: usize,
+    edge_policy: EdgePolicy,
+) {
+    for (plane, layout) in band.iter_mut().zip(layouts) {
+        let line_size = layout.depth * ((width >> layout.horizontal_shift) as usize);
+        let padded_line_size = layout.depth * ((dst_width >> layout.horizontal_shift) as usize);
+        let line_count =
+            (last_line >> layout.vertical_shift) - (first_line >> layout.vertical_shift);
+        for line in plane.chunks_mut(layout.stride).take(line_count) {
+            let (data, padding) = line[..padded_line_size].split_at_mut(line_size);
+            match edge_policy {
+                EdgePolicy::Replicate => {
+                    let last = &data[line_size - layout.depth..];
+                    for sample in padding.chunks_exact_mut(layout.depth) {
+                        sample.copy_from_slice(last);
+                    }
+                }
+                EdgePolicy::Zero => {
+                    for sample in padding.iter_mut() {
+                        *sample = 0;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Fills the lines lying past the bottom edge of the converted image. The first line of
+/// each plane of `band` is the last converted one
+fn pad_lines(
+    band: &mut [&mut [u8]],
+    layouts: &[PlaneLayout],
+    dst_width: u32,
+    edge_policy: EdgePolicy,
+) {
+    for (plane, layout) in band.iter_mut().zip(layouts) {
+        let padded_line_size = layout.depth * ((dst_width >> layout.horizontal_shift) as usize);
+        let (last, padding) = plane.split_at_mut(layout.stride);
+        for line in padding.chunks_mut(layout.stride) {
+            let line = &mut line[..padded_line_size];
+            match edge_policy {
+                EdgePolicy::Replicate => line.copy_from_slice(&last[..padded_line_size]),
+                EdgePolicy::Zero => {
+                    for sample in line.iter_mut() {
+                        *sample = 0;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Converts an image to the top left corner of a larger destination image, filling the
+/// rest of the destination image according to an edge policy
+///
+/// This is the single pass version of [`convert_region_padded`], for whole images: each
+/// band of lines is padded on the right right after being converted, while it is still
+/// in cache, and the lines past the bottom edge are filled at the end. The source image
+/// size must be compatible with the chroma subsampling of both formats.
+///
+/// Padding is computed on the converted planes: with [`EdgePolicy::Replicate`], the last
+/// sample of each line and the last line of each destination plane are repeated.
+///
+/// # Arguments
+/// * `width` - Width of the source image in pixels
+/// * `height` - Height of the source image in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_width` - Width of the destination image in pixels, e.g. as returned by
+///   [`padded_dimensions`]
+/// * `dst_height` - Height of the destination image in pixels
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+/// * `edge_policy` - How the pixels past the edges of the source image are filled
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the source image is larger than the destination image, or is
+///   empty while the destination image is not
+///
+/// * [`InvalidValue`] if `dst_width` or `dst_height` violate the [`size constraints`]
+///   of the destination pixel format
+///
+/// * [`NotEnoughData`] if the destination buffers do not provide enough data for the
+///   destination image
+///
+/// * All the errors reported by [`convert_image`] for the source image, except that
+///   conversions are never routed
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::regions::{convert_image_padded, padded_dimensions, EdgePolicy};
+/// use dcp::{ColorSpace, ImageFormat, PixelFormat};
+///
+/// dcp::initialize();
+///
+/// const WIDTH: u32 = 1920;
+/// const HEIGHT: u32 = 1080;
+///
+/// let src_format = ImageFormat {
+///     pixel_format: PixelFormat::Bgra,
+///     color_space: ColorSpace::Lrgb,
+///     num_planes: 1,
+/// };
+///
+/// let dst_format = ImageFormat {
+///     pixel_format: PixelFormat::Nv12,
+///     color_space: ColorSpace::Bt709,
+///     num_planes: 2,
+/// };
+///
+/// // Round up to 64x64 superblocks
+/// let (dst_width, dst_height) = padded_dimensions(WIDTH, HEIGHT, 64).unwrap();
+///
+/// let src_buffer = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+/// let luma_size = (dst_width as usize) * (dst_height as usize);
+/// let mut y = vec![0u8; luma_size];
+/// let mut uv = vec![0u8; luma_size / 2];
+///
+/// convert_image_padded(
+///     WIDTH,
+///     HEIGHT,
+///     &src_format,
+///     None,
+///     &[&src_buffer],
+///     dst_width,
+///     dst_height,
+///     &dst_format,
+///     None,
+///     &mut [&mut y, &mut uv],
+///     EdgePolicy::Replicate,
+/// )
+/// .unwrap();
+/// ```
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+/// [`size constraints`]: ../struct.ImageFormat.html#note
+/// [`convert_image`]: ../fn.convert_image.html
+/// [`convert_region_padded`]: ./fn.convert_region_padded.html
+/// [`padded_dimensions`]: ./fn.padded_dimensions.html
+/// [`EdgePolicy::Replicate`]: ./enum.EdgePolicy.html#variant.Replicate
+#[allow(clippy::too_many_arguments)]
+pub fn convert_image_padded(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_width: u32,
+    dst_height: u32,
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    edge_policy: EdgePolicy,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_image_padded",
+        width,
+        height,
+        dst_width,
+        dst_height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format,
+        edge_policy = ?edge_policy
+    );
+
+    let (converter, last_src_plane, last_dst_plane) =
+        crate::get_converter(width, height, src_format, dst_format)?;
+
+    if width > dst_width
+        || height > dst_height
+        || !pixel_format::is_compatible(
+            dst_format.pixel_format as u32,
+            dst_width,
+            dst_height,
+            last_dst_plane,
+        )
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    if crate::is_empty(dst_width, dst_height) {
+        return Ok(());
+    }
+
+    if crate::is_empty(width, height) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let src_layouts =
+        crate::get_plane_layouts(src_format, width, height, last_src_plane, src_strides)?;
+    let dst_layouts = crate::get_plane_layouts(
+        dst_format,
+        dst_width,
+        dst_height,
+        last_dst_plane,
+        dst_strides,
+    )?;
+
+    let dst_sizes: Vec<usize> = dst_buffers.iter().map(|buffer| buffer.len()).collect();
+    if !has_enough_data(&dst_layouts, dst_height, &dst_sizes) {
+        return Err(ErrorKind::NotEnoughData);
+    }
+
+    let line_count = height as usize;
+    crate::convert_in_bands(
+        &src_layouts,
+        src_buffers,
+        dst_format,
+        &dst_layouts,
+        dst_buffers,
+        crate::get_bands(line_count, PADDING_BAND_HEIGHT),
+        |mut band| {
+            band.convert(converter, width)?;
+            if width < dst_width {
+                pad_columns(
+                    &mut band.dst_buffers,
+                    &dst_layouts,
+                    width,
+                    dst_width,
+                    band.first_line,
+                    band.last_line,
+                    edge_policy,
+                );
+            }
+
+            Ok(())
+        },
+    )?;
+
+    if height < dst_height {
+        let mut dst_band = crate::get_dst_band(
+            dst_buffers,
+            &dst_layouts,
+            line_count - 1,
+            dst_height as usize,
+        )
+        .ok_or(ErrorKind::NotEnoughData)?;
+        pad_lines(&mut dst_band, &dst_layouts, dst_width, edge_policy);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,420 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Rotation and mirroring of YCbCr images.
+//!
+//! Mobile cameras capture frames in the orientation of the sensor, which is usually not
+//! the orientation of the device. Frames can be rotated before encoding them without
+//! converting them to rgb and back: each plane is rotated on its own, and the two
+//! samples of interleaved chroma are moved together, so that they stay paired.
+//!
+//! Rotations by 90 and 270 degrees transpose the planes a square block at a time, so
+//! that both the source and the destination lines of a block stay in the cache.
+//!
+//! Front cameras usually preview mirrored frames. [`mirror_image`] mirrors a frame in
+//! place, without allocating a second one.
+//!
+//! [`mirror_image`]: ./fn.mirror_image.html
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::rotation::{rotate_image, Rotation};
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn rotate() -> Result<(), Box<dyn error::Error>> {
+//!     const WIDTH: u32 = 640;
+//!     const HEIGHT: u32 = 480;
+//!
+//!     let format = ImageFormat {
+//!         pixel_format: PixelFormat::Nv12,
+//!         color_space: ColorSpace::Bt601,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let src_buffer = vec![0u8; 3 * (WIDTH as usize) * (HEIGHT as usize) / 2];
+//!     let mut dst_buffer = vec![0u8; 3 * (WIDTH as usize) * (HEIGHT as usize) / 2];
+//!
+//!     // The sensor is mounted sideways: the rotated image is 480x640
+//!     let rotation = Rotation::from_degrees(90).unwrap();
+//!     rotate_image(
+//!         WIDTH,
+//!         HEIGHT,
+//!         &format,
+//!         None,
+//!         &[&src_buffer],
+//!         None,
+//!         &mut [&mut dst_buffer],
+//!         rotation,
+//!     )?;
+//!
+//!     Ok(())
+//! }
+//! ```
+use crate::composite::check_layout;
+use crate::dispatcher;
+use crate::pixel_format::{self, PixelFormat};
+use crate::trace_span;
+use crate::{ErrorKind, ImageFormat};
+
+/// Side of the square blocks of samples transposed at once
+const BLOCK_SIZE: usize = 32;
+
+/// A clockwise rotation by a multiple of 90 degrees
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    /// The image is copied as it is
+    Degrees0,
+    /// The first line of the source image becomes the last column of the destination one
+    Degrees90,
+    /// The image is turned upside down
+    Degrees180,
+    /// The first line of the source image becomes the first column of the destination one,
+    /// from bottom to top
+    Degrees270,
+}
+
+impl Rotation {
+    /// Returns the clockwise rotation by the given angle, which can be negative for
+    /// counterclockwise rotations, or `None` if it is not a multiple of 90 degrees
+    pub fn from_degrees(degrees: i32) -> Option<Rotation> {
+        match degrees.rem_euclid(360) {
+            0 => Some(Rotation::Degrees0),
+            90 => Some(Rotation::Degrees90),
+            180 => Some(Rotation::Degrees180),
+            270 => Some(Rotation::Degrees270),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the rotation swaps the width and the height of the image
+    pub fn is_transposing(self) -> bool {
+        match self {
+            Rotation::Degrees90 | Rotation::Degrees270 => true,
+            Rotation::Degrees0 | Rotation::Degrees180 => false,
+        }
+    }
+}
+
+/// Rotates a plane of `columns` by `rows` samples, each of them `depth` bytes long
+#[allow(clippy::too_many_arguments)]
+fn rotate_plane(
+    src: &[u8],
+    src_stride: usize,
+    dst: &mut [u8],
+    dst_stride: usize,
+    columns: usize,
+    rows: usize,
+    depth: usize,
+    rotation: Rotation,
+) {
+    let line_size = depth * columns;
+    match rotation {
+        Rotation::Degrees0 => {
+            for y in 0..rows {
+                dst[y * dst_stride..][..line_size]
+                    .copy_from_slice(&src[y * src_stride..][..line_size]);
+            }
+        }
+        Rotation::Degrees180 => {
+            for y in 0..rows {
+                let src_line = &src[y * src_stride..][..line_size];
+                let dst_line = &mut dst[(rows - 1 - y) * dst_stride..][..line_size];
+                for (s, d) in src_line
+                    .chunks_exact(depth)
+                    .rev()
+                    .zip(dst_line.chunks_exact_mut(depth))
+                {
+                    d.copy_from_slice(s);
+                }
+            }
+        }
+        Rotation::Degrees90 | Rotation::Degrees270 => {
+            // Source column x becomes destination line x (90 degrees) or columns - 1 - x
+            // (270 degrees). Each destination line of a block is written left to right
+            for y0 in (0..rows).step_by(BLOCK_SIZE) {
+                let y1 = rows.min(y0 + BLOCK_SIZE);
+                for x0 in (0..columns).step_by(BLOCK_SIZE) {
+                    let x1 = columns.min(x0 + BLOCK_SIZE);
+                    for x in x0..x1 {
+                        let (line, first_column) = if rotation == Rotation::Degrees90 {
+                            (x, rows - y1)
+                        } else {
+                            (columns - 1 - x, y0)
+                        };
+
+                        let dst_line = &mut dst[line * dst_stride + first_column * depth..]
+                            [..depth * (y1 - y0)];
+                        for (i, d) in dst_line.chunks_exact_mut(depth).enumerate() {
+                            let y = if rotation == Rotation::Degrees90 {
+                                y1 - 1 - i
+                            } else {
+                                y0 + i
+                            };
+
+                            d.copy_from_slice(&src[y * src_stride + x * depth..][..depth]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Rotates a YCbCr image clockwise by a multiple of 90 degrees
+///
+/// The destination image has the same format as the source one. Its width and height are
+/// swapped when rotating by 90 or 270 degrees (see [`Rotation::is_transposing`]), and its
+/// strides refer to the rotated image.
+///
+/// The following pixel formats are supported: `I444`, `I420` and `Nv12`. `I422` is
+/// supported only by rotations that do not transpose the image, since transposing its
+/// chroma would subsample it vertically instead of horizontally.
+///
+/// # Arguments
+/// * `width` - Width of the source image in pixels
+/// * `height` - Height of the source image in pixels
+/// * `format` - Image format of both images
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source image plane
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination image plane
+/// * `rotation` - The rotation to apply
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the color space is not a YCbCr one, the image size does not
+///   match the pixel format or the number of planes is not supported
+///
+/// * [`InvalidOperation`] if the pixel format is not supported by the rotation
+///
+/// * [`NotEnoughData`] if the strides arrays are shorter than the number of planes, or
+///   one or more strides are smaller than the line size
+///
+/// * [`NotEnoughData`] if one or more buffers does not provide enough data
+///
+/// [`Rotation::is_transposing`]: ./enum.Rotation.html#method.is_transposing
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+#[allow(clippy::too_many_arguments)]
+pub fn rotate_image(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    rotation: Rotation,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "rotate_image",
+        width,
+        height,
+        format = ?format.pixel_format,
+        rotation = ?rotation
+    );
+
+    let pixel_format = format.pixel_format as u32;
+    if dispatcher::get_pixel_format_mode(pixel_format)
+        ^ dispatcher::get_color_space_mode(format.color_space as u32)
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    match format.pixel_format {
+        PixelFormat::I444 | PixelFormat::I420 | PixelFormat::Nv12 => {}
+        PixelFormat::I422 if !rotation.is_transposing() => {}
+        _ => return Err(ErrorKind::InvalidOperation),
+    }
+
+    let (dst_width, dst_height) = if rotation.is_transposing() {
+        (height, width)
+    } else {
+        (width, height)
+    };
+
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if !pixel_format::is_compatible(pixel_format, width, height, last_plane)
+        || !pixel_format::is_compatible(pixel_format, dst_width, dst_height, last_plane)
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let src_layouts = crate::get_plane_layouts(
+        format,
+        width,
+        height,
+        last_plane,
+        src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+    )?;
+    let dst_layouts = crate::get_plane_layouts(
+        format,
+        dst_width,
+        dst_height,
+        last_plane,
+        dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+    )?;
+
+    let src_sizes: Vec<usize> = src_buffers.iter().map(|buffer| buffer.len()).collect();
+    let dst_sizes: Vec<usize> = dst_buffers.iter().map(|buffer| buffer.len()).collect();
+    for (src_layout, dst_layout) in src_layouts.iter().zip(dst_layouts.iter()) {
+        check_layout(src_layout, width, height, &src_sizes)?;
+        check_layout(dst_layout, dst_width, dst_height, &dst_sizes)?;
+    }
+
+    for (src_layout, dst_layout) in src_layouts.iter().zip(dst_layouts.iter()) {
+        let columns = (width >> src_layout.horizontal_shift) as usize;
+        let rows = (height >> src_layout.vertical_shift) as usize;
+        if columns == 0 || rows == 0 {
+            continue;
+        }
+
+        rotate_plane(
+            &src_buffers[src_layout.buffer][src_layout.offset..],
+            src_layout.stride,
+            &mut dst_buffers[dst_layout.buffer][dst_layout.offset..],
+            dst_layout.stride,
+            columns,
+            rows,
+            src_layout.depth,
+            rotation,
+        );
+    }
+
+    Ok(())
+}
+
+/// A mirroring of an image
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mirror {
+    /// Left and right are swapped
+    Horizontal,
+    /// Top and bottom are swapped
+    Vertical,
+}
+
+/// Mirrors a YCbCr image in place
+///
+/// The following pixel formats are supported: `I444`, `I422`, `I420` and `Nv12`.
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `format` - Image format
+/// * `strides` - An array of distances in bytes between starts of consecutive lines
+///   in each image planes
+/// * `buffers` - An array of image buffers in each image plane
+/// * `mirror` - The mirroring to apply
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the color space is not a YCbCr one, the image size does not
+///   match the pixel format or the number of planes is not supported
+///
+/// * [`InvalidOperation`] if the pixel format is not supported
+///
+/// * [`NotEnoughData`] if the strides array is shorter than the number of planes, or
+///   one or more strides are smaller than the line size
+///
+/// * [`NotEnoughData`] if one or more buffers does not provide enough data
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+pub fn mirror_image(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+    buffers: &mut [&mut [u8]],
+    mirror: Mirror,
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "mirror_image",
+        width,
+        height,
+        format = ?format.pixel_format,
+        mirror = ?mirror
+    );
+
+    let pixel_format = format.pixel_format as u32;
+    if dispatcher::get_pixel_format_mode(pixel_format)
+        ^ dispatcher::get_color_space_mode(format.color_space as u32)
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    match format.pixel_format {
+        PixelFormat::I444 | PixelFormat::I422 | PixelFormat::I420 | PixelFormat::Nv12 => {}
+        _ => return Err(ErrorKind::InvalidOperation),
+    }
+
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if !pixel_format::is_compatible(pixel_format, width, height, last_plane) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let layouts = crate::get_plane_layouts(
+        format,
+        width,
+        height,
+        last_plane,
+        strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+    )?;
+    let sizes: Vec<usize> = buffers.iter().map(|buffer| buffer.len()).collect();
+    for layout in layouts.iter() {
+        check_layout(layout, width, height, &sizes)?;
+    }
+
+    for layout in layouts.iter() {
+        let depth = layout.depth;
+        let line_size = depth * ((width >> layout.horizontal_shift) as usize);
+        let rows = (height >> layout.vertical_shift) as usize;
+        let buffer = &mut buffers[layout.buffer][layout.offset..];
+        match mirror {
+            Mirror::Horizontal => {
+                for y in 0..rows {
+                    let line = &mut buffer[y * layout.stride..][..line_size];
+                    if depth == 1 {
+                        line.reverse();
+                    } else {
+                        // Interleaved chroma samples are swapped in pairs
+                        let samples = line_size / depth;
+                        for i in 0..samples / 2 {
+                            let (left, right) = line.split_at_mut((samples - 1 - i) * depth);
+                            left[i * depth..][..depth].swap_with_slice(&mut right[..depth]);
+                        }
+                    }
+                }
+            }
+            Mirror::Vertical => {
+                for y in 0..rows / 2 {
+                    let (top, bottom) = buffer.split_at_mut((rows - 1 - y) * layout.stride);
+                    top[y * layout.stride..][..line_size].swap_with_slice(&mut bottom[..line_size]);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
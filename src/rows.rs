@@ -0,0 +1,203 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Conversion of images whose lines are not evenly spaced in memory.
+//!
+//! Screen capture applications often keep the lines of a frame in a ring buffer, so
+//! that scrolled content can be expressed just by rotating the line order. Images in this
+//! layout are described by a table of rows, and can be converted without storing them
+//! in a linear image first: the lines are gathered a few at a time while the conversion
+//! progresses.
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::rows::convert_rows;
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn convert() -> Result<(), Box<dyn error::Error>> {
+//!     dcp::initialize();
+//!
+//!     const WIDTH: u32 = 640;
+//!     const HEIGHT: u32 = 480;
+//!     const SCROLL: usize = 100;
+//!
+//!     let src_format = ImageFormat {
+//!         pixel_format: PixelFormat::Bgra,
+//!         color_space: ColorSpace::Lrgb,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let dst_format = ImageFormat {
+//!         pixel_format: PixelFormat::Nv12,
+//!         color_space: ColorSpace::Bt601,
+//!         num_planes: 1,
+//!     };
+//!
+//!     // The content was scrolled up by 100 lines
+//!     let src_buffer = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+//!     let mut lines: Vec<&[u8]> = src_buffer.chunks(4 * (WIDTH as usize)).collect();
+//!     lines.rotate_left(SCROLL);
+//!
+//!     let mut dst_buffer = vec![0u8; 3 * (WIDTH as usize) * (HEIGHT as usize) / 2];
+//!
+//!     convert_rows(
+//!         WIDTH,
+//!         HEIGHT,
+//!         &src_format,
+//!         &[&lines],
+//!         &dst_format,
+//!         None,
+//!         &mut [&mut dst_buffer],
+//!     )?;
+//!
+//!     Ok(())
+//! }
+//! ```
+use crate::pixel_format;
+use crate::trace_span;
+use crate::{ErrorKind, ImageFormat};
+
+/// Lines gathered at once
+const GATHERED_BAND_HEIGHT: usize = 64;
+
+/// Copies the lines from `first_line` to `last_line` (excluded) of a plane into
+/// a linear buffer
+fn gather(
+    lines: &[&[u8]],
+    line_size: usize,
+    first_line: usize,
+    last_line: usize,
+    dst: &mut Vec<u8>,
+) {
+    dst.clear();
+    for line in &lines[first_line..last_line] {
+        dst.extend_from_slice(&line[..line_size]);
+    }
+}
+
+/// Converts an image described by row tables from a color space to another one,
+/// like [`convert_image`]
+///
+/// Each line of the source image can be stored anywhere in memory. The source image is
+/// described by one row table for each of its logical planes: one table for packed
+/// formats like `Bgra`, two tables (luma and interleaved chroma) for `Nv12`, three tables
+/// for `I420`. The tables do not depend on the number of planes of the source image format.
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `src_rows` - An array of row tables, each one listing the lines of a source logical plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+///
+/// # Errors
+///
+/// * [`NotEnoughData`] if there are fewer row tables than source logical planes, if a table
+///   has fewer rows than the lines of its plane, or if a row is shorter than a line
+///
+/// * All the errors reported by [`convert_image`], except that conversions are never routed
+///
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+/// [`convert_image`]: ../fn.convert_image.html
+pub fn convert_rows(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_rows: &[&[&[u8]]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_rows",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    let (converter, _, last_dst_plane) =
+        crate::get_converter(width, height, src_format, dst_format)?;
+
+    let src_planes = pixel_format::get_logical_planes(src_format.pixel_format);
+    if src_rows.len() < src_planes.len() {
+        return Err(ErrorKind::NotEnoughData);
+    }
+
+    let line_sizes: Vec<usize> = src_planes
+        .iter()
+        .map(|&(depth, horizontal_shift, _)| depth * ((width >> horizontal_shift) as usize))
+        .collect();
+    for ((&(_, _, vertical_shift), lines), &line_size) in
+        src_planes.iter().zip(src_rows).zip(line_sizes.iter())
+    {
+        let line_count = (height >> vertical_shift) as usize;
+        if lines.len() < line_count
+            || lines[..line_count]
+                .iter()
+                .any(|line| line.len() < line_size)
+        {
+            return Err(ErrorKind::NotEnoughData);
+        }
+    }
+
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_layouts =
+        crate::get_plane_layouts(dst_format, width, height, last_dst_plane, dst_strides)?;
+
+    // Source bands are gathered to one packed buffer for each logical plane
+    let band_dst_strides = crate::get_band_strides(&dst_layouts);
+    let band_dst_format = crate::get_band_format(dst_format);
+
+    let mut scratch: Vec<Vec<u8>> = src_planes.iter().map(|_| Vec::new()).collect();
+    for (first_line, last_line) in crate::get_bands(height as usize, GATHERED_BAND_HEIGHT) {
+        let _band_span = trace_span!(TRACE, "band", first_line, last_line);
+        for (((&(_, _, vertical_shift), lines), &line_size), buffer) in src_planes
+            .iter()
+            .zip(src_rows)
+            .zip(line_sizes.iter())
+            .zip(scratch.iter_mut())
+        {
+            gather(
+                lines,
+                line_size,
+                first_line >> vertical_shift,
+                last_line >> vertical_shift,
+                buffer,
+            );
+        }
+
+        crate::Band {
+            first_line,
+            last_line,
+            src_strides: &line_sizes,
+            src_buffers: scratch.iter().map(|buffer| &buffer[..]).collect(),
+            dst_format: &band_dst_format,
+            dst_strides: &band_dst_strides,
+            dst_buffers: crate::get_dst_band(dst_buffers, &dst_layouts, first_line, last_line)
+                .ok_or(ErrorKind::NotEnoughData)?,
+        }
+        .convert(converter, width)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,756 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Conversion with simultaneous downscaling.
+//!
+//! Simulcast streaming sends the same content at several resolutions, e.g. a full
+//! resolution layer plus a half and a quarter resolution one. Converting the frame once
+//! and then downscaling the converted image reads it again from memory for every layer.
+//! [`convert_image_scaled`] converts the frame in horizontal bands instead, and downscales
+//! each band to all the smaller images right after converting it, while it is still in
+//! cache: the source image is read once, and the full resolution image is written once.
+//!
+//! Images are downscaled by powers of two, averaging the samples of each square block
+//! of every plane.
+//!
+//! Motion estimation works on pyramids of YCbCr images instead, each level half the size
+//! of the previous one. [`build_pyramid`] computes the levels of an existing image, and
+//! [`convert_image_pyramid`] computes them while converting the full resolution image,
+//! again in a single pass. Each level is computed from the previous one, and the chroma
+//! planes, or each chroma component of interleaved planes, are downscaled like the luma
+//! plane.
+//!
+//! [`convert_image_scaled`]: ./fn.convert_image_scaled.html
+//! [`build_pyramid`]: ./fn.build_pyramid.html
+//! [`convert_image_pyramid`]: ./fn.convert_image_pyramid.html
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::scaling::{convert_image_scaled, scaled_dimensions, ScaledImage};
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn simulcast() -> Result<(), Box<dyn error::Error>> {
+//!     dcp::initialize();
+//!
+//!     const WIDTH: u32 = 1280;
+//!     const HEIGHT: u32 = 720;
+//!
+//!     let src_format = ImageFormat {
+//!         pixel_format: PixelFormat::Bgra,
+//!         color_space: ColorSpace::Lrgb,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let dst_format = ImageFormat {
+//!         pixel_format: PixelFormat::I420,
+//!         color_space: ColorSpace::Bt709,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let i420_size = |(width, height): (u32, u32)| 3 * (width as usize) * (height as usize) / 2;
+//!     let half_size = scaled_dimensions(WIDTH, HEIGHT, PixelFormat::I420, 2)?;
+//!     let quarter_size = scaled_dimensions(WIDTH, HEIGHT, PixelFormat::I420, 4)?;
+//!     assert_eq!(half_size, (640, 360));
+//!     assert_eq!(quarter_size, (320, 180));
+//!
+//!     let src_buffer = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+//!     let mut full = vec![0u8; i420_size((WIDTH, HEIGHT))];
+//!     let mut half = vec![0u8; i420_size(half_size)];
+//!     let mut quarter = vec![0u8; i420_size(quarter_size)];
+//!
+//!     convert_image_scaled(
+//!         WIDTH,
+//!         HEIGHT,
+//!         &src_format,
+//!         None,
+//!         &[&src_buffer],
+//!         &dst_format,
+//!         None,
+//!         &mut [&mut full],
+//!         &mut [
+//!             ScaledImage {
+//!                 factor: 2,
+//!                 strides: None,
+//!                 buffers: &mut [&mut half],
+//!             },
+//!             ScaledImage {
+//!                 factor: 4,
+//!                 strides: None,
+//!                 buffers: &mut [&mut quarter],
+//!             },
+//!         ],
+//!     )?;
+//!
+//!     Ok(())
+//! }
+//! ```
+use crate::composite::check_layout;
+use crate::dispatcher;
+use crate::pixel_format::{self, PixelFormat};
+use crate::trace_span;
+use crate::{ErrorKind, ImageFormat, PlaneLayout};
+
+/// Height of the bands downscaled right after being converted, while they are still in
+/// cache. Bands are made taller when needed to cover whole blocks of the largest factor
+const SCALING_BAND_HEIGHT: usize = 64;
+
+/// A downscaled destination image of [`convert_image_scaled`]
+///
+/// [`convert_image_scaled`]: ./fn.convert_image_scaled.html
+#[derive(Debug)]
+pub struct ScaledImage<'a, 'b> {
+    /// Ratio between the size of the full resolution image and the size of this image,
+    /// a power of two greater than one
+    pub factor: u32,
+    /// An array of distances in bytes between starts of consecutive lines
+    /// in each image planes
+    pub strides: Option<&'a [usize]>,
+    /// An array of image buffers in each color plane
+    pub buffers: &'a mut [&'b mut [u8]],
+}
+
+/// Returns true if the pixel format has 8-bit samples
+fn is_byte_format(pixel_format: PixelFormat) -> bool {
+    matches!(
+        pixel_format,
+        PixelFormat::Argb
+            | PixelFormat::Bgra
+            | PixelFormat::Bgr
+            | PixelFormat::Rgba
+            | PixelFormat::Rgb
+            | PixelFormat::I444
+            | PixelFormat::I422
+            | PixelFormat::I420
+            | PixelFormat::Nv12
+    )
+}
+
+/// Returns the dimensions of an image downscaled by a factor
+///
+/// The dimensions are divided by the factor and rounded down, to the chroma subsampling
+/// of the pixel format: the pixels of the full resolution image past the last whole block
+/// are not part of the downscaled image.
+///
+/// # Arguments
+/// * `width` - Width of the full resolution image in pixels
+/// * `height` - Height of the full resolution image in pixels
+/// * `pixel_format` - Pixel format of the images
+/// * `factor` - Downscaling factor, a power of two greater than one
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if `factor` is not a power of two greater than one
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+pub fn scaled_dimensions(
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+    factor: u32,
+) -> Result<(u32, u32), ErrorKind> {
+    if factor < 2 || !factor.is_power_of_two() {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let (horizontal_shift, vertical_shift) = pixel_format::get_logical_planes(pixel_format)
+        .iter()
+        .fold((0, 0), |(horizontal, vertical), &(_, h, v)| {
+            (horizontal.max(h), vertical.max(v))
+        });
+
+    Ok((
+        (width / factor) >> horizontal_shift << horizontal_shift,
+        (height / factor) >> vertical_shift << vertical_shift,
+    ))
+}
+
+/// Averages the square blocks of samples of a plane. `src` starts at the first line of
+/// the blocks, and `dst` at the first line to write
+#[allow(clippy::too_many_arguments)]
+fn downscale_plane(
+    src: &[u8],
+    src_stride: usize,
+    dst: &mut [u8],
+    dst_stride: usize,
+    depth: usize,
+    columns: usize,
+    lines: usize,
+    factor: usize,
+) {
+    let shift = 2 * factor.trailing_zeros();
+    let bias = (factor * factor / 2) as u32;
+    let line_size = depth * columns;
+    let mut sums = vec![0u32; line_size];
+    for line in 0..lines {
+        for sum in sums.iter_mut() {
+            *sum = 0;
+        }
+
+        for src_line in src[line * factor * src_stride..]
+            .chunks(src_stride)
+            .take(factor)
+        {
+            for (block, sums) in src_line
+                .chunks(depth * factor)
+                .zip(sums.chunks_exact_mut(depth))
+            {
+                for sample in block.chunks_exact(depth) {
+                    for (sum, &value) in sums.iter_mut().zip(sample) {
+                        *sum += u32::from(value);
+                    }
+                }
+            }
+        }
+
+        for (value, &sum) in dst[line * dst_stride..][..line_size]
+            .iter_mut()
+            .zip(sums.iter())
+        {
+            *value = ((sum + bias) >> shift) as u8;
+        }
+    }
+}
+
+/// Layouts of a downscaled image
+struct ScaledLayout {
+    factor: usize,
+    width: u32,
+    height: u32,
+    layouts: Vec<PlaneLayout>,
+}
+
+/// Converts from a color space to another one, like [`convert_image`], writing downscaled
+/// versions of the destination image in the same pass
+///
+/// Each downscaled image has the dimensions returned by [`scaled_dimensions`] for its
+/// factor, and the same format as the full resolution destination image. The following
+/// destination pixel formats are supported: `Argb`, `Bgra`, `Bgr`, `Rgba`, `Rgb`, `I444`,
+/// `I422`, `I420` and `Nv12`.
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+/// * `scaled_images` - The downscaled destination images
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the factor of a downscaled image is not a power of two greater
+///   than one
+///
+/// * [`InvalidOperation`] if the destination pixel format is not supported
+///
+/// * [`NotEnoughData`] if the strides or buffers of a downscaled image do not provide
+///   enough data
+///
+/// * All the errors reported by [`convert_image`], except that conversions are never routed
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+/// [`convert_image`]: ../fn.convert_image.html
+/// [`scaled_dimensions`]: ./fn.scaled_dimensions.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_image_scaled(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    scaled_images: &mut [ScaledImage],
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_image_scaled",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format,
+        scaled_images = scaled_images.len()
+    );
+
+    let (converter, last_src_plane, last_dst_plane) =
+        crate::get_converter(width, height, src_format, dst_format)?;
+
+    if !is_byte_format(dst_format.pixel_format) {
+        return Err(ErrorKind::InvalidOperation);
+    }
+
+    let mut scaled_layouts = Vec::with_capacity(scaled_images.len());
+    for image in scaled_images.iter() {
+        let (scaled_width, scaled_height) =
+            scaled_dimensions(width, height, dst_format.pixel_format, image.factor)?;
+        let layouts = crate::get_plane_layouts(
+            dst_format,
+            scaled_width,
+            scaled_height,
+            last_dst_plane,
+            image.strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+        )?;
+
+        let sizes: Vec<usize> = image.buffers.iter().map(|buffer| buffer.len()).collect();
+        for layout in layouts.iter() {
+            check_layout(layout, scaled_width, scaled_height, &sizes)?;
+        }
+
+        scaled_layouts.push(ScaledLayout {
+            factor: image.factor as usize,
+            width: scaled_width,
+            height: scaled_height,
+            layouts,
+        });
+    }
+
+    if crate::is_empty(width, height) {
+        return Ok(());
+    }
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let src_layouts =
+        crate::get_plane_layouts(src_format, width, height, last_src_plane, src_strides)?;
+    let dst_layouts =
+        crate::get_plane_layouts(dst_format, width, height, last_dst_plane, dst_strides)?;
+
+    // Bands cover whole blocks of chroma samples of every downscaled image
+    let band_height = scaled_layouts
+        .iter()
+        .map(|scaled| 2 * scaled.factor)
+        .fold(SCALING_BAND_HEIGHT, usize::max);
+
+    crate::convert_in_bands(
+        &src_layouts,
+        src_buffers,
+        dst_format,
+        &dst_layouts,
+        dst_buffers,
+        crate::get_bands(height as usize, band_height),
+        |mut band| {
+            band.convert(converter, width)?;
+            for (scaled, image) in scaled_layouts.iter().zip(scaled_images.iter_mut()) {
+                // Lines of the downscaled image computed from this band
+                let first = band.first_line / scaled.factor;
+                let last = (band.last_line / scaled.factor).min(scaled.height as usize);
+                for ((layout, plane), dst_layout) in scaled
+                    .layouts
+                    .iter()
+                    .zip(band.dst_buffers.iter())
+                    .zip(dst_layouts.iter())
+                {
+                    let shift = layout.vertical_shift;
+                    let lines = (last >> shift).saturating_sub(first >> shift);
+                    if lines == 0 {
+                        continue;
+                    }
+
+                    let buffer = &mut image.buffers[layout.buffer];
+                    downscale_plane(
+                        plane,
+                        dst_layout.stride,
+                        &mut buffer[layout.offset + (first >> shift) * layout.stride..],
+                        layout.stride,
+                        layout.depth,
+                        (scaled.width >> layout.horizontal_shift) as usize,
+                        lines,
+                        scaled.factor,
+                    );
+                }
+            }
+
+            Ok(())
+        },
+    )
+}
+
+/// A level of an image pyramid, half the size of the previous one
+#[derive(Debug)]
+pub struct PyramidLevel<'a, 'b> {
+    /// An array of distances in bytes between starts of consecutive lines
+    /// in each image planes
+    pub strides: Option<&'a [usize]>,
+    /// An array of image buffers in each color plane
+    pub buffers: &'a mut [&'b mut [u8]],
+}
+
+/// Returns true if the pixel format is a supported 8-bit YCbCr format
+fn is_pyramid_format(pixel_format: PixelFormat) -> bool {
+    matches!(
+        pixel_format,
+        PixelFormat::I444 | PixelFormat::I422 | PixelFormat::I420 | PixelFormat::Nv12
+    )
+}
+
+/// Returns the dimensions of a level of an image pyramid
+///
+/// Each level is half the size of the previous one, rounded down to the chroma
+/// subsampling of the pixel format, as returned by [`scaled_dimensions`] with a factor
+/// of two. Level zero is the full resolution image.
+///
+/// # Arguments
+/// * `width` - Width of the full resolution image in pixels
+/// * `height` - Height of the full resolution image in pixels
+/// * `pixel_format` - Pixel format of the images
+/// * `level` - Index of the level
+///
+/// [`scaled_dimensions`]: ./fn.scaled_dimensions.html
+pub fn pyramid_dimensions(
+    width: u32,
+    height: u32,
+    pixel_format: PixelFormat,
+    level: u32,
+) -> (u32, u32) {
+    (0..level).fold((width, height), |(width, height), _| {
+        scaled_dimensions(width, height, pixel_format, 2).unwrap_or((0, 0))
+    })
+}
+
+/// Returns the layouts of the pyramid levels, checking they fit their buffers
+fn get_level_layouts(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    last_plane: u32,
+    levels: &[PyramidLevel],
+) -> Result<Vec<ScaledLayout>, ErrorKind> {
+    let mut level_layouts = Vec::with_capacity(levels.len());
+    for (index, level) in levels.iter().enumerate() {
+        let (level_width, level_height) =
+            pyramid_dimensions(width, height, format.pixel_format, (index + 1) as u32);
+        let layouts = crate::get_plane_layouts(
+            format,
+            level_width,
+            level_height,
+            last_plane,
+            level.strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+        )?;
+
+        let sizes: Vec<usize> = level.buffers.iter().map(|buffer| buffer.len()).collect();
+        for layout in layouts.iter() {
+            check_layout(layout, level_width, level_height, &sizes)?;
+        }
+
+        level_layouts.push(ScaledLayout {
+            factor: 2,
+            width: level_width,
+            height: level_height,
+            layouts,
+        });
+    }
+
+    Ok(level_layouts)
+}
+
+/// Height of the full resolution bands, covering whole chroma samples of every level
+fn get_pyramid_band_height(levels: usize) -> usize {
+    SCALING_BAND_HEIGHT.max(2 << levels.min(31))
+}
+
+/// Computes the lines of the pyramid levels depending on the lines from `first_line`
+/// to `last_line` (excluded) of the full resolution image, stored in `band`
+fn downscale_band(
+    band: &[&[u8]],
+    band_layouts: &[PlaneLayout],
+    first_line: usize,
+    last_line: usize,
+    level_layouts: &[ScaledLayout],
+    levels: &mut [PyramidLevel],
+) {
+    let (mut first, mut last) = (first_line, last_line);
+    for index in 0..levels.len() {
+        let scaled = &level_layouts[index];
+        let level_first = first / 2;
+        let level_last = (last / 2).min(scaled.height as usize);
+        let (previous, current) = levels.split_at_mut(index);
+        for (plane, layout) in scaled.layouts.iter().enumerate() {
+            let shift = layout.vertical_shift;
+            let lines = (level_last >> shift).saturating_sub(level_first >> shift);
+            if lines == 0 {
+                continue;
+            }
+
+            // The previous level is the full resolution band, or the lines just written
+            let (src, src_stride) = if index == 0 {
+                (band[plane], band_layouts[plane].stride)
+            } else {
+                let src_layout = &level_layouts[index - 1].layouts[plane];
+                let src = &previous[index - 1].buffers[src_layout.buffer];
+                (
+                    &src[src_layout.offset + (first >> shift) * src_layout.stride..],
+                    src_layout.stride,
+                )
+            };
+
+            let dst = &mut current[0].buffers[layout.buffer];
+            downscale_plane(
+                src,
+                src_stride,
+                &mut dst[layout.offset + (level_first >> shift) * layout.stride..],
+                layout.stride,
+                layout.depth,
+                (scaled.width >> layout.horizontal_shift) as usize,
+                lines,
+                2,
+            );
+        }
+
+        first = level_first;
+        last = level_last;
+    }
+}
+
+/// Computes the levels of the pyramid of a YCbCr image
+///
+/// Level `i` of `levels` is the one with index `i + 1` for [`pyramid_dimensions`]: the
+/// first one is half the size of the image. All the levels have the format of the image.
+/// The following pixel formats are supported: `I444`, `I422`, `I420` and `Nv12`.
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `format` - Image format
+/// * `strides` - An array of distances in bytes between starts of consecutive lines
+///   in each image planes
+/// * `buffers` - An array of image buffers in each color plane
+/// * `levels` - The levels of the pyramid, from the largest to the smallest
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the color space is not a YCbCr one, the image size does not
+///   match the pixel format or the number of planes is not supported
+///
+/// * [`InvalidOperation`] if the pixel format is not supported
+///
+/// * [`NotEnoughData`] if the strides arrays are shorter than the number of planes, or one
+///   or more buffers of the image or of the levels does not provide enough data
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::scaling::{build_pyramid, pyramid_dimensions, PyramidLevel};
+/// use dcp::{ColorSpace, ImageFormat, PixelFormat};
+/// use std::error;
+///
+/// fn pyramid() -> Result<(), Box<dyn error::Error>> {
+///     const WIDTH: u32 = 640;
+///     const HEIGHT: u32 = 480;
+///
+///     let format = ImageFormat {
+///         pixel_format: PixelFormat::Nv12,
+///         color_space: ColorSpace::Bt601,
+///         num_planes: 1,
+///     };
+///
+///     let nv12_size = |(width, height): (u32, u32)| 3 * (width as usize) * (height as usize) / 2;
+///     let image = vec![128u8; nv12_size((WIDTH, HEIGHT))];
+///     let mut buffers: Vec<Vec<u8>> = (1..4)
+///         .map(|level| pyramid_dimensions(WIDTH, HEIGHT, format.pixel_format, level))
+///         .map(|dimensions| vec![0u8; nv12_size(dimensions)])
+///         .collect();
+///
+///     let mut level_buffers: Vec<[&mut [u8]; 1]> =
+///         buffers.iter_mut().map(|buffer| [&mut buffer[..]]).collect();
+///     let mut levels: Vec<PyramidLevel> = level_buffers
+///         .iter_mut()
+///         .map(|buffers| PyramidLevel {
+///             strides: None,
+///             buffers,
+///         })
+///         .collect();
+///
+///     build_pyramid(WIDTH, HEIGHT, &format, None, &[&image], &mut levels)?;
+///     assert_eq!(buffers[2].len(), 80 * 60 * 3 / 2);
+///
+///     Ok(())
+/// }
+/// # pyramid().unwrap();
+/// ```
+///
+/// [`pyramid_dimensions`]: ./fn.pyramid_dimensions.html
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+pub fn build_pyramid(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: Option<&[usize]>,
+    buffers: &[&[u8]],
+    levels: &mut [PyramidLevel],
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "build_pyramid",
+        width,
+        height,
+        format = ?format.pixel_format,
+        levels = levels.len()
+    );
+
+    let pixel_format = format.pixel_format as u32;
+    if dispatcher::get_pixel_format_mode(pixel_format)
+        ^ dispatcher::get_color_space_mode(format.color_space as u32)
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    if !is_pyramid_format(format.pixel_format) {
+        return Err(ErrorKind::InvalidOperation);
+    }
+
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if !pixel_format::is_compatible(pixel_format, width, height, last_plane) {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let layouts = crate::get_plane_layouts(
+        format,
+        width,
+        height,
+        last_plane,
+        strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+    )?;
+    let sizes: Vec<usize> = buffers.iter().map(|buffer| buffer.len()).collect();
+    for layout in layouts.iter() {
+        check_layout(layout, width, height, &sizes)?;
+    }
+
+    let level_layouts = get_level_layouts(width, height, format, last_plane, levels)?;
+    let band_height = get_pyramid_band_height(levels.len());
+    for (first_line, last_line) in crate::get_bands(height as usize, band_height) {
+        let _band_span = trace_span!(TRACE, "band", first_line, last_line);
+        let band = crate::get_src_band(buffers, &layouts, first_line, last_line)
+            .ok_or(ErrorKind::NotEnoughData)?;
+
+        downscale_band(
+            &band,
+            &layouts,
+            first_line,
+            last_line,
+            &level_layouts,
+            levels,
+        );
+    }
+
+    Ok(())
+}
+
+/// Converts from a color space to another one, like [`convert_image`], computing the
+/// levels of the pyramid of the destination image in the same pass
+///
+/// The levels are the ones computed by [`build_pyramid`] for the destination image, which
+/// must have one of the pixel formats supported by it.
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+/// * `levels` - The levels of the pyramid, from the largest to the smallest
+///
+/// # Errors
+///
+/// * [`InvalidOperation`] if the destination pixel format is not supported
+///
+/// * [`NotEnoughData`] if the strides or buffers of a level do not provide enough data
+///
+/// * All the errors reported by [`convert_image`], except that conversions are never routed
+///
+/// [`InvalidOperation`]: ../enum.ErrorKind.html#variant.InvalidOperation
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+/// [`convert_image`]: ../fn.convert_image.html
+/// [`build_pyramid`]: ./fn.build_pyramid.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_image_pyramid(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+    levels: &mut [PyramidLevel],
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_image_pyramid",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format,
+        levels = levels.len()
+    );
+
+    let (converter, last_src_plane, last_dst_plane) =
+        crate::get_converter(width, height, src_format, dst_format)?;
+
+    if !is_pyramid_format(dst_format.pixel_format) {
+        return Err(ErrorKind::InvalidOperation);
+    }
+
+    let level_layouts = get_level_layouts(width, height, dst_format, last_dst_plane, levels)?;
+    if crate::is_empty(width, height) {
+        return Ok(());
+    }
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let src_layouts =
+        crate::get_plane_layouts(src_format, width, height, last_src_plane, src_strides)?;
+    let dst_layouts =
+        crate::get_plane_layouts(dst_format, width, height, last_dst_plane, dst_strides)?;
+
+    let band_height = get_pyramid_band_height(levels.len());
+    crate::convert_in_bands(
+        &src_layouts,
+        src_buffers,
+        dst_format,
+        &dst_layouts,
+        dst_buffers,
+        crate::get_bands(height as usize, band_height),
+        |mut band| {
+            band.convert(converter, width)?;
+            let planes: Vec<&[u8]> = band.dst_buffers.iter().map(|plane| &plane[..]).collect();
+            downscale_band(
+                &planes,
+                &dst_layouts,
+                band.first_line,
+                band.last_line,
+                &level_layouts,
+                levels,
+            );
+            Ok(())
+        },
+    )
+}
@@ -0,0 +1,443 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Streaming conversion of images.
+//!
+//! Encoders and network senders usually consume a frame a few lines at a time, so
+//! holding the whole converted frame in memory is wasted space. [`convert_to_sink`]
+//! converts an image in horizontal bands, and hands each band to a callback as soon as
+//! it is converted, reusing the same small buffer for all of them.
+//!
+//! In the same way, frames received from the network or from a capture device often
+//! arrive in slices. [`convert_from_source`] asks a callback for each band of the source
+//! image right before converting it, so that the slices never need to be assembled into
+//! a contiguous image.
+//!
+//! Bands exchanged with callbacks store the lines of each logical plane one after the
+//! other, without padding, like an image of the band height with `STRIDE_AUTO` strides
+//! and all the planes in a single buffer: with `I420`, a band of 16 lines holds 16 luma
+//! lines, then 8 lines of each chroma plane.
+//!
+//! [`convert_to_sink`]: ./fn.convert_to_sink.html
+//! [`convert_from_source`]: ./fn.convert_from_source.html
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::stream::convert_to_sink;
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn stream() -> Result<(), Box<dyn error::Error>> {
+//!     dcp::initialize();
+//!
+//!     const WIDTH: u32 = 640;
+//!     const HEIGHT: u32 = 480;
+//!
+//!     let src_format = ImageFormat {
+//!         pixel_format: PixelFormat::Bgra,
+//!         color_space: ColorSpace::Lrgb,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let dst_format = ImageFormat {
+//!         pixel_format: PixelFormat::I420,
+//!         color_space: ColorSpace::Bt601,
+//!         num_planes: 3,
+//!     };
+//!
+//!     let src_buffer = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+//!     let mut encoded = Vec::new();
+//!
+//!     convert_to_sink(
+//!         WIDTH,
+//!         HEIGHT,
+//!         &src_format,
+//!         None,
+//!         &[&src_buffer],
+//!         &dst_format,
+//!         Some(16),
+//!         |rows, band| {
+//!             // Lines rows.start..rows.end: 16 luma lines, then 8 lines of each chroma plane
+//!             assert_eq!(band.len(), 3 * (WIDTH as usize) * (rows.len()) / 2);
+//!             encoded.extend_from_slice(band);
+//!             true
+//!         },
+//!     )?;
+//!
+//!     Ok(())
+//! }
+//! ```
+use crate::pixel_format;
+use crate::trace_span;
+use crate::{ErrorKind, ImageFormat};
+
+use std::ops::Range;
+
+/// Height of the bands exchanged with callbacks, when not chosen by the caller
+const STREAM_BAND_HEIGHT: usize = 64;
+
+/// Layout of the bands exchanged with callbacks
+struct PackedBand {
+    // Line size and vertical subsampling of each logical plane
+    planes: Vec<(usize, u32)>,
+}
+
+impl PackedBand {
+    fn new(width: u32, format: &ImageFormat) -> PackedBand {
+        PackedBand {
+            planes: pixel_format::get_logical_planes(format.pixel_format)
+                .iter()
+                .map(|&(depth, horizontal_shift, vertical_shift)| {
+                    (
+                        depth * ((width >> horizontal_shift) as usize),
+                        vertical_shift,
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn strides(&self) -> Vec<usize> {
+        self.planes
+            .iter()
+            .map(|&(line_size, _)| line_size)
+            .collect()
+    }
+
+    fn max_shift(&self) -> u32 {
+        self.planes
+            .iter()
+            .map(|&(_, vertical_shift)| vertical_shift)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn plane_sizes<'a>(&'a self, lines: usize) -> impl Iterator<Item = usize> + 'a {
+        self.planes
+            .iter()
+            .map(move |&(line_size, vertical_shift)| (lines >> vertical_shift) * line_size)
+    }
+
+    fn size(&self, lines: usize) -> usize {
+        self.plane_sizes(lines).sum()
+    }
+
+    fn split<'a>(&self, lines: usize, mut buffer: &'a [u8]) -> Vec<&'a [u8]> {
+        let mut band = Vec::with_capacity(self.planes.len());
+        for size in self.plane_sizes(lines) {
+            let (plane, tail) = buffer.split_at(size);
+            band.push(plane);
+            buffer = tail;
+        }
+
+        band
+    }
+
+    fn split_mut<'a>(&self, lines: usize, mut buffer: &'a mut [u8]) -> Vec<&'a mut [u8]> {
+        let mut band = Vec::with_capacity(self.planes.len());
+        for size in self.plane_sizes(lines) {
+            let (plane, tail) = buffer.split_at_mut(size);
+            band.push(plane);
+            buffer = tail;
+        }
+
+        band
+    }
+}
+
+/// Returns the height of the bands, which must not split chroma rows
+fn get_band_height(band_height: Option<u32>, max_shift: u32) -> Result<usize, ErrorKind> {
+    match band_height {
+        Some(band_height) => {
+            let band_height = band_height as usize;
+            if band_height == 0 || !band_height.is_multiple_of(1 << max_shift) {
+                return Err(ErrorKind::InvalidValue);
+            }
+
+            Ok(band_height)
+        }
+        None => Ok(STREAM_BAND_HEIGHT),
+    }
+}
+
+/// Converts from a color space to another one, like [`convert_image`], handing the
+/// destination image to a callback one band of lines at a time
+///
+/// `sink` is called with the range of image lines of each band, from top to bottom, and
+/// with a buffer holding the converted band, laid out as described in the
+/// [`module documentation`]. The buffer is only valid for the duration of the call, and
+/// is reused by the next band. `sink` returns `false` to stop the conversion.
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_format` - Destination image format. The layout of the bands does not depend on
+///   its number of planes
+/// * `band_height` - If not `None`, the height of the bands in pixels. The last band can
+///   be shorter.
+/// * `sink` - Callback receiving the range of lines and the content of each band
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if `band_height` is zero, or is not a multiple of the chroma
+///   subsampling of the source and destination pixel formats
+///
+/// * [`Cancelled`] if `sink` returned `false`
+///
+/// * All the errors reported by [`convert_image`] for the source image and the formats,
+///   except that conversions are never routed
+///
+/// [`module documentation`]: ./index.html
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`Cancelled`]: ../enum.ErrorKind.html#variant.Cancelled
+/// [`convert_image`]: ../fn.convert_image.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_to_sink<F>(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    band_height: Option<u32>,
+    mut sink: F,
+) -> Result<(), ErrorKind>
+where
+    F: FnMut(Range<u32>, &[u8]) -> bool,
+{
+    let _span = trace_span!(
+        DEBUG,
+        "convert_to_sink",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    let (converter, last_src_plane, _) =
+        crate::get_converter(width, height, src_format, dst_format)?;
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let src_layouts =
+        crate::get_plane_layouts(src_format, width, height, last_src_plane, src_strides)?;
+    let dst_band_layout = PackedBand::new(width, dst_format);
+
+    // Lines sharing chroma samples are always in the same band
+    let max_shift = src_layouts
+        .iter()
+        .map(|layout| layout.vertical_shift)
+        .max()
+        .unwrap_or(0)
+        .max(dst_band_layout.max_shift());
+    let band_height = get_band_height(band_height, max_shift)?;
+
+    if crate::is_empty(width, height) {
+        return Ok(());
+    }
+
+    // Bands are converted to the scratch buffer, then handed to the sink
+    let band_src_strides = crate::get_band_strides(&src_layouts);
+    let band_dst_strides = dst_band_layout.strides();
+    let band_dst_format = crate::get_band_format(dst_format);
+    let mut scratch = vec![0u8; dst_band_layout.size(band_height)];
+    for (first_line, last_line) in crate::get_bands(height as usize, band_height) {
+        let _band_span = trace_span!(TRACE, "band", first_line, last_line);
+        let lines = last_line - first_line;
+        let buffer = &mut scratch[..dst_band_layout.size(lines)];
+        crate::Band {
+            first_line,
+            last_line,
+            src_strides: &band_src_strides,
+            src_buffers: crate::get_src_band(src_buffers, &src_layouts, first_line, last_line)
+                .ok_or(ErrorKind::NotEnoughData)?,
+            dst_format: &band_dst_format,
+            dst_strides: &band_dst_strides,
+            dst_buffers: dst_band_layout.split_mut(lines, buffer),
+        }
+        .convert(converter, width)?;
+
+        if !sink(first_line as u32..last_line as u32, buffer) {
+            return Err(ErrorKind::Cancelled);
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts from a color space to another one, like [`convert_image`], asking a callback
+/// for the source image one band of lines at a time
+///
+/// `source` is called with the range of image lines of each band, from top to bottom, and
+/// with a buffer to fill with the content of the band, laid out as described in the
+/// [`module documentation`]. The band is converted right after `source` returns, and the
+/// buffer is reused by the next band. `source` returns `false` to stop the conversion,
+/// e.g. when the rest of the frame is lost.
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format. The layout of the bands does not depend on
+///   its number of planes
+/// * `band_height` - If not `None`, the height of the bands in pixels. The last band can
+///   be shorter.
+/// * `source` - Callback filling each band with the range of lines it is given
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if `band_height` is zero, or is not a multiple of the chroma
+///   subsampling of the source and destination pixel formats
+///
+/// * [`Cancelled`] if `source` returned `false`. The bands converted before are
+///   written to the destination image
+///
+/// * All the errors reported by [`convert_image`] for the destination image and the
+///   formats, except that conversions are never routed
+///
+/// # Examples
+/// ```
+/// use dcv_color_primitives as dcp;
+/// use dcp::stream::convert_from_source;
+/// use dcp::{ColorSpace, ImageFormat, PixelFormat};
+/// use std::error;
+///
+/// fn receive() -> Result<(), Box<dyn error::Error>> {
+///     dcp::initialize();
+///
+///     const WIDTH: u32 = 640;
+///     const HEIGHT: u32 = 480;
+///     const LINE_SIZE: usize = 4 * (WIDTH as usize);
+///
+///     let src_format = ImageFormat {
+///         pixel_format: PixelFormat::Bgra,
+///         color_space: ColorSpace::Lrgb,
+///         num_planes: 1,
+///     };
+///
+///     let dst_format = ImageFormat {
+///         pixel_format: PixelFormat::Nv12,
+///         color_space: ColorSpace::Bt601,
+///         num_planes: 1,
+///     };
+///
+///     // Slices of 32 lines, as received from the network
+///     let slices = vec![vec![0u8; 32 * LINE_SIZE]; (HEIGHT as usize) / 32];
+///     let mut dst_buffer = vec![0u8; 3 * (WIDTH as usize) * (HEIGHT as usize) / 2];
+///
+///     convert_from_source(
+///         WIDTH,
+///         HEIGHT,
+///         &src_format,
+///         Some(32),
+///         |rows, band| {
+///             band.copy_from_slice(&slices[(rows.start as usize) / 32]);
+///             true
+///         },
+///         &dst_format,
+///         None,
+///         &mut [&mut dst_buffer],
+///     )?;
+///
+///     Ok(())
+/// }
+/// ```
+///
+/// [`module documentation`]: ./index.html
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`Cancelled`]: ../enum.ErrorKind.html#variant.Cancelled
+/// [`convert_image`]: ../fn.convert_image.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_from_source<F>(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    band_height: Option<u32>,
+    mut source: F,
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind>
+where
+    F: FnMut(Range<u32>, &mut [u8]) -> bool,
+{
+    let _span = trace_span!(
+        DEBUG,
+        "convert_from_source",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    let (converter, _, last_dst_plane) =
+        crate::get_converter(width, height, src_format, dst_format)?;
+
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_layouts =
+        crate::get_plane_layouts(dst_format, width, height, last_dst_plane, dst_strides)?;
+    let src_band_layout = PackedBand::new(width, src_format);
+
+    // Lines sharing chroma samples are always in the same band
+    let max_shift = dst_layouts
+        .iter()
+        .map(|layout| layout.vertical_shift)
+        .max()
+        .unwrap_or(0)
+        .max(src_band_layout.max_shift());
+    let band_height = get_band_height(band_height, max_shift)?;
+
+    if crate::is_empty(width, height) {
+        return Ok(());
+    }
+
+    // Bands are filled by the source in the scratch buffer, then converted
+    let band_src_strides = src_band_layout.strides();
+    let band_dst_strides = crate::get_band_strides(&dst_layouts);
+    let band_dst_format = crate::get_band_format(dst_format);
+    let mut scratch = vec![0u8; src_band_layout.size(band_height)];
+    for (first_line, last_line) in crate::get_bands(height as usize, band_height) {
+        let _band_span = trace_span!(TRACE, "band", first_line, last_line);
+        let dst_band = crate::get_dst_band(dst_buffers, &dst_layouts, first_line, last_line)
+            .ok_or(ErrorKind::NotEnoughData)?;
+
+        let lines = last_line - first_line;
+        let buffer = &mut scratch[..src_band_layout.size(lines)];
+        if !source(first_line as u32..last_line as u32, buffer) {
+            return Err(ErrorKind::Cancelled);
+        }
+
+        crate::Band {
+            first_line,
+            last_line,
+            src_strides: &band_src_strides,
+            src_buffers: src_band_layout.split(lines, buffer),
+            dst_format: &band_dst_format,
+            dst_strides: &band_dst_strides,
+            dst_buffers: dst_band,
+        }
+        .convert(converter, width)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,545 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Conversion of tiled images.
+//!
+//! GPU decoders usually write their frames in a tiled memory layout. Images in this
+//! layout can be converted directly, without detiling them first into a linear image:
+//! the source image is detiled a few lines at a time while the conversion progresses.
+//!
+//! Images can also be moved between the linear and the tiled layouts without converting
+//! them, e.g. to feed hardware encoders that expect tiled input, with [`tile_image`] and
+//! [`detile_image`].
+//!
+//! [`tile_image`]: ./fn.tile_image.html
+//! [`detile_image`]: ./fn.detile_image.html
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::tiling::{convert_tiled_image, Tiling};
+//! use dcp::{ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn convert() -> Result<(), Box<dyn error::Error>> {
+//!     dcp::initialize();
+//!
+//!     const WIDTH: u32 = 1920;
+//!     const HEIGHT: u32 = 1080;
+//!
+//!     let src_format = ImageFormat {
+//!         pixel_format: PixelFormat::Nv12,
+//!         color_space: ColorSpace::Bt709,
+//!         num_planes: 1,
+//!     };
+//!
+//!     let dst_format = ImageFormat {
+//!         pixel_format: PixelFormat::Bgra,
+//!         color_space: ColorSpace::Lrgb,
+//!         num_planes: 1,
+//!     };
+//!
+//!     // Tiled planes are padded to a whole number of tiles: 1088 luma lines
+//!     // and 544 chroma lines, each of them 1920 bytes long
+//!     let src_buffer = vec![0u8; 1920 * (1088 + 544)];
+//!     let mut dst_buffer = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+//!
+//!     convert_tiled_image(
+//!         WIDTH,
+//!         HEIGHT,
+//!         &src_format,
+//!         Tiling::IntelYTiled,
+//!         None,
+//!         &[&src_buffer],
+//!         &dst_format,
+//!         None,
+//!         &mut [&mut dst_buffer],
+//!     )?;
+//!
+//!     Ok(())
+//! }
+//! ```
+use crate::dispatcher;
+use crate::pixel_format::{self, STRIDE_AUTO};
+use crate::trace_span;
+use crate::{ErrorKind, ImageFormat};
+
+/// An enumeration of tiled memory layouts.
+#[derive(Copy, Clone)]
+pub enum Tiling {
+    /// Intel Y-major tiling.
+    ///
+    /// Planes are divided in 4096 bytes tiles, 128 bytes wide and 32 lines tall.
+    /// Each tile is made of eight 16 bytes wide columns, stored one after the other.
+    IntelYTiled,
+    /// NVIDIA block linear layout.
+    ///
+    /// Planes are divided in blocks, 64 bytes wide and `8 << log2_block_height` lines
+    /// tall. Each block is made of 512 bytes groups of 64 bytes by 8 lines (GOBs),
+    /// stored from top to bottom. `log2_block_height` is in the range [0, 5]
+    NvidiaBlockLinear {
+        /// Base 2 logarithm of the number of GOBs in each block
+        log2_block_height: u32,
+    },
+}
+
+/// Lines detiled at once
+const TILED_BAND_HEIGHT: usize = 64;
+
+/// Width of the contiguous runs of bytes in a tiled line
+const RUN_SIZE: usize = 16;
+
+impl Tiling {
+    fn tile_width(self) -> usize {
+        match self {
+            Tiling::IntelYTiled => 128,
+            Tiling::NvidiaBlockLinear { .. } => 64,
+        }
+    }
+
+    fn tile_height(self) -> usize {
+        match self {
+            Tiling::IntelYTiled => 32,
+            Tiling::NvidiaBlockLinear { log2_block_height } => 8 << log2_block_height,
+        }
+    }
+
+    /// Offset of the byte at column `x` of line `y`, in a plane with the given pitch
+    fn get_offset(self, pitch: usize, x: usize, y: usize) -> usize {
+        match self {
+            Tiling::IntelYTiled => {
+                let tile = (y / 32) * (pitch / 128) + x / 128;
+                (tile << 12) + (((x % 128) / 16) << 9) + ((y % 32) << 4) + (x % 16)
+            }
+            Tiling::NvidiaBlockLinear { log2_block_height } => {
+                let gob_y = y / 8;
+                let block = (gob_y >> log2_block_height) * (pitch / 64) + x / 64;
+                let gob = gob_y & ((1 << log2_block_height) - 1);
+                let gob_offset = (((x % 64) / 32) << 8)
+                    + (((y % 8) / 2) << 6)
+                    + (((x % 32) / 16) << 5)
+                    + ((y % 2) << 4)
+                    + (x % 16);
+
+                (block << (9 + log2_block_height)) + (gob << 9) + gob_offset
+            }
+        }
+    }
+}
+
+/// Location of a logical plane inside the tiled image buffers
+struct TiledPlane {
+    buffer: usize,
+    offset: usize,
+    pitch: usize,
+    line_size: usize,
+    vertical_shift: u32,
+}
+
+fn get_tiled_planes(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    tiling: Tiling,
+    strides: &[usize],
+    buffer_sizes: &[usize],
+) -> Result<Vec<TiledPlane>, ErrorKind> {
+    if let Tiling::NvidiaBlockLinear { log2_block_height } = tiling {
+        if log2_block_height > 5 {
+            return Err(ErrorKind::InvalidValue);
+        }
+    }
+
+    let last_plane = format.num_planes.wrapping_sub(1) as usize;
+    if last_plane >= strides.len() || last_plane >= buffer_sizes.len() {
+        return Err(ErrorKind::NotEnoughData);
+    }
+
+    let tile_width = tiling.tile_width();
+    let tile_height = tiling.tile_height();
+
+    // Planes sharing a buffer start at the first tile after the previous plane
+    let planes = pixel_format::get_logical_planes(format.pixel_format);
+    let mut tiled_planes = Vec::with_capacity(planes.len());
+    let mut offset = 0;
+    for (i, &(depth, horizontal_shift, vertical_shift)) in planes.iter().enumerate() {
+        let buffer = i.min(last_plane);
+        if buffer == i {
+            offset = 0;
+        }
+
+        let line_size = depth * ((width >> horizontal_shift) as usize);
+        let pitch = match strides[buffer] {
+            STRIDE_AUTO => line_size.div_ceil(tile_width) * tile_width,
+            pitch => pitch,
+        };
+
+        if pitch < line_size || pitch % tile_width != 0 {
+            return Err(ErrorKind::InvalidValue);
+        }
+
+        let tile_rows = ((height >> vertical_shift) as usize).div_ceil(tile_height);
+        let end = pitch
+            .checked_mul(tile_rows * tile_height)
+            .and_then(|size| size.checked_add(offset))
+            .ok_or(ErrorKind::NotEnoughData)?;
+        if end > buffer_sizes[buffer] {
+            return Err(ErrorKind::NotEnoughData);
+        }
+
+        tiled_planes.push(TiledPlane {
+            buffer,
+            offset,
+            pitch,
+            line_size,
+            vertical_shift,
+        });
+
+        offset = end;
+    }
+
+    Ok(tiled_planes)
+}
+
+/// Copies the lines from `first_line` to `last_line` (excluded) of a tiled plane
+/// into a linear buffer
+fn detile(
+    tiling: Tiling,
+    plane: &TiledPlane,
+    src: &[u8],
+    first_line: usize,
+    last_line: usize,
+    dst: &mut Vec<u8>,
+) {
+    dst.clear();
+    for y in first_line..last_line {
+        for x in (0..plane.line_size).step_by(RUN_SIZE) {
+            let start = plane.offset + tiling.get_offset(plane.pitch, x, y);
+            let count = RUN_SIZE.min(plane.line_size - x);
+            dst.extend_from_slice(&src[start..start + count]);
+        }
+    }
+}
+
+/// Converts a tiled image from a color space to another one, like [`convert_image`]
+///
+/// The source image planes are tiled, and padded to a whole number of tiles both
+/// horizontally and vertically. When the source image is stored in a single buffer,
+/// each plane starts right after the padding of the previous one.
+///
+/// # Arguments
+/// * `width` - Width of the image to convert in pixels
+/// * `height` - Height of the image to convert in pixels
+/// * `src_format` - Source image format
+/// * `tiling` - Memory layout of the source image
+/// * `src_strides` - An array of pitches in bytes in each source image planes, i.e. the
+///   width of a row of tiles. Pitches must be multiple of the tile width.
+///   If `None` or [`STRIDE_AUTO`], the line size is rounded up to the tile width
+/// * `src_buffers` - An array of image buffers in each source color plane
+/// * `dst_format` - Destination image format
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination color plane
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if one or more source pitches are not multiple of the tile width,
+///   or are smaller than the plane line size
+///
+/// * [`InvalidValue`] if `tiling` has an invalid block height
+///
+/// * All the errors reported by [`convert_image`]
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`STRIDE_AUTO`]: ../constant.STRIDE_AUTO.html
+/// [`convert_image`]: ../fn.convert_image.html
+#[allow(clippy::too_many_arguments)]
+pub fn convert_tiled_image(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    tiling: Tiling,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_format: &ImageFormat,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(
+        DEBUG,
+        "convert_tiled_image",
+        width,
+        height,
+        src = ?src_format.pixel_format,
+        dst = ?dst_format.pixel_format
+    );
+
+    let (converter, _, last_dst_plane) =
+        crate::get_converter(width, height, src_format, dst_format)?;
+
+    let src_strides = src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let dst_strides = dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES);
+    let src_sizes: Vec<usize> = src_buffers.iter().map(|buffer| buffer.len()).collect();
+    let src_planes = get_tiled_planes(width, height, src_format, tiling, src_strides, &src_sizes)?;
+    if crate::is_empty(width, height) {
+        return Ok(());
+    }
+
+    let dst_layouts =
+        crate::get_plane_layouts(dst_format, width, height, last_dst_plane, dst_strides)?;
+
+    // Source bands are detiled to one packed buffer for each logical plane
+    let band_src_strides: Vec<usize> = src_planes.iter().map(|plane| plane.line_size).collect();
+    let band_dst_strides = crate::get_band_strides(&dst_layouts);
+    let band_dst_format = crate::get_band_format(dst_format);
+
+    let mut scratch: Vec<Vec<u8>> = src_planes.iter().map(|_| Vec::new()).collect();
+    for (first_line, last_line) in crate::get_bands(height as usize, TILED_BAND_HEIGHT) {
+        let _band_span = trace_span!(TRACE, "band", first_line, last_line);
+        for (plane, buffer) in src_planes.iter().zip(scratch.iter_mut()) {
+            detile(
+                tiling,
+                plane,
+                src_buffers[plane.buffer],
+                first_line >> plane.vertical_shift,
+                last_line >> plane.vertical_shift,
+                buffer,
+            );
+        }
+
+        crate::Band {
+            first_line,
+            last_line,
+            src_strides: &band_src_strides,
+            src_buffers: scratch.iter().map(|buffer| &buffer[..]).collect(),
+            dst_format: &band_dst_format,
+            dst_strides: &band_dst_strides,
+            dst_buffers: crate::get_dst_band(dst_buffers, &dst_layouts, first_line, last_line)
+                .ok_or(ErrorKind::NotEnoughData)?,
+        }
+        .convert(converter, width)?;
+    }
+
+    Ok(())
+}
+
+/// Location of a logical plane inside the linear image buffers
+fn get_linear_planes(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    strides: &[usize],
+    buffer_sizes: &[usize],
+) -> Result<Vec<crate::PlaneLayout>, ErrorKind> {
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if last_plane as usize >= buffer_sizes.len() {
+        return Err(ErrorKind::NotEnoughData);
+    }
+
+    let layouts = crate::get_plane_layouts(format, width, height, last_plane, strides)?;
+    for layout in layouts.iter() {
+        let line_size = layout.depth * ((width >> layout.horizontal_shift) as usize);
+        if layout.stride < line_size {
+            return Err(ErrorKind::InvalidValue);
+        }
+
+        let end = layout
+            .stride
+            .checked_mul((height >> layout.vertical_shift) as usize)
+            .and_then(|size| size.checked_add(layout.offset))
+            .ok_or(ErrorKind::NotEnoughData)?;
+        if end > buffer_sizes[layout.buffer] {
+            return Err(ErrorKind::NotEnoughData);
+        }
+    }
+
+    Ok(layouts)
+}
+
+/// Validates an image format for tiling and detiling, which do not convert samples
+fn check_format(width: u32, height: u32, format: &ImageFormat) -> Result<(), ErrorKind> {
+    let pixel_format = format.pixel_format as u32;
+    if dispatcher::get_pixel_format_mode(pixel_format)
+        ^ dispatcher::get_color_space_mode(format.color_space as u32)
+    {
+        return Err(ErrorKind::InvalidValue);
+    }
+
+    let last_plane = format.num_planes.wrapping_sub(1);
+    if pixel_format::is_compatible(pixel_format, width, height, last_plane) {
+        Ok(())
+    } else {
+        Err(ErrorKind::InvalidValue)
+    }
+}
+
+/// Copies a linear image into the tiled memory layout
+///
+/// The destination image planes are padded to a whole number of tiles both horizontally
+/// and vertically, and laid out as described in [`convert_tiled_image`]. Padding bytes are
+/// left untouched.
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `format` - Image format of both images
+/// * `src_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each source image planes
+/// * `src_buffers` - An array of image buffers in each source image plane
+/// * `tiling` - Memory layout of the destination image
+/// * `dst_strides` - An array of pitches in bytes in each destination image planes, as
+///   described in [`convert_tiled_image`]
+/// * `dst_buffers` - An array of image buffers in each destination image plane
+///
+/// # Errors
+///
+/// * [`InvalidValue`] if the pixel format is not compatible with the color space
+///
+/// * [`InvalidValue`] if `width` or `height` violate the [`size constraints`] that might by
+///   imposed by the image pixel format
+///
+/// * [`InvalidValue`] if the image format has a number of planes which is not compatible
+///   with its pixel format
+///
+/// * [`InvalidValue`] if one or more source strides are smaller than the plane line size
+///
+/// * [`InvalidValue`] if one or more destination pitches are not multiple of the tile width,
+///   or are smaller than the plane line size
+///
+/// * [`InvalidValue`] if `tiling` has an invalid block height
+///
+/// * [`NotEnoughData`] if the strides arrays are not `None` and their length is less than
+///   the image format number of planes
+///
+/// * [`NotEnoughData`] if one or more buffers does not provide enough data
+///
+/// [`InvalidValue`]: ../enum.ErrorKind.html#variant.InvalidValue
+/// [`NotEnoughData`]: ../enum.ErrorKind.html#variant.NotEnoughData
+/// [`size constraints`]: ../struct.ImageFormat.html#note
+/// [`convert_tiled_image`]: ./fn.convert_tiled_image.html
+#[allow(clippy::too_many_arguments)]
+pub fn tile_image(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    tiling: Tiling,
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(DEBUG, "tile_image", width, height, format = ?format.pixel_format);
+
+    check_format(width, height, format)?;
+    let src_sizes: Vec<usize> = src_buffers.iter().map(|buffer| buffer.len()).collect();
+    let dst_sizes: Vec<usize> = dst_buffers.iter().map(|buffer| buffer.len()).collect();
+    let src_planes = get_linear_planes(
+        width,
+        height,
+        format,
+        src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+        &src_sizes,
+    )?;
+    let dst_planes = get_tiled_planes(
+        width,
+        height,
+        format,
+        tiling,
+        dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+        &dst_sizes,
+    )?;
+
+    for (src, dst) in src_planes.iter().zip(dst_planes.iter()) {
+        let src_buffer = src_buffers[src.buffer];
+        let dst_buffer = &mut dst_buffers[dst.buffer];
+        for y in 0..((height >> dst.vertical_shift) as usize) {
+            let line = src.offset + y * src.stride;
+            for x in (0..dst.line_size).step_by(RUN_SIZE) {
+                let start = dst.offset + tiling.get_offset(dst.pitch, x, y);
+                let count = RUN_SIZE.min(dst.line_size - x);
+                dst_buffer[start..start + count]
+                    .copy_from_slice(&src_buffer[line + x..line + x + count]);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies a tiled image into the linear memory layout
+///
+/// # Arguments
+/// * `width` - Width of the image in pixels
+/// * `height` - Height of the image in pixels
+/// * `format` - Image format of both images
+/// * `tiling` - Memory layout of the source image
+/// * `src_strides` - An array of pitches in bytes in each source image planes, as
+///   described in [`convert_tiled_image`]
+/// * `src_buffers` - An array of image buffers in each source image plane
+/// * `dst_strides` - An array of distances in bytes between starts of consecutive lines
+///   in each destination image planes
+/// * `dst_buffers` - An array of image buffers in each destination image plane
+///
+/// # Errors
+///
+/// Same as [`tile_image`], with the roles of source and destination swapped
+///
+/// [`convert_tiled_image`]: ./fn.convert_tiled_image.html
+/// [`tile_image`]: ./fn.tile_image.html
+#[allow(clippy::too_many_arguments)]
+pub fn detile_image(
+    width: u32,
+    height: u32,
+    format: &ImageFormat,
+    tiling: Tiling,
+    src_strides: Option<&[usize]>,
+    src_buffers: &[&[u8]],
+    dst_strides: Option<&[usize]>,
+    dst_buffers: &mut [&mut [u8]],
+) -> Result<(), ErrorKind> {
+    let _span = trace_span!(DEBUG, "detile_image", width, height, format = ?format.pixel_format);
+
+    check_format(width, height, format)?;
+    let src_sizes: Vec<usize> = src_buffers.iter().map(|buffer| buffer.len()).collect();
+    let dst_sizes: Vec<usize> = dst_buffers.iter().map(|buffer| buffer.len()).collect();
+    let src_planes = get_tiled_planes(
+        width,
+        height,
+        format,
+        tiling,
+        src_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+        &src_sizes,
+    )?;
+    let dst_planes = get_linear_planes(
+        width,
+        height,
+        format,
+        dst_strides.unwrap_or(&pixel_format::DEFAULT_STRIDES),
+        &dst_sizes,
+    )?;
+
+    let mut line = Vec::new();
+    for (src, dst) in src_planes.iter().zip(dst_planes.iter()) {
+        let dst_buffer = &mut dst_buffers[dst.buffer];
+        for y in 0..((height >> src.vertical_shift) as usize) {
+            detile(tiling, src, src_buffers[src.buffer], y, y + 1, &mut line);
+            let start = dst.offset + y * dst.stride;
+            dst_buffer[start..start + src.line_size].copy_from_slice(&line);
+        }
+    }
+
+    Ok(())
+}
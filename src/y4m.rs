@@ -0,0 +1,413 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! YUV4MPEG2 (y4m) container.
+//!
+//! Reads and writes the stream header and the frames of planar 8-bit y4m files, the
+//! format produced by `ffmpeg -f yuv4mpegpipe`. Frames are stored in one buffer for
+//! each plane, so they can be handed to [`convert_image`] as they are.
+//!
+//! Only the `420jpeg`, `420paldv`, `420mpeg2`, `420`, `422` and `444` chroma formats
+//! are supported. The color space is not part of the container and has to be
+//! chosen by the caller.
+//!
+//! This module requires the `y4m` feature.
+//!
+//! # Examples
+//! ```
+//! use dcv_color_primitives as dcp;
+//! use dcp::y4m::{Header, Reader, Writer};
+//! use dcp::{convert_image, ColorSpace, ImageFormat, PixelFormat};
+//! use std::error;
+//!
+//! fn round_trip() -> Result<(), Box<dyn error::Error>> {
+//!     dcp::initialize();
+//!
+//!     let header = Header::new(64, 48, PixelFormat::I420);
+//!     let [y_size, u_size, v_size] = header.buffers_size();
+//!     let (y, u, v) = (vec![16u8; y_size], vec![128u8; u_size], vec![128u8; v_size]);
+//!
+//!     let mut stream = Vec::new();
+//!     let mut writer = Writer::new(&mut stream, header)?;
+//!     writer.write_frame(&[&y, &u, &v])?;
+//!
+//!     let mut reader = Reader::new(&stream[..])?;
+//!     let header = reader.header().clone();
+//!     let (mut y, mut u, mut v) = (vec![0u8; y_size], vec![0u8; u_size], vec![0u8; v_size]);
+//!     while reader.read_frame(&mut [&mut y, &mut u, &mut v])? {
+//!         let dst_format = ImageFormat {
+//!             pixel_format: PixelFormat::Bgra,
+//!             color_space: ColorSpace::Lrgb,
+//!             num_planes: 1,
+//!         };
+//!
+//!         let mut dst = vec![0u8; 4 * (header.width as usize) * (header.height as usize)];
+//!         convert_image(
+//!             header.width,
+//!             header.height,
+//!             &header.image_format(ColorSpace::Bt601),
+//!             None,
+//!             &[&y, &u, &v],
+//!             &dst_format,
+//!             None,
+//!             &mut [&mut dst],
+//!         )?;
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`convert_image`]: ../fn.convert_image.html
+use crate::color_space::ColorSpace;
+use crate::pixel_format::{self, PixelFormat};
+use crate::ImageFormat;
+use std::io::{self, Read, Write};
+
+const STREAM_MAGIC: &str = "YUV4MPEG2";
+const FRAME_MAGIC: &[u8] = b"FRAME";
+
+// Header lines are short, a larger one is most likely not a y4m stream
+const MAX_LINE_SIZE: usize = 4096;
+
+/// Stream header of a y4m file
+#[derive(Clone, Debug)]
+pub struct Header {
+    /// Width of the frames in pixels
+    pub width: u32,
+    /// Height of the frames in pixels
+    pub height: u32,
+    /// Pixel format of the frames: `I420`, `I422` or `I444`
+    pub pixel_format: PixelFormat,
+    /// Frame rate, as numerator and denominator
+    pub frame_rate: (u32, u32),
+    /// Pixel aspect ratio, as numerator and denominator. `(0, 0)` when unknown
+    pub pixel_aspect: (u32, u32),
+    /// Interlacing mode: `p` (progressive), `t` (top field first),
+    /// `b` (bottom field first), `m` (mixed) or `?` (unknown)
+    pub interlacing: char,
+}
+
+impl Header {
+    /// Creates a header for progressive frames at 25 frames per second
+    pub fn new(width: u32, height: u32, pixel_format: PixelFormat) -> Header {
+        Header {
+            width,
+            height,
+            pixel_format,
+            frame_rate: (25, 1),
+            pixel_aspect: (1, 1),
+            interlacing: 'p',
+        }
+    }
+
+    /// Returns the image format of the frames, with one buffer for each plane
+    pub fn image_format(&self, color_space: ColorSpace) -> ImageFormat {
+        ImageFormat {
+            pixel_format: self.pixel_format,
+            color_space,
+            num_planes: 3,
+        }
+    }
+
+    /// Returns the size in bytes of each plane of a frame
+    pub fn buffers_size(&self) -> [usize; 3] {
+        let mut buffers_size = [0; 3];
+        for (size, &(depth, horizontal_shift, vertical_shift)) in buffers_size
+            .iter_mut()
+            .zip(pixel_format::get_logical_planes(self.pixel_format))
+        {
+            *size = depth
+                * ((self.width >> horizontal_shift) as usize)
+                * ((self.height >> vertical_shift) as usize);
+        }
+
+        buffers_size
+    }
+
+    fn validate(&self) -> io::Result<()> {
+        if get_chroma_tag(self.pixel_format).is_none() {
+            return Err(invalid_data("unsupported pixel format"));
+        }
+
+        if self.width == 0
+            || self.height == 0
+            || !pixel_format::is_compatible(self.pixel_format as u32, self.width, self.height, 2)
+        {
+            return Err(invalid_data("invalid frame size"));
+        }
+
+        match self.interlacing {
+            'p' | 't' | 'b' | 'm' | '?' => Ok(()),
+            _ => Err(invalid_data("invalid interlacing mode")),
+        }
+    }
+
+    fn parse(line: &[u8]) -> io::Result<Header> {
+        let line = std::str::from_utf8(line).map_err(|_| invalid_data("invalid header"))?;
+        let mut tokens = line.split(' ').filter(|token| !token.is_empty());
+        if tokens.next() != Some(STREAM_MAGIC) {
+            return Err(invalid_data("not a y4m stream"));
+        }
+
+        let mut width = None;
+        let mut height = None;
+        let mut header = Header::new(0, 0, PixelFormat::I420);
+        header.pixel_aspect = (0, 0);
+        header.interlacing = '?';
+        for token in tokens {
+            let mut chars = token.chars();
+            let tag = chars.next();
+            let value = chars.as_str();
+            match tag {
+                Some('W') => width = Some(parse_number(value)?),
+                Some('H') => height = Some(parse_number(value)?),
+                Some('F') => header.frame_rate = parse_ratio(value)?,
+                Some('A') => header.pixel_aspect = parse_ratio(value)?,
+                Some('I') => {
+                    let mut chars = value.chars();
+                    header.interlacing = match (chars.next(), chars.next()) {
+                        (Some(mode), None) => mode,
+                        _ => return Err(invalid_data("invalid interlacing mode")),
+                    };
+                }
+                Some('C') => {
+                    header.pixel_format = match value {
+                        "420jpeg" | "420paldv" | "420mpeg2" | "420" => PixelFormat::I420,
+                        "422" => PixelFormat::I422,
+                        "444" => PixelFormat::I444,
+                        _ => return Err(invalid_data("unsupported chroma format")),
+                    };
+                }
+                // Extensions (X) do not affect the frame layout
+                _ => {}
+            }
+        }
+
+        header.width = width.ok_or_else(|| invalid_data("missing width"))?;
+        header.height = height.ok_or_else(|| invalid_data("missing height"))?;
+        header.validate()?;
+        Ok(header)
+    }
+}
+
+fn get_chroma_tag(pixel_format: PixelFormat) -> Option<&'static str> {
+    match pixel_format {
+        PixelFormat::I420 => Some("420jpeg"),
+        PixelFormat::I422 => Some("422"),
+        PixelFormat::I444 => Some("444"),
+        _ => None,
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn parse_number(value: &str) -> io::Result<u32> {
+    value.parse().map_err(|_| invalid_data("invalid number"))
+}
+
+fn parse_ratio(value: &str) -> io::Result<(u32, u32)> {
+    let mut parts = value.splitn(2, ':');
+    match (parts.next(), parts.next()) {
+        (Some(numerator), Some(denominator)) => {
+            Ok((parse_number(numerator)?, parse_number(denominator)?))
+        }
+        _ => Err(invalid_data("invalid ratio")),
+    }
+}
+
+/// Reads a line without the terminating newline.
+///
+/// Returns `None` if the stream ended before the first byte.
+fn read_line<R: Read>(inner: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut line = Vec::new();
+    let mut byte = [0u8];
+    loop {
+        if inner.read(&mut byte)? == 0 {
+            return if line.is_empty() {
+                Ok(None)
+            } else {
+                Err(io::ErrorKind::UnexpectedEof.into())
+            };
+        }
+
+        if byte[0] == b'\n' {
+            return Ok(Some(line));
+        }
+
+        if line.len() == MAX_LINE_SIZE {
+            return Err(invalid_data("header line too long"));
+        }
+
+        line.push(byte[0]);
+    }
+}
+
+fn check_buffers(header: &Header, buffers_size: &[usize]) -> io::Result<()> {
+    let expected = header.buffers_size();
+    if buffers_size.len() < expected.len()
+        || buffers_size
+            .iter()
+            .zip(expected.iter())
+            .any(|(size, expected)| size < expected)
+    {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not enough buffer space for a frame",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Reads frames from a y4m stream
+///
+/// Headers are read one byte at a time: wrap unbuffered readers
+/// (e.g. a `File`) in a `BufReader`.
+pub struct Reader<R: Read> {
+    inner: R,
+    header: Header,
+}
+
+impl<R: Read> Reader<R> {
+    /// Reads the stream header
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidData` if the stream is not a y4m stream, its chroma format is not
+    ///   supported, or its frame size violates the size constraints of the pixel format
+    ///
+    /// * `UnexpectedEof` if the stream ends before the header
+    pub fn new(mut inner: R) -> io::Result<Reader<R>> {
+        let line = read_line(&mut inner)?.ok_or(io::ErrorKind::UnexpectedEof)?;
+        let header = Header::parse(&line)?;
+        Ok(Reader { inner, header })
+    }
+
+    /// Returns the stream header
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Reads the next frame into a buffer for each plane
+    ///
+    /// Returns `false` when there are no more frames.
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidInput` if there are less than three buffers, or a buffer is smaller
+    ///   than its plane (see [`Header::buffers_size`])
+    ///
+    /// * `InvalidData` if the frame header is malformed
+    ///
+    /// * `UnexpectedEof` if the stream ends in the middle of a frame
+    ///
+    /// [`Header::buffers_size`]: ./struct.Header.html#method.buffers_size
+    pub fn read_frame(&mut self, buffers: &mut [&mut [u8]]) -> io::Result<bool> {
+        let buffers_size: Vec<usize> = buffers.iter().map(|buffer| buffer.len()).collect();
+        check_buffers(&self.header, &buffers_size)?;
+
+        let line = match read_line(&mut self.inner)? {
+            Some(line) => line,
+            None => return Ok(false),
+        };
+
+        // Frame parameters, if any, do not affect the frame layout
+        if !line.starts_with(FRAME_MAGIC)
+            || (line.len() > FRAME_MAGIC.len() && line[FRAME_MAGIC.len()] != b' ')
+        {
+            return Err(invalid_data("invalid frame header"));
+        }
+
+        for (buffer, &size) in buffers.iter_mut().zip(self.header.buffers_size().iter()) {
+            self.inner.read_exact(&mut buffer[..size])?;
+        }
+
+        Ok(true)
+    }
+
+    /// Returns the underlying reader
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+/// Writes frames to a y4m stream
+pub struct Writer<W: Write> {
+    inner: W,
+    header: Header,
+}
+
+impl<W: Write> Writer<W> {
+    /// Writes the stream header
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidData` if the pixel format is not `I420`, `I422` or `I444`, or the frame
+    ///   size violates its size constraints
+    pub fn new(mut inner: W, header: Header) -> io::Result<Writer<W>> {
+        header.validate()?;
+
+        let chroma_tag = get_chroma_tag(header.pixel_format).unwrap_or_default();
+        writeln!(
+            inner,
+            "YUV4MPEG2 W{} H{} F{}:{} I{} A{}:{} C{}",
+            header.width,
+            header.height,
+            header.frame_rate.0,
+            header.frame_rate.1,
+            header.interlacing,
+            header.pixel_aspect.0,
+            header.pixel_aspect.1,
+            chroma_tag
+        )?;
+
+        Ok(Writer { inner, header })
+    }
+
+    /// Returns the stream header
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Writes a frame from a buffer for each plane
+    ///
+    /// # Errors
+    ///
+    /// * `InvalidInput` if there are less than three buffers, or a buffer is smaller
+    ///   than its plane (see [`Header::buffers_size`])
+    ///
+    /// [`Header::buffers_size`]: ./struct.Header.html#method.buffers_size
+    pub fn write_frame(&mut self, buffers: &[&[u8]]) -> io::Result<()> {
+        let buffers_size: Vec<usize> = buffers.iter().map(|buffer| buffer.len()).collect();
+        check_buffers(&self.header, &buffers_size)?;
+
+        self.inner.write_all(FRAME_MAGIC)?;
+        self.inner.write_all(b"\n")?;
+        for (buffer, &size) in buffers.iter().zip(self.header.buffers_size().iter()) {
+            self.inner.write_all(&buffer[..size])?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the underlying writer
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
@@ -0,0 +1,342 @@
+// Copyright 2019 Amazon.com, Inc. or its affiliates. All Rights Reserved.
+// SPDX-License-Identifier: MIT-0
+
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify,
+// merge, publish, distribute, sublicense, and/or sell copies of the Software, and to
+// permit persons to whom the Software is furnished to do so.
+
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A
+// PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT
+// HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION
+// OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+// SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+
+//! Property-based tests: conversions between random image formats, with random sizes and
+//! strides, must honour the invariants below whatever the instruction set.
+#![cfg(not(target_arch = "wasm32"))]
+#![warn(unused)]
+#![deny(trivial_casts)]
+#![deny(trivial_numeric_casts)]
+#![deny(unsafe_code)]
+#![deny(unstable_features)]
+#![deny(unused_import_braces)]
+
+use dcp::*;
+use dcv_color_primitives as dcp;
+use proptest::collection::vec;
+use proptest::prelude::*;
+#[cfg(not(feature = "noise"))]
+use proptest::sample::select;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const MAX_NUMBER_OF_PLANES: u32 = 3;
+
+const PIXEL_FORMATS: &[PixelFormat; 16] = &[
+    PixelFormat::Argb,
+    PixelFormat::Bgra,
+    PixelFormat::Bgr,
+    PixelFormat::Rgba,
+    PixelFormat::Rgb,
+    PixelFormat::I444,
+    PixelFormat::I422,
+    PixelFormat::I420,
+    PixelFormat::Nv12,
+    PixelFormat::P010,
+    PixelFormat::YuvF32Planar,
+    PixelFormat::RgbaF16,
+    PixelFormat::RgbF32,
+    PixelFormat::RgbaF32,
+    PixelFormat::Bgr48,
+    PixelFormat::Rgb48,
+];
+
+const COLOR_SPACES: &[ColorSpace; 5] = &[
+    ColorSpace::Lrgb,
+    ColorSpace::Bt601,
+    ColorSpace::Bt709,
+    ColorSpace::Bt2020Pq,
+    ColorSpace::Jfif,
+];
+
+const MAX_WIDTH: u32 = 96;
+const MAX_HEIGHT: u32 = 48;
+const MAX_PADDING: usize = 67;
+
+/// Value of the destination bytes that conversions must not write
+const CANARY: u8 = 0xA5;
+
+/// Source samples are below this value, so that floating point samples are neither
+/// denormal nor infinite
+const MAX_SAMPLE: u8 = 0x3C;
+
+/// Maximum distance, in code values, between an 8-bit rgb sample and the same sample
+/// after a round trip through 8-bit YCbCr
+#[cfg(not(feature = "noise"))]
+const MAX_ROUND_TRIP_ERROR: i32 = 2;
+
+/// An image with one buffer for each plane, each line followed by padding bytes, and each
+/// buffer followed by as many padding bytes as its lines
+struct Image {
+    strides: Vec<usize>,
+    line_sizes: Vec<usize>,
+    line_counts: Vec<usize>,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl Image {
+    fn new<F: FnMut() -> u8>(
+        width: u32,
+        height: u32,
+        format: &ImageFormat,
+        paddings: &[usize],
+        mut fill: F,
+    ) -> Image {
+        let mut sizes = [0; MAX_NUMBER_OF_PLANES as usize];
+        assert!(get_buffers_size(width, height, format, None, &mut sizes).is_ok());
+
+        let planes = format.num_planes as usize;
+        let line_counts: Vec<usize> = (0..planes)
+            .map(|plane| (height >> vertical_shift(format.pixel_format, plane)) as usize)
+            .collect();
+        let line_sizes: Vec<usize> = sizes[..planes]
+            .iter()
+            .zip(&line_counts)
+            .map(|(size, lines)| size / lines)
+            .collect();
+        let strides: Vec<usize> = line_sizes
+            .iter()
+            .zip(paddings)
+            .map(|(line_size, padding)| line_size + padding)
+            .collect();
+        let buffers = strides
+            .iter()
+            .zip(&line_counts)
+            .zip(paddings)
+            .map(|((stride, lines), padding)| {
+                (0..stride * lines + padding).map(|_| fill()).collect()
+            })
+            .collect();
+
+        Image {
+            strides,
+            line_sizes,
+            line_counts,
+            buffers,
+        }
+    }
+
+    /// Returns the lines of each plane, without padding
+    fn visible(&self) -> Vec<Vec<u8>> {
+        (0..self.buffers.len())
+            .map(|plane| {
+                self.buffers[plane]
+                    .chunks(self.strides[plane])
+                    .take(self.line_counts[plane])
+                    .flat_map(|line| line[..self.line_sizes[plane]].iter().copied())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns the padding bytes of each plane
+    fn padding(&self) -> Vec<u8> {
+        (0..self.buffers.len())
+            .flat_map(|plane| {
+                let stride = self.strides[plane];
+                let line_size = self.line_sizes[plane];
+                self.buffers[plane]
+                    .iter()
+                    .enumerate()
+                    .filter(move |(i, _)| {
+                        i % stride >= line_size || i / stride >= self.line_counts[plane]
+                    })
+                    .map(|(_, &value)| value)
+            })
+            .collect()
+    }
+}
+
+fn vertical_shift(pixel_format: PixelFormat, plane: usize) -> u32 {
+    match pixel_format {
+        PixelFormat::I420 | PixelFormat::Nv12 | PixelFormat::P010 if plane > 0 => 1,
+        _ => 0,
+    }
+}
+
+fn is_valid(width: u32, height: u32, format: &ImageFormat) -> bool {
+    let mut sizes = [0; MAX_NUMBER_OF_PLANES as usize];
+    get_buffers_size(width, height, format, None, &mut sizes).is_ok()
+}
+
+/// Image formats with one buffer for each plane
+fn planar_formats() -> Vec<ImageFormat> {
+    let mut formats = Vec::new();
+    for &pixel_format in PIXEL_FORMATS {
+        for &color_space in COLOR_SPACES {
+            if let Some(format) = (1..=MAX_NUMBER_OF_PLANES)
+                .rev()
+                .map(|num_planes| ImageFormat {
+                    pixel_format,
+                    color_space,
+                    num_planes,
+                })
+                .find(|format| is_valid(2, 2, format))
+            {
+                formats.push(format);
+            }
+        }
+    }
+
+    formats
+}
+
+/// Pairs of planar image formats that can be converted, directly or through a route
+fn supported_pairs() -> Vec<(ImageFormat, ImageFormat)> {
+    dcp::initialize();
+
+    let formats = planar_formats();
+    let copy = |format: &ImageFormat| ImageFormat {
+        pixel_format: format.pixel_format,
+        color_space: format.color_space,
+        num_planes: format.num_planes,
+    };
+
+    let mut pairs = Vec::new();
+    for src_format in &formats {
+        for dst_format in &formats {
+            if describe_conversion(2, 2, src_format, dst_format).is_ok() {
+                pairs.push((copy(src_format), copy(dst_format)));
+            }
+        }
+    }
+
+    pairs
+}
+
+/// Rounds up odd dimensions that do not satisfy the size constraints of the formats
+fn fit_size(width: u32, height: u32, formats: &[&ImageFormat]) -> (u32, u32) {
+    let candidates = [
+        (width, height),
+        (width + (width & 1), height),
+        (width, height + (height & 1)),
+        (width + (width & 1), height + (height & 1)),
+    ];
+
+    candidates
+        .iter()
+        .copied()
+        .find(|&(width, height)| formats.iter().all(|format| is_valid(width, height, format)))
+        .unwrap()
+}
+
+fn convert(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src: &Image,
+    dst_format: &ImageFormat,
+    dst: &mut Image,
+) -> Result<(), ErrorKind> {
+    let src_buffers: Vec<&[u8]> = src.buffers.iter().map(|x| &x[..]).collect();
+    let mut dst_buffers: Vec<&mut [u8]> = dst.buffers.iter_mut().map(|x| &mut x[..]).collect();
+    convert_image(
+        width,
+        height,
+        src_format,
+        Some(&src.strides),
+        &src_buffers,
+        dst_format,
+        Some(&dst.strides),
+        &mut dst_buffers,
+    )
+}
+
+proptest! {
+    #[test]
+    fn padding_is_preserved(
+        pair in 0..supported_pairs().len(),
+        width in 1..=MAX_WIDTH,
+        height in 1..=MAX_HEIGHT,
+        src_paddings in vec(0..=MAX_PADDING, MAX_NUMBER_OF_PLANES as usize),
+        dst_paddings in vec(0..=MAX_PADDING, MAX_NUMBER_OF_PLANES as usize),
+        seed in any::<u64>(),
+    ) {
+        let (src_format, dst_format) = &supported_pairs()[pair];
+        let (width, height) = fit_size(width, height, &[src_format, dst_format]);
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let src = Image::new(width, height, src_format, &src_paddings, || rng.gen_range(0, MAX_SAMPLE));
+        let mut dst = Image::new(width, height, dst_format, &dst_paddings, || CANARY);
+        prop_assert!(convert(width, height, src_format, &src, dst_format, &mut dst).is_ok());
+
+        // Nothing is written outside the lines
+        prop_assert!(dst.padding().iter().all(|&value| value == CANARY));
+
+        // Source padding is never read
+        let no_padding = [0; MAX_NUMBER_OF_PLANES as usize];
+        let mut tight_src = Image::new(width, height, src_format, &no_padding, || 0);
+        tight_src.buffers = src.visible();
+        let mut tight_dst = Image::new(width, height, dst_format, &no_padding, || 0);
+        prop_assert!(convert(width, height, src_format, &tight_src, dst_format, &mut tight_dst).is_ok());
+        prop_assert_eq!(dst.visible(), tight_dst.buffers);
+    }
+
+    // The noise feature moves samples by up to its amplitude, beyond the round trip bound
+    #[cfg(not(feature = "noise"))]
+    #[test]
+    fn round_trip_is_bounded(
+        color_space in select(vec![ColorSpace::Bt601, ColorSpace::Bt709, ColorSpace::Jfif]),
+        yuv_format in select(vec![
+            (PixelFormat::I444, 3),
+            (PixelFormat::I420, 3),
+            (PixelFormat::Nv12, 2),
+        ]),
+        width in 1..=MAX_WIDTH,
+        height in 1..=MAX_HEIGHT,
+        paddings in vec(0..=MAX_PADDING, MAX_NUMBER_OF_PLANES as usize),
+        seed in any::<u64>(),
+    ) {
+        dcp::initialize();
+
+        let rgb_format = ImageFormat {
+            pixel_format: PixelFormat::Bgra,
+            color_space: ColorSpace::Lrgb,
+            num_planes: 1,
+        };
+
+        let yuv_format = ImageFormat {
+            pixel_format: yuv_format.0,
+            color_space,
+            num_planes: yuv_format.1,
+        };
+
+        let (width, height) = fit_size(width, height, &[&yuv_format]);
+
+        // Pixels sharing chroma samples have the same color, so subsampling loses nothing
+        let mut rng = StdRng::seed_from_u64(seed);
+        let colors: Vec<[u8; 3]> = (0..(width * height) as usize).map(|_| rng.gen()).collect();
+        let mut src = Image::new(width, height, &rgb_format, &paddings, || 0);
+        let stride = src.strides[0];
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let color = colors[(y & !1) * (width as usize) + (x & !1)];
+                let pixel = y * stride + 4 * x;
+                src.buffers[0][pixel..pixel + 3].copy_from_slice(&color);
+                src.buffers[0][pixel + 3] = 255;
+            }
+        }
+
+        let mut yuv = Image::new(width, height, &yuv_format, &paddings, || 0);
+        let mut dst = Image::new(width, height, &rgb_format, &paddings, || 0);
+        prop_assert!(convert(width, height, &rgb_format, &src, &yuv_format, &mut yuv).is_ok());
+        prop_assert!(convert(width, height, &yuv_format, &yuv, &rgb_format, &mut dst).is_ok());
+
+        for (expected, value) in src.visible()[0].iter().zip(&dst.visible()[0]) {
+            prop_assert!((i32::from(*expected) - i32::from(*value)).abs() <= MAX_ROUND_TRIP_ERROR);
+        }
+    }
+}
@@ -1,4 +1,6 @@
 #![warn(unused)]
+// The noise feature leaves out the tests checking exact values, and the references they use
+#![cfg_attr(feature = "noise", allow(dead_code))]
 #![deny(trivial_casts)]
 #![deny(trivial_numeric_casts)]
 #![deny(unsafe_code)]
@@ -10,6 +12,7 @@ use wasm_bindgen_test::wasm_bindgen_test as test;
 
 #[cfg(target_arch = "x86_64")]
 use std::alloc::{alloc, alloc_zeroed, dealloc, Layout};
+use std::mem::MaybeUninit;
 #[cfg(target_arch = "x86_64")]
 use std::ptr::write_bytes;
 #[cfg(target_arch = "x86_64")]
@@ -22,7 +25,7 @@ use rand::Rng;
 
 const MAX_NUMBER_OF_PLANES: u32 = 3;
 
-const PIXEL_FORMATS: &[PixelFormat; 9] = &[
+const PIXEL_FORMATS: &[PixelFormat; 16] = &[
     PixelFormat::Argb,
     PixelFormat::Bgra,
     PixelFormat::Bgr,
@@ -32,17 +35,44 @@ const PIXEL_FORMATS: &[PixelFormat; 9] = &[
     PixelFormat::I422,
     PixelFormat::I420,
     PixelFormat::Nv12,
+    PixelFormat::P010,
+    PixelFormat::YuvF32Planar,
+    PixelFormat::RgbaF16,
+    PixelFormat::RgbF32,
+    PixelFormat::RgbaF32,
+    PixelFormat::Bgr48,
+    PixelFormat::Rgb48,
 ];
 
-const COLOR_SPACES: &[ColorSpace; 3] = &[ColorSpace::Lrgb, ColorSpace::Bt601, ColorSpace::Bt709];
+const COLOR_SPACES: &[ColorSpace; 5] = &[
+    ColorSpace::Lrgb,
+    ColorSpace::Bt601,
+    ColorSpace::Bt709,
+    ColorSpace::Bt2020Pq,
+    ColorSpace::Jfif,
+];
 
 const PIXEL_FORMAT_I444: u32 = PixelFormat::I444 as u32;
 const PIXEL_FORMAT_I422: u32 = PixelFormat::I422 as u32;
 const PIXEL_FORMAT_I420: u32 = PixelFormat::I420 as u32;
+const PIXEL_FORMAT_NV12: u32 = PixelFormat::Nv12 as u32;
+const PIXEL_FORMAT_P010: u32 = PixelFormat::P010 as u32;
 const COLOR_SPACE_LRGB: u32 = ColorSpace::Lrgb as u32;
-const PIXEL_FORMAT_ARGB: u32 = PixelFormat::Argb as u32;
-const PIXEL_FORMAT_BGRA: u32 = PixelFormat::Bgra as u32;
-const PIXEL_FORMAT_BGR: u32 = PixelFormat::Bgr as u32;
+const COLOR_SPACE_BT2020PQ: u32 = ColorSpace::Bt2020Pq as u32;
+const COLOR_SPACE_JFIF: u32 = ColorSpace::Jfif as u32;
+
+fn is_rgb(pixel_format: PixelFormat) -> bool {
+    !matches!(
+        pixel_format,
+        PixelFormat::I444
+            | PixelFormat::I422
+            | PixelFormat::I420
+            | PixelFormat::Nv12
+            | PixelFormat::P010
+            | PixelFormat::YuvF32Planar
+    )
+}
+
 const RGB_TO_YUV_INPUT: [[[u8; 4]; 8]; 8] = [
     [
         [161, 24, 44, 58],
@@ -264,7 +294,7 @@ const CR_TO_RGB_INPUT: [[u8; 8]; 2] = [
     [128, 240, 26, 138, 118, 230, 16, 128],
 ];
 
-const NUM_LOG2_DEN: [[usize; 2]; 9] = [
+const NUM_LOG2_DEN: [[usize; 2]; 16] = [
     [4, 0],
     [4, 0],
     [3, 0],
@@ -274,9 +304,16 @@ const NUM_LOG2_DEN: [[usize; 2]; 9] = [
     [2, 0],
     [3, 1],
     [3, 1],
+    [3, 0],
+    [12, 0],
+    [8, 0],
+    [12, 0],
+    [16, 0],
+    [6, 0],
+    [6, 0],
 ];
 
-const NUM_LOG2_DEN_PER_PLANE: [[usize; (2 * MAX_NUMBER_OF_PLANES) as usize]; 9] = [
+const NUM_LOG2_DEN_PER_PLANE: [[usize; (2 * MAX_NUMBER_OF_PLANES) as usize]; 16] = [
     [4, 0, 0, 0, 0, 0],
     [4, 0, 0, 0, 0, 0],
     [3, 0, 0, 0, 0, 0],
@@ -286,6 +323,13 @@ const NUM_LOG2_DEN_PER_PLANE: [[usize; (2 * MAX_NUMBER_OF_PLANES) as usize]; 9]
     [1, 0, 1, 1, 1, 1],
     [1, 0, 1, 2, 1, 2],
     [1, 0, 1, 1, 0, 0],
+    [2, 0, 1, 0, 0, 0],
+    [4, 0, 4, 0, 4, 0],
+    [8, 0, 0, 0, 0, 0],
+    [12, 0, 0, 0, 0, 0],
+    [16, 0, 0, 0, 0, 0],
+    [6, 0, 0, 0, 0, 0],
+    [6, 0, 0, 0, 0, 0],
 ];
 
 macro_rules! set_expected {
@@ -315,6 +359,36 @@ fn init() {
     bootstrap();
 }
 
+#[test]
+fn describe_cpu_ok() {
+    bootstrap();
+
+    let capabilities = describe_cpu().unwrap();
+    assert!(["Intel", "Amd", "Unknown"].contains(&capabilities.manufacturer.as_str()));
+    assert_eq!(capabilities.instruction_sets[0], "X86");
+    assert!(capabilities
+        .instruction_sets
+        .contains(&capabilities.instruction_set));
+
+    // The selected kernels use an extension supported by the cpu
+    let extension = capabilities.instruction_set.to_lowercase();
+    assert!(extension == "x86" || capabilities.extensions.contains(&extension));
+
+    // Both 32-bit and 64-bit x86 dispatch to the vectorized kernels when available
+    #[cfg(all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        not(feature = "no-sse2")
+    ))]
+    {
+        if capabilities.extensions.contains(&"sse2".to_owned()) {
+            assert_ne!(capabilities.instruction_set, "X86");
+        }
+    }
+
+    let description = describe_acceleration().unwrap();
+    assert!(description.contains(&format!("instruction-set:{}", capabilities.instruction_set)));
+}
+
 fn rgb_to_yuv_size_mode_stride(
     num_planes: u32,
     width: u32,
@@ -1061,6 +1135,7 @@ fn yuv_to_rgb_ok(format: PixelFormat, num_planes: u32) {
     }
 }
 
+#[cfg(not(feature = "noise"))]
 #[test]
 fn rgb_to_nv12_ok() {
     bootstrap();
@@ -1069,6 +1144,7 @@ fn rgb_to_nv12_ok() {
     rgb_to_yuv_ok(PixelFormat::Nv12, 2);
 }
 
+#[cfg(not(feature = "noise"))]
 #[test]
 fn rgb_to_i420_ok() {
     bootstrap();
@@ -1076,6 +1152,7 @@ fn rgb_to_i420_ok() {
     rgb_to_yuv_ok(PixelFormat::I420, 3);
 }
 
+#[cfg(not(feature = "noise"))]
 #[test]
 fn rgb_to_i444_ok() {
     bootstrap();
@@ -1112,7 +1189,8 @@ fn rgb_to_yuv_errors() {
     const WIDTH: u32 = 2;
     const HEIGHT: u32 = 2;
     const CHROMA_HEIGHT: u32 = HEIGHT / 2;
-    const SRC_STRIDE: usize = (WIDTH as usize) * 4;
+    // Large enough for any source pixel format, as unsupported ones are routed
+    const SRC_STRIDE: usize = (WIDTH as usize) * 8;
     const IN_SIZE: usize = SRC_STRIDE * (HEIGHT as usize);
     const OUT_SIZE: usize = (WIDTH as usize) * ((HEIGHT as usize) + (CHROMA_HEIGHT as usize));
 
@@ -1149,7 +1227,7 @@ fn rgb_to_yuv_errors() {
                     let src_pf = *src_pixel_format as u32;
                     let src_cs = *src_color_space as u32;
                     let dst_cs = *dst_color_space as u32;
-                    let src_pf_rgb = src_pf < PIXEL_FORMAT_I444;
+                    let src_pf_rgb = is_rgb(*src_pixel_format);
                     let src_cs_rgb = src_cs <= COLOR_SPACE_LRGB;
                     let dst_cs_rgb = dst_cs <= COLOR_SPACE_LRGB;
 
@@ -1166,20 +1244,42 @@ fn rgb_to_yuv_errors() {
                     set_expected!(expected, num_planes < 1, ErrorKind::InvalidValue);
                     set_expected!(expected, num_planes > 2, ErrorKind::InvalidValue);
 
+                    // Sources without a direct conversion are routed through Bgra,
+                    // Bt2020Pq ones through P010 and RgbaF16. Nothing converts from Jfif P010
+                    let src_routable = match *src_pixel_format {
+                        PixelFormat::Rgba
+                        | PixelFormat::RgbF32
+                        | PixelFormat::RgbaF32
+                        | PixelFormat::I422
+                        | PixelFormat::YuvF32Planar => false,
+                        PixelFormat::Nv12 => true,
+                        PixelFormat::P010 => src_cs != COLOR_SPACE_JFIF,
+                        _ => src_cs != COLOR_SPACE_BT2020PQ,
+                    };
+
+                    set_expected!(expected, !src_routable, ErrorKind::InvalidOperation);
+
                     set_expected!(
                         expected,
-                        src_pf != PIXEL_FORMAT_ARGB
-                            && src_pf != PIXEL_FORMAT_BGRA
-                            && src_pf != PIXEL_FORMAT_BGR,
+                        src_pf == PIXEL_FORMAT_NV12 && src_cs == dst_cs,
                         ErrorKind::InvalidOperation
                     );
 
+                    // Only P010 converts to Bt2020Pq Nv12
                     set_expected!(
                         expected,
-                        src_cs != COLOR_SPACE_LRGB,
+                        dst_cs == COLOR_SPACE_BT2020PQ
+                            && (src_pf != PIXEL_FORMAT_P010 || src_cs != dst_cs),
                         ErrorKind::InvalidOperation
                     );
 
+                    // Single plane i444 and i420 images are not supported
+                    set_expected!(
+                        expected,
+                        src_pf == PIXEL_FORMAT_I444 || src_pf == PIXEL_FORMAT_I420,
+                        ErrorKind::NotEnoughData
+                    );
+
                     let status = convert_image(
                         WIDTH,
                         HEIGHT,
@@ -1246,7 +1346,7 @@ fn yuv_to_rgb_errors() {
                     let dst_pf = *dst_pixel_format as u32;
                     let dst_cs = *dst_color_space as u32;
                     let src_cs = *src_color_space as u32;
-                    let dst_pf_rgb = dst_pf < PIXEL_FORMAT_I444;
+                    let dst_pf_rgb = is_rgb(*dst_pixel_format);
                     let dst_cs_rgb = dst_cs <= COLOR_SPACE_LRGB;
                     let src_cs_rgb = src_cs <= COLOR_SPACE_LRGB;
 
@@ -1263,16 +1363,50 @@ fn yuv_to_rgb_errors() {
                     set_expected!(expected, num_planes < 1, ErrorKind::InvalidValue);
                     set_expected!(expected, num_planes > 2, ErrorKind::InvalidValue);
 
+                    // Destinations without a direct conversion are routed through Bgra,
+                    // Bt2020Pq sources through P010 and RgbaF16. Nothing converts to Jfif P010
+                    let dst_routable = match *dst_pixel_format {
+                        PixelFormat::Bgra
+                        | PixelFormat::Rgb
+                        | PixelFormat::RgbF32
+                        | PixelFormat::RgbaF32 => true,
+                        PixelFormat::I444 | PixelFormat::I420 | PixelFormat::Nv12 => {
+                            dst_cs != COLOR_SPACE_BT2020PQ
+                        }
+                        PixelFormat::P010 => {
+                            dst_cs != COLOR_SPACE_JFIF
+                                && (dst_cs != COLOR_SPACE_BT2020PQ || src_cs == dst_cs)
+                        }
+                        PixelFormat::RgbaF16 => src_cs == COLOR_SPACE_BT2020PQ,
+                        PixelFormat::YuvF32Planar => {
+                            dst_cs == ColorSpace::Bt601 as u32 || dst_cs == ColorSpace::Bt709 as u32
+                        }
+                        _ => false,
+                    };
+
+                    set_expected!(expected, !dst_routable, ErrorKind::InvalidOperation);
+
                     set_expected!(
                         expected,
-                        dst_pf != PIXEL_FORMAT_BGRA,
+                        dst_pf == PIXEL_FORMAT_NV12 && dst_cs == src_cs,
                         ErrorKind::InvalidOperation
                     );
 
+                    // Single plane i444 and i420 images are not supported
                     set_expected!(
                         expected,
-                        dst_cs != COLOR_SPACE_LRGB,
-                        ErrorKind::InvalidOperation
+                        dst_pf == PIXEL_FORMAT_I444 || dst_pf == PIXEL_FORMAT_I420,
+                        ErrorKind::NotEnoughData
+                    );
+
+                    // Float destinations need more than four bytes per pixel
+                    set_expected!(
+                        expected,
+                        dst_pf == PixelFormat::RgbaF16 as u32
+                            || dst_pf == PixelFormat::RgbF32 as u32
+                            || dst_pf == PixelFormat::RgbaF32 as u32
+                            || dst_pf == PixelFormat::YuvF32Planar as u32,
+                        ErrorKind::NotEnoughData
                     );
 
                     let status = convert_image(
@@ -1327,7 +1461,11 @@ fn buffers_size() {
             // Invalid width
             let mut expected: Result<(), ErrorKind> = Ok(());
 
-            set_expected!(expected, pf >= PIXEL_FORMAT_I422, ErrorKind::InvalidValue);
+            set_expected!(
+                expected,
+                (PIXEL_FORMAT_I422..=PIXEL_FORMAT_P010).contains(&pf),
+                ErrorKind::InvalidValue
+            );
             set_expected!(
                 expected,
                 num_planes != 1 && num_planes != max_number_of_planes,
@@ -1343,7 +1481,11 @@ fn buffers_size() {
             // Invalid height
             let mut expected: Result<(), ErrorKind> = Ok(());
 
-            set_expected!(expected, pf >= PIXEL_FORMAT_I420, ErrorKind::InvalidValue);
+            set_expected!(
+                expected,
+                (PIXEL_FORMAT_I420..=PIXEL_FORMAT_P010).contains(&pf),
+                ErrorKind::InvalidValue
+            );
             set_expected!(
                 expected,
                 num_planes != 1 && num_planes != max_number_of_planes,
@@ -1394,6 +1536,204 @@ fn buffers_size() {
     }
 }
 
+#[test]
+fn pixel_layout_ok() {
+    use dcp::layout::{self, PixelLayout, PlaneGeometry};
+
+    // Layouts of built-in pixel formats give the same sizes as the pixel formats,
+    // with default strides or a stride for each buffer
+    for pixel_format in PIXEL_FORMATS.iter() {
+        let num_logical_planes = pixel_format.planes().len() as u32;
+        for num_planes in 0..=MAX_NUMBER_OF_PLANES + 1 {
+            let format = ImageFormat {
+                pixel_format: *pixel_format,
+                color_space: ColorSpace::Lrgb,
+                num_planes,
+            };
+
+            for &(width, height) in &[(0, 0), (32, 18), (33, 18), (32, 17), (33, 17)] {
+                let padded_strides: Vec<usize> = (0..num_logical_planes)
+                    .map(|plane| layout::line_size(width, pixel_format, plane).unwrap() + 3)
+                    .collect();
+                let strides = if num_planes > 1 {
+                    vec![None, Some(&padded_strides[..]), Some(&[][..])]
+                } else {
+                    vec![None, Some(&[][..])]
+                };
+
+                for strides in strides.iter() {
+                    let expected_sizes = &mut [0usize; MAX_NUMBER_OF_PLANES as usize];
+                    let expected =
+                        get_buffers_size(width, height, &format, *strides, expected_sizes);
+                    let sizes = &mut [0usize; MAX_NUMBER_OF_PLANES as usize];
+                    let status = layout::get_buffers_size(
+                        width,
+                        height,
+                        pixel_format,
+                        num_planes,
+                        *strides,
+                        sizes,
+                    );
+
+                    match (expected, status) {
+                        (Ok(_), Ok(_)) => assert_eq!(sizes, expected_sizes),
+                        (Err(expected), Err(status)) => assert_eq!(status as u32, expected as u32),
+                        _ => panic!("{:?} {:?} {}x{}", format, strides, width, height),
+                    }
+                }
+            }
+        }
+
+        for plane in 0..=num_logical_planes {
+            assert_eq!(
+                layout::recommended_stride(1366, pixel_format, plane),
+                recommended_stride(1366, *pixel_format, plane)
+            );
+        }
+
+        let bit_depth = match pixel_format {
+            PixelFormat::P010 => 10,
+            PixelFormat::RgbaF16 | PixelFormat::Bgr48 | PixelFormat::Rgb48 => 16,
+            PixelFormat::RgbF32 | PixelFormat::RgbaF32 | PixelFormat::YuvF32Planar => 32,
+            _ => 8,
+        };
+        assert_eq!(pixel_format.bit_depth(), bit_depth);
+    }
+
+    // Downstream layout: a 4:1:0 planar format, chroma subsampled by four in both directions
+    struct Yuv410;
+
+    impl PixelLayout for Yuv410 {
+        fn planes(&self) -> &[PlaneGeometry] {
+            const LUMA: PlaneGeometry = PlaneGeometry {
+                bytes_per_sample: 1,
+                horizontal_shift: 0,
+                vertical_shift: 0,
+            };
+            const CHROMA: PlaneGeometry = PlaneGeometry {
+                bytes_per_sample: 1,
+                horizontal_shift: 2,
+                vertical_shift: 2,
+            };
+
+            &[LUMA, CHROMA, CHROMA]
+        }
+
+        fn bit_depth(&self) -> u32 {
+            8
+        }
+    }
+
+    let sizes = &mut [0usize; 3];
+    assert!(layout::get_buffers_size(64, 32, &Yuv410, 3, None, sizes).is_ok());
+    assert_eq!(sizes, &[64 * 32, 16 * 8, 16 * 8]);
+    assert!(layout::get_buffers_size(64, 32, &Yuv410, 1, Some(&[80]), sizes).is_ok());
+    assert_eq!(sizes[0], 80 * 32 + 2 * 80 * 8);
+    assert!(layout::get_buffers_size(64, 32, &Yuv410, 1, Some(&[80, 16, 24]), sizes).is_ok());
+    assert_eq!(sizes[0], 80 * 32 + 16 * 8 + 24 * 8);
+    assert_eq!(layout::recommended_stride(100, &Yuv410, 1), 64);
+    assert_eq!(layout::line_size(100, &Yuv410, 3), None);
+
+    // Dimensions not multiple of the subsampling factors and invalid number of planes
+    for &(width, height, num_planes) in &[(66, 32, 3), (64, 30, 3), (64, 32, 2), (64, 32, 4)] {
+        assert_eq!(
+            layout::get_buffers_size(width, height, &Yuv410, num_planes, None, sizes)
+                .err()
+                .map(|e| e as u32),
+            Some(ErrorKind::InvalidValue as u32)
+        );
+    }
+
+    assert_eq!(
+        layout::get_buffers_size(64, 32, &Yuv410, 3, Some(&[64, 16]), sizes)
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+    assert_eq!(
+        layout::get_buffers_size(64, 32, &Yuv410, 3, None, &mut [0usize; 2])
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+}
+
+#[test]
+fn buffers_size_limits() {
+    bootstrap();
+
+    let buffers_size = &mut [0usize; MAX_NUMBER_OF_PLANES as usize];
+
+    // Every dimension up to MAX_DIMENSION is valid: sizes are reported unless they do not
+    // fit the address space
+    for pixel_format in PIXEL_FORMATS.iter() {
+        let pf = *pixel_format as usize;
+        let format = ImageFormat {
+            pixel_format: *pixel_format,
+            color_space: ColorSpace::Lrgb,
+            num_planes: 1,
+        };
+
+        // Subsampled formats need even dimensions
+        let largest = MAX_DIMENSION - 1;
+        for &(width, height) in &[(largest, 2), (2, largest), (largest, largest)] {
+            let area = u128::from(width) * u128::from(height);
+            let expected = (area * (NUM_LOG2_DEN[pf][0] as u128)) >> NUM_LOG2_DEN[pf][1];
+            let status = get_buffers_size(width, height, &format, None, buffers_size);
+            if expected <= (usize::MAX as u128) {
+                assert!(status.is_ok());
+                assert_eq!(buffers_size[0] as u128, expected);
+            } else {
+                assert_eq!(
+                    status.err().map(|e| e as u32),
+                    Some(ErrorKind::InvalidValue as u32)
+                );
+            }
+        }
+    }
+
+    // Custom strides overflowing the size of a plane
+    let half = usize::MAX / 2;
+    let strides = &[half, half];
+    let mut format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt601,
+        num_planes: 2,
+    };
+
+    assert!(get_buffers_size(2, 2, &format, Some(strides), buffers_size).is_ok());
+    assert_eq!(buffers_size[0], 2 * half);
+    assert_eq!(buffers_size[1], half);
+
+    assert_eq!(
+        get_buffers_size(2, 4, &format, Some(strides), buffers_size)
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        get_buffers_size(
+            2,
+            2,
+            &format,
+            Some(&[usize::MAX, STRIDE_AUTO]),
+            buffers_size
+        )
+        .err()
+        .map(|e| e as u32),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+
+    // Planes fitting individually, but not once laid out in a single buffer
+    format.num_planes = 1;
+    assert_eq!(
+        get_buffers_size(2, 2, &format, Some(strides), buffers_size)
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+}
+
 #[cfg(target_arch = "x86_64")]
 #[test]
 fn over_4gb() {
@@ -1494,6 +1834,7 @@ fn over_4gb() {
     }
 }
 
+#[cfg(not(feature = "noise"))]
 #[test]
 fn lrgb_ok() {
     bootstrap();
@@ -1614,3 +1955,11624 @@ fn lrgb_conversion_ok(
         }
     }
 }
+
+#[test]
+fn conversion_plan_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 4;
+    const SRC_STRIDE: usize = 4 * (WIDTH as usize) + 3;
+    const DST_STRIDE: usize = (WIDTH as usize) + 2;
+    const DST_SIZE: usize = DST_STRIDE * (HEIGHT as usize) * 3 / 2;
+
+    let src_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let dst_format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt601,
+        num_planes: 1,
+    };
+
+    let plan = ConversionPlan::new(
+        WIDTH,
+        HEIGHT,
+        &src_format,
+        Some(&[SRC_STRIDE]),
+        &dst_format,
+        Some(&[DST_STRIDE]),
+    )
+    .unwrap();
+
+    let mut rng = rand::thread_rng();
+    for _ in 0..4 {
+        let test_input: Vec<u8> = (0..SRC_STRIDE * (HEIGHT as usize))
+            .map(|_| rng.gen::<u8>())
+            .collect();
+        let mut expected = vec![0u8; DST_SIZE];
+        let mut test_output = vec![0u8; DST_SIZE];
+
+        assert!(convert_image(
+            WIDTH,
+            HEIGHT,
+            &src_format,
+            Some(&[SRC_STRIDE]),
+            &[&test_input],
+            &dst_format,
+            Some(&[DST_STRIDE]),
+            &mut [&mut expected],
+        )
+        .is_ok());
+        assert!(plan
+            .convert(&[&test_input], &mut [&mut test_output])
+            .is_ok());
+        assert_eq!(test_output, expected);
+    }
+
+    // Buffers are still validated on execution
+    let test_input = vec![0u8; SRC_STRIDE * (HEIGHT as usize) - 1];
+    let mut test_output = vec![0u8; DST_SIZE];
+    assert_eq!(
+        plan.convert(&[&test_input], &mut [&mut test_output])
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+
+    // Plan parameters are validated on creation
+    assert_eq!(
+        ConversionPlan::new(WIDTH, HEIGHT, &src_format, None, &src_format, None)
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+    assert_eq!(
+        ConversionPlan::new(WIDTH, HEIGHT, &src_format, Some(&[]), &dst_format, None)
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+}
+
+#[cfg(not(feature = "noise"))]
+#[test]
+fn reference_ok() {
+    bootstrap();
+
+    const WIDTH: usize = 34;
+    const HEIGHT: usize = 6;
+    let mut rng = rand::thread_rng();
+
+    for color_space in COLOR_SPACES.iter() {
+        let bgra: Vec<u8> = (0..4 * WIDTH * HEIGHT).map(|_| rng.gen::<u8>()).collect();
+        let pixel = |x: usize, y: usize| {
+            let p = 4 * (y * WIDTH + x);
+            (bgra[p + 2], bgra[p + 1], bgra[p])
+        };
+
+        let src_format = ImageFormat {
+            pixel_format: PixelFormat::Bgra,
+            color_space: ColorSpace::Lrgb,
+            num_planes: 1,
+        };
+
+        let i444_format = ImageFormat {
+            pixel_format: PixelFormat::I444,
+            color_space: *color_space,
+            num_planes: 3,
+        };
+
+        let i420_format = ImageFormat {
+            pixel_format: PixelFormat::I420,
+            color_space: *color_space,
+            num_planes: 3,
+        };
+
+        if let ColorSpace::Lrgb | ColorSpace::Bt2020Pq = color_space {
+            assert!(reference::rgb_to_yuv(0, 0, 0, *color_space).is_err());
+            assert!(reference::rgb_to_yuv_2x2(&[(0, 0, 0); 4], *color_space).is_err());
+            assert!(reference::yuv_to_rgb(16, 128, 128, *color_space).is_err());
+            continue;
+        }
+
+        // Forward, 4:4:4
+        let mut y_plane = vec![0u8; WIDTH * HEIGHT];
+        let mut u_plane = vec![0u8; WIDTH * HEIGHT];
+        let mut v_plane = vec![0u8; WIDTH * HEIGHT];
+        assert!(convert_image(
+            WIDTH as u32,
+            HEIGHT as u32,
+            &src_format,
+            None,
+            &[&bgra],
+            &i444_format,
+            None,
+            &mut [&mut y_plane, &mut u_plane, &mut v_plane],
+        )
+        .is_ok());
+
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let (r, g, b) = pixel(x, y);
+                let i = y * WIDTH + x;
+                assert_eq!(
+                    reference::rgb_to_yuv(r, g, b, *color_space).ok(),
+                    Some((y_plane[i], u_plane[i], v_plane[i]))
+                );
+            }
+        }
+
+        // Backward, 4:4:4
+        let mut rgb_output = vec![0u8; 4 * WIDTH * HEIGHT];
+        assert!(convert_image(
+            WIDTH as u32,
+            HEIGHT as u32,
+            &i444_format,
+            None,
+            &[&y_plane, &u_plane, &v_plane],
+            &src_format,
+            None,
+            &mut [&mut rgb_output],
+        )
+        .is_ok());
+
+        for i in 0..WIDTH * HEIGHT {
+            let (r, g, b) =
+                reference::yuv_to_rgb(y_plane[i], u_plane[i], v_plane[i], *color_space).unwrap();
+            assert_eq!(
+                [b, g, r, 255],
+                [
+                    rgb_output[4 * i],
+                    rgb_output[4 * i + 1],
+                    rgb_output[4 * i + 2],
+                    rgb_output[4 * i + 3]
+                ]
+            );
+        }
+
+        // Jfif chroma rounding depends on the column of the block
+        if let ColorSpace::Jfif = color_space {
+            assert!(reference::rgb_to_yuv_2x2(&[(0, 0, 0); 4], *color_space).is_err());
+            continue;
+        }
+
+        // Forward, 4:2:0
+        let mut y_plane = vec![0u8; WIDTH * HEIGHT];
+        let mut u_plane = vec![0u8; WIDTH * HEIGHT / 4];
+        let mut v_plane = vec![0u8; WIDTH * HEIGHT / 4];
+        assert!(convert_image(
+            WIDTH as u32,
+            HEIGHT as u32,
+            &src_format,
+            None,
+            &[&bgra],
+            &i420_format,
+            None,
+            &mut [&mut y_plane, &mut u_plane, &mut v_plane],
+        )
+        .is_ok());
+
+        for y in 0..HEIGHT / 2 {
+            for x in 0..WIDTH / 2 {
+                let block = [
+                    pixel(2 * x, 2 * y),
+                    pixel(2 * x + 1, 2 * y),
+                    pixel(2 * x, 2 * y + 1),
+                    pixel(2 * x + 1, 2 * y + 1),
+                ];
+                let (luma, cb, cr) = reference::rgb_to_yuv_2x2(&block, *color_space).unwrap();
+                let i = 2 * y * WIDTH + 2 * x;
+                assert_eq!(
+                    luma,
+                    [
+                        y_plane[i],
+                        y_plane[i + 1],
+                        y_plane[i + WIDTH],
+                        y_plane[i + WIDTH + 1]
+                    ]
+                );
+                assert_eq!(cb, u_plane[y * WIDTH / 2 + x]);
+                assert_eq!(cr, v_plane[y * WIDTH / 2 + x]);
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "noise"))]
+#[test]
+fn patterns_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 38;
+    const HEIGHT: u32 = 10;
+    const SIZE: usize = (WIDTH as usize) * (HEIGHT as usize);
+    const PATTERNS: &[patterns::Pattern; 4] = &[
+        patterns::Pattern::ColorBars,
+        patterns::Pattern::ZonePlate,
+        patterns::Pattern::HorizontalRamp,
+        patterns::Pattern::VerticalRamp,
+    ];
+
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    for pattern in PATTERNS.iter() {
+        let mut bgra = vec![0u8; 4 * SIZE];
+        assert!(patterns::generate(
+            *pattern,
+            WIDTH,
+            HEIGHT,
+            &bgra_format,
+            None,
+            &mut [&mut bgra]
+        )
+        .is_ok());
+
+        // Packed rgb formats only differ in the channel order
+        let mut argb = vec![0u8; 4 * SIZE];
+        let mut rgb = vec![0u8; 3 * SIZE];
+        for (pixel_format, buffer) in
+            [(PixelFormat::Argb, &mut argb), (PixelFormat::Rgb, &mut rgb)].iter_mut()
+        {
+            let format = ImageFormat {
+                pixel_format: *pixel_format,
+                color_space: ColorSpace::Lrgb,
+                num_planes: 1,
+            };
+
+            assert!(patterns::generate(
+                *pattern,
+                WIDTH,
+                HEIGHT,
+                &format,
+                None,
+                &mut [&mut buffer[..]]
+            )
+            .is_ok());
+        }
+
+        for i in 0..SIZE {
+            assert_eq!(argb[4 * i], 255);
+            assert_eq!(bgra[4 * i + 3], 255);
+            for c in 0..3 {
+                assert_eq!(argb[4 * i + 1 + c], bgra[4 * i + 2 - c]);
+                assert_eq!(rgb[3 * i + c], bgra[4 * i + 2 - c]);
+            }
+        }
+
+        // Yuv formats match the conversion of the rgb pattern
+        for (pixel_format, color_space) in iproduct!(
+            [PixelFormat::I444, PixelFormat::I420, PixelFormat::Nv12].iter(),
+            [ColorSpace::Bt601, ColorSpace::Bt709].iter()
+        ) {
+            let format = ImageFormat {
+                pixel_format: *pixel_format,
+                color_space: *color_space,
+                num_planes: 1,
+            };
+
+            let size = match pixel_format {
+                PixelFormat::I444 => 3 * SIZE,
+                _ => 3 * SIZE / 2,
+            };
+
+            let mut expected = vec![0u8; size];
+            let mut test_output = vec![0u8; size];
+            if let PixelFormat::Nv12 = pixel_format {
+                assert!(convert_image(
+                    WIDTH,
+                    HEIGHT,
+                    &bgra_format,
+                    None,
+                    &[&bgra],
+                    &format,
+                    None,
+                    &mut [&mut expected],
+                )
+                .is_ok());
+            } else {
+                let three_planes = ImageFormat {
+                    pixel_format: *pixel_format,
+                    color_space: *color_space,
+                    num_planes: 3,
+                };
+
+                let chroma_size = (size - SIZE) / 2;
+                let (y_plane, uv_plane) = expected.split_at_mut(SIZE);
+                let (u_plane, v_plane) = uv_plane.split_at_mut(chroma_size);
+                assert!(convert_image(
+                    WIDTH,
+                    HEIGHT,
+                    &bgra_format,
+                    None,
+                    &[&bgra],
+                    &three_planes,
+                    None,
+                    &mut [y_plane, u_plane, v_plane],
+                )
+                .is_ok());
+            }
+
+            assert!(patterns::generate(
+                *pattern,
+                WIDTH,
+                HEIGHT,
+                &format,
+                None,
+                &mut [&mut test_output]
+            )
+            .is_ok());
+            assert_eq!(test_output, expected);
+        }
+    }
+
+    // Errors
+    let mut buffer = vec![0u8; 4 * SIZE];
+    let bad_color_space = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Bt601,
+        num_planes: 1,
+    };
+    assert!(patterns::generate(
+        patterns::Pattern::ColorBars,
+        WIDTH,
+        HEIGHT,
+        &bad_color_space,
+        None,
+        &mut [&mut buffer]
+    )
+    .is_err());
+
+    let mut buffer = vec![0u8; 4 * SIZE - 1];
+    match patterns::generate(
+        patterns::Pattern::ColorBars,
+        WIDTH,
+        HEIGHT,
+        &bgra_format,
+        None,
+        &mut [&mut buffer],
+    ) {
+        Err(ErrorKind::NotEnoughData) => {}
+        _ => assert!(false),
+    }
+}
+
+#[cfg(not(feature = "noise"))]
+#[test]
+fn hdr_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 4;
+    const HEIGHT: u32 = 2;
+    const Y_CODES: [u16; 8] = [64, 940, 486, 486, 520, 520, 64, 940];
+    const EXPECTED: [f32; 8] = [0.0, 125.0, 0.959, 0.959, 1.415, 1.415, 0.0, 125.0];
+
+    let p010_format = ImageFormat {
+        pixel_format: PixelFormat::P010,
+        color_space: ColorSpace::Bt2020Pq,
+        num_planes: 2,
+    };
+
+    let f16_format = ImageFormat {
+        pixel_format: PixelFormat::RgbaF16,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    // Neutral chroma gives gray pixels
+    let y_plane: Vec<u8> = Y_CODES
+        .iter()
+        .flat_map(|code| (code << 6).to_le_bytes().to_vec())
+        .collect();
+    let uv_plane: Vec<u8> = (0..WIDTH)
+        .flat_map(|_| (512u16 << 6).to_le_bytes().to_vec())
+        .collect();
+    let mut rgba_f16 = vec![0u8; 8 * Y_CODES.len()];
+    assert!(convert_image(
+        WIDTH,
+        HEIGHT,
+        &p010_format,
+        None,
+        &[&y_plane, &uv_plane],
+        &f16_format,
+        None,
+        &mut [&mut rgba_f16],
+    )
+    .is_ok());
+
+    let to_f32 = |h: u16| {
+        let exp = i32::from((h >> 10) & 0x1F);
+        let man = f32::from(h & 0x3FF);
+        let sign = if (h & 0x8000) == 0 { 1.0 } else { -1.0 };
+        if exp == 0 {
+            sign * man * 2f32.powi(-24)
+        } else {
+            sign * (1.0 + man / 1024.0) * 2f32.powi(exp - 15)
+        }
+    };
+
+    for (i, expected) in EXPECTED.iter().enumerate() {
+        let pixel = &rgba_f16[8 * i..8 * (i + 1)];
+        for c in 0..4 {
+            let value = to_f32(u16::from_le_bytes([pixel[2 * c], pixel[2 * c + 1]]));
+            let expected = if c == 3 { 1.0 } else { *expected };
+            assert!((value - expected).abs() <= 0.002 * expected + 0.001);
+        }
+    }
+
+    // Tone mapping to 8-bit sRGB
+    const F16_INPUT: [[u16; 4]; 4] = [
+        [0x0000, 0x0000, 0x0000, 0x3C00],
+        [0x3800, 0x3800, 0x3800, 0x3C00],
+        [0xBC00, 0x3400, 0x3800, 0x3800],
+        [0x57D0, 0x0000, 0x4000, 0x3C00],
+    ];
+    const BGRA_OUTPUT: [[u8; 4]; 4] = [
+        [0, 0, 0, 255],
+        [188, 188, 188, 255],
+        [188, 137, 0, 128],
+        [34, 0, 255, 255],
+    ];
+
+    let rgba_f16: Vec<u8> = F16_INPUT
+        .iter()
+        .flat_map(|pixel| pixel.iter().flat_map(|c| c.to_le_bytes().to_vec()))
+        .collect();
+    let mut bgra = vec![0u8; 4 * F16_INPUT.len()];
+    assert!(convert_image(
+        2,
+        2,
+        &f16_format,
+        None,
+        &[&rgba_f16],
+        &bgra_format,
+        None,
+        &mut [&mut bgra],
+    )
+    .is_ok());
+
+    for (i, expected) in BGRA_OUTPUT.iter().enumerate() {
+        assert_eq!(&bgra[4 * i..4 * (i + 1)], &expected[..]);
+    }
+}
+
+#[test]
+fn float_rgb_ok() {
+    bootstrap();
+
+    const WIDTH: usize = 8;
+    const HEIGHT: usize = 4;
+    const PADDING: usize = 12;
+
+    let mut rng = rand::thread_rng();
+    for (&(pixel_format, num_planes), color_space, dst_pixel_format) in iproduct!(
+        [
+            (PixelFormat::I444, 3),
+            (PixelFormat::I420, 3),
+            (PixelFormat::Nv12, 2)
+        ]
+        .iter(),
+        [ColorSpace::Bt601, ColorSpace::Bt709].iter(),
+        [PixelFormat::RgbF32, PixelFormat::RgbaF32].iter()
+    ) {
+        let src_format = ImageFormat {
+            pixel_format,
+            color_space: *color_space,
+            num_planes,
+        };
+        let dst_format = ImageFormat {
+            pixel_format: *dst_pixel_format,
+            color_space: ColorSpace::Lrgb,
+            num_planes: 1,
+        };
+
+        let mut sizes = [0usize; MAX_NUMBER_OF_PLANES as usize];
+        assert!(get_buffers_size(
+            WIDTH as u32,
+            HEIGHT as u32,
+            &src_format,
+            None,
+            &mut sizes[..num_planes as usize]
+        )
+        .is_ok());
+        let planes: Vec<Vec<u8>> = sizes[..num_planes as usize]
+            .iter()
+            .map(|&size| (0..size).map(|_| rng.gen::<u8>()).collect())
+            .collect();
+        let src_buffers: Vec<&[u8]> = planes.iter().map(|plane| &plane[..]).collect();
+
+        let channels = match dst_pixel_format {
+            PixelFormat::RgbF32 => 3,
+            _ => 4,
+        };
+        let dst_stride = 4 * channels * WIDTH + PADDING;
+        let mut dst = vec![0u8; dst_stride * HEIGHT];
+        assert!(convert_image(
+            WIDTH as u32,
+            HEIGHT as u32,
+            &src_format,
+            None,
+            &src_buffers,
+            &dst_format,
+            Some(&[dst_stride]),
+            &mut [&mut dst],
+        )
+        .is_ok());
+
+        let [rcr, gcb, gcr, bcb] = match color_space {
+            ColorSpace::Bt601 => [1.402, 0.344_136, 0.714_136, 1.772],
+            _ => [1.5748, 0.187_324, 0.468_124, 1.8556],
+        };
+
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let (cb, cr) = match pixel_format {
+                    PixelFormat::I444 => (planes[1][y * WIDTH + x], planes[2][y * WIDTH + x]),
+                    PixelFormat::I420 => {
+                        let index = (y / 2) * (WIDTH / 2) + x / 2;
+                        (planes[1][index], planes[2][index])
+                    }
+                    _ => {
+                        let index = (y / 2) * WIDTH + 2 * (x / 2);
+                        (planes[1][index], planes[1][index + 1])
+                    }
+                };
+
+                let l = (f32::from(planes[0][y * WIDTH + x]) - 16.0) / 219.0;
+                let cb = (f32::from(cb) - 128.0) / 224.0;
+                let cr = (f32::from(cr) - 128.0) / 224.0;
+                let expected = [l + rcr * cr, l - gcb * cb - gcr * cr, l + bcb * cb, 1.0];
+
+                for (c, expected) in expected.iter().take(channels).enumerate() {
+                    let offset = y * dst_stride + 4 * (channels * x + c);
+                    let value = f32::from_bits(u32::from_le_bytes([
+                        dst[offset],
+                        dst[offset + 1],
+                        dst[offset + 2],
+                        dst[offset + 3],
+                    ]));
+                    assert!((value - expected.clamp(0.0, 1.0)).abs() < 1e-5);
+                }
+            }
+
+            // Padding is left untouched
+            let line_end = y * dst_stride + 4 * channels * WIDTH;
+            assert!(dst[line_end..line_end + PADDING].iter().all(|&b| b == 0));
+        }
+    }
+}
+
+#[test]
+fn yuv_f32_planar_ok() {
+    bootstrap();
+
+    const WIDTH: usize = 7;
+    const HEIGHT: usize = 3;
+
+    let mut rng = rand::thread_rng();
+    let normalization = Normalization {
+        mean: [0.45, 0.01, -0.02],
+        std: [0.25, 0.125, 0.5],
+    };
+
+    for (&(pixel_format, depth, offsets), color_space, num_planes) in iproduct!(
+        [
+            (PixelFormat::Argb, 4, [1, 2, 3]),
+            (PixelFormat::Bgra, 4, [2, 1, 0]),
+            (PixelFormat::Bgr, 3, [2, 1, 0]),
+        ]
+        .iter(),
+        [ColorSpace::Bt601, ColorSpace::Bt709].iter(),
+        [1, 3].iter()
+    ) {
+        let src_format = ImageFormat {
+            pixel_format,
+            color_space: ColorSpace::Lrgb,
+            num_planes: 1,
+        };
+        let dst_format = ImageFormat {
+            pixel_format: PixelFormat::YuvF32Planar,
+            color_space: *color_space,
+            num_planes: *num_planes,
+        };
+
+        let src: Vec<u8> = (0..depth * WIDTH * HEIGHT)
+            .map(|_| rng.gen::<u8>())
+            .collect();
+        let mut plain = alloc_planes(WIDTH as u32, HEIGHT as u32, &dst_format);
+        let mut normalized = alloc_planes(WIDTH as u32, HEIGHT as u32, &dst_format);
+        {
+            let mut dst_buffers: Vec<&mut [u8]> =
+                plain.iter_mut().map(|plane| &mut plane[..]).collect();
+            assert!(convert_image(
+                WIDTH as u32,
+                HEIGHT as u32,
+                &src_format,
+                None,
+                &[&src],
+                &dst_format,
+                None,
+                &mut dst_buffers,
+            )
+            .is_ok());
+
+            let mut dst_buffers: Vec<&mut [u8]> =
+                normalized.iter_mut().map(|plane| &mut plane[..]).collect();
+            assert!(convert_image_normalized(
+                WIDTH as u32,
+                HEIGHT as u32,
+                &src_format,
+                None,
+                &[&src],
+                &dst_format,
+                None,
+                &mut dst_buffers,
+                &normalization,
+            )
+            .is_ok());
+        }
+
+        let (kr, kb) = match color_space {
+            ColorSpace::Bt601 => (0.299, 0.114),
+            _ => (0.2126, 0.0722),
+        };
+
+        let plane_size = 4 * WIDTH * HEIGHT;
+        let read = |planes: &[Vec<u8>], plane: usize, index: usize| {
+            let (buffer, offset) = if planes.len() == 1 {
+                (0, plane * plane_size + 4 * index)
+            } else {
+                (plane, 4 * index)
+            };
+            let bytes = &planes[buffer][offset..offset + 4];
+            f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        };
+
+        for index in 0..WIDTH * HEIGHT {
+            let pixel = &src[depth * index..];
+            let r = f32::from(pixel[offsets[0]]) / 255.0;
+            let g = f32::from(pixel[offsets[1]]) / 255.0;
+            let b = f32::from(pixel[offsets[2]]) / 255.0;
+            let l = kr * r + (1.0 - kr - kb) * g + kb * b;
+            let expected = [l, (b - l) / (2.0 - 2.0 * kb), (r - l) / (2.0 - 2.0 * kr)];
+
+            for (plane, &expected) in expected.iter().enumerate() {
+                assert!((read(&plain, plane, index) - expected).abs() < 1e-5);
+
+                let standardized =
+                    (expected - normalization.mean[plane]) / normalization.std[plane];
+                assert!((read(&normalized, plane, index) - standardized).abs() < 1e-4);
+            }
+        }
+    }
+
+    // Only rgb sources and finite, non zero standard deviations are accepted
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+    let nv12_format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt601,
+        num_planes: 1,
+    };
+    let yuv_format = |color_space| ImageFormat {
+        pixel_format: PixelFormat::YuvF32Planar,
+        color_space,
+        num_planes: 1,
+    };
+
+    let bgra = [0u8; 16];
+    let nv12 = [128u8; 6];
+    let mut dst = [0u8; 48];
+    let normalize = |src_format: &ImageFormat,
+                     src: &[u8],
+                     dst_format: &ImageFormat,
+                     dst: &mut [u8],
+                     normalization: &Normalization| {
+        convert_image_normalized(
+            2,
+            2,
+            src_format,
+            None,
+            &[src],
+            dst_format,
+            None,
+            &mut [dst],
+            normalization,
+        )
+        .err()
+        .map(|e| e as u32)
+    };
+
+    assert_eq!(
+        normalize(
+            &bgra_format,
+            &bgra,
+            &yuv_format(ColorSpace::Bt709),
+            &mut dst,
+            &Normalization::default()
+        ),
+        None
+    );
+    assert_eq!(
+        normalize(
+            &nv12_format,
+            &nv12,
+            &yuv_format(ColorSpace::Bt709),
+            &mut dst,
+            &Normalization::default()
+        ),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+    assert_eq!(
+        normalize(
+            &bgra_format,
+            &bgra,
+            &nv12_format,
+            &mut dst,
+            &Normalization::default()
+        ),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+    assert_eq!(
+        normalize(
+            &bgra_format,
+            &bgra,
+            &yuv_format(ColorSpace::Jfif),
+            &mut dst,
+            &Normalization::default()
+        ),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+    assert_eq!(
+        normalize(
+            &bgra_format,
+            &bgra,
+            &yuv_format(ColorSpace::Bt601),
+            &mut dst[..47],
+            &Normalization::default()
+        ),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+    for std in [0.0, f32::NAN, f32::INFINITY].iter() {
+        let normalization = Normalization {
+            std: [1.0, *std, 1.0],
+            ..Default::default()
+        };
+        assert_eq!(
+            normalize(
+                &bgra_format,
+                &bgra,
+                &yuv_format(ColorSpace::Bt601),
+                &mut dst,
+                &normalization
+            ),
+            Some(ErrorKind::InvalidValue as u32)
+        );
+    }
+
+    // Other sources are routed through Bgra
+    let planar_format = ImageFormat {
+        num_planes: 3,
+        ..yuv_format(ColorSpace::Bt601)
+    };
+    let mut routed = alloc_planes(2, 2, &planar_format);
+    let mut dst_buffers: Vec<&mut [u8]> = routed.iter_mut().map(|plane| &mut plane[..]).collect();
+    assert!(convert_image(
+        2,
+        2,
+        &nv12_format,
+        None,
+        &[&nv12],
+        &planar_format,
+        None,
+        &mut dst_buffers,
+    )
+    .is_ok());
+    for plane in routed.iter() {
+        assert!(plane
+            .chunks_exact(4)
+            .all(|x| f32::from_le_bytes([x[0], x[1], x[2], x[3]]).abs() <= 1.0));
+    }
+}
+
+#[test]
+fn adjustment_ok() {
+    use dcp::adjustment::{convert_adjusted_image, ColorAdjustment};
+
+    bootstrap();
+
+    const WIDTH: u32 = 16;
+    const HEIGHT: u32 = 8;
+    const PIXELS: usize = (WIDTH as usize) * (HEIGHT as usize);
+
+    let nv12_format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt709,
+        num_planes: 1,
+    };
+    let format = |pixel_format| ImageFormat {
+        pixel_format,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    // Random ycbcr samples can be out of gamut: generate them from rgb
+    let mut rng = rand::thread_rng();
+    let bgra: Vec<u8> = (0..4 * PIXELS).map(|_| rng.gen::<u8>()).collect();
+    let mut nv12 = vec![0u8; 3 * PIXELS / 2];
+    assert!(convert_image(
+        WIDTH,
+        HEIGHT,
+        &format(PixelFormat::Bgra),
+        None,
+        &[&bgra],
+        &nv12_format,
+        None,
+        &mut [&mut nv12],
+    )
+    .is_ok());
+
+    let adjusted = |pixel_format, bpp: usize, adjustment: &ColorAdjustment| {
+        let mut dst = vec![0u8; bpp * PIXELS];
+        convert_adjusted_image(
+            WIDTH,
+            HEIGHT,
+            &nv12_format,
+            None,
+            &[&nv12],
+            &format(pixel_format),
+            None,
+            &mut [&mut dst],
+            adjustment,
+        )
+        .map(|_| dst)
+    };
+    let converted = |pixel_format, bpp: usize| {
+        let mut dst = vec![0u8; bpp * PIXELS];
+        assert!(convert_image(
+            WIDTH,
+            HEIGHT,
+            &nv12_format,
+            None,
+            &[&nv12],
+            &format(pixel_format),
+            None,
+            &mut [&mut dst],
+        )
+        .is_ok());
+        dst
+    };
+    let to_f32 = |bytes: &[u8]| -> Vec<f32> {
+        bytes
+            .chunks_exact(4)
+            .map(|c| f32::from_bits(u32::from_le_bytes([c[0], c[1], c[2], c[3]])))
+            .collect()
+    };
+
+    // The default adjustment leaves the image unchanged
+    let identity = ColorAdjustment::default();
+    assert_eq!(
+        adjusted(PixelFormat::RgbF32, 12, &identity).unwrap(),
+        converted(PixelFormat::RgbF32, 12)
+    );
+    let bgra = adjusted(PixelFormat::Bgra, 4, &identity).unwrap();
+    for (&x, &y) in bgra.iter().zip(converted(PixelFormat::Bgra, 4).iter()) {
+        assert!((i32::from(x) - i32::from(y)).abs() <= 2);
+    }
+
+    // A full turn of the hue does not change the image
+    let turned = to_f32(
+        &adjusted(
+            PixelFormat::RgbaF32,
+            16,
+            &ColorAdjustment {
+                hue: 360.0,
+                ..identity
+            },
+        )
+        .unwrap(),
+    );
+    let expected = to_f32(&converted(PixelFormat::RgbaF32, 16));
+    for (x, y) in turned.iter().zip(expected.iter()) {
+        assert!((x - y).abs() < 1e-5);
+    }
+
+    // Without saturation the image is gray
+    let gray = adjusted(
+        PixelFormat::Bgra,
+        4,
+        &ColorAdjustment {
+            saturation: 0.0,
+            ..identity
+        },
+    )
+    .unwrap();
+    for (pixel, luma) in gray.chunks_exact(4).zip(nv12.iter()) {
+        let expected = (255.0 * ((f32::from(*luma) - 16.0) / 219.0).clamp(0.0, 1.0) + 0.5) as u8;
+        assert_eq!(pixel, &[expected, expected, expected, 255][..]);
+    }
+
+    // Without contrast and saturation the image is flat: white at full brightness,
+    // black otherwise
+    let white = adjusted(
+        PixelFormat::Bgra,
+        4,
+        &ColorAdjustment {
+            brightness: 1.0,
+            contrast: 0.0,
+            saturation: 0.0,
+            ..identity
+        },
+    )
+    .unwrap();
+    assert!(white.iter().all(|&x| x == 255));
+
+    let black = adjusted(
+        PixelFormat::Bgra,
+        4,
+        &ColorAdjustment {
+            contrast: 0.0,
+            saturation: 0.0,
+            ..identity
+        },
+    )
+    .unwrap();
+    for pixel in black.chunks_exact(4) {
+        assert_eq!(pixel, &[0, 0, 0, 255][..]);
+    }
+
+    // Errors
+    for invalid in [
+        ColorAdjustment {
+            brightness: 1.5,
+            ..identity
+        },
+        ColorAdjustment {
+            contrast: -1.0,
+            ..identity
+        },
+        ColorAdjustment {
+            saturation: f32::NAN,
+            ..identity
+        },
+        ColorAdjustment {
+            hue: f32::INFINITY,
+            ..identity
+        },
+    ]
+    .iter()
+    {
+        match adjusted(PixelFormat::Bgra, 4, invalid) {
+            Err(ErrorKind::InvalidValue) => {}
+            _ => assert!(false),
+        }
+    }
+
+    match adjusted(PixelFormat::Rgb, 3, &identity) {
+        Err(ErrorKind::InvalidOperation) => {}
+        _ => assert!(false),
+    }
+
+    let mut small = vec![0u8; 4 * PIXELS - 1];
+    match convert_adjusted_image(
+        WIDTH,
+        HEIGHT,
+        &nv12_format,
+        None,
+        &[&nv12],
+        &format(PixelFormat::Bgra),
+        None,
+        &mut [&mut small],
+        &identity,
+    ) {
+        Err(ErrorKind::NotEnoughData) => {}
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn gamut_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 3;
+    const HEIGHT: u32 = 2;
+
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let convert =
+        |src, dst, mapping, format: &ImageFormat, strides: Option<&[usize]>, buffer: &mut [u8]| {
+            gamut::convert_primaries_in_place(
+                src,
+                dst,
+                mapping,
+                WIDTH,
+                HEIGHT,
+                format,
+                strides,
+                &mut [buffer],
+            )
+            .err()
+            .map(|e| e as u32)
+        };
+
+    // Neutral colors are preserved, padding and alpha are left untouched
+    let grays = [0u8, 16, 64, 128, 200, 255];
+    let mut bgra = vec![0xAAu8; 2 * 16];
+    for (i, &gray) in grays.iter().enumerate() {
+        let offset = 16 * (i / 3) + 4 * (i % 3);
+        bgra[offset..offset + 4].copy_from_slice(&[gray, gray, gray, 7]);
+    }
+
+    let expected = bgra.clone();
+    for &(src, dst) in &[
+        (gamut::Primaries::Bt601, gamut::Primaries::Bt709),
+        (gamut::Primaries::Bt601Pal, gamut::Primaries::Bt2020),
+        (gamut::Primaries::Bt2020, gamut::Primaries::Bt709),
+    ] {
+        assert_eq!(
+            convert(
+                src,
+                dst,
+                gamut::GamutMapping::Clip,
+                &bgra_format,
+                Some(&[16]),
+                &mut bgra
+            ),
+            None
+        );
+        for (&value, &reference) in bgra.iter().zip(expected.iter()) {
+            assert!((i32::from(value) - i32::from(reference)).abs() <= 1);
+        }
+    }
+
+    // Matrices between primaries are inverse of each other
+    let forward = gamut::get_conversion_matrix(gamut::Primaries::Bt709, gamut::Primaries::Bt2020);
+    let backward = gamut::get_conversion_matrix(gamut::Primaries::Bt2020, gamut::Primaries::Bt709);
+    for (i, row) in forward.iter().enumerate() {
+        for j in 0..3 {
+            let value: f32 = row.iter().zip(backward.iter()).map(|(x, y)| x * y[j]).sum();
+            let identity = if i == j { 1.0 } else { 0.0 };
+            assert!((value - identity).abs() < 1e-4);
+        }
+    }
+
+    // BT.709 colors round trip through the wider BT.2020 gamut
+    let rgbf32_format = ImageFormat {
+        pixel_format: PixelFormat::RgbF32,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let to_floats = |buffer: &[u8]| {
+        buffer
+            .chunks(4)
+            .map(|x| f32::from_bits(u32::from_le_bytes([x[0], x[1], x[2], x[3]])))
+            .collect::<Vec<f32>>()
+    };
+
+    let mut rng = rand::thread_rng();
+    let expected: Vec<f32> = (0..18).map(|_| rng.gen::<f32>()).collect();
+    let mut rgb: Vec<u8> = expected
+        .iter()
+        .flat_map(|x| x.to_bits().to_le_bytes().to_vec())
+        .collect();
+    assert_eq!(
+        convert(
+            gamut::Primaries::Bt709,
+            gamut::Primaries::Bt2020,
+            gamut::GamutMapping::Clip,
+            &rgbf32_format,
+            None,
+            &mut rgb
+        ),
+        None
+    );
+    assert_eq!(
+        convert(
+            gamut::Primaries::Bt2020,
+            gamut::Primaries::Bt709,
+            gamut::GamutMapping::Clip,
+            &rgbf32_format,
+            None,
+            &mut rgb
+        ),
+        None
+    );
+    for (&value, &reference) in to_floats(&rgb).iter().zip(expected.iter()) {
+        assert!((value - reference).abs() < 1e-3);
+    }
+
+    // Pure BT.2020 green is out of the BT.709 gamut
+    let rgbaf32_format = ImageFormat {
+        pixel_format: PixelFormat::RgbaF32,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let green: Vec<u8> = [0.0f32, 1.0, 0.0, 0.5]
+        .iter()
+        .cycle()
+        .take(24)
+        .flat_map(|x| x.to_bits().to_le_bytes().to_vec())
+        .collect();
+    let read = |buffer: &[u8], i: usize| {
+        f32::from_bits(u32::from_le_bytes([
+            buffer[4 * i],
+            buffer[4 * i + 1],
+            buffer[4 * i + 2],
+            buffer[4 * i + 3],
+        ]))
+    };
+
+    let map_green = |mapping| {
+        let mut buffer = green.clone();
+        assert_eq!(
+            convert(
+                gamut::Primaries::Bt2020,
+                gamut::Primaries::Bt709,
+                mapping,
+                &rgbaf32_format,
+                None,
+                &mut buffer
+            ),
+            None
+        );
+
+        buffer
+    };
+
+    let clipped = map_green(gamut::GamutMapping::Clip);
+    let compressed = map_green(gamut::GamutMapping::Compress);
+    for pixel in 0..6 {
+        let i = 4 * pixel;
+        assert_eq!(read(&clipped, i), 0.0);
+        assert_eq!(read(&clipped, i + 1), 1.0);
+        assert_eq!(read(&clipped, i + 2), 0.0);
+        assert_eq!(read(&clipped, i + 3), 0.5);
+
+        // Compression trades saturation for luminance and hue
+        assert!(read(&compressed, i) > 0.0);
+        assert!(read(&compressed, i + 1) < 1.0);
+        assert!(read(&compressed, i + 2) > 0.0);
+        assert!(read(&compressed, i + 1) > read(&compressed, i));
+        assert_eq!(read(&compressed, i + 3), 0.5);
+    }
+
+    // Errors
+    let nv12_format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt601,
+        num_planes: 1,
+    };
+
+    let bgra_bt601_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Bt601,
+        num_planes: 1,
+    };
+
+    let mut buffer = vec![0u8; 32];
+    let check_error = |format: &ImageFormat, strides: Option<&[usize]>, buffer: &mut [u8]| {
+        convert(
+            gamut::Primaries::Bt601,
+            gamut::Primaries::Bt709,
+            gamut::GamutMapping::Clip,
+            format,
+            strides,
+            buffer,
+        )
+    };
+
+    assert_eq!(
+        check_error(&nv12_format, None, &mut buffer),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+    assert_eq!(
+        check_error(&bgra_bt601_format, None, &mut buffer),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(&bgra_format, Some(&[8]), &mut buffer),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(&bgra_format, Some(&[]), &mut buffer),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+    assert_eq!(
+        check_error(&bgra_format, None, &mut buffer[..23]),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+}
+
+#[test]
+fn lut_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 130;
+    const PIXELS: usize = (WIDTH as usize) * (HEIGHT as usize);
+
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let nv12_format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt709,
+        num_planes: 1,
+    };
+
+    let rgbf32_format = ImageFormat {
+        pixel_format: PixelFormat::RgbF32,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let mut rng = rand::thread_rng();
+    let bgra: Vec<u8> = (0..4 * PIXELS).map(|_| rng.gen::<u8>()).collect();
+    let mut nv12 = vec![0u8; 3 * PIXELS / 2];
+    assert!(convert_image(
+        WIDTH,
+        HEIGHT,
+        &bgra_format,
+        None,
+        &[&bgra],
+        &nv12_format,
+        None,
+        &mut [&mut nv12],
+    )
+    .is_ok());
+
+    let mut reference = vec![0u8; 4 * PIXELS];
+    assert!(convert_image(
+        WIDTH,
+        HEIGHT,
+        &nv12_format,
+        None,
+        &[&nv12],
+        &bgra_format,
+        None,
+        &mut [&mut reference],
+    )
+    .is_ok());
+
+    // Per channel tables on 8-bit samples, with padded destination lines
+    let stride = 4 * (WIDTH as usize) + 8;
+    let identity: Vec<u16> = (0..256).collect();
+    let inverse: Vec<u16> = (0..256).rev().collect();
+    let constant = vec![42u16; 256];
+    let lut = lut::Lut1d::new(8, &inverse, &identity, &constant).unwrap();
+    let mut output = vec![0xAAu8; stride * (HEIGHT as usize)];
+    assert!(lut::convert_image_with_lut(
+        WIDTH,
+        HEIGHT,
+        &nv12_format,
+        None,
+        &[&nv12],
+        &bgra_format,
+        Some(&[stride]),
+        &mut [&mut output],
+        &lut,
+    )
+    .is_ok());
+
+    for (line, expected) in output
+        .chunks(stride)
+        .zip(reference.chunks(4 * (WIDTH as usize)))
+    {
+        for (pixel, reference) in line.chunks(4).zip(expected.chunks(4)) {
+            assert_eq!(pixel[0], 42);
+            assert_eq!(pixel[1], reference[1]);
+            assert_eq!(pixel[2], 255 - reference[2]);
+            assert_eq!(pixel[3], reference[3]);
+        }
+
+        assert!(line[4 * (WIDTH as usize)..].iter().all(|&x| x == 0xAA));
+    }
+
+    // 10-bit identity leaves 8-bit samples unchanged
+    let lut = lut::Lut1d::from_fn(10, |x| x).unwrap();
+    assert_eq!(lut.bits(), 10);
+    let mut output = vec![0u8; 4 * PIXELS];
+    assert!(lut::convert_image_with_lut(
+        WIDTH,
+        HEIGHT,
+        &nv12_format,
+        None,
+        &[&nv12],
+        &bgra_format,
+        None,
+        &mut [&mut output],
+        &lut,
+    )
+    .is_ok());
+    assert_eq!(output, reference);
+
+    // Float samples are quantized to the nearest entry
+    let read = |buffer: &[u8]| {
+        buffer
+            .chunks(4)
+            .map(|x| f32::from_bits(u32::from_le_bytes([x[0], x[1], x[2], x[3]])))
+            .collect::<Vec<f32>>()
+    };
+
+    let mut float_reference = vec![0u8; 12 * PIXELS];
+    assert!(convert_image(
+        WIDTH,
+        HEIGHT,
+        &nv12_format,
+        None,
+        &[&nv12],
+        &rgbf32_format,
+        None,
+        &mut [&mut float_reference],
+    )
+    .is_ok());
+
+    let lut = lut::Lut1d::from_fn(10, |x| 1.0 - x).unwrap();
+    let mut output = vec![0u8; 12 * PIXELS];
+    assert!(lut::convert_image_with_lut(
+        WIDTH,
+        HEIGHT,
+        &nv12_format,
+        None,
+        &[&nv12],
+        &rgbf32_format,
+        None,
+        &mut [&mut output],
+        &lut,
+    )
+    .is_ok());
+
+    for (&value, &reference) in read(&output).iter().zip(read(&float_reference).iter()) {
+        assert!((value - (1.0 - reference)).abs() <= 1.0 / 1023.0);
+    }
+
+    // Errors
+    assert_eq!(
+        lut::Lut1d::from_fn(12, |x| x).err().map(|e| e as u32),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        lut::Lut1d::new(8, &identity[..255], &identity, &identity)
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    let overflow = vec![256u16; 256];
+    assert_eq!(
+        lut::Lut1d::new(8, &identity, &overflow, &identity)
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+
+    let lut = lut::Lut1d::new(8, &identity, &identity, &identity).unwrap();
+    assert_eq!(
+        lut::convert_image_with_lut(
+            WIDTH,
+            HEIGHT,
+            &bgra_format,
+            None,
+            &[&bgra],
+            &nv12_format,
+            None,
+            &mut [&mut nv12],
+            &lut,
+        )
+        .err()
+        .map(|e| e as u32),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+    assert_eq!(
+        lut::convert_image_with_lut(
+            WIDTH,
+            HEIGHT,
+            &nv12_format,
+            None,
+            &[&nv12],
+            &bgra_format,
+            None,
+            &mut [&mut output[..4 * PIXELS - 1]],
+            &lut,
+        )
+        .err()
+        .map(|e| e as u32),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+}
+
+#[test]
+fn composite_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 70;
+    const PIXELS: usize = (WIDTH as usize) * (HEIGHT as usize);
+
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let rgb_format = ImageFormat {
+        pixel_format: PixelFormat::Rgb,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let nv12_format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt709,
+        num_planes: 1,
+    };
+
+    // Padded source lines
+    let src_stride = 4 * (WIDTH as usize) + 8;
+    let mut rng = rand::thread_rng();
+    let bgra: Vec<u8> = (0..src_stride * (HEIGHT as usize))
+        .map(|_| rng.gen::<u8>())
+        .collect();
+
+    let blend = |color: u8, background: u8, alpha: u8| {
+        let alpha = u32::from(alpha);
+        ((u32::from(color) * alpha + u32::from(background) * (255 - alpha) + 127) / 255) as u8
+    };
+
+    let backgrounds = [
+        composite::Background::Color([10, 200, 30]),
+        composite::Background::Checkerboard {
+            size: 3,
+            light: [204, 204, 204],
+            dark: [153, 153, 153],
+        },
+    ];
+
+    for background in backgrounds.iter() {
+        let mut opaque = vec![0u8; 4 * PIXELS];
+        for (y, (src, dst)) in bgra
+            .chunks(src_stride)
+            .zip(opaque.chunks_mut(4 * (WIDTH as usize)))
+            .enumerate()
+        {
+            for (x, (s, d)) in src.chunks(4).zip(dst.chunks_mut(4)).enumerate() {
+                let color = match *background {
+                    composite::Background::Color(color) => color,
+                    composite::Background::Checkerboard { size, light, dark } => {
+                        if ((x / size as usize) + (y / size as usize)).is_multiple_of(2) {
+                            light
+                        } else {
+                            dark
+                        }
+                    }
+                };
+
+                d[0] = blend(s[0], color[2], s[3]);
+                d[1] = blend(s[1], color[1], s[3]);
+                d[2] = blend(s[2], color[0], s[3]);
+                d[3] = 255;
+            }
+        }
+
+        for (dst_format, bpp) in [(&nv12_format, 3), (&rgb_format, 6)].iter() {
+            let size = bpp * PIXELS / 2;
+            let mut expected = vec![0u8; size];
+            assert!(convert_image(
+                WIDTH,
+                HEIGHT,
+                &bgra_format,
+                None,
+                &[&opaque],
+                dst_format,
+                None,
+                &mut [&mut expected],
+            )
+            .is_ok());
+
+            let mut output = vec![0u8; size];
+            assert!(composite::convert_composited_image(
+                WIDTH,
+                HEIGHT,
+                &bgra_format,
+                Some(&[src_stride]),
+                &[&bgra],
+                dst_format,
+                None,
+                &mut [&mut output],
+                background,
+            )
+            .is_ok());
+            assert_eq!(output, expected);
+        }
+    }
+
+    // Errors
+    let check_error = |src_format: &ImageFormat, src: &[u8], background: &composite::Background| {
+        let mut output = vec![0u8; 4 * PIXELS];
+        composite::convert_composited_image(
+            WIDTH,
+            HEIGHT,
+            src_format,
+            None,
+            &[src],
+            &nv12_format,
+            None,
+            &mut [&mut output],
+            background,
+        )
+        .err()
+        .map(|e| e as u32)
+    };
+
+    let color = composite::Background::Color([0, 0, 0]);
+    assert_eq!(
+        check_error(&rgb_format, &bgra, &color),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+    assert_eq!(
+        check_error(
+            &bgra_format,
+            &bgra,
+            &composite::Background::Checkerboard {
+                size: 0,
+                light: [0, 0, 0],
+                dark: [0, 0, 0],
+            }
+        ),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(&bgra_format, &bgra[..4 * PIXELS - 1], &color),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+}
+
+#[test]
+fn blend_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 37;
+    const HEIGHT: u32 = 5;
+
+    let div_255 = |x: u32| {
+        let x = x + 128;
+        (x + (x >> 8)) >> 8
+    };
+
+    let src_stride = 4 * (WIDTH as usize) + 4;
+    let dst_stride = 4 * (WIDTH as usize) + 12;
+    let mut rng = rand::thread_rng();
+    for (pixel_format, alpha_mode) in iproduct!(
+        [PixelFormat::Bgra, PixelFormat::Rgba].iter(),
+        [
+            composite::AlphaMode::Straight,
+            composite::AlphaMode::Premultiplied
+        ]
+        .iter()
+    ) {
+        let format = ImageFormat {
+            pixel_format: *pixel_format,
+            color_space: ColorSpace::Lrgb,
+            num_planes: 1,
+        };
+
+        let mut src: Vec<u8> = (0..src_stride * (HEIGHT as usize))
+            .map(|_| rng.gen::<u8>())
+            .collect();
+        let dst: Vec<u8> = (0..dst_stride * (HEIGHT as usize))
+            .map(|_| rng.gen::<u8>())
+            .collect();
+
+        // Fully transparent and fully opaque pixels
+        src[3] = 0;
+        src[7] = 255;
+        if *alpha_mode == composite::AlphaMode::Premultiplied {
+            for pixel in src.chunks_mut(4) {
+                let alpha = u32::from(pixel[3]);
+                for x in pixel[..3].iter_mut() {
+                    *x = div_255(u32::from(*x) * alpha) as u8;
+                }
+            }
+        }
+
+        let mut expected = dst.clone();
+        for (s_line, d_line) in src.chunks(src_stride).zip(expected.chunks_mut(dst_stride)) {
+            for (s, d) in s_line[..4 * (WIDTH as usize)]
+                .chunks(4)
+                .zip(d_line.chunks_mut(4))
+            {
+                let alpha = u32::from(s[3]);
+                for i in 0..4 {
+                    let x = u32::from(s[i]);
+                    let y = u32::from(d[i]) * (255 - alpha);
+                    d[i] = match alpha_mode {
+                        composite::AlphaMode::Premultiplied => x + div_255(y),
+                        composite::AlphaMode::Straight if i == 3 => div_255(255 * alpha + y),
+                        composite::AlphaMode::Straight => div_255(x * alpha + y),
+                    } as u8;
+                }
+            }
+        }
+
+        let mut output = dst.clone();
+        assert!(composite::blend_images(
+            WIDTH,
+            HEIGHT,
+            &format,
+            Some(&[src_stride]),
+            &[&src],
+            Some(&[dst_stride]),
+            &mut [&mut output],
+            *alpha_mode,
+        )
+        .is_ok());
+        assert_eq!(output, expected);
+        assert_eq!(&output[..4], &dst[..4]);
+    }
+
+    // Errors
+    let check_error = |format: &ImageFormat, src: &[u8], dst: &mut [u8]| {
+        composite::blend_images(
+            WIDTH,
+            HEIGHT,
+            format,
+            None,
+            &[src],
+            None,
+            &mut [dst],
+            composite::AlphaMode::Straight,
+        )
+        .err()
+        .map(|e| e as u32)
+    };
+
+    let size = 4 * (WIDTH as usize) * (HEIGHT as usize);
+    let src = vec![0u8; size];
+    let mut dst = vec![0u8; size];
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    assert_eq!(
+        check_error(
+            &ImageFormat {
+                pixel_format: PixelFormat::Argb,
+                color_space: ColorSpace::Lrgb,
+                num_planes: 1,
+            },
+            &src,
+            &mut dst
+        ),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+    assert_eq!(
+        check_error(
+            &ImageFormat {
+                pixel_format: PixelFormat::Bgra,
+                color_space: ColorSpace::Bt709,
+                num_planes: 1,
+            },
+            &src,
+            &mut dst
+        ),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(&bgra_format, &src[..size - 1], &mut dst),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+    assert_eq!(
+        check_error(&bgra_format, &src, &mut dst[..size - 1]),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+}
+
+#[test]
+fn overlay_sprite_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 32;
+    const HEIGHT: u32 = 16;
+    const SPRITE_WIDTH: u32 = 10;
+    const SPRITE_HEIGHT: u32 = 6;
+    const PIXELS: usize = (WIDTH as usize) * (HEIGHT as usize);
+    const SPRITE_PIXELS: usize = (SPRITE_WIDTH as usize) * (SPRITE_HEIGHT as usize);
+
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let mut rng = rand::thread_rng();
+    // Pixel format, number of planes, chroma subsampling shift and chroma sample size
+    for (&(pixel_format, num_planes, chroma_shift, depth), color_space) in iproduct!(
+        [
+            (PixelFormat::Nv12, 2, 1, 2),
+            (PixelFormat::I420, 3, 1, 1),
+            (PixelFormat::I444, 3, 0, 1)
+        ]
+        .iter(),
+        [ColorSpace::Bt601, ColorSpace::Bt709].iter()
+    ) {
+        let format = ImageFormat {
+            pixel_format,
+            color_space: *color_space,
+            num_planes,
+        };
+
+        let bgra: Vec<u8> = (0..4 * PIXELS).map(|_| rng.gen::<u8>()).collect();
+        let mut frame = alloc_planes(WIDTH, HEIGHT, &format);
+        {
+            let mut planes: Vec<&mut [u8]> = frame.iter_mut().map(|p| &mut p[..]).collect();
+            assert!(convert_image(
+                WIDTH,
+                HEIGHT,
+                &bgra_format,
+                None,
+                &[&bgra],
+                &format,
+                None,
+                &mut planes,
+            )
+            .is_ok());
+        }
+
+        let overlay = |frame: &[Vec<u8>], x: i32, y: i32, sprite: &[u8]| {
+            let mut frame = frame.to_vec();
+            let mut planes: Vec<&mut [u8]> = frame.iter_mut().map(|p| &mut p[..]).collect();
+            assert!(composite::overlay_sprite(
+                x,
+                y,
+                SPRITE_WIDTH,
+                SPRITE_HEIGHT,
+                &bgra_format,
+                None,
+                &[sprite],
+                WIDTH,
+                HEIGHT,
+                &format,
+                None,
+                &mut planes,
+            )
+            .is_ok());
+            frame
+        };
+
+        // A fully transparent sprite leaves the frame untouched
+        let transparent = vec![0u8; 4 * SPRITE_PIXELS];
+        assert_eq!(overlay(&frame, 3, 5, &transparent), frame);
+
+        // An opaque sprite replaces the samples it covers
+        let mut sprite: Vec<u8> = (0..4 * SPRITE_PIXELS).map(|_| rng.gen::<u8>()).collect();
+        for pixel in sprite.chunks_mut(4) {
+            pixel[3] = 255;
+        }
+
+        let mut sprite_yuv = alloc_planes(SPRITE_WIDTH, SPRITE_HEIGHT, &format);
+        {
+            let mut planes: Vec<&mut [u8]> = sprite_yuv.iter_mut().map(|p| &mut p[..]).collect();
+            assert!(convert_image(
+                SPRITE_WIDTH,
+                SPRITE_HEIGHT,
+                &bgra_format,
+                None,
+                &[&sprite],
+                &format,
+                None,
+                &mut planes,
+            )
+            .is_ok());
+        }
+
+        let (x, y) = (20, 8);
+        let output = overlay(&frame, x, y, &sprite);
+        let w = WIDTH as usize;
+        let sw = SPRITE_WIDTH as usize;
+        let sh = SPRITE_HEIGHT as usize;
+        for py in 0..(HEIGHT as usize) {
+            for px in 0..w {
+                let inside = (20..20 + sw).contains(&px) && (8..8 + sh).contains(&py);
+                let value = output[0][py * w + px];
+                if inside {
+                    let expected = sprite_yuv[0][(py - 8) * sw + px - 20];
+                    assert!((i32::from(value) - i32::from(expected)).abs() <= 1);
+                } else {
+                    assert_eq!(value, frame[0][py * w + px]);
+                }
+            }
+        }
+
+        let cw = w >> chroma_shift;
+        let csw = sw >> chroma_shift;
+        for plane in 1..frame.len() {
+            for cy in 0..((HEIGHT as usize) >> chroma_shift) {
+                for cx in 0..(depth * cw) {
+                    let (bx, by) = ((cx / depth) << chroma_shift, cy << chroma_shift);
+                    let inside = (20..20 + sw).contains(&bx) && (8..8 + sh).contains(&by);
+                    let value = output[plane][cy * depth * cw + cx];
+                    if inside {
+                        let sx = cx - depth * ((x as usize) >> chroma_shift);
+                        let sy = cy - ((y as usize) >> chroma_shift);
+                        let expected = sprite_yuv[plane][sy * depth * csw + sx];
+                        assert!((i32::from(value) - i32::from(expected)).abs() <= 2);
+                    } else {
+                        assert_eq!(value, frame[plane][cy * depth * cw + cx]);
+                    }
+                }
+            }
+        }
+
+        // Sprites partially or totally outside the frame
+        for &(x, y) in &[(-4, -3), (27, 13), (-10, 0), (0, 16), (100, -100)] {
+            let output = overlay(&frame, x, y, &sprite);
+            for py in 0..(HEIGHT as i32) {
+                for px in 0..(WIDTH as i32) {
+                    let inside = px >= x
+                        && px < x + (SPRITE_WIDTH as i32)
+                        && py >= y
+                        && py < y + (SPRITE_HEIGHT as i32);
+                    let index = (py as usize) * w + (px as usize);
+                    if !inside {
+                        assert_eq!(output[0][index], frame[0][index]);
+                    }
+                }
+            }
+        }
+    }
+
+    // Errors
+    let nv12_format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt601,
+        num_planes: 1,
+    };
+
+    let sprite = vec![0u8; 4 * SPRITE_PIXELS];
+    let mut frame = vec![0u8; 3 * PIXELS / 2];
+    let check_error =
+        |sprite_format: &ImageFormat, sprite: &[u8], format: &ImageFormat, frame: &mut [u8]| {
+            composite::overlay_sprite(
+                0,
+                0,
+                SPRITE_WIDTH,
+                SPRITE_HEIGHT,
+                sprite_format,
+                None,
+                &[sprite],
+                WIDTH,
+                HEIGHT,
+                format,
+                None,
+                &mut [frame],
+            )
+            .err()
+            .map(|e| e as u32)
+        };
+
+    let rgb_format = ImageFormat {
+        pixel_format: PixelFormat::Rgb,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    assert_eq!(
+        check_error(&rgb_format, &sprite, &nv12_format, &mut frame),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+    assert_eq!(
+        check_error(&bgra_format, &sprite, &bgra_format, &mut frame),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+    assert_eq!(
+        check_error(
+            &bgra_format,
+            &sprite,
+            &ImageFormat {
+                pixel_format: PixelFormat::Nv12,
+                color_space: ColorSpace::Lrgb,
+                num_planes: 1,
+            },
+            &mut frame
+        ),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(&bgra_format, &sprite[1..], &nv12_format, &mut frame),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+    assert_eq!(
+        check_error(&bgra_format, &sprite, &nv12_format, &mut frame[1..]),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+}
+
+#[test]
+fn chroma_key_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 4;
+    const HEIGHT: u32 = 2;
+
+    let rgb_format = ImageFormat {
+        pixel_format: PixelFormat::Rgb,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let rgba_format = ImageFormat {
+        pixel_format: PixelFormat::Rgba,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    // Padded source lines
+    let rgb: [u8; 30] = [
+        0, 255, 0, 5, 250, 5, 0, 255, 11, 0, 235, 0, 0xAA, 0xAA, 0xAA, //
+        255, 255, 255, 10, 245, 10, 0, 0, 0, 20, 255, 0, 0xAA, 0xAA, 0xAA,
+    ];
+
+    let key_image = |key: &composite::ChromaKey,
+                     src_format: &ImageFormat,
+                     src_strides: Option<&[usize]>,
+                     src: &[u8],
+                     dst_format: &ImageFormat| {
+        let mut output = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+        assert!(composite::chroma_key(
+            WIDTH,
+            HEIGHT,
+            src_format,
+            src_strides,
+            &[src],
+            dst_format,
+            None,
+            &mut [&mut output],
+            key,
+        )
+        .is_ok());
+        output
+    };
+
+    // Hard edge
+    let key = composite::ChromaKey {
+        color: [0, 255, 0],
+        tolerance: 10,
+        softness: 0,
+    };
+
+    let output = key_image(&key, &rgb_format, Some(&[15]), &rgb, &rgba_format);
+    let alphas: Vec<u8> = output.chunks(4).map(|pixel| pixel[3]).collect();
+    assert_eq!(alphas, [0, 0, 255, 255, 255, 0, 255, 255]);
+    assert_eq!(&output[..3], &rgb[..3]);
+    assert_eq!(&output[16..19], &rgb[15..18]);
+
+    // Soft edge
+    let key = composite::ChromaKey {
+        color: [0, 255, 0],
+        tolerance: 10,
+        softness: 20,
+    };
+
+    let output = key_image(&key, &rgb_format, Some(&[15]), &rgb, &rgba_format);
+    let alphas: Vec<u8> = output.chunks(4).map(|pixel| pixel[3]).collect();
+    assert_eq!(alphas, [0, 0, 13, 128, 255, 0, 255, 128]);
+
+    // Source alpha is kept outside the key, destination channel order is honored
+    let bgra: Vec<u8> = rgb
+        .chunks(15)
+        .flat_map(|line| {
+            line[..12]
+                .chunks(3)
+                .flat_map(|p| vec![p[2], p[1], p[0], 100])
+        })
+        .collect();
+    let output = key_image(&key, &bgra_format, None, &bgra, &bgra_format);
+    let alphas: Vec<u8> = output.chunks(4).map(|pixel| pixel[3]).collect();
+    assert_eq!(alphas, [0, 0, 5, 50, 100, 0, 100, 50]);
+    for (pixel, source) in output.chunks(4).zip(bgra.chunks(4)) {
+        assert_eq!(&pixel[..3], &source[..3]);
+    }
+
+    // Errors
+    let mut output = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+    let mut check_error =
+        |src_format: &ImageFormat, src: &[u8], dst_format: &ImageFormat, size: usize| {
+            composite::chroma_key(
+                WIDTH,
+                HEIGHT,
+                src_format,
+                None,
+                &[src],
+                dst_format,
+                None,
+                &mut [&mut output[..size]],
+                &key,
+            )
+            .err()
+            .map(|e| e as u32)
+        };
+
+    let nv12_format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt601,
+        num_planes: 1,
+    };
+
+    assert_eq!(
+        check_error(&rgb_format, &rgb, &rgb_format, 32),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+    assert_eq!(
+        check_error(&nv12_format, &rgb, &rgba_format, 32),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+    assert_eq!(
+        check_error(
+            &ImageFormat {
+                pixel_format: PixelFormat::Rgb,
+                color_space: ColorSpace::Bt709,
+                num_planes: 1,
+            },
+            &rgb,
+            &rgba_format,
+            32
+        ),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(&rgb_format, &rgb[..23], &rgba_format, 32),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+    assert_eq!(
+        check_error(&rgb_format, &rgb, &rgba_format, 31),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+}
+
+#[test]
+fn format_display_ok() {
+    let nv12 = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt601,
+        num_planes: 2,
+    };
+    assert_eq!(nv12.to_string(), "Nv12 Bt601, 2 planes");
+    assert_eq!(
+        format!("{:?}", nv12),
+        "ImageFormat { pixel_format: Nv12, color_space: Bt601, num_planes: 2 }"
+    );
+    assert_eq!(
+        nv12.layout(640, 480).to_string(),
+        "Nv12 Bt601, 2 planes, 640x480, strides [640, 640]"
+    );
+
+    // Planes sharing a buffer report the stride of the buffer only
+    let nv12_single = ImageFormat {
+        num_planes: 1,
+        ..nv12
+    };
+    assert_eq!(
+        nv12_single.layout(640, 480).to_string(),
+        "Nv12 Bt601, 1 plane, 640x480, strides [640]"
+    );
+
+    let p010 = ImageFormat {
+        pixel_format: PixelFormat::P010,
+        color_space: ColorSpace::Bt2020Pq,
+        num_planes: 2,
+    };
+    assert_eq!(
+        p010.layout(64, 2).to_string(),
+        "P010 Bt2020Pq, 2 planes, 64x2, strides [128, 128]"
+    );
+
+    let bgr = ImageFormat {
+        pixel_format: PixelFormat::Bgr,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+    assert_eq!(
+        bgr.layout(33, 3).to_string(),
+        "Bgr Lrgb, 1 plane, 33x3, strides [99]"
+    );
+
+    // Unsupported sizes and plane counts
+    assert_eq!(
+        nv12.layout(641, 480).to_string(),
+        "Nv12 Bt601, 2 planes, 641x480, invalid size"
+    );
+    let bgr_planar = ImageFormat {
+        num_planes: 3,
+        ..bgr
+    };
+    assert_eq!(
+        bgr_planar.layout(32, 32).to_string(),
+        "Bgr Lrgb, 3 planes, 32x32, invalid size"
+    );
+}
+
+#[test]
+fn fourcc_ok() {
+    for pixel_format in PIXEL_FORMATS.iter() {
+        match pixel_format.to_fourcc() {
+            Some(fourcc) => {
+                assert_eq!(
+                    PixelFormat::from_fourcc(&fourcc).map(|pf| pf as u32),
+                    Some(*pixel_format as u32)
+                );
+            }
+            None => match pixel_format {
+                PixelFormat::RgbF32
+                | PixelFormat::RgbaF32
+                | PixelFormat::Bgr48
+                | PixelFormat::Rgb48
+                | PixelFormat::YuvF32Planar => {}
+                _ => assert!(false),
+            },
+        }
+    }
+
+    for (fourcc, pixel_format) in [
+        (b"IYUV", PixelFormat::I420),
+        (b"YU12", PixelFormat::I420),
+        (b"YU16", PixelFormat::I422),
+        (b"YU24", PixelFormat::I444),
+    ]
+    .iter()
+    {
+        assert_eq!(
+            PixelFormat::from_fourcc(fourcc).map(|pf| pf as u32),
+            Some(*pixel_format as u32)
+        );
+    }
+
+    for fourcc in [b"YUY2", b"nv12", b"\0\0\0\0"].iter() {
+        assert!(PixelFormat::from_fourcc(fourcc).is_none());
+    }
+}
+
+#[test]
+fn frame_size_ok() {
+    const I420_FRAME_SIZE: Option<usize> = PixelFormat::I420.frame_size(640, 480);
+    assert_eq!(I420_FRAME_SIZE, Some(640 * 480 * 3 / 2));
+
+    for (pixel_format, width, height) in iproduct!(
+        PIXEL_FORMATS.iter(),
+        [0, 1, 2, 3, 32, 33, 640, 1920].iter(),
+        [0, 1, 2, 3, 32, 33, 480, 1080].iter()
+    ) {
+        let num_planes = match pixel_format {
+            PixelFormat::I444 | PixelFormat::I422 | PixelFormat::I420 => 3,
+            PixelFormat::Nv12 | PixelFormat::P010 => 2,
+            _ => 1,
+        };
+
+        let format = ImageFormat {
+            pixel_format: *pixel_format,
+            color_space: ColorSpace::Lrgb,
+            num_planes,
+        };
+
+        let expected =
+            (*width as usize) * (*height as usize) * (pixel_format.bits_per_pixel() as usize) / 8;
+        let compatible = match pixel_format {
+            PixelFormat::I422 => width % 2 == 0,
+            PixelFormat::I420 | PixelFormat::Nv12 | PixelFormat::P010 => {
+                width % 2 == 0 && height % 2 == 0
+            }
+            _ => true,
+        };
+
+        if compatible {
+            let buffers_size = &mut [0usize; MAX_NUMBER_OF_PLANES as usize];
+            assert!(get_buffers_size(*width, *height, &format, None, buffers_size).is_ok());
+            let size: usize = buffers_size.iter().sum();
+
+            assert_eq!(pixel_format.frame_size(*width, *height), Some(size));
+            assert_eq!(size, expected);
+        } else {
+            assert_eq!(pixel_format.frame_size(*width, *height), None);
+        }
+    }
+
+    assert_eq!(PixelFormat::Bgra.frame_size(u32::MAX, u32::MAX), None);
+    assert_eq!(
+        PixelFormat::Bgra.frame_size(MAX_DIMENSION, 1),
+        Some(4 * (MAX_DIMENSION as usize))
+    );
+    assert_eq!(
+        PixelFormat::Bgra.frame_size(1, MAX_DIMENSION),
+        Some(4 * (MAX_DIMENSION as usize))
+    );
+}
+
+#[test]
+fn recommended_stride_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 98;
+    const HEIGHT: u32 = 34;
+
+    for pixel_format in PIXEL_FORMATS.iter() {
+        let (num_planes, line_sizes): (u32, &[usize]) = match pixel_format {
+            PixelFormat::Argb | PixelFormat::Bgra | PixelFormat::Rgba => (1, &[392]),
+            PixelFormat::Bgr | PixelFormat::Rgb => (1, &[294]),
+            PixelFormat::RgbaF16 => (1, &[784]),
+            PixelFormat::RgbF32 => (1, &[1176]),
+            PixelFormat::RgbaF32 => (1, &[1568]),
+            PixelFormat::Bgr48 | PixelFormat::Rgb48 => (1, &[588]),
+            PixelFormat::I444 => (3, &[98, 98, 98]),
+            PixelFormat::I422 | PixelFormat::I420 => (3, &[98, 49, 49]),
+            PixelFormat::Nv12 => (2, &[98, 98]),
+            PixelFormat::P010 => (2, &[196, 196]),
+            PixelFormat::YuvF32Planar => (3, &[392, 392, 392]),
+        };
+
+        let strides: Vec<usize> = (0..num_planes)
+            .map(|plane| recommended_stride(WIDTH, *pixel_format, plane))
+            .collect();
+        for (stride, line_size) in strides.iter().zip(line_sizes.iter()) {
+            assert_eq!(stride % 64, 0);
+            assert!(stride >= line_size && stride - line_size < 64);
+        }
+
+        assert_eq!(recommended_stride(WIDTH, *pixel_format, num_planes), 0);
+        assert_eq!(recommended_stride(0, *pixel_format, 0), 0);
+
+        let format = ImageFormat {
+            pixel_format: *pixel_format,
+            color_space: if num_planes == 1 {
+                ColorSpace::Lrgb
+            } else {
+                ColorSpace::Bt601
+            },
+            num_planes,
+        };
+
+        let buffers_size = &mut [0usize; MAX_NUMBER_OF_PLANES as usize];
+        assert!(get_buffers_size(WIDTH, HEIGHT, &format, Some(&strides), buffers_size).is_ok());
+    }
+
+    // Conversions give the same result with packed and recommended strides
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let i420_format = ImageFormat {
+        pixel_format: PixelFormat::I420,
+        color_space: ColorSpace::Bt709,
+        num_planes: 3,
+    };
+
+    let mut rng = rand::thread_rng();
+    let width = WIDTH as usize;
+    let height = HEIGHT as usize;
+    let src: Vec<u8> = (0..4 * width * height).map(|_| rng.gen::<u8>()).collect();
+    let mut expected = alloc_planes(WIDTH, HEIGHT, &i420_format);
+    assert!(convert_planes(
+        WIDTH,
+        HEIGHT,
+        &bgra_format,
+        std::slice::from_ref(&src),
+        &i420_format,
+        &mut expected
+    )
+    .is_ok());
+
+    let src_stride = recommended_stride(WIDTH, PixelFormat::Bgra, 0);
+    let mut padded_src = vec![0u8; src_stride * height];
+    for (dst_line, src_line) in padded_src.chunks_mut(src_stride).zip(src.chunks(4 * width)) {
+        dst_line[..4 * width].copy_from_slice(src_line);
+    }
+
+    let dst_strides: Vec<usize> = (0..3)
+        .map(|plane| recommended_stride(WIDTH, PixelFormat::I420, plane))
+        .collect();
+    let mut planes = [
+        vec![0u8; dst_strides[0] * height],
+        vec![0u8; dst_strides[1] * height / 2],
+        vec![0u8; dst_strides[2] * height / 2],
+    ];
+    let mut dst_buffers: Vec<&mut [u8]> = planes.iter_mut().map(|plane| &mut plane[..]).collect();
+    assert!(convert_image(
+        WIDTH,
+        HEIGHT,
+        &bgra_format,
+        Some(&[src_stride]),
+        &[&padded_src],
+        &i420_format,
+        Some(&dst_strides),
+        &mut dst_buffers,
+    )
+    .is_ok());
+
+    for (i, (plane, expected)) in planes.iter().zip(expected.iter()).enumerate() {
+        let line_size = if i == 0 { width } else { width / 2 };
+        for (line, expected_line) in plane.chunks(dst_strides[i]).zip(expected.chunks(line_size)) {
+            assert_eq!(&line[..line_size], expected_line);
+        }
+    }
+}
+
+#[test]
+fn misaligned_buffers_ok() {
+    bootstrap();
+
+    // Large enough for the vectorized paths, and with a tail
+    const WIDTH: u32 = 130;
+    const HEIGHT: u32 = 6;
+
+    let mut rng = rand::thread_rng();
+    let num_planes = |pixel_format: PixelFormat| match pixel_format {
+        PixelFormat::I444 | PixelFormat::I422 | PixelFormat::I420 => 3,
+        PixelFormat::Nv12 | PixelFormat::P010 => 2,
+        _ => 1,
+    };
+
+    // Allocates the planes of an image, each one starting at the given offset
+    let alloc = |format: &ImageFormat, offset: usize| -> Vec<Vec<u8>> {
+        let buffers_size = &mut [0usize; MAX_NUMBER_OF_PLANES as usize];
+        assert!(get_buffers_size(WIDTH, HEIGHT, format, None, buffers_size).is_ok());
+        buffers_size[..format.num_planes as usize]
+            .iter()
+            .map(|&size| vec![0u8; size + offset])
+            .collect()
+    };
+
+    for (src_pixel_format, src_color_space, dst_pixel_format, dst_color_space) in iproduct!(
+        PIXEL_FORMATS.iter(),
+        COLOR_SPACES.iter(),
+        PIXEL_FORMATS.iter(),
+        COLOR_SPACES.iter()
+    ) {
+        let src_format = ImageFormat {
+            pixel_format: *src_pixel_format,
+            color_space: *src_color_space,
+            num_planes: num_planes(*src_pixel_format),
+        };
+
+        let dst_format = ImageFormat {
+            pixel_format: *dst_pixel_format,
+            color_space: *dst_color_space,
+            num_planes: num_planes(*dst_pixel_format),
+        };
+
+        let mut src = alloc(&src_format, 0);
+        for plane in src.iter_mut() {
+            for byte in plane.iter_mut() {
+                *byte = rng.gen::<u8>();
+            }
+        }
+
+        let mut expected = alloc(&dst_format, 0);
+        if convert_planes(WIDTH, HEIGHT, &src_format, &src, &dst_format, &mut expected).is_err() {
+            continue;
+        }
+
+        for &offset in [1, 3].iter() {
+            let mut misaligned_src = alloc(&src_format, offset);
+            for (plane, data) in misaligned_src.iter_mut().zip(src.iter()) {
+                plane[offset..].copy_from_slice(data);
+            }
+
+            let mut misaligned_dst = alloc(&dst_format, offset);
+            let src_buffers: Vec<&[u8]> = misaligned_src
+                .iter()
+                .map(|plane| &plane[offset..])
+                .collect();
+            let mut dst_buffers: Vec<&mut [u8]> = misaligned_dst
+                .iter_mut()
+                .map(|plane| &mut plane[offset..])
+                .collect();
+            assert!(convert_image(
+                WIDTH,
+                HEIGHT,
+                &src_format,
+                None,
+                &src_buffers,
+                &dst_format,
+                None,
+                &mut dst_buffers,
+            )
+            .is_ok());
+
+            for (plane, expected) in misaligned_dst.iter().zip(expected.iter()) {
+                assert_eq!(&plane[offset..], &expected[..]);
+            }
+        }
+    }
+}
+
+#[test]
+fn degenerate_size_ok() {
+    bootstrap();
+
+    let mut rng = rand::thread_rng();
+
+    // Image formats with one buffer for each plane
+    let mut formats = Vec::new();
+    for pixel_format in PIXEL_FORMATS {
+        for color_space in COLOR_SPACES {
+            for num_planes in (1..=MAX_NUMBER_OF_PLANES).rev() {
+                let format = ImageFormat {
+                    pixel_format: *pixel_format,
+                    color_space: *color_space,
+                    num_planes,
+                };
+
+                let mut sizes = [0; MAX_NUMBER_OF_PLANES as usize];
+                if get_buffers_size(2, 2, &format, None, &mut sizes).is_ok() {
+                    formats.push(format);
+                    break;
+                }
+            }
+        }
+    }
+
+    let buffers_size = |width, height, format: &ImageFormat| {
+        let mut sizes = vec![0; format.num_planes as usize];
+        get_buffers_size(width, height, format, None, &mut sizes).map(|_| sizes)
+    };
+
+    for src_format in &formats {
+        for dst_format in &formats {
+            if describe_conversion(2, 2, src_format, dst_format).is_err() {
+                continue;
+            }
+
+            for &(width, height) in &[(1, 1), (1, 3), (1, 67), (67, 1), (66, 1)] {
+                let (src_sizes, dst_sizes) = match (
+                    buffers_size(width, height, src_format),
+                    buffers_size(width, height, dst_format),
+                ) {
+                    (Ok(src_sizes), Ok(dst_sizes)) => (src_sizes, dst_sizes),
+                    _ => {
+                        // Chroma samples of subsampled formats cover two pixels in each
+                        // subsampled direction: a lone pixel has no chroma
+                        let src = vec![vec![0u8; 1024]; src_format.num_planes as usize];
+                        let mut dst = vec![vec![0u8; 1024]; dst_format.num_planes as usize];
+                        assert_eq!(
+                            convert_planes(width, height, src_format, &src, dst_format, &mut dst)
+                                .err()
+                                .map(|e| e as u32),
+                            Some(ErrorKind::InvalidValue as u32)
+                        );
+                        continue;
+                    }
+                };
+
+                // Lines, and the columns of formats without horizontal subsampling, are
+                // converted independently: the result must match the top left corner of
+                // a larger image
+                let large_width = if width == 1 { 66 } else { width };
+                let large_height = if height == 1 { 3 } else { height };
+                let large_src_sizes = buffers_size(large_width, large_height, src_format).unwrap();
+                let large_dst_sizes = buffers_size(large_width, large_height, dst_format).unwrap();
+
+                let large_src: Vec<Vec<u8>> = large_src_sizes
+                    .iter()
+                    .map(|&size| (0..size).map(|_| rng.gen_range(0, 0x3C)).collect())
+                    .collect();
+                let mut large_dst: Vec<Vec<u8>> =
+                    large_dst_sizes.iter().map(|&size| vec![0; size]).collect();
+                assert!(convert_planes(
+                    large_width,
+                    large_height,
+                    src_format,
+                    &large_src,
+                    dst_format,
+                    &mut large_dst
+                )
+                .is_ok());
+
+                let corner = |large: &[Vec<u8>], sizes: &[usize], large_sizes: &[usize]| {
+                    let lines = height as usize;
+                    let large_lines = large_height as usize;
+                    large
+                        .iter()
+                        .zip(sizes.iter().zip(large_sizes))
+                        .map(|(plane, (&size, &large_size))| {
+                            plane
+                                .chunks(large_size / large_lines)
+                                .take(lines)
+                                .flat_map(|line| line[..size / lines].iter().copied())
+                                .collect()
+                        })
+                        .collect::<Vec<Vec<u8>>>()
+                };
+
+                let src = corner(&large_src, &src_sizes, &large_src_sizes);
+                let expected = corner(&large_dst, &dst_sizes, &large_dst_sizes);
+                let mut dst: Vec<Vec<u8>> = dst_sizes.iter().map(|&size| vec![0; size]).collect();
+                assert!(
+                    convert_planes(width, height, src_format, &src, dst_format, &mut dst).is_ok()
+                );
+                assert_eq!(
+                    dst,
+                    expected,
+                    "{:?}-{:?} -> {:?}-{:?} {}x{}",
+                    src_format.pixel_format,
+                    src_format.color_space,
+                    dst_format.pixel_format,
+                    dst_format.color_space,
+                    width,
+                    height
+                );
+            }
+        }
+    }
+}
+
+#[test]
+#[allow(unsafe_code)]
+fn raw_parts_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 6;
+
+    let mut rng = rand::thread_rng();
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+    let i420_format = ImageFormat {
+        pixel_format: PixelFormat::I420,
+        color_space: ColorSpace::Bt709,
+        num_planes: 3,
+    };
+
+    let bgra: Vec<u8> = (0..4 * WIDTH * HEIGHT).map(|_| rng.gen::<u8>()).collect();
+    let sizes = [
+        (WIDTH * HEIGHT) as usize,
+        (WIDTH * HEIGHT / 4) as usize,
+        (WIDTH * HEIGHT / 4) as usize,
+    ];
+
+    let mut expected: Vec<Vec<u8>> = sizes.iter().map(|&size| vec![0u8; size]).collect();
+    {
+        let mut dst_buffers: Vec<&mut [u8]> =
+            expected.iter_mut().map(|plane| &mut plane[..]).collect();
+        assert!(convert_image(
+            WIDTH,
+            HEIGHT,
+            &bgra_format,
+            None,
+            &[&bgra],
+            &i420_format,
+            None,
+            &mut dst_buffers,
+        )
+        .is_ok());
+    }
+
+    let mut i420: Vec<Vec<u8>> = sizes.iter().map(|&size| vec![0u8; size]).collect();
+    let dst_ptrs: Vec<*mut u8> = i420.iter_mut().map(|plane| plane.as_mut_ptr()).collect();
+    assert!(unsafe {
+        convert_image_from_raw_parts(
+            WIDTH,
+            HEIGHT,
+            &bgra_format,
+            None,
+            &[bgra.as_ptr()],
+            &[bgra.len()],
+            &i420_format,
+            None,
+            &dst_ptrs,
+            &sizes,
+        )
+    }
+    .is_ok());
+    assert_eq!(i420, expected);
+
+    // Null pointers are fine for empty buffers only
+    assert!(unsafe {
+        convert_image_from_raw_parts(
+            0,
+            0,
+            &bgra_format,
+            None,
+            &[std::ptr::null()],
+            &[0],
+            &i420_format,
+            None,
+            &[std::ptr::null_mut(); 3],
+            &[0; 3],
+        )
+    }
+    .is_ok());
+
+    match unsafe {
+        convert_image_from_raw_parts(
+            WIDTH,
+            HEIGHT,
+            &bgra_format,
+            None,
+            &[std::ptr::null()],
+            &[bgra.len()],
+            &i420_format,
+            None,
+            &dst_ptrs,
+            &sizes,
+        )
+    } {
+        Err(ErrorKind::InvalidValue) => {}
+        _ => assert!(false),
+    }
+
+    // Lengths must match the buffers, and still be large enough
+    match unsafe {
+        convert_image_from_raw_parts(
+            WIDTH,
+            HEIGHT,
+            &bgra_format,
+            None,
+            &[bgra.as_ptr()],
+            &[bgra.len()],
+            &i420_format,
+            None,
+            &dst_ptrs,
+            &sizes[..2],
+        )
+    } {
+        Err(ErrorKind::NotEnoughData) => {}
+        _ => assert!(false),
+    }
+
+    match unsafe {
+        convert_image_from_raw_parts(
+            WIDTH,
+            HEIGHT,
+            &bgra_format,
+            None,
+            &[bgra.as_ptr()],
+            &[bgra.len() - 1],
+            &i420_format,
+            None,
+            &dst_ptrs,
+            &sizes,
+        )
+    } {
+        Err(ErrorKind::NotEnoughData) => {}
+        _ => assert!(false),
+    }
+}
+
+#[test]
+#[allow(unsafe_code)]
+fn uninit_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 6;
+    const PADDING: usize = 6;
+
+    let mut rng = rand::thread_rng();
+    for (src_pixel_format, dst_pixel_format, num_planes) in [
+        (PixelFormat::Bgra, PixelFormat::Nv12, 1),
+        (PixelFormat::Bgra, PixelFormat::I420, 3),
+        // Routed through Bgra
+        (PixelFormat::Rgb, PixelFormat::I420, 3),
+    ]
+    .iter()
+    {
+        let src_format = ImageFormat {
+            pixel_format: *src_pixel_format,
+            color_space: ColorSpace::Lrgb,
+            num_planes: 1,
+        };
+
+        let dst_format = ImageFormat {
+            pixel_format: *dst_pixel_format,
+            color_space: ColorSpace::Bt709,
+            num_planes: *num_planes,
+        };
+
+        let mut src = alloc_planes(WIDTH, HEIGHT, &src_format);
+        for sample in src[0].iter_mut() {
+            *sample = rng.gen::<u8>();
+        }
+
+        let src_buffers: Vec<&[u8]> = src.iter().map(|x| &x[..]).collect();
+
+        // Lines are padded, and buffers are larger than needed. Planes sharing a buffer
+        // use the stride of the buffer, which all planes have the same here
+        let planes = *num_planes as usize;
+        let mut dst_strides = [0; 3];
+        for (plane, dst_stride) in dst_strides.iter_mut().enumerate() {
+            let stride = recommended_stride(WIDTH, *dst_pixel_format, plane as u32);
+            if stride > 0 {
+                *dst_stride = stride + PADDING;
+            }
+        }
+
+        let mut dst_sizes = [0; 3];
+        assert!(get_buffers_size(
+            WIDTH,
+            HEIGHT,
+            &dst_format,
+            Some(&dst_strides),
+            &mut dst_sizes
+        )
+        .is_ok());
+
+        let mut expected: Vec<Vec<u8>> = dst_sizes[..planes]
+            .iter()
+            .map(|&size| vec![0u8; size + PADDING])
+            .collect();
+        let mut expected_buffers: Vec<&mut [u8]> =
+            expected.iter_mut().map(|x| &mut x[..]).collect();
+        assert!(convert_image(
+            WIDTH,
+            HEIGHT,
+            &src_format,
+            None,
+            &src_buffers,
+            &dst_format,
+            Some(&dst_strides[..planes]),
+            &mut expected_buffers,
+        )
+        .is_ok());
+
+        // Stale bytes stand in for uninitialized memory, unless Miri can track the real thing
+        let mut dst: Vec<Vec<MaybeUninit<u8>>> = dst_sizes[..planes]
+            .iter()
+            .map(|&size| {
+                (0..size + PADDING)
+                    .map(|_| {
+                        if cfg!(miri) {
+                            MaybeUninit::uninit()
+                        } else {
+                            MaybeUninit::new(rng.gen::<u8>())
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut dst_buffers: Vec<&mut [MaybeUninit<u8>]> =
+            dst.iter_mut().map(|x| &mut x[..]).collect();
+        assert!(convert_image_uninit(
+            WIDTH,
+            HEIGHT,
+            &src_format,
+            None,
+            &src_buffers,
+            &dst_format,
+            Some(&dst_strides[..planes]),
+            &mut dst_buffers,
+        )
+        .is_ok());
+
+        let dst: Vec<Vec<u8>> = dst
+            .into_iter()
+            .map(|plane| {
+                plane
+                    .into_iter()
+                    .map(|sample| unsafe { sample.assume_init() })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(dst, expected);
+    }
+}
+
+#[cfg(not(feature = "noise"))]
+#[test]
+fn depth_ok() {
+    use dcp::depth::{convert_depth, Dithering};
+
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 6;
+
+    let mut rng = rand::thread_rng();
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    let read_u16 = |buffer: &[u8], i: usize| u16::from_le_bytes([buffer[2 * i], buffer[2 * i + 1]]);
+    let words = |buffer: &[u8]| buffer.len() / 2;
+
+    for (color_space, nv12_planes, p010_planes) in iproduct!(
+        [ColorSpace::Bt601, ColorSpace::Bt709, ColorSpace::Bt2020Pq].iter(),
+        1..=2,
+        1..=2
+    ) {
+        let nv12_format = format(PixelFormat::Nv12, *color_space, nv12_planes);
+        let p010_format = format(PixelFormat::P010, *color_space, p010_planes);
+
+        // 8-bit samples are scaled by 4, and converting back gives the same image
+        let mut nv12 = alloc_planes(WIDTH, HEIGHT, &nv12_format);
+        for plane in nv12.iter_mut() {
+            for sample in plane.iter_mut() {
+                *sample = rng.gen::<u8>();
+            }
+        }
+
+        let mut p010 = alloc_planes(WIDTH, HEIGHT, &p010_format);
+        assert!(
+            convert_planes(WIDTH, HEIGHT, &nv12_format, &nv12, &p010_format, &mut p010).is_ok()
+        );
+
+        let nv12_samples: Vec<u8> = nv12.concat();
+        let p010_samples: Vec<u8> = p010.concat();
+        assert_eq!(words(&p010_samples), nv12_samples.len());
+        for (i, &sample) in nv12_samples.iter().enumerate() {
+            assert_eq!(read_u16(&p010_samples, i), u16::from(sample) << 8);
+        }
+
+        let mut round_trip = alloc_planes(WIDTH, HEIGHT, &nv12_format);
+        assert!(convert_planes(
+            WIDTH,
+            HEIGHT,
+            &p010_format,
+            &p010,
+            &nv12_format,
+            &mut round_trip
+        )
+        .is_ok());
+        assert_eq!(round_trip.concat(), nv12_samples);
+
+        // 10-bit samples are rounded to nearest, saturating
+        for plane in p010.iter_mut() {
+            for sample in plane.chunks_exact_mut(2) {
+                sample.copy_from_slice(&(rng.gen::<u16>() & 0xFFC0).to_le_bytes());
+            }
+        }
+
+        let mut rounded = alloc_planes(WIDTH, HEIGHT, &nv12_format);
+        assert!(convert_planes(
+            WIDTH,
+            HEIGHT,
+            &p010_format,
+            &p010,
+            &nv12_format,
+            &mut rounded
+        )
+        .is_ok());
+
+        let p010_samples: Vec<u8> = p010.concat();
+        for (i, &sample) in rounded.concat().iter().enumerate() {
+            let expected = ((read_u16(&p010_samples, i) >> 6) + 2) >> 2;
+            assert_eq!(u16::from(sample), expected.min(255));
+        }
+
+        // Rounding is the default of convert_depth too
+        let src_buffers: Vec<&[u8]> = p010.iter().map(|x| &x[..]).collect();
+        let mut dst = alloc_planes(WIDTH, HEIGHT, &nv12_format);
+        {
+            let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+            assert!(convert_depth(
+                WIDTH,
+                HEIGHT,
+                &p010_format,
+                None,
+                &src_buffers,
+                &nv12_format,
+                None,
+                &mut dst_buffers,
+                Dithering::None,
+            )
+            .is_ok());
+        }
+        assert_eq!(dst, rounded);
+    }
+
+    // Ordered dithering preserves the average of each 2x2 block of samples
+    const FLAT_WIDTH: u32 = 4;
+    const FLAT_HEIGHT: u32 = 4;
+    let p010_format = format(PixelFormat::P010, ColorSpace::Bt709, 1);
+    let nv12_format = format(PixelFormat::Nv12, ColorSpace::Bt709, 1);
+    for value in 0..=1020u16 {
+        let mut p010 = alloc_planes(FLAT_WIDTH, FLAT_HEIGHT, &p010_format);
+        for sample in p010[0].chunks_exact_mut(2) {
+            sample.copy_from_slice(&(value << 6).to_le_bytes());
+        }
+
+        let mut nv12 = alloc_planes(FLAT_WIDTH, FLAT_HEIGHT, &nv12_format);
+        assert!(convert_depth(
+            FLAT_WIDTH,
+            FLAT_HEIGHT,
+            &p010_format,
+            None,
+            &[&p010[0]],
+            &nv12_format,
+            None,
+            &mut [&mut nv12[0]],
+            Dithering::Ordered,
+        )
+        .is_ok());
+
+        let sample = |x: usize, y: usize, stride: usize, offset: usize| {
+            u16::from(nv12[0][offset + y * stride + x])
+        };
+        let block_sum = |x: usize, y: usize, step: usize, stride: usize, offset: usize| {
+            sample(x, y, stride, offset)
+                + sample(x + step, y, stride, offset)
+                + sample(x, y + 1, stride, offset)
+                + sample(x + step, y + 1, stride, offset)
+        };
+
+        let stride = FLAT_WIDTH as usize;
+        for (x, y) in iproduct!([0, 2].iter(), [0, 2].iter()) {
+            assert_eq!(block_sum(*x, *y, 1, stride, 0), value);
+        }
+
+        // Interleaved cb and cr samples
+        let chroma_offset = stride * (FLAT_HEIGHT as usize);
+        assert_eq!(block_sum(0, 0, 2, stride, chroma_offset), value);
+        assert_eq!(block_sum(1, 0, 2, stride, chroma_offset), value);
+    }
+
+    // Only conversions between Nv12 and P010 with the same color space are allowed
+    let nv12 = alloc_planes(WIDTH, HEIGHT, &nv12_format);
+    for dst_format in [
+        format(PixelFormat::Nv12, ColorSpace::Bt601, 1),
+        format(PixelFormat::P010, ColorSpace::Bt601, 1),
+        format(PixelFormat::Bgra, ColorSpace::Lrgb, 1),
+    ]
+    .iter()
+    {
+        let mut dst = alloc_planes(WIDTH, HEIGHT, dst_format);
+        match convert_depth(
+            WIDTH,
+            HEIGHT,
+            &nv12_format,
+            None,
+            &[&nv12[0]],
+            dst_format,
+            None,
+            &mut [&mut dst[0]],
+            Dithering::Ordered,
+        ) {
+            Err(ErrorKind::InvalidOperation) => {}
+            _ => assert!(false),
+        }
+    }
+
+    // Buffers are still checked
+    let mut small = vec![0u8; 1];
+    match convert_depth(
+        WIDTH,
+        HEIGHT,
+        &nv12_format,
+        None,
+        &[&nv12[0]],
+        &format(PixelFormat::P010, ColorSpace::Bt709, 1),
+        None,
+        &mut [&mut small],
+        Dithering::None,
+    ) {
+        Err(ErrorKind::NotEnoughData) => {}
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn bgra30_ok() {
+    use dcp::hdr::{bgra30_to_bgra, ToneCurve, ToneMapping};
+
+    const WIDTH: u32 = 1024;
+
+    // A gray ramp over all the 10-bit codes, with every alpha value
+    let src: Vec<u8> = (0..WIDTH)
+        .flat_map(|code| {
+            (code | (code << 10) | (code << 20) | ((code & 3) << 30))
+                .to_le_bytes()
+                .to_vec()
+        })
+        .collect();
+
+    let convert = |curve, src_stride, dst_stride, dst: &mut [u8]| {
+        let tone_mapping = ToneMapping {
+            curve,
+            ..Default::default()
+        };
+        bgra30_to_bgra(WIDTH, 1, src_stride, &src, dst_stride, dst, &tone_mapping)
+    };
+
+    let mut clipped = vec![0u8; 4 * WIDTH as usize];
+    assert!(convert(ToneCurve::Clip, STRIDE_AUTO, STRIDE_AUTO, &mut clipped).is_ok());
+    for curve in &[ToneCurve::Clip, ToneCurve::Reinhard, ToneCurve::Bt2390] {
+        let mut dst = vec![0u8; 4 * WIDTH as usize];
+        assert!(convert(*curve, 4 * WIDTH as usize, STRIDE_AUTO, &mut dst).is_ok());
+
+        assert_eq!(&dst[..3], &[0, 0, 0]);
+        assert_eq!(&dst[4 * (WIDTH as usize - 1)..][..3], &[255, 255, 255]);
+        for (code, (pixel, clip)) in dst.chunks_exact(4).zip(clipped.chunks_exact(4)).enumerate() {
+            assert_eq!(pixel[0], pixel[1]);
+            assert_eq!(pixel[1], pixel[2]);
+            assert!(pixel[0] <= clip[0]);
+            assert_eq!(pixel[3], 85 * (code as u8 & 3));
+            if code > 0 {
+                assert!(pixel[0] >= dst[4 * (code - 1)]);
+            }
+        }
+
+        // Shadows are not affected by the BT.2390 curve
+        if *curve == ToneCurve::Bt2390 {
+            assert_eq!(&dst[..1600], &clipped[..1600]);
+        } else if *curve == ToneCurve::Reinhard {
+            assert!(dst[4 * 400] < clipped[4 * 400]);
+        }
+    }
+
+    // Invalid luminances
+    for (sdr_white, peak) in &[
+        (0.0, 1000.0),
+        (203.0, -1.0),
+        (203.0, 20000.0),
+        (f32::NAN, 1000.0),
+    ] {
+        let tone_mapping = ToneMapping {
+            curve: ToneCurve::Bt2390,
+            sdr_white: *sdr_white,
+            peak: *peak,
+        };
+        match bgra30_to_bgra(
+            WIDTH,
+            1,
+            STRIDE_AUTO,
+            &src,
+            STRIDE_AUTO,
+            &mut clipped,
+            &tone_mapping,
+        ) {
+            Err(ErrorKind::InvalidValue) => {}
+            _ => assert!(false),
+        }
+    }
+
+    // Short strides and buffers
+    let line_size = 4 * WIDTH as usize;
+    let mut short = vec![0u8; line_size - 1];
+    let results = [
+        convert(ToneCurve::Clip, line_size - 1, STRIDE_AUTO, &mut clipped),
+        convert(ToneCurve::Clip, STRIDE_AUTO, line_size - 1, &mut clipped),
+        convert(ToneCurve::Clip, STRIDE_AUTO, STRIDE_AUTO, &mut short),
+    ];
+    for result in &results {
+        match result {
+            Err(ErrorKind::NotEnoughData) => {}
+            _ => assert!(false),
+        }
+    }
+}
+
+#[test]
+fn reduce_luma_ok() {
+    use dcp::depth::{convert_depth, reduce_luma, Dithering};
+
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 6;
+
+    let mut rng = rand::thread_rng();
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    for (num_planes, dithering, dst_stride) in iproduct!(
+        1..=2,
+        [Dithering::None, Dithering::Ordered].iter(),
+        [STRIDE_AUTO, WIDTH as usize + 3].iter()
+    ) {
+        let p010_format = format(PixelFormat::P010, ColorSpace::Bt709, num_planes);
+        let nv12_format = format(PixelFormat::Nv12, ColorSpace::Bt709, 1);
+
+        let mut p010 = alloc_planes(WIDTH, HEIGHT, &p010_format);
+        for plane in p010.iter_mut() {
+            for sample in plane.chunks_exact_mut(2) {
+                sample.copy_from_slice(&(rng.gen::<u16>() & 0xFFC0).to_le_bytes());
+            }
+        }
+
+        // Same luma as a full depth conversion
+        let src_buffers: Vec<&[u8]> = p010.iter().map(|x| &x[..]).collect();
+        let mut nv12 = alloc_planes(WIDTH, HEIGHT, &nv12_format);
+        assert!(convert_depth(
+            WIDTH,
+            HEIGHT,
+            &p010_format,
+            None,
+            &src_buffers,
+            &nv12_format,
+            None,
+            &mut [&mut nv12[0]],
+            *dithering,
+        )
+        .is_ok());
+
+        let stride = if *dst_stride == STRIDE_AUTO {
+            WIDTH as usize
+        } else {
+            *dst_stride
+        };
+
+        let mut luma = vec![0u8; stride * (HEIGHT as usize)];
+        assert!(reduce_luma(
+            WIDTH,
+            HEIGHT,
+            &p010_format,
+            None,
+            &src_buffers,
+            *dst_stride,
+            &mut luma,
+            *dithering,
+        )
+        .is_ok());
+
+        for (row, expected) in luma
+            .chunks(stride)
+            .zip(nv12[0].chunks(WIDTH as usize))
+            .take(HEIGHT as usize)
+        {
+            assert_eq!(&row[..WIDTH as usize], expected);
+        }
+    }
+
+    let p010_format = format(PixelFormat::P010, ColorSpace::Bt709, 1);
+    let p010 = alloc_planes(WIDTH, HEIGHT, &p010_format);
+    let mut luma = vec![0u8; (WIDTH * HEIGHT) as usize];
+
+    // Only P010 images are supported
+    let nv12_format = format(PixelFormat::Nv12, ColorSpace::Bt709, 1);
+    match reduce_luma(
+        WIDTH,
+        HEIGHT,
+        &nv12_format,
+        None,
+        &[&p010[0]],
+        STRIDE_AUTO,
+        &mut luma,
+        Dithering::None,
+    ) {
+        Err(ErrorKind::InvalidOperation) => {}
+        _ => assert!(false),
+    }
+
+    // Odd sizes and rgb color spaces are not compatible with P010
+    for (width, color_space) in [(WIDTH + 1, ColorSpace::Bt709), (WIDTH, ColorSpace::Lrgb)].iter() {
+        match reduce_luma(
+            *width,
+            HEIGHT,
+            &format(PixelFormat::P010, *color_space, 1),
+            None,
+            &[&p010[0]],
+            STRIDE_AUTO,
+            &mut luma,
+            Dithering::None,
+        ) {
+            Err(ErrorKind::InvalidValue) => {}
+            _ => assert!(false),
+        }
+    }
+
+    // The destination stride and buffer must fit the luma plane
+    for (dst_stride, dst_size) in [
+        (WIDTH as usize - 1, luma.len()),
+        (STRIDE_AUTO, luma.len() - 1),
+    ]
+    .iter()
+    {
+        match reduce_luma(
+            WIDTH,
+            HEIGHT,
+            &p010_format,
+            None,
+            &[&p010[0]],
+            *dst_stride,
+            &mut luma[..*dst_size],
+            Dithering::None,
+        ) {
+            Err(ErrorKind::NotEnoughData) => {}
+            _ => assert!(false),
+        }
+    }
+
+    match reduce_luma(
+        WIDTH,
+        HEIGHT,
+        &p010_format,
+        None,
+        &[&p010[0][..1]],
+        STRIDE_AUTO,
+        &mut luma,
+        Dithering::None,
+    ) {
+        Err(ErrorKind::NotEnoughData) => {}
+        _ => assert!(false),
+    }
+}
+
+#[cfg(not(feature = "noise"))]
+#[test]
+fn rgb48_ok() {
+    use dcp::depth::{convert_depth, Dithering};
+
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 6;
+
+    let mut rng = rand::thread_rng();
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    let read_u16 = |buffer: &[u8], i: usize| u16::from_le_bytes([buffer[2 * i], buffer[2 * i + 1]]);
+
+    let rgb48_format = format(PixelFormat::Rgb48, ColorSpace::Lrgb, 1);
+    let bgr48_format = format(PixelFormat::Bgr48, ColorSpace::Lrgb, 1);
+    let bgra_format = format(PixelFormat::Bgra, ColorSpace::Lrgb, 1);
+
+    let mut rgb48 = alloc_planes(WIDTH, HEIGHT, &rgb48_format);
+    for sample in rgb48[0].iter_mut() {
+        *sample = rng.gen::<u8>();
+    }
+
+    // Bgr48 is Rgb48 with red and blue swapped
+    let mut bgr48 = rgb48.clone();
+    for (bgr, rgb) in bgr48[0].chunks_exact_mut(6).zip(rgb48[0].chunks_exact(6)) {
+        bgr[..2].copy_from_slice(&rgb[4..]);
+        bgr[4..].copy_from_slice(&rgb[..2]);
+    }
+
+    // Components are rounded to the nearest 8-bit value
+    let mut bgra = alloc_planes(WIDTH, HEIGHT, &bgra_format);
+    assert!(convert_planes(
+        WIDTH,
+        HEIGHT,
+        &rgb48_format,
+        &rgb48,
+        &bgra_format,
+        &mut bgra
+    )
+    .is_ok());
+    for (pixel, rgb) in bgra[0].chunks_exact(4).zip(rgb48[0].chunks_exact(6)) {
+        let expected = |i: usize| ((u32::from(read_u16(rgb, i)) * 255 + 32767) / 65535) as u8;
+        assert_eq!(pixel, &[expected(2), expected(1), expected(0), 255][..]);
+    }
+
+    let mut from_bgr48 = alloc_planes(WIDTH, HEIGHT, &bgra_format);
+    assert!(convert_planes(
+        WIDTH,
+        HEIGHT,
+        &bgr48_format,
+        &bgr48,
+        &bgra_format,
+        &mut from_bgr48
+    )
+    .is_ok());
+    assert_eq!(from_bgr48, bgra);
+
+    // 10-bit samples match the floating point formula, in the upper bits of each word
+    let component = |x: usize, y: usize, i: usize| {
+        f64::from(read_u16(&rgb48[0], 3 * (y * (WIDTH as usize) + x) + i)) / 65535.0
+    };
+
+    for (&(color_space, kr, kb), num_planes) in iproduct!(
+        [
+            (ColorSpace::Bt601, 0.299, 0.114),
+            (ColorSpace::Bt709, 0.2126, 0.0722)
+        ]
+        .iter(),
+        1..=2
+    ) {
+        let p010_format = format(PixelFormat::P010, color_space, num_planes);
+        let mut p010 = alloc_planes(WIDTH, HEIGHT, &p010_format);
+        assert!(convert_planes(
+            WIDTH,
+            HEIGHT,
+            &rgb48_format,
+            &rgb48,
+            &p010_format,
+            &mut p010
+        )
+        .is_ok());
+
+        let mut from_bgr48 = alloc_planes(WIDTH, HEIGHT, &p010_format);
+        assert!(convert_planes(
+            WIDTH,
+            HEIGHT,
+            &bgr48_format,
+            &bgr48,
+            &p010_format,
+            &mut from_bgr48
+        )
+        .is_ok());
+        assert_eq!(from_bgr48, p010);
+
+        let samples = p010.concat();
+        let check = |i: usize, expected: f64| {
+            let sample = read_u16(&samples, i);
+            assert_eq!(sample & 0x3F, 0);
+            assert!((f64::from(sample >> 6) - expected).abs() <= 0.51);
+        };
+
+        let luma = |r: f64, g: f64, b: f64| kr * r + (1.0 - kr - kb) * g + kb * b;
+        for (x, y) in iproduct!(0..(WIDTH as usize), 0..(HEIGHT as usize)) {
+            let (r, g, b) = (component(x, y, 0), component(x, y, 1), component(x, y, 2));
+            check(y * (WIDTH as usize) + x, 64.0 + 876.0 * luma(r, g, b));
+        }
+
+        let luma_size = (WIDTH * HEIGHT) as usize;
+        for (x, y) in iproduct!(0..(WIDTH as usize / 2), 0..(HEIGHT as usize / 2)) {
+            let average = |i: usize| {
+                (component(2 * x, 2 * y, i)
+                    + component(2 * x + 1, 2 * y, i)
+                    + component(2 * x, 2 * y + 1, i)
+                    + component(2 * x + 1, 2 * y + 1, i))
+                    / 4.0
+            };
+
+            let (r, g, b) = (average(0), average(1), average(2));
+            let l = luma(r, g, b);
+            let index = luma_size + 2 * (y * (WIDTH as usize / 2) + x);
+            check(index, 512.0 + 896.0 * (b - l) / (2.0 - 2.0 * kb));
+            check(index + 1, 512.0 + 896.0 * (r - l) / (2.0 - 2.0 * kr));
+        }
+
+        // Routed conversions go through the 16-bit source as well
+        let nv12_format = format(PixelFormat::Nv12, color_space, num_planes);
+        let mut nv12 = alloc_planes(WIDTH, HEIGHT, &nv12_format);
+        assert!(convert_planes(
+            WIDTH,
+            HEIGHT,
+            &rgb48_format,
+            &rgb48,
+            &nv12_format,
+            &mut nv12
+        )
+        .is_ok());
+    }
+
+    // Rounding is the default of convert_depth
+    let mut dst = alloc_planes(WIDTH, HEIGHT, &bgra_format);
+    assert!(convert_depth(
+        WIDTH,
+        HEIGHT,
+        &rgb48_format,
+        None,
+        &[&rgb48[0]],
+        &bgra_format,
+        None,
+        &mut [&mut dst[0]],
+        Dithering::None,
+    )
+    .is_ok());
+    assert_eq!(dst, bgra);
+
+    // Ordered dithering preserves the average of each 2x2 block, and exact 8-bit levels
+    const FLAT_WIDTH: u32 = 4;
+    const FLAT_HEIGHT: u32 = 4;
+    for value in (0..=65535u32).step_by(97).chain(Some(65535)) {
+        let mut flat = alloc_planes(FLAT_WIDTH, FLAT_HEIGHT, &bgr48_format);
+        for sample in flat[0].chunks_exact_mut(2) {
+            sample.copy_from_slice(&(value as u16).to_le_bytes());
+        }
+
+        let mut dithered = alloc_planes(FLAT_WIDTH, FLAT_HEIGHT, &bgra_format);
+        assert!(convert_depth(
+            FLAT_WIDTH,
+            FLAT_HEIGHT,
+            &bgr48_format,
+            None,
+            &[&flat[0]],
+            &bgra_format,
+            None,
+            &mut [&mut dithered[0]],
+            Dithering::Ordered,
+        )
+        .is_ok());
+
+        let sample = |x: usize, y: usize, i: usize| {
+            f64::from(dithered[0][4 * (y * (FLAT_WIDTH as usize) + x) + i])
+        };
+
+        let level = f64::from(value) * 255.0 / 65535.0;
+        for (x, y, i) in iproduct!([0, 2].iter(), [0, 2].iter(), 0..3) {
+            let sum = sample(*x, *y, i)
+                + sample(*x + 1, *y, i)
+                + sample(*x, *y + 1, i)
+                + sample(*x + 1, *y + 1, i);
+            assert!((sum - 4.0 * level).abs() <= 0.5);
+        }
+
+        if value % 257 == 0 {
+            for pixel in dithered[0].chunks_exact(4) {
+                assert_eq!(&pixel[..3], &[(value / 257) as u8; 3]);
+            }
+        }
+    }
+
+    // Only Bgra is a valid 8-bit destination
+    let p010_format = format(PixelFormat::P010, ColorSpace::Bt709, 1);
+    let mut p010 = alloc_planes(WIDTH, HEIGHT, &p010_format);
+    match convert_depth(
+        WIDTH,
+        HEIGHT,
+        &rgb48_format,
+        None,
+        &[&rgb48[0]],
+        &p010_format,
+        None,
+        &mut [&mut p010[0]],
+        Dithering::Ordered,
+    ) {
+        Err(ErrorKind::InvalidOperation) => {}
+        _ => assert!(false),
+    }
+}
+
+#[cfg(not(feature = "noise"))]
+#[test]
+fn quality_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 256;
+    const HEIGHT: u32 = 32;
+    const QUALITIES: &[Quality] = &[Quality::Fast, Quality::Balanced, Quality::Accurate];
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    let convert = |src_format: &ImageFormat,
+                   src: &[Vec<u8>],
+                   dst_format: &ImageFormat,
+                   quality: Quality|
+     -> Vec<Vec<u8>> {
+        let src_buffers: Vec<&[u8]> = src.iter().map(|x| &x[..]).collect();
+        let mut dst = alloc_planes(WIDTH, HEIGHT, dst_format);
+        {
+            let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+            assert!(convert_image_with_quality(
+                WIDTH,
+                HEIGHT,
+                src_format,
+                None,
+                &src_buffers,
+                dst_format,
+                None,
+                &mut dst_buffers,
+                quality,
+            )
+            .is_ok());
+        }
+        dst
+    };
+
+    // Sum of the absolute errors and largest error against a reference
+    let errors = |values: &[u8], expected: &[u8]| {
+        values
+            .iter()
+            .zip(expected.iter())
+            .map(|(&a, &b)| (i32::from(a) - i32::from(b)).abs())
+            .fold((0, 0), |(sum, max), error| (sum + error, max.max(error)))
+    };
+
+    let to_u8 = |value: f64| (value.clamp(0.0, 255.0) + 0.5).floor() as u8;
+
+    for (color_space, [rcr, gcb, gcr, bcb]) in [
+        (ColorSpace::Bt601, [1.402, 0.344_136, 0.714_136, 1.772]),
+        (ColorSpace::Bt709, [1.5748, 0.187_324, 0.468_124, 1.8556]),
+    ]
+    .iter()
+    {
+        // Every luma value, against a sweep of chroma values
+        let i444_format = format(PixelFormat::I444, *color_space, 3);
+        let mut yuv = alloc_planes(WIDTH, HEIGHT, &i444_format);
+        if let [y_plane, u_plane, v_plane] = &mut yuv[..] {
+            for (i, ((y, u), v)) in y_plane
+                .iter_mut()
+                .zip(u_plane.iter_mut())
+                .zip(v_plane.iter_mut())
+                .enumerate()
+            {
+                let line = i / (WIDTH as usize);
+                *y = i as u8;
+                *u = (16 + (line * 7 + i / 16) % 225) as u8;
+                *v = (16 + (line * 13 + i / 32) % 225) as u8;
+            }
+        }
+
+        let mut expected = Vec::new();
+        for ((&y, &u), &v) in yuv[0].iter().zip(yuv[1].iter()).zip(yuv[2].iter()) {
+            let l = (f64::from(y) - 16.0) / 219.0;
+            let cb = (f64::from(u) - 128.0) / 224.0;
+            let cr = (f64::from(v) - 128.0) / 224.0;
+            expected.push(to_u8(255.0 * (l + bcb * cb)));
+            expected.push(to_u8(255.0 * (l - gcb * cb - gcr * cr)));
+            expected.push(to_u8(255.0 * (l + rcr * cr)));
+            expected.push(255);
+        }
+
+        let bgra_format = format(PixelFormat::Bgra, ColorSpace::Lrgb, 1);
+        let yuv_errors: Vec<(i32, i32)> = QUALITIES
+            .iter()
+            .map(|quality| {
+                errors(
+                    &convert(&i444_format, &yuv, &bgra_format, *quality)[0],
+                    &expected,
+                )
+            })
+            .collect();
+
+        assert!(yuv_errors[0].0 > yuv_errors[1].0);
+        assert!(yuv_errors[1].0 >= yuv_errors[2].0);
+        assert!(yuv_errors[1].1 <= 1);
+        assert_eq!(yuv_errors[2].1, 0);
+
+        // A sweep of rgb values, converted to full resolution and subsampled chroma
+        let mut bgra = alloc_planes(WIDTH, HEIGHT, &bgra_format);
+        for (i, pixel) in bgra[0].chunks_exact_mut(4).enumerate() {
+            let line = i / (WIDTH as usize);
+            pixel[0] = (i * 3) as u8;
+            pixel[1] = (line * 8 + i / 64) as u8;
+            pixel[2] = (255 - (i % (WIDTH as usize))) as u8;
+        }
+
+        let rgb = |x: usize, y: usize| {
+            let pixel = &bgra[0][4 * (y * (WIDTH as usize) + x)..];
+            (
+                f64::from(pixel[2]),
+                f64::from(pixel[1]),
+                f64::from(pixel[0]),
+            )
+        };
+
+        let kr = 1.0 - 0.5 * rcr;
+        let kb = 1.0 - 0.5 * bcb;
+        let luma = |(r, g, b): (f64, f64, f64)| kr * r + (1.0 - kr - kb) * g + kb * b;
+        for (pixel_format, shift) in [(PixelFormat::I444, 0), (PixelFormat::I420, 1)].iter() {
+            let yuv_format = format(*pixel_format, *color_space, 3);
+
+            let mut expected = Vec::new();
+            for (y, x) in iproduct!(0..HEIGHT as usize, 0..WIDTH as usize) {
+                expected.push(to_u8(16.0 + 219.0 * luma(rgb(x, y)) / 255.0));
+            }
+
+            let mut cb_expected = Vec::new();
+            let mut cr_expected = Vec::new();
+            let size: u32 = 1 << *shift;
+            for (y, x) in iproduct!(
+                0..(HEIGHT as usize) >> *shift,
+                0..(WIDTH as usize) >> *shift
+            ) {
+                let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+                for (dy, dx) in iproduct!(0..size, 0..size) {
+                    let (sr, sg, sb) =
+                        rgb((x << *shift) + dx as usize, (y << *shift) + dy as usize);
+                    r += sr / f64::from(size * size);
+                    g += sg / f64::from(size * size);
+                    b += sb / f64::from(size * size);
+                }
+
+                let l = luma((r, g, b));
+                cb_expected.push(to_u8(128.0 + 224.0 * (b - l) / (bcb * 255.0)));
+                cr_expected.push(to_u8(128.0 + 224.0 * (r - l) / (rcr * 255.0)));
+            }
+            expected.extend(cb_expected);
+            expected.extend(cr_expected);
+
+            let results: Vec<Vec<u8>> = QUALITIES
+                .iter()
+                .map(|quality| convert(&bgra_format, &bgra, &yuv_format, *quality).concat())
+                .collect();
+
+            // Balanced rgb to ycbcr is the fast kernel
+            assert_eq!(results[0], results[1]);
+
+            let fast_errors = errors(&results[0], &expected);
+            let accurate_errors = errors(&results[2], &expected);
+            assert!(fast_errors.0 >= accurate_errors.0);
+            assert!(fast_errors.1 <= 1);
+            assert_eq!(accurate_errors.1, 0);
+        }
+    }
+
+    // Other conversions do not depend on the quality
+    let rgb_format = format(PixelFormat::Rgb, ColorSpace::Lrgb, 1);
+    let mut rgb = alloc_planes(WIDTH, HEIGHT, &rgb_format);
+    for (i, sample) in rgb[0].iter_mut().enumerate() {
+        *sample = (i * 7) as u8;
+    }
+
+    for dst_format in [
+        format(PixelFormat::Bgra, ColorSpace::Lrgb, 1),
+        format(PixelFormat::Nv12, ColorSpace::Bt601, 2),
+    ]
+    .iter()
+    {
+        let mut expected = alloc_planes(WIDTH, HEIGHT, dst_format);
+        assert!(
+            convert_planes(WIDTH, HEIGHT, &rgb_format, &rgb, dst_format, &mut expected).is_ok()
+        );
+        for quality in QUALITIES.iter() {
+            assert_eq!(convert(&rgb_format, &rgb, dst_format, *quality), expected);
+        }
+    }
+
+    // Errors are the ones of convert_image
+    let nv12_format = format(PixelFormat::Nv12, ColorSpace::Bt601, 2);
+    let nv12 = alloc_planes(WIDTH, HEIGHT, &nv12_format);
+    let mut small = vec![0u8; 4];
+    for quality in QUALITIES.iter() {
+        match convert_image_with_quality(
+            WIDTH,
+            HEIGHT,
+            &nv12_format,
+            None,
+            &[&nv12[0], &nv12[1]],
+            &format(PixelFormat::Bgra, ColorSpace::Lrgb, 1),
+            None,
+            &mut [&mut small],
+            *quality,
+        ) {
+            Err(ErrorKind::NotEnoughData) => {}
+            _ => assert!(false),
+        }
+
+        match convert_image_with_quality(
+            WIDTH,
+            HEIGHT,
+            &nv12_format,
+            None,
+            &[&nv12[0], &nv12[1]],
+            &format(PixelFormat::Bgra, ColorSpace::Bt601, 1),
+            None,
+            &mut [&mut small],
+            *quality,
+        ) {
+            Err(ErrorKind::InvalidValue) => {}
+            _ => assert!(false),
+        }
+    }
+}
+
+#[cfg(not(feature = "noise"))]
+#[test]
+fn max_error_ok() {
+    bootstrap();
+
+    const QUALITIES: &[Quality] = &[Quality::Fast, Quality::Balanced, Quality::Accurate];
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    let luma_weights = |color_space| match color_space {
+        ColorSpace::Bt601 => (0.299, 0.114),
+        _ => (0.2126, 0.0722),
+    };
+
+    // Exact conversions, in 8-bit code values
+    let to_rgb = |[y, u, v]: [f64; 3], color_space| {
+        let (kr, kb) = luma_weights(color_space);
+        let kg = 1.0 - kr - kb;
+        let l = (y - 16.0) / 219.0;
+        let (cb, cr) = ((u - 128.0) / 224.0, (v - 128.0) / 224.0);
+        let (rcr, bcb) = (2.0 * (1.0 - kr), 2.0 * (1.0 - kb));
+        [
+            l + rcr * cr,
+            l - (bcb * kb * cb + rcr * kr * cr) / kg,
+            l + bcb * cb,
+        ]
+        .iter()
+        .map(|x| (255.0 * x).clamp(0.0, 255.0))
+        .collect::<Vec<f64>>()
+    };
+
+    let to_yuv = |rgb: &[f64], color_space| {
+        let (kr, kb) = luma_weights(color_space);
+        let l = kr * rgb[0] + (1.0 - kr - kb) * rgb[1] + kb * rgb[2];
+        [
+            16.0 + 219.0 * l / 255.0,
+            128.0 + 224.0 * (rgb[2] - l) / (255.0 * 2.0 * (1.0 - kb)),
+            128.0 + 224.0 * (rgb[0] - l) / (255.0 * 2.0 * (1.0 - kr)),
+        ]
+    };
+
+    let convert = |width,
+                   height,
+                   src_format: &ImageFormat,
+                   src: &[Vec<u8>],
+                   dst_format: &ImageFormat,
+                   quality| {
+        let src_buffers: Vec<&[u8]> = src.iter().map(|x| &x[..]).collect();
+        let mut dst = alloc_planes(width, height, dst_format);
+        {
+            let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+            assert!(convert_image_with_quality(
+                width,
+                height,
+                src_format,
+                None,
+                &src_buffers,
+                dst_format,
+                None,
+                &mut dst_buffers,
+                quality,
+            )
+            .is_ok());
+        }
+        dst
+    };
+
+    let max_difference = |values: &[u8], expected: &[f64]| {
+        values
+            .iter()
+            .zip(expected.iter())
+            .map(|(&value, expected)| (f64::from(value) - expected).abs())
+            .fold(0.0, f64::max) as f32
+    };
+
+    let bgra_format = format(PixelFormat::Bgra, ColorSpace::Lrgb, 1);
+    let mut rng = rand::thread_rng();
+    for &color_space in [ColorSpace::Bt601, ColorSpace::Bt709].iter() {
+        // Every luma value of the limited range, against a grid of chroma values
+        const WIDTH: u32 = 220;
+        const HEIGHT: u32 = 29 * 29;
+        let i444_format = format(PixelFormat::I444, color_space, 3);
+        let samples: Vec<[f64; 3]> = (0..(WIDTH * HEIGHT) as usize)
+            .map(|i| {
+                let line = i / WIDTH as usize;
+                [
+                    (16 + i % WIDTH as usize) as f64,
+                    (16 + 8 * (line % 29)) as f64,
+                    (16 + 8 * (line / 29)) as f64,
+                ]
+            })
+            .collect();
+
+        let mut yuv = alloc_planes(WIDTH, HEIGHT, &i444_format);
+        for (index, plane) in yuv.iter_mut().enumerate() {
+            for (value, sample) in plane.iter_mut().zip(samples.iter()) {
+                *value = sample[index] as u8;
+            }
+        }
+
+        let mut expected = Vec::new();
+        for &sample in samples.iter() {
+            let rgb = to_rgb(sample, color_space);
+            expected.extend_from_slice(&[rgb[2], rgb[1], rgb[0], 255.0]);
+        }
+
+        let mut bounds = Vec::new();
+        for &quality in QUALITIES.iter() {
+            let bound = max_error(&i444_format, &bgra_format, quality).unwrap();
+            let bgra = convert(WIDTH, HEIGHT, &i444_format, &yuv, &bgra_format, quality);
+            assert!(max_difference(&bgra[0], &expected) <= bound);
+            bounds.push(bound);
+        }
+
+        assert!(bounds[0] >= bounds[1] && bounds[1] >= bounds[2] && bounds[2] > 0.5);
+
+        // Floats keep the fractional part
+        let rgbf32_format = format(PixelFormat::RgbF32, ColorSpace::Lrgb, 1);
+        let bound = max_error(&i444_format, &rgbf32_format, Quality::Fast).unwrap();
+        assert!(bound < 0.01);
+
+        let rgbf32 = convert(
+            WIDTH,
+            HEIGHT,
+            &i444_format,
+            &yuv,
+            &rgbf32_format,
+            Quality::Fast,
+        );
+        for (value, expected) in rgbf32[0].chunks_exact(4).zip(
+            expected
+                .chunks_exact(4)
+                .flat_map(|bgra| vec![bgra[2], bgra[1], bgra[0]]),
+        ) {
+            let value = f32::from_le_bytes([value[0], value[1], value[2], value[3]]);
+            assert!((255.0 * f64::from(value) - expected).abs() as f32 <= bound);
+        }
+
+        // Change of color space
+        let other_color_space = match color_space {
+            ColorSpace::Bt601 => ColorSpace::Bt709,
+            _ => ColorSpace::Bt601,
+        };
+
+        let other_format = format(PixelFormat::I444, other_color_space, 3);
+        let mut expected = vec![Vec::new(); 3];
+        let (kr, kb) = luma_weights(color_space);
+        let (rcr, bcb) = (2.0 * (1.0 - kr), 2.0 * (1.0 - kb));
+        for &[y, u, v] in samples.iter() {
+            // Rgb is not clipped, as luma and chroma are converted directly
+            let (cb, cr) = ((u - 128.0) / 224.0, (v - 128.0) / 224.0);
+            let luma = 255.0 * (y - 16.0) / 219.0;
+            let rgb = [
+                luma + 255.0 * rcr * cr,
+                luma - 255.0 * (bcb * kb * cb + rcr * kr * cr) / (1.0 - kr - kb),
+                luma + 255.0 * bcb * cb,
+            ];
+            for (plane, x) in expected
+                .iter_mut()
+                .zip(to_yuv(&rgb, other_color_space).iter())
+            {
+                plane.push(x.clamp(0.0, 255.0));
+            }
+        }
+
+        let bound = max_error(&i444_format, &other_format, Quality::Fast).unwrap();
+        let converted = convert(
+            WIDTH,
+            HEIGHT,
+            &i444_format,
+            &yuv,
+            &other_format,
+            Quality::Fast,
+        );
+        for (plane, expected) in converted.iter().zip(expected.iter()) {
+            assert!(max_difference(plane, expected) <= bound);
+        }
+
+        // Random rgb to each YCbCr format
+        const RGB_WIDTH: u32 = 64;
+        const RGB_HEIGHT: u32 = 32;
+        let mut bgra = alloc_planes(RGB_WIDTH, RGB_HEIGHT, &bgra_format);
+        for sample in bgra[0].iter_mut() {
+            *sample = rng.gen::<u8>();
+        }
+
+        let rgb = |x: usize, y: usize| {
+            let pixel = &bgra[0][4 * (y * RGB_WIDTH as usize + x)..];
+            [
+                f64::from(pixel[2]),
+                f64::from(pixel[1]),
+                f64::from(pixel[0]),
+            ]
+        };
+
+        for &(pixel_format, num_planes, shift) in [
+            (PixelFormat::I444, 3, 0),
+            (PixelFormat::I420, 3, 1),
+            (PixelFormat::Nv12, 2, 1),
+        ]
+        .iter()
+        {
+            let yuv_format = format(pixel_format, color_space, num_planes);
+            let mut luma = Vec::new();
+            for (y, x) in iproduct!(0..RGB_HEIGHT as usize, 0..RGB_WIDTH as usize) {
+                luma.push(to_yuv(&rgb(x, y), color_space)[0]);
+            }
+
+            let mut chroma = Vec::new();
+            for (y, x) in iproduct!(
+                0..(RGB_HEIGHT as usize) >> shift,
+                0..(RGB_WIDTH as usize) >> shift
+            ) {
+                let mut average = [0.0; 3];
+                for (dy, dx) in iproduct!(0..1 << shift, 0..1 << shift) {
+                    let pixel = rgb((x << shift) + dx, (y << shift) + dy);
+                    for (sum, value) in average.iter_mut().zip(pixel.iter()) {
+                        *sum += value / f64::from(1 << (2 * shift));
+                    }
+                }
+
+                let yuv = to_yuv(&average, color_space);
+                chroma.push((yuv[1], yuv[2]));
+            }
+
+            for &quality in QUALITIES.iter() {
+                let bound = max_error(&bgra_format, &yuv_format, quality).unwrap();
+                assert!((0.5..0.51).contains(&bound));
+
+                let yuv = convert(
+                    RGB_WIDTH,
+                    RGB_HEIGHT,
+                    &bgra_format,
+                    &bgra,
+                    &yuv_format,
+                    quality,
+                );
+                assert!(max_difference(&yuv[0], &luma) <= bound);
+
+                let (cb, cr): (Vec<f64>, Vec<f64>) = chroma.iter().cloned().unzip();
+                if num_planes == 3 {
+                    assert!(max_difference(&yuv[1], &cb) <= bound);
+                    assert!(max_difference(&yuv[2], &cr) <= bound);
+                } else {
+                    let interleaved: Vec<f64> =
+                        chroma.iter().flat_map(|&(u, v)| vec![u, v]).collect();
+                    assert!(max_difference(&yuv[1], &interleaved) <= bound);
+                }
+            }
+        }
+    }
+
+    // A routed conversion accumulates the error of each conversion
+    const WIDTH: u32 = 64;
+    const HEIGHT: u32 = 32;
+    let i420_format = format(PixelFormat::I420, ColorSpace::Bt601, 3);
+    let i444_format = format(PixelFormat::I444, ColorSpace::Bt709, 3);
+    let mut yuv = alloc_planes(WIDTH, HEIGHT, &i420_format);
+    for sample in yuv[0].iter_mut() {
+        *sample = rng.gen_range(16, 236);
+    }
+
+    for plane in yuv[1..].iter_mut() {
+        for sample in plane.iter_mut() {
+            *sample = rng.gen_range(16, 241);
+        }
+    }
+
+    let mut expected = vec![Vec::new(); 3];
+    for (y, x) in iproduct!(0..HEIGHT as usize, 0..WIDTH as usize) {
+        let chroma = (y / 2) * (WIDTH as usize / 2) + x / 2;
+        let rgb = to_rgb(
+            [
+                f64::from(yuv[0][y * WIDTH as usize + x]),
+                f64::from(yuv[1][chroma]),
+                f64::from(yuv[2][chroma]),
+            ],
+            ColorSpace::Bt601,
+        );
+        for (plane, x) in expected
+            .iter_mut()
+            .zip(to_yuv(&rgb, ColorSpace::Bt709).iter())
+        {
+            plane.push(*x);
+        }
+    }
+
+    let bound = max_error(&i420_format, &i444_format, Quality::Accurate).unwrap();
+    assert_eq!(
+        bound,
+        max_error(&i420_format, &i444_format, Quality::Fast).unwrap()
+    );
+    assert!(bound > 0.9 && bound < 1.0);
+
+    let converted = convert(
+        WIDTH,
+        HEIGHT,
+        &i420_format,
+        &yuv,
+        &i444_format,
+        Quality::Fast,
+    );
+    for (plane, expected) in converted.iter().zip(expected.iter()) {
+        assert!(max_difference(plane, expected) <= bound);
+    }
+
+    // Exact and unbounded conversions
+    let bound = |src_format: &ImageFormat, dst_format: &ImageFormat| {
+        max_error(src_format, dst_format, Quality::Fast)
+    };
+
+    let nv12 = format(PixelFormat::Nv12, ColorSpace::Bt709, 2);
+    let p010 = format(PixelFormat::P010, ColorSpace::Bt709, 2);
+    let rgb = format(PixelFormat::Rgb, ColorSpace::Lrgb, 1);
+    let nv12_pq = format(PixelFormat::Nv12, ColorSpace::Bt2020Pq, 2);
+    let p010_pq = format(PixelFormat::P010, ColorSpace::Bt2020Pq, 2);
+    assert_eq!(bound(&nv12, &p010).ok(), Some(0.0));
+    assert_eq!(bound(&p010, &nv12).ok(), Some(0.5));
+    assert_eq!(bound(&rgb, &bgra_format).ok(), Some(0.0));
+    assert_eq!(bound(&nv12_pq, &p010_pq).ok(), Some(0.0));
+    assert_eq!(bound(&p010_pq, &bgra_format).ok(), Some(f32::INFINITY));
+
+    // Errors are the ones of the conversion
+    for (src_format, dst_format, invalid_operation) in [
+        (&nv12, &nv12, true),
+        (
+            &format(PixelFormat::RgbF32, ColorSpace::Lrgb, 1),
+            &nv12,
+            true,
+        ),
+        (
+            &format(PixelFormat::Bgra, ColorSpace::Bt601, 1),
+            &nv12,
+            false,
+        ),
+        (
+            &format(PixelFormat::Nv12, ColorSpace::Bt601, 3),
+            &bgra_format,
+            false,
+        ),
+    ]
+    .iter()
+    {
+        match (bound(src_format, dst_format), invalid_operation) {
+            (Err(ErrorKind::InvalidOperation), true) | (Err(ErrorKind::InvalidValue), false) => {}
+            _ => assert!(false),
+        }
+    }
+}
+
+#[cfg(not(feature = "noise"))]
+#[test]
+fn jfif_ok() {
+    bootstrap();
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    // Color conversion of libjpeg (jccolor.c and jdcolor.c)
+    let fix = |x: f64| (x * 65536.0 + 0.5) as i32;
+    let rgb_to_ycc = |r: i32, g: i32, b: i32| {
+        let cbcr_offset = (128 << 16) + 32767;
+        (
+            (fix(0.299) * r + fix(0.587) * g + fix(0.114) * b + 32768) >> 16,
+            (-fix(0.16874) * r - fix(0.33126) * g + fix(0.5) * b + cbcr_offset) >> 16,
+            (fix(0.5) * r - fix(0.41869) * g - fix(0.08131) * b + cbcr_offset) >> 16,
+        )
+    };
+    let ycc_to_rgb = |y: i32, cb: i32, cr: i32| {
+        let (cb, cr) = (cb - 128, cr - 128);
+        let limit = |x: i32| x.clamp(0, 255) as u8;
+        (
+            limit(y + ((fix(1.402) * cr + 32768) >> 16)),
+            limit(y + ((-fix(0.34414) * cb - fix(0.71414) * cr + 32768) >> 16)),
+            limit(y + ((fix(1.772) * cb + 32768) >> 16)),
+        )
+    };
+
+    assert_eq!(
+        reference::rgb_to_yuv(255, 0, 0, ColorSpace::Jfif).ok(),
+        Some((76, 85, 255))
+    );
+    assert_eq!(
+        reference::rgb_to_yuv(255, 255, 255, ColorSpace::Jfif).ok(),
+        Some((255, 128, 128))
+    );
+    assert_eq!(
+        reference::yuv_to_rgb(255, 128, 128, ColorSpace::Jfif).ok(),
+        Some((255, 255, 255))
+    );
+
+    let mut rng = rand::thread_rng();
+    let yuv_formats = [
+        format(PixelFormat::I444, ColorSpace::Jfif, 3),
+        format(PixelFormat::I420, ColorSpace::Jfif, 3),
+        format(PixelFormat::Nv12, ColorSpace::Jfif, 2),
+    ];
+    let bgra_format = format(PixelFormat::Bgra, ColorSpace::Lrgb, 1);
+
+    // Returns the luma plane and the cb and cr samples of an image
+    let split = |yuv: &[Vec<u8>]| -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        if yuv.len() == 3 {
+            (yuv[0].clone(), yuv[1].clone(), yuv[2].clone())
+        } else {
+            (
+                yuv[0].clone(),
+                yuv[1].iter().step_by(2).cloned().collect(),
+                yuv[1].iter().skip(1).step_by(2).cloned().collect(),
+            )
+        }
+    };
+
+    // Widths which are and are not a multiple of the vectorized kernels group size
+    for &(width, height) in [(34u32, 6u32), (64, 4)].iter() {
+        let (w, h) = (width as usize, height as usize);
+
+        for &(pixel_format, depth, offsets) in [
+            (PixelFormat::Argb, 4, [1, 2, 3]),
+            (PixelFormat::Bgra, 4, [2, 1, 0]),
+            (PixelFormat::Bgr, 3, [2, 1, 0]),
+        ]
+        .iter()
+        {
+            let rgb_format = format(pixel_format, ColorSpace::Lrgb, 1);
+            let rgb = vec![(0..depth * w * h)
+                .map(|_| rng.gen::<u8>())
+                .collect::<Vec<u8>>()];
+            let ycc = |x: usize, y: usize| {
+                let pixel = &rgb[0][depth * (y * w + x)..];
+                rgb_to_ycc(
+                    i32::from(pixel[offsets[0]]),
+                    i32::from(pixel[offsets[1]]),
+                    i32::from(pixel[offsets[2]]),
+                )
+            };
+
+            for yuv_format in yuv_formats.iter() {
+                let mut yuv = alloc_planes(width, height, yuv_format);
+                assert!(
+                    convert_planes(width, height, &rgb_format, &rgb, yuv_format, &mut yuv).is_ok()
+                );
+
+                let (luma, cb, cr) = split(&yuv);
+                for y in 0..h {
+                    for x in 0..w {
+                        assert_eq!(i32::from(luma[y * w + x]), ycc(x, y).0);
+                    }
+                }
+
+                // Downsampling of libjpeg (jcsample.c), with a bias alternating between 1 and 2
+                let shift = if let PixelFormat::I444 = yuv_format.pixel_format {
+                    0
+                } else {
+                    1
+                };
+
+                for y in 0..(h >> shift) {
+                    for x in 0..(w >> shift) {
+                        let (mut sum_cb, mut sum_cr) = (0, 0);
+                        for (dx, dy) in iproduct!(0..(1 << shift), 0..(1 << shift)) {
+                            let (_, cb, cr) = ycc((x << shift) + dx, (y << shift) + dy);
+                            sum_cb += cb;
+                            sum_cr += cr;
+                        }
+
+                        let bias = if shift == 0 { 0 } else { 1 + (x & 1) as i32 };
+                        let i = y * (w >> shift) + x;
+                        assert_eq!(i32::from(cb[i]), (sum_cb + bias) >> (2 * shift));
+                        assert_eq!(i32::from(cr[i]), (sum_cr + bias) >> (2 * shift));
+                    }
+                }
+            }
+        }
+
+        for yuv_format in yuv_formats.iter() {
+            let mut yuv = alloc_planes(width, height, yuv_format);
+            for plane in yuv.iter_mut() {
+                rng.fill(&mut plane[..]);
+            }
+
+            let mut bgra = alloc_planes(width, height, &bgra_format);
+            assert!(
+                convert_planes(width, height, yuv_format, &yuv, &bgra_format, &mut bgra).is_ok()
+            );
+
+            // Chroma is replicated, as libjpeg does without fancy upsampling
+            let (luma, cb, cr) = split(&yuv);
+            let shift = if cb.len() == w * h { 0 } else { 1 };
+            for y in 0..h {
+                for x in 0..w {
+                    let c = (y >> shift) * (w >> shift) + (x >> shift);
+                    let (r, g, b) = ycc_to_rgb(
+                        i32::from(luma[y * w + x]),
+                        i32::from(cb[c]),
+                        i32::from(cr[c]),
+                    );
+                    let p = 4 * (y * w + x);
+                    assert_eq!(&bgra[0][p..p + 4], &[b, g, r, 255]);
+                    assert_eq!(
+                        reference::yuv_to_rgb(luma[y * w + x], cb[c], cr[c], ColorSpace::Jfif).ok(),
+                        Some((r, g, b))
+                    );
+                }
+            }
+        }
+    }
+
+    // Samples are rounded once, subsampled chroma twice
+    let i444 = max_error(&yuv_formats[0], &bgra_format, Quality::Fast).unwrap();
+    let i420 = max_error(&bgra_format, &yuv_formats[1], Quality::Fast).unwrap();
+    assert!(i444 < 0.51);
+    assert!(i420 > 1.0 && i420 < 1.01);
+}
+
+#[cfg(not(feature = "noise"))]
+#[test]
+fn rounding_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 256;
+    const HEIGHT: u32 = 32;
+    const ROUNDINGS: &[Rounding] = &[Rounding::Truncate, Rounding::HalfUp, Rounding::HalfEven];
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    let convert = |width: u32,
+                   height: u32,
+                   src_format: &ImageFormat,
+                   src: &[Vec<u8>],
+                   dst_format: &ImageFormat,
+                   rounding: Rounding|
+     -> Vec<u8> {
+        let src_buffers: Vec<&[u8]> = src.iter().map(|x| &x[..]).collect();
+        let mut dst = alloc_planes(width, height, dst_format);
+        {
+            let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+            assert!(convert_image_with_rounding(
+                width,
+                height,
+                src_format,
+                None,
+                &src_buffers,
+                dst_format,
+                None,
+                &mut dst_buffers,
+                rounding,
+            )
+            .is_ok());
+        }
+        dst.concat()
+    };
+
+    // Truncation is at most one below rounding half up, rounding half to even only
+    // differs on ties, toward the even value
+    let check_modes = |results: &[Vec<u8>]| {
+        for ((&truncated, &half_up), &half_even) in results[0]
+            .iter()
+            .zip(results[1].iter())
+            .zip(results[2].iter())
+        {
+            assert!(truncated <= half_up && half_up - truncated <= 1);
+            if half_even != half_up {
+                assert_eq!(half_even + 1, half_up);
+                assert_eq!(half_even & 1, 0);
+            }
+        }
+    };
+
+    // Luma of this color is exactly 16 + 110.5 for BT.601
+    let bgra_format = format(PixelFormat::Bgra, ColorSpace::Lrgb, 1);
+    let i444_format = format(PixelFormat::I444, ColorSpace::Bt601, 3);
+    let bgra = vec![vec![140u8, 192, 0, 255]];
+    for (rounding, expected) in ROUNDINGS
+        .iter()
+        .zip([[126u8, 133, 47], [127, 134, 47], [126, 134, 47]].iter())
+    {
+        assert_eq!(
+            convert(1, 1, &bgra_format, &bgra, &i444_format, *rounding),
+            expected
+        );
+    }
+
+    for color_space in &[ColorSpace::Bt601, ColorSpace::Bt709] {
+        // A sweep of rgb values
+        let mut bgra = alloc_planes(WIDTH, HEIGHT, &bgra_format);
+        for (i, pixel) in bgra[0].chunks_exact_mut(4).enumerate() {
+            let line = i / (WIDTH as usize);
+            pixel[0] = (i * 3) as u8;
+            pixel[1] = (line * 8 + i / 64) as u8;
+            pixel[2] = (255 - (i % (WIDTH as usize))) as u8;
+        }
+
+        for (pixel_format, num_planes) in [
+            (PixelFormat::I444, 3),
+            (PixelFormat::I420, 3),
+            (PixelFormat::Nv12, 2),
+        ]
+        .iter()
+        {
+            let yuv_format = format(*pixel_format, *color_space, *num_planes);
+            let results: Vec<Vec<u8>> = ROUNDINGS
+                .iter()
+                .map(|rounding| convert(WIDTH, HEIGHT, &bgra_format, &bgra, &yuv_format, *rounding))
+                .collect();
+
+            let mut expected = alloc_planes(WIDTH, HEIGHT, &yuv_format);
+            assert!(convert_planes(
+                WIDTH,
+                HEIGHT,
+                &bgra_format,
+                &bgra,
+                &yuv_format,
+                &mut expected
+            )
+            .is_ok());
+            assert_eq!(results[1], expected.concat());
+            check_modes(&results);
+        }
+
+        // Every luma value, against a sweep of chroma values
+        let i444_format = format(PixelFormat::I444, *color_space, 3);
+        let mut yuv = alloc_planes(WIDTH, HEIGHT, &i444_format);
+        if let [y_plane, u_plane, v_plane] = &mut yuv[..] {
+            for (i, ((y, u), v)) in y_plane
+                .iter_mut()
+                .zip(u_plane.iter_mut())
+                .zip(v_plane.iter_mut())
+                .enumerate()
+            {
+                let line = i / (WIDTH as usize);
+                *y = i as u8;
+                *u = (16 + (line * 7 + i / 16) % 225) as u8;
+                *v = (16 + (line * 13 + i / 32) % 225) as u8;
+            }
+        }
+
+        let results: Vec<Vec<u8>> = ROUNDINGS
+            .iter()
+            .map(|rounding| convert(WIDTH, HEIGHT, &i444_format, &yuv, &bgra_format, *rounding))
+            .collect();
+
+        let yuv_buffers: Vec<&[u8]> = yuv.iter().map(|x| &x[..]).collect();
+        let mut expected = alloc_planes(WIDTH, HEIGHT, &bgra_format);
+        assert!(convert_image_with_quality(
+            WIDTH,
+            HEIGHT,
+            &i444_format,
+            None,
+            &yuv_buffers,
+            &bgra_format,
+            None,
+            &mut [&mut expected[0][..]],
+            Quality::Balanced,
+        )
+        .is_ok());
+        assert_eq!(results[1], expected[0]);
+        check_modes(&results);
+    }
+}
+
+#[cfg(not(feature = "noise"))]
+#[test]
+fn luma_chroma_only_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 6;
+    const FILL: u8 = 0x5A;
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    let convert = |only_luma: bool,
+                   src_format: &ImageFormat,
+                   src: &[Vec<u8>],
+                   dst_format: &ImageFormat,
+                   dst: &mut [Vec<u8>]|
+     -> Result<(), ErrorKind> {
+        let src_buffers: Vec<&[u8]> = src.iter().map(|x| &x[..]).collect();
+        let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+        let convert = if only_luma {
+            convert_luma_only
+        } else {
+            convert_chroma_only
+        };
+
+        convert(
+            WIDTH,
+            HEIGHT,
+            src_format,
+            None,
+            &src_buffers,
+            dst_format,
+            None,
+            &mut dst_buffers,
+        )
+    };
+
+    let mut rng = rand::thread_rng();
+    for (src_pixel_format, (dst_pixel_format, num_planes), color_space) in iproduct!(
+        [PixelFormat::Argb, PixelFormat::Bgra, PixelFormat::Bgr].iter(),
+        [
+            (PixelFormat::I444, 3),
+            (PixelFormat::I420, 3),
+            (PixelFormat::Nv12, 1),
+            (PixelFormat::Nv12, 2),
+        ]
+        .iter(),
+        [ColorSpace::Bt601, ColorSpace::Bt709].iter()
+    ) {
+        let src_format = format(*src_pixel_format, ColorSpace::Lrgb, 1);
+        let dst_format = format(*dst_pixel_format, *color_space, *num_planes);
+
+        let mut src = alloc_planes(WIDTH, HEIGHT, &src_format);
+        for sample in src[0].iter_mut() {
+            *sample = rng.gen::<u8>();
+        }
+
+        let mut expected = alloc_planes(WIDTH, HEIGHT, &dst_format);
+        assert!(
+            convert_planes(WIDTH, HEIGHT, &src_format, &src, &dst_format, &mut expected).is_ok()
+        );
+        let expected = expected.concat();
+        let luma_size = (WIDTH * HEIGHT) as usize;
+
+        // Luma first, chroma is left untouched
+        let mut dst = alloc_planes(WIDTH, HEIGHT, &dst_format);
+        for plane in dst.iter_mut() {
+            plane.iter_mut().for_each(|sample| *sample = FILL);
+        }
+
+        assert!(convert(true, &src_format, &src, &dst_format, &mut dst).is_ok());
+        let written = dst.concat();
+        assert_eq!(written[..luma_size], expected[..luma_size]);
+        assert!(written[luma_size..].iter().all(|&sample| sample == FILL));
+
+        assert!(convert(false, &src_format, &src, &dst_format, &mut dst).is_ok());
+        assert_eq!(dst.concat(), expected);
+
+        // Chroma first, luma is left untouched
+        let mut dst = alloc_planes(WIDTH, HEIGHT, &dst_format);
+        for plane in dst.iter_mut() {
+            plane.iter_mut().for_each(|sample| *sample = FILL);
+        }
+
+        assert!(convert(false, &src_format, &src, &dst_format, &mut dst).is_ok());
+        let written = dst.concat();
+        assert!(written[..luma_size].iter().all(|&sample| sample == FILL));
+        assert_eq!(written[luma_size..], expected[luma_size..]);
+
+        // Not enough data
+        let mut dst = alloc_planes(WIDTH, HEIGHT, &dst_format);
+        let last = dst.len() - 1;
+        dst[last].pop();
+        for only_luma in &[true, false] {
+            assert_eq!(
+                convert(*only_luma, &src_format, &src, &dst_format, &mut dst)
+                    .err()
+                    .map(|e| e as u32),
+                Some(ErrorKind::NotEnoughData as u32)
+            );
+        }
+    }
+
+    // Only rgb to 8-bit YCbCr conversions are supported
+    let bgra = alloc_planes(
+        WIDTH,
+        HEIGHT,
+        &format(PixelFormat::Bgra, ColorSpace::Lrgb, 1),
+    );
+    for (src_pixel_format, dst_pixel_format, color_space, num_planes) in [
+        (PixelFormat::Bgra, PixelFormat::I420, ColorSpace::Jfif, 3),
+        (PixelFormat::Bgra, PixelFormat::P010, ColorSpace::Bt709, 2),
+        (PixelFormat::Bgra, PixelFormat::Rgb, ColorSpace::Lrgb, 1),
+        (PixelFormat::Rgba, PixelFormat::I420, ColorSpace::Bt601, 3),
+    ]
+    .iter()
+    {
+        let src_format = format(*src_pixel_format, ColorSpace::Lrgb, 1);
+        let dst_format = format(*dst_pixel_format, *color_space, *num_planes);
+        let mut dst = alloc_planes(WIDTH, HEIGHT, &dst_format);
+        for only_luma in &[true, false] {
+            assert_eq!(
+                convert(*only_luma, &src_format, &bgra, &dst_format, &mut dst)
+                    .err()
+                    .map(|e| e as u32),
+                Some(ErrorKind::InvalidOperation as u32)
+            );
+        }
+    }
+}
+
+#[cfg(not(feature = "noise"))]
+#[test]
+fn coefficients_ok() {
+    use dcp::coefficients::*;
+
+    bootstrap();
+
+    const WIDTH: u32 = 64;
+    const HEIGHT: u32 = 16;
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    let convert = |src_format: &ImageFormat, src: &[Vec<u8>], dst_format: &ImageFormat| {
+        let mut dst = alloc_planes(WIDTH, HEIGHT, dst_format);
+        assert!(convert_planes(WIDTH, HEIGHT, src_format, src, dst_format, &mut dst).is_ok());
+        dst
+    };
+
+    let dot = |w: &[i32; 3], (r, g, b): (i32, i32, i32)| w[0] * r + w[1] * g + w[2] * b;
+    let clamp = |x: i32| (x >> BACKWARD_FRAC_BITS).clamp(0, 255) as u8;
+    let width = WIDTH as usize;
+
+    let mut rng = rand::thread_rng();
+    let bgra_format = format(PixelFormat::Bgra, ColorSpace::Lrgb, 1);
+    let mut bgra = alloc_planes(WIDTH, HEIGHT, &bgra_format);
+    for sample in bgra[0].iter_mut() {
+        *sample = rng.gen::<u8>();
+    }
+
+    let rgb = |x: usize, y: usize| {
+        let pixel = &bgra[0][4 * (y * width + x)..];
+        (
+            i32::from(pixel[2]),
+            i32::from(pixel[1]),
+            i32::from(pixel[0]),
+        )
+    };
+
+    for (color_space, forward, backward) in [
+        (ColorSpace::Bt601, BT601_RGB_TO_YCBCR, BT601_YCBCR_TO_RGB),
+        (ColorSpace::Bt709, BT709_RGB_TO_YCBCR, BT709_YCBCR_TO_RGB),
+    ]
+    .iter()
+    {
+        // Rgb to YCbCr, without and with subsampling
+        let i444_format = format(PixelFormat::I444, *color_space, 3);
+        let i444 = convert(&bgra_format, &bgra, &i444_format);
+        let i420 = convert(
+            &bgra_format,
+            &bgra,
+            &format(PixelFormat::I420, *color_space, 3),
+        );
+
+        for (y, x) in iproduct!(0..(HEIGHT as usize), 0..width) {
+            let i = y * width + x;
+            let luma = (dot(&forward.y, rgb(x, y)) + LUMA_OFFSET) >> FORWARD_FRAC_BITS;
+            let cb = (dot(&forward.cb, rgb(x, y)) + CHROMA_OFFSET) >> FORWARD_FRAC_BITS;
+            let cr = (dot(&forward.cr, rgb(x, y)) + CHROMA_OFFSET) >> FORWARD_FRAC_BITS;
+            assert_eq!(i32::from(i444[0][i]), luma);
+            assert_eq!(i32::from(i444[1][i]), cb);
+            assert_eq!(i32::from(i444[2][i]), cr);
+            assert_eq!(i32::from(i420[0][i]), luma);
+        }
+
+        for (y, x) in iproduct!(0..(HEIGHT as usize) / 2, 0..width / 2) {
+            let (mut sr, mut sg, mut sb) = (0, 0, 0);
+            for (dy, dx) in iproduct!(0..2, 0..2) {
+                let (r, g, b) = rgb(2 * x + dx, 2 * y + dy);
+                sr += r;
+                sg += g;
+                sb += b;
+            }
+
+            let i = y * width / 2 + x;
+            let sum = (sr, sg, sb);
+            let cb = (dot(&forward.cb, sum) + SUBSAMPLED_CHROMA_OFFSET) >> SUBSAMPLED_FRAC_BITS;
+            let cr = (dot(&forward.cr, sum) + SUBSAMPLED_CHROMA_OFFSET) >> SUBSAMPLED_FRAC_BITS;
+            assert_eq!(i32::from(i420[1][i]), cb);
+            assert_eq!(i32::from(i420[2][i]), cr);
+        }
+
+        // YCbCr to rgb, with random samples in the nominal range
+        let mut yuv = alloc_planes(WIDTH, HEIGHT, &i444_format);
+        for (i, plane) in yuv.iter_mut().enumerate() {
+            let end = if i == 0 { 236 } else { 241 };
+            for sample in plane.iter_mut() {
+                *sample = rng.gen_range(16, end);
+            }
+        }
+
+        let rgb = convert(&i444_format, &yuv, &bgra_format);
+        for i in 0..(width * (HEIGHT as usize)) {
+            let (y, cb, cr) = (
+                i32::from(yuv[0][i]),
+                i32::from(yuv[1][i]),
+                i32::from(yuv[2][i]),
+            );
+
+            let l = (y * backward.y) >> PRODUCT_SHIFT;
+            let r = l + ((cr * backward.r_cr) >> PRODUCT_SHIFT) - backward.r_bias;
+            let g = l
+                - ((cb * backward.g_cb) >> PRODUCT_SHIFT)
+                - ((cr * backward.g_cr) >> PRODUCT_SHIFT)
+                + backward.g_bias;
+            let b = l + ((cb * backward.b_cb) >> PRODUCT_SHIFT) - backward.b_bias;
+            assert_eq!(
+                &rgb[0][4 * i..4 * i + 4],
+                &[clamp(b), clamp(g), clamp(r), 255]
+            );
+        }
+    }
+}
+
+#[test]
+fn range_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 4;
+    const HEIGHT: u32 = 2;
+    const Y_LIMITED: [u8; 8] = [16, 235, 0, 255, 126, 17, 234, 125];
+    const Y_FULL: [u8; 8] = [0, 255, 0, 255, 128, 1, 254, 127];
+    const C_LIMITED: [u8; 2] = [16, 240];
+    const C_FULL: [u8; 2] = [0, 255];
+    const V_LIMITED: [u8; 2] = [128, 72];
+    const V_FULL: [u8; 2] = [128, 64];
+
+    let i420_format = ImageFormat {
+        pixel_format: PixelFormat::I420,
+        color_space: ColorSpace::Bt601,
+        num_planes: 3,
+    };
+
+    // Padded source lines, packed destination lines
+    let src_strides = [6, 3, 3];
+    let mut y_src = vec![0xAAu8; 12];
+    let mut u_src = vec![0xAAu8; 3];
+    let mut v_src = vec![0xAAu8; 3];
+    for y in 0..2 {
+        y_src[y * 6..y * 6 + 4].copy_from_slice(&Y_LIMITED[y * 4..(y + 1) * 4]);
+    }
+    u_src[..2].copy_from_slice(&C_LIMITED);
+    v_src[..2].copy_from_slice(&V_LIMITED);
+
+    let mut y_dst = vec![0u8; 8];
+    let mut u_dst = vec![0u8; 2];
+    let mut v_dst = vec![0u8; 2];
+    assert!(range::convert_range(
+        range::RangeConversion::LimitedToFull,
+        WIDTH,
+        HEIGHT,
+        &i420_format,
+        Some(&src_strides),
+        &[&y_src, &u_src, &v_src],
+        None,
+        &mut [&mut y_dst, &mut u_dst, &mut v_dst],
+    )
+    .is_ok());
+
+    assert_eq!(&y_dst[..], &Y_FULL[..]);
+    assert_eq!(&u_dst[..], &C_FULL[..]);
+    assert_eq!(&v_dst[..], &V_FULL[..]);
+
+    // Full to limited range is the inverse, except for clipped values
+    for (i, (&limited, &full)) in Y_LIMITED.iter().zip(Y_FULL.iter()).enumerate() {
+        y_dst[i] = full;
+        y_src[i] = limited.clamp(16, 235);
+    }
+
+    assert!(range::convert_range_in_place(
+        range::RangeConversion::FullToLimited,
+        WIDTH,
+        HEIGHT,
+        &i420_format,
+        None,
+        &mut [&mut y_dst, &mut u_dst, &mut v_dst],
+    )
+    .is_ok());
+
+    assert_eq!(&y_dst[..], &y_src[..8]);
+    assert_eq!(&u_dst[..], &C_LIMITED[..]);
+    assert_eq!(&v_dst[..], &V_LIMITED[..]);
+
+    // Single plane nv12 and p010, in place
+    let nv12_format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt709,
+        num_planes: 1,
+    };
+
+    let mut nv12 = [16, 235, 16, 235, 16, 235, 16, 235, 16, 240, 128, 72];
+    assert!(range::convert_range_in_place(
+        range::RangeConversion::LimitedToFull,
+        WIDTH,
+        HEIGHT,
+        &nv12_format,
+        None,
+        &mut [&mut nv12],
+    )
+    .is_ok());
+    assert_eq!(nv12, [0, 255, 0, 255, 0, 255, 0, 255, 0, 255, 128, 64]);
+
+    let p010_format = ImageFormat {
+        pixel_format: PixelFormat::P010,
+        color_space: ColorSpace::Bt2020Pq,
+        num_planes: 1,
+    };
+
+    let p010_codes: [u16; 12] = [64, 940, 64, 940, 64, 940, 64, 940, 64, 960, 512, 288];
+    let mut p010: Vec<u8> = p010_codes
+        .iter()
+        .flat_map(|code| (code << 6).to_le_bytes().to_vec())
+        .collect();
+    assert!(range::convert_range_in_place(
+        range::RangeConversion::LimitedToFull,
+        WIDTH,
+        HEIGHT,
+        &p010_format,
+        None,
+        &mut [&mut p010],
+    )
+    .is_ok());
+
+    let p010_codes: Vec<u16> = p010
+        .chunks(2)
+        .map(|word| u16::from_le_bytes([word[0], word[1]]) >> 6)
+        .collect();
+    assert_eq!(
+        &p010_codes[..],
+        &[0, 1023, 0, 1023, 0, 1023, 0, 1023, 0, 1023, 512, 256][..]
+    );
+
+    // Errors
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let mut bgra = vec![0u8; 32];
+    let check_error = |format: &ImageFormat, strides: Option<&[usize]>, buffer: &mut [u8]| {
+        range::convert_range_in_place(
+            range::RangeConversion::LimitedToFull,
+            WIDTH,
+            HEIGHT,
+            format,
+            strides,
+            &mut [buffer],
+        )
+        .err()
+        .map(|e| e as u32)
+    };
+
+    assert_eq!(
+        check_error(&bgra_format, None, &mut bgra),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+    assert_eq!(
+        check_error(&nv12_format, Some(&[3, 4]), &mut nv12),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(&nv12_format, None, &mut nv12[..11]),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+}
+
+#[test]
+fn cancellation_ok() {
+    bootstrap();
+
+    // Three bands, the last one shorter than the others
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 146;
+    const SIZE: usize = (WIDTH as usize) * (HEIGHT as usize);
+
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let mut rng = rand::thread_rng();
+    let bgra: Vec<u8> = (0..4 * SIZE).map(|_| rng.gen::<u8>()).collect();
+    for (pixel_format, num_planes) in [
+        (PixelFormat::Nv12, 1),
+        (PixelFormat::Nv12, 2),
+        (PixelFormat::I420, 3),
+        (PixelFormat::I444, 3),
+    ]
+    .iter()
+    {
+        let yuv_format = ImageFormat {
+            pixel_format: *pixel_format,
+            color_space: ColorSpace::Bt709,
+            num_planes: *num_planes,
+        };
+
+        let buffers_size = &mut [0usize; 3];
+        assert!(get_buffers_size(WIDTH, HEIGHT, &yuv_format, None, buffers_size).is_ok());
+
+        let mut expected: Vec<Vec<u8>> = buffers_size[..*num_planes as usize]
+            .iter()
+            .map(|size| vec![0u8; *size])
+            .collect();
+        let mut test_output = expected.clone();
+        {
+            let mut expected: Vec<&mut [u8]> = expected.iter_mut().map(|b| &mut b[..]).collect();
+            assert!(convert_image(
+                WIDTH,
+                HEIGHT,
+                &bgra_format,
+                None,
+                &[&bgra],
+                &yuv_format,
+                None,
+                &mut expected,
+            )
+            .is_ok());
+
+            let mut test_output: Vec<&mut [u8]> =
+                test_output.iter_mut().map(|b| &mut b[..]).collect();
+            assert!(convert_image_cancellable(
+                WIDTH,
+                HEIGHT,
+                &bgra_format,
+                None,
+                &[&bgra],
+                &yuv_format,
+                None,
+                &mut test_output,
+                &CancellationToken::new(),
+            )
+            .is_ok());
+        }
+
+        assert_eq!(test_output, expected);
+
+        // Backward conversion, when available
+        if let PixelFormat::I444 = pixel_format {
+            continue;
+        }
+
+        let yuv: Vec<&[u8]> = expected.iter().map(|b| &b[..]).collect();
+        let mut expected = vec![0u8; 4 * SIZE];
+        let mut test_output = vec![0u8; 4 * SIZE];
+        assert!(convert_image(
+            WIDTH,
+            HEIGHT,
+            &yuv_format,
+            None,
+            &yuv,
+            &bgra_format,
+            None,
+            &mut [&mut expected],
+        )
+        .is_ok());
+        assert!(convert_image_cancellable(
+            WIDTH,
+            HEIGHT,
+            &yuv_format,
+            None,
+            &yuv,
+            &bgra_format,
+            None,
+            &mut [&mut test_output],
+            &CancellationToken::new(),
+        )
+        .is_ok());
+        assert_eq!(test_output, expected);
+    }
+
+    let nv12_format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt601,
+        num_planes: 1,
+    };
+
+    let check_error = |src_size: usize, dst_size: usize, token: &CancellationToken| {
+        let mut nv12 = vec![0u8; dst_size];
+        convert_image_cancellable(
+            WIDTH,
+            HEIGHT,
+            &bgra_format,
+            None,
+            &[&bgra[..src_size]],
+            &nv12_format,
+            None,
+            &mut [&mut nv12],
+            token,
+        )
+        .err()
+        .map(|e| e as u32)
+    };
+
+    // Cancellation is visible from clones
+    let token = CancellationToken::new();
+    let clone = token.clone();
+    assert!(!clone.is_cancelled());
+    token.cancel();
+    assert!(clone.is_cancelled());
+    assert_eq!(
+        check_error(4 * SIZE, 3 * SIZE / 2, &clone),
+        Some(ErrorKind::Cancelled as u32)
+    );
+
+    let token = CancellationToken::new();
+    assert_eq!(
+        check_error(4 * SIZE - 1, 3 * SIZE / 2, &token),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+    assert_eq!(
+        check_error(4 * SIZE, 3 * SIZE / 2 - 1, &token),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+}
+
+#[test]
+fn dirty_rows_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 12;
+    const FILL: u8 = 0x5A;
+
+    let src_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let mut rng = rand::thread_rng();
+    let mut src = alloc_planes(WIDTH, HEIGHT, &src_format);
+    for sample in src[0].iter_mut() {
+        *sample = rng.gen::<u8>();
+    }
+
+    let src_buffers: Vec<&[u8]> = src.iter().map(|x| &x[..]).collect();
+
+    // Pixel format, number of planes, chroma vertical shift, chroma line size divisor
+    for (pixel_format, num_planes, chroma_shift, chroma_divisor) in [
+        (PixelFormat::I444, 3, 0, 1),
+        (PixelFormat::I420, 3, 1, 2),
+        (PixelFormat::Nv12, 1, 1, 1),
+    ]
+    .iter()
+    {
+        let dst_format = ImageFormat {
+            pixel_format: *pixel_format,
+            color_space: ColorSpace::Bt709,
+            num_planes: *num_planes,
+        };
+
+        let mut expected = alloc_planes(WIDTH, HEIGHT, &dst_format);
+        assert!(
+            convert_planes(WIDTH, HEIGHT, &src_format, &src, &dst_format, &mut expected).is_ok()
+        );
+
+        let convert = |dst: &mut [Vec<u8>], dirty_rows: Option<&[u8]>| {
+            let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+            convert_image_dirty_rows(
+                WIDTH,
+                HEIGHT,
+                &src_format,
+                None,
+                &src_buffers,
+                &dst_format,
+                None,
+                &mut dst_buffers,
+                dirty_rows,
+            )
+        };
+
+        // Lines 3, 4 and 11 changed
+        let mut dst = alloc_planes(WIDTH, HEIGHT, &dst_format);
+        for plane in dst.iter_mut() {
+            plane.iter_mut().for_each(|sample| *sample = FILL);
+        }
+
+        assert!(convert(&mut dst, Some(&[0b0001_1000, 0b0000_1000])).is_ok());
+
+        // Subsampled chroma pairs the changed lines with their neighbours
+        let converted: &[usize] = if *chroma_shift == 0 {
+            &[3, 4, 11]
+        } else {
+            &[2, 3, 4, 5, 10, 11]
+        };
+
+        let written = dst.concat();
+        let expected = expected.concat();
+        let width = WIDTH as usize;
+        let luma_size = width * (HEIGHT as usize);
+        let chroma_stride = width / chroma_divisor;
+        let chroma_height = (HEIGHT as usize) >> chroma_shift;
+        let chroma_planes = (written.len() - luma_size) / (chroma_stride * chroma_height);
+
+        for y in 0..(HEIGHT as usize) {
+            let line = y * width..(y + 1) * width;
+            if converted.contains(&y) {
+                assert_eq!(written[line.clone()], expected[line]);
+            } else {
+                assert!(written[line].iter().all(|&sample| sample == FILL));
+            }
+        }
+
+        for plane in 0..chroma_planes {
+            for y in 0..chroma_height {
+                let start = luma_size + (plane * chroma_height + y) * chroma_stride;
+                let line = start..start + chroma_stride;
+                if converted.contains(&(y << chroma_shift)) {
+                    assert_eq!(written[line.clone()], expected[line]);
+                } else {
+                    assert!(written[line].iter().all(|&sample| sample == FILL));
+                }
+            }
+        }
+
+        // All lines changed, or no bitmap
+        for dirty_rows in &[Some(&[0xFFu8, 0x0F][..]), None] {
+            let mut dst = alloc_planes(WIDTH, HEIGHT, &dst_format);
+            assert!(convert(&mut dst, *dirty_rows).is_ok());
+            assert_eq!(dst.concat(), expected);
+        }
+
+        // No line changed
+        let mut dst = alloc_planes(WIDTH, HEIGHT, &dst_format);
+        assert!(convert(&mut dst, Some(&[0, 0])).is_ok());
+        assert!(dst.concat().iter().all(|&sample| sample == 0));
+
+        // Bitmap too short
+        assert_eq!(
+            convert(&mut dst, Some(&[0xFF])).err().map(|e| e as u32),
+            Some(ErrorKind::NotEnoughData as u32)
+        );
+    }
+}
+
+#[test]
+fn checksum_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 130;
+    const PADDING: usize = 6;
+
+    // CRC-32 check value
+    let rgb_format = ImageFormat {
+        pixel_format: PixelFormat::Rgb,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    assert_eq!(
+        image_checksum(3, 1, &rgb_format, None, &[b"123456789"]).unwrap(),
+        [0xCBF4_3926]
+    );
+    assert_eq!(
+        image_checksum(3, 2, &rgb_format, None, &[b"123456789"])
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+
+    let src_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let mut rng = rand::thread_rng();
+    let mut src = alloc_planes(WIDTH, HEIGHT, &src_format);
+    for sample in src[0].iter_mut() {
+        *sample = rng.gen::<u8>();
+    }
+
+    let src_buffers: Vec<&[u8]> = src.iter().map(|x| &x[..]).collect();
+
+    for (pixel_format, num_planes) in [
+        (PixelFormat::I444, 3),
+        (PixelFormat::I420, 3),
+        (PixelFormat::Nv12, 1),
+        (PixelFormat::Nv12, 2),
+    ]
+    .iter()
+    {
+        let dst_format = ImageFormat {
+            pixel_format: *pixel_format,
+            color_space: ColorSpace::Bt601,
+            num_planes: *num_planes,
+        };
+
+        let mut expected = alloc_planes(WIDTH, HEIGHT, &dst_format);
+        assert!(
+            convert_planes(WIDTH, HEIGHT, &src_format, &src, &dst_format, &mut expected).is_ok()
+        );
+
+        let expected_buffers: Vec<&[u8]> = expected.iter().map(|x| &x[..]).collect();
+        let expected = image_checksum(WIDTH, HEIGHT, &dst_format, None, &expected_buffers).unwrap();
+        let logical_planes = if let PixelFormat::Nv12 = pixel_format {
+            2
+        } else {
+            3
+        };
+        assert_eq!(expected.len(), logical_planes);
+
+        // Padding bytes are not part of the checksum. Planes sharing a buffer use its stride
+        let planes = *num_planes as usize;
+        let stride =
+            |plane: usize| recommended_stride(WIDTH, *pixel_format, plane as u32) + PADDING;
+        let mut dst_strides = [0; 3];
+        for (plane, dst_stride) in dst_strides.iter_mut().enumerate().take(logical_planes) {
+            *dst_stride = stride(plane.min(planes - 1));
+        }
+
+        let mut dst_sizes = [0; 3];
+        assert!(get_buffers_size(
+            WIDTH,
+            HEIGHT,
+            &dst_format,
+            Some(&dst_strides),
+            &mut dst_sizes
+        )
+        .is_ok());
+
+        let mut dst: Vec<Vec<u8>> = dst_sizes[..planes]
+            .iter()
+            .map(|&size| (0..size).map(|_| rng.gen::<u8>()).collect())
+            .collect();
+        let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+
+        let checksums = convert_image_with_checksum(
+            WIDTH,
+            HEIGHT,
+            &src_format,
+            None,
+            &src_buffers,
+            &dst_format,
+            Some(&dst_strides[..planes]),
+            &mut dst_buffers,
+        )
+        .unwrap();
+
+        assert_eq!(checksums, expected);
+    }
+}
+
+#[test]
+fn aligned_ok() {
+    bootstrap();
+
+    // Taller than a band, lines are not multiple of the vector sizes
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 130;
+    const PADDING: u8 = 0xCD;
+
+    assert_eq!(aligned_stride(WIDTH, PixelFormat::Bgra, 0, 256), 256);
+    assert_eq!(aligned_stride(WIDTH, PixelFormat::I420, 1, 48), 48);
+    assert_eq!(aligned_stride(WIDTH, PixelFormat::Nv12, 1, 5), 35);
+    assert_eq!(
+        aligned_stride(WIDTH, PixelFormat::Bgra, 0, 64),
+        recommended_stride(WIDTH, PixelFormat::Bgra, 0)
+    );
+    assert_eq!(aligned_stride(WIDTH, PixelFormat::Bgra, 0, 0), 0);
+    assert_eq!(aligned_stride(WIDTH, PixelFormat::Bgra, 1, 256), 0);
+
+    let mut rng = rand::thread_rng();
+    for (src_pixel_format, dst_pixel_format, num_planes, alignment) in [
+        (PixelFormat::Bgra, PixelFormat::Nv12, 1, 256),
+        (PixelFormat::Bgra, PixelFormat::Nv12, 2, 48),
+        (PixelFormat::Bgra, PixelFormat::I420, 3, 256),
+        // Routed through Bgra
+        (PixelFormat::Rgb, PixelFormat::I420, 3, 48),
+    ]
+    .iter()
+    {
+        let src_format = ImageFormat {
+            pixel_format: *src_pixel_format,
+            color_space: ColorSpace::Lrgb,
+            num_planes: 1,
+        };
+
+        let dst_format = ImageFormat {
+            pixel_format: *dst_pixel_format,
+            color_space: ColorSpace::Bt709,
+            num_planes: *num_planes,
+        };
+
+        let mut src = alloc_planes(WIDTH, HEIGHT, &src_format);
+        for sample in src[0].iter_mut() {
+            *sample = rng.gen::<u8>();
+        }
+
+        let src_buffers: Vec<&[u8]> = src.iter().map(|x| &x[..]).collect();
+
+        // Planes sharing a buffer use its stride
+        let planes = *num_planes as usize;
+        let logical_planes = if let PixelFormat::Nv12 = dst_pixel_format {
+            2
+        } else {
+            3
+        };
+        let mut dst_strides = [0; 3];
+        for (plane, dst_stride) in dst_strides.iter_mut().enumerate().take(logical_planes) {
+            *dst_stride = aligned_stride(
+                WIDTH,
+                *dst_pixel_format,
+                plane.min(planes - 1) as u32,
+                *alignment,
+            );
+        }
+
+        let mut dst_sizes = [0; 3];
+        assert!(get_buffers_size(
+            WIDTH,
+            HEIGHT,
+            &dst_format,
+            Some(&dst_strides),
+            &mut dst_sizes
+        )
+        .is_ok());
+
+        // Padding bytes are left untouched by convert_image
+        let mut expected: Vec<Vec<u8>> = dst_sizes[..planes]
+            .iter()
+            .map(|&size| vec![PADDING; size])
+            .collect();
+        let mut expected_buffers: Vec<&mut [u8]> =
+            expected.iter_mut().map(|x| &mut x[..]).collect();
+        assert!(convert_image(
+            WIDTH,
+            HEIGHT,
+            &src_format,
+            None,
+            &src_buffers,
+            &dst_format,
+            Some(&dst_strides[..planes]),
+            &mut expected_buffers,
+        )
+        .is_ok());
+
+        let mut dst: Vec<Vec<u8>> = dst_sizes[..planes]
+            .iter()
+            .map(|&size| (0..size).map(|_| rng.gen::<u8>()).collect())
+            .collect();
+        let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+        assert!(convert_image_aligned(
+            WIDTH,
+            HEIGHT,
+            &src_format,
+            None,
+            &src_buffers,
+            &dst_format,
+            &mut dst_buffers,
+            *alignment,
+            PADDING,
+        )
+        .is_ok());
+
+        assert_eq!(dst_buffers, expected_buffers);
+
+        assert_eq!(
+            convert_image_aligned(
+                WIDTH,
+                HEIGHT,
+                &src_format,
+                None,
+                &src_buffers,
+                &dst_format,
+                &mut dst_buffers,
+                0,
+                PADDING,
+            )
+            .err()
+            .map(|e| e as u32),
+            Some(ErrorKind::InvalidValue as u32)
+        );
+
+        let last = dst_buffers.len() - 1;
+        let size = dst_buffers[last].len() - 1;
+        let (head, tail) = dst_buffers.split_at_mut(last);
+        let mut short_buffers: Vec<&mut [u8]> = head.iter_mut().map(|x| &mut x[..]).collect();
+        short_buffers.push(&mut tail[0][..size]);
+        assert_eq!(
+            convert_image_aligned(
+                WIDTH,
+                HEIGHT,
+                &src_format,
+                None,
+                &src_buffers,
+                &dst_format,
+                &mut short_buffers,
+                *alignment,
+                PADDING,
+            )
+            .err()
+            .map(|e| e as u32),
+            Some(ErrorKind::NotEnoughData as u32)
+        );
+    }
+}
+
+#[test]
+fn diff_ok() {
+    bootstrap();
+
+    // Lines are not multiple of the vector sizes
+    const WIDTH: u32 = 70;
+    const HEIGHT: u32 = 6;
+
+    let mut rng = rand::thread_rng();
+    for (pixel_format, color_space, num_planes) in [
+        (PixelFormat::Bgra, ColorSpace::Lrgb, 1),
+        (PixelFormat::Rgb, ColorSpace::Lrgb, 1),
+        (PixelFormat::I420, ColorSpace::Bt601, 3),
+        (PixelFormat::Nv12, ColorSpace::Bt709, 1),
+    ]
+    .iter()
+    {
+        let format = ImageFormat {
+            pixel_format: *pixel_format,
+            color_space: *color_space,
+            num_planes: *num_planes,
+        };
+
+        let planes: &[(usize, usize)] = match pixel_format {
+            PixelFormat::Bgra => &[(4, 1)],
+            PixelFormat::Rgb => &[(3, 1)],
+            PixelFormat::I420 => &[(1, 1), (1, 4), (1, 4)],
+            _ => &[(1, 1), (1, 2)],
+        };
+
+        let buffers_size = &mut [0usize; 3];
+        assert!(get_buffers_size(WIDTH, HEIGHT, &format, None, buffers_size).is_ok());
+
+        let buffers_size = &buffers_size[..*num_planes as usize];
+        let a: Vec<Vec<u8>> = buffers_size
+            .iter()
+            .map(|size| (0..*size).map(|_| rng.gen::<u8>()).collect())
+            .collect();
+        let b: Vec<Vec<u8>> = a
+            .iter()
+            .map(|buffer| {
+                buffer
+                    .iter()
+                    .map(|&x| x.saturating_add(rng.gen_range(0, 4)))
+                    .collect()
+            })
+            .collect();
+        let a_buffers: Vec<&[u8]> = a.iter().map(|buffer| &buffer[..]).collect();
+        let b_buffers: Vec<&[u8]> = b.iter().map(|buffer| &buffer[..]).collect();
+
+        let stats =
+            diff::diff_images(WIDTH, HEIGHT, &format, None, &a_buffers, None, &b_buffers).unwrap();
+
+        let mut map: Vec<Vec<u8>> = buffers_size.iter().map(|size| vec![0u8; *size]).collect();
+        {
+            let mut map_buffers: Vec<&mut [u8]> = map.iter_mut().map(|b| &mut b[..]).collect();
+            assert!(diff::diff_map(
+                WIDTH,
+                HEIGHT,
+                &format,
+                None,
+                &a_buffers,
+                None,
+                &b_buffers,
+                None,
+                &mut map_buffers,
+            )
+            .is_ok());
+        }
+
+        // Logical planes are stored one after the other
+        let a: Vec<u8> = a.concat();
+        let b: Vec<u8> = b.concat();
+        let map: Vec<u8> = map.concat();
+        let pixel_count = (WIDTH as usize) * (HEIGHT as usize);
+        let mut offset = 0;
+        assert_eq!(stats.planes.len(), planes.len());
+        for (plane, &(num, den)) in stats.planes.iter().zip(planes.iter()) {
+            let size = num * pixel_count / den;
+            let deltas: Vec<u8> = a[offset..offset + size]
+                .iter()
+                .zip(b[offset..offset + size].iter())
+                .map(|(&x, &y)| x.abs_diff(y))
+                .collect();
+            let sum: u64 = deltas.iter().map(|&delta| u64::from(delta)).sum();
+
+            assert_eq!(&map[offset..offset + size], &deltas[..]);
+            assert_eq!(plane.max, *deltas.iter().max().unwrap());
+            assert!((plane.mean - (sum as f64) / (size as f64)).abs() < 1e-9);
+            offset += size;
+        }
+    }
+
+    // Strides are honored and padding is ignored
+    let format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let a = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+    let mut b = vec![255u8; (4 * (WIDTH as usize) + 8) * (HEIGHT as usize)];
+    for line in b.chunks_mut(4 * (WIDTH as usize) + 8) {
+        for value in line[..4 * (WIDTH as usize)].iter_mut() {
+            *value = 1;
+        }
+    }
+
+    let stats = diff::diff_images(
+        WIDTH,
+        HEIGHT,
+        &format,
+        None,
+        &[&a],
+        Some(&[4 * (WIDTH as usize) + 8]),
+        &[&b],
+    )
+    .unwrap();
+    assert_eq!(stats.planes[0].max, 1);
+    assert!((stats.planes[0].mean - 1.0).abs() < 1e-9);
+
+    // Errors
+    let p010_format = ImageFormat {
+        pixel_format: PixelFormat::P010,
+        color_space: ColorSpace::Bt2020Pq,
+        num_planes: 1,
+    };
+
+    let check_error = |format: &ImageFormat, a: &[u8], b: &[u8]| {
+        diff::diff_images(WIDTH, HEIGHT, format, None, &[a], None, &[b])
+            .err()
+            .map(|e| e as u32)
+    };
+
+    assert_eq!(
+        check_error(&p010_format, &b, &b),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+    assert_eq!(
+        check_error(&format, &a, &a[1..]),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+}
+
+#[test]
+fn tiling_ok() {
+    bootstrap();
+
+    // Planes are not a whole number of tiles, and are converted in several bands
+    const WIDTH: u32 = 70;
+    const HEIGHT: u32 = 150;
+    const PITCH: usize = 128;
+
+    // Tiles a linear plane, writing the tiled plane in memory order
+    fn tile(linear: &[u8], line_size: usize, lines: usize, tiling: tiling::Tiling) -> Vec<u8> {
+        let sample = |x: usize, y: usize| {
+            if x < line_size && y < lines {
+                linear[y * line_size + x]
+            } else {
+                0
+            }
+        };
+
+        let mut tiled = Vec::new();
+        match tiling {
+            tiling::Tiling::IntelYTiled => {
+                for tile_y in 0..lines.div_ceil(32) {
+                    for tile_x in 0..PITCH / 128 {
+                        for column in 0..8 {
+                            for y in 0..32 {
+                                for x in 0..16 {
+                                    tiled.push(sample(
+                                        128 * tile_x + 16 * column + x,
+                                        32 * tile_y + y,
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            tiling::Tiling::NvidiaBlockLinear { log2_block_height } => {
+                let block_height = 8 << log2_block_height;
+                for block_y in 0..lines.div_ceil(block_height) {
+                    for block_x in 0..PITCH / 64 {
+                        for gob in 0..(1 << log2_block_height) {
+                            let (x0, y0) = (64 * block_x, block_y * block_height + 8 * gob);
+                            for half in 0..2 {
+                                for y_pair in 0..4 {
+                                    for quarter in 0..2 {
+                                        for y_odd in 0..2 {
+                                            for x in 0..16 {
+                                                tiled.push(sample(
+                                                    x0 + 32 * half + 16 * quarter + x,
+                                                    y0 + 2 * y_pair + y_odd,
+                                                ));
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        tiled
+    }
+
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let width = WIDTH as usize;
+    let height = HEIGHT as usize;
+    let mut rng = rand::thread_rng();
+    for (pixel_format, num_planes, tiling) in iproduct!(
+        [PixelFormat::Nv12, PixelFormat::I420].iter(),
+        [1, 2, 3].iter(),
+        [
+            tiling::Tiling::IntelYTiled,
+            tiling::Tiling::NvidiaBlockLinear {
+                log2_block_height: 0,
+            },
+            tiling::Tiling::NvidiaBlockLinear {
+                log2_block_height: 2,
+            },
+        ]
+        .iter()
+    ) {
+        let planes: &[(usize, usize)] = match (pixel_format, num_planes) {
+            (PixelFormat::Nv12, 1) | (PixelFormat::Nv12, 2) => {
+                &[(width, height), (width, height / 2)]
+            }
+            (PixelFormat::I420, 3) => &[
+                (width, height),
+                (width / 2, height / 2),
+                (width / 2, height / 2),
+            ],
+            _ => continue,
+        };
+
+        let yuv_format = ImageFormat {
+            pixel_format: *pixel_format,
+            color_space: ColorSpace::Bt601,
+            num_planes: *num_planes,
+        };
+
+        let linear: Vec<Vec<u8>> = planes
+            .iter()
+            .map(|(line_size, lines)| (0..line_size * lines).map(|_| rng.gen::<u8>()).collect())
+            .collect();
+        let mut tiled: Vec<Vec<u8>> = planes
+            .iter()
+            .zip(linear.iter())
+            .map(|(&(line_size, lines), plane)| tile(plane, line_size, lines, *tiling))
+            .collect();
+        if *num_planes == 1 {
+            tiled = vec![tiled.concat()];
+        }
+
+        let linear_single = linear.concat();
+        let linear_buffers: Vec<&[u8]> = if *num_planes == 1 {
+            vec![&linear_single[..]]
+        } else {
+            linear.iter().map(|plane| &plane[..]).collect()
+        };
+
+        let mut expected = vec![0u8; 4 * width * height];
+        assert!(convert_image(
+            WIDTH,
+            HEIGHT,
+            &yuv_format,
+            None,
+            &linear_buffers,
+            &bgra_format,
+            None,
+            &mut [&mut expected],
+        )
+        .is_ok());
+
+        let tiled_buffers: Vec<&[u8]> = tiled.iter().map(|plane| &plane[..]).collect();
+        let mut test_output = vec![0u8; 4 * width * height];
+        assert!(tiling::convert_tiled_image(
+            WIDTH,
+            HEIGHT,
+            &yuv_format,
+            *tiling,
+            Some(&[PITCH, PITCH, PITCH]),
+            &tiled_buffers,
+            &bgra_format,
+            None,
+            &mut [&mut test_output],
+        )
+        .is_ok());
+        assert_eq!(test_output, expected);
+
+        // Errors
+        let check_error = |strides: &[usize], buffers: &[&[u8]], tiling: tiling::Tiling| {
+            let mut bgra = vec![0u8; 4 * width * height];
+            tiling::convert_tiled_image(
+                WIDTH,
+                HEIGHT,
+                &yuv_format,
+                tiling,
+                Some(strides),
+                buffers,
+                &bgra_format,
+                None,
+                &mut [&mut bgra],
+            )
+            .err()
+            .map(|e| e as u32)
+        };
+
+        assert_eq!(
+            check_error(&[96, 96, 96], &tiled_buffers, *tiling),
+            Some(ErrorKind::InvalidValue as u32)
+        );
+        assert_eq!(
+            check_error(
+                &[PITCH, PITCH, PITCH],
+                &tiled_buffers,
+                tiling::Tiling::NvidiaBlockLinear {
+                    log2_block_height: 6
+                }
+            ),
+            Some(ErrorKind::InvalidValue as u32)
+        );
+
+        let last = tiled_buffers.len() - 1;
+        let mut short_buffers = tiled_buffers.clone();
+        short_buffers[last] = &tiled_buffers[last][1..];
+        assert_eq!(
+            check_error(&[PITCH, PITCH, PITCH], &short_buffers, *tiling),
+            Some(ErrorKind::NotEnoughData as u32)
+        );
+
+        // Linear to tiled layout and back
+        let mut retiled: Vec<Vec<u8>> = tiled.iter().map(|plane| vec![0u8; plane.len()]).collect();
+        let mut retiled_buffers: Vec<&mut [u8]> =
+            retiled.iter_mut().map(|plane| &mut plane[..]).collect();
+        assert!(tiling::tile_image(
+            WIDTH,
+            HEIGHT,
+            &yuv_format,
+            None,
+            &linear_buffers,
+            *tiling,
+            Some(&[PITCH, PITCH, PITCH]),
+            &mut retiled_buffers,
+        )
+        .is_ok());
+        assert_eq!(retiled, tiled);
+
+        let mut detiled: Vec<Vec<u8>> = linear_buffers
+            .iter()
+            .map(|plane| vec![0u8; plane.len()])
+            .collect();
+        let mut detiled_buffers: Vec<&mut [u8]> =
+            detiled.iter_mut().map(|plane| &mut plane[..]).collect();
+        assert!(tiling::detile_image(
+            WIDTH,
+            HEIGHT,
+            &yuv_format,
+            *tiling,
+            Some(&[PITCH, PITCH, PITCH]),
+            &tiled_buffers,
+            None,
+            &mut detiled_buffers,
+        )
+        .is_ok());
+        for (plane, expected) in detiled.iter().zip(linear_buffers.iter()) {
+            assert_eq!(&plane[..], *expected);
+        }
+
+        let mut retiled_buffers: Vec<&mut [u8]> =
+            retiled.iter_mut().map(|plane| &mut plane[..]).collect();
+        assert_eq!(
+            tiling::tile_image(
+                WIDTH,
+                HEIGHT,
+                &yuv_format,
+                None,
+                &linear_buffers,
+                *tiling,
+                Some(&[96, 96, 96]),
+                &mut retiled_buffers,
+            )
+            .err()
+            .map(|e| e as u32),
+            Some(ErrorKind::InvalidValue as u32)
+        );
+        let short = retiled_buffers.pop().unwrap();
+        retiled_buffers.push(&mut short[1..]);
+        assert_eq!(
+            tiling::tile_image(
+                WIDTH,
+                HEIGHT,
+                &yuv_format,
+                None,
+                &linear_buffers,
+                *tiling,
+                Some(&[PITCH, PITCH, PITCH]),
+                &mut retiled_buffers,
+            )
+            .err()
+            .map(|e| e as u32),
+            Some(ErrorKind::NotEnoughData as u32)
+        );
+    }
+}
+
+#[test]
+fn bands_ok() {
+    // Pixel format, planes, width, height, threads, strides of the logical planes and their
+    // vertical shifts, expected band alignment
+    type Case = (
+        PixelFormat,
+        u32,
+        u32,
+        u32,
+        usize,
+        &'static [(usize, u32)],
+        u32,
+    );
+    let cases: &[Case] = &[
+        (
+            PixelFormat::I420,
+            3,
+            1920,
+            1080,
+            8,
+            &[(1920, 0), (960, 1), (960, 1)],
+            2,
+        ),
+        (PixelFormat::Nv12, 2, 70, 150, 4, &[(70, 0), (70, 1)], 64),
+        (PixelFormat::Nv12, 1, 70, 150, 3, &[(70, 0), (70, 1)], 64),
+        (PixelFormat::Bgra, 1, 33, 1000, 16, &[(132, 0)], 16),
+        (PixelFormat::I444, 3, 3840, 2160, 2, &[(3840, 0); 3], 1),
+        (PixelFormat::Bgra, 1, 640, 1, 8, &[(2560, 0)], 1),
+    ];
+
+    for &(pixel_format, num_planes, width, height, threads, planes, alignment) in cases {
+        let format = ImageFormat {
+            pixel_format,
+            color_space: ColorSpace::Bt601,
+            num_planes,
+        };
+
+        let bands = bands::plan_bands(width, height, &format, None, threads, None).unwrap();
+        let mut next_line = 0;
+        for band in bands.iter() {
+            assert_eq!((band.x, band.y, band.width), (0, next_line, width));
+            assert!(band.height > 0);
+            assert_eq!(band.y % alignment, 0);
+            for &(stride, vertical_shift) in planes {
+                assert_eq!(((band.y >> vertical_shift) as usize * stride) % 64, 0);
+            }
+
+            // Bands fit in a per-core cache, unless they are as small as they can be
+            let band_size: usize = planes
+                .iter()
+                .map(|&(stride, vertical_shift)| (band.height >> vertical_shift) as usize * stride)
+                .sum();
+            assert!(band_size <= 256 * 1024 || band.height <= alignment);
+            next_line += band.height;
+        }
+
+        assert_eq!(next_line, height);
+        let ideal_bands = height.div_ceil(alignment) as usize;
+        assert!(bands.len() >= threads.min(ideal_bands));
+    }
+
+    let i420_format = ImageFormat {
+        pixel_format: PixelFormat::I420,
+        color_space: ColorSpace::Bt601,
+        num_planes: 3,
+    };
+
+    // Band height override
+    let bands = bands::plan_bands(64, 32, &i420_format, None, 1, Some(10)).unwrap();
+    let heights: Vec<u32> = bands.iter().map(|band| band.height).collect();
+    assert_eq!(heights, [10, 10, 10, 2]);
+
+    let bands = bands::plan_bands(64, 0, &i420_format, None, 4, None).unwrap();
+    assert!(bands.is_empty());
+
+    // Errors
+    let check_error = |width: u32, threads: usize, band_height: Option<u32>, strides: &[usize]| {
+        bands::plan_bands(width, 32, &i420_format, Some(strides), threads, band_height)
+            .err()
+            .map(|e| e as u32)
+    };
+
+    assert_eq!(
+        check_error(64, 0, None, &[0, 0, 0]),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(63, 1, None, &[0, 0, 0]),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(64, 1, Some(0), &[0, 0, 0]),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(64, 1, Some(3), &[0, 0, 0]),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(64, 1, None, &[0, 0]),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+}
+
+#[test]
+fn self_test_ok() {
+    bootstrap();
+
+    let reports = self_test().unwrap();
+    for report in reports.iter() {
+        assert_eq!(
+            report.mismatches, 0,
+            "{} {}",
+            report.kernel, report.instruction_set
+        );
+        assert!(report.speedup > 0.0);
+    }
+
+    #[cfg(all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        not(feature = "no-sse2")
+    ))]
+    {
+        assert!(reports.iter().any(|report| report.kernel == "diff"));
+        assert!(reports.iter().any(|report| report.kernel == "cube"));
+        assert!(reports
+            .iter()
+            .any(|report| report.kernel == "Bgra-Lrgb:Nv12-Bt601"));
+    }
+}
+
+#[test]
+fn describe_kernels_ok() {
+    bootstrap();
+
+    let kernels = describe_kernels();
+    assert!(!kernels.is_empty());
+    for kernel in kernels {
+        let src_format = ImageFormat {
+            pixel_format: kernel.src_pixel_format,
+            color_space: kernel.src_color_space,
+            num_planes: 1,
+        };
+        let dst_format = ImageFormat {
+            pixel_format: kernel.dst_pixel_format,
+            color_space: kernel.dst_color_space,
+            num_planes: 1,
+        };
+
+        // Each kernel converts its formats directly
+        assert_eq!(
+            describe_conversion(2, 2, &src_format, &dst_format).unwrap(),
+            format!(
+                "{{route:[{:?}-{:?},{:?}-{:?}]}}",
+                kernel.src_pixel_format,
+                kernel.src_color_space,
+                kernel.dst_pixel_format,
+                kernel.dst_color_space
+            )
+        );
+        assert_eq!(
+            kernel.feature,
+            format!(
+                "only-{:?}-{:?}",
+                kernel.src_pixel_format, kernel.dst_pixel_format
+            )
+            .to_lowercase()
+        );
+    }
+}
+
+fn alloc_planes(width: u32, height: u32, format: &ImageFormat) -> Vec<Vec<u8>> {
+    let buffers_size = &mut [0usize; MAX_NUMBER_OF_PLANES as usize];
+    assert!(get_buffers_size(width, height, format, None, buffers_size).is_ok());
+
+    buffers_size[..format.num_planes as usize]
+        .iter()
+        .map(|&size| vec![0u8; size])
+        .collect()
+}
+
+fn convert_planes(
+    width: u32,
+    height: u32,
+    src_format: &ImageFormat,
+    src: &[Vec<u8>],
+    dst_format: &ImageFormat,
+    dst: &mut [Vec<u8>],
+) -> Result<(), ErrorKind> {
+    let src_buffers: Vec<&[u8]> = src.iter().map(|x| &x[..]).collect();
+    let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+    convert_image(
+        width,
+        height,
+        src_format,
+        None,
+        &src_buffers,
+        dst_format,
+        None,
+        &mut dst_buffers,
+    )
+}
+
+#[test]
+#[allow(unsafe_code)]
+fn register_converter_errors() {
+    bootstrap();
+
+    #[allow(clippy::too_many_arguments)]
+    fn converter(
+        _width: u32,
+        _height: u32,
+        _last_src_plane: u32,
+        _src_strides: &[usize],
+        _src_buffers: &[&[u8]],
+        _last_dst_plane: u32,
+        _dst_strides: &[usize],
+        _dst_buffers: &mut [&mut [u8]],
+    ) -> bool {
+        false
+    }
+
+    let format = |pixel_format, color_space| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes: 1,
+    };
+
+    // Pixel formats and color spaces must be compatible
+    for (src_format, dst_format) in [
+        (
+            format(PixelFormat::Bgra, ColorSpace::Bt601),
+            format(PixelFormat::Rgba, ColorSpace::Lrgb),
+        ),
+        (
+            format(PixelFormat::Bgra, ColorSpace::Lrgb),
+            format(PixelFormat::Nv12, ColorSpace::Lrgb),
+        ),
+    ]
+    .iter()
+    {
+        // Invalid formats are rejected before the conversion table is written, so this does
+        // not race with the tests running in parallel
+        assert_eq!(
+            unsafe { register_converter(src_format, dst_format, converter) }
+                .err()
+                .map(|e| e as u32),
+            Some(ErrorKind::InvalidValue as u32)
+        );
+    }
+}
+
+#[cfg(not(feature = "noise"))]
+#[test]
+fn routing_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 6;
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    // Direct conversions have no intermediate images
+    assert_eq!(
+        describe_conversion(
+            WIDTH,
+            HEIGHT,
+            &format(PixelFormat::Bgra, ColorSpace::Lrgb, 1),
+            &format(PixelFormat::Nv12, ColorSpace::Bt601, 2),
+        )
+        .unwrap(),
+        "{route:[Bgra-Lrgb,Nv12-Bt601]}"
+    );
+
+    // A routed conversion gives the same result as converting one hop after the other
+    let mut rng = rand::thread_rng();
+    for (route, description) in [
+        (
+            vec![
+                format(PixelFormat::Rgb, ColorSpace::Lrgb, 1),
+                format(PixelFormat::Bgra, ColorSpace::Lrgb, 1),
+                format(PixelFormat::Nv12, ColorSpace::Bt601, 2),
+            ],
+            "{route:[Rgb-Lrgb,Bgra-Lrgb,Nv12-Bt601]}",
+        ),
+        (
+            vec![
+                format(PixelFormat::Nv12, ColorSpace::Bt601, 1),
+                format(PixelFormat::Bgra, ColorSpace::Lrgb, 1),
+                format(PixelFormat::I420, ColorSpace::Bt709, 3),
+            ],
+            "{route:[Nv12-Bt601,Bgra-Lrgb,I420-Bt709]}",
+        ),
+        (
+            // Intermediate images without chroma subsampling are preferred
+            vec![
+                format(PixelFormat::Argb, ColorSpace::Lrgb, 1),
+                format(PixelFormat::I444, ColorSpace::Bt601, 3),
+                format(PixelFormat::Bgra, ColorSpace::Lrgb, 1),
+                format(PixelFormat::Rgb, ColorSpace::Lrgb, 1),
+            ],
+            "{route:[Argb-Lrgb,I444-Bt601,Bgra-Lrgb,Rgb-Lrgb]}",
+        ),
+        (
+            vec![
+                format(PixelFormat::P010, ColorSpace::Bt2020Pq, 2),
+                format(PixelFormat::RgbaF16, ColorSpace::Lrgb, 1),
+                format(PixelFormat::Bgra, ColorSpace::Lrgb, 1),
+                format(PixelFormat::Nv12, ColorSpace::Bt709, 1),
+            ],
+            "{route:[P010-Bt2020Pq,RgbaF16-Lrgb,Bgra-Lrgb,Nv12-Bt709]}",
+        ),
+    ]
+    .iter()
+    {
+        let src_format = &route[0];
+        let dst_format = &route[route.len() - 1];
+        assert_eq!(
+            describe_conversion(WIDTH, HEIGHT, src_format, dst_format).unwrap(),
+            *description
+        );
+
+        let mut src = alloc_planes(WIDTH, HEIGHT, src_format);
+        for buffer in src.iter_mut() {
+            for x in buffer.iter_mut() {
+                *x = rng.gen::<u8>();
+            }
+        }
+
+        // Keep half floats finite
+        if let PixelFormat::P010 = src_format.pixel_format {
+            for buffer in src.iter_mut() {
+                for x in buffer.iter_mut().skip(1).step_by(2) {
+                    *x &= 0x3F;
+                }
+            }
+        }
+
+        let mut expected = src.clone();
+        for hop in route.windows(2) {
+            let mut next = alloc_planes(WIDTH, HEIGHT, &hop[1]);
+            assert!(convert_planes(WIDTH, HEIGHT, &hop[0], &expected, &hop[1], &mut next).is_ok());
+            expected = next;
+        }
+
+        let mut dst = alloc_planes(WIDTH, HEIGHT, dst_format);
+        assert!(convert_planes(WIDTH, HEIGHT, src_format, &src, dst_format, &mut dst).is_ok());
+        assert_eq!(dst, expected);
+    }
+
+    // Buffers provided by the caller are still checked
+    let src_format = format(PixelFormat::Rgb, ColorSpace::Lrgb, 1);
+    let dst_format = format(PixelFormat::Nv12, ColorSpace::Bt601, 2);
+    let src = alloc_planes(WIDTH, HEIGHT, &src_format);
+    let mut dst = alloc_planes(WIDTH, HEIGHT - 2, &dst_format);
+    assert_eq!(
+        convert_planes(WIDTH, HEIGHT, &src_format, &src, &dst_format, &mut dst)
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+
+    // Intermediate images must satisfy the size constraints of their pixel format
+    assert_eq!(
+        describe_conversion(
+            WIDTH + 1,
+            HEIGHT + 1,
+            &format(PixelFormat::Argb, ColorSpace::Lrgb, 1),
+            &format(PixelFormat::Rgb, ColorSpace::Lrgb, 1),
+        )
+        .unwrap(),
+        "{route:[Argb-Lrgb,I444-Bt601,Bgra-Lrgb,Rgb-Lrgb]}"
+    );
+
+    // Formats which can not be reached, or do not need any conversion
+    for (src_format, dst_format) in [
+        (
+            format(PixelFormat::Rgba, ColorSpace::Lrgb, 1),
+            format(PixelFormat::Nv12, ColorSpace::Bt601, 1),
+        ),
+        (
+            format(PixelFormat::Nv12, ColorSpace::Bt601, 1),
+            format(PixelFormat::Argb, ColorSpace::Lrgb, 1),
+        ),
+        (
+            format(PixelFormat::Nv12, ColorSpace::Bt601, 1),
+            format(PixelFormat::Nv12, ColorSpace::Bt601, 2),
+        ),
+    ]
+    .iter()
+    {
+        assert_eq!(
+            describe_conversion(WIDTH, HEIGHT, src_format, dst_format)
+                .err()
+                .map(|e| e as u32),
+            Some(ErrorKind::InvalidOperation as u32)
+        );
+
+        let src = alloc_planes(WIDTH, HEIGHT, src_format);
+        let mut dst = alloc_planes(WIDTH, HEIGHT, dst_format);
+        assert_eq!(
+            convert_planes(WIDTH, HEIGHT, src_format, &src, dst_format, &mut dst)
+                .err()
+                .map(|e| e as u32),
+            Some(ErrorKind::InvalidOperation as u32)
+        );
+    }
+}
+
+#[cfg(not(feature = "noise"))]
+#[test]
+fn routing_bands_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const PADDING: usize = 6;
+
+    let format = |pixel_format, color_space| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes: 1,
+    };
+
+    // Routed conversions are done in bands: images spanning several bands, with a partial
+    // last band and padded lines, give the same result as converting one hop after the other
+    let src_format = format(PixelFormat::Rgb, ColorSpace::Lrgb);
+    let bgra_format = format(PixelFormat::Bgra, ColorSpace::Lrgb);
+    let dst_format = format(PixelFormat::Nv12, ColorSpace::Bt601);
+    let mut rng = rand::thread_rng();
+    for &height in &[2, 64, 66, 130, 1030] {
+        let mut src = alloc_planes(WIDTH, height, &src_format);
+        for x in src[0].iter_mut() {
+            *x = rng.gen::<u8>();
+        }
+
+        let mut bgra = alloc_planes(WIDTH, height, &bgra_format);
+        assert!(convert_planes(WIDTH, height, &src_format, &src, &bgra_format, &mut bgra).is_ok());
+        let mut expected = alloc_planes(WIDTH, height, &dst_format);
+        assert!(convert_planes(
+            WIDTH,
+            height,
+            &bgra_format,
+            &bgra,
+            &dst_format,
+            &mut expected
+        )
+        .is_ok());
+
+        let src_line = 3 * WIDTH as usize;
+        let src_stride = src_line + PADDING;
+        let mut padded_src = vec![0u8; src_stride * height as usize];
+        for (line, padded_line) in src[0]
+            .chunks(src_line)
+            .zip(padded_src.chunks_mut(src_stride))
+        {
+            padded_line[..src_line].copy_from_slice(line);
+        }
+
+        let dst_line = WIDTH as usize;
+        let dst_stride = dst_line + PADDING;
+        let mut dst = vec![0u8; dst_stride * (3 * height as usize) / 2];
+        assert!(convert_image(
+            WIDTH,
+            height,
+            &src_format,
+            Some(&[src_stride]),
+            &[&padded_src[..]],
+            &dst_format,
+            Some(&[dst_stride]),
+            &mut [&mut dst[..]],
+        )
+        .is_ok());
+
+        for (line, padded_line) in expected[0].chunks(dst_line).zip(dst.chunks(dst_stride)) {
+            assert_eq!(line, &padded_line[..dst_line]);
+        }
+
+        // Lines can not overlap
+        assert_eq!(
+            convert_image(
+                WIDTH,
+                height,
+                &src_format,
+                Some(&[src_line - 1]),
+                &[&padded_src[..]],
+                &dst_format,
+                Some(&[dst_stride]),
+                &mut [&mut dst[..]],
+            )
+            .err()
+            .map(|e| e as u32),
+            Some(ErrorKind::NotEnoughData as u32)
+        );
+    }
+}
+
+#[cfg(not(feature = "noise"))]
+#[test]
+fn split_planes_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 6;
+    const STRIDE: usize = 48;
+
+    let format = |pixel_format, num_planes| ImageFormat {
+        pixel_format,
+        color_space: ColorSpace::Bt601,
+        num_planes,
+    };
+
+    for (format, strides, expected) in [
+        (format(PixelFormat::Nv12, 1), None, vec![306]),
+        (format(PixelFormat::Nv12, 2), None, vec![204, 102]),
+        (
+            format(PixelFormat::Nv12, 2),
+            Some(&[STRIDE, STRIDE][..]),
+            vec![288, 144],
+        ),
+        (format(PixelFormat::I420, 3), None, vec![204, 51, 51]),
+        (format(PixelFormat::I444, 3), None, vec![204, 204, 204]),
+    ]
+    .iter()
+    {
+        let total_size: usize = expected.iter().sum();
+        let mut buffer = vec![0u8; total_size + 1];
+        let base = buffer.as_ptr() as usize;
+
+        let planes = split_planes(WIDTH, HEIGHT, format, *strides, &buffer).unwrap();
+        let sizes: Vec<usize> = planes.iter().map(|plane| plane.len()).collect();
+        assert_eq!(&sizes, expected);
+
+        let mut offset = 0;
+        for (plane, size) in planes.iter().zip(expected.iter()) {
+            assert_eq!(plane.as_ptr() as usize - base, offset);
+            offset += size;
+        }
+
+        let planes = split_planes_mut(WIDTH, HEIGHT, format, *strides, &mut buffer).unwrap();
+        let sizes: Vec<usize> = planes.iter().map(|plane| plane.len()).collect();
+        assert_eq!(&sizes, expected);
+
+        assert_eq!(
+            split_planes(WIDTH, HEIGHT, format, *strides, &buffer[..total_size - 1])
+                .err()
+                .map(|e| e as u32),
+            Some(ErrorKind::NotEnoughData as u32)
+        );
+        assert_eq!(
+            split_planes_mut(
+                WIDTH,
+                HEIGHT,
+                format,
+                *strides,
+                &mut buffer[..total_size - 1]
+            )
+            .err()
+            .map(|e| e as u32),
+            Some(ErrorKind::NotEnoughData as u32)
+        );
+    }
+
+    // The planes of a split allocation can be converted
+    let src_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+    let dst_format = format(PixelFormat::Nv12, 2);
+    let src_buffer = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+    let mut dst_buffer = vec![0u8; 306];
+    let mut dst_buffers =
+        split_planes_mut(WIDTH, HEIGHT, &dst_format, None, &mut dst_buffer).unwrap();
+    assert!(convert_image(
+        WIDTH,
+        HEIGHT,
+        &src_format,
+        None,
+        &[&src_buffer],
+        &dst_format,
+        None,
+        &mut dst_buffers,
+    )
+    .is_ok());
+    assert!(dst_buffer[..204].iter().all(|&y| y == 16));
+    assert!(dst_buffer[204..].iter().all(|&uv| uv == 128));
+
+    // Errors of get_buffers_size
+    assert_eq!(
+        split_planes(WIDTH + 1, HEIGHT, &dst_format, None, &dst_buffer)
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        split_planes(
+            WIDTH,
+            HEIGHT,
+            &format(PixelFormat::Nv12, 3),
+            None,
+            &dst_buffer
+        )
+        .err()
+        .map(|e| e as u32),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        split_planes(WIDTH, HEIGHT, &dst_format, Some(&[STRIDE][..]), &dst_buffer)
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+}
+
+#[test]
+fn io_slices_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 6;
+    const STRIDE: usize = 48;
+
+    let format = |pixel_format, num_planes| ImageFormat {
+        pixel_format,
+        color_space: ColorSpace::Bt601,
+        num_planes,
+    };
+
+    // Tightly packed images are a single segment per buffer
+    let buffer: Vec<u8> = (0..306).map(|i| i as u8).collect();
+    let nv12 = format(PixelFormat::Nv12, 1);
+    let slices = get_io_slices(WIDTH, HEIGHT, &nv12, None, &[&buffer]).unwrap();
+    assert_eq!(slices.len(), 1);
+    assert_eq!(&*slices[0], &buffer[..]);
+
+    let i420 = format(PixelFormat::I420, 3);
+    let planes = split_planes(WIDTH, HEIGHT, &i420, None, &buffer).unwrap();
+    let slices = get_io_slices(WIDTH, HEIGHT, &i420, None, &planes).unwrap();
+    assert_eq!(slices.len(), 3);
+    let packed: Vec<u8> = slices
+        .iter()
+        .flat_map(|slice| slice.iter().copied())
+        .collect();
+    assert_eq!(packed, buffer);
+
+    // Padding is skipped, line after line
+    let nv12 = format(PixelFormat::Nv12, 2);
+    let strides = &[STRIDE, STRIDE][..];
+    let y_plane: Vec<u8> = (0..STRIDE * (HEIGHT as usize))
+        .map(|i| if i % STRIDE < WIDTH as usize { 1 } else { 0 })
+        .collect();
+    let uv_plane: Vec<u8> = (0..STRIDE * (HEIGHT as usize) / 2)
+        .map(|i| if i % STRIDE < WIDTH as usize { 2 } else { 0 })
+        .collect();
+    let slices =
+        get_io_slices(WIDTH, HEIGHT, &nv12, Some(strides), &[&y_plane, &uv_plane]).unwrap();
+    assert_eq!(slices.len(), (HEIGHT as usize) * 3 / 2);
+    let packed: Vec<u8> = slices
+        .iter()
+        .flat_map(|slice| slice.iter().copied())
+        .collect();
+    assert_eq!(packed.len(), 306);
+    assert!(packed[..204].iter().all(|&y| y == 1));
+    assert!(packed[204..].iter().all(|&uv| uv == 2));
+
+    // Empty images have no segments
+    assert!(get_io_slices(0, HEIGHT, &nv12, None, &[])
+        .unwrap()
+        .is_empty());
+
+    // Errors
+    assert_eq!(
+        get_io_slices(
+            WIDTH,
+            HEIGHT,
+            &nv12,
+            Some(strides),
+            &[&y_plane, &uv_plane[..1]]
+        )
+        .err()
+        .map(|e| e as u32),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+    assert_eq!(
+        get_io_slices(WIDTH + 1, HEIGHT, &nv12, None, &[&y_plane, &uv_plane])
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        get_io_slices(
+            WIDTH,
+            HEIGHT,
+            &nv12,
+            Some(&[STRIDE][..]),
+            &[&y_plane, &uv_plane]
+        )
+        .err()
+        .map(|e| e as u32),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+}
+
+#[cfg(not(feature = "noise"))]
+#[test]
+fn frame_ok() {
+    use dcp::frame::{FrameDescriptor, PlaneDescriptor};
+
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 6;
+    const STRIDE: usize = 48;
+    const UV_OFFSET: usize = 320;
+
+    let plane = |memory, offset, stride| PlaneDescriptor {
+        memory,
+        offset,
+        stride,
+    };
+    let frame = |pixel_format, num_planes, planes| FrameDescriptor {
+        width: WIDTH,
+        height: HEIGHT,
+        format: ImageFormat {
+            pixel_format,
+            color_space: ColorSpace::Bt601,
+            num_planes,
+        },
+        planes,
+    };
+
+    // Two planes sharing a mapping, with padding between them
+    let nv12 = frame(
+        PixelFormat::Nv12,
+        2,
+        vec![plane(0, 0, STRIDE), plane(0, UV_OFFSET, STRIDE)],
+    );
+    let mut mapping = vec![0u8; UV_OFFSET + STRIDE * (HEIGHT as usize) / 2];
+    for (i, value) in mapping.iter_mut().enumerate() {
+        *value = if i < UV_OFFSET { 16 } else { 128 };
+    }
+
+    let planes = nv12.import(&[&mapping]).unwrap();
+    assert_eq!(planes.len(), 2);
+    assert_eq!(planes[0].as_ptr(), mapping.as_ptr());
+    assert_eq!(planes[0].len(), STRIDE * (HEIGHT as usize));
+    assert_eq!(planes[1].as_ptr(), mapping[UV_OFFSET..].as_ptr());
+    assert_eq!(planes[1].len(), STRIDE * (HEIGHT as usize) / 2);
+    assert_eq!(nv12.strides(), vec![STRIDE, STRIDE]);
+
+    let dst_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+    let mut dst_buffer = vec![0xFFu8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+    assert!(nv12
+        .convert(&[&mapping], &dst_format, None, &mut [&mut dst_buffer])
+        .is_ok());
+    assert!(dst_buffer
+        .chunks(4)
+        .all(|pixel| pixel[..3].iter().all(|&x| x == 0)));
+
+    // Planes in different mappings, packed
+    let i420 = frame(
+        PixelFormat::I420,
+        3,
+        vec![
+            plane(2, 0, STRIDE_AUTO),
+            plane(0, 7, STRIDE_AUTO),
+            plane(1, 0, STRIDE_AUTO),
+        ],
+    );
+    let y_mapping = vec![0u8; 204];
+    let u_mapping = vec![0u8; 58];
+    let v_mapping = vec![0u8; 51];
+    let planes = i420.import(&[&u_mapping, &v_mapping, &y_mapping]).unwrap();
+    let sizes: Vec<usize> = planes.iter().map(|plane| plane.len()).collect();
+    assert_eq!(sizes, vec![204, 51, 51]);
+    assert!(i420.validate(&[&u_mapping, &v_mapping, &y_mapping]).is_ok());
+
+    // A single plane holds all the logical planes
+    let packed = frame(PixelFormat::Nv12, 1, vec![plane(0, 2, STRIDE)]);
+    let planes = packed.import(&[&mapping]).unwrap();
+    assert_eq!(planes[0].len(), STRIDE * (HEIGHT as usize) * 3 / 2);
+
+    // Empty images do not access the mappings
+    let mut empty = frame(PixelFormat::Nv12, 2, vec![plane(3, 0, 0), plane(3, 0, 0)]);
+    empty.width = 0;
+    assert!(empty.validate(&[]).is_ok());
+
+    // Errors
+    for (frame, mappings, error) in [
+        (
+            frame(PixelFormat::Nv12, 2, vec![plane(0, 0, STRIDE)]),
+            vec![&mapping[..]],
+            ErrorKind::InvalidValue,
+        ),
+        (
+            frame(PixelFormat::Nv12, 3, vec![plane(0, 0, STRIDE); 3]),
+            vec![&mapping[..]],
+            ErrorKind::InvalidValue,
+        ),
+        (
+            frame(
+                PixelFormat::Nv12,
+                2,
+                vec![plane(0, 0, STRIDE), plane(1, UV_OFFSET, STRIDE)],
+            ),
+            vec![&mapping[..]],
+            ErrorKind::NotEnoughData,
+        ),
+        (
+            frame(
+                PixelFormat::Nv12,
+                2,
+                vec![plane(0, 0, STRIDE), plane(0, UV_OFFSET + 1, STRIDE)],
+            ),
+            vec![&mapping[..]],
+            ErrorKind::NotEnoughData,
+        ),
+        (
+            frame(
+                PixelFormat::Nv12,
+                2,
+                vec![plane(0, 0, STRIDE), plane(0, usize::MAX, STRIDE)],
+            ),
+            vec![&mapping[..]],
+            ErrorKind::NotEnoughData,
+        ),
+        (
+            frame(
+                PixelFormat::Nv12,
+                2,
+                vec![plane(0, 0, 33), plane(0, UV_OFFSET, STRIDE)],
+            ),
+            vec![&mapping[..]],
+            ErrorKind::NotEnoughData,
+        ),
+    ] {
+        assert_eq!(
+            frame.validate(&mappings).err().map(|e| e as u32),
+            Some(error as u32)
+        );
+    }
+
+    let mut odd = frame(PixelFormat::Nv12, 1, vec![plane(0, 0, STRIDE)]);
+    odd.width = WIDTH + 1;
+    assert_eq!(
+        odd.validate(&[&mapping]).err().map(|e| e as u32),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+}
+
+#[cfg(not(feature = "noise"))]
+#[test]
+fn regions_ok() {
+    use dcp::regions::{convert_regions, Rect};
+
+    bootstrap();
+
+    const WIDTH: u32 = 38;
+    const HEIGHT: u32 = 10;
+    const PATTERN: u8 = 0x5A;
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    // Logical planes, as (bytes per sample, horizontal and vertical subsampling shifts)
+    let logical_planes = |pixel_format| match pixel_format {
+        PixelFormat::Bgra => vec![(4, 0, 0)],
+        PixelFormat::Rgb => vec![(3, 0, 0)],
+        PixelFormat::Nv12 => vec![(1, 0, 0), (2, 1, 1)],
+        PixelFormat::I420 => vec![(1, 0, 0), (1, 1, 1), (1, 1, 1)],
+        _ => vec![(1, 0, 0), (1, 0, 0), (1, 0, 0)],
+    };
+
+    // Returns (buffer, offset, stride, line size, line count, shifts) of each logical plane
+    let get_layouts = |format: &ImageFormat, strides: &[usize]| {
+        let last_plane = (format.num_planes - 1) as usize;
+        let mut layouts = Vec::new();
+        let mut offset = 0;
+        for (i, &(depth, horizontal_shift, vertical_shift)) in
+            logical_planes(format.pixel_format).iter().enumerate()
+        {
+            let buffer = i.min(last_plane);
+            if buffer == i {
+                offset = 0;
+            }
+
+            let line_count = (HEIGHT >> vertical_shift) as usize;
+            let line_size = depth * ((WIDTH >> horizontal_shift) as usize);
+            layouts.push((
+                buffer,
+                offset,
+                strides[buffer],
+                line_size,
+                line_count,
+                (depth, horizontal_shift, vertical_shift),
+            ));
+            offset += strides[buffer] * line_count;
+        }
+
+        layouts
+    };
+
+    let bgra = || format(PixelFormat::Bgra, ColorSpace::Lrgb, 1);
+    let mut rng = rand::thread_rng();
+    for (src_format, dst_format) in [
+        (bgra(), format(PixelFormat::Nv12, ColorSpace::Bt601, 1)),
+        (bgra(), format(PixelFormat::Nv12, ColorSpace::Bt709, 2)),
+        (bgra(), format(PixelFormat::I420, ColorSpace::Bt601, 3)),
+        (bgra(), format(PixelFormat::I444, ColorSpace::Bt709, 3)),
+        (format(PixelFormat::Rgb, ColorSpace::Lrgb, 1), bgra()),
+        (format(PixelFormat::Nv12, ColorSpace::Bt601, 1), bgra()),
+        (format(PixelFormat::I420, ColorSpace::Bt709, 3), bgra()),
+        (
+            format(PixelFormat::Nv12, ColorSpace::Bt601, 2),
+            format(PixelFormat::Nv12, ColorSpace::Bt709, 2),
+        ),
+    ]
+    .iter()
+    {
+        let (horizontal_shift, vertical_shift) = logical_planes(src_format.pixel_format)
+            .iter()
+            .chain(logical_planes(dst_format.pixel_format).iter())
+            .fold((0, 0), |(h, v), &(_, hs, vs)| (h.max(hs), v.max(vs)));
+
+        for padding in [0, 8].iter() {
+            let get_strides = |format: &ImageFormat| -> Vec<usize> {
+                logical_planes(format.pixel_format)
+                    .iter()
+                    .take(format.num_planes as usize)
+                    .map(|&(depth, hs, _)| depth * ((WIDTH >> hs) as usize) + padding)
+                    .collect()
+            };
+
+            let src_strides = get_strides(src_format);
+            let dst_strides = get_strides(dst_format);
+            // Planes sharing a buffer use the stride of the buffer
+            let get_sizes = |format: &ImageFormat, strides: &[usize]| {
+                let mut sizes = vec![0; format.num_planes as usize];
+                for (buffer, offset, stride, _, line_count, _) in get_layouts(format, strides) {
+                    sizes[buffer] = offset + stride * line_count;
+                }
+
+                sizes
+            };
+
+            let src_sizes = get_sizes(src_format, &src_strides);
+            let dst_sizes = get_sizes(dst_format, &dst_strides);
+            let mut src_planes: Vec<Vec<u8>> = src_sizes
+                .iter()
+                .map(|&size| (0..size).map(|_| rng.gen::<u8>()).collect())
+                .collect();
+
+            // Random chroma samples saturate: take them from a random rgb image instead
+            if let ColorSpace::Bt601 | ColorSpace::Bt709 = src_format.color_space {
+                let rgb_image: Vec<u8> = (0..4 * WIDTH * HEIGHT).map(|_| rng.gen::<u8>()).collect();
+                let mut src_buffers: Vec<&mut [u8]> =
+                    src_planes.iter_mut().map(|plane| &mut plane[..]).collect();
+                assert!(convert_image(
+                    WIDTH,
+                    HEIGHT,
+                    &bgra(),
+                    None,
+                    &[&rgb_image],
+                    src_format,
+                    Some(&src_strides),
+                    &mut src_buffers,
+                )
+                .is_ok());
+            }
+
+            let src_buffers: Vec<&[u8]> = src_planes.iter().map(|plane| &plane[..]).collect();
+            let new_dst =
+                || -> Vec<Vec<u8>> { dst_sizes.iter().map(|&size| vec![PATTERN; size]).collect() };
+
+            let mut reference = new_dst();
+            {
+                let mut dst_buffers: Vec<&mut [u8]> =
+                    reference.iter_mut().map(|plane| &mut plane[..]).collect();
+                assert!(convert_image(
+                    WIDTH,
+                    HEIGHT,
+                    src_format,
+                    Some(&src_strides),
+                    &src_buffers,
+                    dst_format,
+                    Some(&dst_strides),
+                    &mut dst_buffers,
+                )
+                .is_ok());
+            }
+
+            // Regions covering the whole image give the same result as a full conversion,
+            // while a single region does not touch anything outside of its aligned bounds
+            for regions in [
+                vec![
+                    Rect {
+                        x: 0,
+                        y: 0,
+                        width: 13,
+                        height: 3,
+                    },
+                    Rect {
+                        x: 13,
+                        y: 0,
+                        width: 25,
+                        height: 3,
+                    },
+                    Rect {
+                        x: 0,
+                        y: 3,
+                        width: 7,
+                        height: 7,
+                    },
+                    Rect {
+                        x: 7,
+                        y: 3,
+                        width: 31,
+                        height: 7,
+                    },
+                ],
+                vec![Rect {
+                    x: 5,
+                    y: 3,
+                    width: 9,
+                    height: 4,
+                }],
+                vec![Rect {
+                    x: 31,
+                    y: 7,
+                    width: 7,
+                    height: 3,
+                }],
+                vec![Rect {
+                    x: 3,
+                    y: 5,
+                    width: 0,
+                    height: 3,
+                }],
+            ]
+            .iter()
+            {
+                let mut dst_planes = new_dst();
+                {
+                    let mut dst_buffers: Vec<&mut [u8]> =
+                        dst_planes.iter_mut().map(|plane| &mut plane[..]).collect();
+                    assert!(convert_regions(
+                        WIDTH,
+                        HEIGHT,
+                        src_format,
+                        Some(&src_strides),
+                        &src_buffers,
+                        dst_format,
+                        Some(&dst_strides),
+                        &mut dst_buffers,
+                        regions,
+                    )
+                    .is_ok());
+                }
+
+                let is_inside = |x: u32, y: u32| {
+                    regions.iter().any(|region| {
+                        let align = |value: u32, shift: u32| value >> shift << shift;
+                        let right = align(
+                            region.x + region.width + (1 << horizontal_shift) - 1,
+                            horizontal_shift,
+                        );
+                        let bottom = align(
+                            region.y + region.height + (1 << vertical_shift) - 1,
+                            vertical_shift,
+                        );
+                        region.width > 0
+                            && region.height > 0
+                            && x >= align(region.x, horizontal_shift)
+                            && y >= align(region.y, vertical_shift)
+                            && x < right
+                            && y < bottom
+                    })
+                };
+
+                for (buffer, offset, stride, line_size, line_count, (depth, hs, vs)) in
+                    get_layouts(dst_format, &dst_strides)
+                {
+                    for (line, byte) in iproduct!(0..line_count, 0..stride) {
+                        let position = offset + line * stride + byte;
+                        let inside = byte < line_size
+                            && is_inside(((byte / depth) as u32) << hs, (line as u32) << vs);
+                        let expected = if inside {
+                            reference[buffer][position]
+                        } else {
+                            PATTERN
+                        };
+
+                        assert_eq!(dst_planes[buffer][position], expected);
+                    }
+                }
+            }
+        }
+    }
+
+    let nv12 = format(PixelFormat::Nv12, ColorSpace::Bt601, 1);
+    let src_buffer = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+    let mut dst_buffer = vec![0u8; 3 * (WIDTH as usize) * (HEIGHT as usize) / 2];
+    let mut convert = |src_format: &ImageFormat, dst_size: usize, region: Rect| {
+        convert_regions(
+            WIDTH,
+            HEIGHT,
+            src_format,
+            None,
+            &[&src_buffer],
+            &nv12,
+            None,
+            &mut [&mut dst_buffer[..dst_size]],
+            &[Rect::default(), region],
+        )
+        .err()
+        .map(|e| e as u32)
+    };
+
+    let size = 3 * (WIDTH as usize) * (HEIGHT as usize) / 2;
+    let region = Rect {
+        x: 30,
+        y: 0,
+        width: 8,
+        height: 10,
+    };
+    assert_eq!(convert(&bgra(), size, region), None);
+    assert_eq!(
+        convert(&bgra(), size, Rect { x: 31, ..region }),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        convert(
+            &bgra(),
+            size,
+            Rect {
+                height: 11,
+                ..region
+            }
+        ),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        convert(&bgra(), size - 1, region),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+    assert_eq!(
+        convert(&format(PixelFormat::Rgb, ColorSpace::Lrgb, 1), size, region),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+}
+
+#[test]
+fn region_padded_ok() {
+    use dcp::regions::{convert_region_padded, EdgePolicy, Rect};
+
+    bootstrap();
+
+    const WIDTH: u32 = 37;
+    const HEIGHT: u32 = 9;
+    const DST_WIDTH: u32 = 16;
+    const DST_HEIGHT: u32 = 8;
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    let bgra = format(PixelFormat::Bgra, ColorSpace::Lrgb, 1);
+    let mut rng = rand::thread_rng();
+    let mut src = alloc_planes(WIDTH, HEIGHT, &bgra);
+    for sample in src[0].iter_mut() {
+        *sample = rng.gen::<u8>();
+    }
+
+    let src_buffers: Vec<&[u8]> = src.iter().map(|x| &x[..]).collect();
+    for (dst_format, region) in iproduct!(
+        [
+            format(PixelFormat::Nv12, ColorSpace::Bt601, 1),
+            format(PixelFormat::I420, ColorSpace::Bt709, 3),
+            format(PixelFormat::I444, ColorSpace::Bt601, 3),
+        ]
+        .iter(),
+        [
+            // Touching the right and bottom edges, with odd sizes
+            Rect {
+                x: 28,
+                y: 2,
+                width: 9,
+                height: 7,
+            },
+            Rect {
+                x: 3,
+                y: 1,
+                width: DST_WIDTH,
+                height: DST_HEIGHT,
+            },
+            Rect {
+                x: 36,
+                y: 8,
+                width: 1,
+                height: 1,
+            },
+        ]
+        .iter()
+    ) {
+        // Reference source image, explicitly padded by repeating the last column and line
+        let mut padded = alloc_planes(DST_WIDTH, DST_HEIGHT, &bgra);
+        for (y, x) in iproduct!(0..DST_HEIGHT, 0..DST_WIDTH) {
+            let src_x = region.x + x.min(region.width - 1);
+            let src_y = region.y + y.min(region.height - 1);
+            let src_start = 4 * (src_y * WIDTH + src_x) as usize;
+            let dst_start = 4 * (y * DST_WIDTH + x) as usize;
+            padded[0][dst_start..dst_start + 4].copy_from_slice(&src[0][src_start..src_start + 4]);
+        }
+
+        let mut expected = alloc_planes(DST_WIDTH, DST_HEIGHT, dst_format);
+        assert!(convert_planes(
+            DST_WIDTH,
+            DST_HEIGHT,
+            &bgra,
+            &padded,
+            dst_format,
+            &mut expected
+        )
+        .is_ok());
+
+        let mut dst = alloc_planes(DST_WIDTH, DST_HEIGHT, dst_format);
+        let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+        assert!(convert_region_padded(
+            WIDTH,
+            HEIGHT,
+            &bgra,
+            None,
+            &src_buffers,
+            region,
+            DST_WIDTH,
+            DST_HEIGHT,
+            dst_format,
+            None,
+            &mut dst_buffers,
+            EdgePolicy::Replicate,
+        )
+        .is_ok());
+        assert_eq!(dst, expected);
+
+        // Samples entirely past the region are zero, partially covered ones are kept
+        let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+        assert!(convert_region_padded(
+            WIDTH,
+            HEIGHT,
+            &bgra,
+            None,
+            &src_buffers,
+            region,
+            DST_WIDTH,
+            DST_HEIGHT,
+            dst_format,
+            None,
+            &mut dst_buffers,
+            EdgePolicy::Zero,
+        )
+        .is_ok());
+
+        let logical_planes = match dst_format.pixel_format {
+            PixelFormat::Nv12 => vec![(0, 0, 1, 0), (0, DST_WIDTH * DST_HEIGHT, 2, 1)],
+            PixelFormat::I420 => vec![(0, 0, 1, 0), (1, 0, 1, 1), (2, 0, 1, 1)],
+            _ => vec![(0, 0, 1, 0), (1, 0, 1, 0), (2, 0, 1, 0)],
+        };
+
+        for &(buffer, offset, depth, shift) in &logical_planes {
+            let columns = (region.width + (1 << shift) - 1) >> shift;
+            let lines = (region.height + (1 << shift) - 1) >> shift;
+            let line_size = depth * (DST_WIDTH >> shift);
+            for (line, byte) in iproduct!(0..DST_HEIGHT >> shift, 0..line_size) {
+                let position = (offset + line * line_size + byte) as usize;
+                let value = if line < lines && byte / depth < columns {
+                    expected[buffer][position]
+                } else {
+                    0
+                };
+
+                assert_eq!(dst[buffer][position], value);
+            }
+        }
+    }
+
+    let i420 = format(PixelFormat::I420, ColorSpace::Bt601, 3);
+    let mut dst = alloc_planes(DST_WIDTH, DST_HEIGHT, &i420);
+    let mut convert = |src_format: &ImageFormat, src_buffers: &[&[u8]], region: Rect| {
+        let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+        convert_region_padded(
+            WIDTH - 1,
+            HEIGHT - 1,
+            src_format,
+            None,
+            src_buffers,
+            &region,
+            DST_WIDTH,
+            DST_HEIGHT,
+            &i420,
+            None,
+            &mut dst_buffers,
+            EdgePolicy::Replicate,
+        )
+        .err()
+        .map(|e| e as u32)
+    };
+
+    let region = Rect {
+        x: 20,
+        y: 0,
+        width: 16,
+        height: 8,
+    };
+    assert_eq!(convert(&bgra, &src_buffers, region), None);
+    assert_eq!(
+        convert(&bgra, &src_buffers, Rect { x: 21, ..region }),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        convert(
+            &bgra,
+            &src_buffers,
+            Rect {
+                width: 17,
+                x: 0,
+                ..region
+            }
+        ),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        convert(&bgra, &src_buffers, Rect { width: 0, ..region }),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        convert(&bgra, &[&src[0][..100]], region),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+
+    // Regions of subsampled sources start on a chroma sample
+    let src_i420 = alloc_planes(WIDTH - 1, HEIGHT - 1, &i420);
+    let src_i420_buffers: Vec<&[u8]> = src_i420.iter().map(|x| &x[..]).collect();
+    let i420_to_bgra = |region: Rect| {
+        let mut dst = alloc_planes(DST_WIDTH, DST_HEIGHT, &bgra);
+        convert_region_padded(
+            WIDTH - 1,
+            HEIGHT - 1,
+            &i420,
+            None,
+            &src_i420_buffers,
+            &region,
+            DST_WIDTH,
+            DST_HEIGHT,
+            &bgra,
+            None,
+            &mut [&mut dst[0]],
+            EdgePolicy::Replicate,
+        )
+        .err()
+        .map(|e| e as u32)
+    };
+
+    assert_eq!(
+        i420_to_bgra(Rect {
+            x: 2,
+            y: 2,
+            width: 7,
+            height: 5
+        }),
+        None
+    );
+    assert_eq!(
+        i420_to_bgra(Rect {
+            x: 3,
+            y: 2,
+            width: 7,
+            height: 5
+        }),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+}
+
+#[test]
+fn image_padded_ok() {
+    use dcp::regions::{convert_image_padded, padded_dimensions, EdgePolicy};
+
+    bootstrap();
+
+    const WIDTH: u32 = 38;
+    const HEIGHT: u32 = 70;
+
+    assert_eq!(padded_dimensions(1366, 767, 16).ok(), Some((1376, 768)));
+    assert_eq!(padded_dimensions(37, 9, 2).ok(), Some((38, 10)));
+    assert_eq!(padded_dimensions(64, 1, 64).ok(), Some((64, 64)));
+    assert_eq!(padded_dimensions(37, 9, 1).ok(), Some((37, 9)));
+    for &(width, block_size) in &[(16, 0), (16, 24), (u32::MAX, 2)] {
+        match padded_dimensions(width, 16, block_size) {
+            Err(ErrorKind::InvalidValue) => {}
+            _ => assert!(false),
+        }
+    }
+
+    let (dst_width, dst_height) = padded_dimensions(WIDTH, HEIGHT, 16).unwrap();
+    assert_eq!((dst_width, dst_height), (48, 80));
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    // Logical planes, as (buffer, offset, depth, horizontal and vertical subsampling)
+    let logical_planes = |format: &ImageFormat, width: u32, height: u32| match format.pixel_format {
+        PixelFormat::Nv12 => vec![(0, 0, 1, 0, 0), (0, width * height, 2, 1, 1)],
+        PixelFormat::I420 => vec![(0, 0, 1, 0, 0), (1, 0, 1, 1, 1), (2, 0, 1, 1, 1)],
+        _ => vec![(0, 0, 1, 0, 0), (1, 0, 1, 0, 0), (2, 0, 1, 0, 0)],
+    };
+
+    let bgra = format(PixelFormat::Bgra, ColorSpace::Lrgb, 1);
+    let mut rng = rand::thread_rng();
+    let mut src = alloc_planes(WIDTH, HEIGHT, &bgra);
+    for sample in src[0].iter_mut() {
+        *sample = rng.gen::<u8>();
+    }
+
+    let src_buffers: Vec<&[u8]> = src.iter().map(|x| &x[..]).collect();
+    for (dst_format, edge_policy) in iproduct!(
+        [
+            format(PixelFormat::Nv12, ColorSpace::Bt601, 1),
+            format(PixelFormat::I420, ColorSpace::Bt709, 3),
+            format(PixelFormat::I444, ColorSpace::Bt601, 3),
+        ]
+        .iter(),
+        [EdgePolicy::Replicate, EdgePolicy::Zero].iter()
+    ) {
+        let mut image = alloc_planes(WIDTH, HEIGHT, dst_format);
+        assert!(convert_planes(WIDTH, HEIGHT, &bgra, &src, dst_format, &mut image).is_ok());
+
+        // Reference destination image, explicitly padded
+        let mut expected = alloc_planes(dst_width, dst_height, dst_format);
+        for (&(buffer, offset, depth, horizontal_shift, vertical_shift), &(_, dst_offset, ..)) in
+            logical_planes(dst_format, WIDTH, HEIGHT)
+                .iter()
+                .zip(logical_planes(dst_format, dst_width, dst_height).iter())
+        {
+            let columns = WIDTH >> horizontal_shift;
+            let lines = HEIGHT >> vertical_shift;
+            for (y, x) in iproduct!(
+                0..dst_height >> vertical_shift,
+                0..dst_width >> horizontal_shift
+            ) {
+                let dst_start =
+                    (dst_offset + depth * (y * (dst_width >> horizontal_shift) + x)) as usize;
+                let sample = &mut expected[buffer][dst_start..dst_start + depth as usize];
+                if x < columns && y < lines || *edge_policy == EdgePolicy::Replicate {
+                    let src_start = (offset
+                        + depth * (y.min(lines - 1) * columns + x.min(columns - 1)))
+                        as usize;
+                    sample.copy_from_slice(&image[buffer][src_start..src_start + depth as usize]);
+                } else {
+                    for value in sample.iter_mut() {
+                        *value = 0;
+                    }
+                }
+            }
+        }
+
+        let mut dst = alloc_planes(dst_width, dst_height, dst_format);
+        for plane in dst.iter_mut() {
+            for value in plane.iter_mut() {
+                *value = 0xAA;
+            }
+        }
+
+        let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+        assert!(convert_image_padded(
+            WIDTH,
+            HEIGHT,
+            &bgra,
+            None,
+            &src_buffers,
+            dst_width,
+            dst_height,
+            dst_format,
+            None,
+            &mut dst_buffers,
+            *edge_policy,
+        )
+        .is_ok());
+        assert_eq!(dst, expected);
+    }
+
+    let i420 = format(PixelFormat::I420, ColorSpace::Bt601, 3);
+    let convert = |width: u32, height: u32, dst_width: u32, dst_height: u32, shrink: usize| {
+        let mut dst = alloc_planes(48, 80, &i420);
+        let size = dst[0].len() - shrink;
+        let (y, chroma) = dst.split_at_mut(1);
+        let (u, v) = chroma.split_at_mut(1);
+        convert_image_padded(
+            width,
+            height,
+            &bgra,
+            None,
+            &src_buffers,
+            dst_width,
+            dst_height,
+            &i420,
+            None,
+            &mut [&mut y[0][..size], &mut u[0], &mut v[0]],
+            EdgePolicy::Replicate,
+        )
+        .err()
+        .map(|e| e as u32)
+    };
+
+    assert_eq!(convert(WIDTH, HEIGHT, 48, 80, 0), None);
+    assert_eq!(convert(WIDTH, HEIGHT, WIDTH, HEIGHT, 0), None);
+    assert_eq!(convert(0, 0, 0, 0, 0), None);
+    assert_eq!(
+        convert(WIDTH, HEIGHT, 36, 80, 0),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        convert(WIDTH, HEIGHT, 47, 80, 0),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        convert(WIDTH - 1, HEIGHT, 48, 80, 0),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        convert(0, 0, 48, 80, 0),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        convert(WIDTH, HEIGHT, 48, 80, 1),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+}
+
+#[test]
+fn scaled_ok() {
+    use dcp::scaling::{convert_image_scaled, scaled_dimensions, ScaledImage};
+
+    bootstrap();
+
+    const WIDTH: u32 = 100;
+    const HEIGHT: u32 = 150;
+    const FACTORS: [u32; 5] = [2, 4, 8, 32, 256];
+
+    assert_eq!(
+        scaled_dimensions(WIDTH, HEIGHT, PixelFormat::I420, 4).ok(),
+        Some((24, 36))
+    );
+    assert_eq!(
+        scaled_dimensions(WIDTH, HEIGHT, PixelFormat::I444, 4).ok(),
+        Some((25, 37))
+    );
+    assert_eq!(
+        scaled_dimensions(WIDTH, HEIGHT, PixelFormat::Nv12, 256).ok(),
+        Some((0, 0))
+    );
+    for &factor in &[0, 1, 3, 12] {
+        match scaled_dimensions(WIDTH, HEIGHT, PixelFormat::Bgra, factor) {
+            Err(ErrorKind::InvalidValue) => {}
+            _ => assert!(false),
+        }
+    }
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    // Logical planes, one for each buffer, as (depth, horizontal and vertical subsampling)
+    let logical_planes = |pixel_format| match pixel_format {
+        PixelFormat::Bgra => vec![(4, 0, 0)],
+        PixelFormat::Nv12 => vec![(1, 0, 0), (2, 1, 1)],
+        PixelFormat::I444 => vec![(1, 0, 0); 3],
+        _ => vec![(1, 0, 0), (1, 1, 1), (1, 1, 1)],
+    };
+
+    let bgra = format(PixelFormat::Bgra, ColorSpace::Lrgb, 1);
+    let mut rng = rand::thread_rng();
+    let mut rgb_image = alloc_planes(WIDTH, HEIGHT, &bgra);
+    for sample in rgb_image[0].iter_mut() {
+        *sample = rng.gen::<u8>();
+    }
+
+    let nv12 = format(PixelFormat::Nv12, ColorSpace::Bt709, 2);
+    let mut nv12_image = alloc_planes(WIDTH, HEIGHT, &nv12);
+    assert!(convert_planes(WIDTH, HEIGHT, &bgra, &rgb_image, &nv12, &mut nv12_image).is_ok());
+
+    for (src_format, src, dst_format) in [
+        (
+            &bgra,
+            &rgb_image,
+            format(PixelFormat::I420, ColorSpace::Bt601, 3),
+        ),
+        (
+            &bgra,
+            &rgb_image,
+            format(PixelFormat::Nv12, ColorSpace::Bt709, 2),
+        ),
+        (
+            &bgra,
+            &rgb_image,
+            format(PixelFormat::I444, ColorSpace::Bt601, 3),
+        ),
+        (
+            &nv12,
+            &nv12_image,
+            format(PixelFormat::Bgra, ColorSpace::Lrgb, 1),
+        ),
+    ]
+    .iter()
+    {
+        let mut expected = alloc_planes(WIDTH, HEIGHT, dst_format);
+        assert!(convert_planes(WIDTH, HEIGHT, src_format, src, dst_format, &mut expected).is_ok());
+
+        let mut full = alloc_planes(WIDTH, HEIGHT, dst_format);
+        let mut scaled: Vec<Vec<Vec<u8>>> = FACTORS
+            .iter()
+            .map(|&factor| {
+                let (width, height) =
+                    scaled_dimensions(WIDTH, HEIGHT, dst_format.pixel_format, factor).unwrap();
+                alloc_planes(width, height, dst_format)
+            })
+            .collect();
+        {
+            let src_buffers: Vec<&[u8]> = src.iter().map(|x| &x[..]).collect();
+            let mut dst_buffers: Vec<&mut [u8]> = full.iter_mut().map(|x| &mut x[..]).collect();
+            let mut scaled_buffers: Vec<Vec<&mut [u8]>> = scaled
+                .iter_mut()
+                .map(|image| image.iter_mut().map(|x| &mut x[..]).collect())
+                .collect();
+            let mut scaled_images: Vec<ScaledImage> = scaled_buffers
+                .iter_mut()
+                .zip(FACTORS.iter())
+                .map(|(buffers, &factor)| ScaledImage {
+                    factor,
+                    strides: None,
+                    buffers,
+                })
+                .collect();
+
+            assert!(convert_image_scaled(
+                WIDTH,
+                HEIGHT,
+                src_format,
+                None,
+                &src_buffers,
+                dst_format,
+                None,
+                &mut dst_buffers,
+                &mut scaled_images,
+            )
+            .is_ok());
+        }
+
+        assert_eq!(full, expected);
+        for (image, &factor) in scaled.iter().zip(FACTORS.iter()) {
+            let (width, height) =
+                scaled_dimensions(WIDTH, HEIGHT, dst_format.pixel_format, factor).unwrap();
+            for (plane, (&(depth, horizontal_shift, vertical_shift), full_plane)) in
+                image.iter().zip(
+                    logical_planes(dst_format.pixel_format)
+                        .iter()
+                        .zip(expected.iter()),
+                )
+            {
+                let full_line_size = depth * (WIDTH >> horizontal_shift) as usize;
+                let line_size = depth * (width >> horizontal_shift) as usize;
+                let factor = factor as usize;
+                for (y, x) in iproduct!(0..(height >> vertical_shift) as usize, 0..line_size) {
+                    let (column, component) = (x / depth, x % depth);
+                    let sum: usize = iproduct!(0..factor, 0..factor)
+                        .map(|(i, j)| {
+                            full_plane[(y * factor + i) * full_line_size
+                                + (column * factor + j) * depth
+                                + component] as usize
+                        })
+                        .sum();
+
+                    assert_eq!(
+                        plane[y * line_size + x] as usize,
+                        (sum + factor * factor / 2) / (factor * factor)
+                    );
+                }
+            }
+        }
+    }
+
+    let i420 = format(PixelFormat::I420, ColorSpace::Bt601, 1);
+    let mut full = alloc_planes(WIDTH, HEIGHT, &i420);
+    let mut convert = |factor: u32, size: usize| {
+        let mut scaled = vec![0u8; size];
+        let mut dst_buffers: Vec<&mut [u8]> = full.iter_mut().map(|x| &mut x[..]).collect();
+        convert_image_scaled(
+            WIDTH,
+            HEIGHT,
+            &bgra,
+            None,
+            &[&rgb_image[0]],
+            &i420,
+            None,
+            &mut dst_buffers,
+            &mut [ScaledImage {
+                factor,
+                strides: None,
+                buffers: &mut [&mut scaled],
+            }],
+        )
+        .err()
+        .map(|e| e as u32)
+    };
+
+    let half_size = 50 * 74 * 3 / 2;
+    assert_eq!(convert(2, half_size), None);
+    assert_eq!(convert(3, half_size), Some(ErrorKind::InvalidValue as u32));
+    assert_eq!(
+        convert(2, half_size - 1),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+
+    // 16-bit destination formats are not supported
+    let p010 = format(PixelFormat::P010, ColorSpace::Bt709, 2);
+    let mut p010_image = alloc_planes(WIDTH, HEIGHT, &p010);
+    let nv12_buffers: Vec<&[u8]> = nv12_image.iter().map(|x| &x[..]).collect();
+    let mut p010_buffers: Vec<&mut [u8]> = p010_image.iter_mut().map(|x| &mut x[..]).collect();
+    match convert_image_scaled(
+        WIDTH,
+        HEIGHT,
+        &nv12,
+        None,
+        &nv12_buffers,
+        &p010,
+        None,
+        &mut p010_buffers,
+        &mut [],
+    ) {
+        Err(ErrorKind::InvalidOperation) => {}
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn pyramid_ok() {
+    use dcp::scaling::{build_pyramid, convert_image_pyramid, pyramid_dimensions, PyramidLevel};
+
+    bootstrap();
+
+    const WIDTH: u32 = 100;
+    const HEIGHT: u32 = 150;
+    const LEVELS: u32 = 7;
+
+    let i420_dimensions: Vec<(u32, u32)> = (0..=LEVELS)
+        .map(|level| pyramid_dimensions(WIDTH, HEIGHT, PixelFormat::I420, level))
+        .collect();
+    assert_eq!(
+        i420_dimensions,
+        [
+            (100, 150),
+            (50, 74),
+            (24, 36),
+            (12, 18),
+            (6, 8),
+            (2, 4),
+            (0, 2),
+            (0, 0)
+        ]
+    );
+    assert_eq!(
+        pyramid_dimensions(WIDTH, HEIGHT, PixelFormat::I444, 2),
+        (25, 37)
+    );
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    // Logical planes, one for each buffer, as (depth, horizontal and vertical subsampling)
+    let logical_planes = |pixel_format| match pixel_format {
+        PixelFormat::Nv12 => vec![(1, 0, 0), (2, 1, 1)],
+        PixelFormat::I444 => vec![(1, 0, 0); 3],
+        PixelFormat::I422 => vec![(1, 0, 0), (1, 1, 0), (1, 1, 0)],
+        _ => vec![(1, 0, 0), (1, 1, 1), (1, 1, 1)],
+    };
+
+    let bgra = format(PixelFormat::Bgra, ColorSpace::Lrgb, 1);
+    let mut rng = rand::thread_rng();
+    let mut rgb_image = alloc_planes(WIDTH, HEIGHT, &bgra);
+    for sample in rgb_image[0].iter_mut() {
+        *sample = rng.gen::<u8>();
+    }
+
+    let rgb_buffers: Vec<&[u8]> = rgb_image.iter().map(|x| &x[..]).collect();
+    for dst_format in [
+        format(PixelFormat::I420, ColorSpace::Bt601, 3),
+        format(PixelFormat::Nv12, ColorSpace::Bt709, 2),
+        format(PixelFormat::I444, ColorSpace::Bt601, 3),
+    ]
+    .iter()
+    {
+        let mut full = alloc_planes(WIDTH, HEIGHT, dst_format);
+        assert!(convert_planes(WIDTH, HEIGHT, &bgra, &rgb_image, dst_format, &mut full).is_ok());
+
+        // Reference levels, each one downscaled from the previous one
+        let planes = logical_planes(dst_format.pixel_format);
+        let mut expected = vec![full.clone()];
+        for level in 1..=LEVELS {
+            let (width, height) = pyramid_dimensions(WIDTH, HEIGHT, dst_format.pixel_format, level);
+            let (previous_width, _) =
+                pyramid_dimensions(WIDTH, HEIGHT, dst_format.pixel_format, level - 1);
+            let mut image = alloc_planes(width, height, dst_format);
+            for (plane, (&(depth, horizontal_shift, vertical_shift), previous)) in image
+                .iter_mut()
+                .zip(planes.iter().zip(expected[level as usize - 1].iter()))
+            {
+                let line_size = depth * (width >> horizontal_shift) as usize;
+                let previous_line_size = depth * (previous_width >> horizontal_shift) as usize;
+                for (y, x) in iproduct!(0..(height >> vertical_shift) as usize, 0..line_size) {
+                    let (column, component) = (x / depth, x % depth);
+                    let sum: usize = iproduct!(0..2, 0..2)
+                        .map(|(i, j)| {
+                            previous[(2 * y + i) * previous_line_size
+                                + (2 * column + j) * depth
+                                + component] as usize
+                        })
+                        .sum();
+
+                    plane[y * line_size + x] = ((sum + 2) / 4) as u8;
+                }
+            }
+
+            expected.push(image);
+        }
+
+        let new_levels = || -> Vec<Vec<Vec<u8>>> {
+            (1..=LEVELS)
+                .map(|level| {
+                    let (width, height) =
+                        pyramid_dimensions(WIDTH, HEIGHT, dst_format.pixel_format, level);
+                    alloc_planes(width, height, dst_format)
+                })
+                .collect()
+        };
+
+        // Fused with the conversion
+        let mut dst = alloc_planes(WIDTH, HEIGHT, dst_format);
+        let mut levels = new_levels();
+        {
+            let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+            let mut level_buffers: Vec<Vec<&mut [u8]>> = levels
+                .iter_mut()
+                .map(|image| image.iter_mut().map(|x| &mut x[..]).collect())
+                .collect();
+            let mut pyramid: Vec<PyramidLevel> = level_buffers
+                .iter_mut()
+                .map(|buffers| PyramidLevel {
+                    strides: None,
+                    buffers,
+                })
+                .collect();
+
+            assert!(convert_image_pyramid(
+                WIDTH,
+                HEIGHT,
+                &bgra,
+                None,
+                &rgb_buffers,
+                dst_format,
+                None,
+                &mut dst_buffers,
+                &mut pyramid,
+            )
+            .is_ok());
+        }
+
+        assert_eq!(dst, full);
+        assert_eq!(&levels[..], &expected[1..]);
+
+        // From an existing image
+        let mut levels = new_levels();
+        {
+            let buffers: Vec<&[u8]> = full.iter().map(|x| &x[..]).collect();
+            let mut level_buffers: Vec<Vec<&mut [u8]>> = levels
+                .iter_mut()
+                .map(|image| image.iter_mut().map(|x| &mut x[..]).collect())
+                .collect();
+            let mut pyramid: Vec<PyramidLevel> = level_buffers
+                .iter_mut()
+                .map(|buffers| PyramidLevel {
+                    strides: None,
+                    buffers,
+                })
+                .collect();
+
+            assert!(build_pyramid(WIDTH, HEIGHT, dst_format, None, &buffers, &mut pyramid).is_ok());
+        }
+
+        assert_eq!(&levels[..], &expected[1..]);
+    }
+
+    // Strided levels of a single buffer image
+    let nv12 = format(PixelFormat::Nv12, ColorSpace::Bt601, 1);
+    let mut image = alloc_planes(WIDTH, HEIGHT, &nv12);
+    for sample in image[0].iter_mut() {
+        *sample = rng.gen::<u8>();
+    }
+
+    let (level_width, level_height) = pyramid_dimensions(WIDTH, HEIGHT, PixelFormat::Nv12, 1);
+    let mut packed = alloc_planes(level_width, level_height, &nv12);
+    let mut strided = vec![0u8; 64 * 3 * level_height as usize / 2];
+    {
+        let mut pyramid = [PyramidLevel {
+            strides: None,
+            buffers: &mut [&mut packed[0]],
+        }];
+        assert!(build_pyramid(WIDTH, HEIGHT, &nv12, None, &[&image[0]], &mut pyramid).is_ok());
+    }
+    {
+        let mut pyramid = [PyramidLevel {
+            strides: Some(&[64]),
+            buffers: &mut [&mut strided],
+        }];
+        assert!(build_pyramid(WIDTH, HEIGHT, &nv12, None, &[&image[0]], &mut pyramid).is_ok());
+    }
+
+    for (line, packed_line) in packed[0].chunks(level_width as usize).enumerate() {
+        assert_eq!(&strided[64 * line..][..level_width as usize], packed_line);
+    }
+
+    let build = |format: &ImageFormat, width: u32, size: usize| {
+        let mut level = vec![0u8; size];
+        build_pyramid(
+            width,
+            HEIGHT,
+            format,
+            None,
+            &[&image[0]],
+            &mut [PyramidLevel {
+                strides: None,
+                buffers: &mut [&mut level],
+            }],
+        )
+        .err()
+        .map(|e| e as u32)
+    };
+
+    let level_size = 3 * (level_width * level_height) as usize / 2;
+    assert_eq!(build(&nv12, WIDTH, level_size), None);
+    assert_eq!(
+        build(&nv12, WIDTH, level_size - 1),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+    assert_eq!(
+        build(&nv12, WIDTH - 1, level_size),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        build(
+            &format(PixelFormat::Nv12, ColorSpace::Lrgb, 1),
+            WIDTH,
+            level_size
+        ),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        build(&bgra, WIDTH, level_size),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+
+    // Only YCbCr destination formats are supported
+    let mut dst = alloc_planes(WIDTH, HEIGHT, &bgra);
+    match convert_image_pyramid(
+        WIDTH,
+        HEIGHT,
+        &nv12,
+        None,
+        &[&image[0]],
+        &bgra,
+        None,
+        &mut [&mut dst[0]],
+        &mut [],
+    ) {
+        Err(ErrorKind::InvalidOperation) => {}
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn planner_ok() {
+    use dcp::planner::{CostModel, Plan};
+
+    bootstrap();
+
+    const WIDTH: u32 = 64;
+    const HEIGHT: u32 = 32;
+    const PIXELS: u64 = (WIDTH as u64) * (HEIGHT as u64);
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    let bgr48 = format(PixelFormat::Bgr48, ColorSpace::Lrgb, 1);
+    let nv12 = format(PixelFormat::Nv12, ColorSpace::Bt601, 2);
+    let bgra = format(PixelFormat::Bgra, ColorSpace::Lrgb, 1);
+
+    let mut rng = rand::thread_rng();
+    let mut src = alloc_planes(WIDTH, HEIGHT, &bgr48);
+    for sample in src[0].iter_mut() {
+        *sample = rng.gen::<u8>();
+    }
+
+    let mut expected = alloc_planes(WIDTH, HEIGHT, &nv12);
+    assert!(convert_planes(WIDTH, HEIGHT, &bgr48, &src, &nv12, &mut expected).is_ok());
+
+    let src_buffers: Vec<&[u8]> = src.iter().map(|x| &x[..]).collect();
+    for (cost_model, intermediate, bandwidth) in [
+        // Same route as convert_image
+        (
+            CostModel::Passes,
+            PixelFormat::Bgra,
+            6 * PIXELS + 2 * 4 * PIXELS + 3 * PIXELS / 2,
+        ),
+        // Twelve bytes for each 2x2 block of P010 instead of sixteen for Bgra
+        (
+            CostModel::Bandwidth,
+            PixelFormat::P010,
+            6 * PIXELS + 2 * 3 * PIXELS + 3 * PIXELS / 2,
+        ),
+    ]
+    .iter()
+    {
+        let plan = Plan::new(WIDTH, HEIGHT, &bgr48, &nv12, *cost_model).unwrap();
+        let passes = plan.passes();
+        assert_eq!(passes.len(), 2);
+        assert_eq!(
+            passes[0].src_format.pixel_format as u32,
+            PixelFormat::Bgr48 as u32
+        );
+        assert_eq!(
+            passes[0].dst_format.pixel_format as u32,
+            *intermediate as u32
+        );
+        assert_eq!(
+            passes[1].src_format.pixel_format as u32,
+            *intermediate as u32
+        );
+        assert_eq!(
+            passes[1].dst_format.pixel_format as u32,
+            PixelFormat::Nv12 as u32
+        );
+        assert_eq!(passes[0].bytes_read, 6 * PIXELS);
+        assert_eq!(passes[1].bytes_written, 3 * PIXELS / 2);
+        assert_eq!(passes[0].bytes_written, passes[1].bytes_read);
+        assert_eq!(plan.estimated_bandwidth(), *bandwidth);
+        assert!(plan.scratch_size().unwrap() > 0);
+
+        let mut dst = alloc_planes(WIDTH, HEIGHT, &nv12);
+        let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+        assert!(plan
+            .convert(None, &src_buffers, None, &mut dst_buffers)
+            .is_ok());
+        if *cost_model == CostModel::Passes {
+            assert_eq!(dst, expected);
+        }
+
+        let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+        dst_buffers[1] = &mut [];
+        assert_eq!(
+            plan.convert(None, &src_buffers, None, &mut dst_buffers)
+                .err()
+                .map(|e| e as u32),
+            Some(ErrorKind::NotEnoughData as u32)
+        );
+    }
+
+    // Direct conversions are the cheapest with any cost model
+    for cost_model in [CostModel::Passes, CostModel::Bandwidth].iter() {
+        let plan = Plan::new(WIDTH, HEIGHT, &bgra, &nv12, *cost_model).unwrap();
+        assert_eq!(plan.passes().len(), 1);
+        assert_eq!(plan.estimated_bandwidth(), 4 * PIXELS + 3 * PIXELS / 2);
+        assert_eq!(plan.scratch_size().unwrap(), 0);
+    }
+
+    assert_eq!(
+        Plan::new(WIDTH, HEIGHT, &nv12, &nv12, CostModel::Bandwidth)
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+    assert_eq!(
+        Plan::new(WIDTH + 1, HEIGHT, &bgra, &nv12, CostModel::Bandwidth)
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+}
+
+#[test]
+fn levels_ok() {
+    use dcp::levels::{apply_levels, Levels, YuvLevels};
+
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 6;
+    const PIXELS: usize = (WIDTH as usize) * (HEIGHT as usize);
+
+    let format = |pixel_format, num_planes| ImageFormat {
+        pixel_format,
+        color_space: ColorSpace::Bt709,
+        num_planes,
+    };
+
+    let reference = |levels: &Levels, x: u8| {
+        let black = f32::from(levels.input_black);
+        let range = f32::from(levels.input_white) - black;
+        let output_black = f32::from(levels.output_black);
+        let output_range = f32::from(levels.output_white) - output_black;
+        let v = ((f32::from(x) - black) / range).clamp(0.0, 1.0);
+        (output_black + v.powf(1.0 / levels.gamma) * output_range + 0.5) as u8
+    };
+
+    let levels = YuvLevels {
+        luma: Levels {
+            input_black: 16,
+            input_white: 160,
+            gamma: 1.4,
+            output_black: 16,
+            output_white: 235,
+        },
+        cb: Levels {
+            input_black: 40,
+            input_white: 220,
+            gamma: 0.8,
+            output_black: 0,
+            output_white: 255,
+        },
+        // Inverted output range
+        cr: Levels {
+            input_black: 0,
+            input_white: 255,
+            gamma: 1.0,
+            output_black: 240,
+            output_white: 16,
+        },
+    };
+
+    let mut rng = rand::thread_rng();
+    for image_format in [
+        format(PixelFormat::Nv12, 1),
+        format(PixelFormat::Nv12, 2),
+        format(PixelFormat::I420, 3),
+        format(PixelFormat::I422, 3),
+        format(PixelFormat::I444, 3),
+    ]
+    .iter()
+    {
+        let mut planes = alloc_planes(WIDTH, HEIGHT, image_format);
+        for sample in planes.iter_mut().flatten() {
+            *sample = rng.gen::<u8>();
+        }
+
+        // Channel of each sample: planes have no padding with the default strides
+        let channel = |plane: usize, offset: usize| match image_format.pixel_format {
+            PixelFormat::Nv12 if plane == 0 && offset < PIXELS => 0,
+            PixelFormat::Nv12 if plane == 0 => 1 + (offset - PIXELS) % 2,
+            PixelFormat::Nv12 => 1 + offset % 2,
+            _ => plane,
+        };
+
+        let channels = [&levels.luma, &levels.cb, &levels.cr];
+        let expected: Vec<Vec<u8>> = planes
+            .iter()
+            .enumerate()
+            .map(|(plane, samples)| {
+                samples
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, &x)| reference(channels[channel(plane, offset)], x))
+                    .collect()
+            })
+            .collect();
+
+        let mut buffers: Vec<&mut [u8]> = planes.iter_mut().map(|x| &mut x[..]).collect();
+        assert!(apply_levels(WIDTH, HEIGHT, image_format, None, &mut buffers, &levels).is_ok());
+        assert_eq!(planes, expected);
+
+        // Default levels leave the image unchanged
+        let mut buffers: Vec<&mut [u8]> = planes.iter_mut().map(|x| &mut x[..]).collect();
+        assert!(apply_levels(
+            WIDTH,
+            HEIGHT,
+            image_format,
+            None,
+            &mut buffers,
+            &YuvLevels::default()
+        )
+        .is_ok());
+        assert_eq!(planes, expected);
+    }
+
+    let check_error = |image_format: &ImageFormat, width: u32, levels: &YuvLevels| {
+        let mut planes = alloc_planes(WIDTH, HEIGHT, &format(PixelFormat::I444, 3));
+        let mut buffers: Vec<&mut [u8]> = planes.iter_mut().map(|x| &mut x[..]).collect();
+        apply_levels(width, HEIGHT, image_format, None, &mut buffers, levels)
+            .err()
+            .map(|error| error as u32)
+    };
+
+    let nv12 = format(PixelFormat::Nv12, 2);
+    let default_levels = YuvLevels::default();
+    assert_eq!(check_error(&nv12, WIDTH, &default_levels), None);
+    assert_eq!(
+        check_error(&nv12, WIDTH + 1, &default_levels),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(&format(PixelFormat::Nv12, 3), WIDTH, &default_levels),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(
+            &ImageFormat {
+                pixel_format: PixelFormat::Nv12,
+                color_space: ColorSpace::Lrgb,
+                num_planes: 2,
+            },
+            WIDTH,
+            &default_levels
+        ),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(
+            &ImageFormat {
+                pixel_format: PixelFormat::Bgra,
+                color_space: ColorSpace::Lrgb,
+                num_planes: 1,
+            },
+            WIDTH,
+            &default_levels
+        ),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+
+    for invalid in [
+        Levels {
+            input_black: 128,
+            input_white: 128,
+            ..Default::default()
+        },
+        Levels {
+            gamma: 0.0,
+            ..Default::default()
+        },
+        Levels {
+            gamma: f32::NAN,
+            ..Default::default()
+        },
+    ]
+    .iter()
+    {
+        let invalid_levels = YuvLevels {
+            cr: *invalid,
+            ..Default::default()
+        };
+        assert_eq!(
+            check_error(&nv12, WIDTH, &invalid_levels),
+            Some(ErrorKind::InvalidValue as u32)
+        );
+    }
+
+    // Buffers too small for the strides
+    let mut planes = alloc_planes(WIDTH, HEIGHT, &nv12);
+    let mut buffers: Vec<&mut [u8]> = planes.iter_mut().map(|x| &mut x[..]).collect();
+    assert_eq!(
+        apply_levels(
+            WIDTH,
+            HEIGHT,
+            &nv12,
+            Some(&[WIDTH as usize + 1, WIDTH as usize]),
+            &mut buffers,
+            &default_levels
+        )
+        .err()
+        .map(|error| error as u32),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+}
+
+#[test]
+fn blend_frames_ok() {
+    use dcp::composite::blend_frames;
+
+    bootstrap();
+
+    const WIDTH: u32 = 38;
+    const HEIGHT: u32 = 4;
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    let div_255 = |x: u32| {
+        let x = x + 128;
+        (x + (x >> 8)) >> 8
+    };
+
+    let mut rng = rand::thread_rng();
+    for (image_format, &alpha) in iproduct!(
+        [
+            format(PixelFormat::Bgra, ColorSpace::Lrgb, 1),
+            format(PixelFormat::Rgb, ColorSpace::Lrgb, 1),
+            format(PixelFormat::Nv12, ColorSpace::Bt601, 1),
+            format(PixelFormat::Nv12, ColorSpace::Bt709, 2),
+            format(PixelFormat::I420, ColorSpace::Bt601, 3),
+            format(PixelFormat::I444, ColorSpace::Bt709, 3),
+        ]
+        .iter(),
+        [0u8, 1, 77, 128, 254, 255].iter()
+    ) {
+        let mut src = alloc_planes(WIDTH, HEIGHT, image_format);
+        let mut dst = alloc_planes(WIDTH, HEIGHT, image_format);
+        for sample in src.iter_mut().chain(dst.iter_mut()).flatten() {
+            *sample = rng.gen::<u8>();
+        }
+
+        let expected: Vec<Vec<u8>> = src
+            .iter()
+            .zip(dst.iter())
+            .map(|(s, d)| {
+                s.iter()
+                    .zip(d.iter())
+                    .map(|(&x, &y)| {
+                        div_255(
+                            u32::from(x) * u32::from(alpha) + u32::from(y) * u32::from(255 - alpha),
+                        ) as u8
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let src_buffers: Vec<&[u8]> = src.iter().map(|x| &x[..]).collect();
+        let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+        assert!(blend_frames(
+            WIDTH,
+            HEIGHT,
+            image_format,
+            None,
+            &src_buffers,
+            None,
+            &mut dst_buffers,
+            alpha
+        )
+        .is_ok());
+        assert_eq!(dst, expected);
+        if alpha == 255 {
+            assert_eq!(dst, src);
+        }
+    }
+
+    // Padded lines, the padding is left untouched
+    let bgra = format(PixelFormat::Bgra, ColorSpace::Lrgb, 1);
+    let src_stride = 4 * (WIDTH as usize) + 8;
+    let dst_stride = 4 * (WIDTH as usize) + 4;
+    let src = vec![200u8; src_stride * (HEIGHT as usize)];
+    let mut dst = vec![100u8; dst_stride * (HEIGHT as usize)];
+    assert!(blend_frames(
+        WIDTH,
+        HEIGHT,
+        &bgra,
+        Some(&[src_stride]),
+        &[&src],
+        Some(&[dst_stride]),
+        &mut [&mut dst],
+        128
+    )
+    .is_ok());
+    for line in dst.chunks(dst_stride) {
+        assert!(line[..4 * (WIDTH as usize)].iter().all(|&x| x == 150));
+        assert!(line[4 * (WIDTH as usize)..].iter().all(|&x| x == 100));
+    }
+
+    let check_error = |image_format: &ImageFormat, width: u32, dst_stride: usize| {
+        let src = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+        let mut dst = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+        blend_frames(
+            width,
+            HEIGHT,
+            image_format,
+            None,
+            &[&src],
+            Some(&[dst_stride]),
+            &mut [&mut dst],
+            128,
+        )
+        .err()
+        .map(|error| error as u32)
+    };
+
+    let nv12 = format(PixelFormat::Nv12, ColorSpace::Bt601, 1);
+    assert_eq!(check_error(&nv12, WIDTH, 0), None);
+    assert_eq!(
+        check_error(&nv12, WIDTH + 1, 0),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(&format(PixelFormat::Nv12, ColorSpace::Lrgb, 1), WIDTH, 0),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(&format(PixelFormat::Bgra, ColorSpace::Bt601, 1), WIDTH, 0),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(&nv12, WIDTH, WIDTH as usize - 1),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+    assert_eq!(
+        check_error(&bgra, WIDTH, 8 * (WIDTH as usize)),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+    assert_eq!(
+        check_error(&format(PixelFormat::P010, ColorSpace::Bt601, 2), WIDTH, 0),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+}
+
+#[test]
+fn accumulate_frames_ok() {
+    use dcp::composite::accumulate_frames;
+
+    bootstrap();
+
+    const WIDTH: u32 = 38;
+    const HEIGHT: u32 = 4;
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    let div_255 = |x: u32| {
+        let x = x + 128;
+        (x + (x >> 8)) >> 8
+    };
+
+    let mut rng = rand::thread_rng();
+    for (image_format, &alpha) in iproduct!(
+        [
+            format(PixelFormat::Bgra, ColorSpace::Lrgb, 1),
+            format(PixelFormat::Rgb, ColorSpace::Lrgb, 1),
+            format(PixelFormat::Nv12, ColorSpace::Bt601, 1),
+            format(PixelFormat::Nv12, ColorSpace::Bt709, 2),
+            format(PixelFormat::I420, ColorSpace::Bt601, 3),
+            format(PixelFormat::I444, ColorSpace::Bt709, 3),
+        ]
+        .iter(),
+        [0u8, 1, 51, 128, 254, 255].iter()
+    ) {
+        let mut src = alloc_planes(WIDTH, HEIGHT, image_format);
+        let mut dst = alloc_planes(WIDTH, HEIGHT, image_format);
+        for sample in src.iter_mut().chain(dst.iter_mut()).flatten() {
+            *sample = rng.gen::<u8>();
+        }
+
+        let expected: Vec<Vec<u8>> = src
+            .iter()
+            .zip(dst.iter())
+            .map(|(s, d)| {
+                s.iter()
+                    .zip(d.iter())
+                    .map(|(&x, &y)| {
+                        (u32::from(y) + div_255(u32::from(x) * u32::from(alpha))).min(255) as u8
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let src_buffers: Vec<&[u8]> = src.iter().map(|x| &x[..]).collect();
+        let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+        assert!(accumulate_frames(
+            WIDTH,
+            HEIGHT,
+            image_format,
+            None,
+            &src_buffers,
+            None,
+            &mut dst_buffers,
+            alpha
+        )
+        .is_ok());
+        assert_eq!(dst, expected);
+    }
+
+    // Averaging a window of five frames into a cleared image, the padding is left untouched
+    let bgra = format(PixelFormat::Bgra, ColorSpace::Lrgb, 1);
+    let src_stride = 4 * (WIDTH as usize) + 8;
+    let dst_stride = 4 * (WIDTH as usize) + 4;
+    let mut dst = vec![0u8; dst_stride * (HEIGHT as usize)];
+    for line in dst.chunks_mut(dst_stride) {
+        for sample in line[4 * (WIDTH as usize)..].iter_mut() {
+            *sample = 7;
+        }
+    }
+
+    for &value in [50u8, 100, 150, 200, 250].iter() {
+        let src = vec![value; src_stride * (HEIGHT as usize)];
+        assert!(accumulate_frames(
+            WIDTH,
+            HEIGHT,
+            &bgra,
+            Some(&[src_stride]),
+            &[&src],
+            Some(&[dst_stride]),
+            &mut [&mut dst],
+            51
+        )
+        .is_ok());
+    }
+
+    for line in dst.chunks(dst_stride) {
+        assert!(line[..4 * (WIDTH as usize)].iter().all(|&x| x == 150));
+        assert!(line[4 * (WIDTH as usize)..].iter().all(|&x| x == 7));
+    }
+
+    // Sums saturate
+    let src = vec![255u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+    assert!(accumulate_frames(
+        WIDTH,
+        HEIGHT,
+        &bgra,
+        None,
+        &[&src],
+        Some(&[dst_stride]),
+        &mut [&mut dst],
+        128
+    )
+    .is_ok());
+    for line in dst.chunks(dst_stride) {
+        assert!(line[..4 * (WIDTH as usize)].iter().all(|&x| x == 255));
+    }
+
+    let check_error = |image_format: &ImageFormat, width: u32, dst_stride: usize| {
+        let src = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+        let mut dst = vec![0u8; 4 * (WIDTH as usize) * (HEIGHT as usize)];
+        accumulate_frames(
+            width,
+            HEIGHT,
+            image_format,
+            None,
+            &[&src],
+            Some(&[dst_stride]),
+            &mut [&mut dst],
+            128,
+        )
+        .err()
+        .map(|error| error as u32)
+    };
+
+    let nv12 = format(PixelFormat::Nv12, ColorSpace::Bt601, 1);
+    assert_eq!(check_error(&nv12, WIDTH, 0), None);
+    assert_eq!(
+        check_error(&nv12, WIDTH + 1, 0),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(&format(PixelFormat::Bgra, ColorSpace::Bt601, 1), WIDTH, 0),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        check_error(&nv12, WIDTH, WIDTH as usize - 1),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+    assert_eq!(
+        check_error(&bgra, WIDTH, 8 * (WIDTH as usize)),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+    assert_eq!(
+        check_error(&format(PixelFormat::P010, ColorSpace::Bt601, 2), WIDTH, 0),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+}
+
+#[test]
+fn planes_ok() {
+    use dcp::planes::{copy_plane_with_strides, fill_plane};
+
+    const LINE_SIZE: usize = 37;
+    const LINES: usize = 5;
+
+    let mut rng = rand::thread_rng();
+    for (&src_stride, &dst_stride) in iproduct!(
+        [STRIDE_AUTO, LINE_SIZE, LINE_SIZE + 11].iter(),
+        [STRIDE_AUTO, LINE_SIZE, LINE_SIZE + 3].iter()
+    ) {
+        let src_pitch = src_stride.max(LINE_SIZE);
+        let dst_pitch = dst_stride.max(LINE_SIZE);
+
+        // The last line is not padded
+        let mut src = vec![0u8; src_pitch * (LINES - 1) + LINE_SIZE];
+        for sample in src.iter_mut() {
+            *sample = rng.gen::<u8>();
+        }
+
+        let mut dst = vec![0xAAu8; dst_pitch * (LINES - 1) + LINE_SIZE];
+        assert!(
+            copy_plane_with_strides(LINE_SIZE, LINES, src_stride, &src, dst_stride, &mut dst)
+                .is_ok()
+        );
+        for line in 0..LINES {
+            let dst_line = &dst[line * dst_pitch..];
+            assert_eq!(
+                dst_line[..LINE_SIZE],
+                src[line * src_pitch..line * src_pitch + LINE_SIZE]
+            );
+            if line + 1 < LINES {
+                assert!(dst_line[LINE_SIZE..dst_pitch].iter().all(|&x| x == 0xAA));
+            }
+        }
+
+        assert!(fill_plane(LINE_SIZE, LINES, dst_stride, &mut dst, 16).is_ok());
+        for (i, &x) in dst.iter().enumerate() {
+            assert_eq!(x, if i % dst_pitch < LINE_SIZE { 16 } else { 0xAA });
+        }
+
+        // Buffers one byte too short
+        let last = dst.len() - 1;
+        assert_eq!(
+            fill_plane(LINE_SIZE, LINES, dst_stride, &mut dst[..last], 0)
+                .err()
+                .map(|error| error as u32),
+            Some(ErrorKind::NotEnoughData as u32)
+        );
+        assert_eq!(
+            copy_plane_with_strides(
+                LINE_SIZE,
+                LINES,
+                src_stride,
+                &src[..src.len() - 1],
+                dst_stride,
+                &mut dst
+            )
+            .err()
+            .map(|error| error as u32),
+            Some(ErrorKind::NotEnoughData as u32)
+        );
+    }
+
+    // Empty planes
+    assert!(fill_plane(LINE_SIZE, 0, 0, &mut [], 0).is_ok());
+    assert!(copy_plane_with_strides(0, LINES, 0, &[], 0, &mut []).is_ok());
+
+    let mut buffer = vec![0u8; LINE_SIZE * LINES];
+    assert_eq!(
+        fill_plane(LINE_SIZE, LINES, LINE_SIZE - 1, &mut buffer, 0)
+            .err()
+            .map(|error| error as u32),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    let src = buffer.clone();
+    assert_eq!(
+        copy_plane_with_strides(LINE_SIZE, LINES, LINE_SIZE - 1, &src, 0, &mut buffer)
+            .err()
+            .map(|error| error as u32),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        fill_plane(LINE_SIZE, 2, usize::MAX, &mut buffer, 0)
+            .err()
+            .map(|error| error as u32),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+}
+
+#[test]
+fn consolidate_ok() {
+    use dcp::planes::{consolidate, consolidated_size, split};
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 6;
+
+    let mut rng = rand::thread_rng();
+    for (&(pixel_format, num_planes), &alignment, &padding) in iproduct!(
+        [
+            (PixelFormat::Nv12, 2),
+            (PixelFormat::I420, 3),
+            (PixelFormat::I444, 3),
+            (PixelFormat::P010, 2),
+        ]
+        .iter(),
+        [1, 3, 64].iter(),
+        [0, 5].iter()
+    ) {
+        let format = ImageFormat {
+            pixel_format,
+            color_space: ColorSpace::Bt709,
+            num_planes,
+        };
+
+        let planes = describe_layout(WIDTH, HEIGHT, &format, None).unwrap();
+        let strides: Vec<usize> = planes
+            .iter()
+            .map(|plane| (plane.bytes_per_sample * plane.width) as usize + padding)
+            .collect();
+        let src: Vec<Vec<u8>> = planes
+            .iter()
+            .zip(strides.iter())
+            .map(|(plane, stride)| {
+                (0..stride * (plane.height as usize))
+                    .map(|_| rng.gen::<u8>())
+                    .collect()
+            })
+            .collect();
+        let src_buffers: Vec<&[u8]> = src.iter().map(|x| &x[..]).collect();
+
+        let size = consolidated_size(WIDTH, HEIGHT, pixel_format, alignment).unwrap();
+        let mut shared = vec![0u8; size];
+        let stride = consolidate(
+            WIDTH,
+            HEIGHT,
+            &format,
+            Some(&strides),
+            &src_buffers,
+            alignment,
+            &mut shared,
+        )
+        .unwrap();
+        assert_eq!(stride % alignment, 0);
+
+        // The single buffer is laid out as an image with a single plane
+        let single_format = ImageFormat {
+            pixel_format,
+            color_space: ColorSpace::Bt709,
+            num_planes: 1,
+        };
+        let single_planes =
+            describe_layout(WIDTH, HEIGHT, &single_format, Some(&[stride])).unwrap();
+        let last = &single_planes[single_planes.len() - 1];
+        assert_eq!(last.offset + last.size, size);
+        for ((plane, single_plane), (src_plane, src_stride)) in planes
+            .iter()
+            .zip(single_planes.iter())
+            .zip(src.iter().zip(strides.iter()))
+        {
+            let line_size = (plane.bytes_per_sample * plane.width) as usize;
+            assert_eq!(single_plane.offset % alignment, 0);
+            for line in 0..(plane.height as usize) {
+                assert_eq!(
+                    shared[single_plane.offset + line * stride..][..line_size],
+                    src_plane[line * src_stride..][..line_size]
+                );
+            }
+        }
+
+        // Splitting gives the planes back
+        let mut dst: Vec<Vec<u8>> = src.iter().map(|plane| vec![0u8; plane.len()]).collect();
+        {
+            let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+            assert!(split(
+                WIDTH,
+                HEIGHT,
+                &format,
+                stride,
+                &shared,
+                Some(&strides),
+                &mut dst_buffers
+            )
+            .is_ok());
+        }
+        for ((src_plane, dst_plane), src_stride) in src.iter().zip(dst.iter()).zip(strides.iter()) {
+            for (src_line, dst_line) in src_plane
+                .chunks(*src_stride)
+                .zip(dst_plane.chunks(*src_stride))
+            {
+                assert_eq!(
+                    src_line[..src_stride - padding],
+                    dst_line[..src_stride - padding]
+                );
+            }
+        }
+
+        // Buffers missing a line
+        match consolidate(
+            WIDTH,
+            HEIGHT,
+            &format,
+            Some(&strides),
+            &src_buffers,
+            alignment,
+            &mut shared[..size - stride],
+        ) {
+            Err(ErrorKind::NotEnoughData) => {}
+            _ => assert!(false),
+        }
+
+        let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+        match split(
+            WIDTH,
+            HEIGHT,
+            &format,
+            stride,
+            &shared[..size - stride],
+            Some(&strides),
+            &mut dst_buffers,
+        ) {
+            Err(ErrorKind::NotEnoughData) => {}
+            _ => assert!(false),
+        }
+    }
+
+    let format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt601,
+        num_planes: 2,
+    };
+    let mut shared = vec![0u8; 3 * (WIDTH * HEIGHT) as usize];
+    for (width, alignment) in [(WIDTH + 1, 1), (WIDTH, 0)].iter() {
+        match consolidated_size(*width, HEIGHT, format.pixel_format, *alignment) {
+            Err(ErrorKind::InvalidValue) => {}
+            _ => assert!(false),
+        }
+
+        match consolidate(
+            *width,
+            HEIGHT,
+            &format,
+            None,
+            &[&[], &[]],
+            *alignment,
+            &mut shared,
+        ) {
+            Err(ErrorKind::InvalidValue) => {}
+            _ => assert!(false),
+        }
+    }
+
+    // A stride shorter than a line
+    match split(
+        WIDTH,
+        HEIGHT,
+        &format,
+        WIDTH as usize - 1,
+        &shared,
+        None,
+        &mut [&mut [], &mut []],
+    ) {
+        Err(ErrorKind::NotEnoughData) => {}
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn describe_layout_ok() {
+    const WIDTH: u32 = 64;
+    const HEIGHT: u32 = 32;
+
+    let formats = [
+        (PixelFormat::Argb, ColorSpace::Lrgb, 1),
+        (PixelFormat::Rgb, ColorSpace::Lrgb, 1),
+        (PixelFormat::I444, ColorSpace::Bt601, 3),
+        (PixelFormat::I422, ColorSpace::Bt601, 1),
+        (PixelFormat::I422, ColorSpace::Bt601, 3),
+        (PixelFormat::I420, ColorSpace::Bt709, 1),
+        (PixelFormat::I420, ColorSpace::Bt709, 3),
+        (PixelFormat::Nv12, ColorSpace::Bt709, 1),
+        (PixelFormat::Nv12, ColorSpace::Bt709, 2),
+    ];
+
+    for &(pixel_format, color_space, num_planes) in formats.iter() {
+        let format = ImageFormat {
+            pixel_format,
+            color_space,
+            num_planes,
+        };
+
+        // With default strides, the descriptors span exactly the buffers sizes
+        let planes = describe_layout(WIDTH, HEIGHT, &format, None).unwrap();
+        let buffers_size = &mut [0usize; 3];
+        assert!(get_buffers_size(WIDTH, HEIGHT, &format, None, buffers_size).is_ok());
+
+        let mut spans = [0usize; 3];
+        for plane in planes.iter() {
+            let buffer = plane.buffer as usize;
+            assert!(buffer < num_planes as usize);
+            assert_eq!(plane.offset, spans[buffer]);
+            assert_eq!(plane.width, WIDTH / plane.horizontal_subsampling);
+            assert_eq!(plane.height, HEIGHT / plane.vertical_subsampling);
+            assert_eq!(
+                plane.stride,
+                (plane.bytes_per_sample * plane.width) as usize
+            );
+            assert_eq!(plane.size, plane.stride * plane.height as usize);
+            spans[buffer] += plane.size;
+        }
+
+        assert_eq!(&spans[..], &buffers_size[..]);
+
+        // Padded strides move the planes sharing a buffer accordingly
+        let strides = [256, 128, 128];
+        let planes = describe_layout(WIDTH, HEIGHT, &format, Some(&strides)).unwrap();
+        let mut spans = [0usize; 3];
+        for (i, plane) in planes.iter().enumerate() {
+            let buffer = plane.buffer as usize;
+            assert_eq!(plane.offset, spans[buffer]);
+            assert_eq!(plane.stride, strides[i]);
+            assert_eq!(plane.size, plane.stride * plane.height as usize);
+            spans[buffer] += plane.size;
+        }
+
+        // Strides smaller than the line size
+        let strides = [1, 1, 1];
+        match describe_layout(WIDTH, HEIGHT, &format, Some(&strides)) {
+            Err(ErrorKind::NotEnoughData) => {}
+            _ => assert!(false),
+        }
+
+        // Not enough strides
+        match describe_layout(WIDTH, HEIGHT, &format, Some(&[])) {
+            Err(ErrorKind::NotEnoughData) => {}
+            _ => assert!(false),
+        }
+    }
+
+    // Size and number of planes not compatible with the pixel format
+    let format = ImageFormat {
+        pixel_format: PixelFormat::I420,
+        color_space: ColorSpace::Bt601,
+        num_planes: 3,
+    };
+
+    match describe_layout(WIDTH + 1, HEIGHT, &format, None) {
+        Err(ErrorKind::InvalidValue) => {}
+        _ => assert!(false),
+    }
+
+    let format = ImageFormat {
+        pixel_format: PixelFormat::I420,
+        color_space: ColorSpace::Bt601,
+        num_planes: 2,
+    };
+
+    match describe_layout(WIDTH, HEIGHT, &format, None) {
+        Err(ErrorKind::InvalidValue) => {}
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn logical_strides_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 6;
+    const W: usize = WIDTH as usize;
+    const H: usize = HEIGHT as usize;
+
+    let mut rng = rand::thread_rng();
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let bgra: Vec<u8> = (0..4 * W * H).map(|_| rng.gen::<u8>()).collect();
+
+    // Only the luma lines are padded
+    for &(pixel_format, chroma_line) in [(PixelFormat::Nv12, W), (PixelFormat::P010, 2 * W)].iter()
+    {
+        let luma_stride = 2 * chroma_line + 16;
+        let strides = [luma_stride, chroma_line];
+        let single_format = ImageFormat {
+            pixel_format,
+            color_space: ColorSpace::Bt709,
+            num_planes: 1,
+        };
+
+        let planar_format = ImageFormat {
+            pixel_format,
+            color_space: ColorSpace::Bt709,
+            num_planes: 2,
+        };
+
+        let size = luma_stride * H + chroma_line * H / 2;
+        let buffers_size = &mut [0usize; 1];
+        assert!(
+            get_buffers_size(WIDTH, HEIGHT, &single_format, Some(&strides), buffers_size).is_ok()
+        );
+        assert_eq!(buffers_size[0], size);
+
+        let planes = describe_layout(WIDTH, HEIGHT, &single_format, Some(&strides)).unwrap();
+        assert_eq!(
+            (planes[1].offset, planes[1].stride),
+            (luma_stride * H, chroma_line)
+        );
+
+        // Same samples as a buffer for each plane
+        let mut single = vec![0u8; size];
+        assert!(convert_image(
+            WIDTH,
+            HEIGHT,
+            &bgra_format,
+            None,
+            &[&bgra],
+            &single_format,
+            Some(&strides),
+            &mut [&mut single],
+        )
+        .is_ok());
+
+        let (mut y, mut uv) = (vec![0u8; luma_stride * H], vec![0u8; chroma_line * H / 2]);
+        assert!(convert_image(
+            WIDTH,
+            HEIGHT,
+            &bgra_format,
+            None,
+            &[&bgra],
+            &planar_format,
+            Some(&strides),
+            &mut [&mut y, &mut uv],
+        )
+        .is_ok());
+        assert_eq!(single, [&y[..], &uv[..]].concat());
+
+        let mut single_bgra = vec![0u8; 4 * W * H];
+        let mut planar_bgra = vec![0u8; 4 * W * H];
+        assert!(convert_image(
+            WIDTH,
+            HEIGHT,
+            &single_format,
+            Some(&strides),
+            &[&single],
+            &bgra_format,
+            None,
+            &mut [&mut single_bgra],
+        )
+        .is_ok());
+        assert!(convert_image(
+            WIDTH,
+            HEIGHT,
+            &planar_format,
+            Some(&strides),
+            &[&y, &uv],
+            &bgra_format,
+            None,
+            &mut [&mut planar_bgra],
+        )
+        .is_ok());
+        assert_eq!(single_bgra, planar_bgra);
+
+        // The stride of the buffer is used when the chroma stride is not given
+        assert!(get_buffers_size(
+            WIDTH,
+            HEIGHT,
+            &single_format,
+            Some(&[luma_stride, STRIDE_AUTO]),
+            buffers_size
+        )
+        .is_ok());
+        assert_eq!(buffers_size[0], luma_stride * H * 3 / 2);
+
+        // Buffers missing a chroma line and chroma strides shorter than a line
+        match convert_image(
+            WIDTH,
+            HEIGHT,
+            &bgra_format,
+            None,
+            &[&bgra],
+            &single_format,
+            Some(&strides),
+            &mut [&mut single[..size - chroma_line]],
+        ) {
+            Err(ErrorKind::NotEnoughData) => {}
+            _ => assert!(false),
+        }
+
+        match convert_image(
+            WIDTH,
+            HEIGHT,
+            &single_format,
+            Some(&[luma_stride, chroma_line - 1]),
+            &[&single],
+            &bgra_format,
+            None,
+            &mut [&mut single_bgra],
+        ) {
+            Err(ErrorKind::NotEnoughData) => {}
+            _ => assert!(false),
+        }
+    }
+}
+
+#[test]
+fn batch_ok() {
+    use dcp::batch::{convert_images, Conversion};
+    use dcp::meta::FrameMeta;
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 18;
+    const SRC_SIZE: usize = 4 * (WIDTH as usize) * (HEIGHT as usize);
+    const DST_SIZE: usize = 3 * (WIDTH as usize) * (HEIGHT as usize) / 2;
+
+    let src_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let dst_format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt709,
+        num_planes: 1,
+    };
+
+    let bad_format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    bootstrap();
+    assert!(convert_images(&mut []).is_empty());
+
+    let mut rng = rand::thread_rng();
+    let src_buffer: Vec<u8> = (0..SRC_SIZE).map(|_| rng.gen::<u8>()).collect();
+    let truncated_buffer = vec![0u8; SRC_SIZE - 1];
+
+    let mut expected = vec![0u8; DST_SIZE];
+    assert!(convert_image(
+        WIDTH,
+        HEIGHT,
+        &src_format,
+        None,
+        &[&src_buffer],
+        &dst_format,
+        None,
+        &mut [&mut expected],
+    )
+    .is_ok());
+
+    let metas: Vec<FrameMeta> = (0..4)
+        .map(|index| FrameMeta {
+            timestamp: Some(index),
+            time_base: (1, 30),
+            ..Default::default()
+        })
+        .collect();
+
+    let mut outputs = vec![vec![0u8; DST_SIZE]; 4];
+    {
+        let src_buffers = [&src_buffer[..]];
+        let truncated_buffers = [&truncated_buffer[..]];
+        let mut dst_buffers: Vec<[&mut [u8]; 1]> =
+            outputs.iter_mut().map(|output| [&mut output[..]]).collect();
+        let mut dst_buffers = dst_buffers.iter_mut();
+
+        let mut batch = [
+            Conversion {
+                width: WIDTH,
+                height: HEIGHT,
+                src_format: &src_format,
+                src_strides: None,
+                src_buffers: &src_buffers,
+                dst_format: &dst_format,
+                dst_strides: None,
+                dst_buffers: dst_buffers.next().unwrap(),
+                meta: Some(&metas[0]),
+            },
+            Conversion {
+                width: WIDTH,
+                height: HEIGHT,
+                src_format: &src_format,
+                src_strides: None,
+                src_buffers: &truncated_buffers,
+                dst_format: &dst_format,
+                dst_strides: None,
+                dst_buffers: dst_buffers.next().unwrap(),
+                meta: Some(&metas[1]),
+            },
+            Conversion {
+                width: WIDTH,
+                height: HEIGHT,
+                src_format: &src_format,
+                src_strides: None,
+                src_buffers: &src_buffers,
+                dst_format: &bad_format,
+                dst_strides: None,
+                dst_buffers: dst_buffers.next().unwrap(),
+                meta: Some(&metas[2]),
+            },
+            Conversion {
+                width: WIDTH,
+                height: HEIGHT,
+                src_format: &src_format,
+                src_strides: None,
+                src_buffers: &src_buffers,
+                dst_format: &dst_format,
+                dst_strides: None,
+                dst_buffers: dst_buffers.next().unwrap(),
+                meta: Some(&metas[3]),
+            },
+        ];
+
+        let results = convert_images(&mut batch);
+        assert_eq!(results.len(), 4);
+        assert!(results[0].is_ok());
+        match results[1] {
+            Err(ErrorKind::NotEnoughData) => {}
+            _ => assert!(false),
+        }
+        match results[2] {
+            Err(ErrorKind::InvalidValue) => {}
+            _ => assert!(false),
+        }
+        assert!(results[3].is_ok());
+
+        // Each conversion keeps its metadata
+        for (index, conversion) in batch.iter().enumerate() {
+            assert_eq!(conversion.meta.unwrap().timestamp, Some(index as i64));
+        }
+    }
+
+    // Conversions following a failed one are still run
+    assert_eq!(outputs[0], expected);
+    assert_eq!(outputs[3], expected);
+}
+
+#[test]
+fn rotation_ok() {
+    use dcp::rotation::{rotate_image, Rotation};
+
+    const WIDTH: usize = 70;
+    const HEIGHT: usize = 38;
+
+    // Depth, horizontal and vertical subsampling of each logical plane
+    type Planes = &'static [(usize, usize, usize)];
+    let formats: [(PixelFormat, u32, Planes); 5] = [
+        (PixelFormat::I444, 3, &[(1, 1, 1), (1, 1, 1), (1, 1, 1)]),
+        (PixelFormat::I420, 1, &[(1, 1, 1), (1, 2, 2), (1, 2, 2)]),
+        (PixelFormat::I420, 3, &[(1, 1, 1), (1, 2, 2), (1, 2, 2)]),
+        (PixelFormat::Nv12, 1, &[(1, 1, 1), (2, 2, 2)]),
+        (PixelFormat::Nv12, 2, &[(1, 1, 1), (2, 2, 2)]),
+    ];
+
+    let rotations = [
+        Rotation::Degrees0,
+        Rotation::Degrees90,
+        Rotation::Degrees180,
+        Rotation::Degrees270,
+    ];
+
+    assert_eq!(Rotation::from_degrees(0), Some(Rotation::Degrees0));
+    assert_eq!(Rotation::from_degrees(450), Some(Rotation::Degrees90));
+    assert_eq!(Rotation::from_degrees(-90), Some(Rotation::Degrees270));
+    assert_eq!(Rotation::from_degrees(-180), Some(Rotation::Degrees180));
+    assert_eq!(Rotation::from_degrees(45), None);
+
+    let mut rng = rand::thread_rng();
+    for &(pixel_format, num_planes, planes) in formats.iter() {
+        let format = ImageFormat {
+            pixel_format,
+            color_space: ColorSpace::Bt601,
+            num_planes,
+        };
+
+        let plane_sizes: Vec<usize> = planes
+            .iter()
+            .map(|&(depth, h, v)| depth * (WIDTH / h) * (HEIGHT / v))
+            .collect();
+        let image_size: usize = plane_sizes.iter().sum();
+        let image: Vec<u8> = (0..image_size).map(|_| rng.gen::<u8>()).collect();
+
+        // Splits an image stored as consecutive planes into the buffers of the format
+        let buffer_sizes: Vec<usize> = if num_planes == 1 {
+            vec![image_size]
+        } else {
+            plane_sizes.clone()
+        };
+
+        let mut src_buffers: Vec<&[u8]> = Vec::new();
+        let mut start = 0;
+        for size in buffer_sizes.iter() {
+            src_buffers.push(&image[start..start + size]);
+            start += size;
+        }
+
+        for &rotation in rotations.iter() {
+            let (dst_width, dst_height) = if rotation.is_transposing() {
+                (HEIGHT, WIDTH)
+            } else {
+                (WIDTH, HEIGHT)
+            };
+
+            // Naive rotation of each logical plane
+            let mut expected = Vec::with_capacity(image_size);
+            let mut plane_start = 0;
+            for (&(depth, h, v), size) in planes.iter().zip(plane_sizes.iter()) {
+                let src = &image[plane_start..plane_start + size];
+                let (columns, rows) = (WIDTH / h, HEIGHT / v);
+                let (dst_columns, dst_rows) = (dst_width / h, dst_height / v);
+                for dy in 0..dst_rows {
+                    for dx in 0..dst_columns {
+                        let (x, y) = match rotation {
+                            Rotation::Degrees0 => (dx, dy),
+                            Rotation::Degrees90 => (dy, rows - 1 - dx),
+                            Rotation::Degrees180 => (columns - 1 - dx, rows - 1 - dy),
+                            Rotation::Degrees270 => (columns - 1 - dy, dx),
+                        };
+
+                        let offset = depth * (y * columns + x);
+                        expected.extend_from_slice(&src[offset..offset + depth]);
+                    }
+                }
+
+                plane_start += size;
+            }
+
+            let mut output = vec![0u8; image_size];
+            {
+                let mut dst_buffers: Vec<&mut [u8]> = Vec::new();
+                let mut rest = &mut output[..];
+                for &size in buffer_sizes.iter() {
+                    let (buffer, tail) = rest.split_at_mut(size);
+                    dst_buffers.push(buffer);
+                    rest = tail;
+                }
+
+                assert!(rotate_image(
+                    WIDTH as u32,
+                    HEIGHT as u32,
+                    &format,
+                    None,
+                    &src_buffers,
+                    None,
+                    &mut dst_buffers,
+                    rotation,
+                )
+                .is_ok());
+            }
+
+            assert_eq!(output, expected);
+        }
+    }
+
+    // Padded strides only move the lines
+    let format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt709,
+        num_planes: 2,
+    };
+
+    let y_plane: Vec<u8> = (0..WIDTH * HEIGHT).map(|_| rng.gen::<u8>()).collect();
+    let uv_plane: Vec<u8> = (0..WIDTH * HEIGHT / 2).map(|_| rng.gen::<u8>()).collect();
+    let src_strides = [WIDTH + 3, WIDTH + 9];
+    let mut padded_y = vec![0u8; src_strides[0] * HEIGHT];
+    let mut padded_uv = vec![0u8; src_strides[1] * HEIGHT / 2];
+    for (src, dst) in y_plane
+        .chunks_exact(WIDTH)
+        .zip(padded_y.chunks_exact_mut(src_strides[0]))
+    {
+        dst[..WIDTH].copy_from_slice(src);
+    }
+    for (src, dst) in uv_plane
+        .chunks_exact(WIDTH)
+        .zip(padded_uv.chunks_exact_mut(src_strides[1]))
+    {
+        dst[..WIDTH].copy_from_slice(src);
+    }
+
+    for &rotation in rotations.iter() {
+        let dst_width = if rotation.is_transposing() {
+            HEIGHT
+        } else {
+            WIDTH
+        };
+        let dst_strides = [dst_width + 17, dst_width + 1];
+
+        let mut expected_y = vec![0u8; WIDTH * HEIGHT];
+        let mut expected_uv = vec![0u8; WIDTH * HEIGHT / 2];
+        assert!(rotate_image(
+            WIDTH as u32,
+            HEIGHT as u32,
+            &format,
+            None,
+            &[&y_plane, &uv_plane],
+            None,
+            &mut [&mut expected_y, &mut expected_uv],
+            rotation,
+        )
+        .is_ok());
+
+        let mut output_y = vec![0u8; dst_strides[0] * WIDTH * HEIGHT / dst_width];
+        let mut output_uv = vec![0u8; dst_strides[1] * WIDTH * HEIGHT / dst_width / 2];
+        assert!(rotate_image(
+            WIDTH as u32,
+            HEIGHT as u32,
+            &format,
+            Some(&src_strides),
+            &[&padded_y, &padded_uv],
+            Some(&dst_strides),
+            &mut [&mut output_y, &mut output_uv],
+            rotation,
+        )
+        .is_ok());
+
+        for (output, expected) in output_y
+            .chunks_exact(dst_strides[0])
+            .zip(expected_y.chunks_exact(dst_width))
+        {
+            assert_eq!(&output[..dst_width], expected);
+        }
+        for (output, expected) in output_uv
+            .chunks_exact(dst_strides[1])
+            .zip(expected_uv.chunks_exact(dst_width))
+        {
+            assert_eq!(&output[..dst_width], expected);
+        }
+
+        // Destination buffers too small for the rotated image
+        let y_size = output_y.len() - dst_strides[0];
+        match rotate_image(
+            WIDTH as u32,
+            HEIGHT as u32,
+            &format,
+            Some(&src_strides),
+            &[&padded_y, &padded_uv],
+            Some(&dst_strides),
+            &mut [&mut output_y[..y_size], &mut output_uv],
+            rotation,
+        ) {
+            Err(ErrorKind::NotEnoughData) => {}
+            _ => assert!(false),
+        }
+    }
+
+    // Unsupported formats and sizes
+    let y_plane = vec![0u8; 4 * WIDTH * HEIGHT];
+    let mut output = vec![0u8; 4 * WIDTH * HEIGHT];
+    let cases = [
+        (
+            PixelFormat::I422,
+            ColorSpace::Bt601,
+            3,
+            WIDTH,
+            Rotation::Degrees90,
+            ErrorKind::InvalidOperation as u32,
+        ),
+        (
+            PixelFormat::Bgra,
+            ColorSpace::Lrgb,
+            1,
+            WIDTH,
+            Rotation::Degrees180,
+            ErrorKind::InvalidOperation as u32,
+        ),
+        (
+            PixelFormat::Nv12,
+            ColorSpace::Lrgb,
+            1,
+            WIDTH,
+            Rotation::Degrees180,
+            ErrorKind::InvalidValue as u32,
+        ),
+        (
+            PixelFormat::I420,
+            ColorSpace::Bt601,
+            2,
+            WIDTH,
+            Rotation::Degrees90,
+            ErrorKind::InvalidValue as u32,
+        ),
+        (
+            PixelFormat::I420,
+            ColorSpace::Bt601,
+            1,
+            WIDTH + 1,
+            Rotation::Degrees270,
+            ErrorKind::InvalidValue as u32,
+        ),
+    ];
+
+    for &(pixel_format, color_space, num_planes, width, rotation, error) in cases.iter() {
+        let format = ImageFormat {
+            pixel_format,
+            color_space,
+            num_planes,
+        };
+
+        let src_buffers: Vec<&[u8]> = y_plane.chunks(WIDTH * HEIGHT).take(3).collect();
+        let mut dst_buffers: Vec<&mut [u8]> = output.chunks_mut(WIDTH * HEIGHT).take(3).collect();
+
+        let result = rotate_image(
+            width as u32,
+            HEIGHT as u32,
+            &format,
+            None,
+            &src_buffers,
+            None,
+            &mut dst_buffers,
+            rotation,
+        );
+        assert_eq!(result.unwrap_err() as u32, error);
+    }
+}
+
+#[test]
+fn mirror_ok() {
+    use dcp::rotation::{mirror_image, rotate_image, Mirror, Rotation};
+
+    const WIDTH: usize = 70;
+    const HEIGHT: usize = 38;
+
+    // Depth, horizontal and vertical subsampling of each logical plane
+    type Planes = &'static [(usize, usize, usize)];
+    let formats: [(PixelFormat, u32, Planes); 6] = [
+        (PixelFormat::I444, 3, &[(1, 1, 1), (1, 1, 1), (1, 1, 1)]),
+        (PixelFormat::I422, 1, &[(1, 1, 1), (1, 2, 1), (1, 2, 1)]),
+        (PixelFormat::I420, 1, &[(1, 1, 1), (1, 2, 2), (1, 2, 2)]),
+        (PixelFormat::I420, 3, &[(1, 1, 1), (1, 2, 2), (1, 2, 2)]),
+        (PixelFormat::Nv12, 1, &[(1, 1, 1), (2, 2, 2)]),
+        (PixelFormat::Nv12, 2, &[(1, 1, 1), (2, 2, 2)]),
+    ];
+
+    let mut rng = rand::thread_rng();
+    for &(pixel_format, num_planes, planes) in formats.iter() {
+        let format = ImageFormat {
+            pixel_format,
+            color_space: ColorSpace::Bt709,
+            num_planes,
+        };
+
+        let plane_sizes: Vec<usize> = planes
+            .iter()
+            .map(|&(depth, h, v)| depth * (WIDTH / h) * (HEIGHT / v))
+            .collect();
+        let image_size: usize = plane_sizes.iter().sum();
+        let image: Vec<u8> = (0..image_size).map(|_| rng.gen::<u8>()).collect();
+        let buffer_sizes: Vec<usize> = if num_planes == 1 {
+            vec![image_size]
+        } else {
+            plane_sizes.clone()
+        };
+
+        for &mirror in [Mirror::Horizontal, Mirror::Vertical].iter() {
+            // Naive mirroring of each logical plane
+            let mut expected = Vec::with_capacity(image_size);
+            let mut plane_start = 0;
+            for (&(depth, h, v), size) in planes.iter().zip(plane_sizes.iter()) {
+                let src = &image[plane_start..plane_start + size];
+                let (columns, rows) = (WIDTH / h, HEIGHT / v);
+                for y in 0..rows {
+                    for x in 0..columns {
+                        let (x, y) = match mirror {
+                            Mirror::Horizontal => (columns - 1 - x, y),
+                            Mirror::Vertical => (x, rows - 1 - y),
+                        };
+
+                        let offset = depth * (y * columns + x);
+                        expected.extend_from_slice(&src[offset..offset + depth]);
+                    }
+                }
+
+                plane_start += size;
+            }
+
+            let mut output = image.clone();
+            {
+                let mut buffers: Vec<&mut [u8]> = Vec::new();
+                let mut rest = &mut output[..];
+                for &size in buffer_sizes.iter() {
+                    let (buffer, tail) = rest.split_at_mut(size);
+                    buffers.push(buffer);
+                    rest = tail;
+                }
+
+                assert!(mirror_image(
+                    WIDTH as u32,
+                    HEIGHT as u32,
+                    &format,
+                    None,
+                    &mut buffers,
+                    mirror,
+                )
+                .is_ok());
+            }
+
+            assert_eq!(output, expected);
+        }
+    }
+
+    // Mirroring both ways is a rotation by 180 degrees, and padding is left untouched
+    let format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt601,
+        num_planes: 1,
+    };
+
+    let stride = WIDTH + 6;
+    let image: Vec<u8> = (0..stride * HEIGHT * 3 / 2)
+        .map(|_| rng.gen::<u8>())
+        .collect();
+    let mut expected = image.clone();
+    assert!(rotate_image(
+        WIDTH as u32,
+        HEIGHT as u32,
+        &format,
+        Some(&[stride]),
+        &[&image],
+        Some(&[stride]),
+        &mut [&mut expected],
+        Rotation::Degrees180,
+    )
+    .is_ok());
+
+    let mut output = image.clone();
+    for &mirror in [Mirror::Horizontal, Mirror::Vertical].iter() {
+        assert!(mirror_image(
+            WIDTH as u32,
+            HEIGHT as u32,
+            &format,
+            Some(&[stride]),
+            &mut [&mut output],
+            mirror,
+        )
+        .is_ok());
+    }
+
+    assert_eq!(output, expected);
+    for (line, original) in output.chunks_exact(stride).zip(image.chunks_exact(stride)) {
+        assert_eq!(&line[WIDTH..], &original[WIDTH..]);
+    }
+
+    // Errors
+    let size = output.len();
+    match mirror_image(
+        WIDTH as u32,
+        HEIGHT as u32,
+        &format,
+        Some(&[stride]),
+        &mut [&mut output[..size - stride]],
+        Mirror::Vertical,
+    ) {
+        Err(ErrorKind::NotEnoughData) => {}
+        _ => assert!(false),
+    }
+
+    match mirror_image(
+        WIDTH as u32 + 1,
+        HEIGHT as u32,
+        &format,
+        Some(&[stride]),
+        &mut [&mut output],
+        Mirror::Horizontal,
+    ) {
+        Err(ErrorKind::InvalidValue) => {}
+        _ => assert!(false),
+    }
+
+    let format = ImageFormat {
+        pixel_format: PixelFormat::Rgb,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    match mirror_image(
+        WIDTH as u32,
+        HEIGHT as u32,
+        &format,
+        None,
+        &mut [&mut output],
+        Mirror::Horizontal,
+    ) {
+        Err(ErrorKind::InvalidOperation) => {}
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn watermark_ok() {
+    use dcp::composite::{convert_image_watermarked, stamp_watermark, StampMode, Watermark};
+
+    const WIDTH: usize = 100;
+    const HEIGHT: usize = 70;
+    const MASK_WIDTH: usize = 30;
+    const MASK_HEIGHT: usize = 20;
+    const MASK_STRIDE: usize = 32;
+
+    bootstrap();
+
+    let src_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let mut rng = rand::thread_rng();
+    let src_buffer: Vec<u8> = (0..4 * WIDTH * HEIGHT).map(|_| rng.gen::<u8>()).collect();
+    let mask: Vec<u8> = (0..MASK_STRIDE * MASK_HEIGHT)
+        .map(|_| rng.gen::<u8>())
+        .collect();
+
+    let modes = [
+        StampMode::Multiply,
+        StampMode::Add(200),
+        StampMode::Blend(128),
+    ];
+
+    // The mask crosses the left edge of the frame and the boundary of two bands
+    for (&color_space, &mode) in
+        iproduct!([ColorSpace::Bt601, ColorSpace::Bt709].iter(), modes.iter())
+    {
+        let dst_format = ImageFormat {
+            pixel_format: PixelFormat::Nv12,
+            color_space,
+            num_planes: 1,
+        };
+
+        let watermark = Watermark {
+            x: -5,
+            y: 55,
+            width: MASK_WIDTH as u32,
+            height: MASK_HEIGHT as u32,
+            stride: MASK_STRIDE,
+            mask: &mask,
+            mode,
+        };
+
+        let mut converted = vec![0u8; 3 * WIDTH * HEIGHT / 2];
+        assert!(convert_image(
+            WIDTH as u32,
+            HEIGHT as u32,
+            &src_format,
+            None,
+            &[&src_buffer],
+            &dst_format,
+            None,
+            &mut [&mut converted],
+        )
+        .is_ok());
+
+        let mut expected = converted.clone();
+        assert!(stamp_watermark(
+            WIDTH as u32,
+            HEIGHT as u32,
+            &dst_format,
+            None,
+            &mut [&mut expected],
+            &watermark,
+        )
+        .is_ok());
+
+        let mut output = vec![0u8; 3 * WIDTH * HEIGHT / 2];
+        assert!(convert_image_watermarked(
+            WIDTH as u32,
+            HEIGHT as u32,
+            &src_format,
+            None,
+            &[&src_buffer],
+            &dst_format,
+            None,
+            &mut [&mut output],
+            &watermark,
+        )
+        .is_ok());
+
+        assert_eq!(output, expected);
+
+        // Only the luma below the mask changes, and it stays in the video range
+        for (i, (&before, &after)) in converted.iter().zip(expected.iter()).enumerate() {
+            let (x, y) = (i % WIDTH, i / WIDTH);
+            if !(55..HEIGHT).contains(&y) || x >= MASK_WIDTH - 5 {
+                assert_eq!(before, after);
+                continue;
+            }
+
+            let coverage = mask[(y - 55) * MASK_STRIDE + x + 5];
+            if coverage == 0 {
+                assert_eq!(before, after);
+            }
+
+            assert!((16..=235).contains(&after));
+        }
+    }
+
+    // Fully covered luma
+    let format = ImageFormat {
+        pixel_format: PixelFormat::I444,
+        color_space: ColorSpace::Bt709,
+        num_planes: 3,
+    };
+
+    let full_mask = [255u8; 4];
+    for &(mode, expected) in [
+        (StampMode::Multiply, 16),
+        (StampMode::Add(255), 235),
+        (StampMode::Blend(128), 128),
+        (StampMode::Blend(0), 16),
+    ]
+    .iter()
+    {
+        let mut y_plane = vec![100u8; 16];
+        let mut u_plane = vec![100u8; 16];
+        let mut v_plane = vec![100u8; 16];
+        let watermark = Watermark {
+            x: 1,
+            y: 1,
+            width: 2,
+            height: 2,
+            stride: STRIDE_AUTO,
+            mask: &full_mask,
+            mode,
+        };
+
+        assert!(stamp_watermark(
+            4,
+            4,
+            &format,
+            None,
+            &mut [&mut y_plane, &mut u_plane, &mut v_plane],
+            &watermark,
+        )
+        .is_ok());
+
+        for (i, &value) in y_plane.iter().enumerate() {
+            let inside = (1..3).contains(&(i % 4)) && (1..3).contains(&(i / 4));
+            assert_eq!(value, if inside { expected } else { 100 });
+        }
+
+        assert!(u_plane
+            .iter()
+            .chain(v_plane.iter())
+            .all(|&value| value == 100));
+    }
+
+    // Errors
+    let dst_format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt601,
+        num_planes: 1,
+    };
+
+    let mut output = vec![0u8; 4 * WIDTH * HEIGHT];
+    let cases = [
+        (MASK_WIDTH - 1, MASK_HEIGHT, ErrorKind::InvalidValue as u32),
+        (
+            MASK_STRIDE,
+            MASK_HEIGHT + 1,
+            ErrorKind::NotEnoughData as u32,
+        ),
+    ];
+
+    for &(stride, height, error) in cases.iter() {
+        let watermark = Watermark {
+            x: 0,
+            y: 0,
+            width: MASK_WIDTH as u32,
+            height: height as u32,
+            stride,
+            mask: &mask,
+            mode: StampMode::Multiply,
+        };
+
+        let result = convert_image_watermarked(
+            WIDTH as u32,
+            HEIGHT as u32,
+            &src_format,
+            None,
+            &[&src_buffer],
+            &dst_format,
+            None,
+            &mut [&mut output],
+            &watermark,
+        );
+        assert_eq!(result.unwrap_err() as u32, error);
+
+        let result = stamp_watermark(
+            WIDTH as u32,
+            HEIGHT as u32,
+            &dst_format,
+            None,
+            &mut [&mut output],
+            &watermark,
+        );
+        assert_eq!(result.unwrap_err() as u32, error);
+    }
+
+    let watermark = Watermark {
+        x: 0,
+        y: 0,
+        width: MASK_WIDTH as u32,
+        height: MASK_HEIGHT as u32,
+        stride: MASK_STRIDE,
+        mask: &mask,
+        mode: StampMode::Multiply,
+    };
+
+    let rgb_format = ImageFormat {
+        pixel_format: PixelFormat::Rgb,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    match convert_image_watermarked(
+        WIDTH as u32,
+        HEIGHT as u32,
+        &src_format,
+        None,
+        &[&src_buffer],
+        &rgb_format,
+        None,
+        &mut [&mut output],
+        &watermark,
+    ) {
+        Err(ErrorKind::InvalidOperation) => {}
+        _ => assert!(false),
+    }
+
+    match stamp_watermark(
+        WIDTH as u32,
+        HEIGHT as u32,
+        &rgb_format,
+        None,
+        &mut [&mut output],
+        &watermark,
+    ) {
+        Err(ErrorKind::InvalidOperation) => {}
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn frame_meta_ok() {
+    use dcp::meta::FrameMeta;
+
+    let meta = FrameMeta::default();
+    assert!(meta.timestamp.is_none());
+    assert!(meta.timestamp_seconds().is_none());
+    assert!(meta.user_fields.is_empty());
+
+    let mut meta = FrameMeta {
+        timestamp: Some(-90),
+        time_base: (1, 30),
+        color: ColorSpaceHints {
+            color_space: Some(ColorSpace::Bt709),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    assert_eq!(meta.timestamp_seconds(), Some(-3.0));
+    meta.time_base = (0, 0);
+    assert!(meta.timestamp_seconds().is_none());
+
+    meta.user_fields
+        .insert("sequence".to_owned(), "42".to_owned());
+    let copy = meta.clone();
+    assert_eq!(
+        copy.user_fields.get("sequence").map(String::as_str),
+        Some("42")
+    );
+    assert!(matches!(copy.color.color_space, Some(ColorSpace::Bt709)));
+}
+
+#[test]
+fn rows_ok() {
+    use dcp::rows::convert_rows;
+
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 150;
+    const SCROLL: usize = 37;
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    // Logical planes, as (line size, line count)
+    let logical_planes = |pixel_format| match pixel_format {
+        PixelFormat::Bgra => vec![(4 * WIDTH, HEIGHT)],
+        PixelFormat::Nv12 => vec![(WIDTH, HEIGHT), (WIDTH, HEIGHT / 2)],
+        _ => vec![
+            (WIDTH, HEIGHT),
+            (WIDTH / 2, HEIGHT / 2),
+            (WIDTH / 2, HEIGHT / 2),
+        ],
+    };
+
+    let bgra = || format(PixelFormat::Bgra, ColorSpace::Lrgb, 1);
+    let mut rng = rand::thread_rng();
+    let rgb_image: Vec<u8> = (0..4 * WIDTH * HEIGHT).map(|_| rng.gen::<u8>()).collect();
+    for (src_format, dst_format) in [
+        (bgra(), format(PixelFormat::Nv12, ColorSpace::Bt601, 1)),
+        (bgra(), format(PixelFormat::I420, ColorSpace::Bt709, 3)),
+        (format(PixelFormat::Nv12, ColorSpace::Bt709, 1), bgra()),
+        (format(PixelFormat::I420, ColorSpace::Bt601, 3), bgra()),
+    ]
+    .iter()
+    {
+        let src_sizes = &mut [0usize; MAX_NUMBER_OF_PLANES as usize];
+        let dst_sizes = &mut [0usize; MAX_NUMBER_OF_PLANES as usize];
+        assert!(get_buffers_size(WIDTH, HEIGHT, src_format, None, src_sizes).is_ok());
+        assert!(get_buffers_size(WIDTH, HEIGHT, dst_format, None, dst_sizes).is_ok());
+
+        let mut src_planes: Vec<Vec<u8>> = src_sizes[..src_format.num_planes as usize]
+            .iter()
+            .map(|&size| vec![0u8; size])
+            .collect();
+        if let PixelFormat::Bgra = src_format.pixel_format {
+            src_planes[0].copy_from_slice(&rgb_image);
+        } else {
+            let mut src_buffers: Vec<&mut [u8]> =
+                src_planes.iter_mut().map(|plane| &mut plane[..]).collect();
+            assert!(convert_image(
+                WIDTH,
+                HEIGHT,
+                &bgra(),
+                None,
+                &[&rgb_image],
+                src_format,
+                None,
+                &mut src_buffers,
+            )
+            .is_ok());
+        }
+
+        let new_dst = || -> Vec<Vec<u8>> {
+            dst_sizes[..dst_format.num_planes as usize]
+                .iter()
+                .map(|&size| vec![0u8; size])
+                .collect()
+        };
+
+        let src_buffers: Vec<&[u8]> = src_planes.iter().map(|plane| &plane[..]).collect();
+        let mut reference = new_dst();
+        {
+            let mut dst_buffers: Vec<&mut [u8]> =
+                reference.iter_mut().map(|plane| &mut plane[..]).collect();
+            assert!(convert_image(
+                WIDTH,
+                HEIGHT,
+                src_format,
+                None,
+                &src_buffers,
+                dst_format,
+                None,
+                &mut dst_buffers,
+            )
+            .is_ok());
+        }
+
+        // Store the lines of each logical plane in a ring buffer, scrolled and padded
+        let planes = logical_planes(src_format.pixel_format);
+        let src_image: Vec<u8> = src_buffers.concat();
+        let mut ring_buffers = Vec::new();
+        let mut offset = 0;
+        for &(line_size, line_count) in planes.iter() {
+            let (line_size, line_count) = (line_size as usize, line_count as usize);
+            let plane = &src_image[offset..offset + line_size * line_count];
+            let mut ring_buffer = Vec::new();
+            for line in plane.chunks(line_size) {
+                ring_buffer.extend_from_slice(line);
+                ring_buffer.extend_from_slice(&[0xFF; 3]);
+            }
+
+            let scroll = (SCROLL * line_count / HEIGHT as usize) * (line_size + 3);
+            ring_buffer.rotate_left(scroll);
+            ring_buffers.push(ring_buffer);
+            offset += line_size * line_count;
+        }
+
+        let row_tables: Vec<Vec<&[u8]>> = ring_buffers
+            .iter()
+            .zip(planes.iter())
+            .map(|(ring_buffer, &(line_size, line_count))| {
+                let mut rows: Vec<&[u8]> = ring_buffer.chunks(line_size as usize + 3).collect();
+                rows.rotate_right((SCROLL * line_count as usize) / HEIGHT as usize);
+                rows
+            })
+            .collect();
+        let src_rows: Vec<&[&[u8]]> = row_tables.iter().map(|rows| &rows[..]).collect();
+
+        let mut dst_planes = new_dst();
+        {
+            let mut dst_buffers: Vec<&mut [u8]> =
+                dst_planes.iter_mut().map(|plane| &mut plane[..]).collect();
+            assert!(convert_rows(
+                WIDTH,
+                HEIGHT,
+                src_format,
+                &src_rows,
+                dst_format,
+                None,
+                &mut dst_buffers,
+            )
+            .is_ok());
+        }
+
+        assert_eq!(dst_planes, reference);
+
+        let mut dst_buffers: Vec<&mut [u8]> =
+            dst_planes.iter_mut().map(|plane| &mut plane[..]).collect();
+        let mut convert = |src_rows: &[&[&[u8]]]| {
+            convert_rows(
+                WIDTH,
+                HEIGHT,
+                src_format,
+                src_rows,
+                dst_format,
+                None,
+                &mut dst_buffers,
+            )
+            .err()
+            .map(|e| e as u32)
+        };
+
+        let short_row: &[u8] = &row_tables[0][1][..planes[0].0 as usize - 1];
+        let mut short_rows = row_tables[0].clone();
+        short_rows[1] = short_row;
+        let mut missing_rows = row_tables[0].clone();
+        missing_rows.pop();
+
+        let not_enough_data = Some(ErrorKind::NotEnoughData as u32);
+        assert_eq!(convert(&src_rows[..src_rows.len() - 1]), not_enough_data);
+        for rows in [short_rows, missing_rows].iter() {
+            let mut invalid_rows = src_rows.clone();
+            invalid_rows[0] = rows;
+            assert_eq!(convert(&invalid_rows), not_enough_data);
+        }
+    }
+}
+
+#[test]
+fn sink_ok() {
+    use dcp::stream::convert_to_sink;
+
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 150;
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    // Logical planes, as (line size, vertical subsampling)
+    let logical_planes = |pixel_format| match pixel_format {
+        PixelFormat::Bgra => vec![(4 * WIDTH as usize, 0)],
+        PixelFormat::Nv12 => vec![(WIDTH as usize, 0), (WIDTH as usize, 1)],
+        PixelFormat::I444 => vec![(WIDTH as usize, 0); 3],
+        _ => vec![
+            (WIDTH as usize, 0),
+            (WIDTH as usize / 2, 1),
+            (WIDTH as usize / 2, 1),
+        ],
+    };
+
+    let bgra = || format(PixelFormat::Bgra, ColorSpace::Lrgb, 1);
+    let mut rng = rand::thread_rng();
+    let rgb_image: Vec<u8> = (0..4 * WIDTH * HEIGHT).map(|_| rng.gen::<u8>()).collect();
+    let nv12 = || format(PixelFormat::Nv12, ColorSpace::Bt601, 1);
+    let nv12_size = 3 * (WIDTH * HEIGHT) as usize / 2;
+    let mut nv12_image = vec![0u8; nv12_size];
+    assert!(convert_image(
+        WIDTH,
+        HEIGHT,
+        &bgra(),
+        None,
+        &[&rgb_image],
+        &nv12(),
+        None,
+        &mut [&mut nv12_image],
+    )
+    .is_ok());
+
+    for (src_format, src_image, dst_format, band_height) in [
+        (bgra(), &rgb_image, nv12(), None),
+        (
+            bgra(),
+            &rgb_image,
+            format(PixelFormat::I420, ColorSpace::Bt709, 3),
+            Some(16),
+        ),
+        (nv12(), &nv12_image, bgra(), Some(2)),
+        (
+            bgra(),
+            &rgb_image,
+            format(PixelFormat::I444, ColorSpace::Bt601, 3),
+            Some(7),
+        ),
+    ]
+    .iter()
+    {
+        let dst_sizes = &mut [0usize; MAX_NUMBER_OF_PLANES as usize];
+        assert!(get_buffers_size(WIDTH, HEIGHT, dst_format, None, dst_sizes).is_ok());
+        let mut reference: Vec<Vec<u8>> = dst_sizes[..dst_format.num_planes as usize]
+            .iter()
+            .map(|&size| vec![0u8; size])
+            .collect();
+        {
+            let mut dst_buffers: Vec<&mut [u8]> =
+                reference.iter_mut().map(|plane| &mut plane[..]).collect();
+            assert!(convert_image(
+                WIDTH,
+                HEIGHT,
+                src_format,
+                None,
+                &[src_image],
+                dst_format,
+                None,
+                &mut dst_buffers,
+            )
+            .is_ok());
+        }
+
+        let planes = logical_planes(dst_format.pixel_format);
+        let reference = reference.concat();
+        let mut next_line = 0;
+        assert!(convert_to_sink(
+            WIDTH,
+            HEIGHT,
+            src_format,
+            None,
+            &[src_image],
+            dst_format,
+            *band_height,
+            |rows, band| {
+                assert_eq!(rows.start, next_line);
+                assert!(rows.end > rows.start && rows.end <= HEIGHT);
+                if let Some(band_height) = band_height {
+                    assert!(rows.len() == *band_height as usize || rows.end == HEIGHT);
+                }
+
+                // Compare the lines of each logical plane
+                let mut plane_offset = 0;
+                let mut band_offset = 0;
+                for &(line_size, vertical_shift) in planes.iter() {
+                    let start = (rows.start as usize >> vertical_shift) * line_size;
+                    let end = (rows.end as usize >> vertical_shift) * line_size;
+                    let size = end - start;
+                    assert_eq!(
+                        &band[band_offset..band_offset + size],
+                        &reference[plane_offset + start..plane_offset + end]
+                    );
+
+                    band_offset += size;
+                    plane_offset += ((HEIGHT as usize) >> vertical_shift) * line_size;
+                }
+
+                assert_eq!(band_offset, band.len());
+                next_line = rows.end;
+                true
+            },
+        )
+        .is_ok());
+        assert_eq!(next_line, HEIGHT);
+    }
+
+    // Bands splitting chroma rows
+    match convert_to_sink(
+        WIDTH,
+        HEIGHT,
+        &bgra(),
+        None,
+        &[&rgb_image],
+        &nv12(),
+        Some(3),
+        |_, _| true,
+    ) {
+        Err(ErrorKind::InvalidValue) => {}
+        _ => assert!(false),
+    }
+
+    // Stopped by the sink
+    let mut calls = 0;
+    match convert_to_sink(
+        WIDTH,
+        HEIGHT,
+        &bgra(),
+        None,
+        &[&rgb_image],
+        &nv12(),
+        Some(16),
+        |_, _| {
+            calls += 1;
+            false
+        },
+    ) {
+        Err(ErrorKind::Cancelled) => {}
+        _ => assert!(false),
+    }
+    assert_eq!(calls, 1);
+
+    // Short source
+    match convert_to_sink(
+        WIDTH,
+        HEIGHT,
+        &bgra(),
+        None,
+        &[&rgb_image[..rgb_image.len() - 1]],
+        &nv12(),
+        None,
+        |_, _| true,
+    ) {
+        Err(ErrorKind::NotEnoughData) => {}
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn source_ok() {
+    use dcp::stream::convert_from_source;
+
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 150;
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    // Logical planes, as (line size, vertical subsampling)
+    let logical_planes = |pixel_format| match pixel_format {
+        PixelFormat::Bgra => vec![(4 * WIDTH as usize, 0)],
+        PixelFormat::Nv12 => vec![(WIDTH as usize, 0), (WIDTH as usize, 1)],
+        PixelFormat::I444 => vec![(WIDTH as usize, 0); 3],
+        _ => vec![
+            (WIDTH as usize, 0),
+            (WIDTH as usize / 2, 1),
+            (WIDTH as usize / 2, 1),
+        ],
+    };
+
+    let new_image = |format: &ImageFormat| -> Vec<Vec<u8>> {
+        let sizes = &mut [0usize; MAX_NUMBER_OF_PLANES as usize];
+        assert!(get_buffers_size(WIDTH, HEIGHT, format, None, sizes).is_ok());
+        sizes[..format.num_planes as usize]
+            .iter()
+            .map(|&size| vec![0u8; size])
+            .collect()
+    };
+
+    let bgra = || format(PixelFormat::Bgra, ColorSpace::Lrgb, 1);
+    let mut rng = rand::thread_rng();
+    let rgb_image: Vec<u8> = (0..4 * WIDTH * HEIGHT).map(|_| rng.gen::<u8>()).collect();
+    for (src_format, dst_format, band_height) in [
+        (
+            bgra(),
+            format(PixelFormat::Nv12, ColorSpace::Bt601, 2),
+            None,
+        ),
+        (
+            format(PixelFormat::Nv12, ColorSpace::Bt709, 1),
+            bgra(),
+            Some(16),
+        ),
+        (
+            format(PixelFormat::I420, ColorSpace::Bt601, 3),
+            bgra(),
+            Some(2),
+        ),
+        (
+            format(PixelFormat::I444, ColorSpace::Bt709, 3),
+            bgra(),
+            Some(7),
+        ),
+    ]
+    .iter()
+    {
+        let mut src_planes = new_image(src_format);
+        if let PixelFormat::Bgra = src_format.pixel_format {
+            src_planes[0].copy_from_slice(&rgb_image);
+        } else {
+            let mut src_buffers: Vec<&mut [u8]> =
+                src_planes.iter_mut().map(|plane| &mut plane[..]).collect();
+            assert!(convert_image(
+                WIDTH,
+                HEIGHT,
+                &bgra(),
+                None,
+                &[&rgb_image],
+                src_format,
+                None,
+                &mut src_buffers,
+            )
+            .is_ok());
+        }
+
+        let src_buffers: Vec<&[u8]> = src_planes.iter().map(|plane| &plane[..]).collect();
+        let mut reference = new_image(dst_format);
+        {
+            let mut dst_buffers: Vec<&mut [u8]> =
+                reference.iter_mut().map(|plane| &mut plane[..]).collect();
+            assert!(convert_image(
+                WIDTH,
+                HEIGHT,
+                src_format,
+                None,
+                &src_buffers,
+                dst_format,
+                None,
+                &mut dst_buffers,
+            )
+            .is_ok());
+        }
+
+        let planes = logical_planes(src_format.pixel_format);
+        let src_image = src_buffers.concat();
+        let mut next_line = 0;
+        let mut dst_planes = new_image(dst_format);
+        {
+            let mut dst_buffers: Vec<&mut [u8]> =
+                dst_planes.iter_mut().map(|plane| &mut plane[..]).collect();
+            assert!(convert_from_source(
+                WIDTH,
+                HEIGHT,
+                src_format,
+                *band_height,
+                |rows, band| {
+                    assert_eq!(rows.start, next_line);
+                    assert!(rows.end > rows.start && rows.end <= HEIGHT);
+                    if let Some(band_height) = band_height {
+                        assert!(rows.len() == *band_height as usize || rows.end == HEIGHT);
+                    }
+
+                    // Copy the lines of each logical plane
+                    let mut plane_offset = 0;
+                    let mut band_offset = 0;
+                    for &(line_size, vertical_shift) in planes.iter() {
+                        let start = (rows.start as usize >> vertical_shift) * line_size;
+                        let end = (rows.end as usize >> vertical_shift) * line_size;
+                        let size = end - start;
+                        band[band_offset..band_offset + size]
+                            .copy_from_slice(&src_image[plane_offset + start..plane_offset + end]);
+
+                        band_offset += size;
+                        plane_offset += ((HEIGHT as usize) >> vertical_shift) * line_size;
+                    }
+
+                    assert_eq!(band_offset, band.len());
+                    next_line = rows.end;
+                    true
+                },
+                dst_format,
+                None,
+                &mut dst_buffers,
+            )
+            .is_ok());
+        }
+
+        assert_eq!(next_line, HEIGHT);
+        assert_eq!(dst_planes, reference);
+    }
+
+    let nv12 = || format(PixelFormat::Nv12, ColorSpace::Bt601, 1);
+    let mut dst_image = vec![0u8; 4 * (WIDTH * HEIGHT) as usize];
+
+    // Bands splitting chroma rows
+    match convert_from_source(
+        WIDTH,
+        HEIGHT,
+        &nv12(),
+        Some(3),
+        |_, _| true,
+        &bgra(),
+        None,
+        &mut [&mut dst_image],
+    ) {
+        Err(ErrorKind::InvalidValue) => {}
+        _ => assert!(false),
+    }
+
+    // Stopped by the source, after the first band was converted
+    let mut calls = 0;
+    match convert_from_source(
+        WIDTH,
+        HEIGHT,
+        &nv12(),
+        Some(16),
+        |_, band| {
+            calls += 1;
+            for value in band.iter_mut() {
+                *value = 128;
+            }
+            calls == 1
+        },
+        &bgra(),
+        None,
+        &mut [&mut dst_image],
+    ) {
+        Err(ErrorKind::Cancelled) => {}
+        _ => assert!(false),
+    }
+    assert_eq!(calls, 2);
+    assert!(dst_image[..4 * 16 * WIDTH as usize]
+        .iter()
+        .all(|&value| value != 0));
+    assert!(dst_image[4 * 16 * WIDTH as usize..]
+        .iter()
+        .all(|&value| value == 0));
+
+    // Short destination
+    let dst_size = dst_image.len() - 1;
+    match convert_from_source(
+        WIDTH,
+        HEIGHT,
+        &nv12(),
+        None,
+        |_, _| true,
+        &bgra(),
+        None,
+        &mut [&mut dst_image[..dst_size]],
+    ) {
+        Err(ErrorKind::NotEnoughData) => {}
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn negotiate_ok() {
+    bootstrap();
+
+    let bgra = || ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+    let yuv = |pixel_format, num_planes| ImageFormat {
+        pixel_format,
+        color_space: ColorSpace::Bt601,
+        num_planes,
+    };
+    let rgb = |pixel_format| ImageFormat {
+        pixel_format,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let negotiated = |src_candidates: &[ImageFormat], dst_candidates: &[ImageFormat]| {
+        negotiate(src_candidates, dst_candidates).map(|(src_format, dst_format, score)| {
+            (
+                src_format.pixel_format as u32,
+                dst_format.pixel_format as u32,
+                src_format.num_planes,
+                dst_format.num_planes,
+                score.conversions,
+                score.subsampled_images,
+            )
+        })
+    };
+
+    const ARGB: u32 = PixelFormat::Argb as u32;
+    const BGRA: u32 = PixelFormat::Bgra as u32;
+    const RGB: u32 = PixelFormat::Rgb as u32;
+
+    // Direct conversions are preferred, then the earliest candidates
+    assert_eq!(
+        negotiated(
+            &[rgb(PixelFormat::Rgb), bgra()],
+            &[yuv(PixelFormat::Nv12, 2), yuv(PixelFormat::I420, 3)]
+        ),
+        Some((BGRA, PIXEL_FORMAT_NV12, 1, 2, 1, 0))
+    );
+    assert_eq!(
+        negotiated(
+            &[rgb(PixelFormat::Argb), bgra()],
+            &[yuv(PixelFormat::I420, 3)]
+        ),
+        Some((ARGB, PIXEL_FORMAT_I420, 1, 3, 1, 0))
+    );
+
+    // Routed conversions
+    assert_eq!(
+        negotiated(&[rgb(PixelFormat::Rgb)], &[yuv(PixelFormat::Nv12, 1)]),
+        Some((RGB, PIXEL_FORMAT_NV12, 1, 1, 2, 0))
+    );
+
+    // Identical formats need no conversion, but they must have the same planes
+    assert_eq!(
+        negotiated(
+            &[bgra(), yuv(PixelFormat::I420, 3)],
+            &[yuv(PixelFormat::I420, 3)]
+        ),
+        Some((PIXEL_FORMAT_I420, PIXEL_FORMAT_I420, 3, 3, 0, 0))
+    );
+    assert_eq!(
+        negotiated(&[yuv(PixelFormat::Nv12, 2)], &[yuv(PixelFormat::Nv12, 1)]),
+        None
+    );
+
+    // Invalid formats and formats without conversions are never picked
+    assert_eq!(
+        negotiated(
+            &[yuv(PixelFormat::Nv12, 3), rgb(PixelFormat::Rgba)],
+            &[yuv(PixelFormat::I422, 3)]
+        ),
+        None
+    );
+    assert_eq!(negotiated(&[], &[bgra()]), None);
+}
+
+#[test]
+fn suggest_color_space_ok() {
+    let suggest =
+        |width, height, hints: &ColorSpaceHints| suggest_color_space(width, height, hints) as u32;
+
+    let hints = ColorSpaceHints::default();
+    for (width, height, color_space) in [
+        (640, 480, ColorSpace::Bt601),
+        (720, 576, ColorSpace::Bt601),
+        (1024, 576, ColorSpace::Bt601),
+        (1280, 720, ColorSpace::Bt709),
+        (720, 720, ColorSpace::Bt709),
+        (1920, 1080, ColorSpace::Bt709),
+        (2048, 1080, ColorSpace::Bt709),
+        (3840, 2160, ColorSpace::Bt2020Pq),
+        (1920, 1200, ColorSpace::Bt2020Pq),
+    ]
+    .iter()
+    {
+        assert_eq!(suggest(*width, *height, &hints), *color_space as u32);
+    }
+
+    let full_range = ColorSpaceHints {
+        full_range: true,
+        ..Default::default()
+    };
+    assert_eq!(suggest(1920, 1080, &full_range), ColorSpace::Jfif as u32);
+
+    let pq_transfer = ColorSpaceHints {
+        pq_transfer: true,
+        ..Default::default()
+    };
+    assert_eq!(suggest(640, 480, &pq_transfer), ColorSpace::Bt2020Pq as u32);
+
+    // An explicit color space overrides every other hint
+    let explicit = ColorSpaceHints {
+        color_space: Some(ColorSpace::Bt709),
+        full_range: true,
+        pq_transfer: true,
+    };
+    assert_eq!(suggest(640, 480, &explicit), ColorSpace::Bt709 as u32);
+    assert_eq!(suggest(3840, 2160, &explicit), ColorSpace::Bt709 as u32);
+}
+
+#[test]
+fn image_format_ext_ok() {
+    for (pixel_format, color_space, num_planes) in iproduct!(
+        PIXEL_FORMATS.iter(),
+        COLOR_SPACES.iter(),
+        1..=MAX_NUMBER_OF_PLANES
+    ) {
+        let format = ImageFormat {
+            pixel_format: *pixel_format,
+            color_space: *color_space,
+            num_planes,
+        };
+
+        let ext = ImageFormatExt::from(&format);
+        assert!(ext.is_implied());
+
+        let expected_range = match color_space {
+            ColorSpace::Lrgb | ColorSpace::Jfif => SampleRange::Full,
+            _ => SampleRange::Limited,
+        };
+        assert_eq!(ext.range, expected_range);
+
+        let expected_bit_depths = match pixel_format {
+            PixelFormat::Argb
+            | PixelFormat::Bgra
+            | PixelFormat::Bgr
+            | PixelFormat::Rgba
+            | PixelFormat::Rgb => [8, 0, 0],
+            PixelFormat::RgbaF16 | PixelFormat::Bgr48 | PixelFormat::Rgb48 => [16, 0, 0],
+            PixelFormat::RgbF32 | PixelFormat::RgbaF32 => [32, 0, 0],
+            PixelFormat::I444 | PixelFormat::I422 | PixelFormat::I420 => [8, 8, 8],
+            PixelFormat::Nv12 => [8, 8, 0],
+            PixelFormat::P010 => [10, 10, 0],
+            PixelFormat::YuvF32Planar => [32, 32, 32],
+        };
+        assert_eq!(ext.bit_depths, expected_bit_depths);
+
+        let back = ext.to_format().unwrap();
+        assert_eq!(back.pixel_format as u32, format.pixel_format as u32);
+        assert_eq!(back.color_space as u32, format.color_space as u32);
+        assert_eq!(back.num_planes, format.num_planes);
+
+        let mut full = ext;
+        full.range = match ext.range {
+            SampleRange::Limited => SampleRange::Full,
+            SampleRange::Full => SampleRange::Limited,
+        };
+        assert!(!full.is_implied());
+        match full.to_format() {
+            Err(ErrorKind::InvalidOperation) => {}
+            _ => assert!(false),
+        }
+
+        let mut deeper = ext;
+        deeper.bit_depths[0] = 12;
+        match deeper.to_format() {
+            Err(ErrorKind::InvalidOperation) => {}
+            _ => assert!(false),
+        }
+    }
+}
+
+#[test]
+fn empty_image_ok() {
+    bootstrap();
+
+    let mut formats = Vec::new();
+    for (pixel_format, color_space, num_planes) in iproduct!(
+        PIXEL_FORMATS.iter(),
+        COLOR_SPACES.iter(),
+        1..=MAX_NUMBER_OF_PLANES
+    ) {
+        formats.push(ImageFormat {
+            pixel_format: *pixel_format,
+            color_space: *color_space,
+            num_planes,
+        });
+    }
+
+    let token = CancellationToken::new();
+    token.cancel();
+
+    for ((width, height), src_format, dst_format) in iproduct!(
+        [(0, 0), (0, 4), (4, 0)].iter(),
+        formats.iter(),
+        formats.iter()
+    ) {
+        let src_planes = vec![Vec::<u8>::new(); src_format.num_planes as usize];
+        let mut dst_planes = vec![Vec::<u8>::new(); dst_format.num_planes as usize];
+        let src_buffers: Vec<&[u8]> = src_planes.iter().map(|plane| &plane[..]).collect();
+        let mut dst_buffers: Vec<&mut [u8]> =
+            dst_planes.iter_mut().map(|plane| &mut plane[..]).collect();
+
+        let sizes = &mut [1usize; MAX_NUMBER_OF_PLANES as usize];
+        if get_buffers_size(*width, *height, src_format, None, sizes).is_ok() {
+            assert!(sizes[..src_format.num_planes as usize]
+                .iter()
+                .all(|&size| size == 0));
+        }
+
+        // Empty images fail only when the formats can not be converted
+        let expected = describe_conversion(*width, *height, src_format, dst_format)
+            .err()
+            .map(|e| e as u32);
+        assert_eq!(
+            convert_image(
+                *width,
+                *height,
+                src_format,
+                None,
+                &src_buffers,
+                dst_format,
+                None,
+                &mut dst_buffers,
+            )
+            .err()
+            .map(|e| e as u32),
+            expected
+        );
+
+        if let Ok(plan) = ConversionPlan::new(*width, *height, src_format, None, dst_format, None) {
+            assert!(plan.convert(&src_buffers, &mut dst_buffers).is_ok());
+            assert!(convert_image_cancellable(
+                *width,
+                *height,
+                src_format,
+                None,
+                &src_buffers,
+                dst_format,
+                None,
+                &mut dst_buffers,
+                &token,
+            )
+            .is_ok());
+        }
+    }
+
+    // Explicit strides do not require any storage either
+    let format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt601,
+        num_planes: 2,
+    };
+    for (width, height) in [(0, 4), (4, 0)].iter() {
+        let sizes = &mut [1usize; 2];
+        assert!(get_buffers_size(*width, *height, &format, Some(&[64, 64]), sizes).is_ok());
+        assert_eq!(sizes, &[0, 0]);
+    }
+}
+
+#[test]
+fn yuv_color_space_ok() {
+    bootstrap();
+
+    // Lines are not multiple of the vector sizes
+    const WIDTH: u32 = 70;
+    const HEIGHT: u32 = 6;
+
+    let format = |pixel_format, color_space, num_planes| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes,
+    };
+
+    // Each 2x2 block has a single color, so chroma subsampling loses nothing
+    let mut rng = rand::thread_rng();
+    let rgb_format = format(PixelFormat::Bgra, ColorSpace::Lrgb, 1);
+    let mut rgb = alloc_planes(WIDTH, HEIGHT, &rgb_format);
+    let block_colors: Vec<[u8; 3]> = (0..(WIDTH * HEIGHT / 4))
+        .map(|_| {
+            [
+                rng.gen_range(32, 224),
+                rng.gen_range(32, 224),
+                rng.gen_range(32, 224),
+            ]
+        })
+        .collect();
+    for (i, pixel) in rgb[0].chunks_mut(4).enumerate() {
+        let (x, y) = (i % (WIDTH as usize), i / (WIDTH as usize));
+        let color = block_colors[(y / 2) * (WIDTH as usize / 2) + x / 2];
+        pixel[..3].copy_from_slice(&color);
+        pixel[3] = 255;
+    }
+
+    for (pixel_format, num_planes) in [
+        (PixelFormat::I444, 3),
+        (PixelFormat::I420, 3),
+        (PixelFormat::Nv12, 1),
+        (PixelFormat::Nv12, 2),
+    ]
+    .iter()
+    {
+        let bt601 = format(*pixel_format, ColorSpace::Bt601, *num_planes);
+        let bt709 = format(*pixel_format, ColorSpace::Bt709, *num_planes);
+        assert_eq!(
+            describe_conversion(WIDTH, HEIGHT, &bt601, &bt709).unwrap(),
+            format!(
+                "{{route:[{:?}-Bt601,{:?}-Bt709]}}",
+                pixel_format, pixel_format
+            )
+        );
+
+        let mut reference_601 = alloc_planes(WIDTH, HEIGHT, &bt601);
+        let mut reference_709 = alloc_planes(WIDTH, HEIGHT, &bt709);
+        assert!(
+            convert_planes(WIDTH, HEIGHT, &rgb_format, &rgb, &bt601, &mut reference_601).is_ok()
+        );
+        assert!(
+            convert_planes(WIDTH, HEIGHT, &rgb_format, &rgb, &bt709, &mut reference_709).is_ok()
+        );
+
+        // The fused conversion matches the conversion from rgb
+        let mut image_709 = alloc_planes(WIDTH, HEIGHT, &bt709);
+        let mut image_601 = alloc_planes(WIDTH, HEIGHT, &bt601);
+        assert!(convert_planes(
+            WIDTH,
+            HEIGHT,
+            &bt601,
+            &reference_601,
+            &bt709,
+            &mut image_709
+        )
+        .is_ok());
+        assert!(convert_planes(
+            WIDTH,
+            HEIGHT,
+            &bt709,
+            &reference_709,
+            &bt601,
+            &mut image_601
+        )
+        .is_ok());
+
+        for (image, reference, format) in [
+            (&image_709, &reference_709, &bt709),
+            (&image_601, &reference_601, &bt601),
+        ]
+        .iter()
+        {
+            let image: Vec<&[u8]> = image.iter().map(|x| &x[..]).collect();
+            let reference: Vec<&[u8]> = reference.iter().map(|x| &x[..]).collect();
+            let stats =
+                diff::diff_images(WIDTH, HEIGHT, format, None, &image, None, &reference).unwrap();
+            assert!(stats.planes.iter().all(|plane| plane.max <= 2));
+        }
+
+        // Destination buffers are checked
+        let mut small = alloc_planes(WIDTH, HEIGHT, &bt709);
+        let last = small.len() - 1;
+        small[last].pop();
+        assert_eq!(
+            convert_planes(WIDTH, HEIGHT, &bt601, &reference_601, &bt709, &mut small)
+                .err()
+                .map(|e| e as u32),
+            Some(ErrorKind::NotEnoughData as u32)
+        );
+    }
+
+    // Perceptual quantizer is not a change of matrix
+    let nv12 = format(PixelFormat::Nv12, ColorSpace::Bt601, 2);
+    assert_eq!(
+        describe_conversion(
+            WIDTH,
+            HEIGHT,
+            &nv12,
+            &format(PixelFormat::Nv12, ColorSpace::Bt2020Pq, 2)
+        )
+        .err()
+        .map(|e| e as u32),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+}
+
+#[cfg(feature = "noise")]
+#[test]
+fn noise_ok() {
+    bootstrap();
+
+    const WIDTH: u32 = 64;
+    const HEIGHT: u32 = 8;
+
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let i444_format = ImageFormat {
+        pixel_format: PixelFormat::I444,
+        color_space: ColorSpace::Bt601,
+        num_planes: 3,
+    };
+
+    let mut rng = rand::thread_rng();
+    let mut bgra = alloc_planes(WIDTH, HEIGHT, &bgra_format);
+    for sample in bgra[0].iter_mut() {
+        *sample = rng.gen::<u8>();
+    }
+
+    let mut yuv = alloc_planes(WIDTH, HEIGHT, &i444_format);
+    assert!(convert_planes(WIDTH, HEIGHT, &bgra_format, &bgra, &i444_format, &mut yuv).is_ok());
+
+    // The perturbation is deterministic
+    let mut other = alloc_planes(WIDTH, HEIGHT, &i444_format);
+    assert!(convert_planes(WIDTH, HEIGHT, &bgra_format, &bgra, &i444_format, &mut other).is_ok());
+    assert_eq!(yuv, other);
+
+    // Every entry point perturbs the samples it writes the same way
+    let src_buffers: &[&[u8]] = &[&bgra[0]];
+    let plan = ConversionPlan::new(WIDTH, HEIGHT, &bgra_format, None, &i444_format, None).unwrap();
+    let mut other = alloc_planes(WIDTH, HEIGHT, &i444_format);
+    {
+        let mut dst_buffers: Vec<&mut [u8]> = other.iter_mut().map(|x| &mut x[..]).collect();
+        assert!(plan.convert(src_buffers, &mut dst_buffers).is_ok());
+    }
+    assert_eq!(yuv, other);
+
+    let mut other = alloc_planes(WIDTH, HEIGHT, &i444_format);
+    {
+        let mut dst_buffers: Vec<&mut [u8]> = other.iter_mut().map(|x| &mut x[..]).collect();
+        assert!(convert_image_cancellable(
+            WIDTH,
+            HEIGHT,
+            &bgra_format,
+            None,
+            src_buffers,
+            &i444_format,
+            None,
+            &mut dst_buffers,
+            &CancellationToken::new(),
+        )
+        .is_ok());
+    }
+    assert_eq!(yuv, other);
+
+    // Including bands which do not start at the first line
+    let mut other = alloc_planes(WIDTH, HEIGHT, &i444_format);
+    {
+        let mut dst_buffers: Vec<&mut [u8]> = other.iter_mut().map(|x| &mut x[..]).collect();
+        assert!(convert_image_dirty_rows(
+            WIDTH,
+            HEIGHT,
+            &bgra_format,
+            None,
+            src_buffers,
+            &i444_format,
+            None,
+            &mut dst_buffers,
+            Some(&[0b1111_0000]),
+        )
+        .is_ok());
+    }
+
+    let first_dirty = 4 * (WIDTH as usize);
+    for (plane, expected) in other.iter().zip(yuv.iter()) {
+        assert!(plane[..first_dirty].iter().all(|&x| x == 0));
+        assert_eq!(plane[first_dirty..], expected[first_dirty..]);
+    }
+
+    let distance = |x: u8, y: u8| (i32::from(x) - i32::from(y)).abs();
+    let mut perturbed = 0;
+    for (i, pixel) in bgra[0].chunks_exact(4).enumerate() {
+        let (y, u, v) =
+            reference::rgb_to_yuv(pixel[2], pixel[1], pixel[0], ColorSpace::Bt601).unwrap();
+        for &(value, expected) in &[(yuv[0][i], y), (yuv[1][i], u), (yuv[2][i], v)] {
+            assert!(distance(value, expected) <= 1);
+            perturbed += (value != expected) as usize;
+        }
+    }
+
+    assert!(perturbed > 0);
+
+    // Alpha is left untouched
+    let mut rgb = alloc_planes(WIDTH, HEIGHT, &bgra_format);
+    assert!(convert_planes(WIDTH, HEIGHT, &i444_format, &yuv, &bgra_format, &mut rgb).is_ok());
+    for (i, pixel) in rgb[0].chunks_exact(4).enumerate() {
+        let (r, g, b) =
+            reference::yuv_to_rgb(yuv[0][i], yuv[1][i], yuv[2][i], ColorSpace::Bt601).unwrap();
+        assert!(distance(pixel[0], b) <= 1);
+        assert!(distance(pixel[1], g) <= 1);
+        assert!(distance(pixel[2], r) <= 1);
+        assert_eq!(pixel[3], 255);
+    }
+
+    // Error bounds account for the perturbation
+    assert!(max_error(&bgra_format, &i444_format, Quality::Fast).unwrap() >= 1.0);
+}
+
+#[cfg(feature = "caps")]
+#[test]
+fn caps_ok() {
+    use dcp::caps::{from_caps, to_caps};
+
+    let parse = |caps: &str| {
+        from_caps(caps)
+            .map(|format| {
+                (
+                    format.pixel_format as u32,
+                    format.color_space as u32,
+                    format.num_planes,
+                )
+            })
+            .map_err(|e| e as u32)
+    };
+
+    for (caps, pixel_format, color_space, num_planes) in [
+        (
+            "video/x-raw,format=NV12,colorimetry=bt709",
+            PixelFormat::Nv12,
+            ColorSpace::Bt709,
+            2,
+        ),
+        (
+            "video/x-raw, format=(string)I420, width=(int)640, height=(int)480, \
+             framerate=(fraction)30/1, colorimetry=(string)\"bt601\"",
+            PixelFormat::I420,
+            ColorSpace::Bt601,
+            3,
+        ),
+        (
+            "video/x-raw,format=Y444,colorimetry=1:4:0:0",
+            PixelFormat::I444,
+            ColorSpace::Jfif,
+            3,
+        ),
+        (
+            "video/x-raw,format=Y42B,colorimetry=2:3:5:1",
+            PixelFormat::I422,
+            ColorSpace::Bt709,
+            3,
+        ),
+        (
+            "video/x-raw,format=P010_10LE,colorimetry=bt2100-pq",
+            PixelFormat::P010,
+            ColorSpace::Bt2020Pq,
+            2,
+        ),
+        (
+            "video/x-raw,format=BGRA,colorimetry=sRGB;",
+            PixelFormat::Bgra,
+            ColorSpace::Lrgb,
+            1,
+        ),
+        (
+            "video/x-raw,format=RGB",
+            PixelFormat::Rgb,
+            ColorSpace::Lrgb,
+            1,
+        ),
+        // Default colorimetry depends on the height
+        (
+            "video/x-raw,format=NV12,height=576",
+            PixelFormat::Nv12,
+            ColorSpace::Bt601,
+            2,
+        ),
+        (
+            "video/x-raw,format=NV12,height=720",
+            PixelFormat::Nv12,
+            ColorSpace::Bt709,
+            2,
+        ),
+    ]
+    .iter()
+    {
+        assert_eq!(
+            parse(caps),
+            Ok((*pixel_format as u32, *color_space as u32, *num_planes))
+        );
+    }
+
+    for (caps, error) in vec![
+        (
+            "video/x-raw(memory:DMABuf),format=NV12",
+            ErrorKind::InvalidValue,
+        ),
+        ("audio/x-raw,format=S16LE", ErrorKind::InvalidValue),
+        ("video/x-raw,width=640", ErrorKind::InvalidValue),
+        (
+            "video/x-raw,format=NV12,height=tall",
+            ErrorKind::InvalidValue,
+        ),
+        (
+            "video/x-raw,format=NV12,colorimetry",
+            ErrorKind::InvalidValue,
+        ),
+        ("video/x-raw,format={ NV12, I420 }", ErrorKind::InvalidValue),
+        ("video/x-raw,format=NV21", ErrorKind::InvalidOperation),
+        (
+            "video/x-raw,format=NV12,colorimetry=smpte240m",
+            ErrorKind::InvalidOperation,
+        ),
+        (
+            "video/x-raw,format=NV12,colorimetry=2:4:5",
+            ErrorKind::InvalidValue,
+        ),
+        (
+            "video/x-raw,format=NV12,colorimetry=1:3:5:1",
+            ErrorKind::InvalidOperation,
+        ),
+        (
+            "video/x-raw,format=NV12,colorimetry=sRGB",
+            ErrorKind::InvalidOperation,
+        ),
+    ] {
+        assert_eq!(parse(caps), Err(error as u32));
+    }
+
+    let format = |pixel_format, color_space| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes: 1,
+    };
+    for (pixel_format, color_space, caps) in [
+        (
+            PixelFormat::Nv12,
+            ColorSpace::Bt709,
+            "video/x-raw,format=NV12,colorimetry=bt709",
+        ),
+        (
+            PixelFormat::I420,
+            ColorSpace::Jfif,
+            "video/x-raw,format=I420,colorimetry=1:4:0:0",
+        ),
+        (
+            PixelFormat::Argb,
+            ColorSpace::Lrgb,
+            "video/x-raw,format=ARGB,colorimetry=sRGB",
+        ),
+    ]
+    .iter()
+    {
+        assert_eq!(
+            to_caps(&format(*pixel_format, *color_space)).unwrap(),
+            *caps
+        );
+    }
+
+    // Formatted caps can be parsed back
+    for pixel_format in PIXEL_FORMATS {
+        for color_space in COLOR_SPACES {
+            let format = format(*pixel_format, *color_space);
+            if let Ok(caps) = to_caps(&format) {
+                assert_eq!(
+                    parse(&caps).map(|(pixel_format, color_space, _)| (pixel_format, color_space)),
+                    Ok((*pixel_format as u32, *color_space as u32))
+                );
+            }
+        }
+    }
+
+    for (pixel_format, color_space) in [
+        (PixelFormat::RgbaF16, ColorSpace::Lrgb),
+        (PixelFormat::Nv12, ColorSpace::Lrgb),
+        (PixelFormat::Bgra, ColorSpace::Bt709),
+    ]
+    .iter()
+    {
+        assert_eq!(
+            to_caps(&format(*pixel_format, *color_space))
+                .err()
+                .map(|e| e as u32),
+            Some(ErrorKind::InvalidOperation as u32)
+        );
+    }
+}
+
+#[cfg(all(feature = "y4m", not(feature = "noise")))]
+#[test]
+fn y4m_ok() {
+    use std::io;
+
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 6;
+    const FRAME_COUNT: usize = 3;
+
+    let mut rng = rand::thread_rng();
+    for pixel_format in [PixelFormat::I420, PixelFormat::I422, PixelFormat::I444].iter() {
+        let header = y4m::Header::new(WIDTH, HEIGHT, *pixel_format);
+        let buffers_size = header.buffers_size();
+        assert!(get_buffers_size(
+            WIDTH,
+            HEIGHT,
+            &header.image_format(ColorSpace::Bt601),
+            None,
+            &mut [0usize; 3]
+        )
+        .is_ok());
+
+        let frames: Vec<Vec<Vec<u8>>> = (0..FRAME_COUNT)
+            .map(|_| {
+                buffers_size
+                    .iter()
+                    .map(|size| (0..*size).map(|_| rng.gen::<u8>()).collect())
+                    .collect()
+            })
+            .collect();
+
+        let mut stream = Vec::new();
+        {
+            let mut writer = y4m::Writer::new(&mut stream, header).unwrap();
+            for frame in &frames {
+                let buffers: Vec<&[u8]> = frame.iter().map(|plane| &plane[..]).collect();
+                assert!(writer.write_frame(&buffers).is_ok());
+            }
+        }
+
+        let mut reader = y4m::Reader::new(&stream[..]).unwrap();
+        assert_eq!(reader.header().width, WIDTH);
+        assert_eq!(reader.header().height, HEIGHT);
+        assert_eq!(reader.header().pixel_format as u32, *pixel_format as u32);
+
+        let mut frame: Vec<Vec<u8>> = buffers_size.iter().map(|size| vec![0u8; *size]).collect();
+        let mut frame_count = 0;
+        loop {
+            let mut buffers: Vec<&mut [u8]> =
+                frame.iter_mut().map(|plane| &mut plane[..]).collect();
+            if !reader.read_frame(&mut buffers).unwrap() {
+                break;
+            }
+
+            assert_eq!(frame, frames[frame_count]);
+            frame_count += 1;
+        }
+
+        assert_eq!(frame_count, FRAME_COUNT);
+    }
+
+    // Stream produced by ffmpeg, with extensions and frame parameters
+    let header =
+        b"YUV4MPEG2 W2 H2 F30000:1001 Ip A1:1 C420jpeg XYSCSS=420JPEG XCOLORRANGE=LIMITED\n";
+    let mut stream = header.to_vec();
+    stream.extend_from_slice(b"FRAME Ixyz\n");
+    stream.extend_from_slice(&[16, 16, 16, 16, 128, 128]);
+
+    let mut reader = y4m::Reader::new(&stream[..]).unwrap();
+    let header = reader.header().clone();
+    assert_eq!(header.frame_rate, (30000, 1001));
+    assert_eq!(header.pixel_aspect, (1, 1));
+    assert_eq!(header.interlacing, 'p');
+
+    let (mut y, mut u, mut v) = (vec![0u8; 4], vec![0u8; 1], vec![0u8; 1]);
+    assert!(reader.read_frame(&mut [&mut y, &mut u, &mut v]).unwrap());
+    assert!(!reader.read_frame(&mut [&mut y, &mut u, &mut v]).unwrap());
+
+    // Frames can be converted as they are
+    let dst_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+    let mut dst = vec![0u8; 16];
+    assert!(convert_image(
+        header.width,
+        header.height,
+        &header.image_format(ColorSpace::Bt601),
+        None,
+        &[&y, &u, &v],
+        &dst_format,
+        None,
+        &mut [&mut dst],
+    )
+    .is_ok());
+    assert!(dst.chunks(4).all(|pixel| pixel == [0, 0, 0, 255]));
+
+    // Error cases
+    let read_error = |stream: &[u8]| y4m::Reader::new(stream).err().map(|e| e.kind());
+    assert_eq!(
+        read_error(b"YUV4MPEG W2 H2\n"),
+        Some(io::ErrorKind::InvalidData)
+    );
+    assert_eq!(
+        read_error(b"YUV4MPEG2 H2\n"),
+        Some(io::ErrorKind::InvalidData)
+    );
+    assert_eq!(
+        read_error(b"YUV4MPEG2 W3 H2\n"),
+        Some(io::ErrorKind::InvalidData)
+    );
+    assert_eq!(
+        read_error(b"YUV4MPEG2 W2 H2 C420p10\n"),
+        Some(io::ErrorKind::InvalidData)
+    );
+    assert_eq!(
+        read_error(b"YUV4MPEG2 W2 H2"),
+        Some(io::ErrorKind::UnexpectedEof)
+    );
+    assert_eq!(read_error(b"YUV4MPEG2 W3 H1 C444\n"), None);
+
+    let mut reader = y4m::Reader::new(&stream[..stream.len() - 1]).unwrap();
+    assert_eq!(
+        reader
+            .read_frame(&mut [&mut y, &mut u, &mut v])
+            .err()
+            .map(|e| e.kind()),
+        Some(io::ErrorKind::UnexpectedEof)
+    );
+    assert_eq!(
+        reader
+            .read_frame(&mut [&mut y, &mut u])
+            .err()
+            .map(|e| e.kind()),
+        Some(io::ErrorKind::InvalidInput)
+    );
+
+    let mut sink = Vec::new();
+    assert!(y4m::Writer::new(&mut sink, y4m::Header::new(2, 2, PixelFormat::Nv12)).is_err());
+    let mut writer =
+        y4m::Writer::new(&mut sink, y4m::Header::new(2, 2, PixelFormat::I420)).unwrap();
+    assert_eq!(
+        writer
+            .write_frame(&[&y[..3], &u, &v])
+            .err()
+            .map(|e| e.kind()),
+        Some(io::ErrorKind::InvalidInput)
+    );
+}
+
+#[cfg(feature = "io")]
+#[test]
+fn pnm_ok() {
+    use std::io;
+
+    const WIDTH: u32 = 5;
+    const HEIGHT: u32 = 3;
+
+    let mut rng = rand::thread_rng();
+    for (depth, max_value, tuple_type, magic) in [
+        (1, 255, None, "P5\n5 3\n255\n"),
+        (3, 255, None, "P6\n5 3\n255\n"),
+        (1, 1023, None, "P5\n5 3\n1023\n"),
+        (
+            4,
+            255,
+            Some("RGB_ALPHA"),
+            "P7\nWIDTH 5\nHEIGHT 3\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n",
+        ),
+        (
+            2,
+            65535,
+            Some(""),
+            "P7\nWIDTH 5\nHEIGHT 3\nDEPTH 2\nMAXVAL 65535\nENDHDR\n",
+        ),
+    ]
+    .iter()
+    {
+        let header = pnm::Header {
+            width: WIDTH,
+            height: HEIGHT,
+            depth: *depth,
+            max_value: *max_value,
+            tuple_type: tuple_type.map(String::from),
+        };
+
+        let data_size = header.data_size().unwrap();
+        assert_eq!(
+            data_size,
+            (WIDTH * HEIGHT * depth) as usize * header.bytes_per_sample()
+        );
+
+        let data: Vec<u8> = (0..data_size).map(|_| rng.gen::<u8>()).collect();
+        let mut file = Vec::new();
+        assert!(pnm::write(&mut file, &header, &data).is_ok());
+        assert!(file.starts_with(magic.as_bytes()));
+        assert_eq!(file.len(), magic.len() + data_size);
+
+        // 16-bit samples are big-endian in the file
+        if header.bytes_per_sample() == 2 {
+            assert_eq!(file[magic.len()], data[1]);
+            assert_eq!(file[magic.len() + 1], data[0]);
+        }
+
+        let (read_header, read_data) = pnm::read(&file[..]).unwrap();
+        assert_eq!(read_header.width, WIDTH);
+        assert_eq!(read_header.height, HEIGHT);
+        assert_eq!(read_header.depth, *depth);
+        assert_eq!(read_header.max_value, *max_value);
+        assert_eq!(read_header.tuple_type.as_deref(), *tuple_type);
+        assert_eq!(read_data, data);
+    }
+
+    // Comments and arbitrary whitespace
+    let (header, data) =
+        pnm::read(&b"P5 # comment\n2\t# another\n 1\n\n255\n\x01\x02"[..]).unwrap();
+    assert_eq!((header.width, header.height, header.depth), (2, 1, 1));
+    assert_eq!(data, [1, 2]);
+
+    let (header, data) =
+        pnm::read(&b"P7\n# comment\nWIDTH 1\nHEIGHT 1\nDEPTH 1\nMAXVAL 1\nTUPLTYPE BLACKANDWHITE\nENDHDR\n\x01"[..]).unwrap();
+    assert_eq!(header.tuple_type.as_deref(), Some("BLACKANDWHITE"));
+    assert_eq!(data, [1]);
+
+    // Error cases
+    let read_error = |file: &[u8]| pnm::read(file).err().map(|e| e.kind());
+    assert_eq!(
+        read_error(b"P3\n1 1\n255\n0 0 0\n"),
+        Some(io::ErrorKind::InvalidData)
+    );
+    assert_eq!(
+        read_error(b"P5\n0 1\n255\n"),
+        Some(io::ErrorKind::InvalidData)
+    );
+    assert_eq!(
+        read_error(b"P5\n1 1\n65536\n"),
+        Some(io::ErrorKind::InvalidData)
+    );
+    assert_eq!(
+        read_error(b"P5\nx 1\n255\n"),
+        Some(io::ErrorKind::InvalidData)
+    );
+    assert_eq!(
+        read_error(b"P7\nWIDTH 1\nHEIGHT 1\nDEPTH 1\nENDHDR\n\x00"),
+        Some(io::ErrorKind::InvalidData)
+    );
+    assert_eq!(
+        read_error(b"P5\n2 1\n255\n\x00"),
+        Some(io::ErrorKind::UnexpectedEof)
+    );
+    assert_eq!(read_error(b"P5\n2 1"), Some(io::ErrorKind::UnexpectedEof));
+
+    let write_error = |header: &pnm::Header, data: &[u8]| {
+        pnm::write(&mut Vec::new(), header, data)
+            .err()
+            .map(|e| e.kind())
+    };
+    assert_eq!(
+        write_error(&pnm::Header::new(2, 1, 2), &[0; 4]),
+        Some(io::ErrorKind::InvalidInput)
+    );
+    assert_eq!(
+        write_error(&pnm::Header::new(2, 1, 3), &[0; 5]),
+        Some(io::ErrorKind::InvalidInput)
+    );
+    assert_eq!(
+        write_error(&pnm::Header::new(2, 0, 1), &[]),
+        Some(io::ErrorKind::InvalidData)
+    );
+}
+
+#[cfg(feature = "cube")]
+#[test]
+fn cube_ok() {
+    use std::io;
+
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 70;
+    const PIXELS: usize = (WIDTH as usize) * (HEIGHT as usize);
+
+    let write_cube = |size: usize, f: &dyn Fn(f32, f32, f32) -> (f32, f32, f32)| {
+        let mut file = format!("# comment\nTITLE \"test\"\n\nLUT_3D_SIZE {}\n", size);
+        let scale = (size - 1) as f32;
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    let (x, y, z) = f(r as f32 / scale, g as f32 / scale, b as f32 / scale);
+                    file.push_str(&format!("{} {} {}\n", x, y, z));
+                }
+            }
+        }
+
+        file
+    };
+
+    let bgra_format = ImageFormat {
+        pixel_format: PixelFormat::Bgra,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let nv12_format = ImageFormat {
+        pixel_format: PixelFormat::Nv12,
+        color_space: ColorSpace::Bt601,
+        num_planes: 1,
+    };
+
+    // Tetrahedral interpolation reproduces linear functions exactly
+    let identity = cube::read(write_cube(17, &|r, g, b| (r, g, b)).as_bytes()).unwrap();
+    assert_eq!(identity.size(), 17);
+
+    let mut rng = rand::thread_rng();
+    let bgra: Vec<u8> = (0..4 * PIXELS).map(|_| rng.gen::<u8>()).collect();
+    let mut image = bgra.clone();
+    assert!(cube::apply_lut_in_place(
+        WIDTH,
+        HEIGHT,
+        &bgra_format,
+        None,
+        &mut [&mut image],
+        &identity
+    )
+    .is_ok());
+    assert_eq!(image, bgra);
+
+    for _ in 0..100 {
+        let rgb = [rng.gen::<f32>(), rng.gen::<f32>(), rng.gen::<f32>()];
+        let out = identity.sample(rgb);
+        for (x, y) in out.iter().zip(rgb.iter()) {
+            assert!((x - y).abs() < 1e-5);
+        }
+    }
+
+    // Table points are returned as they are, out of domain values are clamped
+    let lut =
+        cube::read(write_cube(5, &|r, g, b| (r * g, (g + b) / 2.0, b * b)).as_bytes()).unwrap();
+    assert_eq!(lut.sample([0.5, 0.75, 0.25]), [0.375, 0.5, 0.0625]);
+    assert_eq!(lut.sample([2.0, -1.0, 1.0]), [0.0, 0.5, 1.0]);
+
+    // Accelerated kernels interpolate lines exactly like single samples, ties included
+    let rgbf32_format = ImageFormat {
+        pixel_format: PixelFormat::RgbF32,
+        color_space: ColorSpace::Lrgb,
+        num_planes: 1,
+    };
+
+    let samples: Vec<f32> = (0..3 * PIXELS)
+        .map(|i| match i % 7 {
+            0 => 0.5,
+            1 => rng.gen::<f32>().round(),
+            _ => rng.gen::<f32>(),
+        })
+        .collect();
+    let mut rgbf32: Vec<u8> = samples.iter().flat_map(|x| x.to_le_bytes()).collect();
+    assert!(cube::apply_lut_in_place(
+        WIDTH,
+        HEIGHT,
+        &rgbf32_format,
+        None,
+        &mut [&mut rgbf32],
+        &lut
+    )
+    .is_ok());
+
+    for (pixel, rgb) in rgbf32.chunks(12).zip(samples.chunks(3)) {
+        let expected = lut.sample([rgb[0], rgb[1], rgb[2]]);
+        for (value, expected) in pixel.chunks(4).zip(expected.iter()) {
+            let value = f32::from_le_bytes([value[0], value[1], value[2], value[3]]);
+            assert_eq!(value.to_bits(), expected.to_bits());
+        }
+    }
+
+    // Fused with a conversion
+    let mut nv12 = vec![0u8; 3 * PIXELS / 2];
+    assert!(convert_image(
+        WIDTH,
+        HEIGHT,
+        &bgra_format,
+        None,
+        &[&bgra],
+        &nv12_format,
+        None,
+        &mut [&mut nv12],
+    )
+    .is_ok());
+
+    let mut reference = vec![0u8; 4 * PIXELS];
+    assert!(convert_image(
+        WIDTH,
+        HEIGHT,
+        &nv12_format,
+        None,
+        &[&nv12],
+        &bgra_format,
+        None,
+        &mut [&mut reference],
+    )
+    .is_ok());
+
+    let invert =
+        cube::read(write_cube(2, &|r, g, b| (1.0 - r, 1.0 - g, 1.0 - b)).as_bytes()).unwrap();
+    let stride = 4 * (WIDTH as usize) + 4;
+    let mut output = vec![0xAAu8; stride * (HEIGHT as usize)];
+    assert!(cube::convert_image_with_cube(
+        WIDTH,
+        HEIGHT,
+        &nv12_format,
+        None,
+        &[&nv12],
+        &bgra_format,
+        Some(&[stride]),
+        &mut [&mut output],
+        &invert,
+    )
+    .is_ok());
+
+    for (line, expected) in output
+        .chunks(stride)
+        .zip(reference.chunks(4 * (WIDTH as usize)))
+    {
+        for (pixel, reference) in line.chunks(4).zip(expected.chunks(4)) {
+            assert_eq!(pixel[0], 255 - reference[0]);
+            assert_eq!(pixel[1], 255 - reference[1]);
+            assert_eq!(pixel[2], 255 - reference[2]);
+            assert_eq!(pixel[3], reference[3]);
+        }
+
+        assert!(line[4 * (WIDTH as usize)..].iter().all(|&x| x == 0xAA));
+    }
+
+    // Domain
+    let lut = cube::read(
+        "LUT_3D_SIZE 2\nDOMAIN_MIN 0 0 0\nDOMAIN_MAX 2 2 2\n0 0 0\n1 0 0\n0 1 0\n1 1 0\n0 0 1\n1 0 1\n0 1 1\n1 1 1\n"
+            .as_bytes(),
+    )
+    .unwrap();
+    assert_eq!(lut.sample([1.0, 0.5, 2.0]), [0.5, 0.25, 1.0]);
+
+    // Errors
+    let read_error = |file: &str| cube::read(file.as_bytes()).err().map(|e| e.kind());
+    for file in &[
+        "",
+        "LUT_1D_SIZE 2\n0 0 0\n1 1 1\n",
+        "0 0 0\n",
+        "LUT_3D_SIZE 1\n0 0 0\n",
+        "LUT_3D_SIZE 2\n0 0 0\n",
+        "LUT_3D_SIZE 2\n0 0\n",
+        "LUT_3D_SIZE 2\n0 0 x\n",
+        "LUT_3D_SIZE 2\nDOMAIN_MIN 1 0 0\nDOMAIN_MAX 1 1 1\n0 0 0\n1 0 0\n0 1 0\n1 1 0\n0 0 1\n1 0 1\n0 1 1\n1 1 1\n",
+        "LUT_3D_SIZE 2\n0 0 0\n1 0 0\n0 1 0\n1 1 0\n0 0 1\n1 0 1\n0 1 1\n1 1 1\n1 1 1\n",
+    ] {
+        assert_eq!(read_error(file), Some(io::ErrorKind::InvalidData));
+    }
+
+    assert_eq!(
+        cube::Lut3d::new(2, vec![[0.0; 3]; 7])
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+
+    assert_eq!(
+        cube::apply_lut_in_place(
+            WIDTH,
+            HEIGHT,
+            &nv12_format,
+            None,
+            &mut [&mut nv12],
+            &identity
+        )
+        .err()
+        .map(|e| e as u32),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+    assert_eq!(
+        cube::apply_lut_in_place(
+            WIDTH,
+            HEIGHT,
+            &bgra_format,
+            None,
+            &mut [&mut image[1..]],
+            &identity
+        )
+        .err()
+        .map(|e| e as u32),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+    assert_eq!(
+        cube::convert_image_with_cube(
+            WIDTH,
+            HEIGHT,
+            &bgra_format,
+            None,
+            &[&bgra],
+            &nv12_format,
+            None,
+            &mut [&mut nv12],
+            &identity,
+        )
+        .err()
+        .map(|e| e as u32),
+        Some(ErrorKind::InvalidOperation as u32)
+    );
+}
+
+#[cfg(feature = "context")]
+#[test]
+#[allow(unsafe_code)]
+fn context_ok() {
+    use dcp::context::{AllocError, Allocator, Context, Global};
+    use std::cell::Cell;
+    use std::ptr::NonNull;
+
+    struct CountingAllocator {
+        allocations: Cell<usize>,
+    }
+
+    unsafe impl Allocator for CountingAllocator {
+        fn allocate(&self, layout: std::alloc::Layout) -> Result<NonNull<[u8]>, AllocError> {
+            self.allocations.set(self.allocations.get() + 1);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: std::alloc::Layout) {
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    bootstrap();
+
+    const WIDTH: u32 = 34;
+    const HEIGHT: u32 = 130;
+
+    let format = |pixel_format, color_space| ImageFormat {
+        pixel_format,
+        color_space,
+        num_planes: 1,
+    };
+
+    let rgb_format = format(PixelFormat::Rgb, ColorSpace::Lrgb);
+    let bgra_format = format(PixelFormat::Bgra, ColorSpace::Lrgb);
+    let nv12_format = format(PixelFormat::Nv12, ColorSpace::Bt601);
+
+    let mut rng = rand::thread_rng();
+    let mut src = alloc_planes(WIDTH, HEIGHT, &rgb_format);
+    for x in src[0].iter_mut() {
+        *x = rng.gen::<u8>();
+    }
+
+    let allocator = CountingAllocator {
+        allocations: Cell::new(0),
+    };
+    let mut context = Context::new_in(&allocator).unwrap();
+    assert_eq!(context.scratch_size(), 0);
+
+    let convert = |context: &mut Context<&CountingAllocator>, dst_format| {
+        let mut expected = alloc_planes(WIDTH, HEIGHT, dst_format);
+        assert!(
+            convert_planes(WIDTH, HEIGHT, &rgb_format, &src, dst_format, &mut expected).is_ok()
+        );
+
+        let mut dst = alloc_planes(WIDTH, HEIGHT, dst_format);
+        let mut dst_buffers: Vec<&mut [u8]> = dst.iter_mut().map(|x| &mut x[..]).collect();
+        assert!(context
+            .convert_image(
+                WIDTH,
+                HEIGHT,
+                &rgb_format,
+                None,
+                &[&src[0]],
+                dst_format,
+                None,
+                &mut dst_buffers,
+            )
+            .is_ok());
+        assert_eq!(dst, expected);
+    };
+
+    // Direct conversions need no scratch memory
+    convert(&mut context, &bgra_format);
+    assert_eq!(context.scratch_size(), 0);
+    assert_eq!(allocator.allocations.get(), 0);
+
+    // Routed conversions allocate a band of Bgra once, and reuse it
+    assert!(context
+        .reserve(WIDTH, HEIGHT, &rgb_format, &nv12_format)
+        .is_ok());
+    assert_eq!(context.scratch_size(), 4 * (WIDTH as usize) * 64);
+    assert_eq!(allocator.allocations.get(), 1);
+    convert(&mut context, &nv12_format);
+    convert(&mut context, &nv12_format);
+    assert_eq!(allocator.allocations.get(), 1);
+
+    // Errors are the ones of convert_image
+    assert_eq!(
+        context
+            .reserve(
+                WIDTH,
+                HEIGHT,
+                &rgb_format,
+                &format(PixelFormat::I444, ColorSpace::Lrgb)
+            )
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::InvalidValue as u32)
+    );
+    assert_eq!(
+        context
+            .convert_image(
+                WIDTH,
+                HEIGHT,
+                &rgb_format,
+                None,
+                &[&src[0][1..]],
+                &nv12_format,
+                None,
+                &mut [&mut [0u8; 1]],
+            )
+            .err()
+            .map(|e| e as u32),
+        Some(ErrorKind::NotEnoughData as u32)
+    );
+
+    assert!(Context::new().is_ok());
+}